@@ -9,7 +9,10 @@ to PDF.
 
 #![deny(missing_docs)]
 
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 use std::io::Read;
 use std::sync::Arc;
 
@@ -124,9 +127,48 @@ pub fn render_svg_glyph(
     glyph: GlyphId,
     surface: &mut Surface,
 ) -> Option<()> {
-    let mut data = data;
     let settings = SvgSettings::default();
 
+    let mut hasher = DefaultHasher::new();
+    (
+        data,
+        context_color.red(),
+        context_color.green(),
+        context_color.blue(),
+    )
+        .hash(&mut hasher);
+    let key = hasher.finish();
+
+    // Fonts like Noto Color Emoji contain hundreds of glyphs in the same SVG document, and
+    // since glyphs of the same document are usually rendered one after another, we keep the
+    // last parsed document around instead of reparsing it for each glyph. The entry is taken
+    // out of the cache while rendering, in case rendering the glyph renders other SVG glyphs.
+    let tree = match LAST_DOCUMENT.with_borrow_mut(|last| last.take()) {
+        Some((last_key, tree)) if last_key == key => tree,
+        _ => parse_svg_glyph(data, context_color)?,
+    };
+
+    if let Some(node) = tree.node_by_id(&format!("glyph{}", glyph.to_u32())) {
+        render_node(node, tree.fontdb().clone(), settings, surface)
+    } else {
+        // Twitter Color Emoji SVGs contain the glyph ID on the root element, which isn't saved by
+        // usvg. So in this case, we simply draw the whole document.
+        render_tree(&tree, settings, surface)
+    };
+
+    LAST_DOCUMENT.with_borrow_mut(|last| *last = Some((key, tree)));
+
+    Some(())
+}
+
+thread_local! {
+    /// The most recently parsed SVG document of a glyph, with the hash of its data
+    /// and context color.
+    static LAST_DOCUMENT: RefCell<Option<(u64, Tree)>> = const { RefCell::new(None) };
+}
+
+fn parse_svg_glyph(data: &[u8], context_color: rgb::Color) -> Option<Tree> {
+    let mut data = data;
     let mut decoded = vec![];
     if data.starts_with(&[0x1f, 0x8b]) {
         let mut decoder = flate2::read::GzDecoder::new(data);
@@ -137,11 +179,6 @@ pub fn render_svg_glyph(
     let xml = std::str::from_utf8(data).ok()?;
     let document = roxmltree::Document::parse(xml).ok()?;
 
-    // Reparsing every time might be pretty slow in some cases, because Noto Color Emoji
-    // for example contains hundreds of glyphs in the same SVG document, meaning that we have
-    // to reparse it every time. However, Twitter Color Emoji does have each glyph in a
-    // separate SVG document, and since we use COLRv1 for Noto Color Emoji anyway, this is
-    // good enough.
     let opts = usvg::Options {
         style_sheet: Some(format!(
             "svg {{ color: rgb({}, {}, {}) }}",
@@ -151,17 +188,8 @@ pub fn render_svg_glyph(
         )),
         ..Default::default()
     };
-    let tree = Tree::from_xmltree(&document, &opts).ok()?;
 
-    if let Some(node) = tree.node_by_id(&format!("glyph{}", glyph.to_u32())) {
-        render_node(node, tree.fontdb().clone(), settings, surface)
-    } else {
-        // Twitter Color Emoji SVGs contain the glyph ID on the root element, which isn't saved by
-        // usvg. So in this case, we simply draw the whole document.
-        render_tree(&tree, settings, surface)
-    };
-
-    Some(())
+    Tree::from_xmltree(&document, &opts).ok()
 }
 
 fn get_context_from_group(
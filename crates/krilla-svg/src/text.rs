@@ -1,6 +1,4 @@
-use krilla::color::rgb;
 use krilla::geom::Point;
-use krilla::num::NormalizedF32;
 use krilla::paint::{Fill, Stroke};
 use krilla::surface::Surface;
 use krilla::text::{Font, GlyphId, KrillaGlyph};
@@ -62,6 +60,17 @@ pub(crate) fn render(
                 .as_ref()
                 .map(|s| convert_stroke(s, surface.stream_builder(), process_context, inverted));
 
+            let krilla_glyph = KrillaGlyph::new(
+                GlyphId::new(glyph.id.0 as u32),
+                // Don't care about those, since we render only one glyph.
+                0.0,
+                0.0,
+                0.0,
+                0.0,
+                0..glyph.text.len(),
+                None,
+            );
+
             let draw_op = |s: &mut Surface,
                            fill: Option<Fill>,
                            stroke: Option<Stroke>,
@@ -72,16 +81,7 @@ pub(crate) fn render(
 
                 s.draw_glyphs(
                     Point::from_xy(0.0, 0.0),
-                    &[KrillaGlyph::new(
-                        GlyphId::new(glyph.id.0 as u32),
-                        // Don't care about those, since we render only one glyph.
-                        0.0,
-                        0.0,
-                        0.0,
-                        0.0,
-                        0..glyph.text.len(),
-                        None,
-                    )],
+                    std::slice::from_ref(&krilla_glyph),
                     font,
                     &glyph.text,
                     span.font_size.get(),
@@ -92,18 +92,17 @@ pub(crate) fn render(
             surface.push_transform(&transform.to_krilla());
 
             if fill.is_none() && stroke.is_none() {
-                // Emulate invisible glyph by drawing it with an opacity of zero.
-                draw_op(
-                    surface,
-                    Some(Fill {
-                        paint: rgb::Color::new(0, 0, 0).into(),
-                        opacity: NormalizedF32::ZERO,
-                        rule: Default::default(),
-                    }),
-                    None,
-                    font,
-                    process_context.svg_settings.embed_text,
-                )
+                // Invisible glyphs are only needed for text extraction, so there is nothing
+                // to draw if the text isn't embedded.
+                if process_context.svg_settings.embed_text {
+                    surface.draw_invisible_glyphs(
+                        Point::from_xy(0.0, 0.0),
+                        std::slice::from_ref(&krilla_glyph),
+                        font,
+                        &glyph.text,
+                        span.font_size.get(),
+                    );
+                }
             } else {
                 match span.paint_order {
                     PaintOrder::FillAndStroke => {
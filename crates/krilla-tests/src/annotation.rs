@@ -1,12 +1,29 @@
+use krilla::action::{
+    Action, JavaScriptAction, LaunchAction, NamedAction, RemoteDestination, RemoteGoToAction,
+};
+use krilla::annotation::{
+    Annotation, AnnotationFlags, BorderStyle, Callout, CircleAnnotation, FileAttachmentAnnotation,
+    FileAttachmentIcon, FreeTextAnnotation, HighlightAnnotation, InkAnnotation, Justification,
+    LineAnnotation, LineEnding, PolygonAnnotation, PolylineAnnotation, SquareAnnotation,
+    SquigglyAnnotation, StampAnnotation, StampAppearance, StandardStamp, StrikeOutAnnotation,
+    TextAnnotation, TextIcon, UnderlineAnnotation,
+};
+use krilla::blend::BlendMode;
+use krilla::color::rgb;
 use krilla::destination::XyzDestination;
 use krilla::geom::{Point, Rect};
+use krilla::metadata::DateTime;
+use krilla::num::NormalizedF32;
 use krilla::page::{Page, PageSettings};
+use krilla::surface::Surface;
+use krilla::text::{Font, GlyphId, KrillaGlyph, TextDirection};
 use krilla::Document;
 use krilla_macros::snapshot;
 
-use crate::{green_fill, rect_to_path, red_fill};
-use crate::{settings_1, LinkAction};
-use crate::{LinkAnnotation, Target};
+use crate::embed::file_1;
+use crate::{green_fill, rect_to_path, red_fill, NOTO_SANS, NOTO_SANS_ARABIC};
+use crate::{pdf_contains, pdf_count, LinkAnnotation, Target};
+use crate::{settings_1, settings_2, settings_25, LinkAction};
 
 #[snapshot]
 fn annotation_to_link(page: &mut Page) {
@@ -20,6 +37,208 @@ fn annotation_to_link(page: &mut Page) {
     );
 }
 
+fn remote_link(y: f32, action: Action) -> Annotation {
+    LinkAnnotation::new(
+        Rect::from_xywh(50.0, y, 100.0, 30.0).unwrap(),
+        None,
+        Target::Action(action),
+    )
+    .into()
+}
+
+#[test]
+fn annotation_to_remote_goto() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(remote_link(
+        20.0,
+        RemoteGoToAction::new(
+            "chapter2.pdf".to_string(),
+            RemoteDestination::Page(4),
+            false,
+        )
+        .into(),
+    ));
+    page.add_annotation(remote_link(
+        60.0,
+        RemoteGoToAction::new(
+            "chapter3.pdf".to_string(),
+            RemoteDestination::Named("introduction".to_string()),
+            true,
+        )
+        .into(),
+    ));
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/S /GoToR"), 2);
+    assert_eq!(pdf_count(&pdf, b"/Type /Filespec"), 2);
+    assert_eq!(pdf_count(&pdf, b"/F (chapter2.pdf)"), 1);
+    assert_eq!(pdf_count(&pdf, b"/D [4 /Fit]"), 1);
+    assert_eq!(pdf_count(&pdf, b"/F (chapter3.pdf)"), 1);
+    assert_eq!(pdf_count(&pdf, b"/D (introduction)"), 1);
+    assert_eq!(pdf_count(&pdf, b"/NewWindow true"), 1);
+}
+
+#[test]
+fn annotation_to_launch() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(remote_link(
+        20.0,
+        LaunchAction::new("data.csv".to_string(), false).into(),
+    ));
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/S /Launch"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Rect [50 150 150 180]"), 1);
+    assert_eq!(pdf_count(&pdf, b"/F (data.csv)"), 1);
+    assert_eq!(pdf_count(&pdf, b"/NewWindow"), 0);
+}
+
+#[test]
+fn annotation_remote_file_specs() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(remote_link(
+        20.0,
+        RemoteGoToAction::new("chapter2.pdf".to_string(), RemoteDestination::Page(4), true).into(),
+    ));
+    page.add_annotation(remote_link(
+        60.0,
+        LaunchAction::new("übersicht.pdf".to_string(), false).into(),
+    ));
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/S /GoToR"));
+    assert!(pdf_contains(&pdf, b"/D [4 /Fit]"));
+    assert!(pdf_contains(&pdf, b"/NewWindow true"));
+    assert!(pdf_contains(&pdf, b"/S /Launch"));
+    // Only the non-ASCII path needs a Unicode file name.
+    assert_eq!(pdf_count(&pdf, b"/UF"), 1);
+}
+
+#[test]
+fn annotation_to_named_actions() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    let actions = [
+        NamedAction::FirstPage,
+        NamedAction::PrevPage,
+        NamedAction::NextPage,
+        NamedAction::LastPage,
+    ];
+
+    for (i, action) in actions.into_iter().enumerate() {
+        page.add_annotation(remote_link(20.0 + i as f32 * 40.0, action.into()));
+    }
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Link"), 4);
+    assert_eq!(pdf_count(&pdf, b"/S /Named"), 4);
+    for (name, rect) in [
+        ("FirstPage", "50 150 150 180"),
+        ("PrevPage", "50 110 150 140"),
+        ("NextPage", "50 70 150 100"),
+        ("LastPage", "50 30 150 60"),
+    ] {
+        assert_eq!(pdf_count(&pdf, format!("/N /{name}").as_bytes()), 1);
+        assert_eq!(pdf_count(&pdf, format!("/Rect [{rect}]").as_bytes()), 1);
+    }
+}
+
+#[test]
+fn annotation_javascript_string_and_stream() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(remote_link(
+        20.0,
+        JavaScriptAction::new("app.alert('Hello');".to_string()).into(),
+    ));
+    // Long scripts are written as a stream, which is shared between identical scripts.
+    let long_script = "app.alert('Hello');\n".repeat(20);
+    page.add_annotation(remote_link(
+        60.0,
+        JavaScriptAction::new(long_script.clone()).into(),
+    ));
+    page.add_annotation(remote_link(
+        100.0,
+        JavaScriptAction::new(long_script).into(),
+    ));
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/S /JavaScript"), 3);
+    assert_eq!(pdf_count(&pdf, b"/JS (app.alert"), 1);
+    assert_eq!(pdf_count(&pdf, b"app.alert('Hello');\n"), 20);
+}
+
+#[test]
+fn annotation_to_action_chain() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(remote_link(
+        20.0,
+        Action::from(NamedAction::NextPage)
+            .then(LinkAction::new("https://www.youtube.com".to_string())),
+    ));
+    page.add_annotation(remote_link(
+        60.0,
+        Action::from(NamedAction::FirstPage)
+            .then(NamedAction::NextPage)
+            .then(NamedAction::NextPage),
+    ));
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Link"), 2);
+    assert_eq!(pdf_count(&pdf, b"/S /Named"), 4);
+    assert_eq!(pdf_count(&pdf, b"/N /NextPage"), 3);
+    assert_eq!(pdf_count(&pdf, b"/N /FirstPage"), 1);
+    assert_eq!(pdf_count(&pdf, b"/S /URI"), 1);
+    assert_eq!(pdf_count(&pdf, b"/URI (https://www.youtube.com)"), 1);
+    // The first chain has a single follow-up action, the second one has two.
+    assert_eq!(pdf_count(&pdf, b"/Next <<"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Next ["), 1);
+}
+
+#[test]
+fn annotation_action_chain_next() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(remote_link(
+        20.0,
+        Action::from(NamedAction::FirstPage).then(NamedAction::LastPage),
+    ));
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    // A single follow-up action is written as a dictionary instead of an array.
+    assert!(pdf_contains(&pdf, b"/Next <<"));
+    assert!(!pdf_contains(&pdf, b"/Next ["));
+}
+
+#[test]
+fn annotation_long_action_chain() {
+    let mut action = Action::from(NamedAction::FirstPage);
+    for _ in 0..100 {
+        // Chaining a chain should flatten it instead of nesting it.
+        action = action.then(Action::from(NamedAction::NextPage).then(NamedAction::PrevPage));
+    }
+
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(remote_link(20.0, action));
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Next ["), 1);
+    assert_eq!(pdf_count(&pdf, b"/S /Named"), 201);
+}
+
 #[snapshot]
 fn annotation_with_quad_points(page: &mut Page) {
     let mut surface = page.surface();
@@ -49,6 +268,67 @@ fn annotation_with_quad_points(page: &mut Page) {
     );
 }
 
+#[test]
+fn annotation_link_with_quads() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    let mut surface = page.surface();
+    text_markup_text(&mut surface, 20.0);
+    surface.finish();
+
+    page.add_annotation(
+        LinkAnnotation::new_with_quads(
+            multi_line_quads(20.0),
+            Target::Action(LinkAction::new("https://www.youtube.com".to_string()).into()),
+        )
+        .into(),
+    );
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Link"), 1);
+    assert_eq!(
+        pdf_count(
+            &pdf,
+            b"/QuadPoints [20 180 180 180 20 160 180 160 20 160 90 160 20 140 90 140]"
+        ),
+        1
+    );
+    assert_eq!(pdf_count(&pdf, b"/URI (https://www.youtube.com)"), 1);
+    assert_eq!(pdf_count(&pdf, b"] TJ"), 2);
+}
+
+#[test]
+fn annotation_link_with_quads_rect() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(
+        LinkAnnotation::new_with_quads(
+            multi_line_quads(20.0),
+            Target::Action(LinkAction::new("https://www.youtube.com".to_string()).into()),
+        )
+        .into(),
+    );
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    // The union of both quadrilaterals, in PDF coordinates.
+    assert!(pdf_contains(&pdf, b"/Rect [20 140 180 180]"));
+    assert!(pdf_contains(
+        &pdf,
+        b"/QuadPoints [20 180 180 180 20 160 180 160 20 160 90 160 20 140 90 140]"
+    ));
+}
+
+#[test]
+#[should_panic]
+fn annotation_link_with_no_quads() {
+    LinkAnnotation::new_with_quads(
+        vec![],
+        Target::Action(LinkAction::new("https://www.youtube.com".to_string()).into()),
+    );
+}
+
 #[test]
 #[should_panic]
 fn annotation_to_invalid_destination() {
@@ -101,3 +381,780 @@ fn annotation_to_destination(d: &mut Document) {
     my_surface.finish();
     page.finish();
 }
+
+/// Quad points of a selection that spans two lines of different widths.
+pub(crate) fn multi_line_quads(y: f32) -> Vec<[Point; 4]> {
+    vec![
+        [
+            Point::from_xy(20.0, y),
+            Point::from_xy(180.0, y),
+            Point::from_xy(20.0, y + 20.0),
+            Point::from_xy(180.0, y + 20.0),
+        ],
+        [
+            Point::from_xy(20.0, y + 20.0),
+            Point::from_xy(90.0, y + 20.0),
+            Point::from_xy(20.0, y + 40.0),
+            Point::from_xy(90.0, y + 40.0),
+        ],
+    ]
+}
+
+fn text_markup_text(surface: &mut Surface, y: f32) {
+    let font = Font::new(NOTO_SANS.clone(), 0).unwrap();
+    surface.draw_text(
+        Point::from_xy(20.0, y + 15.0),
+        font.clone(),
+        16.0,
+        "Some marked up text",
+        false,
+        TextDirection::Auto,
+    );
+    surface.draw_text(
+        Point::from_xy(20.0, y + 35.0),
+        font,
+        16.0,
+        "on two lines",
+        false,
+        TextDirection::Auto,
+    );
+}
+
+#[test]
+fn annotation_highlight() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    let mut surface = page.surface();
+    text_markup_text(&mut surface, 20.0);
+    surface.finish();
+
+    page.add_annotation(
+        HighlightAnnotation::new(
+            multi_line_quads(20.0),
+            rgb::Color::new(255, 255, 0),
+            NormalizedF32::ONE,
+            Some("A highlight".to_string()),
+        )
+        .into(),
+    );
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Highlight"), 1);
+    assert_eq!(
+        pdf_count(
+            &pdf,
+            b"/QuadPoints [20 180 180 180 20 160 180 160 20 160 90 160 20 140 90 140]"
+        ),
+        1
+    );
+    assert_eq!(pdf_count(&pdf, b"/Rect [20 140 180 180]"), 1);
+    assert_eq!(pdf_count(&pdf, b"/BBox [20 140 180 180]"), 1);
+    assert_eq!(pdf_count(&pdf, b"/C [1 1 0]"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Contents (A highlight)"), 1);
+    // The highlight is multiplied with the text below it.
+    assert_eq!(pdf_count(&pdf, b"/BM /Multiply"), 1);
+    assert_eq!(pdf_count(&pdf, b"/gs0 gs"), 1);
+}
+
+#[test]
+fn annotation_text_markup() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    let mut surface = page.surface();
+    text_markup_text(&mut surface, 10.0);
+    text_markup_text(&mut surface, 70.0);
+    text_markup_text(&mut surface, 130.0);
+    surface.finish();
+
+    let color = rgb::Color::new(255, 0, 0);
+    let opacity = NormalizedF32::new(0.8).unwrap();
+    page.add_annotation(
+        UnderlineAnnotation::new(multi_line_quads(10.0), color, opacity, None).into(),
+    );
+    page.add_annotation(
+        StrikeOutAnnotation::new(multi_line_quads(70.0), color, opacity, None).into(),
+    );
+    page.add_annotation(
+        SquigglyAnnotation::new(multi_line_quads(130.0), color, opacity, None).into(),
+    );
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Underline"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Subtype /StrikeOut"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Squiggly"), 1);
+    assert_eq!(pdf_count(&pdf, b"/C [1 0 0]"), 3);
+    // The opacity of the annotations and of their appearances.
+    assert_eq!(pdf_count(&pdf, b"/CA 0.8"), 6);
+    assert_eq!(pdf_count(&pdf, b"/ca 0.8"), 3);
+    assert_eq!(pdf_count(&pdf, b"/BM"), 0);
+    // Only squiggly lines have round caps, which are set for each of the two lines.
+    assert_eq!(pdf_count(&pdf, b"1 J"), 2);
+}
+
+#[test]
+#[should_panic]
+fn annotation_text_markup_with_no_quads() {
+    UnderlineAnnotation::new(vec![], rgb::Color::new(255, 0, 0), NormalizedF32::ONE, None);
+}
+
+fn text_annotation_1() -> TextAnnotation {
+    TextAnnotation::new(
+        Rect::from_xywh(20.0, 20.0, 20.0, 20.0).unwrap(),
+        TextIcon::Comment,
+        rgb::Color::new(255, 200, 0),
+        Some("Please rephrase this paragraph.".to_string()),
+    )
+    .with_author("Jane Doe".to_string())
+    .with_creation_date(DateTime::new(2024).month(5).day(3))
+    .with_modification_date(DateTime::new(2024).month(5).day(4))
+}
+
+#[snapshot(document)]
+fn annotation_text(document: &mut Document) {
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(text_annotation_1().into());
+    page.finish();
+}
+
+#[test]
+fn annotation_text_with_popup_rect() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(
+        text_annotation_1()
+            .with_popup_rect(Rect::from_xywh(20.0, 60.0, 160.0, 100.0).unwrap())
+            .into(),
+    );
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Popup"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Rect [20 40 180 140]"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Rect [40 60 220 180]"), 0);
+}
+
+fn free_text_annotation_1() -> FreeTextAnnotation {
+    FreeTextAnnotation::new(
+        Rect::from_xywh(20.0, 20.0, 160.0, 50.0).unwrap(),
+        "A free text annotation\nwith two lines".to_string(),
+        Font::new(NOTO_SANS.clone(), 0).unwrap(),
+        12.0,
+        rgb::Color::new(255, 0, 0),
+    )
+}
+
+#[snapshot(document)]
+fn annotation_free_text(document: &mut Document) {
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(free_text_annotation_1().into());
+    page.add_annotation(
+        free_text_annotation_1()
+            .with_border_style(BorderStyle {
+                width: 2.0,
+                dash: Some(vec![4.0, 2.0]),
+            })
+            .with_justification(Justification::Center)
+            .into(),
+    );
+    page.finish();
+}
+
+#[test]
+fn annotation_free_text_callout() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(
+        FreeTextAnnotation::new(
+            Rect::from_xywh(80.0, 20.0, 100.0, 40.0).unwrap(),
+            "Look here!".to_string(),
+            Font::new(NOTO_SANS.clone(), 0).unwrap(),
+            14.0,
+            rgb::Color::new(0, 0, 255),
+        )
+        .with_callout(Callout::new(
+            Point::from_xy(30.0, 150.0),
+            Some(Point::from_xy(50.0, 40.0)),
+            Point::from_xy(80.0, 40.0),
+            LineEnding::OpenArrow,
+        ))
+        .into(),
+    );
+    page.add_annotation(
+        FreeTextAnnotation::new(
+            Rect::from_xywh(80.0, 120.0, 100.0, 40.0).unwrap(),
+            "Or here!".to_string(),
+            Font::new(NOTO_SANS.clone(), 0).unwrap(),
+            14.0,
+            rgb::Color::new(0, 0, 255),
+        )
+        .with_callout(Callout::new(
+            Point::from_xy(160.0, 190.0),
+            None,
+            Point::from_xy(130.0, 160.0),
+            LineEnding::ClosedArrow,
+        ))
+        .into(),
+    );
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/IT /FreeTextCallout"), 2);
+    assert_eq!(pdf_count(&pdf, b"/CL [30 50 50 160 80 160]"), 1);
+    assert_eq!(pdf_count(&pdf, b"/CL [160 10 130 40]"), 1);
+    assert_eq!(pdf_count(&pdf, b"/LE /OpenArrow"), 1);
+    assert_eq!(pdf_count(&pdf, b"/LE /ClosedArrow"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Contents (Look here!)"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Contents (Or here!)"), 1);
+}
+
+#[test]
+fn annotation_free_text_complex_script() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(
+        FreeTextAnnotation::new(
+            Rect::from_xywh(20.0, 20.0, 160.0, 40.0).unwrap(),
+            "مرحبا بالعالم".to_string(),
+            Font::new(NOTO_SANS_ARABIC.clone(), 0).unwrap(),
+            16.0,
+            rgb::Color::new(0, 0, 0),
+        )
+        .with_justification(Justification::Right)
+        .into(),
+    );
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /FreeText"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Q 2"), 1);
+    assert_eq!(pdf_count(&pdf, b"/DA (/f0 16 Tf 0 0 0 rg)"), 1);
+    // The text is encoded as UTF-16, starting with the first letter in logical order.
+    assert_eq!(pdf_count(&pdf, b"/Contents <FEFF0645"), 1);
+    assert_eq!(pdf_count(&pdf, b"/FontFile2"), 1);
+}
+
+#[test]
+fn annotation_free_text_default_appearance() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(
+        free_text_annotation_1()
+            .with_justification(Justification::Right)
+            .with_callout(Callout::new(
+                Point::from_xy(10.0, 150.0),
+                None,
+                Point::from_xy(100.0, 70.0),
+                LineEnding::OpenArrow,
+            ))
+            .into(),
+    );
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/Subtype /FreeText"));
+    // The default appearance refers to the font of the generated appearance.
+    assert!(pdf_contains(&pdf, b"/DA (/f0 12 Tf 1 0 0 rg)"));
+    assert!(pdf_contains(&pdf, b"/DR"));
+    assert!(pdf_contains(&pdf, b"/Q 2"));
+    assert!(pdf_contains(&pdf, b"/IT /FreeTextCallout"));
+    assert!(pdf_contains(&pdf, b"/LE /OpenArrow"));
+    assert!(pdf_contains(
+        &pdf,
+        b"/Contents <FEFF0041002000660072006500650020"
+    ));
+}
+
+#[test]
+fn annotation_free_text_no_device_cs() {
+    let mut document = Document::new_with(settings_2());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(free_text_annotation_1().into());
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/DA (/f0 12 Tf /cs0 cs 1 0 0 sc)"));
+    assert!(pdf_contains(&pdf, b"/ColorSpace"));
+}
+
+#[test]
+fn annotation_free_text_with_glyphs() {
+    let glyph =
+        |id: u32, range| KrillaGlyph::new(GlyphId::new(id), 0.5, 0.0, 0.0, 0.0, range, None);
+
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(
+        FreeTextAnnotation::new_with_glyphs(
+            Rect::from_xywh(20.0, 20.0, 160.0, 50.0).unwrap(),
+            "AB\nC".to_string(),
+            vec![
+                vec![glyph(36, 0..1), glyph(37, 1..2)],
+                vec![glyph(38, 3..4)],
+            ],
+            Font::new(NOTO_SANS.clone(), 0).unwrap(),
+            12.0,
+            rgb::Color::new(255, 0, 0),
+        )
+        .into(),
+    );
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/Subtype /FreeText"));
+    // The text contains a newline, so it's encoded as UTF-16.
+    assert!(pdf_contains(&pdf, b"/Contents <FEFF00410042000A0043>"));
+    assert!(pdf_contains(&pdf, b"/DA (/f0 12 Tf 1 0 0 rg)"));
+}
+
+fn ink_paths() -> Vec<Vec<Point>> {
+    vec![
+        vec![
+            Point::from_xy(20.0, 100.0),
+            Point::from_xy(50.0, 40.0),
+            Point::from_xy(80.0, 100.0),
+            Point::from_xy(110.0, 40.0),
+            Point::from_xy(140.0, 100.0),
+        ],
+        vec![Point::from_xy(30.0, 150.0), Point::from_xy(170.0, 170.0)],
+        vec![Point::from_xy(100.0, 130.0)],
+    ]
+}
+
+#[snapshot(document)]
+fn annotation_ink(document: &mut Document) {
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(
+        InkAnnotation::new(
+            ink_paths(),
+            4.0,
+            rgb::Color::new(0, 0, 255),
+            NormalizedF32::new(0.7).unwrap(),
+            Some("A signature".to_string()),
+        )
+        .into(),
+    );
+    page.finish();
+}
+
+#[test]
+fn annotation_ink_list() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(
+        InkAnnotation::new(
+            ink_paths(),
+            2.0,
+            rgb::Color::new(0, 0, 0),
+            NormalizedF32::ONE,
+            None,
+        )
+        .into(),
+    );
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/Subtype /Ink"));
+    // The points are converted to PDF coordinates, which have their origin at the bottom.
+    assert!(pdf_contains(
+        &pdf,
+        b"[20 100 50 160 80 100 110 160 140 100]"
+    ));
+    assert!(pdf_contains(&pdf, b"[30 50 170 30]"));
+    assert!(pdf_contains(&pdf, b"[100 70]"));
+    assert!(pdf_contains(&pdf, b"/Rect [19 29 171 161]"));
+}
+
+#[test]
+#[should_panic]
+fn annotation_ink_empty() {
+    InkAnnotation::new(
+        vec![vec![]],
+        2.0,
+        rgb::Color::new(0, 0, 0),
+        NormalizedF32::ONE,
+        None,
+    );
+}
+
+fn stamp_appearance() -> StampAppearance {
+    StampAppearance::Custom(Box::new(draw_stamp))
+}
+
+fn draw_stamp(surface: &mut Surface) {
+    surface.set_fill(Some(red_fill(0.2)));
+    surface.draw_path(&rect_to_path(0.0, 0.0, 120.0, 40.0));
+    surface.set_fill(Some(red_fill(1.0)));
+    surface.draw_text(
+        Point::from_xy(10.0, 28.0),
+        Font::new(NOTO_SANS.clone(), 0).unwrap(),
+        20.0,
+        "APPROVED",
+        false,
+        TextDirection::Auto,
+    );
+}
+
+#[test]
+fn annotation_stamp_custom() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(
+        StampAnnotation::new(
+            Rect::from_xywh(20.0, 20.0, 120.0, 40.0).unwrap(),
+            stamp_appearance(),
+            Some("Approved".to_string()),
+        )
+        .into(),
+    );
+    page.add_annotation(
+        StampAnnotation::new(
+            Rect::from_xywh(40.0, 110.0, 120.0, 40.0).unwrap(),
+            stamp_appearance(),
+            Some("Approved".to_string()),
+        )
+        .with_rotation(30.0)
+        .with_opacity(NormalizedF32::new(0.6).unwrap())
+        .into(),
+    );
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Stamp"), 2);
+    assert_eq!(pdf_count(&pdf, b"/Name /Custom"), 2);
+    assert_eq!(pdf_count(&pdf, b"/Contents (Approved)"), 2);
+    // The unrotated stamp covers exactly its rect.
+    assert_eq!(pdf_count(&pdf, b"/Rect [20 140 140 180]"), 1);
+    assert_eq!(pdf_count(&pdf, b"/CA 0.6"), 1);
+    // Both wrappers draw the same stamp.
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Form"), 3);
+    assert_eq!(pdf_count(&pdf, b"/x0 Do"), 2);
+}
+
+#[test]
+fn annotation_stamp_standard() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(
+        StampAnnotation::new(
+            Rect::from_xywh(20.0, 20.0, 120.0, 40.0).unwrap(),
+            StampAppearance::Standard(StandardStamp::Draft),
+            Some("Draft".to_string()),
+        )
+        .into(),
+    );
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Stamp"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Name /Draft"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Rect [20 140 140 180]"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Contents (Draft)"), 1);
+    // The appearance of standard stamps is left up to the viewer.
+    assert_eq!(pdf_count(&pdf, b"/AP"), 0);
+}
+
+#[test]
+fn annotation_stamp_deduplicated() {
+    let mut document = Document::new_with(settings_1());
+
+    for rotation in [0.0, 45.0] {
+        let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+        page.add_annotation(
+            StampAnnotation::new(
+                Rect::from_xywh(20.0, 20.0, 120.0, 40.0).unwrap(),
+                stamp_appearance(),
+                None,
+            )
+            .with_rotation(rotation)
+            .into(),
+        );
+        page.finish();
+    }
+
+    let pdf = document.finish().unwrap();
+    // One wrapper for each stamp, but the stamp itself is only written once, since
+    // both are drawn with the same contents.
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Form"), 3);
+    assert_eq!(pdf_count(&pdf, b"/Name /Custom"), 2);
+}
+
+fn dashed_border() -> BorderStyle {
+    BorderStyle {
+        width: 3.0,
+        dash: Some(vec![6.0, 3.0]),
+    }
+}
+
+#[snapshot(document)]
+fn annotation_shapes(document: &mut Document) {
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    let red = rgb::Color::new(255, 0, 0);
+    let yellow = rgb::Color::new(255, 255, 0);
+
+    page.add_annotation(
+        SquareAnnotation::new(Rect::from_xywh(10.0, 10.0, 80.0, 50.0).unwrap(), red, None)
+            .with_interior_color(yellow)
+            .into(),
+    );
+    page.add_annotation(
+        CircleAnnotation::new(Rect::from_xywh(110.0, 10.0, 80.0, 50.0).unwrap(), red, None)
+            .with_border_style(dashed_border())
+            .into(),
+    );
+    page.add_annotation(
+        PolygonAnnotation::new(
+            vec![
+                Point::from_xy(50.0, 80.0),
+                Point::from_xy(90.0, 180.0),
+                Point::from_xy(10.0, 180.0),
+            ],
+            red,
+            None,
+        )
+        .with_interior_color(yellow)
+        .with_border_style(dashed_border())
+        .into(),
+    );
+    page.add_annotation(
+        PolylineAnnotation::new(
+            vec![
+                Point::from_xy(110.0, 180.0),
+                Point::from_xy(130.0, 90.0),
+                Point::from_xy(160.0, 170.0),
+                Point::from_xy(190.0, 80.0),
+            ],
+            red,
+            None,
+        )
+        .with_line_endings(LineEnding::Circle, LineEnding::ClosedArrow)
+        .with_interior_color(yellow)
+        .into(),
+    );
+    page.finish();
+}
+
+#[test]
+fn annotation_line_endings() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    let endings = [
+        LineEnding::None,
+        LineEnding::OpenArrow,
+        LineEnding::ClosedArrow,
+        LineEnding::ROpenArrow,
+        LineEnding::RClosedArrow,
+        LineEnding::Square,
+        LineEnding::Circle,
+        LineEnding::Diamond,
+        LineEnding::Butt,
+        LineEnding::Slash,
+    ];
+
+    // Draw the line endings at arbitrary angles, arranged in a star.
+    for (i, ending) in endings.into_iter().enumerate() {
+        let (sin, cos) = (i as f32 * 36.0 + 10.0).to_radians().sin_cos();
+        let start = Point::from_xy(100.0 + 20.0 * cos, 100.0 + 20.0 * sin);
+        let end = Point::from_xy(100.0 + 85.0 * cos, 100.0 + 85.0 * sin);
+        page.add_annotation(
+            LineAnnotation::new(start, end, rgb::Color::new(0, 0, 255), None)
+                .with_line_endings(LineEnding::None, ending)
+                .with_interior_color(rgb::Color::new(0, 255, 0))
+                .with_border_style(BorderStyle {
+                    width: 1.5,
+                    dash: None,
+                })
+                .into(),
+        );
+    }
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Line"), 10);
+    for ending in [
+        "None",
+        "OpenArrow",
+        "ClosedArrow",
+        "ROpenArrow",
+        "RClosedArrow",
+        "Square",
+        "Circle",
+        "Diamond",
+        "Butt",
+        "Slash",
+    ] {
+        assert_eq!(
+            pdf_count(&pdf, format!("/LE [/None /{ending}]").as_bytes()),
+            1
+        );
+    }
+    assert_eq!(pdf_count(&pdf, b"/C [0 0 1]"), 10);
+    assert_eq!(pdf_count(&pdf, b"/IC [0 1 0]"), 10);
+    assert_eq!(pdf_count(&pdf, b"/W 1.5"), 10);
+    assert_eq!(pdf_count(&pdf, b"/LL "), 0);
+}
+
+#[snapshot(document)]
+fn annotation_line_leader_lines(document: &mut Document) {
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(
+        LineAnnotation::new(
+            Point::from_xy(30.0, 150.0),
+            Point::from_xy(170.0, 110.0),
+            rgb::Color::new(0, 0, 0),
+            Some("Distance".to_string()),
+        )
+        .with_line_endings(LineEnding::OpenArrow, LineEnding::OpenArrow)
+        .with_leader_lines(30.0, 10.0, 5.0)
+        .into(),
+    );
+    page.add_annotation(
+        LineAnnotation::new(
+            Point::from_xy(30.0, 60.0),
+            Point::from_xy(170.0, 60.0),
+            rgb::Color::new(0, 0, 0),
+            None,
+        )
+        .with_line_endings(LineEnding::Butt, LineEnding::Butt)
+        .with_leader_lines(-20.0, 5.0, 0.0)
+        .with_border_style(dashed_border())
+        .into(),
+    );
+    page.finish();
+}
+
+#[test]
+fn annotation_line_entries() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(
+        LineAnnotation::new(
+            Point::from_xy(20.0, 20.0),
+            Point::from_xy(120.0, 20.0),
+            rgb::Color::new(255, 0, 0),
+            None,
+        )
+        .with_line_endings(LineEnding::ClosedArrow, LineEnding::Slash)
+        .with_leader_lines(10.0, 2.0, 1.0)
+        .with_interior_color(rgb::Color::new(0, 0, 255))
+        .into(),
+    );
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/Subtype /Line"));
+    assert!(pdf_contains(&pdf, b"/L [20 180 120 180]"));
+    assert!(pdf_contains(&pdf, b"/LE [/ClosedArrow /Slash]"));
+    assert!(pdf_contains(&pdf, b"/LL 10"));
+    assert!(pdf_contains(&pdf, b"/IC [0 0 1]"));
+}
+
+#[snapshot(document)]
+fn annotation_file_attachment(document: &mut Document) {
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    let icons = [
+        FileAttachmentIcon::Graph,
+        FileAttachmentIcon::PushPin,
+        FileAttachmentIcon::Paperclip,
+        FileAttachmentIcon::Tag,
+    ];
+
+    for (i, icon) in icons.into_iter().enumerate() {
+        page.add_annotation(
+            FileAttachmentAnnotation::new(
+                Rect::from_xywh(20.0 + i as f32 * 45.0, 50.0, 30.0, 30.0).unwrap(),
+                icon,
+                rgb::Color::new(0, 0, 255),
+                file_1(),
+                Some("An attached file.".to_string()),
+            )
+            .into(),
+        );
+    }
+    page.finish();
+}
+
+#[test]
+fn annotation_file_attachment_shared_file() {
+    let mut document = Document::new_with(settings_1());
+    document.embed_file(file_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    for y in [20.0, 60.0] {
+        page.add_annotation(
+            FileAttachmentAnnotation::new(
+                Rect::from_xywh(20.0, y, 20.0, 20.0).unwrap(),
+                FileAttachmentIcon::default(),
+                rgb::Color::new(0, 0, 0),
+                file_1(),
+                None,
+            )
+            .into(),
+        );
+    }
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /FileAttachment"), 2);
+    assert_eq!(pdf_count(&pdf, b"/Type /EmbeddedFile"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Type /Filespec"), 1);
+}
+
+fn flagged_annotations(page: &mut Page) {
+    page.add_annotation(
+        Annotation::from(SquareAnnotation::new(
+            Rect::from_xywh(20.0, 20.0, 60.0, 60.0).unwrap(),
+            rgb::Color::new(255, 0, 0),
+            None,
+        ))
+        .with_flags(
+            AnnotationFlags::default()
+                .with_print(false)
+                .with_locked(true),
+        )
+        .with_opacity(NormalizedF32::new(0.5).unwrap())
+        .with_blend_mode(BlendMode::Multiply),
+    );
+    page.add_annotation(
+        Annotation::from(InkAnnotation::new(
+            vec![vec![
+                Point::from_xy(100.0, 100.0),
+                Point::from_xy(150.0, 150.0),
+            ]],
+            2.0,
+            rgb::Color::new(0, 0, 255),
+            NormalizedF32::new(0.5).unwrap(),
+            None,
+        ))
+        .with_opacity(NormalizedF32::new(0.5).unwrap()),
+    );
+}
+
+#[test]
+fn annotation_flags_opacity_blend_mode() {
+    let mut document = Document::new_with(settings_25());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    flagged_annotations(&mut page);
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    // Locked, but not printed.
+    assert!(pdf_contains(&pdf, b"/F 128"));
+    assert!(pdf_contains(&pdf, b"/CA 0.5"));
+    assert!(pdf_contains(&pdf, b"/BM /Multiply"));
+    // The opacity of the ink annotation is combined with the one of the annotation.
+    assert!(pdf_contains(&pdf, b"/CA 0.25"));
+}
+
+#[test]
+fn annotation_blend_mode_pdf_17() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    flagged_annotations(&mut page);
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    // Blend modes of annotations are only supported in PDF 2.0.
+    assert!(!pdf_contains(&pdf, b"/BM /Multiply"));
+    assert!(pdf_contains(&pdf, b"/CA 0.5"));
+}
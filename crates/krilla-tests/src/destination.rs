@@ -1,10 +1,18 @@
-use krilla::annotation::{LinkAnnotation, Target};
-use krilla::destination::{NamedDestination, XyzDestination};
+use krilla::action::{Action, DocumentActions, JavaScriptAction, NamedAction};
+use krilla::annotation::{Annotation, LinkAnnotation, Target};
+use krilla::destination::{
+    FitDestination, FitHDestination, FitRDestination, FitVDestination, NamedDestination,
+    StructureDestination, XyzDestination,
+};
+use krilla::error::KrillaError;
 use krilla::geom::{Point, Rect};
+use krilla::outline::{Outline, OutlineNode};
+use krilla::page::PageSettings;
+use krilla::tagging::{ContentTag, Tag, TagGroup, TagTree};
 use krilla_macros::snapshot;
 
 use crate::{blue_fill, green_fill, rect_to_path, red_fill};
-use crate::{cmyk_fill, Document};
+use crate::{cmyk_fill, pdf_contains, pdf_count, settings_1, settings_25, Document};
 
 #[snapshot(document)]
 fn destination_named(d: &mut Document) {
@@ -58,3 +66,326 @@ fn destination_named(d: &mut Document) {
     surface.finish();
     page.finish();
 }
+
+#[test]
+fn destination_open_action() {
+    let mut d = Document::new_with(settings_1());
+    for fill in [red_fill(1.0), green_fill(1.0)] {
+        let mut page = d.start_page();
+        let mut surface = page.surface();
+        surface.set_fill(Some(fill));
+        surface.draw_path(&rect_to_path(0.0, 0.0, 100.0, 100.0));
+        surface.finish();
+        page.finish();
+    }
+
+    d.set_open_action(Target::Destination(
+        XyzDestination::new(1, Point::from_xy(0.0, 0.0)).into(),
+    ));
+
+    let pdf = d.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/OpenAction"), 1);
+    // Destinations are wrapped in a go-to action.
+    assert_eq!(pdf_count(&pdf, b"/S /GoTo"), 1);
+    // The top of the A4 page, with the zoom factor left unchanged.
+    assert_eq!(pdf_count(&pdf, b"/XYZ 0 842 null]"), 1);
+}
+
+#[test]
+fn destination_open_action_and_document_actions() {
+    let mut d = Document::new_with(settings_1());
+    d.start_page().finish();
+    d.set_open_action(Target::Action(NamedAction::LastPage.into()));
+    d.set_document_actions(
+        DocumentActions::new()
+            .with_will_close(JavaScriptAction::new("app.alert('Bye');".to_string()).into())
+            .with_did_print(NamedAction::FirstPage.into()),
+    );
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/OpenAction"));
+    assert!(pdf_contains(&pdf, b"/N /LastPage"));
+    assert!(pdf_contains(&pdf, b"/AA"));
+    assert!(pdf_contains(&pdf, b"/WC <<"));
+    assert!(pdf_contains(&pdf, b"/DP <<"));
+    assert!(!pdf_contains(&pdf, b"/WP"));
+}
+
+#[test]
+fn destination_empty_document_actions() {
+    let mut d = Document::new_with(settings_1());
+    d.start_page().finish();
+    d.set_document_actions(DocumentActions::new());
+
+    let pdf = d.finish().unwrap();
+    assert!(!pdf_contains(&pdf, b"/AA"));
+}
+
+fn fit_destinations_document(d: &mut Document) {
+    let link = |y: f32, target: Target| -> Annotation {
+        LinkAnnotation::new(Rect::from_xywh(0.0, y, 100.0, 20.0).unwrap(), None, target).into()
+    };
+
+    let mut page = d.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(link(
+        0.0,
+        Target::Destination(FitDestination::new(1).into()),
+    ));
+    page.add_annotation(link(
+        20.0,
+        Target::Action(Action::Goto(FitVDestination::new(1, 30.0).into())),
+    ));
+    page.add_annotation(link(
+        40.0,
+        Target::Destination(
+            NamedDestination::new(
+                "fit-rect".to_string(),
+                FitRDestination::new(1, Rect::from_ltrb(20.0, 40.0, 120.0, 140.0).unwrap()),
+            )
+            .into(),
+        ),
+    ));
+    let mut surface = page.surface();
+    surface.set_fill(Some(red_fill(1.0)));
+    surface.draw_path(&rect_to_path(0.0, 0.0, 100.0, 60.0));
+    surface.finish();
+    page.finish();
+
+    let mut page = d.start_page_with(PageSettings::new(200.0, 200.0));
+    let mut surface = page.surface();
+    surface.set_fill(Some(green_fill(1.0)));
+    surface.draw_path(&rect_to_path(20.0, 40.0, 120.0, 140.0));
+    surface.finish();
+    page.finish();
+
+    let mut outline = Outline::new();
+    outline.push_child(OutlineNode::new(
+        "Fit width".to_string(),
+        FitHDestination::new(1, 50.0),
+    ));
+    d.set_outline(outline);
+}
+
+#[test]
+fn destination_fit() {
+    let mut d = Document::new_with(settings_1());
+    fit_destinations_document(&mut d);
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/Fit]"));
+    // Vertical coordinates are converted from the top-left origin, just like
+    // for XYZ destinations.
+    assert!(pdf_contains(&pdf, b"/FitH 150]"));
+    assert!(pdf_contains(&pdf, b"/FitV 30]"));
+    assert!(pdf_contains(&pdf, b"/FitR 20 60 120 160]"));
+}
+
+#[test]
+#[should_panic]
+fn destination_named_of_named() {
+    let named = NamedDestination::new(
+        "inner".to_string(),
+        XyzDestination::new(0, Point::from_xy(0.0, 0.0)),
+    );
+    NamedDestination::new("outer".to_string(), named);
+}
+
+fn forward_references_document(d: &mut Document) {
+    let mut page = d.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(
+        LinkAnnotation::new(
+            Rect::from_xywh(0.0, 0.0, 100.0, 50.0).unwrap(),
+            None,
+            Target::Destination(NamedDestination::by_name("sec-2".to_string()).into()),
+        )
+        .into(),
+    );
+    page.add_annotation(
+        LinkAnnotation::new(
+            Rect::from_xywh(0.0, 50.0, 100.0, 50.0).unwrap(),
+            None,
+            Target::Action(Action::Goto(
+                NamedDestination::by_name("sec-2".to_string()).into(),
+            )),
+        )
+        .into(),
+    );
+    let mut surface = page.surface();
+    surface.set_fill(Some(red_fill(1.0)));
+    surface.draw_path(&rect_to_path(0.0, 0.0, 100.0, 100.0));
+    surface.finish();
+    page.finish();
+
+    let mut page = d.start_page_with(PageSettings::new(200.0, 200.0));
+    let mut surface = page.surface();
+    surface.set_fill(Some(green_fill(1.0)));
+    surface.draw_path(&rect_to_path(0.0, 100.0, 100.0, 200.0));
+    surface.finish();
+    page.finish();
+
+    d.add_named_destination(
+        "sec-2".to_string(),
+        XyzDestination::new(1, Point::from_xy(0.0, 100.0)),
+    );
+}
+
+#[test]
+fn destination_named_forward_reference() {
+    let mut d = Document::new_with(settings_1());
+    forward_references_document(&mut d);
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/Dest (sec-2)"));
+    assert!(pdf_contains(&pdf, b"/D (sec-2)"));
+    assert!(pdf_contains(&pdf, b"/Names [(sec-2)"));
+    assert!(pdf_contains(&pdf, b"/XYZ 0 100 null]"));
+}
+
+#[test]
+fn destination_named_registry_takes_precedence() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(
+        LinkAnnotation::new(
+            Rect::from_xywh(0.0, 0.0, 100.0, 50.0).unwrap(),
+            None,
+            Target::Destination(
+                NamedDestination::new("sec-1".to_string(), FitDestination::new(0)).into(),
+            ),
+        )
+        .into(),
+    );
+    page.finish();
+    d.add_named_destination("sec-1".to_string(), FitHDestination::new(0, 50.0));
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/FitH 150]"));
+    assert!(!pdf_contains(&pdf, b"/Fit]"));
+}
+
+#[test]
+fn destination_named_unresolved() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    for (index, name) in ["sec-2", "sec-1", "sec-2"].into_iter().enumerate() {
+        page.add_annotation(
+            LinkAnnotation::new(
+                Rect::from_xywh(0.0, index as f32 * 50.0, 100.0, 50.0).unwrap(),
+                None,
+                Target::Destination(NamedDestination::by_name(name.to_string()).into()),
+            )
+            .into(),
+        );
+    }
+    page.finish();
+
+    assert_eq!(
+        d.finish(),
+        Err(KrillaError::UnresolvedNamedDestinations(vec![
+            "sec-1".to_string(),
+            "sec-2".to_string()
+        ]))
+    );
+}
+
+#[test]
+fn destination_xyz_zoom() {
+    let mut d = Document::new_with(settings_1());
+    d.start_page_with(PageSettings::new(200.0, 200.0)).finish();
+
+    let point = Point::from_xy(20.0, 50.0);
+    let mut outline = Outline::new();
+    outline.push_child(OutlineNode::new(
+        "Zoomed".to_string(),
+        XyzDestination::new(0, point).with_zoom(Some(1.5)),
+    ));
+    outline.push_child(OutlineNode::new(
+        "Keep left".to_string(),
+        XyzDestination::new(0, point).with_left(None),
+    ));
+    outline.push_child(OutlineNode::new(
+        "Keep top".to_string(),
+        XyzDestination::new(0, point)
+            .with_top(None)
+            .with_zoom(Some(2.0)),
+    ));
+    d.set_outline(outline);
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/XYZ 20 150 1.5]"));
+    assert!(pdf_contains(&pdf, b"/XYZ null 150 null]"));
+    assert!(pdf_contains(&pdf, b"/XYZ 20 null 2]"));
+}
+
+fn structure_destination_document(d: &mut Document) {
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    let id = surface.start_tagged(ContentTag::Other);
+    surface.set_fill(Some(red_fill(1.0)));
+    surface.draw_path(&rect_to_path(20.0, 100.0, 120.0, 200.0));
+    surface.end_tagged();
+    surface.finish();
+    page.finish();
+
+    let destination = StructureDestination::new(id, FitHDestination::new(0, 100.0));
+
+    let mut page = d.start_page();
+    page.add_annotation(
+        LinkAnnotation::new(
+            Rect::from_xywh(0.0, 0.0, 100.0, 100.0).unwrap(),
+            None,
+            Target::Destination(destination.clone().into()),
+        )
+        .into(),
+    );
+    page.finish();
+
+    let mut outline = Outline::new();
+    outline.push_child(OutlineNode::new("Section".to_string(), destination));
+    d.set_outline(outline);
+
+    let mut tag_tree = TagTree::new();
+    let mut section = TagGroup::new(Tag::Section);
+    section.push(id);
+    tag_tree.push(section);
+    d.set_tag_tree(tag_tree);
+}
+
+#[test]
+fn destination_structure() {
+    let mut d = Document::new_with(settings_25());
+    structure_destination_document(&mut d);
+
+    let pdf = d.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/S /GoTo"), 2);
+    assert_eq!(pdf_count(&pdf, b"/SD "), 2);
+    // The fallback destination and the structure destination, which both
+    // use the same view.
+    assert_eq!(pdf_count(&pdf, b"/FitH 742]"), 2);
+    assert_eq!(pdf_count(&pdf, b"/Dest"), 0);
+}
+
+#[test]
+fn destination_structure_before_pdf_20() {
+    let mut d = Document::new_with(settings_1());
+    structure_destination_document(&mut d);
+
+    let pdf = d.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Dest "), 2);
+    assert_eq!(pdf_count(&pdf, b"/FitH 742]"), 1);
+    assert_eq!(pdf_count(&pdf, b"/SD"), 0);
+}
+
+#[test]
+#[should_panic]
+fn destination_structure_with_named_fallback() {
+    let mut d = Document::new();
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    let id = surface.start_tagged(ContentTag::Other);
+    surface.end_tagged();
+    surface.finish();
+    page.finish();
+
+    StructureDestination::new(id, NamedDestination::by_name("hi".to_string()));
+}
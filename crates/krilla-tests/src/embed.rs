@@ -1,12 +1,13 @@
 use krilla::configure::ValidationError;
 use krilla::embed::{AssociationKind, EmbedError, EmbeddedFile};
 use krilla::error::KrillaError;
+use krilla::facturx::{ConformanceLevel, FacturXError};
 use krilla::metadata::{DateTime, Metadata};
-use krilla::tagging::TagTree;
+use krilla::tagging::{ContentTag, Tag, TagGroup, TagTree};
 use krilla_macros::snapshot;
 
-use crate::{metadata_1, Document};
-use crate::{settings_13, settings_23, ASSETS_PATH};
+use crate::{green_fill, metadata_1, rect_to_path, Document};
+use crate::{pdf_count, settings_1, settings_13, settings_23, settings_25, ASSETS_PATH};
 
 pub(crate) fn file_1() -> EmbeddedFile {
     let data = std::fs::read(ASSETS_PATH.join("emojis.txt")).unwrap();
@@ -117,6 +118,56 @@ fn embedded_file_pdf_20(d: &mut Document) {
     embedded_file_impl(d)
 }
 
+fn associated_files_impl(d: &mut Document) {
+    d.set_metadata(metadata_1());
+    d.add_associated_file(file_1());
+
+    let mut page = d.start_page();
+    page.add_associated_file(file_2());
+    let mut surface = page.surface();
+    let id = surface.start_tagged(ContentTag::Other);
+    surface.set_fill(Some(green_fill(1.0)));
+    surface.draw_path(&rect_to_path(20.0, 20.0, 120.0, 120.0));
+    surface.end_tagged();
+    surface.finish();
+    page.finish();
+
+    let mut figure = TagGroup::new(Tag::Figure(Some("A chart.".to_string())));
+    figure.add_associated_file(file_3());
+    figure.push(id);
+    let mut tag_tree = TagTree::new();
+    tag_tree.push(figure);
+    d.set_tag_tree(tag_tree);
+}
+
+#[test]
+fn embedded_file_associated_files() {
+    let mut d = Document::new_with(settings_25());
+    associated_files_impl(&mut d);
+
+    let pdf = d.finish().unwrap();
+    // The catalog, the page and the struct element.
+    assert_eq!(pdf_count(&pdf, b"/AF ["), 3);
+    assert_eq!(pdf_count(&pdf, b"/AFRelationship /Supplement"), 2);
+    assert_eq!(pdf_count(&pdf, b"/AFRelationship /Unspecified"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Type /EmbeddedFile"), 3);
+    // Only the file associated with the document is listed as an attachment.
+    assert_eq!(pdf_count(&pdf, b"(emojis.txt)"), 3);
+    assert_eq!(pdf_count(&pdf, b"(image.svg)"), 2);
+}
+
+#[test]
+fn embedded_file_associated_files_before_pdf_20() {
+    let mut d = Document::new_with(settings_1());
+    associated_files_impl(&mut d);
+
+    let pdf = d.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/AF"), 0);
+    // The file associated with the document is still embedded.
+    assert_eq!(pdf_count(&pdf, b"/Type /EmbeddedFile"), 1);
+    assert_eq!(pdf_count(&pdf, b"/EmbeddedFiles"), 1);
+}
+
 #[test]
 fn embedded_file_duplicate() {
     let mut d = Document::new();
@@ -146,3 +197,65 @@ fn embedded_file_pdf_a2() {
         ]))
     )
 }
+
+fn facturx_xml() -> Vec<u8> {
+    b"<?xml version=\"1.0\" encoding=\"UTF-8\"?><rsm:CrossIndustryInvoice/>".to_vec()
+}
+
+#[test]
+fn embedded_file_facturx() {
+    let mut d = Document::new_with(settings_23());
+    d.set_metadata(metadata_1());
+    d.set_facturx(facturx_xml().into(), ConformanceLevel::En16931);
+
+    let pdf = d.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/AFRelationship /Alternative"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Subtype /text#2Fxml"), 1);
+    assert_eq!(pdf_count(&pdf, b"(factur-x.xml)"), 3);
+    assert_eq!(
+        pdf_count(
+            &pdf,
+            b"urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#"
+        ),
+        2
+    );
+    assert_eq!(pdf_count(&pdf, b"EN 16931"), 1);
+    assert_eq!(pdf_count(&pdf, b"INVOICE"), 1);
+}
+
+#[test]
+fn embedded_file_facturx_minimum() {
+    let mut d = Document::new_with(settings_23());
+    d.set_metadata(metadata_1());
+    d.set_facturx(facturx_xml().into(), ConformanceLevel::Minimum);
+
+    let pdf = d.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/AFRelationship /Data"), 1);
+    assert_eq!(pdf_count(&pdf, b"MINIMUM"), 1);
+}
+
+#[test]
+fn embedded_file_facturx_not_pdf_a3() {
+    let mut d = Document::new_with(settings_1());
+    d.set_facturx(facturx_xml().into(), ConformanceLevel::En16931);
+
+    assert_eq!(
+        d.finish(),
+        Err(KrillaError::FacturX(FacturXError::NotPdfA3))
+    );
+}
+
+#[test]
+fn embedded_file_facturx_duplicate() {
+    let mut d = Document::new_with(settings_23());
+    d.set_metadata(metadata_1());
+    let mut file = file_1();
+    file.path = "factur-x.xml".to_string();
+    d.embed_file(file);
+    d.set_facturx(facturx_xml().into(), ConformanceLevel::En16931);
+
+    assert_eq!(
+        d.finish(),
+        Err(KrillaError::FacturX(FacturXError::DuplicateInvoice))
+    );
+}
@@ -0,0 +1,148 @@
+use krilla::configure::{Configuration, PdfVersion, ValidationError, Validator};
+use krilla::embed::{AssociationKind, EmbeddedFile};
+use krilla::encryption::{Encryption, EncryptionError, Permissions};
+use krilla::error::KrillaError;
+use krilla::Document;
+use krilla::SerializeSettings;
+
+use crate::metadata::metadata_impl;
+use crate::text::simple_text_impl;
+use crate::{metadata_1, pdf_contains, pdf_count, settings_1, NOTO_SANS};
+
+fn encryption_1() -> Encryption {
+    Encryption::new("user".to_string(), "owner".to_string())
+}
+
+fn encrypted_document(settings: SerializeSettings) -> Document {
+    let mut document = Document::new_with(SerializeSettings {
+        encryption: Some(encryption_1()),
+        ..settings
+    });
+    let mut page = document.start_page();
+    simple_text_impl(&mut page, NOTO_SANS.clone());
+    page.finish();
+    metadata_impl(&mut document);
+
+    document
+}
+
+#[test]
+fn encryption_aes_256() {
+    let pdf = encrypted_document(settings_1()).finish().unwrap();
+
+    assert!(pdf_contains(&pdf, b"/Filter /Standard"));
+    assert!(pdf_contains(&pdf, b"/CFM /AESV3"));
+    assert!(pdf_contains(&pdf, b"/ExtensionLevel 8"));
+    // Strings should not be readable anymore.
+    assert!(!pdf_contains(&pdf, b"An awesome title"));
+
+    assert_valid_xref(&pdf);
+}
+
+/// Check that the trailer refers to the encryption dictionary and that
+/// the cross-reference table is still where `startxref` points to.
+fn assert_valid_xref(pdf: &[u8]) {
+    let startxref = pdf.windows(10).rposition(|w| w == b"startxref\n").unwrap();
+    let offset = std::str::from_utf8(&pdf[startxref + 10..pdf.len() - 6])
+        .unwrap()
+        .trim()
+        .parse::<usize>()
+        .unwrap();
+    assert!(pdf[offset..].starts_with(b"xref\n"));
+
+    let trailer = pdf.windows(8).rposition(|w| w == b"trailer\n").unwrap();
+    assert!(trailer > offset);
+    assert!(pdf_contains(&pdf[trailer..], b"/Encrypt "));
+}
+
+#[test]
+fn encryption_stream_with_keywords() {
+    let mut document = encrypted_document(settings_1());
+    // The data of streams must not be confused with the structure of the file.
+    document.embed_file(EmbeddedFile {
+        path: "keywords.txt".to_string(),
+        mime_type: None,
+        description: None,
+        association_kind: AssociationKind::Unspecified,
+        data: b"endstream\nendobj\nxref\ntrailer\n<< >>\nstartxref\n0\n%%EOF"
+            .to_vec()
+            .into(),
+        compress: Some(false),
+        location: None,
+    });
+    let pdf = document.finish().unwrap();
+
+    assert!(!pdf_contains(&pdf, b"endobj\nxref\ntrailer"));
+    assert_eq!(pdf_count(&pdf, b"trailer\n"), 1);
+    assert_valid_xref(&pdf);
+}
+
+#[test]
+fn encryption_unsupported_pdf_version() {
+    let settings = SerializeSettings {
+        configuration: Configuration::new_with_version(PdfVersion::Pdf16),
+        ..settings_1()
+    };
+
+    assert_eq!(
+        encrypted_document(settings).finish(),
+        Err(KrillaError::Encryption(
+            EncryptionError::UnsupportedPdfVersion
+        ))
+    );
+}
+
+#[test]
+fn encryption_invalid_password() {
+    let mut document = Document::new_with(SerializeSettings {
+        encryption: Some(Encryption::new("\u{7}".to_string(), "owner".to_string())),
+        ..settings_1()
+    });
+    document.start_page().finish();
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::Encryption(EncryptionError::InvalidPassword))
+    );
+}
+
+#[test]
+fn encryption_pdf_20() {
+    let settings = SerializeSettings {
+        configuration: Configuration::new_with_version(PdfVersion::Pdf20),
+        ..settings_1()
+    };
+    let pdf = encrypted_document(settings).finish().unwrap();
+
+    assert!(pdf_contains(&pdf, b"/Encrypt"));
+    assert!(!pdf_contains(&pdf, b"/Extensions"));
+}
+
+#[test]
+fn encryption_permissions() {
+    let settings = SerializeSettings {
+        encryption: Some(Encryption {
+            permissions: Permissions::none().allow_form_filling(true),
+            ..encryption_1()
+        }),
+        ..settings_1()
+    };
+    let pdf = Document::new_with(settings).finish().unwrap();
+
+    assert!(pdf_contains(&pdf, b"/P -3648"));
+}
+
+#[test]
+fn encryption_pdf_a_forbidden() {
+    let mut document = Document::new_with(SerializeSettings {
+        configuration: Configuration::new_with_validator(Validator::A2_B),
+        encryption: Some(encryption_1()),
+        ..settings_1()
+    });
+    document.set_metadata(metadata_1());
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::Validation(vec![ValidationError::Encryption]))
+    );
+}
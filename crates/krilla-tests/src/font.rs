@@ -21,16 +21,18 @@ mod bitmap {
 }
 
 mod colr {
+    use krilla::color::rgb;
     use krilla::geom::Point;
     use krilla::paint::Stroke;
     use krilla::surface::Surface;
     use krilla::text::TextDirection;
-    use krilla::text::{Font, GlyphId};
+    use krilla::text::{ColorPalette, Font, GlyphId, KrillaGlyph};
     use krilla::Document;
     use krilla_macros::visreg;
 
     use crate::{
-        all_glyphs_to_pdf, blue_stroke, purple_fill, COLR_TEST_GLYPHS, NOTO_COLOR_EMOJI_COLR,
+        all_glyphs_to_pdf, blue_stroke, pdf_contains, pdf_count, purple_fill, settings_1,
+        COLR_TEST_GLYPHS, NOTO_COLOR_EMOJI_COLR,
     };
 
     #[visreg(document)]
@@ -44,6 +46,34 @@ mod colr {
         all_glyphs_to_pdf(font_data, Some(glyphs), false, document);
     }
 
+    fn font_colr_composite_modes_impl(document: &mut Document) {
+        let font_data = COLR_TEST_GLYPHS.clone();
+
+        // Glyphs that use the `DestOver` and `SrcOver` composite modes.
+        let glyphs = (84..=89)
+            .chain(156..=160)
+            .map(|n| (GlyphId::new(n), "".to_string()))
+            .collect::<Vec<_>>();
+
+        all_glyphs_to_pdf(font_data, Some(glyphs), false, document);
+    }
+
+    #[visreg(document)]
+    fn font_colr_composite_modes(document: &mut Document) {
+        font_colr_composite_modes_impl(document);
+    }
+
+    #[test]
+    fn font_colr_composite_modes_entries() {
+        let mut document = Document::new_with(settings_1());
+        font_colr_composite_modes_impl(&mut document);
+
+        let pdf = document.finish().unwrap();
+        assert!(pdf_contains(&pdf, b"/Subtype /Type3"));
+        // Both modes can be emulated by reordering the layers, so no masks are needed.
+        assert!(!pdf_contains(&pdf, b"/S /Alpha"));
+    }
+
     #[visreg]
     fn font_colr_context_color(surface: &mut Surface) {
         let font_data = COLR_TEST_GLYPHS.clone();
@@ -125,16 +155,74 @@ mod colr {
         let font_data = NOTO_COLOR_EMOJI_COLR.clone();
         all_glyphs_to_pdf(font_data, None, false, document);
     }
+
+    fn colr_palettes_pdf(palettes: &[Option<ColorPalette>]) -> Vec<u8> {
+        let mut d = Document::new_with(settings_1());
+        let mut page = d.start_page();
+        let mut surface = page.surface();
+        let font = Font::new(COLR_TEST_GLYPHS.clone(), 0).unwrap();
+
+        let glyphs = (0..=220)
+            .map(|i| KrillaGlyph::new(GlyphId::new(i), 1.0, 0.0, 0.0, 0.0, 0..1, None))
+            .collect::<Vec<_>>();
+
+        for palette in palettes {
+            if let Some(palette) = palette {
+                surface.set_color_palette(palette.clone());
+            }
+
+            surface.draw_glyphs(
+                Point::from_xy(0.0, 100.0),
+                &glyphs,
+                font.clone(),
+                "a",
+                20.0,
+                false,
+            );
+        }
+
+        surface.finish();
+        page.finish();
+
+        d.finish().unwrap()
+    }
+
+    #[test]
+    fn font_colr_palettes() {
+        let default = colr_palettes_pdf(&[None]);
+
+        // The first palette is used by default.
+        assert_eq!(default, colr_palettes_pdf(&[Some(ColorPalette::new(0))]));
+        // Palettes that don't exist fall back to the first one.
+        assert_eq!(default, colr_palettes_pdf(&[Some(ColorPalette::new(10))]));
+        assert_ne!(default, colr_palettes_pdf(&[Some(ColorPalette::new(1))]));
+
+        let palette = ColorPalette::new(0).with_override(4, rgb::Color::new(1, 2, 3));
+        assert_ne!(default, colr_palettes_pdf(&[Some(palette)]));
+
+        // Glyphs drawn with different palettes are not merged, while drawing the same
+        // glyphs again with the same palette reuses them.
+        let same = colr_palettes_pdf(&[None, Some(ColorPalette::new(0))]);
+        let different = colr_palettes_pdf(&[None, Some(ColorPalette::new(1))]);
+        assert_eq!(
+            pdf_count(&same, b"/Subtype /Form"),
+            pdf_count(&default, b"/Subtype /Form")
+        );
+        assert!(pdf_count(&different, b"/Subtype /Form") > pdf_count(&same, b"/Subtype /Form"));
+    }
 }
 
 mod svg {
     use krilla::geom::Point;
     use krilla::surface::Surface;
-    use krilla::text::{Font, TextDirection};
+    use krilla::text::{Font, GlyphId, KrillaGlyph, TextDirection};
     use krilla::Document;
     use krilla_macros::visreg;
 
-    use crate::{all_glyphs_to_pdf, purple_fill, red_fill, SVG_EXTRA, TWITTER_COLOR_EMOJI};
+    use crate::{
+        all_glyphs_to_pdf, pdf_contains, purple_fill, red_fill, settings_1, SVG_EXTRA,
+        TWITTER_COLOR_EMOJI,
+    };
 
     #[visreg(document, all)]
     fn font_twitter_color_emoji(document: &mut Document) {
@@ -167,6 +255,34 @@ mod svg {
             TextDirection::Auto,
         );
     }
+
+    #[test]
+    fn font_svg_glyph_without_document() {
+        let mut d = Document::new_with(settings_1());
+        let mut page = d.start_page();
+        let mut surface = page.surface();
+
+        // Glyph 1 doesn't have an SVG document, while glyph 2 does.
+        let glyphs = (1..=2)
+            .map(|i| KrillaGlyph::new(GlyphId::new(i), 1.0, 0.0, 0.0, 0.0, 0..1, None))
+            .collect::<Vec<_>>();
+        surface.draw_glyphs(
+            Point::from_xy(0.0, 100.0),
+            &glyphs,
+            Font::new(TWITTER_COLOR_EMOJI.clone(), 0).unwrap(),
+            "a",
+            20.0,
+            false,
+        );
+        surface.finish();
+        page.finish();
+
+        let pdf = d.finish().unwrap();
+        // Only the glyph with an SVG document is drawn as a Type3 glyph, the other
+        // one uses its outline.
+        assert!(pdf_contains(&pdf, b"/Subtype /Type3"));
+        assert!(pdf_contains(&pdf, b"/FontFile2"));
+    }
 }
 
 mod cid {
@@ -174,12 +290,59 @@ mod cid {
     use krilla::page::Page;
     use krilla::surface::Surface;
     use krilla::text::{Font, TextDirection};
+    use krilla::{Data, Document, SerializeSettings};
     use krilla_macros::{snapshot, visreg};
 
     use crate::{
-        ASSETS_PATH, DEJAVU_SANS_MONO, FONT_PATH, LATIN_MODERN_ROMAN, NOTO_SANS, NOTO_SANS_ARABIC,
+        pdf_contains, settings_1, ASSETS_PATH, DEJAVU_SANS_MONO, FONT_PATH, LATIN_MODERN_ROMAN,
+        NOTO_SANS, NOTO_SANS_ARABIC,
     };
 
+    fn font_without_subset_pdf(font_data: Data, subset_fonts: bool) -> Vec<u8> {
+        let mut d = Document::new_with(SerializeSettings {
+            subset_fonts,
+            ..settings_1()
+        });
+        let mut page = d.start_page();
+        let mut surface = page.surface();
+        surface.draw_text(
+            Point::from_xy(0.0, 100.0),
+            Font::new(font_data, 0).unwrap(),
+            32.0,
+            "hello world",
+            false,
+            TextDirection::Auto,
+        );
+        surface.finish();
+        page.finish();
+
+        d.finish().unwrap()
+    }
+
+    #[test]
+    fn font_ttf_without_subset() {
+        let subset = font_without_subset_pdf(NOTO_SANS.clone(), true);
+        let full = font_without_subset_pdf(NOTO_SANS.clone(), false);
+
+        // The whole font is embedded, including all of its tables and glyphs.
+        assert!(full.len() > 10 * subset.len());
+        assert!(pdf_contains(&full, b"/FontFile2"));
+        assert!(pdf_contains(&full, b"/ToUnicode"));
+        // No subset tag is added to the name of the font.
+        assert!(pdf_contains(&full, b"/BaseFont /NotoSans-Regular"));
+        assert!(!pdf_contains(&full, b"+NotoSans-Regular"));
+    }
+
+    #[test]
+    fn font_cff_without_subset() {
+        let subset = font_without_subset_pdf(LATIN_MODERN_ROMAN.clone(), true);
+        let full = font_without_subset_pdf(LATIN_MODERN_ROMAN.clone(), false);
+
+        assert!(full.len() > 5 * subset.len());
+        assert!(pdf_contains(&full, b"/FontFile3"));
+        assert!(!pdf_contains(&full, b"+LMRoman10-Regular"));
+    }
+
     #[visreg(all)]
     fn font_ttf_simple_text(surface: &mut Surface) {
         let font = Font::new(NOTO_SANS.clone(), 0).unwrap();
@@ -349,3 +512,169 @@ mod type3 {
         );
     }
 }
+
+mod metrics {
+    use krilla::text::{Font, GlyphId};
+    use skrifa::MetadataProvider;
+
+    use crate::NOTO_SANS;
+
+    fn glyph_id(c: char) -> GlyphId {
+        let font_ref = skrifa::FontRef::new(NOTO_SANS.as_ref()).unwrap();
+        GlyphId::new(font_ref.charmap().map(c).unwrap().to_u32())
+    }
+
+    #[test]
+    fn font_glyph_metrics() {
+        let font = Font::new(NOTO_SANS.clone(), 0).unwrap();
+
+        let h = font.glyph_metrics(glyph_id('H')).unwrap();
+        assert!(h.advance_width > 0.0);
+        let bbox = h.bbox.unwrap();
+        assert_eq!(bbox.top(), 0.0);
+        assert!(bbox.height() > 0.0 && bbox.height() < font.units_per_em());
+
+        let space = font.glyph_metrics(glyph_id(' ')).unwrap();
+        assert!(space.advance_width > 0.0);
+        assert!(space.bbox.is_none());
+    }
+
+    #[test]
+    fn font_decoration_metrics() {
+        let font = Font::new(NOTO_SANS.clone(), 0).unwrap();
+
+        let underline = font.underline_metrics().unwrap();
+        assert!(underline.position < 0.0);
+        assert!(underline.thickness > 0.0);
+
+        let strikeout = font.strikeout_metrics().unwrap();
+        assert!(strikeout.position > 0.0);
+        assert!(strikeout.thickness > 0.0);
+    }
+
+    #[test]
+    fn font_outline_glyph() {
+        let font = Font::new(NOTO_SANS.clone(), 0).unwrap();
+        assert!(font.outline_glyph(glyph_id('H')).is_some());
+        assert!(font.outline_glyph(glyph_id(' ')).is_none());
+    }
+}
+
+mod system {
+    use krilla::text::{Font, SystemFontStyle};
+
+    #[test]
+    fn font_from_system() {
+        assert!(Font::from_system("Not A Font Family", SystemFontStyle::default()).is_none());
+
+        // The installed fonts differ between machines, so only the fonts that
+        // happen to be installed can be checked.
+        if let Some(family) = Font::system_families().first() {
+            let bold = SystemFontStyle {
+                weight: 700,
+                ..SystemFontStyle::default()
+            };
+
+            // Loading the same font twice results in the same font.
+            assert_eq!(
+                Font::from_system(family, bold),
+                Font::from_system(family, bold)
+            );
+        }
+    }
+}
+
+mod woff {
+    use krilla::paint::Fill;
+    use krilla::text::{Font, GlyphId, WoffError};
+    use krilla::{Data, Document};
+    use skrifa::raw::TableProvider;
+    use skrifa::FontRef;
+
+    use crate::{NOTO_SANS, NOTO_SANS_WOFF, NOTO_SANS_WOFF2};
+
+    /// Draw the outlines of all glyphs of the font on a single page.
+    fn outlines_to_pdf(font: &Font, num_glyphs: u16) -> Vec<u8> {
+        let mut document = Document::new();
+        let mut page = document.start_page();
+        let mut surface = page.surface();
+        surface.set_fill(Some(Fill::default()));
+
+        for gid in 0..num_glyphs {
+            if let Some(path) = font.outline_glyph(GlyphId::new(gid as u32)) {
+                surface.draw_path(&path);
+            }
+        }
+
+        surface.finish();
+        page.finish();
+
+        document.finish().unwrap()
+    }
+
+    fn assert_same_font(woff: Data) {
+        let expected = Font::new(NOTO_SANS.clone(), 0).unwrap();
+        let actual = Font::new(woff, 0).unwrap();
+        let num_glyphs = FontRef::new(NOTO_SANS.as_ref())
+            .unwrap()
+            .maxp()
+            .unwrap()
+            .num_glyphs();
+
+        assert_eq!(actual.units_per_em(), expected.units_per_em());
+        assert_eq!(actual.underline_metrics(), expected.underline_metrics());
+
+        for gid in 0..num_glyphs {
+            let gid = GlyphId::new(gid as u32);
+            assert_eq!(actual.glyph_metrics(gid), expected.glyph_metrics(gid));
+        }
+
+        assert!(outlines_to_pdf(&actual, num_glyphs) == outlines_to_pdf(&expected, num_glyphs));
+    }
+
+    #[test]
+    fn font_woff1() {
+        assert_same_font(NOTO_SANS_WOFF.clone());
+    }
+
+    #[test]
+    fn font_woff2() {
+        assert_same_font(NOTO_SANS_WOFF2.clone());
+    }
+
+    #[test]
+    fn font_woff_invalid() {
+        assert_eq!(
+            Font::from_woff(NOTO_SANS.as_ref(), 0).unwrap_err(),
+            WoffError::UnknownFormat
+        );
+
+        let woff1 = NOTO_SANS_WOFF.as_ref();
+        assert_eq!(
+            Font::from_woff(&woff1[..woff1.len() / 2], 0).unwrap_err(),
+            WoffError::Truncated
+        );
+
+        let mut woff2 = NOTO_SANS_WOFF2.as_ref().to_vec();
+        let len = woff2.len();
+        assert_eq!(
+            Font::from_woff(&woff2[..len - 100], 0).unwrap_err(),
+            WoffError::Truncated
+        );
+
+        // Corrupt the compressed data, which is stored at the end of the file.
+        let compressed_size = u32::from_be_bytes(woff2[20..24].try_into().unwrap()) as usize;
+        let start = len - compressed_size;
+        woff2[start..start + 16].fill(0xff);
+        assert!(matches!(
+            Font::from_woff(&woff2, 0),
+            Err(WoffError::Decompression | WoffError::Malformed)
+        ));
+        assert!(Font::new(Data::from(woff2), 0).is_none());
+
+        assert_eq!(
+            Font::from_woff(b"wOF2\0\0\0\0", 0).unwrap_err(),
+            WoffError::Truncated
+        );
+    }
+}
@@ -0,0 +1,583 @@
+use krilla::action::{FieldSelection, ResetFormAction, SubmitFormAction, SubmitFormat};
+use krilla::error::{KrillaError, KrillaWarning};
+use krilla::form::{
+    CheckBoxSettings, ChoiceField, ChoiceKind, ChoiceOption, PushButton, RadioButton,
+    RadioGroupSettings, SignatureFieldSettings, SignaturePlaceholder, TextField,
+};
+use krilla::geom::{Point, Rect, Size};
+use krilla::page::PageSettings;
+use krilla::surface::Surface;
+use krilla::text::{Font, TextDirection};
+use krilla::Document;
+use krilla_macros::snapshot;
+
+use crate::{
+    blue_fill, dummy_text_with_spans, pdf_contains, pdf_count, rect_to_path, settings_1,
+    NOTO_COLOR_EMOJI_CBDT, NOTO_SANS,
+};
+
+fn text_field_1() -> TextField {
+    TextField {
+        name: "Name".to_string(),
+        page_index: 0,
+        rect: Rect::from_xywh(20.0, 20.0, 160.0, 30.0).unwrap(),
+        default_value: Some("Hello World".to_string()),
+        max_len: None,
+        multiline: false,
+        comb: false,
+        font: Font::new(NOTO_SANS.clone(), 0).unwrap(),
+        font_size: 14.0,
+    }
+}
+
+#[snapshot(document)]
+fn form_text_field(document: &mut Document) {
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_text_field(text_field_1());
+}
+
+#[test]
+fn form_text_field_multiline_unicode() {
+    let mut document = Document::new_with(settings_1());
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_text_field(TextField {
+        rect: Rect::from_xywh(20.0, 20.0, 160.0, 80.0).unwrap(),
+        default_value: Some("Grüße\nΚαλημέρα".to_string()),
+        multiline: true,
+        ..text_field_1()
+    });
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Ff 4096"), 1);
+    assert_eq!(pdf_count(&pdf, b"/BBox [0 0 160 80]"), 1);
+    // Each line is shown separately.
+    assert_eq!(pdf_count(&pdf, b" Tj"), 2);
+}
+
+#[test]
+fn form_text_field_comb() {
+    let mut document = Document::new_with(settings_1());
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_text_field(TextField {
+        default_value: Some("12345".to_string()),
+        max_len: Some(8),
+        comb: true,
+        ..text_field_1()
+    });
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/V (12345)"), 1);
+    assert_eq!(pdf_count(&pdf, b"/MaxLen 8"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Ff 16777216"), 1);
+    assert_eq!(pdf_count(&pdf, b" Tj"), 5);
+}
+
+#[test]
+fn form_text_field_shares_default_resources() {
+    let mut document = Document::new_with(settings_1());
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_text_field(text_field_1());
+    document.add_text_field(TextField {
+        name: "Empty".to_string(),
+        rect: Rect::from_xywh(20.0, 60.0, 160.0, 30.0).unwrap(),
+        default_value: None,
+        ..text_field_1()
+    });
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/FontFile2"), 1);
+    assert_eq!(pdf_count(&pdf, b"/DR"), 1);
+    assert_eq!(pdf_count(&pdf, b"(/f0 14 Tf 0 g)"), 2);
+}
+
+#[test]
+fn form_text_field_comb_truncated() {
+    let mut document = Document::new_with(settings_1());
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_text_field(TextField {
+        default_value: Some("1234567890".to_string()),
+        max_len: Some(8),
+        comb: true,
+        ..text_field_1()
+    });
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/V (12345678)"), 1);
+    assert_eq!(pdf_count(&pdf, b"/DV (12345678)"), 1);
+    assert_eq!(pdf_count(&pdf, b"/MaxLen 8"), 1);
+    // One glyph is shown per cell.
+    assert_eq!(pdf_count(&pdf, b" Tj"), 8);
+}
+
+#[test]
+fn form_text_field_invalid_font() {
+    let font = Font::new(NOTO_COLOR_EMOJI_CBDT.clone(), 0).unwrap();
+    let mut document = Document::new_with(settings_1());
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_text_field(TextField {
+        font: font.clone(),
+        ..text_field_1()
+    });
+
+    assert!(matches!(
+        document.finish(),
+        Err(KrillaError::Font(f, _)) if f == font
+    ));
+}
+
+fn choice_field_1(kind: ChoiceKind) -> ChoiceField {
+    let option = |export_value: &str, label: Option<&str>| ChoiceOption {
+        export_value: export_value.to_string(),
+        label: label.map(|l| l.to_string()),
+    };
+
+    ChoiceField {
+        name: "Language".to_string(),
+        page_index: 0,
+        rect: Rect::from_xywh(20.0, 20.0, 160.0, 80.0).unwrap(),
+        kind,
+        options: vec![
+            option("en", Some("English")),
+            option("de", Some("Deutsch")),
+            option("el", Some("Ελληνικά")),
+            option("Other", None),
+        ],
+        selected: vec!["de".to_string()],
+        font: Font::new(NOTO_SANS.clone(), 0).unwrap(),
+        font_size: 14.0,
+    }
+}
+
+#[snapshot(document)]
+fn form_choice_field_combo_box(document: &mut Document) {
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_choice_field(ChoiceField {
+        rect: Rect::from_xywh(20.0, 20.0, 160.0, 30.0).unwrap(),
+        ..choice_field_1(ChoiceKind::ComboBox { editable: true })
+    });
+}
+
+#[snapshot(document)]
+fn form_choice_field_list_box(document: &mut Document) {
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_choice_field(choice_field_1(ChoiceKind::ListBox {
+        multi_select: false,
+    }));
+}
+
+#[test]
+fn form_choice_field_multi_select() {
+    let mut document = Document::new_with(settings_1());
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_choice_field(ChoiceField {
+        selected: vec!["en".to_string(), "el".to_string()],
+        ..choice_field_1(ChoiceKind::ListBox { multi_select: true })
+    });
+
+    let pdf = document.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/Ff 2097152"));
+    assert!(pdf_contains(&pdf, b"/V [(en) (el)]"));
+    assert!(pdf_contains(&pdf, b"/I [0 2]"));
+    // Non-ASCII labels are encoded as UTF-16.
+    assert!(pdf_contains(&pdf, b"[(el) "));
+    assert!(!pdf_contains(&pdf, "Ελληνικά".as_bytes()));
+}
+
+fn push_button_1() -> PushButton {
+    PushButton {
+        name: "Submit".to_string(),
+        page_index: 0,
+        rect: Rect::from_xywh(20.0, 20.0, 100.0, 30.0).unwrap(),
+        caption: "Submit".to_string(),
+        font: Font::new(NOTO_SANS.clone(), 0).unwrap(),
+        font_size: 14.0,
+        action: Some(
+            SubmitFormAction::new(
+                "https://example.com/submit".to_string(),
+                SubmitFormat::Html,
+                FieldSelection::All,
+            )
+            .into(),
+        ),
+    }
+}
+
+#[snapshot(document)]
+fn form_push_button(document: &mut Document) {
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_push_button(push_button_1());
+}
+
+#[test]
+fn form_push_button_actions() {
+    let mut document = Document::new_with(settings_1());
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_push_button(push_button_1());
+    document.add_push_button(PushButton {
+        name: "Reset".to_string(),
+        rect: Rect::from_xywh(20.0, 60.0, 100.0, 30.0).unwrap(),
+        caption: "Reset".to_string(),
+        action: Some(
+            ResetFormAction::new(FieldSelection::Exclude(vec!["Name".to_string()])).into(),
+        ),
+        ..push_button_1()
+    });
+
+    let pdf = document.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/S /SubmitForm"));
+    assert!(pdf_contains(&pdf, b"/FS /URL"));
+    assert!(pdf_contains(&pdf, b"/Flags 4"));
+    assert!(pdf_contains(&pdf, b"/S /ResetForm"));
+    assert!(pdf_contains(&pdf, b"/Fields [(Name)]"));
+    assert!(pdf_contains(&pdf, b"/Flags 1"));
+    assert!(pdf_contains(&pdf, b"/Ff 65536"));
+}
+
+fn check_box_settings() -> CheckBoxSettings {
+    CheckBoxSettings {
+        name: "Agree".to_string(),
+        page_index: 0,
+        rect: Rect::from_xywh(20.0, 20.0, 20.0, 20.0).unwrap(),
+        export_value: "Yes".to_string(),
+        checked: true,
+    }
+}
+
+#[snapshot(document)]
+fn form_check_box(document: &mut Document) {
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_check_box(check_box_settings());
+}
+
+#[test]
+fn form_check_box_custom_appearance() {
+    let mut document = Document::new_with(settings_1());
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    let mut check_box = document.add_check_box(CheckBoxSettings {
+        checked: false,
+        ..check_box_settings()
+    });
+    let size = Size::from_wh(20.0, 20.0).unwrap();
+
+    let mut surface = check_box.on_appearance(size);
+    surface.set_fill(Some(blue_fill(1.0)));
+    surface.draw_path(&rect_to_path(2.0, 2.0, 18.0, 18.0));
+    surface.finish();
+
+    let mut surface = check_box.off_appearance(size);
+    surface.set_fill(Some(blue_fill(0.2)));
+    surface.draw_path(&rect_to_path(2.0, 2.0, 18.0, 18.0));
+    surface.finish();
+
+    check_box.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/V /Off"), 1);
+    assert_eq!(pdf_count(&pdf, b"/AS /Off"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Form"), 2);
+    assert_eq!(pdf_count(&pdf, b"/ca 0.2"), 1);
+    // The generated check mark isn't used.
+    assert_eq!(pdf_count(&pdf, b"1 J"), 0);
+}
+
+fn radio_group_1() -> RadioGroupSettings {
+    let button = |y: f32, export_value: &str| RadioButton {
+        page_index: 0,
+        rect: Rect::from_xywh(20.0, y, 20.0, 20.0).unwrap(),
+        export_value: export_value.to_string(),
+    };
+
+    RadioGroupSettings {
+        name: "Color".to_string(),
+        buttons: vec![
+            button(20.0, "Red"),
+            button(50.0, "Green"),
+            button(80.0, "Blue"),
+        ],
+        selected: Some("Green".to_string()),
+        radios_in_unison: false,
+    }
+}
+
+#[snapshot(document)]
+fn form_radio_group(document: &mut Document) {
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_radio_group(radio_group_1());
+}
+
+#[test]
+fn form_button_states() {
+    let mut document = Document::new_with(settings_1());
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_check_box(CheckBoxSettings {
+        checked: false,
+        ..check_box_settings()
+    });
+    document.add_radio_group(RadioGroupSettings {
+        radios_in_unison: true,
+        ..radio_group_1()
+    });
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/AS /Off"), 3);
+    assert_eq!(pdf_count(&pdf, b"/AS /Green"), 1);
+    assert_eq!(pdf_count(&pdf, b"/V /Green"), 1);
+    // Radio | NoToggleToOff | RadiosInUnison
+    assert_eq!(pdf_count(&pdf, b"/Ff 33603584"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Widget"), 4);
+}
+
+#[test]
+fn form_radio_group_custom_appearance() {
+    let mut document = Document::new_with(settings_1());
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    let mut radio_group = document.add_radio_group(radio_group_1());
+    let size = Size::from_wh(20.0, 20.0).unwrap();
+
+    let mut surface = radio_group.on_appearance(1, size);
+    surface.set_fill(Some(blue_fill(1.0)));
+    surface.draw_path(&rect_to_path(2.0, 2.0, 18.0, 18.0));
+    surface.finish();
+
+    let mut surface = radio_group.off_appearance(1, size);
+    surface.set_fill(Some(blue_fill(0.2)));
+    surface.draw_path(&rect_to_path(2.0, 2.0, 18.0, 18.0));
+    surface.finish();
+
+    radio_group.finish();
+
+    let pdf = document.finish().unwrap();
+    // Two generated appearances for each of the other buttons, and the two custom ones.
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Form"), 6);
+    assert_eq!(pdf_count(&pdf, b"/AS /Green"), 1);
+}
+
+#[test]
+fn form_invalid_export_value() {
+    let mut document = Document::new_with(settings_1());
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_check_box(CheckBoxSettings {
+        export_value: "Off".to_string(),
+        ..check_box_settings()
+    });
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::InvalidExportValue("Agree".to_string()))
+    );
+
+    let mut document = Document::new_with(settings_1());
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    let mut group = radio_group_1();
+    group.buttons[2].export_value = "Off".to_string();
+    document.add_radio_group(group);
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::InvalidExportValue("Color".to_string()))
+    );
+}
+
+#[test]
+fn form_invalid_field_name() {
+    let mut document = Document::new_with(settings_1());
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_text_field(TextField {
+        name: "Address.Street".to_string(),
+        ..text_field_1()
+    });
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::InvalidFieldName("Address.Street".to_string()))
+    );
+}
+
+#[test]
+fn form_duplicate_field_name() {
+    let mut document = Document::new_with(settings_1());
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_text_field(text_field_1());
+    document.add_check_box(CheckBoxSettings {
+        name: "Name".to_string(),
+        ..check_box_settings()
+    });
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::DuplicateFieldName("Name".to_string()))
+    );
+}
+
+fn signature_field_settings() -> SignatureFieldSettings {
+    SignatureFieldSettings {
+        name: "Signature1".to_string(),
+        page_index: 0,
+        rect: Rect::from_xywh(20.0, 20.0, 100.0, 50.0).unwrap(),
+        reserved_size: 16,
+    }
+}
+
+#[snapshot(document)]
+fn form_signature_field(document: &mut Document) {
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_signature_field(signature_field_settings());
+}
+
+fn signature_appearance_impl(surface: &mut Surface) {
+    let font = Font::new(NOTO_SANS.clone(), 0).unwrap();
+    surface.set_fill(Some(blue_fill(0.3)));
+    surface.draw_path(&rect_to_path(0.0, 0.0, 100.0, 50.0));
+    surface.set_fill(None);
+    surface.draw_text(
+        Point::from_xy(5.0, 30.0),
+        font,
+        14.0,
+        "John Doe",
+        false,
+        TextDirection::Auto,
+    );
+}
+
+#[test]
+fn form_signature_field_with_appearance() {
+    let mut document = Document::new_with(settings_1());
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    let mut field = document.add_signature_field(signature_field_settings());
+    let mut surface = field.appearance(Size::from_wh(100.0, 50.0).unwrap());
+    signature_appearance_impl(&mut surface);
+    surface.finish();
+    field.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/FT /Sig"), 1);
+    assert_eq!(pdf_count(&pdf, b"/AP"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Form"), 1);
+    assert_eq!(pdf_count(&pdf, b"/BBox [0 0 100 50]"), 1);
+    assert_eq!(pdf_count(&pdf, b"/FontFile2"), 1);
+}
+
+#[test]
+fn form_signature_appearance_shares_fonts() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    let mut surface = page.surface();
+    signature_appearance_impl(&mut surface);
+    surface.finish();
+    page.finish();
+
+    let mut field = document.add_signature_field(signature_field_settings());
+    let mut surface = field.appearance(Size::from_wh(100.0, 50.0).unwrap());
+    signature_appearance_impl(&mut surface);
+    surface.finish();
+    field.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/FontFile2"), 1);
+    assert_eq!(pdf_count(&pdf, b"/AP"), 1);
+}
+
+#[test]
+fn form_signature_placeholder() {
+    let mut document = Document::new_with(settings_1());
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    document.add_signature_field(signature_field_settings());
+
+    let (pdf, placeholders, warnings) = document.finish_with_signatures().unwrap();
+    assert!(warnings.is_empty());
+    assert_eq!(placeholders.len(), 1);
+    assert_eq!(placeholders[0].name, "Signature1");
+    assert_eq!(placeholders[0].contents.len(), 2 * 16 + 2);
+    check_signature_placeholder(&pdf, &placeholders[0]);
+}
+
+#[test]
+fn form_signature_placeholder_warnings() {
+    let mut document = Document::new_with(settings_1());
+    let font = Font::new(NOTO_SANS.clone(), 0).unwrap();
+    let (text, glyphs) = dummy_text_with_spans();
+    let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+    let mut surface = page.surface();
+    surface.draw_glyphs(
+        Point::from_xy(0.0, 50.0),
+        &glyphs,
+        font.clone(),
+        &text,
+        12.0,
+        false,
+    );
+    surface.finish();
+    page.finish();
+    document.add_signature_field(signature_field_settings());
+
+    // Warnings that haven't been taken are returned together with the placeholders.
+    let (_, placeholders, warnings) = document.finish_with_signatures().unwrap();
+    assert_eq!(placeholders.len(), 1);
+    assert_eq!(
+        warnings,
+        vec![KrillaWarning::NotDefGlyph(font, 1..2, Some(0), Some(4))]
+    );
+}
+
+fn check_signature_placeholder(pdf: &[u8], placeholder: &SignaturePlaceholder) {
+    assert_eq!(
+        placeholder.byte_range,
+        [
+            0,
+            placeholder.contents.start,
+            placeholder.contents.end,
+            pdf.len() - placeholder.contents.end
+        ]
+    );
+
+    let contents = &pdf[placeholder.contents.clone()];
+    assert_eq!(contents[0], b'<');
+    assert_eq!(contents[contents.len() - 1], b'>');
+    assert!(contents[1..contents.len() - 1].iter().all(|b| *b == b'0'));
+
+    let byte_range = format!(
+        "/ByteRange [0 {} {} {}",
+        placeholder.byte_range[1], placeholder.byte_range[2], placeholder.byte_range[3]
+    );
+    assert!(pdf_contains(pdf, byte_range.as_bytes()));
+}
+
+#[test]
+fn form_signature_placeholders_unambiguous() {
+    let mut document = Document::new_with(settings_1());
+    document.start_page_with(PageSettings::new(200.0, 200.0));
+    // A field whose value looks like the placeholders of a signature.
+    document.add_text_field(TextField {
+        default_value: Some(
+            "/ByteRange [0 2147483647 2147483647 2147483647] /Contents (00)".to_string(),
+        ),
+        ..text_field_1()
+    });
+    document.add_signature_field(signature_field_settings());
+    document.add_signature_field(SignatureFieldSettings {
+        name: "Signature2".to_string(),
+        reserved_size: 8,
+        ..signature_field_settings()
+    });
+
+    let (pdf, placeholders, _) = document.finish_with_signatures().unwrap();
+    assert_eq!(placeholders.len(), 2);
+    assert_eq!(placeholders[0].name, "Signature1");
+    assert_eq!(placeholders[1].name, "Signature2");
+    assert_eq!(placeholders[1].contents.len(), 2 * 8 + 2);
+
+    for placeholder in &placeholders {
+        check_signature_placeholder(&pdf, placeholder);
+    }
+}
+
+#[test]
+#[should_panic]
+fn form_signature_field_invalid_page() {
+    let mut document = Document::new_with(settings_1());
+    document.add_signature_field(SignatureFieldSettings {
+        page_index: 1,
+        ..signature_field_settings()
+    });
+    let _ = document.finish();
+}
@@ -1,12 +1,17 @@
 use krilla::geom::{Size, Transform};
 use krilla::graphic::Graphic;
 use krilla::mask::MaskType;
-use krilla::page::Page;
+use krilla::page::{Page, PageSettings};
+use krilla::tagging::{ArtifactType, ContentTag};
+use krilla::template::Template;
+use krilla::Document;
 use krilla_macros::snapshot;
 use krilla_svg::{SurfaceExt, SvgSettings};
 
 use crate::svg::sample_svg;
-use crate::{basic_mask, green_fill, rect_to_path, red_fill};
+use crate::{
+    basic_mask, blue_fill, green_fill, pdf_contains, pdf_count, rect_to_path, red_fill, settings_1,
+};
 
 #[snapshot]
 fn graphic(page: &mut Page) {
@@ -53,3 +58,56 @@ fn graphic_svg(page: &mut Page) {
 
     surface.finish()
 }
+
+fn header_template(d: &mut Document) -> Template {
+    d.create_template(Size::from_wh(200.0, 50.0).unwrap(), |surface| {
+        surface.set_fill(Some(blue_fill(1.0)));
+        surface.draw_path(&rect_to_path(0.0, 0.0, 200.0, 50.0));
+        surface.set_fill(Some(red_fill(1.0)));
+        surface.draw_path(&rect_to_path(10.0, 10.0, 40.0, 40.0));
+    })
+}
+
+fn template_impl(d: &mut Document) {
+    let template = header_template(d);
+
+    for _ in 0..2 {
+        let mut page = d.start_page_with(PageSettings::new(200.0, 200.0));
+        let mut surface = page.surface();
+        surface.draw_template(&template, Transform::identity());
+        surface.draw_template(&template, Transform::from_translate(0.0, 150.0));
+        surface.finish();
+        page.finish();
+    }
+}
+
+#[test]
+fn template() {
+    let mut d = Document::new_with(settings_1());
+    template_impl(&mut d);
+
+    let pdf = d.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Form"), 1);
+    assert_eq!(pdf_count(&pdf, b"/BBox [0 0 200 50]"), 1);
+    // Templates are marked as artifacts on tagged pages.
+    assert_eq!(pdf_count(&pdf, b"/Artifact BMC"), 4);
+}
+
+#[test]
+fn template_inside_tag() {
+    let mut d = Document::new_with(settings_1());
+    let template = header_template(&mut d);
+    assert_eq!(template.size(), Size::from_wh(200.0, 50.0).unwrap());
+
+    let mut page = d.start_page_with(PageSettings::new(200.0, 200.0));
+    let mut surface = page.surface();
+    surface.start_tagged(ContentTag::Artifact(ArtifactType::Header, None));
+    surface.draw_template(&template, Transform::identity());
+    surface.end_tagged();
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert!(!pdf_contains(&pdf, b"/Artifact BMC"));
+    assert!(pdf_contains(&pdf, b"/Artifact <<"));
+}
@@ -1,12 +1,20 @@
-use krilla::geom::Size;
-use krilla::image::Image;
-use krilla::page::Page;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use krilla::error::KrillaError;
+use krilla::geom::{Point, Size, Transform};
+use krilla::image::{
+    BitsPerComponent, CustomImage, Image, ImageColorspace, ImageFormat, PixelFormat, RawImageError,
+};
+use krilla::page::{Page, PageSettings};
+use krilla::paint::{Fill, LinearGradient, SpreadMethod};
 use krilla::surface::Surface;
-use krilla::Document;
+use krilla::{Document, SerializeSettings};
 use krilla_macros::{snapshot, visreg};
 
 use crate::{
-    load_custom_image, load_gif_image, load_jpg_image, load_png_image, load_webp_image, ASSETS_PATH,
+    load_custom_image, load_gif_image, load_jpg_image, load_png_image, load_webp_image,
+    pdf_contains, pdf_count, red_fill, settings_1, stops_with_3_solid_1, ASSETS_PATH,
 };
 
 fn image_visreg_impl(surface: &mut Surface, name: &str, load_fn: fn(&str) -> Image) {
@@ -193,3 +201,942 @@ fn image_no_device_cs(page: &mut Page) {
     let size = Size::from_wh(image.size().0 as f32, image.size().1 as f32).unwrap();
     surface.draw_image(image, size);
 }
+
+fn load_indexed_gif_image(name: &str) -> Image {
+    load_gif_image(name).indexed(true)
+}
+
+#[visreg(all)]
+fn image_rgba8_gif_indexed(surface: &mut Surface) {
+    image_visreg_impl(surface, "rgba8.gif", load_indexed_gif_image);
+}
+
+fn image_document(image: Image) -> Vec<u8> {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    let size = Size::from_wh(image.size().0 as f32, image.size().1 as f32).unwrap();
+    surface.draw_image(image, size);
+    surface.finish();
+    page.finish();
+
+    document.finish().unwrap()
+}
+
+fn image_deduplication_document(deduplicate_images: bool) -> Vec<u8> {
+    let settings = SerializeSettings {
+        deduplicate_images,
+        ..settings_1()
+    };
+    let mut document = Document::new_with(settings);
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    let size = Size::from_wh(32.0, 32.0).unwrap();
+    surface.draw_image(load_png_image("rgba8.png"), size);
+    surface.draw_image(load_png_image("rgba8.png"), size);
+    surface.finish();
+    page.finish();
+
+    document.finish().unwrap()
+}
+
+#[test]
+fn image_deduplicate_content() {
+    let pdf = image_deduplication_document(true);
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Image"), 2);
+
+    let pdf = image_deduplication_document(false);
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Image"), 4);
+}
+
+#[test]
+fn image_indexed_smaller() {
+    let pdf = image_document(load_gif_image("rgba8.gif"));
+    let indexed_pdf = image_document(load_indexed_gif_image("rgba8.gif"));
+
+    assert!(pdf_contains(&indexed_pdf, b"/Indexed"));
+    assert!(pdf_contains(&indexed_pdf, b"/SMask"));
+    assert!(indexed_pdf.len() < pdf.len());
+}
+
+#[test]
+fn image_indexed_bits_per_index() {
+    // Three colors, one of them semi-transparent.
+    let data = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 128]]
+        .into_iter()
+        .cycle()
+        .take(10 * 10)
+        .flatten()
+        .collect::<Vec<_>>();
+    let pdf = image_document(Image::from_rgba8(data, 10, 10).indexed(true));
+
+    assert!(pdf_contains(&pdf, b"/Indexed /DeviceRGB 2"));
+    assert!(pdf_contains(&pdf, b"/BitsPerComponent 2"));
+    assert!(pdf_contains(&pdf, b"/SMask"));
+}
+
+#[test]
+fn image_indexed_too_many_colors() {
+    let data = (0..300u32)
+        .flat_map(|i| [(i % 256) as u8, (i / 256) as u8, 0, 255])
+        .collect::<Vec<_>>();
+    let pdf = image_document(Image::from_rgba8(data, 30, 10).indexed(true));
+
+    assert!(!pdf_contains(&pdf, b"/Indexed"));
+    assert!(pdf_contains(&pdf, b"/BitsPerComponent 8"));
+}
+
+fn load_decoded_jpg_image(name: &str) -> Image {
+    Image::from_jpeg_decoded(
+        std::fs::read(ASSETS_PATH.join("images").join(name))
+            .unwrap()
+            .into(),
+        false,
+    )
+    .unwrap()
+}
+
+#[visreg(all)]
+fn image_cmyk_jpg_decoded(surface: &mut Surface) {
+    image_visreg_impl(surface, "cmyk.jpg", load_decoded_jpg_image);
+}
+
+#[visreg(all)]
+fn image_rgb8_icc_jpg_decoded(surface: &mut Surface) {
+    image_visreg_impl(surface, "rgb8_icc.jpg", load_decoded_jpg_image);
+}
+
+#[test]
+fn image_jpg_passthrough() {
+    // `rgb8_icc.jpg` is a progressive JPEG.
+    for name in ["rgb8.jpg", "rgb8_icc.jpg", "luma8.jpg"] {
+        let pdf = image_document(load_jpg_image(name));
+
+        assert!(pdf_contains(&pdf, b"/DCTDecode"), "{name}");
+        assert!(!pdf_contains(&pdf, b"/Decode"), "{name}");
+    }
+}
+
+#[test]
+fn image_cmyk_jpg_passthrough_inverted() {
+    let pdf = image_document(load_jpg_image("cmyk.jpg"));
+
+    assert!(pdf_contains(&pdf, b"/DCTDecode"));
+    assert!(pdf_contains(&pdf, b"/Decode [1 0 1 0 1 0 1 0]"));
+}
+
+#[test]
+fn image_jpg_decoded() {
+    let pdf = image_document(load_decoded_jpg_image("cmyk.jpg"));
+
+    assert!(!pdf_contains(&pdf, b"/DCTDecode"));
+    assert!(!pdf_contains(&pdf, b"/Decode"));
+    assert!(pdf_contains(&pdf, b"/FlateDecode"));
+    assert!(pdf_contains(&pdf, b"/DeviceCMYK"));
+}
+
+#[test]
+fn image_jpg_decoded_icc() {
+    let pdf = image_document(load_decoded_jpg_image("rgb8_icc.jpg"));
+
+    assert!(!pdf_contains(&pdf, b"/DCTDecode"));
+    assert!(pdf_contains(&pdf, b"/FlateDecode"));
+    // The ICC profile of the JPEG is kept.
+    assert!(pdf_contains(&pdf, b"/ICCBased"));
+}
+
+#[test]
+fn image_jpg_decoded_not_deduplicated() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    let size = Size::from_wh(10.0, 10.0).unwrap();
+    surface.draw_image(load_jpg_image("rgb8.jpg"), size);
+    surface.draw_image(load_decoded_jpg_image("rgb8.jpg"), size);
+    surface.finish();
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Image"), 2);
+}
+
+fn jp2_box(box_type: &[u8; 4], contents: &[u8]) -> Vec<u8> {
+    let mut data = ((contents.len() + 8) as u32).to_be_bytes().to_vec();
+    data.extend_from_slice(box_type);
+    data.extend_from_slice(contents);
+    data
+}
+
+/// A minimal JP2 file, consisting only of the boxes needed to read its metadata.
+pub(crate) fn jp2_data(width: u32, height: u32, num_components: u16) -> Vec<u8> {
+    let mut ihdr = height.to_be_bytes().to_vec();
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&num_components.to_be_bytes());
+    ihdr.extend_from_slice(&[7, 7, 0, 0]);
+    // Enumerated sRGB color space.
+    let colr = [1, 0, 0, 0, 0, 0, 16];
+
+    let mut header = jp2_box(b"ihdr", &ihdr);
+    header.extend(jp2_box(b"colr", &colr));
+
+    let mut data = jp2_box(b"jP  ", b"\r\n\x87\n");
+    data.extend(jp2_box(b"ftyp", b"jp2 \0\0\0\0jp2 "));
+    data.extend(jp2_box(b"jp2h", &header));
+    data.extend(jp2_box(b"jp2c", b"\xFF\x4F\xFF\xD9"));
+    data
+}
+
+#[test]
+fn image_jpeg2000_jp2() {
+    let image = Image::from_jpeg2000(jp2_data(30, 20, 3).into(), false).unwrap();
+    assert_eq!(image.size(), (30, 20));
+
+    let pdf = image_document(image);
+
+    assert!(pdf_contains(&pdf, b"/JPXDecode"));
+    assert!(pdf_contains(&pdf, b"/DeviceRGB"));
+    assert!(!pdf_contains(&pdf, b"/BitsPerComponent"));
+}
+
+#[test]
+fn image_jpeg2000_codestream() {
+    let mut data = b"\xFF\x4F\xFF\x51\x00\x29\x00\x00".to_vec();
+    // Image size, image offset, tile size and tile offset.
+    for value in [40u32, 25, 10, 5, 30, 20, 0, 0] {
+        data.extend_from_slice(&value.to_be_bytes());
+    }
+    data.extend_from_slice(&[0, 1, 7, 1, 1]);
+
+    let image = Image::from_jpeg2000(data.into(), false).unwrap();
+    assert_eq!(image.size(), (30, 20));
+
+    let pdf = image_document(image);
+
+    assert!(pdf_contains(&pdf, b"/JPXDecode"));
+    assert!(pdf_contains(&pdf, b"/DeviceGray"));
+}
+
+#[test]
+fn image_jpeg2000_invalid() {
+    let data = std::fs::read(ASSETS_PATH.join("images").join("rgb8.jpg")).unwrap();
+    assert!(Image::from_jpeg2000(data.into(), false).is_none());
+    assert!(Image::from_jpeg2000(jp2_data(30, 20, 2).into(), false).is_none());
+    assert!(Image::from_jpeg2000(jp2_data(0, 20, 3).into(), false).is_none());
+}
+
+fn bilevel_pixels(width: u32, height: u32) -> Vec<bool> {
+    // Rings and stripes of varying sizes at pseudo-random positions, similar to what
+    // one would find in a scanned document.
+    let mut pixels = vec![false; (width * height) as usize];
+    let mut state = 0x2545_f491_u32;
+    let mut next = |max: u32| {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (state >> 8) % max
+    };
+
+    for _ in 0..(width * height / 4000).max(1) {
+        let (cx, cy) = (next(width) as i32, next(height) as i32);
+        let (inner, outer) = (5 + next(20) as i32, 28 + next(10) as i32);
+        let (stripe_len, stripe_height) = (20 + next(100), 2 + next(3));
+
+        for y in (cy - outer).max(0)..(cy + outer).min(height as i32) {
+            for x in (cx - outer).max(0)..(cx + outer).min(width as i32) {
+                let r = (x - cx).pow(2) + (y - cy).pow(2);
+                if (inner * inner..outer * outer).contains(&r) {
+                    pixels[(y as u32 * width + x as u32) as usize] = true;
+                }
+            }
+        }
+
+        for y in cy as u32..(cy as u32 + stripe_height).min(height) {
+            for x in cx as u32..(cx as u32 + stripe_len).min(width) {
+                pixels[(y * width + x) as usize] = true;
+            }
+        }
+    }
+
+    pixels
+}
+
+fn bilevel_data(width: u32, height: u32) -> Vec<u8> {
+    let pixels = bilevel_pixels(width, height);
+    let stride = width.div_ceil(8) as usize;
+    let mut data = vec![0; stride * height as usize];
+
+    for (i, _) in pixels.iter().enumerate().filter(|(_, black)| **black) {
+        let (x, y) = (i % width as usize, i / width as usize);
+        data[y * stride + x / 8] |= 0x80 >> (x % 8);
+    }
+
+    data
+}
+
+fn bilevel_image(width: u32, height: u32) -> Image {
+    Image::from_bilevel(bilevel_data(width, height), width, height).unwrap()
+}
+
+#[visreg(all)]
+fn image_bilevel(surface: &mut Surface) {
+    let image = bilevel_image(197, 150);
+    surface.draw_image(image, Size::from_wh(197.0, 150.0).unwrap());
+}
+
+#[test]
+fn image_bilevel_entries() {
+    let pdf = image_document(bilevel_image(197, 150));
+
+    assert!(pdf_contains(&pdf, b"/CCITTFaxDecode"));
+    assert!(pdf_contains(&pdf, b"/K -1"));
+    assert!(pdf_contains(&pdf, b"/Columns 197"));
+    assert!(pdf_contains(&pdf, b"/BlackIs1 false"));
+    assert!(pdf_contains(&pdf, b"/BitsPerComponent 1"));
+}
+
+#[test]
+fn image_bilevel_smaller_than_flate() {
+    let (width, height) = (1000, 1400);
+    let luma = ::image::GrayImage::from_fn(width, height, {
+        let pixels = bilevel_pixels(width, height);
+        move |x, y| {
+            ::image::Luma([if pixels[(y * width + x) as usize] {
+                0
+            } else {
+                255
+            }])
+        }
+    });
+    let mut png = std::io::Cursor::new(vec![]);
+    luma.write_to(&mut png, ::image::ImageFormat::Png).unwrap();
+
+    let flate_pdf = image_document(Image::from_png(png.into_inner().into(), false).unwrap());
+    let ccitt_pdf = image_document(bilevel_image(width, height));
+
+    assert!(ccitt_pdf.len() < flate_pdf.len());
+}
+
+#[test]
+fn image_bilevel_invalid() {
+    // Each row needs to start at a new byte.
+    assert!(Image::from_bilevel(vec![0; 6], 9, 5).is_none());
+    assert!(Image::from_bilevel(vec![0; 10], 9, 5).is_some());
+    assert!(Image::from_bilevel(vec![], 0, 0).is_none());
+}
+
+fn stencil_image(width: u32, height: u32) -> Image {
+    Image::stencil(bilevel_data(width, height), width, height).unwrap()
+}
+
+#[test]
+fn image_stencil_default_fill() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    surface.set_fill(None);
+    surface.draw_image(
+        stencil_image(197, 150),
+        Size::from_wh(197.0, 150.0).unwrap(),
+    );
+    surface.finish();
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+
+    assert!(pdf_contains(&pdf, b"/ImageMask true"));
+    // Without a fill, the stencil mask is painted black.
+    assert!(pdf_contains(&pdf, b"0 g\n"));
+}
+
+#[test]
+fn image_stencil_gradient() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    let gradient = LinearGradient {
+        x1: 0.0,
+        y1: 0.0,
+        x2: 200.0,
+        y2: 0.0,
+        transform: Default::default(),
+        spread_method: SpreadMethod::Pad,
+        stops: stops_with_3_solid_1(),
+        anti_alias: false,
+    };
+    surface.set_fill(Some(Fill {
+        paint: gradient.into(),
+        ..Fill::default()
+    }));
+    surface.push_transform(&Transform::from_translate(0.0, 25.0));
+    surface.draw_image(
+        stencil_image(200, 150),
+        Size::from_wh(200.0, 150.0).unwrap(),
+    );
+    surface.pop();
+    surface.finish();
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+
+    assert!(pdf_contains(&pdf, b"/ImageMask true"));
+    assert!(pdf_contains(&pdf, b"/Pattern cs"));
+    assert!(pdf_contains(&pdf, b"/ShadingType 2"));
+}
+
+#[test]
+fn image_stencil() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    surface.set_fill(Some(red_fill(0.5)));
+    surface.draw_image(
+        stencil_image(197, 150),
+        Size::from_wh(197.0, 150.0).unwrap(),
+    );
+    surface.finish();
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+
+    assert!(pdf_contains(&pdf, b"/ImageMask true"));
+    assert!(pdf_contains(&pdf, b"/CCITTFaxDecode"));
+    assert!(pdf_contains(&pdf, b"/ca 0.5"));
+    assert!(!pdf_contains(&pdf, b"/Decode ["));
+}
+
+#[test]
+fn image_stencil_not_deduplicated() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    let size = Size::from_wh(10.0, 10.0).unwrap();
+    surface.draw_image(bilevel_image(10, 10), size);
+    surface.draw_image(stencil_image(10, 10), size);
+    surface.finish();
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Image"), 2);
+    assert_eq!(pdf_count(&pdf, b"/ImageMask true"), 1);
+}
+
+#[derive(Clone, Hash)]
+struct PackedImage {
+    color_channel: Vec<u8>,
+    alpha_channel: Option<Vec<u8>>,
+    bits_per_component: BitsPerComponent,
+    size: (u32, u32),
+    color_space: ImageColorspace,
+}
+
+impl CustomImage for PackedImage {
+    fn color_channel(&self) -> &[u8] {
+        &self.color_channel
+    }
+
+    fn alpha_channel(&self) -> Option<&[u8]> {
+        self.alpha_channel.as_deref()
+    }
+
+    fn bits_per_component(&self) -> BitsPerComponent {
+        self.bits_per_component
+    }
+
+    fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    fn icc_profile(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn color_space(&self) -> ImageColorspace {
+        self.color_space
+    }
+}
+
+/// A grayscale heatmap with the given bit depth, where each row is padded to a byte boundary.
+fn heatmap(bits: u8, width: u32, height: u32) -> Vec<u8> {
+    let max = (1u32 << bits) - 1;
+    let row_len = (width as usize * bits as usize).div_ceil(8);
+    let mut data = vec![0; row_len * height as usize];
+
+    for y in 0..height {
+        for x in 0..width {
+            let dx = x as f32 / width as f32 - 0.5;
+            let dy = y as f32 / height as f32 - 0.5;
+            let value = ((1.0 - (dx * dx + dy * dy).sqrt() * 2.0).max(0.0) * max as f32) as u8;
+            let offset = x as usize * bits as usize;
+            data[y as usize * row_len + offset / 8] |= value << (8 - bits as usize - offset % 8);
+        }
+    }
+
+    data
+}
+
+fn packed_luma_image(bits_per_component: BitsPerComponent, bits: u8) -> Image {
+    Image::from_custom(
+        PackedImage {
+            color_channel: heatmap(bits, 101, 80),
+            alpha_channel: None,
+            bits_per_component,
+            size: (101, 80),
+            color_space: ImageColorspace::Luma,
+        },
+        false,
+    )
+    .unwrap()
+}
+
+#[test]
+fn image_rgba4_custom() {
+    // 30 pixels with 3 components of four bits each need 45 bytes per row.
+    let color_channel = (0..20u32)
+        .flat_map(|y| (0..45u32).map(move |x| ((x / 3) as u8) << 4 | (y * 15 / 19) as u8))
+        .collect();
+    let alpha_channel = (0..600u32).map(|i| (i % 30 * 8) as u8).collect();
+
+    let image = Image::from_custom(
+        PackedImage {
+            color_channel,
+            alpha_channel: Some(alpha_channel),
+            bits_per_component: BitsPerComponent::Four,
+            size: (30, 20),
+            color_space: ImageColorspace::Rgb,
+        },
+        false,
+    )
+    .unwrap();
+    let pdf = image_document(image);
+
+    assert!(pdf_contains(&pdf, b"/DeviceRGB"));
+    assert_eq!(pdf_count(&pdf, b"/SMask"), 1);
+    // The soft mask keeps eight bits per component.
+    assert_eq!(pdf_count(&pdf, b"/BitsPerComponent 4"), 1);
+    assert_eq!(pdf_count(&pdf, b"/BitsPerComponent 8"), 1);
+}
+
+#[test]
+fn image_luma_custom() {
+    for (bits_per_component, bits) in [
+        (BitsPerComponent::One, 1),
+        (BitsPerComponent::Two, 2),
+        (BitsPerComponent::Four, 4),
+    ] {
+        let pdf = image_document(packed_luma_image(bits_per_component, bits));
+
+        assert!(pdf_contains(
+            &pdf,
+            format!("/BitsPerComponent {bits}").as_bytes()
+        ));
+        assert!(!pdf_contains(&pdf, b"/BitsPerComponent 8"));
+        assert!(pdf_contains(&pdf, b"/DeviceGray"));
+        assert!(!pdf_contains(&pdf, b"/SMask"));
+    }
+}
+
+#[test]
+fn image_low_bit_depth_smaller() {
+    let eight_bit = image_document(packed_luma_image(BitsPerComponent::Eight, 8));
+    let two_bit = image_document(packed_luma_image(BitsPerComponent::Two, 2));
+
+    assert!(two_bit.len() < eight_bit.len());
+}
+
+#[test]
+fn image_low_bit_depth_alpha() {
+    let image = Image::from_custom(
+        PackedImage {
+            color_channel: heatmap(4, 101, 80),
+            alpha_channel: Some(vec![128; 101 * 80]),
+            bits_per_component: BitsPerComponent::Four,
+            size: (101, 80),
+            color_space: ImageColorspace::Luma,
+        },
+        false,
+    )
+    .unwrap();
+    let pdf = image_document(image);
+
+    // The soft mask keeps eight bits per component.
+    assert_eq!(pdf_count(&pdf, b"/BitsPerComponent 4"), 1);
+    assert_eq!(pdf_count(&pdf, b"/BitsPerComponent 8"), 1);
+}
+
+#[test]
+fn image_low_bit_depth_indexed() {
+    // Two distinct RGB colors with four bits per component can be written with
+    // one bit per index.
+    let color_channel = (0..40)
+        .flat_map(|i| {
+            if i % 2 == 0 {
+                [0xF0, 0x0F, 0x00]
+            } else {
+                [0x00, 0xF0, 0x0F]
+            }
+        })
+        .collect();
+    let image = Image::from_custom(
+        PackedImage {
+            color_channel,
+            alpha_channel: None,
+            bits_per_component: BitsPerComponent::Four,
+            size: (16, 5),
+            color_space: ImageColorspace::Rgb,
+        },
+        false,
+    )
+    .unwrap()
+    .indexed(true);
+    let pdf = image_document(image);
+
+    assert!(pdf_contains(&pdf, b"/Indexed /DeviceRGB 1"));
+    assert!(pdf_contains(&pdf, b"/BitsPerComponent 1"));
+
+    // One bit luma images won't get any smaller.
+    let pdf = image_document(packed_luma_image(BitsPerComponent::One, 1).indexed(true));
+
+    assert!(!pdf_contains(&pdf, b"/Indexed"));
+    assert!(pdf_contains(&pdf, b"/BitsPerComponent 1"));
+}
+
+#[test]
+fn image_draw_dpi() {
+    let image = Image::from_rgba8(vec![255; 100 * 50 * 4], 100, 50);
+
+    let draw = |f: &dyn Fn(&mut Surface)| {
+        let mut document = Document::new_with(settings_1());
+        let mut page = document.start_page();
+        let mut surface = page.surface();
+        f(&mut surface);
+        surface.finish();
+        page.finish();
+        document.finish().unwrap()
+    };
+
+    // 144 DPI means half a unit per pixel.
+    let dpi_pdf =
+        draw(&|surface| surface.draw_image_dpi(image.clone(), Point::from_xy(10.0, 20.0), 144.0));
+    let size_pdf = draw(&|surface| {
+        surface.push_transform(&Transform::from_translate(10.0, 20.0));
+        surface.draw_image(image.clone(), Size::from_wh(50.0, 25.0).unwrap());
+        surface.pop();
+    });
+    assert_eq!(dpi_pdf, size_pdf);
+
+    let invalid_pdf =
+        draw(&|surface| surface.draw_image_dpi(image.clone(), Point::from_xy(10.0, 20.0), 0.0));
+    let empty_pdf = draw(&|_| {});
+    assert_eq!(invalid_pdf, empty_pdf);
+}
+
+#[test]
+fn image_interpolate_upscaled() {
+    let data = (0..16u32)
+        .flat_map(|i| {
+            if (i + i / 4) % 2 == 0 {
+                [255, 0, 0, 255]
+            } else {
+                [0, 0, 255, 255]
+            }
+        })
+        .collect();
+    let image = Image::from_rgba8(data, 4, 4).interpolate(true);
+    let pdf = image_document(image);
+
+    assert!(pdf_contains(&pdf, b"/Width 4"));
+    assert!(pdf_contains(&pdf, b"/Interpolate true"));
+    // The image is fully opaque.
+    assert!(!pdf_contains(&pdf, b"/SMask"));
+}
+
+#[test]
+fn image_interpolate_not_deduplicated() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    let size = Size::from_wh(10.0, 10.0).unwrap();
+    surface.draw_image(load_png_image("rgb8.png"), size);
+    surface.draw_image(load_png_image("rgb8.png").interpolate(true), size);
+    surface.finish();
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Image"), 2);
+    assert_eq!(pdf_count(&pdf, b"/Interpolate true"), 1);
+}
+
+fn lazy_image(name: &str, format: ImageFormat, loads: Arc<AtomicUsize>) -> Image {
+    let path = ASSETS_PATH.join("images").join(name);
+
+    Image::from_lazy(
+        format,
+        move || {
+            loads.fetch_add(1, Ordering::SeqCst);
+            Some(std::fs::read(&path).ok()?.into())
+        },
+        false,
+    )
+    .unwrap()
+}
+
+#[test]
+fn image_rgba8_png_lazy() {
+    let lazy_pdf = image_document(lazy_image("rgba8.png", ImageFormat::Png, Arc::default()));
+    assert_eq!(lazy_pdf, image_document(load_png_image("rgba8.png")));
+}
+
+#[test]
+fn image_lazy_loaded_when_serialized() {
+    let loads = Arc::new(AtomicUsize::new(0));
+    let image = lazy_image("rgb8.jpg", ImageFormat::Jpeg, loads.clone());
+    // The data is only loaded once to read the metadata.
+    assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+    let lazy_pdf = image_document(image);
+    assert_eq!(loads.load(Ordering::SeqCst), 2);
+
+    assert_eq!(lazy_pdf, image_document(load_jpg_image("rgb8.jpg")));
+}
+
+#[test]
+fn image_lazy_load_failed() {
+    let loaded = AtomicBool::new(false);
+    let image = Image::from_lazy(
+        ImageFormat::Png,
+        move || {
+            // Fail when loading the image for the second time.
+            if loaded.swap(true, Ordering::SeqCst) {
+                return None;
+            }
+
+            Some(
+                std::fs::read(ASSETS_PATH.join("images").join("rgb8.png"))
+                    .ok()?
+                    .into(),
+            )
+        },
+        false,
+    )
+    .unwrap();
+
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    surface.draw_image(image.clone(), Size::from_wh(10.0, 10.0).unwrap());
+    surface.finish();
+    page.finish();
+
+    assert_eq!(document.finish(), Err(KrillaError::Image(image, None)));
+}
+
+fn raw_pixels(bytes_per_pixel: usize, width: u32, height: u32) -> Vec<u8> {
+    (0..width * height)
+        .flat_map(|i| {
+            let (x, y) = (i % width, i / width);
+            (0..bytes_per_pixel).map(move |c| match c % 4 {
+                0 => (x * 255 / width) as u8,
+                1 => (y * 255 / height) as u8,
+                2 => ((x + y) * 255 / (width + height)) as u8,
+                _ => 255 - (x * 255 / width) as u8,
+            })
+        })
+        .collect()
+}
+
+fn raw_image(format: PixelFormat, bytes_per_pixel: usize) -> Image {
+    Image::from_raw(raw_pixels(bytes_per_pixel, 60, 40), 60, 40, format, None).unwrap()
+}
+
+#[test]
+fn image_raw_formats() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    let formats = [
+        (PixelFormat::Gray8, 1),
+        (PixelFormat::Gray16, 2),
+        (PixelFormat::Rgb8, 3),
+        (PixelFormat::Rgb16, 6),
+        (PixelFormat::Rgba8, 4),
+        (PixelFormat::Cmyk8, 4),
+    ];
+
+    for (i, (format, bytes_per_pixel)) in formats.into_iter().enumerate() {
+        let (x, y) = ((i % 3) as f32 * 65.0, (i / 3) as f32 * 45.0);
+        surface.push_transform(&Transform::from_translate(x, y));
+        surface.draw_image(
+            raw_image(format, bytes_per_pixel),
+            Size::from_wh(60.0, 40.0).unwrap(),
+        );
+        surface.pop();
+    }
+    surface.finish();
+    page.finish();
+
+    let pdf = document.finish().unwrap();
+    // One image for each format, plus the soft mask of the RGBA image.
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Image"), 7);
+    assert_eq!(pdf_count(&pdf, b"/SMask"), 1);
+    assert_eq!(pdf_count(&pdf, b"/BitsPerComponent 16"), 2);
+    assert!(pdf_contains(&pdf, b"/DeviceCMYK"));
+}
+
+#[test]
+fn image_raw_alpha() {
+    let pdf = image_document(raw_image(PixelFormat::Rgba8, 4));
+    assert_eq!(pdf_count(&pdf, b"/SMask"), 1);
+
+    // Fully opaque images don't need a soft mask.
+    let opaque = Image::from_raw(vec![255; 60 * 40 * 4], 60, 40, PixelFormat::Rgba8, None);
+    let pdf = image_document(opaque.unwrap());
+    assert_eq!(pdf_count(&pdf, b"/SMask"), 0);
+}
+
+#[test]
+fn image_raw_sixteen_bit() {
+    let pdf = image_document(raw_image(PixelFormat::Rgb16, 6));
+    assert!(pdf_contains(&pdf, b"/BitsPerComponent 16"));
+}
+
+#[test]
+fn image_raw_icc() {
+    let icc = std::fs::read(ASSETS_PATH.join("icc").join("eciCMYK_v2.icc")).unwrap();
+
+    let image = Image::from_raw(
+        raw_pixels(4, 60, 40),
+        60,
+        40,
+        PixelFormat::Cmyk8,
+        Some(icc.clone().into()),
+    );
+    let pdf = image_document(image.unwrap());
+    assert!(pdf_contains(&pdf, b"/ICCBased"));
+
+    // The profile doesn't match the color space, so it's ignored.
+    let image = Image::from_raw(
+        raw_pixels(3, 60, 40),
+        60,
+        40,
+        PixelFormat::Rgb8,
+        Some(icc.into()),
+    );
+    let pdf = image_document(image.unwrap());
+    assert!(!pdf_contains(&pdf, b"/ICCBased"));
+}
+
+#[test]
+fn image_raw_invalid() {
+    assert_eq!(
+        Image::from_raw(vec![0; 100], 10, 10, PixelFormat::Rgb8, None),
+        Err(RawImageError::InvalidLength(300, 100))
+    );
+    assert_eq!(
+        Image::from_raw(vec![0; 100], 10, 5, PixelFormat::Gray16, None).map(|i| i.size()),
+        Ok((10, 5))
+    );
+    assert_eq!(
+        Image::from_raw(vec![], 0, 10, PixelFormat::Gray8, None),
+        Err(RawImageError::EmptyImage)
+    );
+}
+
+/// Insert an EXIF segment with the given orientation into a JPEG.
+fn jpeg_with_orientation(name: &str, orientation: u16, big_endian: bool) -> Image {
+    let data = std::fs::read(ASSETS_PATH.join("images").join(name)).unwrap();
+    let u16_bytes = |n: u16| {
+        if big_endian {
+            n.to_be_bytes()
+        } else {
+            n.to_le_bytes()
+        }
+    };
+    let u32_bytes = |n: u32| {
+        if big_endian {
+            n.to_be_bytes()
+        } else {
+            n.to_le_bytes()
+        }
+    };
+
+    let mut tiff = vec![];
+    tiff.extend(if big_endian { b"MM" } else { b"II" });
+    tiff.extend(u16_bytes(42));
+    // The offset of the first IFD.
+    tiff.extend(u32_bytes(8));
+    tiff.extend(u16_bytes(1));
+    // The orientation tag, with a single SHORT value.
+    tiff.extend(u16_bytes(0x0112));
+    tiff.extend(u16_bytes(3));
+    tiff.extend(u32_bytes(1));
+    tiff.extend(u16_bytes(orientation));
+    tiff.extend([0, 0]);
+    // The offset of the next IFD.
+    tiff.extend(u32_bytes(0));
+
+    let mut segment = b"Exif\0\0".to_vec();
+    segment.extend(tiff);
+
+    let mut jpeg = data[..2].to_vec();
+    jpeg.extend([0xFF, 0xE1]);
+    jpeg.extend((segment.len() as u16 + 2).to_be_bytes());
+    jpeg.extend(segment);
+    jpeg.extend(&data[2..]);
+
+    Image::from_jpeg(jpeg.into(), false).unwrap()
+}
+
+#[visreg(all)]
+fn image_jpeg_orientation(surface: &mut Surface) {
+    for orientation in 1..=8 {
+        let image = jpeg_with_orientation("rgb8.jpg", orientation, orientation % 2 == 0);
+        let x = ((orientation - 1) % 4) as f32 * 50.0;
+        let y = ((orientation - 1) / 4) as f32 * 100.0;
+
+        surface.push_transform(&Transform::from_translate(x, y));
+        surface.draw_image(image, Size::from_wh(45.0, 90.0).unwrap());
+        surface.pop();
+    }
+}
+
+#[test]
+fn image_jpeg_orientation_transform() {
+    // The transforms also include the flip of the y-axis of the page.
+    let transforms = [
+        "100 0 0 50 0 0 cm",
+        "-100 0 0 50 100 0 cm",
+        "-100 0 0 -50 100 50 cm",
+        "100 0 0 -50 0 50 cm",
+        "0 -50 -100 0 100 50 cm",
+        "0 -50 100 0 0 50 cm",
+        "0 50 100 0 0 0 cm",
+        "0 50 -100 0 100 0 cm",
+    ];
+
+    let draw = |image: Image| {
+        let mut document = Document::new_with(settings_1());
+        let mut page = document.start_page_with(PageSettings::new(100.0, 50.0));
+        let mut surface = page.surface();
+        surface.draw_image(image, Size::from_wh(100.0, 50.0).unwrap());
+        surface.finish();
+        page.finish();
+
+        document.finish().unwrap()
+    };
+
+    for (orientation, transform) in (1..=8).zip(transforms) {
+        for big_endian in [true, false] {
+            let image = jpeg_with_orientation("rgb8.jpg", orientation, big_endian);
+            let pdf = draw(image.clone());
+            assert!(
+                pdf_contains(&pdf, transform.as_bytes()),
+                "orientation {orientation}"
+            );
+
+            let pdf = draw(image.respect_orientation(false));
+            assert!(pdf_contains(&pdf, transforms[0].as_bytes()));
+        }
+    }
+
+    // Invalid orientations are ignored.
+    let pdf = draw(jpeg_with_orientation("rgb8.jpg", 9, true));
+    assert!(pdf_contains(&pdf, transforms[0].as_bytes()));
+}
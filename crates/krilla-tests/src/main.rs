@@ -46,7 +46,9 @@ use skrifa::{FontRef, MetadataProvider};
 mod annotation;
 mod destination;
 mod embed;
+mod encryption;
 mod font;
+mod form;
 mod graphic;
 mod image;
 mod mask;
@@ -110,8 +112,14 @@ macro_rules! lazy_font {
 #[rustfmt::skip]
 lazy_font!(NOTO_SANS, FONT_PATH.join("NotoSans-Regular.ttf"));
 #[rustfmt::skip]
+lazy_font!(NOTO_SANS_WOFF, FONT_PATH.join("NotoSans-Regular.woff"));
+#[rustfmt::skip]
+lazy_font!(NOTO_SANS_WOFF2, FONT_PATH.join("NotoSans-Regular.woff2"));
+#[rustfmt::skip]
 lazy_font!(LATIN_MODERN_ROMAN, FONT_PATH.join("LatinModernRoman-Regular.otf"));
 #[rustfmt::skip]
+lazy_font!(NOTO_SANS_VARIABLE, FONT_PATH.join("NotoSans_variable.ttf"));
+#[rustfmt::skip]
 lazy_font!(NOTO_SANS_ARABIC, FONT_PATH.join("NotoSansArabic-Regular.ttf"));
 #[rustfmt::skip]
 lazy_font!(NOTO_SANS_CJK, FONT_PATH.join("NotoSansCJKsc-Regular.otf"));
@@ -319,6 +327,16 @@ pub fn rect_to_path(x1: f32, y1: f32, x2: f32, y2: f32) -> Path {
     builder.finish().unwrap()
 }
 
+/// Check whether the bytes of a PDF contain a needle.
+pub fn pdf_contains(pdf: &[u8], needle: &[u8]) -> bool {
+    pdf.windows(needle.len()).any(|w| w == needle)
+}
+
+/// Count the (possibly overlapping) occurrences of a needle in the bytes of a PDF.
+pub fn pdf_count(pdf: &[u8], needle: &[u8]) -> usize {
+    pdf.windows(needle.len()).filter(|w| *w == needle).count()
+}
+
 pub fn load_png_image(name: &str) -> Image {
     Image::from_png(
         std::fs::read(ASSETS_PATH.join("images").join(name))
@@ -856,9 +874,13 @@ pub fn settings_1() -> SerializeSettings {
         no_device_cs: false,
         xmp_metadata: false,
         cmyk_profile: None,
+        convert_cmyk_to_rgb: false,
+        deduplicate_images: true,
+        subset_fonts: true,
         enable_tagging: true,
         configuration: Configuration::new(),
         render_svg_glyph_fn: render_svg_glyph,
+        encryption: None,
     }
 }
 
@@ -1,12 +1,13 @@
-use krilla::color::rgb;
-use krilla::mask::MaskType;
+use krilla::color::{luma, rgb};
+use krilla::mask::{MaskType, TransferFunction};
 use krilla::num::NormalizedF32;
 use krilla::page::Page;
-use krilla::paint::Fill;
+use krilla::paint::{Fill, RadialGradient, SpreadMethod, Stop};
 use krilla::surface::Surface;
+use krilla::Document;
 use krilla_macros::{snapshot, visreg};
 
-use crate::{basic_mask, green_fill, rect_to_path};
+use crate::{basic_mask, green_fill, pdf_contains, pdf_count, rect_to_path, settings_1};
 
 fn mask_visreg_impl(mask_type: MaskType, surface: &mut Surface, color: rgb::Color) {
     let path = rect_to_path(20.0, 20.0, 180.0, 180.0);
@@ -41,3 +42,143 @@ fn mask(page: &mut Page) {
     surface.draw_path(&path);
     surface.pop();
 }
+
+fn vignette_fill() -> Fill {
+    let gradient = RadialGradient {
+        cx: 100.0,
+        cy: 100.0,
+        cr: 90.0,
+        fx: 100.0,
+        fy: 100.0,
+        fr: 0.0,
+        transform: Default::default(),
+        spread_method: SpreadMethod::Pad,
+        stops: vec![
+            Stop {
+                offset: NormalizedF32::new(0.6).unwrap(),
+                color: luma::Color::white().into(),
+                opacity: NormalizedF32::ONE,
+            },
+            Stop {
+                offset: NormalizedF32::ONE,
+                color: luma::Color::black().into(),
+                opacity: NormalizedF32::ONE,
+            },
+        ],
+        anti_alias: false,
+    };
+
+    Fill {
+        paint: gradient.into(),
+        opacity: NormalizedF32::ONE,
+        rule: Default::default(),
+    }
+}
+
+#[test]
+fn mask_backdrop_and_transfer_function_entries() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    let mask = surface
+        .create_mask(MaskType::Luminosity, |surface| {
+            surface.set_fill(Some(vignette_fill()));
+            surface.draw_path(&rect_to_path(20.0, 20.0, 180.0, 180.0));
+        })
+        .with_backdrop(rgb::Color::white())
+        .with_transfer_function(TransferFunction::inverted());
+    surface.push_mask(mask);
+    surface.set_fill(Some(green_fill(1.0)));
+    surface.draw_path(&rect_to_path(0.0, 0.0, 200.0, 200.0));
+    surface.pop();
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/S /Luminosity"));
+    assert!(pdf_contains(&pdf, b"/BC [1 1 1]"));
+    assert!(pdf_contains(&pdf, b"/TR "));
+    assert!(pdf_contains(&pdf, b"/FunctionType 0"));
+}
+
+#[test]
+fn mask_transfer_function_invalid() {
+    assert!(TransferFunction::exponential(0.0).is_none());
+    assert!(TransferFunction::exponential(f32::NAN).is_none());
+    assert!(TransferFunction::sampled(vec![0]).is_none());
+    assert!(TransferFunction::exponential(2.2).is_some());
+}
+
+fn mask_from_surface_impl(surface: &mut Surface) {
+    let mask = surface.create_mask(MaskType::Luminosity, |surface| {
+        surface.set_fill(Some(vignette_fill()));
+        surface.draw_path(&rect_to_path(0.0, 0.0, 200.0, 200.0));
+    });
+
+    surface.push_mask(mask);
+    surface.set_fill(Some(green_fill(1.0)));
+    surface.draw_path(&rect_to_path(0.0, 0.0, 200.0, 200.0));
+    surface.pop();
+}
+
+#[visreg(all)]
+fn mask_from_surface(surface: &mut Surface) {
+    mask_from_surface_impl(surface);
+}
+
+#[test]
+fn mask_from_surface_cached() {
+    let mut d = Document::new_with(settings_1());
+
+    for _ in 0..2 {
+        let mut page = d.start_page();
+        let mut surface = page.surface();
+        mask_from_surface_impl(&mut surface);
+        surface.finish();
+        page.finish();
+    }
+
+    let pdf = d.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Type /Mask"), 1);
+    assert_eq!(pdf_count(&pdf, b"/S /Luminosity"), 1);
+}
+
+fn mask_transfer_function_impl(transfer_function: TransferFunction) -> Vec<u8> {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    let mask = surface
+        .create_mask(MaskType::Alpha, |surface| {
+            surface.set_fill(Some(green_fill(0.5)));
+            surface.draw_path(&rect_to_path(20.0, 20.0, 180.0, 180.0));
+        })
+        .with_transfer_function(transfer_function);
+    surface.push_mask(mask);
+    surface.set_fill(Some(green_fill(1.0)));
+    surface.draw_path(&rect_to_path(0.0, 0.0, 200.0, 200.0));
+    surface.pop();
+    surface.finish();
+    page.finish();
+
+    d.finish().unwrap()
+}
+
+#[test]
+fn mask_transfer_function_exponential() {
+    let pdf = mask_transfer_function_impl(TransferFunction::exponential(2.2).unwrap());
+    assert!(pdf_contains(&pdf, b"/TR "));
+    assert!(pdf_contains(&pdf, b"/FunctionType 2"));
+    assert!(pdf_contains(&pdf, b"/N 2.2"));
+}
+
+#[test]
+fn mask_transfer_function_identity_omitted() {
+    for transfer_function in [
+        TransferFunction::exponential(1.0).unwrap(),
+        TransferFunction::sampled(vec![0, 255]).unwrap(),
+        TransferFunction::sampled(vec![0, 128, 255]).unwrap(),
+    ] {
+        let pdf = mask_transfer_function_impl(transfer_function);
+        assert!(!pdf_contains(&pdf, b"/TR "));
+    }
+}
@@ -1,7 +1,15 @@
-use krilla::metadata::{DateTime, Metadata, PageLayout, TextDirection};
+use std::num::NonZeroU32;
+
+use krilla::error::KrillaError;
+use krilla::metadata::{
+    DateTime, Duplex, Metadata, PageLayout, PageMode, PrintScaling, TextDirection,
+    ViewerPreferences,
+};
 use krilla::Document;
 use krilla_macros::snapshot;
 
+use crate::{pdf_contains, settings_1, settings_17};
+
 pub(crate) fn metadata_impl(document: &mut Document) {
     let date = DateTime::new(2024)
         .month(11)
@@ -44,3 +52,120 @@ fn metadata_full(document: &mut Document) {
 fn metadata_full_with_xmp(document: &mut Document) {
     metadata_impl(document);
 }
+
+fn viewer_preferences_impl(document: &mut Document) {
+    let metadata = Metadata::new().viewer_preferences(
+        ViewerPreferences::new()
+            .hide_toolbar(true)
+            .hide_menubar(true)
+            .hide_window_ui(true)
+            .fit_window(true)
+            .center_window(true)
+            .display_doc_title(true),
+    );
+    document.set_metadata(metadata);
+}
+
+#[test]
+fn metadata_viewer_preferences() {
+    let mut document = Document::new_with(settings_1());
+    viewer_preferences_impl(&mut document);
+
+    let pdf = document.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/HideToolbar true"));
+    assert!(pdf_contains(&pdf, b"/HideMenubar true"));
+    assert!(pdf_contains(&pdf, b"/HideWindowUI true"));
+    assert!(pdf_contains(&pdf, b"/FitWindow true"));
+    assert!(pdf_contains(&pdf, b"/CenterWindow true"));
+    assert!(pdf_contains(&pdf, b"/DisplayDocTitle true"));
+}
+
+#[test]
+fn metadata_viewer_preferences_default() {
+    let mut document = Document::new_with(settings_1());
+    document.set_metadata(Metadata::new().viewer_preferences(ViewerPreferences::new()));
+
+    let pdf = document.finish().unwrap();
+    assert!(!pdf_contains(&pdf, b"/ViewerPreferences"));
+}
+
+#[test]
+fn metadata_page_layout_and_mode() {
+    let mut document = Document::new_with(settings_1());
+    document.set_metadata(
+        Metadata::new()
+            .page_layout(PageLayout::TwoPageRight)
+            .page_mode(PageMode::UseOutlines),
+    );
+
+    let pdf = document.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/PageLayout /TwoPageRight"));
+    assert!(pdf_contains(&pdf, b"/PageMode /UseOutlines"));
+}
+
+#[test]
+fn metadata_page_layout_pdf_14() {
+    let mut document = Document::new_with(settings_17());
+    document.set_metadata(Metadata::new().page_layout(PageLayout::TwoPageLeft));
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::PageLayout(PageLayout::TwoPageLeft))
+    );
+}
+
+#[test]
+fn metadata_page_mode_pdf_14() {
+    let mut document = Document::new_with(settings_17());
+    document.set_metadata(Metadata::new().page_mode(PageMode::UseAttachments));
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::PageMode(PageMode::UseAttachments))
+    );
+}
+
+fn print_preferences_metadata() -> Metadata {
+    Metadata::new().viewer_preferences(
+        ViewerPreferences::new()
+            .duplex(Duplex::DuplexFlipLongEdge)
+            .print_scaling(PrintScaling::None)
+            .print_page_range(vec![0..=1, 3..=3])
+            .num_copies(NonZeroU32::new(2).unwrap()),
+    )
+}
+
+#[test]
+fn metadata_print_preferences() {
+    let mut document = Document::new_with(settings_1());
+    document.set_metadata(print_preferences_metadata());
+
+    let pdf = document.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/Duplex /DuplexFlipLongEdge"));
+    assert!(pdf_contains(&pdf, b"/PrintScaling /None"));
+    assert!(pdf_contains(&pdf, b"/PrintPageRange [1 2 4 4]"));
+    assert!(pdf_contains(&pdf, b"/NumCopies 2"));
+}
+
+#[test]
+fn metadata_print_preferences_pdf_14() {
+    let mut document = Document::new_with(settings_17());
+    document.set_metadata(print_preferences_metadata());
+
+    assert_eq!(document.finish(), Err(KrillaError::PrintPreferences));
+}
+
+#[test]
+fn metadata_viewer_preferences_direction() {
+    let mut document = Document::new_with(settings_1());
+    document.set_metadata(
+        Metadata::new()
+            .language("he".to_string())
+            .text_direction(TextDirection::LeftToRight)
+            .viewer_preferences(ViewerPreferences::new().direction(TextDirection::RightToLeft)),
+    );
+
+    let pdf = document.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/Direction /R2L"));
+    assert!(!pdf_contains(&pdf, b"/Direction /L2R"));
+}
@@ -1,11 +1,16 @@
+use std::num::NonZeroU32;
+
+use krilla::action::{JavaScriptAction, LinkAction, NamedAction};
+use krilla::color::rgb;
 use krilla::destination::XyzDestination;
 use krilla::geom::Point;
 use krilla::outline::{Outline, OutlineNode};
 use krilla::page::PageSettings;
+use krilla::tagging::{ContentTag, SpanTag, Tag, TagGroup, TagTree};
 use krilla::Document;
 use krilla_macros::snapshot;
 
-use crate::{blue_fill, green_fill, rect_to_path, red_fill};
+use crate::{blue_fill, green_fill, pdf_contains, pdf_count, rect_to_path, red_fill, settings_1};
 
 #[snapshot(document)]
 fn outline_simple(d: &mut Document) {
@@ -54,3 +59,184 @@ fn outline_with_empty_title(d: &mut Document) {
 
     d.set_outline(outline);
 }
+
+#[test]
+fn outline_with_actions() {
+    let mut d = Document::new_with(settings_1());
+    for _ in 0..3 {
+        d.start_page_with(PageSettings::new(200.0, 200.0)).finish();
+    }
+
+    let mut outline = Outline::new();
+    outline.push_child(OutlineNode::new_with_action(
+        "Next page".to_string(),
+        NamedAction::NextPage.into(),
+    ));
+    outline.push_child(OutlineNode::new_with_action(
+        "Last page".to_string(),
+        NamedAction::LastPage.into(),
+    ));
+    outline.push_child(OutlineNode::new_with_action(
+        "Say hello".to_string(),
+        JavaScriptAction::new("app.alert('Hello');".to_string()).into(),
+    ));
+
+    d.set_outline(outline);
+
+    let pdf = d.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Title (Next page)"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Title (Last page)"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Title (Say hello)"), 1);
+    assert_eq!(pdf_count(&pdf, b"/S /Named"), 2);
+    assert_eq!(pdf_count(&pdf, b"/N /NextPage"), 1);
+    assert_eq!(pdf_count(&pdf, b"/N /LastPage"), 1);
+    assert_eq!(pdf_count(&pdf, b"/S /JavaScript"), 1);
+    assert_eq!(pdf_count(&pdf, b"/JS (app.alert"), 1);
+    // Entries with an action don't have a destination.
+    assert_eq!(pdf_count(&pdf, b"/Dest"), 0);
+}
+
+fn styled_outline() -> Outline {
+    let dest = |page| XyzDestination::new(page, Point::from_xy(0.0, 0.0));
+
+    let mut chapter1 = OutlineNode::new("Chapter 1".to_string(), dest(0))
+        .with_bold(true)
+        .with_open(true);
+    let mut section1 = OutlineNode::new("Section 1.1".to_string(), dest(0))
+        .with_color(rgb::Color::new(255, 0, 0))
+        .with_italic(true);
+    section1.push_child(OutlineNode::new("Section 1.1.1".to_string(), dest(0)));
+    section1.push_child(OutlineNode::new("Section 1.1.2".to_string(), dest(0)));
+    chapter1.push_child(section1);
+    chapter1.push_child(OutlineNode::new("Section 1.2".to_string(), dest(0)));
+
+    let mut chapter2 = OutlineNode::new("Chapter 2".to_string(), dest(1))
+        .with_bold(true)
+        .with_italic(true);
+    chapter2.push_child(OutlineNode::new("Section 2.1".to_string(), dest(1)));
+
+    let mut outline = Outline::new();
+    outline.push_child(chapter1);
+    outline.push_child(chapter2);
+    outline
+}
+
+#[snapshot(document)]
+fn outline_styled(d: &mut Document) {
+    d.start_page_with(PageSettings::new(200.0, 200.0)).finish();
+    d.start_page_with(PageSettings::new(200.0, 200.0)).finish();
+    d.set_outline(styled_outline());
+}
+
+fn mixed_outline() -> Outline {
+    let mut chapter = OutlineNode::new(
+        "Chapter 1".to_string(),
+        XyzDestination::new(0, Point::from_xy(0.0, 0.0)),
+    )
+    .with_open(true);
+    chapter.push_child(OutlineNode::new_with_action(
+        "Next page".to_string(),
+        NamedAction::NextPage.into(),
+    ));
+    chapter.push_child(OutlineNode::new(
+        "Section 1.1".to_string(),
+        XyzDestination::new(1, Point::from_xy(0.0, 50.0)),
+    ));
+
+    let mut outline = Outline::new();
+    outline.push_child(chapter);
+    outline.push_child(OutlineNode::new_with_action(
+        "Project homepage".to_string(),
+        LinkAction::new("https://github.com/LaurenzV/krilla".to_string()).into(),
+    ));
+    outline
+}
+
+#[test]
+fn outline_mixed_destinations_and_actions() {
+    let mut d = Document::new_with(settings_1());
+    d.start_page_with(PageSettings::new(200.0, 200.0)).finish();
+    d.start_page_with(PageSettings::new(200.0, 200.0)).finish();
+    d.set_outline(mixed_outline());
+
+    let pdf = d.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Title (Chapter 1)"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Title (Next page)"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Title (Section 1.1)"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Title (Project homepage)"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Dest "), 2);
+    assert_eq!(pdf_count(&pdf, b"/A <<"), 2);
+    assert_eq!(pdf_count(&pdf, b"/S /URI"), 1);
+    assert_eq!(pdf_count(&pdf, b"/S /Named"), 1);
+}
+
+fn heading(level: u32, title: &str) -> TagGroup {
+    TagGroup::new(Tag::Hn(
+        NonZeroU32::new(level).unwrap(),
+        Some(title.to_string()),
+    ))
+}
+
+fn headings_document(d: &mut Document) {
+    let mut chapter1 = heading(1, "Chapter 1");
+    let mut section = heading(2, "Section 1.1");
+    let mut chapter2 = heading(1, "Chapter 2");
+    let mut subsection = heading(3, "Subsection 2.0.1");
+
+    let mut page = d.start_page_with(PageSettings::new(200.0, 200.0));
+    let mut surface = page.surface();
+    surface.set_fill(Some(red_fill(1.0)));
+    chapter1.push(surface.start_tagged(ContentTag::Span(SpanTag::empty())));
+    surface.draw_path(&rect_to_path(20.0, 20.0, 180.0, 40.0));
+    surface.end_tagged();
+    // The second heading starts at the bottom of the first page and continues
+    // on the second one.
+    section.push(surface.start_tagged(ContentTag::Span(SpanTag::empty())));
+    surface.draw_path(&rect_to_path(20.0, 160.0, 180.0, 180.0));
+    surface.end_tagged();
+    surface.finish();
+    page.finish();
+
+    let mut page = d.start_page_with(PageSettings::new(200.0, 200.0));
+    let mut surface = page.surface();
+    surface.set_fill(Some(green_fill(1.0)));
+    section.push(surface.start_tagged(ContentTag::Span(SpanTag::empty())));
+    surface.draw_path(&rect_to_path(20.0, 10.0, 180.0, 30.0));
+    surface.end_tagged();
+    chapter2.push(surface.start_tagged(ContentTag::Span(SpanTag::empty())));
+    surface.draw_path(&rect_to_path(20.0, 80.0, 180.0, 100.0));
+    surface.end_tagged();
+    surface.set_fill(Some(blue_fill(1.0)));
+    subsection.push(surface.start_tagged(ContentTag::Span(SpanTag::empty())));
+    surface.draw_path(&rect_to_path(40.0, 120.0, 180.0, 140.0));
+    surface.end_tagged();
+    surface.finish();
+    page.finish();
+
+    let mut tag_tree = TagTree::new();
+    tag_tree.push(chapter1);
+    tag_tree.push(section);
+    tag_tree.push(chapter2);
+    tag_tree.push(subsection);
+    // Headings without any content are skipped.
+    tag_tree.push(heading(1, "Empty"));
+
+    d.set_tag_tree(tag_tree);
+    d.outline_from_headings();
+}
+
+#[snapshot(document)]
+fn outline_from_headings(d: &mut Document) {
+    headings_document(d);
+}
+
+#[test]
+fn outline_from_headings_manual_outline_takes_precedence() {
+    let mut d = Document::new_with(settings_1());
+    headings_document(&mut d);
+    d.set_outline(styled_outline());
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/Title (Section 1.2)"));
+    assert!(!pdf_contains(&pdf, b"/Title (Subsection 2.0.1)"));
+}
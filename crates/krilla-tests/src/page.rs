@@ -1,12 +1,22 @@
 use std::num::NonZeroUsize;
 
-use krilla::geom::Rect;
-use krilla::page::{NumberingStyle, PageLabel, PageSettings};
+use krilla::action::LinkAction;
+use krilla::annotation::{LinkAnnotation, Target};
+use krilla::destination::XyzDestination;
+use krilla::error::KrillaError;
+use krilla::geom::{Point, Rect};
+use krilla::page::{
+    NumberingStyle, PageLabel, PageRotation, PageSettings, PageTransition, TabOrder,
+    TransitionDimension, TransitionDirection, TransitionMotion, TransitionStyle,
+};
 use krilla::Document;
 use krilla_macros::{snapshot, visreg};
 use tiny_skia_path::PathBuilder;
 
-use crate::{blue_fill, green_fill, purple_fill, rect_to_path, red_fill};
+use crate::{
+    blue_fill, green_fill, pdf_contains, pdf_count, purple_fill, rect_to_path, red_fill,
+    settings_1, settings_17,
+};
 
 fn media_box_impl(d: &mut Document, media_box: Rect) {
     let mut page =
@@ -36,6 +46,86 @@ fn page_label(d: &mut Document) {
     d.start_page_with(settings);
 }
 
+fn page_label_ranges_impl(d: &mut Document) {
+    for _ in 0..8 {
+        d.start_page_with(PageSettings::new(200.0, 200.0));
+    }
+
+    // Front matter: i, ii, iii
+    d.set_page_label_range(
+        0,
+        PageLabel::new(Some(NumberingStyle::LowerRoman), None, None),
+    );
+    // Body: 1, 2, 3
+    d.set_page_label_range(3, PageLabel::new(Some(NumberingStyle::Arabic), None, None));
+    // Appendix: A-1, A-2
+    d.set_page_label_range(
+        6,
+        PageLabel::new(
+            Some(NumberingStyle::Arabic),
+            Some("A-".to_string()),
+            NonZeroUsize::new(1),
+        ),
+    );
+}
+
+#[test]
+fn page_label_ranges() {
+    let mut d = Document::new_with(settings_1());
+    page_label_ranges_impl(&mut d);
+
+    let pdf = d.finish().unwrap();
+    // Consecutive pages of a range are merged into a single entry.
+    assert_eq!(pdf_count(&pdf, b"/St 1"), 3);
+    assert!(pdf_contains(&pdf, b"/S /r"));
+    assert_eq!(pdf_count(&pdf, b"/S /D"), 2);
+    assert!(pdf_contains(&pdf, b"/P (A-)"));
+}
+
+#[test]
+fn page_label_ranges_page_settings_take_precedence() {
+    let mut d = Document::new_with(settings_1());
+    d.start_page_with(PageSettings::new(200.0, 200.0));
+    d.start_page_with(
+        PageSettings::new(200.0, 200.0).with_page_label(PageLabel::new(
+            None,
+            Some("Cover".to_string()),
+            None,
+        )),
+    );
+    d.start_page_with(PageSettings::new(200.0, 200.0));
+    d.set_page_label_range(0, PageLabel::new(Some(NumberingStyle::Arabic), None, None));
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/P (Cover)"));
+    // The numbering continues after the page with the custom label.
+    assert!(pdf_contains(&pdf, b"/St 3"));
+}
+
+#[test]
+fn page_label_ranges_unsorted() {
+    let mut d = Document::new_with(settings_1());
+    for _ in 0..4 {
+        d.start_page_with(PageSettings::new(200.0, 200.0));
+    }
+    d.set_page_label_range(2, PageLabel::new(Some(NumberingStyle::Arabic), None, None));
+    d.set_page_label_range(
+        2,
+        PageLabel::new(Some(NumberingStyle::LowerAlpha), None, None),
+    );
+
+    assert_eq!(d.finish(), Err(KrillaError::InvalidPageLabelRange(2)));
+}
+
+#[test]
+fn page_label_ranges_out_of_bounds() {
+    let mut d = Document::new_with(settings_1());
+    d.start_page_with(PageSettings::new(200.0, 200.0));
+    d.set_page_label_range(1, PageLabel::new(Some(NumberingStyle::Arabic), None, None));
+
+    assert_eq!(d.finish(), Err(KrillaError::InvalidPageLabelRange(1)));
+}
+
 #[snapshot(document)]
 fn page_with_crop_bleeding_trim_art_boxes(d: &mut Document) {
     // Create page settings with different boxes
@@ -49,6 +139,277 @@ fn page_with_crop_bleeding_trim_art_boxes(d: &mut Document) {
     let _ = d.start_page_with(page_settings);
 }
 
+fn page_rotation_impl(d: &mut Document, rotation: PageRotation) {
+    let mut page = d.start_page_with(PageSettings::new(200.0, 100.0).with_rotation(rotation));
+    page.add_annotation(
+        LinkAnnotation::new(
+            Rect::from_xywh(20.0, 10.0, 60.0, 30.0).unwrap(),
+            None,
+            Target::Action(LinkAction::new("https://www.youtube.com".to_string()).into()),
+        )
+        .into(),
+    );
+    let mut surface = page.surface();
+    surface.set_fill(Some(red_fill(1.0)));
+    surface.draw_path(&rect_to_path(20.0, 10.0, 80.0, 40.0));
+    surface.set_fill(Some(blue_fill(1.0)));
+    surface.draw_path(&rect_to_path(120.0, 60.0, 180.0, 90.0));
+    surface.finish();
+    page.finish();
+}
+
+#[test]
+fn page_rotation() {
+    for (rotation, entry) in [
+        (PageRotation::Rotate90, &b"/Rotate 90"[..]),
+        (PageRotation::Rotate180, &b"/Rotate 180"[..]),
+        (PageRotation::Rotate270, &b"/Rotate 270"[..]),
+    ] {
+        let mut d = Document::new_with(settings_1());
+        page_rotation_impl(&mut d, rotation);
+
+        let pdf = d.finish().unwrap();
+        assert!(pdf_contains(&pdf, entry));
+        // The hit area of the link is still specified in the unrotated coordinate space.
+        assert!(pdf_contains(&pdf, b"/Rect [20 60 80 90]"));
+        assert!(pdf_contains(&pdf, b"/MediaBox [0 0 200 100]"));
+    }
+}
+
+#[test]
+fn page_tab_order() {
+    let mut d = Document::new_with(settings_1());
+    d.set_tab_order(TabOrder::Row);
+
+    for tab_order in [None, Some(TabOrder::Column)] {
+        let mut page = d.start_page_with(PageSettings::default().with_tab_order(tab_order));
+        page.add_annotation(
+            LinkAnnotation::new(
+                Rect::from_xywh(20.0, 10.0, 60.0, 30.0).unwrap(),
+                None,
+                Target::Action(LinkAction::new("https://www.youtube.com".to_string()).into()),
+            )
+            .into(),
+        );
+        page.finish();
+    }
+
+    // Pages without annotations don't need a tab order.
+    d.start_page();
+
+    let pdf = d.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Tabs /R"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Tabs /C"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Tabs"), 2);
+}
+
+#[test]
+fn page_rotation_none() {
+    let mut d = Document::new_with(settings_1());
+    page_rotation_impl(&mut d, PageRotation::None);
+
+    let pdf = d.finish().unwrap();
+    assert!(!pdf_contains(&pdf, b"/Rotate"));
+}
+
+#[test]
+fn page_boxes_with_bleed() {
+    // A 100x100 page with a bleed of 3mm (~8.5pt) on each side.
+    let bleed = 8.5;
+    let size = 100.0 + 2.0 * bleed;
+    let mut d = Document::new_with(settings_1());
+    d.start_page_with(
+        PageSettings::new(size, size)
+            .with_bleed_box(Some(Rect::from_xywh(0.0, 0.0, size, size).unwrap()))
+            .with_trim_box(Some(Rect::from_xywh(bleed, bleed, 100.0, 100.0).unwrap())),
+    );
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/BleedBox [0 0 117 117]"));
+    assert!(pdf_contains(&pdf, b"/TrimBox [8.5 8.5 108.5 108.5]"));
+}
+
+#[test]
+fn page_boxes_outside_media_box() {
+    let mut d = Document::new_with(settings_1());
+    d.start_page_with(PageSettings::new(200.0, 200.0));
+    d.start_page_with(
+        PageSettings::new(200.0, 200.0)
+            .with_trim_box(Some(Rect::from_xywh(-10.0, 0.0, 100.0, 100.0).unwrap())),
+    );
+
+    assert_eq!(d.finish(), Err(KrillaError::PageBoxOutsideMediaBox(1)));
+}
+
+fn page_transitions_impl(d: &mut Document) {
+    let transitions = [
+        PageTransition::new(TransitionStyle::Split)
+            .with_dimension(TransitionDimension::Vertical)
+            .with_motion(TransitionMotion::Outward),
+        PageTransition::new(TransitionStyle::Wipe)
+            .with_direction(TransitionDirection::TopToBottom)
+            .with_duration(0.5),
+        PageTransition::new(TransitionStyle::Dissolve),
+        PageTransition::new(TransitionStyle::Fade).with_duration(2.0),
+    ];
+    let fills = [
+        red_fill(1.0),
+        green_fill(1.0),
+        blue_fill(1.0),
+        purple_fill(1.0),
+    ];
+
+    for (transition, fill) in transitions.into_iter().zip(fills) {
+        let mut page = d.start_page_with(
+            PageSettings::new(200.0, 150.0)
+                .with_transition(Some(transition))
+                .with_display_duration(Some(3.0)),
+        );
+        let mut surface = page.surface();
+        surface.set_fill(Some(fill));
+        surface.draw_path(&rect_to_path(20.0, 20.0, 180.0, 130.0));
+        surface.finish();
+        page.finish();
+    }
+}
+
+#[snapshot(document)]
+fn page_transitions(d: &mut Document) {
+    page_transitions_impl(d);
+}
+
+#[test]
+fn page_transitions_pdf_14() {
+    let mut d = Document::new_with(settings_17());
+    page_transitions_impl(&mut d);
+
+    let pdf = d.finish().unwrap();
+    // Fade requires PDF 1.5.
+    assert!(!pdf_contains(&pdf, b"/S /Fade"));
+    assert!(pdf_contains(&pdf, b"/S /R"));
+}
+
+#[test]
+fn page_user_unit() {
+    let mut d = Document::new_with(settings_1());
+    // A 400 x 200 inch drawing.
+    let mut page = d.start_page_with(PageSettings::new(14_400.0, 7_200.0).with_user_unit(2.0));
+    let mut surface = page.surface();
+    surface.set_fill(Some(red_fill(1.0)));
+    surface.draw_path(&rect_to_path(100.0, 100.0, 14_300.0, 7_100.0));
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/UserUnit 2"));
+    assert!(pdf_contains(&pdf, b"/MediaBox [0 0 14400 7200]"));
+}
+
+#[test]
+fn page_media_box_too_large() {
+    let mut d = Document::new_with(settings_1());
+    d.start_page_with(PageSettings::new(20_000.0, 200.0));
+
+    assert_eq!(d.finish(), Err(KrillaError::MediaBoxTooLarge(0)));
+}
+
+#[test]
+fn page_user_unit_pdf_14() {
+    let mut d = Document::new_with(settings_17());
+    d.start_page_with(PageSettings::new(200.0, 200.0).with_user_unit(2.0));
+
+    assert_eq!(d.finish(), Err(KrillaError::UserUnit(0)));
+}
+
+fn page_reorder_impl(d: &mut Document) {
+    let mut page = d.start_page_with(PageSettings::new(200.0, 200.0));
+    let mut surface = page.surface();
+    surface.set_fill(Some(green_fill(1.0)));
+    surface.draw_path(&rect_to_path(50.0, 50.0, 150.0, 150.0));
+    surface.finish();
+    page.finish();
+
+    let mut page = d.insert_page_at(0, PageSettings::new(200.0, 300.0));
+    let mut surface = page.surface();
+    surface.set_fill(Some(red_fill(1.0)));
+    surface.draw_path(&rect_to_path(50.0, 50.0, 150.0, 250.0));
+    surface.finish();
+    page.finish();
+
+    let mut page = d.start_page_with(PageSettings::new(300.0, 200.0));
+    let mut surface = page.surface();
+    surface.set_fill(Some(blue_fill(1.0)));
+    surface.draw_path(&rect_to_path(50.0, 50.0, 250.0, 150.0));
+    surface.finish();
+    page.finish();
+
+    // Results in blue, red, green, red.
+    d.move_page(2, 0);
+    d.duplicate_page(1);
+}
+
+#[test]
+fn page_reorder() {
+    let mut d = Document::new_with(settings_1());
+    page_reorder_impl(&mut d);
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/Count 4"));
+    assert_eq!(pdf_count(&pdf, b"/Contents "), 4);
+    assert_eq!(pdf_count(&pdf, b"/MediaBox [0 0 200 300]"), 2);
+    assert_eq!(pdf_count(&pdf, b"/MediaBox [0 0 200 200]"), 1);
+    assert_eq!(pdf_count(&pdf, b"/MediaBox [0 0 300 200]"), 1);
+}
+
+#[test]
+fn page_insert_at_destination() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page_with(PageSettings::new(200.0, 200.0));
+    page.add_annotation(
+        LinkAnnotation::new(
+            Rect::from_xywh(0.0, 0.0, 100.0, 100.0).unwrap(),
+            None,
+            Target::Destination(XyzDestination::new(1, Point::from_xy(0.0, 50.0)).into()),
+        )
+        .into(),
+    );
+    page.finish();
+    d.insert_page_at(0, PageSettings::new(200.0, 400.0));
+
+    let pdf = d.finish().unwrap();
+    // Page 1 now refers to the page that was created first.
+    assert!(pdf_contains(&pdf, b"/XYZ 0 150 null]"));
+}
+
+#[test]
+fn page_insert_at_error_index() {
+    let mut d = Document::new_with(settings_1());
+    d.start_page_with(PageSettings::new(200.0, 200.0));
+    d.insert_page_at(
+        0,
+        PageSettings::new(200.0, 200.0)
+            .with_trim_box(Some(Rect::from_xywh(-10.0, 0.0, 100.0, 100.0).unwrap())),
+    );
+
+    assert_eq!(d.finish(), Err(KrillaError::PageBoxOutsideMediaBox(0)));
+}
+
+#[test]
+#[should_panic]
+fn page_insert_at_out_of_bounds() {
+    let mut d = Document::new_with(settings_1());
+    d.start_page();
+    d.insert_page_at(2, PageSettings::default());
+}
+
+#[test]
+#[should_panic]
+fn page_move_out_of_bounds() {
+    let mut d = Document::new_with(settings_1());
+    d.start_page();
+    d.move_page(0, 1);
+}
+
 #[visreg(document)]
 fn page_media_box_top_left(d: &mut Document) {
     media_box_impl(d, Rect::from_xywh(-100.0, -100.0, 200.0, 200.0).unwrap())
@@ -1,8 +1,17 @@
-use krilla::page::Page;
+use krilla::color::lab::WhitePoint;
+use krilla::color::{cmyk, lab, rgb, DeviceN, SpotColor};
+use krilla::error::KrillaError;
+use krilla::geom::{CornerRadii, PathBuilder, Rect};
+use krilla::num::NormalizedF32;
+use krilla::page::{Page, PageSettings};
+use krilla::paint::{Fill, LinearGradient, SpreadMethod, Stop, Stroke, StrokeDash};
 use krilla::surface::Surface;
+use krilla::Document;
 use krilla_macros::{snapshot, visreg};
 
-use crate::{cmyk_fill, gray_fill, rect_to_path, red_fill};
+use crate::{
+    cmyk_fill, gray_fill, pdf_contains, pdf_count, rect_to_path, red_fill, settings_1, settings_7,
+};
 
 #[snapshot]
 fn path_with_rgb(page: &mut Page) {
@@ -55,3 +64,403 @@ fn path_with_cmyk_icc(surface: &mut Surface) {
     surface.set_fill(Some(cmyk_fill(1.0)));
     surface.draw_path(&path);
 }
+
+fn pantone_fill(tint: f32) -> Fill {
+    Fill {
+        paint: SpotColor::new(
+            "PANTONE 185 C".to_string(),
+            cmyk::Color::new(0, 232, 194, 0),
+            NormalizedF32::new(tint).unwrap(),
+        )
+        .into(),
+        ..Default::default()
+    }
+}
+
+fn path_with_spot_color_impl(surface: &mut Surface) {
+    surface.set_fill(Some(pantone_fill(1.0)));
+    surface.draw_path(&rect_to_path(20.0, 20.0, 100.0, 180.0));
+    surface.set_fill(Some(pantone_fill(0.5)));
+    surface.set_stroke(Some(Stroke {
+        paint: SpotColor::new(
+            "PANTONE 185 C".to_string(),
+            cmyk::Color::new(0, 232, 194, 0),
+            NormalizedF32::ONE,
+        )
+        .into(),
+        width: 5.0,
+        ..Default::default()
+    }));
+    surface.draw_path(&rect_to_path(100.0, 20.0, 180.0, 180.0));
+}
+
+#[test]
+fn path_with_spot_color() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    path_with_spot_color_impl(&mut surface);
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Separation"), 1);
+    assert!(pdf_contains(&pdf, b"/FunctionType 2"));
+    assert!(pdf_contains(&pdf, b"0.5 scn"));
+    assert!(pdf_contains(&pdf, b"1 SCN"));
+}
+
+#[test]
+fn path_with_spot_color_conflicting_alternates() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    surface.set_fill(Some(pantone_fill(1.0)));
+    surface.draw_path(&rect_to_path(20.0, 20.0, 100.0, 180.0));
+    surface.set_fill(Some(Fill {
+        paint: SpotColor::new(
+            "PANTONE 185 C".to_string(),
+            rgb::Color::new(228, 0, 43),
+            NormalizedF32::ONE,
+        )
+        .into(),
+        ..Default::default()
+    }));
+    surface.draw_path(&rect_to_path(100.0, 20.0, 180.0, 180.0));
+    surface.finish();
+    page.finish();
+
+    assert_eq!(
+        d.finish(),
+        Err(KrillaError::ConflictingSpotColor(
+            "PANTONE 185 C".to_string()
+        ))
+    );
+}
+
+fn duotone() -> DeviceN {
+    DeviceN::new(
+        vec!["PANTONE 185 C".to_string(), "Black".to_string()],
+        2,
+        |tints| {
+            [
+                0.0,
+                tints[0] * 232.0 / 255.0,
+                tints[0] * 194.0 / 255.0,
+                tints[1],
+            ]
+        },
+    )
+    .unwrap()
+}
+
+fn duotone_fill(device_n: &DeviceN, tint1: f32, tint2: f32) -> Fill {
+    Fill {
+        paint: device_n
+            .color(vec![
+                NormalizedF32::new(tint1).unwrap(),
+                NormalizedF32::new(tint2).unwrap(),
+            ])
+            .into(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn path_with_device_n_color() {
+    let device_n = duotone();
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page_with(PageSettings::new(200.0, 200.0));
+    let mut surface = page.surface();
+    surface.set_fill(Some(duotone_fill(&device_n, 1.0, 0.0)));
+    surface.draw_path(&rect_to_path(20.0, 20.0, 100.0, 180.0));
+    surface.set_fill(Some(duotone_fill(&device_n, 0.5, 0.5)));
+    surface.draw_path(&rect_to_path(100.0, 20.0, 180.0, 180.0));
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    // Both colors share the same color space.
+    assert_eq!(pdf_count(&pdf, b"/DeviceN"), 1);
+    assert_eq!(pdf_count(&pdf, b"/FunctionType 0"), 1);
+    assert_eq!(pdf_count(&pdf, b"1 0 scn"), 1);
+    assert_eq!(pdf_count(&pdf, b"0.5 0.5 scn"), 1);
+}
+
+#[test]
+fn path_with_device_n_color_entries() {
+    let device_n = duotone();
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    surface.set_fill(Some(duotone_fill(&device_n, 1.0, 0.0)));
+    surface.draw_path(&rect_to_path(20.0, 20.0, 100.0, 180.0));
+    surface.set_fill(Some(pantone_fill(0.5)));
+    surface.draw_path(&rect_to_path(100.0, 20.0, 180.0, 180.0));
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/DeviceN"), 1);
+    assert!(pdf_contains(&pdf, b"/FunctionType 0"));
+    assert!(pdf_contains(&pdf, b"/Size [2 2]"));
+    assert!(pdf_contains(&pdf, b"1 0 scn"));
+}
+
+#[test]
+fn path_with_device_n_color_conflicting_spot_color() {
+    let device_n = DeviceN::new(vec!["PANTONE 185 C".to_string()], 2, |tints| {
+        [0.0, tints[0], tints[0], 0.0]
+    })
+    .unwrap();
+
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    surface.set_fill(Some(pantone_fill(1.0)));
+    surface.draw_path(&rect_to_path(20.0, 20.0, 100.0, 180.0));
+    surface.set_fill(Some(Fill {
+        paint: device_n.color(vec![NormalizedF32::ONE]).into(),
+        ..Default::default()
+    }));
+    surface.draw_path(&rect_to_path(100.0, 20.0, 180.0, 180.0));
+    surface.finish();
+    page.finish();
+
+    assert_eq!(
+        d.finish(),
+        Err(KrillaError::ConflictingSpotColor(
+            "PANTONE 185 C".to_string()
+        ))
+    );
+}
+
+#[test]
+fn device_n_invalid() {
+    assert!(DeviceN::new(vec![], 2, |_| [0.0; 4]).is_none());
+    assert!(DeviceN::new(vec!["Black".to_string()], 1, |_| [0.0; 4]).is_none());
+    assert!(
+        DeviceN::new(vec!["Black".to_string(), "Black".to_string()], 2, |_| [0.0;
+            4])
+        .is_none()
+    );
+}
+
+fn lab_document_impl(document: &mut Document) {
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    surface.set_fill(Some(Fill {
+        paint: lab::Color::new(54.0, 80.0, 67.0).into(),
+        ..Default::default()
+    }));
+    surface.draw_path(&rect_to_path(20.0, 20.0, 100.0, 180.0));
+    surface.set_fill(Some(Fill {
+        paint: lab::Color::new(54.0, 80.0, 67.0)
+            .with_white_point(WhitePoint::D65)
+            .into(),
+        ..Default::default()
+    }));
+    surface.draw_path(&rect_to_path(100.0, 20.0, 180.0, 180.0));
+    surface.finish();
+    page.finish();
+}
+
+#[test]
+fn path_with_lab() {
+    let mut d = Document::new_with(settings_1());
+    lab_document_impl(&mut d);
+
+    let pdf = d.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Lab"), 2);
+    assert!(pdf_contains(&pdf, b"/WhitePoint [0.9642 1 0.8249]"));
+    assert!(pdf_contains(&pdf, b"/WhitePoint [0.9505 1 1.0888]"));
+    assert!(pdf_contains(&pdf, b"54 80 67 scn"));
+}
+
+#[test]
+fn path_with_lab_pdf_a() {
+    let mut d = Document::new_with(settings_7());
+    lab_document_impl(&mut d);
+
+    assert!(d.finish().is_ok());
+}
+
+#[test]
+fn path_with_lab_clamped() {
+    assert_eq!(
+        lab::Color::new(120.0, -200.0, f32::NAN),
+        lab::Color::new(100.0, -128.0, 0.0)
+    );
+    assert!(WhitePoint::new(0.0, 1.0).is_none());
+}
+
+#[test]
+fn path_with_lab_gradient() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    let gradient = LinearGradient {
+        x1: 50.0,
+        y1: 0.0,
+        x2: 150.0,
+        y2: 0.0,
+        transform: Default::default(),
+        spread_method: SpreadMethod::Pad,
+        stops: vec![
+            Stop {
+                offset: NormalizedF32::ZERO,
+                color: lab::Color::new(54.0, 80.0, 67.0).into(),
+                opacity: NormalizedF32::ONE,
+            },
+            Stop {
+                offset: NormalizedF32::ONE,
+                color: lab::Color::new(30.0, 68.0, -112.0).into(),
+                opacity: NormalizedF32::ONE,
+            },
+        ],
+        anti_alias: false,
+    };
+    surface.set_fill(Some(Fill {
+        paint: gradient.into(),
+        ..Default::default()
+    }));
+    surface.draw_path(&rect_to_path(20.0, 20.0, 180.0, 180.0));
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/Lab"));
+    assert!(pdf_contains(&pdf, b"/Range [0 100 -128 127 -128 127]"));
+    assert!(pdf_contains(&pdf, b"/C0 [54 80 67]"));
+    assert!(pdf_contains(&pdf, b"/C1 [30 68 -112]"));
+}
+
+#[test]
+fn color_conversion() {
+    assert_eq!(cmyk::Color::new(0, 0, 0, 255).to_rgb(), rgb::Color::black());
+    assert_eq!(cmyk::Color::new(0, 0, 0, 0).to_rgb(), rgb::Color::white());
+    assert_eq!(
+        cmyk::Color::new(0, 255, 255, 0).to_rgb(),
+        rgb::Color::new(255, 0, 0)
+    );
+    assert_eq!(
+        rgb::Color::black().to_cmyk(),
+        cmyk::Color::new(0, 0, 0, 255)
+    );
+    assert_eq!(rgb::Color::white().to_cmyk(), cmyk::Color::new(0, 0, 0, 0));
+    assert_eq!(
+        rgb::Color::new(255, 0, 0).to_cmyk(),
+        cmyk::Color::new(0, 255, 255, 0)
+    );
+}
+
+fn dashed_stroke(array: Vec<f32>, offset: f32) -> Stroke {
+    Stroke {
+        width: 4.0,
+        dash: Some(StrokeDash { array, offset }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn stroke_dash_normalized() {
+    let dash = dashed_stroke(vec![4.0, 2.0, 1.0], 15.0)
+        .dash_pattern()
+        .unwrap();
+    assert_eq!(dash.array, vec![4.0, 2.0, 1.0, 4.0, 2.0, 1.0]);
+    assert_eq!(dash.offset, 1.0);
+
+    let dash = dashed_stroke(vec![4.0, 2.0], -1.0).dash_pattern().unwrap();
+    assert_eq!(dash.array, vec![4.0, 2.0]);
+    assert_eq!(dash.offset, 5.0);
+
+    // An empty dash array results in a solid stroke.
+    assert!(dashed_stroke(vec![], 0.0).dash_pattern().is_none());
+    assert!(dashed_stroke(vec![0.0, 0.0], 0.0).dash_pattern().is_none());
+    assert!(Stroke::default().dash_pattern().is_none());
+}
+
+#[test]
+fn stroke_dash_invalid() {
+    assert!(StrokeDash::new(vec![0.0, 2.0], 1.0).is_some());
+    assert!(StrokeDash::new(vec![], 0.0).is_some());
+    assert!(StrokeDash::new(vec![0.0, 0.0], 0.0).is_none());
+    assert!(StrokeDash::new(vec![3.0, -1.0], 0.0).is_none());
+    assert!(StrokeDash::new(vec![3.0, f32::NAN], 0.0).is_none());
+    assert!(StrokeDash::new(vec![3.0, 1.0], f32::INFINITY).is_none());
+}
+
+#[test]
+fn stroke_dash_entries() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    surface.set_stroke(Some(dashed_stroke(vec![4.0, 2.0, 1.0], 15.0)));
+    surface.draw_path(&rect_to_path(20.0, 20.0, 180.0, 180.0));
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"[4 2 1 4 2 1] 1 d"));
+}
+
+#[test]
+fn stroke_dash_all_zero() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    surface.set_location(7);
+    surface.set_stroke(Some(dashed_stroke(vec![0.0, 0.0], 0.0)));
+    surface.draw_path(&rect_to_path(20.0, 20.0, 180.0, 180.0));
+    surface.reset_location();
+    surface.finish();
+    page.finish();
+
+    assert_eq!(d.finish(), Err(KrillaError::InvalidStrokeDash(Some(7))));
+}
+
+fn path_shapes_impl(surface: &mut Surface) {
+    let mut pb = PathBuilder::new();
+    pb.push_circle(50.0, 50.0, 30.0);
+    pb.push_ellipse(140.0, 50.0, 45.0, 25.0);
+    pb.push_rounded_rect(
+        Rect::from_xywh(15.0, 110.0, 80.0, 70.0).unwrap(),
+        CornerRadii {
+            top_left: 30.0,
+            top_right: 5.0,
+            bottom_right: 15.0,
+            bottom_left: 0.0,
+        },
+    );
+    pb.move_to(110.0, 150.0);
+    pb.arc_to(30.0, 20.0, 30.0, true, false, 170.0, 150.0);
+    pb.close();
+    let path = pb.finish().unwrap();
+
+    surface.set_fill(Some(red_fill(1.0)));
+    surface.draw_path(&path);
+}
+
+#[visreg(all)]
+fn path_shapes(surface: &mut Surface) {
+    path_shapes_impl(surface);
+}
+
+#[test]
+fn path_shapes_entries() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    path_shapes_impl(&mut surface);
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    for start in ["80 50 m", "185 50 m", "45 110 m", "110 150 m"] {
+        assert!(pdf_contains(&pdf, start.as_bytes()), "{start}");
+    }
+    // Four curves each for the circle and the ellipse, three for the rounded corners
+    // and two for the half ellipse of the arc.
+    assert_eq!(pdf_count(&pdf, b" c\n"), 13);
+    assert_eq!(pdf_count(&pdf, b"\nh\n"), 4);
+}
@@ -1,15 +1,54 @@
 mod shading {
+    use krilla::color::rgb;
+    use krilla::geom::Point;
     use krilla::num::NormalizedF32;
-    use krilla::page::Page;
-    use krilla::paint::{Fill, LinearGradient, RadialGradient, SpreadMethod, SweepGradient};
+    use krilla::page::{Page, PageSettings};
+    use krilla::paint::{
+        Fill, LinearGradient, MeshGradient, MeshPatch, RadialGradient, SpreadMethod, SweepGradient,
+    };
     use krilla::surface::Surface;
+    use krilla::{Document, SerializeSettings};
     use krilla_macros::{snapshot, visreg};
 
     use crate::{
-        rect_to_path, stops_with_1_solid, stops_with_2_solid_1, stops_with_3_luma,
-        stops_with_3_solid_1,
+        pdf_contains, pdf_count, rect_to_path, settings_1, settings_7, stops_with_1_solid,
+        stops_with_2_solid_1, stops_with_3_luma, stops_with_3_solid_1,
     };
 
+    fn mesh_grid() -> MeshGradient {
+        let colors = [
+            [(255, 0, 0), (255, 255, 0), (0, 255, 0)],
+            [(255, 0, 255), (255, 255, 255), (0, 255, 255)],
+            [(0, 0, 255), (0, 0, 0), (0, 128, 255)],
+        ];
+
+        let rows = colors
+            .iter()
+            .enumerate()
+            .map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(x, &(r, g, b))| {
+                        (
+                            Point::from_xy(20.0 + x as f32 * 80.0, 20.0 + y as f32 * 80.0),
+                            rgb::Color::new(r, g, b).into(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+
+        MeshGradient::from_grid(rows).unwrap()
+    }
+
+    fn mesh_fill(mesh: MeshGradient) -> Fill {
+        Fill {
+            paint: mesh.into(),
+            opacity: NormalizedF32::ONE,
+            rule: Default::default(),
+        }
+    }
+
     #[visreg(all)]
     fn pattern_linear_gradient_pad(surface: &mut Surface) {
         let path = rect_to_path(20.0, 20.0, 180.0, 180.0);
@@ -122,6 +161,127 @@ mod shading {
         surface.draw_path(&path);
     }
 
+    fn spread_radial_gradient(spread_method: SpreadMethod) -> RadialGradient {
+        RadialGradient {
+            cx: 100.0,
+            cy: 100.0,
+            cr: 30.0,
+            fx: 110.0,
+            fy: 100.0,
+            fr: 0.0,
+            transform: Default::default(),
+            spread_method,
+            stops: stops_with_3_solid_1(),
+            anti_alias: false,
+        }
+    }
+
+    fn pattern_radial_gradient_spread_impl(surface: &mut Surface, gradient: RadialGradient) {
+        surface.set_fill(Some(Fill {
+            paint: gradient.into(),
+            opacity: NormalizedF32::ONE,
+            rule: Default::default(),
+        }));
+        surface.draw_path(&rect_to_path(20.0, 20.0, 180.0, 180.0));
+    }
+
+    fn shrinking_radial_gradient() -> RadialGradient {
+        let mut gradient = spread_radial_gradient(SpreadMethod::Reflect);
+        gradient.fr = 40.0;
+        gradient.cr = 10.0;
+        gradient
+    }
+
+    fn pattern_radial_gradient_spread_document(
+        settings: SerializeSettings,
+        gradient: RadialGradient,
+    ) -> Vec<u8> {
+        let mut document = Document::new_with(settings);
+        let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+        let mut surface = page.surface();
+        pattern_radial_gradient_spread_impl(&mut surface, gradient);
+        surface.finish();
+        page.finish();
+
+        document.finish().unwrap()
+    }
+
+    #[visreg(all)]
+    fn pattern_radial_gradient_repeat(surface: &mut Surface) {
+        pattern_radial_gradient_spread_impl(surface, spread_radial_gradient(SpreadMethod::Repeat));
+    }
+
+    #[visreg(all)]
+    fn pattern_radial_gradient_reflect(surface: &mut Surface) {
+        pattern_radial_gradient_spread_impl(surface, spread_radial_gradient(SpreadMethod::Reflect));
+    }
+
+    // The start circle is larger than the end circle.
+    #[visreg(all)]
+    fn pattern_radial_gradient_reflect_shrinking(surface: &mut Surface) {
+        pattern_radial_gradient_spread_impl(surface, shrinking_radial_gradient());
+    }
+
+    #[test]
+    fn pattern_radial_gradient_reflect_entries() {
+        let pdf = pattern_radial_gradient_spread_document(
+            settings_1(),
+            spread_radial_gradient(SpreadMethod::Reflect),
+        );
+        // The start circle has a radius of zero, so the stops are only spread outwards.
+        assert!(pdf_contains(&pdf, b"/Domain [0 "));
+        // Every second repetition of the stops is reversed.
+        assert!(pdf_contains(&pdf, b"/Encode [0 1 1 0"));
+    }
+
+    #[test]
+    fn pattern_radial_gradient_reflect_shrinking_entries() {
+        let pdf =
+            pattern_radial_gradient_spread_document(settings_1(), shrinking_radial_gradient());
+        assert!(pdf_contains(&pdf, b"/ShadingType 3"));
+        // The stops are spread inwards, towards the point where the radius becomes zero.
+        assert!(pdf_contains(&pdf, b"/Domain [-"));
+        assert!(pdf_contains(&pdf, b"/FunctionType 3"));
+    }
+
+    #[test]
+    fn pattern_radial_gradient_repeat_entries() {
+        // Unlike PostScript functions, stitching functions are allowed in PDF/A.
+        let pdf = pattern_radial_gradient_spread_document(
+            settings_7(),
+            spread_radial_gradient(SpreadMethod::Repeat),
+        );
+        assert!(pdf_contains(&pdf, b"/ShadingType 3"));
+        assert!(pdf_contains(&pdf, b"/Domain [0 "));
+        assert!(pdf_contains(&pdf, b"/FunctionType 3"));
+        assert!(!pdf_contains(&pdf, b"/FunctionType 4"));
+    }
+
+    #[test]
+    fn pattern_radial_gradient_deduplicated() {
+        // Shadings are deduplicated based on their content, independently of images.
+        let settings = SerializeSettings {
+            deduplicate_images: false,
+            ..settings_1()
+        };
+        let mut document = Document::new_with(settings);
+
+        for _ in 0..2 {
+            let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+            let mut surface = page.surface();
+            pattern_radial_gradient_spread_impl(
+                &mut surface,
+                spread_radial_gradient(SpreadMethod::Pad),
+            );
+            surface.finish();
+            page.finish();
+        }
+
+        let pdf = document.finish().unwrap();
+        assert_eq!(pdf_count(&pdf, b"/PatternType 2"), 1);
+        assert_eq!(pdf_count(&pdf, b"/ShadingType 3"), 1);
+    }
+
     // Should be turned into a solid color.
     #[snapshot]
     fn pattern_gradient_single_stop(page: &mut Page) {
@@ -174,15 +334,173 @@ mod shading {
         }));
         surface.draw_path(&path);
     }
+
+    fn curved_mesh() -> MeshGradient {
+        let mut patch = MeshPatch::new(
+            [
+                Point::from_xy(20.0, 20.0),
+                Point::from_xy(20.0, 180.0),
+                Point::from_xy(180.0, 180.0),
+                Point::from_xy(180.0, 20.0),
+            ],
+            [
+                rgb::Color::new(255, 0, 0).into(),
+                rgb::Color::new(0, 255, 0).into(),
+                rgb::Color::new(0, 0, 255).into(),
+                rgb::Color::new(255, 255, 0).into(),
+            ],
+        );
+        patch.control_points[0] = [Point::from_xy(60.0, 60.0), Point::from_xy(-20.0, 120.0)];
+        patch.control_points[2] = [Point::from_xy(140.0, 120.0), Point::from_xy(220.0, 60.0)];
+        patch.opacities[2] = NormalizedF32::new(0.3).unwrap();
+
+        MeshGradient {
+            patches: vec![patch],
+            transform: Default::default(),
+            anti_alias: true,
+        }
+    }
+
+    // Two coincident corners turn the patch into a triangle.
+    fn triangle_mesh() -> MeshGradient {
+        let top = Point::from_xy(100.0, 20.0);
+        let patch = MeshPatch::new(
+            [
+                top,
+                Point::from_xy(20.0, 180.0),
+                Point::from_xy(180.0, 180.0),
+                top,
+            ],
+            [
+                rgb::Color::new(255, 0, 0).into(),
+                rgb::Color::new(0, 255, 0).into(),
+                rgb::Color::new(0, 0, 255).into(),
+                rgb::Color::new(255, 0, 0).into(),
+            ],
+        );
+
+        MeshGradient {
+            patches: vec![patch],
+            transform: Default::default(),
+            anti_alias: false,
+        }
+    }
+
+    fn pattern_mesh_gradient_impl(surface: &mut Surface, mesh: MeshGradient) {
+        surface.set_fill(Some(mesh_fill(mesh)));
+        surface.draw_path(&rect_to_path(0.0, 0.0, 200.0, 200.0));
+    }
+
+    fn pattern_mesh_gradient_document(mesh: MeshGradient) -> Vec<u8> {
+        let mut document = Document::new_with(settings_1());
+        let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+        let mut surface = page.surface();
+        pattern_mesh_gradient_impl(&mut surface, mesh);
+        surface.finish();
+        page.finish();
+
+        document.finish().unwrap()
+    }
+
+    #[visreg(all)]
+    fn pattern_mesh_gradient_grid(surface: &mut Surface) {
+        pattern_mesh_gradient_impl(surface, mesh_grid());
+    }
+
+    #[visreg(all)]
+    fn pattern_mesh_gradient_curved(surface: &mut Surface) {
+        pattern_mesh_gradient_impl(surface, curved_mesh());
+    }
+
+    #[visreg(all)]
+    fn pattern_mesh_gradient_triangle(surface: &mut Surface) {
+        pattern_mesh_gradient_impl(surface, triangle_mesh());
+    }
+
+    #[test]
+    fn pattern_mesh_gradient_curved_entries() {
+        let pdf = pattern_mesh_gradient_document(curved_mesh());
+        // The opacities are drawn with a second mesh in a soft mask.
+        assert_eq!(pdf_count(&pdf, b"/ShadingType 6"), 2);
+        assert!(pdf_contains(&pdf, b"/SMask"));
+        // The control points extend beyond the corners.
+        assert!(pdf_contains(&pdf, b"/Decode [-20 220 20 180 0 1 0 1 0 1]"));
+        assert!(pdf_contains(&pdf, b"/Decode [-20 220 20 180 0 1]"));
+        assert!(pdf_contains(&pdf, b"/AntiAlias true"));
+    }
+
+    #[test]
+    fn pattern_mesh_gradient_triangle_entries() {
+        let pdf = pattern_mesh_gradient_document(triangle_mesh());
+        assert_eq!(pdf_count(&pdf, b"/ShadingType 6"), 1);
+        assert!(!pdf_contains(&pdf, b"/SMask"));
+        assert!(pdf_contains(&pdf, b"/Decode [20 180 20 180 0 1 0 1 0 1]"));
+        assert!(pdf_contains(&pdf, b"/AntiAlias false"));
+    }
+
+    #[test]
+    fn pattern_mesh_gradient_grid_entries() {
+        let mut mesh = mesh_grid();
+        // Patches that don't cover any area are skipped.
+        let point = Point::from_xy(50.0, 50.0);
+        mesh.patches
+            .push(MeshPatch::new([point; 4], [rgb::Color::black().into(); 4]));
+
+        let pdf = pattern_mesh_gradient_document(mesh);
+        assert!(pdf_contains(&pdf, b"/ShadingType 6"));
+        assert!(pdf_contains(&pdf, b"/BitsPerCoordinate 32"));
+        assert!(pdf_contains(&pdf, b"/BitsPerComponent 16"));
+        assert!(pdf_contains(&pdf, b"/BitsPerFlag 8"));
+        assert!(pdf_contains(&pdf, b"/Decode [20 180 20 180 0 1 0 1 0 1]"));
+    }
+
+    #[test]
+    fn pattern_mesh_gradient_invalid_grid() {
+        let corner = (Point::from_xy(0.0, 0.0), rgb::Color::black().into());
+
+        assert!(MeshGradient::from_grid(vec![]).is_none());
+        assert!(MeshGradient::from_grid(vec![vec![corner; 2]]).is_none());
+        assert!(MeshGradient::from_grid(vec![vec![corner; 1]; 2]).is_none());
+        assert!(MeshGradient::from_grid(vec![vec![corner; 2], vec![corner; 3]]).is_none());
+        assert_eq!(
+            MeshGradient::from_grid(vec![vec![corner; 3]; 3])
+                .unwrap()
+                .patches
+                .len(),
+            4
+        );
+    }
 }
 
 mod tiling {
+    use krilla::color::rgb;
+    use krilla::geom::{Size, Transform};
     use krilla::num::NormalizedF32;
-    use krilla::paint::{Fill, Pattern};
+    use krilla::page::PageSettings;
+    use krilla::paint::{Fill, Pattern, Stroke, TilingPattern};
     use krilla::surface::Surface;
+    use krilla::{Document, SerializeSettings};
     use krilla_macros::visreg;
 
-    use crate::{basic_pattern_stream, rect_to_path};
+    use crate::{
+        basic_pattern_stream, green_fill, pdf_contains, pdf_count, rect_to_path, red_fill,
+        settings_1,
+    };
+
+    fn checkerboard(surface: &mut Surface) {
+        let path = rect_to_path(0.0, 0.0, 10.0, 10.0);
+
+        surface.set_fill(Some(red_fill(1.0)));
+        surface.draw_path(&path);
+        surface.push_transform(&Transform::from_translate(10.0, 10.0));
+        surface.set_fill(Some(green_fill(1.0)));
+        surface.draw_path(&path);
+        surface.pop();
+    }
+
+    fn tile_size() -> Size {
+        Size::from_wh(20.0, 20.0).unwrap()
+    }
 
     #[visreg(all)]
     fn pattern_tiling_basic(surface: &mut Surface) {
@@ -204,4 +522,188 @@ mod tiling {
         }));
         surface.draw_path(&path)
     }
+
+    #[visreg(all)]
+    fn pattern_tiling_surface_spacing(surface: &mut Surface) {
+        let path = rect_to_path(20.0, 20.0, 180.0, 180.0);
+        let pattern = surface.create_tiling_pattern(
+            tile_size(),
+            5.0,
+            10.0,
+            Transform::identity(),
+            checkerboard,
+        );
+
+        surface.set_fill(Some(Fill {
+            paint: pattern.into(),
+            opacity: NormalizedF32::ONE,
+            rule: Default::default(),
+        }));
+        surface.draw_path(&path)
+    }
+
+    fn pattern_tiling_surface_stroke_impl(surface: &mut Surface) {
+        let path = rect_to_path(40.0, 40.0, 160.0, 160.0);
+        let pattern = surface.create_tiling_pattern(
+            tile_size(),
+            0.0,
+            0.0,
+            Transform::from_rotate(45.0),
+            checkerboard,
+        );
+
+        surface.set_fill(None);
+        surface.set_stroke(Some(Stroke {
+            paint: pattern.into(),
+            width: 20.0,
+            ..Default::default()
+        }));
+        surface.draw_path(&path)
+    }
+
+    #[visreg(all)]
+    fn pattern_tiling_surface_stroke(surface: &mut Surface) {
+        pattern_tiling_surface_stroke_impl(surface);
+    }
+
+    #[test]
+    fn pattern_tiling_surface_stroke_entries() {
+        let mut document = Document::new_with(settings_1());
+        let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+        let mut surface = page.surface();
+        pattern_tiling_surface_stroke_impl(&mut surface);
+        surface.finish();
+        page.finish();
+
+        let pdf = document.finish().unwrap();
+        assert!(pdf_contains(&pdf, b"/PaintType 1"));
+        assert!(pdf_contains(&pdf, b"/BBox [0 0 20 20]"));
+        assert!(pdf_contains(&pdf, b"/XStep 20"));
+        // The pattern is used for stroking, not for filling.
+        assert!(pdf_contains(&pdf, b"/Pattern CS"));
+        assert!(pdf_contains(&pdf, b"/p0 SCN"));
+        assert!(!pdf_contains(&pdf, b"/Pattern cs"));
+    }
+
+    #[visreg(all)]
+    fn pattern_tiling_uncolored(surface: &mut Surface) {
+        let path = rect_to_path(20.0, 20.0, 180.0, 180.0);
+        let pattern = surface
+            .create_uncolored_tiling_pattern(
+                tile_size(),
+                0.0,
+                0.0,
+                Transform::identity(),
+                checkerboard,
+            )
+            .with_color(rgb::Color::new(0, 0, 255));
+
+        surface.set_fill(Some(Fill {
+            paint: pattern.into(),
+            opacity: NormalizedF32::ONE,
+            rule: Default::default(),
+        }));
+        surface.draw_path(&path)
+    }
+
+    #[test]
+    fn pattern_tiling_uncolored_entries() {
+        let mut document = Document::new_with(settings_1());
+        let pattern = document.create_uncolored_tiling_pattern(
+            tile_size(),
+            0.0,
+            0.0,
+            Transform::identity(),
+            checkerboard,
+        );
+        assert!(pattern.is_uncolored());
+
+        let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+        let mut surface = page.surface();
+        surface.set_fill(Some(Fill {
+            paint: pattern.with_color(rgb::Color::new(0, 0, 255)).into(),
+            opacity: NormalizedF32::ONE,
+            rule: Default::default(),
+        }));
+        surface.draw_path(&rect_to_path(20.0, 20.0, 180.0, 180.0));
+        surface.finish();
+        page.finish();
+
+        let pdf = document.finish().unwrap();
+        assert!(pdf_contains(&pdf, b"/PaintType 2"));
+        assert!(pdf_contains(&pdf, b"[/Pattern /DeviceRGB]"));
+        assert!(pdf_contains(&pdf, b"0 0 1 /p0 scn"));
+        // The tile itself must not set any colors.
+        assert!(!pdf_contains(&pdf, b"1 0 0 rg"));
+        assert!(!pdf_contains(&pdf, b"0 1 0 rg"));
+    }
+
+    #[test]
+    fn pattern_tiling_cached() {
+        let mut document = Document::new_with(settings_1());
+        let pattern: TilingPattern = document.create_tiling_pattern(
+            tile_size(),
+            5.0,
+            10.0,
+            Transform::identity(),
+            checkerboard,
+        );
+
+        let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+        let mut surface = page.surface();
+        surface.set_fill(Some(Fill {
+            paint: pattern.clone().into(),
+            opacity: NormalizedF32::ONE,
+            rule: Default::default(),
+        }));
+        surface.draw_path(&rect_to_path(20.0, 20.0, 100.0, 100.0));
+        surface.set_fill(None);
+        surface.set_stroke(Some(Stroke {
+            paint: pattern.into(),
+            width: 10.0,
+            ..Default::default()
+        }));
+        surface.draw_path(&rect_to_path(120.0, 120.0, 180.0, 180.0));
+        surface.finish();
+        page.finish();
+
+        let pdf = document.finish().unwrap();
+        assert_eq!(pdf_count(&pdf, b"/PatternType 1"), 1);
+        assert!(pdf_contains(&pdf, b"/PaintType 1"));
+        assert!(pdf_contains(&pdf, b"/XStep 25"));
+        assert!(pdf_contains(&pdf, b"/YStep 30"));
+    }
+
+    #[test]
+    fn pattern_tiling_deduplicated() {
+        // Patterns are deduplicated based on their content, independently of images.
+        let settings = SerializeSettings {
+            deduplicate_images: false,
+            ..settings_1()
+        };
+        let mut document = Document::new_with(settings);
+
+        for _ in 0..2 {
+            let mut page = document.start_page_with(PageSettings::new(200.0, 200.0));
+            let mut surface = page.surface();
+            let pattern = surface.create_tiling_pattern(
+                tile_size(),
+                0.0,
+                0.0,
+                Transform::identity(),
+                checkerboard,
+            );
+            surface.set_fill(Some(Fill {
+                paint: pattern.into(),
+                opacity: NormalizedF32::ONE,
+                rule: Default::default(),
+            }));
+            surface.draw_path(&rect_to_path(20.0, 20.0, 180.0, 180.0));
+            surface.finish();
+            page.finish();
+        }
+
+        let pdf = document.finish().unwrap();
+        assert_eq!(pdf_count(&pdf, b"/PatternType 1"), 1);
+    }
 }
@@ -1,9 +1,11 @@
 use krilla::error::KrillaError;
 use krilla::geom::Size;
+use krilla::image::Image;
 use krilla::page::Page;
 use krilla::Document;
 use krilla_macros::snapshot;
 
+use crate::image::jp2_data;
 use crate::metadata::metadata_impl;
 use crate::text::simple_text_impl;
 use crate::{load_png_image, rect_to_path, red_fill, settings_16, NOTO_SANS};
@@ -61,3 +63,20 @@ fn pdf_14_no_sixteen_bit_images() {
         Err(KrillaError::SixteenBitImage(image.clone(), None))
     );
 }
+
+#[test]
+fn pdf_14_no_jpeg2000_images() {
+    let mut document = Document::new_with(settings_16());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    let image = Image::from_jpeg2000(jp2_data(30, 20, 3).into(), false).unwrap();
+    surface.draw_image(image.clone(), Size::from_wh(30.0, 20.0).unwrap());
+
+    surface.finish();
+    page.finish();
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::Jpeg2000Image(image.clone(), None))
+    );
+}
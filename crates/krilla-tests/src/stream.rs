@@ -1,10 +1,16 @@
-use krilla::blend::BlendMode;
-use krilla::geom::{Size, Transform};
+use krilla::blend::{BlendMode, GroupColorSpace, OverprintMode};
+use krilla::color::RenderingIntent;
+use krilla::configure::{Configuration, PdfVersion};
+use krilla::geom::{Point, Size, Transform};
+use krilla::num::NormalizedF32;
 use krilla::page::Page;
+use krilla::surface::Surface;
+use krilla::Document;
+use krilla::SerializeSettings;
 use krilla_macros::snapshot;
 
-use crate::{blue_fill, load_png_image, red_fill};
-use crate::{green_fill, rect_to_path};
+use crate::{blue_fill, load_png_image, red_fill, settings_1, settings_17, settings_25};
+use crate::{green_fill, pdf_contains, pdf_count, rect_to_path};
 
 #[snapshot(settings_2)]
 fn stream_resource_cache(page: &mut Page) {
@@ -63,3 +69,313 @@ fn stream_compress_by_default(page: &mut Page) {
     let size = Size::from_wh(image.size().0 as f32, image.size().1 as f32).unwrap();
     surface.draw_image(image, size);
 }
+
+fn stream_rendering_intent_impl(surface: &mut Surface) {
+    let path1 = rect_to_path(0.0, 0.0, 100.0, 100.0);
+    let path2 = rect_to_path(50.0, 50.0, 150.0, 150.0);
+    surface.push_rendering_intent(RenderingIntent::Perceptual);
+    surface.set_fill(Some(green_fill(1.0)));
+    surface.draw_path(&path1);
+    surface.push_rendering_intent(RenderingIntent::Saturation);
+    surface.set_fill(Some(red_fill(1.0)));
+    surface.draw_path(&path2);
+    surface.pop();
+    surface.pop();
+    surface.set_fill(Some(blue_fill(1.0)));
+    surface.draw_path(&path1);
+}
+
+#[test]
+fn stream_rendering_intent() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    stream_rendering_intent_impl(&mut surface);
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/RI /Perceptual"), 1);
+    assert_eq!(pdf_count(&pdf, b"/RI /Saturation"), 1);
+    // The last path is drawn without a rendering intent, so it doesn't need a graphics state.
+    assert_eq!(pdf_count(&pdf, b" gs"), 2);
+}
+
+fn stream_overprint_impl(surface: &mut Surface) {
+    let path1 = rect_to_path(0.0, 0.0, 100.0, 100.0);
+    let path2 = rect_to_path(50.0, 50.0, 150.0, 150.0);
+    surface.set_fill(Some(green_fill(1.0)));
+    surface.draw_path(&path1);
+    surface.push_overprint(true, false, OverprintMode::NonZero);
+    surface.push_blend_mode(BlendMode::Multiply);
+    surface.set_fill(Some(red_fill(1.0)));
+    surface.draw_path(&path2);
+    surface.pop();
+    surface.pop();
+}
+
+#[test]
+fn stream_overprint() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    stream_overprint_impl(&mut surface);
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/BM /Multiply"));
+    assert!(pdf_contains(&pdf, b"/OP false"));
+    assert!(pdf_contains(&pdf, b"/op true"));
+    assert!(pdf_contains(&pdf, b"/OPM 1"));
+}
+
+fn stream_transparency_group_impl(surface: &mut Surface) {
+    let path1 = rect_to_path(0.0, 0.0, 100.0, 100.0);
+    let path2 = rect_to_path(50.0, 50.0, 150.0, 150.0);
+    surface.set_fill(Some(green_fill(1.0)));
+    surface.draw_path(&path1);
+    surface.push_blend_mode(BlendMode::Multiply);
+    surface.push_transparency_group(true, true, GroupColorSpace::default());
+    surface.set_fill(Some(red_fill(0.5)));
+    surface.draw_path(&path1);
+    surface.set_fill(Some(blue_fill(0.5)));
+    surface.draw_path(&path2);
+    surface.pop();
+    surface.pop();
+}
+
+#[test]
+fn stream_transparency_group() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    stream_transparency_group_impl(&mut surface);
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/S /Transparency"));
+    assert!(pdf_contains(&pdf, b"/I true"));
+    assert!(pdf_contains(&pdf, b"/K true"));
+    assert!(pdf_contains(&pdf, b"/CS /DeviceRGB"));
+    assert!(pdf_contains(&pdf, b"/BM /Multiply"));
+}
+
+#[test]
+fn stream_transparency_group_nested() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    let path = rect_to_path(0.0, 0.0, 100.0, 100.0);
+    surface.push_transparency_group(false, true, GroupColorSpace::Luma);
+    surface.push_transparency_group(true, false, GroupColorSpace::Srgb);
+    surface.set_fill(Some(red_fill(0.5)));
+    surface.draw_path(&path);
+    surface.pop();
+    surface.set_fill(Some(blue_fill(0.5)));
+    surface.draw_path(&path);
+    surface.pop();
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/S /Transparency"), 2);
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Form"), 2);
+    assert!(pdf_contains(&pdf, b"/CS /DeviceGray"));
+    assert!(pdf_contains(&pdf, b"/CS /DeviceRGB"));
+    assert_eq!(pdf_count(&pdf, b"/K true"), 1);
+    assert_eq!(pdf_count(&pdf, b"/I true"), 1);
+}
+
+fn stream_group_opacity_impl(surface: &mut Surface) {
+    let path1 = rect_to_path(20.0, 20.0, 120.0, 120.0);
+    let path2 = rect_to_path(80.0, 80.0, 180.0, 180.0);
+    surface.push_opacity(NormalizedF32::new(0.5).unwrap());
+    surface.set_fill(Some(red_fill(1.0)));
+    surface.draw_path(&path1);
+    surface.set_fill(Some(blue_fill(1.0)));
+    surface.draw_path(&path2);
+    surface.pop();
+}
+
+#[test]
+fn stream_group_opacity() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    stream_group_opacity_impl(&mut surface);
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    // The opacity is applied once to the whole group instead of to each fill.
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Form"), 1);
+    assert!(pdf_contains(&pdf, b"/S /Transparency"));
+    assert_eq!(pdf_count(&pdf, b"/CA 0.5"), 1);
+    assert_eq!(pdf_count(&pdf, b"/ca 0.5"), 1);
+}
+
+#[test]
+fn stream_group_opacity_one() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    surface.push_opacity(NormalizedF32::ONE);
+    surface.set_fill(Some(red_fill(1.0)));
+    surface.draw_path(&rect_to_path(20.0, 20.0, 120.0, 120.0));
+    surface.pop();
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    // A fully opaque group doesn't need to be written at all.
+    assert!(!pdf_contains(&pdf, b"/Subtype /Form"));
+}
+
+#[test]
+fn stream_ctm_nested() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    surface.push_transform(&Transform::from_translate(10.0, 20.0));
+    surface.push_opacity(NormalizedF32::new(0.5).unwrap());
+    surface.push_transform(&Transform::from_scale(2.0, 3.0));
+    surface.push_isolated();
+    surface.push_transform(&Transform::from_rotate_at(90.0, 5.0, 5.0));
+
+    assert_eq!(
+        surface.ctm(),
+        Transform::from_translate(10.0, 20.0)
+            .pre_concat(Transform::from_scale(2.0, 3.0))
+            .pre_concat(Transform::from_rotate_at(90.0, 5.0, 5.0))
+    );
+
+    let point = surface.ctm().map_point(Point::from_xy(5.0, 5.0));
+    assert_eq!(point, Point::from_xy(20.0, 35.0));
+
+    for _ in 0..5 {
+        surface.pop();
+    }
+
+    assert_eq!(surface.ctm(), Transform::identity());
+    surface.finish();
+    page.finish();
+}
+
+const ALL_BLEND_MODES: [BlendMode; 16] = [
+    BlendMode::Normal,
+    BlendMode::Multiply,
+    BlendMode::Screen,
+    BlendMode::Overlay,
+    BlendMode::Darken,
+    BlendMode::Lighten,
+    BlendMode::ColorDodge,
+    BlendMode::ColorBurn,
+    BlendMode::HardLight,
+    BlendMode::SoftLight,
+    BlendMode::Difference,
+    BlendMode::Exclusion,
+    BlendMode::Hue,
+    BlendMode::Saturation,
+    BlendMode::Color,
+    BlendMode::Luminosity,
+];
+
+#[test]
+fn stream_all_blend_modes_pdf_14() {
+    let mut d = Document::new_with(settings_17());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+
+    for blend_mode in ALL_BLEND_MODES {
+        surface.push_blend_mode(blend_mode);
+        surface.set_fill(Some(red_fill(1.0)));
+        surface.draw_path(&rect_to_path(0.0, 0.0, 100.0, 100.0));
+        surface.pop();
+    }
+
+    surface.finish();
+    page.finish();
+
+    // Blend modes are part of the transparency model of PDF 1.4, so they can
+    // be used with all PDF versions.
+    let pdf = d.finish().unwrap();
+
+    for name in [
+        "Multiply",
+        "Screen",
+        "Overlay",
+        "Darken",
+        "Lighten",
+        "ColorDodge",
+        "ColorBurn",
+        "HardLight",
+        "SoftLight",
+        "Difference",
+        "Exclusion",
+        "Hue",
+        "Saturation",
+        "Color",
+        "Luminosity",
+    ] {
+        assert!(
+            pdf_contains(&pdf, format!("/BM /{name}").as_bytes()),
+            "{name}"
+        );
+    }
+
+    // The normal blend mode is the default, so it doesn't need to be written.
+    assert!(!pdf_contains(&pdf, b"/BM /Normal"));
+}
+
+fn stream_black_point_compensation_impl(settings: SerializeSettings) -> Vec<u8> {
+    let mut d = Document::new_with(settings);
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    let path = rect_to_path(0.0, 0.0, 100.0, 100.0);
+    surface.push_black_point_compensation(true);
+    surface.set_fill(Some(green_fill(1.0)));
+    surface.draw_path(&path);
+    surface.pop();
+    surface.finish();
+    page.finish();
+
+    d.finish().unwrap()
+}
+
+#[test]
+fn stream_black_point_compensation() {
+    let pdf = stream_black_point_compensation_impl(settings_25());
+    assert!(pdf_contains(&pdf, b"/UseBlackPtComp /ON"));
+}
+
+#[test]
+fn stream_black_point_compensation_pdf_17() {
+    let pdf = stream_black_point_compensation_impl(settings_1());
+    assert!(!pdf_contains(&pdf, b"/UseBlackPtComp"));
+}
+
+#[test]
+fn stream_black_point_compensation_global() {
+    let mut d = Document::new_with(SerializeSettings {
+        configuration: Configuration::new_with_version(PdfVersion::Pdf20)
+            .with_black_point_compensation(true)
+            .unwrap(),
+        ..settings_1()
+    });
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    let path = rect_to_path(0.0, 0.0, 100.0, 100.0);
+    surface.set_fill(Some(green_fill(1.0)));
+    surface.draw_path(&path);
+    surface.push_black_point_compensation(false);
+    surface.draw_path(&path);
+    surface.pop();
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/UseBlackPtComp /ON"));
+    assert!(pdf_contains(&pdf, b"/UseBlackPtComp /OFF"));
+}
@@ -2,10 +2,11 @@
 
 use krilla::geom::{Size, Transform};
 use krilla::surface::Surface;
+use krilla::Document;
 use krilla_macros::visreg;
 use krilla_svg::{SurfaceExt, SvgSettings};
 
-use crate::{FONTDB, SVGS_PATH};
+use crate::{pdf_contains, settings_1, FONTDB, SVGS_PATH};
 
 pub(crate) fn sample_svg() -> usvg::Tree {
     let data = std::fs::read(SVGS_PATH.join("resvg_masking_mask_with_opacity_1.svg")).unwrap();
@@ -87,4 +88,45 @@ fn svg_with_filter(surface: &mut Surface) {
     );
 }
 
+fn invisible_text_pdf(embed_text: bool) -> Vec<u8> {
+    let data = br#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="200">
+        <text x="20" y="100" font-family="Noto Sans" font-size="20" fill="none">Hidden</text>
+    </svg>"#;
+    let tree = usvg::Tree::from_data(
+        data,
+        &usvg::Options {
+            fontdb: FONTDB.clone(),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    let settings = SvgSettings {
+        embed_text,
+        ..Default::default()
+    };
+    surface.draw_svg(&tree, Size::from_wh(200.0, 200.0).unwrap(), settings);
+    surface.finish();
+    page.finish();
+
+    document.finish().unwrap()
+}
+
+#[test]
+fn svg_invisible_text() {
+    let pdf = invisible_text_pdf(true);
+    // The text is drawn with the invisible text rendering mode instead of a zero opacity.
+    assert!(pdf_contains(&pdf, b"3 Tr"));
+    assert!(!pdf_contains(&pdf, b"/ca 0"));
+    assert!(pdf_contains(&pdf, b"/FontFile2"));
+
+    // Outlined invisible text is not drawn at all.
+    let pdf = invisible_text_pdf(false);
+    assert!(!pdf_contains(&pdf, b"/FontFile2"));
+    assert!(!pdf_contains(&pdf, b"/Subtype /Type3"));
+}
+
 include!("svg_generated.rs");
@@ -6,13 +6,20 @@ use krilla::error::KrillaError;
 use krilla::geom::{Point, Rect, Size, Transform};
 use krilla::paint::Fill;
 use krilla::surface::Surface;
-use krilla::tagging::{ArtifactType, ContentTag, SpanTag, Tag, TagGroup, TagTree};
+use krilla::tagging::{
+    ArtifactType, BBox, ContentTag, LayoutAttributes, Placement, SpanTag, Tag, TagGroup, TagTree,
+    TextAlign,
+};
 use krilla::text::{Font, TextDirection};
 use krilla::Document;
 use krilla_macros::snapshot;
 use krilla_svg::{SurfaceExt, SvgSettings};
 
-use crate::{green_fill, load_png_image, rect_to_path, NOTO_SANS, SVGS_PATH};
+use crate::annotation::multi_line_quads;
+use crate::{
+    green_fill, load_png_image, pdf_contains, pdf_count, rect_to_path, settings_1, NOTO_SANS,
+    SVGS_PATH,
+};
 
 pub trait SurfaceTaggingExt {
     fn fill_text_(&mut self, y: f32, content: &str);
@@ -96,6 +103,37 @@ fn tagging_simple_with_link_impl(document: &mut Document) {
     document.set_tag_tree(tag_tree);
 }
 
+fn tagging_link_with_quads_impl(document: &mut Document) {
+    let mut tag_tree = TagTree::new();
+    let mut par = TagGroup::new(Tag::P);
+    let mut link = TagGroup::new(Tag::Link);
+
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    let id = surface.start_tagged(ContentTag::Span(SpanTag::empty()));
+    surface.fill_text_(25.0, "a paragraph");
+    surface.end_tagged();
+
+    surface.finish();
+
+    let link_id = page.add_tagged_annotation(
+        LinkAnnotation::new_with_quads(
+            multi_line_quads(10.0),
+            Target::Action(Action::Link(LinkAction::new("www.youtube.com".to_string()))),
+        )
+        .into(),
+    );
+
+    page.finish();
+
+    link.push(link_id);
+    link.push(id);
+    par.push(link);
+    tag_tree.push(par);
+
+    document.set_tag_tree(tag_tree);
+}
+
 #[snapshot(document)]
 fn tagging_simple(document: &mut Document) {
     tagging_simple_impl(document);
@@ -106,6 +144,28 @@ fn tagging_simple_with_link(document: &mut Document) {
     tagging_simple_with_link_impl(document);
 }
 
+#[test]
+fn tagging_link_with_quads() {
+    let mut document = Document::new_with(settings_1());
+    tagging_link_with_quads_impl(&mut document);
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Link"), 1);
+    // The page has the default A4 size.
+    assert_eq!(pdf_count(&pdf, b"/Rect [20 792 180 832]"), 1);
+    assert_eq!(
+        pdf_count(
+            &pdf,
+            b"/QuadPoints [20 832 180 832 20 812 180 812 20 812 90 812 20 792 90 792]"
+        ),
+        1
+    );
+    // The annotation is referenced from the link structure element.
+    assert_eq!(pdf_count(&pdf, b"/StructParent "), 1);
+    assert_eq!(pdf_count(&pdf, b"/Type /OBJR"), 1);
+    assert_eq!(pdf_count(&pdf, b"/S /Link"), 1);
+}
+
 #[snapshot(document, settings_12)]
 fn tagging_disabled(document: &mut Document) {
     tagging_simple_impl(document);
@@ -157,7 +217,7 @@ fn tagging_multiple_content_tags(document: &mut Document) {
     let id1 = surface.start_tagged(ContentTag::Span(SpanTag::empty()));
     surface.fill_text_(25.0, "a span");
     surface.end_tagged();
-    let id2 = surface.start_tagged(ContentTag::Artifact(ArtifactType::Header));
+    let id2 = surface.start_tagged(ContentTag::Artifact(ArtifactType::Header, None));
     surface.fill_text_(50.0, "a header artifact");
     surface.end_tagged();
     let id3 = surface.start_tagged(ContentTag::Other);
@@ -183,7 +243,7 @@ fn tagging_multiple_content_tags(document: &mut Document) {
     surface.pop();
     surface.end_tagged();
 
-    let id6 = surface.start_tagged(ContentTag::Artifact(ArtifactType::Other));
+    let id6 = surface.start_tagged(ContentTag::Artifact(ArtifactType::Other, None));
     surface.fill_text_(75.0, "a different type of artifact");
     surface.end_tagged();
 
@@ -430,3 +490,207 @@ fn tagging_missing_identifier_in_tree() {
 
     let _ = document.finish();
 }
+
+fn tagging_figure_bbox_impl(document: &mut Document, bbox: BBox, pages: usize) {
+    let mut tag_tree = TagTree::new();
+    let mut figure = TagGroup::new(Tag::Figure(Some("A rectangle.".to_string())));
+    figure.set_placement(Placement::Block);
+    figure.set_bbox(bbox);
+
+    for _ in 0..pages {
+        let mut page = document.start_page();
+        let mut surface = page.surface();
+
+        surface.push_transform(&Transform::from_translate(10.0, 20.0));
+        let id = surface.start_tagged(ContentTag::Other);
+        surface.set_fill(Some(green_fill(1.0)));
+        surface.draw_path(&rect_to_path(20.0, 30.0, 120.0, 80.0));
+        surface.end_tagged();
+        surface.pop();
+
+        surface.finish();
+        page.finish();
+
+        figure.push(id);
+    }
+
+    tag_tree.push(figure);
+    document.set_tag_tree(tag_tree);
+}
+
+#[test]
+fn tagging_figure_bbox_auto() {
+    let mut document = Document::new_with(settings_1());
+    tagging_figure_bbox_impl(&mut document, BBox::Auto, 1);
+    let pdf = document.finish().unwrap();
+
+    assert!(pdf_contains(&pdf, b"/O /Layout"));
+    assert!(pdf_contains(&pdf, b"/Placement /Block"));
+    assert!(pdf_contains(&pdf, b"/BBox [30 742 130 792]"));
+}
+
+#[test]
+fn tagging_figure_bbox_custom() {
+    let mut document = Document::new_with(settings_1());
+    let rect = Rect::from_xywh(0.0, 0.0, 100.0, 100.0).unwrap();
+    tagging_figure_bbox_impl(&mut document, BBox::Rect(rect), 1);
+    let pdf = document.finish().unwrap();
+
+    assert!(pdf_contains(&pdf, b"/BBox [0 742 100 842]"));
+}
+
+#[test]
+fn tagging_figure_bbox_multiple_pages() {
+    let mut document = Document::new_with(settings_1());
+    tagging_figure_bbox_impl(&mut document, BBox::Auto, 2);
+
+    assert_eq!(document.finish(), Err(KrillaError::MultiPageBBox));
+}
+
+#[snapshot(document)]
+fn tagging_layout_attributes(document: &mut Document) {
+    let mut tag_tree = TagTree::new();
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+
+    let mut par = TagGroup::new(Tag::P);
+    par.set_layout(LayoutAttributes {
+        placement: Some(Placement::Block),
+        space_before: 12.0,
+        text_align: TextAlign::Center,
+        width: Some(200.0),
+        ..LayoutAttributes::default()
+    });
+    let id = surface.start_tagged(ContentTag::Span(SpanTag::empty()));
+    surface.set_fill(Some(green_fill(1.0)));
+    surface.draw_path(&rect_to_path(20.0, 30.0, 120.0, 80.0));
+    surface.end_tagged();
+    par.push(id);
+    tag_tree.push(par);
+
+    // Default values are not written at all.
+    let mut default = TagGroup::new(Tag::P);
+    default.set_layout(LayoutAttributes::default());
+    let id = surface.start_tagged(ContentTag::Span(SpanTag::empty()));
+    surface.draw_path(&rect_to_path(20.0, 90.0, 120.0, 140.0));
+    surface.end_tagged();
+    default.push(id);
+    tag_tree.push(default);
+
+    surface.finish();
+    page.finish();
+
+    document.set_tag_tree(tag_tree);
+}
+
+#[test]
+fn tagging_artifact_types() {
+    let mut document = Document::new_with(settings_1());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+
+    let rect = Rect::from_xywh(0.0, 0.0, 100.0, 100.0).unwrap();
+
+    for (artifact_type, bbox) in [
+        (ArtifactType::Watermark, Some(rect)),
+        (ArtifactType::Background, Some(rect)),
+        (ArtifactType::Layout, None),
+        (ArtifactType::Other, Some(rect)),
+    ] {
+        surface.start_tagged(ContentTag::Artifact(artifact_type, bbox));
+        surface.set_fill(Some(green_fill(1.0)));
+        surface.draw_path(&rect_to_path(0.0, 0.0, 100.0, 100.0));
+        surface.end_tagged();
+    }
+
+    surface.finish();
+    page.finish();
+
+    document.set_tag_tree(TagTree::new());
+    let pdf = document.finish().unwrap();
+
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Watermark"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Type /Pagination"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Type /Background"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Type /Layout"), 1);
+    assert_eq!(pdf_count(&pdf, b"/BBox [0 742 100 842]"), 3);
+}
+
+fn tagging_lang_impl(document: &mut Document, group_lang: &str, span_lang: &str) {
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+
+    let id1 = surface.start_tagged(ContentTag::Span(SpanTag::empty()));
+    surface.fill_text_(25.0, "Ein Zitat:");
+    surface.end_tagged();
+
+    let id2 = surface.start_tagged(ContentTag::Span(SpanTag {
+        lang: Some(span_lang),
+        ..SpanTag::empty()
+    }));
+    surface.fill_text_(50.0, "a quote");
+    surface.end_tagged();
+
+    surface.finish();
+    page.finish();
+
+    let mut quote = TagGroup::new(Tag::InlineQuote);
+    quote.set_lang(group_lang);
+    quote.set_actual_text("a quote");
+    quote.push(id2);
+
+    let mut par = TagGroup::new(Tag::P);
+    par.push(id1);
+    par.push(quote);
+
+    let mut tag_tree = TagTree::new();
+    tag_tree.push(par);
+    document.set_tag_tree(tag_tree);
+}
+
+#[test]
+fn tagging_lang() {
+    let mut document = Document::new_with(settings_1());
+    tagging_lang_impl(&mut document, "en-US", "en");
+    let pdf = document.finish().unwrap();
+
+    assert!(pdf_contains(&pdf, b"/Lang (en-US)"));
+    assert!(pdf_contains(&pdf, b"/Lang (en)"));
+    assert!(pdf_contains(&pdf, b"/ActualText (a quote)"));
+}
+
+#[test]
+fn tagging_lang_empty() {
+    let mut document = Document::new_with(settings_1());
+    tagging_lang_impl(&mut document, "", "en");
+    assert_eq!(document.finish(), Err(KrillaError::EmptyLanguage(None)));
+
+    let mut document = Document::new_with(settings_1());
+    tagging_lang_impl(&mut document, "en-US", "");
+    assert_eq!(document.finish(), Err(KrillaError::EmptyLanguage(None)));
+}
+
+#[test]
+#[should_panic]
+fn tagging_ruby_text_outside_of_ruby() {
+    let mut document = Document::new();
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+
+    let id = surface.start_tagged(ContentTag::Other);
+    surface.set_fill(Some(green_fill(1.0)));
+    surface.draw_path(&rect_to_path(50.0, 50.0, 100.0, 100.0));
+    surface.end_tagged();
+
+    surface.finish();
+    page.finish();
+
+    let mut par = TagGroup::new(Tag::P);
+    par.push(TagGroup::with_children(Tag::RT, vec![id.into()]));
+
+    let mut tag_tree = TagTree::new();
+    tag_tree.push(par);
+    document.set_tag_tree(tag_tree);
+
+    let _ = document.finish();
+}
@@ -1,15 +1,21 @@
+use krilla::error::KrillaWarning;
 use krilla::geom::Point;
 use krilla::page::Page;
 use krilla::paint::{Fill, LinearGradient, Paint, SpreadMethod, Stroke};
 use krilla::surface::Surface;
-use krilla::text::{Font, GlyphId, KrillaGlyph, TextDirection};
+use krilla::tagging::{ContentTag, SpanTag, Tag, TagGroup, TagTree};
+use krilla::text::{
+    Font, GlyphId, KrillaGlyph, SyntheticStyle, TextDirection, TextSpacing, WritingMode,
+};
 use krilla::{Data, Document};
 use krilla_macros::{snapshot, visreg};
+use skrifa::MetadataProvider;
 
 use crate::{
-    blue_fill, blue_stroke, red_fill, red_stroke, stops_with_3_solid_1, LATIN_MODERN_ROMAN,
-    LIBERTINUS_SERIF, NOTO_COLOR_EMOJI_COLR, NOTO_SANS, NOTO_SANS_CJK, NOTO_SANS_DEVANAGARI,
-    TWITTER_COLOR_EMOJI,
+    blue_fill, blue_stroke, dummy_text_with_spans, pdf_contains, pdf_count, rect_to_path, red_fill,
+    red_stroke, settings_1, stops_with_3_solid_1, LATIN_MODERN_ROMAN, LIBERTINUS_SERIF,
+    NOTO_COLOR_EMOJI_CBDT, NOTO_COLOR_EMOJI_COLR, NOTO_SANS, NOTO_SANS_CJK, NOTO_SANS_DEVANAGARI,
+    NOTO_SANS_VARIABLE, TWITTER_COLOR_EMOJI,
 };
 
 fn text_gradient(spread_method: SpreadMethod) -> LinearGradient {
@@ -301,6 +307,8 @@ fn text_small_caps(page: &mut Page) {
             y_offset: 0.0,
             y_advance: 0.0,
             location: None,
+            text_override: None,
+            font_index: 0,
         },
         KrillaGlyph {
             glyph_id: GlyphId::new(2464),
@@ -310,6 +318,8 @@ fn text_small_caps(page: &mut Page) {
             y_offset: 0.0,
             y_advance: 0.0,
             location: None,
+            text_override: None,
+            font_index: 0,
         },
     ];
 
@@ -421,3 +431,725 @@ fn text_two_fonts_reproducibility() {
         assert_eq!(expected, render_single());
     }
 }
+
+fn text_glyph_clip_impl(surface: &mut Surface, glyphs: &[KrillaGlyph]) {
+    let font = Font::new(NOTO_SANS.clone(), 0).unwrap();
+    surface.push_glyph_clip(Point::from_xy(10.0, 120.0), glyphs, font, "KRIL", 60.0);
+    surface.set_fill(Some(Fill {
+        paint: Paint::from(text_gradient(SpreadMethod::Pad)),
+        ..Default::default()
+    }));
+    surface.draw_path(&rect_to_path(0.0, 0.0, 200.0, 200.0));
+    surface.pop();
+    surface.set_fill(Some(blue_fill(0.5)));
+    surface.draw_path(&rect_to_path(0.0, 150.0, 200.0, 200.0));
+}
+
+fn text_glyph_clip_glyphs() -> Vec<KrillaGlyph> {
+    (0..4)
+        .map(|i| {
+            KrillaGlyph::new(
+                GlyphId::new(46 + i),
+                0.6,
+                0.0,
+                0.0,
+                0.0,
+                i as usize..i as usize + 1,
+                None,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn text_glyph_clip() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    text_glyph_clip_impl(&mut surface, &text_glyph_clip_glyphs());
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"7 Tr"));
+    assert!(pdf_contains(&pdf, b"/Artifact BMC"));
+}
+
+#[test]
+fn text_glyph_clip_empty() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    text_glyph_clip_impl(&mut surface, &[]);
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert!(!pdf_contains(&pdf, b"7 Tr"));
+    assert!(pdf_contains(&pdf, b"0 0 0 0 re"));
+}
+
+fn text_invisible_impl(surface: &mut Surface) {
+    let font = Font::new(NOTO_SANS.clone(), 0).unwrap();
+    surface.set_fill(Some(red_fill(0.5)));
+    surface.draw_path(&rect_to_path(0.0, 0.0, 200.0, 200.0));
+    surface.draw_invisible_glyphs(
+        Point::from_xy(10.0, 120.0),
+        &text_glyph_clip_glyphs(),
+        font,
+        "KRIL",
+        60.0,
+    );
+}
+
+#[test]
+fn text_invisible() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    let id = surface.start_tagged(ContentTag::Span(SpanTag::empty()));
+    text_invisible_impl(&mut surface);
+    surface.end_tagged();
+    surface.finish();
+    page.finish();
+
+    let mut tag_tree = TagTree::new();
+    let mut par = TagGroup::new(Tag::P);
+    par.push(id);
+    tag_tree.push(par);
+    d.set_tag_tree(tag_tree);
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"3 Tr"));
+    assert!(pdf_contains(&pdf, b"/ToUnicode"));
+    assert!(pdf_contains(&pdf, b"/StructParents"));
+    assert!(!pdf_contains(&pdf, b"/Artifact"));
+}
+
+fn text_spacing_impl(surface: &mut Surface, outlined: bool) {
+    let font = Font::new(NOTO_SANS.clone(), 0).unwrap();
+    let spacings = [
+        TextSpacing::default(),
+        TextSpacing {
+            char_spacing: 3.0,
+            ..Default::default()
+        },
+        TextSpacing {
+            word_spacing: 15.0,
+            ..Default::default()
+        },
+        TextSpacing {
+            horizontal_scaling: 0.5,
+            ..Default::default()
+        },
+        TextSpacing {
+            char_spacing: 2.0,
+            word_spacing: 10.0,
+            horizontal_scaling: 1.3,
+        },
+    ];
+
+    surface.set_fill(Some(red_fill(0.5)));
+    surface.set_stroke(Some(blue_stroke(0.8)));
+
+    for (i, spacing) in spacings.into_iter().enumerate() {
+        surface.set_text_spacing(spacing);
+        surface.draw_text(
+            Point::from_xy(0.0, 30.0 + i as f32 * 35.0),
+            font.clone(),
+            20.0,
+            "spaced text",
+            outlined,
+            TextDirection::Auto,
+        );
+    }
+}
+
+#[test]
+fn text_spacing() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    text_spacing_impl(&mut surface, false);
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"3 Tc"));
+    assert!(pdf_contains(&pdf, b"50 Tz"));
+    assert!(pdf_contains(&pdf, b"2 Tc"));
+    assert!(pdf_contains(&pdf, b"130 Tz"));
+    // The spacing is reset after each run in which it was set.
+    assert_eq!(pdf_count(&pdf, b"0 Tc"), 2);
+    assert_eq!(pdf_count(&pdf, b"100 Tz"), 2);
+    // Word spacing is applied using adjustments instead.
+    assert!(!pdf_contains(&pdf, b"Tw"));
+}
+
+#[test]
+fn text_spacing_outlined() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    text_spacing_impl(&mut surface, true);
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    // The spacing is applied to the outlines, so no text is written at all.
+    assert!(!pdf_contains(&pdf, b"/Type /Font"));
+    assert!(!pdf_contains(&pdf, b" Tc"));
+    assert!(!pdf_contains(&pdf, b" Tz"));
+}
+
+#[test]
+fn text_spacing_default() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    let font = Font::new(NOTO_SANS.clone(), 0).unwrap();
+    surface.set_fill(Some(red_fill(0.5)));
+    surface.draw_text(
+        Point::from_xy(0.0, 30.0),
+        font,
+        20.0,
+        "unspaced text",
+        false,
+        TextDirection::Auto,
+    );
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert!(!pdf_contains(&pdf, b"Tc"));
+    assert!(!pdf_contains(&pdf, b"Tz"));
+}
+
+fn text_rise_glyphs() -> Vec<KrillaGlyph> {
+    // Shift the middle glyphs up and the last one down.
+    let y_offsets = [0.0, 0.25, 0.25, -0.125];
+
+    (0..4)
+        .map(|i| {
+            KrillaGlyph::new(
+                GlyphId::new(46 + i),
+                0.6,
+                0.0,
+                y_offsets[i as usize],
+                0.0,
+                i as usize..i as usize + 1,
+                None,
+            )
+        })
+        .collect()
+}
+
+fn text_rise_impl(surface: &mut Surface) {
+    let font = Font::new(NOTO_SANS.clone(), 0).unwrap();
+    surface.draw_glyphs(
+        Point::from_xy(10.0, 100.0),
+        &text_rise_glyphs(),
+        font,
+        "KRIL",
+        40.0,
+        false,
+    );
+}
+
+#[test]
+fn text_rise() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    text_rise_impl(&mut surface);
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"10 Ts"));
+    assert!(pdf_contains(&pdf, b"-5 Ts"));
+    assert!(pdf_contains(&pdf, b"\n0 Ts"));
+    // All glyphs are written on the same line.
+    assert_eq!(pdf_count(&pdf, b" Tm"), 1);
+    assert_eq!(pdf_count(&pdf, b" Tf"), 1);
+}
+
+fn text_vertical_writing_mode_impl(surface: &mut Surface) {
+    let font = Font::new(NOTO_SANS_CJK.clone(), 0).unwrap();
+    surface.set_writing_mode(WritingMode::Vertical);
+    surface.draw_text(
+        Point::from_xy(100.0, 0.0),
+        font,
+        20.0,
+        "你好这是一段则是文字",
+        false,
+        TextDirection::TopToBottom,
+    );
+}
+
+#[test]
+fn text_vertical_writing_mode() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    text_vertical_writing_mode_impl(&mut surface);
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/Encoding /Identity-V"));
+    assert!(pdf_contains(&pdf, b"/DW2 ["));
+    assert!(pdf_contains(&pdf, b"/W2 ["));
+    assert!(pdf_contains(&pdf, b"/WMode 1"));
+    assert!(!pdf_contains(&pdf, b"/Identity-H"));
+    // The glyphs are positioned using the vertical metrics of the font.
+    assert_eq!(pdf_count(&pdf, b" Tm"), 1);
+}
+
+#[test]
+fn text_vertical_writing_mode_mixed() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    let font = Font::new(NOTO_SANS_CJK.clone(), 0).unwrap();
+    surface.draw_text(
+        Point::from_xy(0.0, 20.0),
+        font,
+        20.0,
+        "你好",
+        false,
+        TextDirection::Auto,
+    );
+    text_vertical_writing_mode_impl(&mut surface);
+    assert_eq!(surface.get_writing_mode(), WritingMode::Vertical);
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    // The same font is embedded once for each writing mode.
+    assert_eq!(pdf_count(&pdf, b"/Encoding /Identity-H"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Encoding /Identity-V"), 1);
+}
+
+#[test]
+fn text_font_variations() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+
+    for (i, weight) in [100.0, 400.0, 900.0].into_iter().enumerate() {
+        let font =
+            Font::new_with_variations(NOTO_SANS_VARIABLE.clone(), 0, &[("wght", weight)]).unwrap();
+        surface.draw_text(
+            Point::from_xy(0.0, 30.0 + i as f32 * 40.0),
+            font,
+            30.0,
+            "Variable",
+            false,
+            TextDirection::Auto,
+        );
+    }
+
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    // Each weight is embedded as a separate instance.
+    assert_eq!(pdf_count(&pdf, b"/FontFile2"), 3);
+    assert_eq!(pdf_count(&pdf, b"] TJ"), 3);
+}
+
+#[test]
+fn text_font_variations_entries() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+
+    for variations in [
+        [("wght", 650.0), ("wdth", 87.0)],
+        [("wght", 400.0), ("wdth", 100.0)],
+    ] {
+        let font = Font::new_with_variations(NOTO_SANS_VARIABLE.clone(), 0, &variations).unwrap();
+        surface.draw_text(
+            Point::from_xy(0.0, 100.0),
+            font,
+            20.0,
+            "Test",
+            false,
+            TextDirection::Auto,
+        );
+    }
+
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    // Both instances are embedded separately.
+    assert_eq!(pdf_count(&pdf, b"/FontFile2"), 2);
+
+    let subset_tags = pdf
+        .windows(b"/FontName /".len() + 6)
+        .filter(|w| w.starts_with(b"/FontName /"))
+        .map(|w| w[b"/FontName /".len()..].to_vec())
+        .collect::<std::collections::HashSet<_>>();
+    assert_eq!(subset_tags.len(), 2);
+}
+
+fn text_font_variations_composite_impl(surface: &mut Surface) {
+    for (i, weight) in [100.0, 900.0].into_iter().enumerate() {
+        let font =
+            Font::new_with_variations(NOTO_SANS_VARIABLE.clone(), 0, &[("wght", weight)]).unwrap();
+        surface.draw_text(
+            Point::from_xy(0.0, 30.0 + i as f32 * 40.0),
+            font,
+            30.0,
+            "ÄÖÜ éñç",
+            false,
+            TextDirection::Auto,
+        );
+    }
+}
+
+#[visreg]
+fn text_font_variations_composite(surface: &mut Surface) {
+    text_font_variations_composite_impl(surface);
+}
+
+#[test]
+fn text_font_variations_widths() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+
+    let font =
+        Font::new_with_variations(NOTO_SANS_VARIABLE.clone(), 0, &[("wght", 900.0)]).unwrap();
+    surface.draw_text(
+        Point::from_xy(0.0, 30.0),
+        font.clone(),
+        30.0,
+        "Ä",
+        false,
+        TextDirection::Auto,
+    );
+
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+
+    let font_ref = skrifa::FontRef::new(NOTO_SANS_VARIABLE.as_ref()).unwrap();
+    let glyph_id = GlyphId::new(font_ref.charmap().map('Ä').unwrap().to_u32());
+    let default_font = Font::new(NOTO_SANS_VARIABLE.clone(), 0).unwrap();
+    let width = font.glyph_metrics(glyph_id).unwrap().advance_width;
+    assert_ne!(
+        width,
+        default_font.glyph_metrics(glyph_id).unwrap().advance_width
+    );
+
+    // The widths use the advance of the chosen instance.
+    assert!(pdf_contains(&pdf, format!("1 1 {width}]").as_bytes()));
+}
+
+#[test]
+fn text_font_variations_default() {
+    let font = Font::new(NOTO_SANS_VARIABLE.clone(), 0).unwrap();
+
+    // Empty variations and axes that don't exist result in the default instance.
+    assert_eq!(
+        Font::new_with_variations(NOTO_SANS_VARIABLE.clone(), 0, &[]),
+        Some(font.clone())
+    );
+    assert_eq!(
+        Font::new_with_variations(NOTO_SANS_VARIABLE.clone(), 0, &[("abcd", 10.0)]),
+        Some(font.clone())
+    );
+    assert_ne!(
+        Font::new_with_variations(NOTO_SANS_VARIABLE.clone(), 0, &[("wght", 700.0)]),
+        Some(font)
+    );
+    assert!(
+        Font::new_with_variations(NOTO_SANS_VARIABLE.clone(), 0, &[("weight", 700.0)]).is_none()
+    );
+}
+
+fn text_bitmap_font_impl(surface: &mut Surface) {
+    // A font that only contains bitmaps in the `CBDT` table, but no outlines.
+    let font = Font::new(NOTO_COLOR_EMOJI_CBDT.clone(), 0).unwrap();
+    let glyphs = (0..2)
+        .map(|i| {
+            KrillaGlyph::new(
+                GlyphId::new(103),
+                1.25,
+                0.0,
+                0.0,
+                0.0,
+                i * 4..(i + 1) * 4,
+                None,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    surface.draw_glyphs(
+        Point::from_xy(0.0, 100.0),
+        &glyphs,
+        font,
+        "\u{1F600}\u{1F600}",
+        40.0,
+        false,
+    );
+}
+
+#[visreg]
+fn text_bitmap_font(surface: &mut Surface) {
+    text_bitmap_font_impl(surface);
+}
+
+#[snapshot]
+fn text_bitmap_font(page: &mut Page) {
+    let mut surface = page.surface();
+    text_bitmap_font_impl(&mut surface);
+}
+
+#[test]
+fn text_bitmap_font_entries() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    text_bitmap_font_impl(&mut surface);
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    // The bitmap is drawn as an image in the char proc of a Type3 font, which
+    // is only written once, together with its soft mask.
+    assert!(pdf_contains(&pdf, b"/Subtype /Type3"));
+    assert!(pdf_contains(&pdf, b"/ToUnicode"));
+    assert!(pdf_contains(&pdf, b"/FontMatrix"));
+    assert_eq!(pdf_count(&pdf, b"/Subtype /Image"), 2);
+    assert_eq!(pdf_count(&pdf, b"/SMask"), 1);
+}
+
+fn text_synthetic_style_impl(surface: &mut Surface) {
+    let font = Font::new(NOTO_SANS.clone(), 0).unwrap();
+    let styles = [
+        SyntheticStyle::default(),
+        SyntheticStyle {
+            bold: 0.03,
+            ..Default::default()
+        },
+        SyntheticStyle {
+            oblique: 14.0,
+            ..Default::default()
+        },
+        SyntheticStyle {
+            bold: 0.03,
+            oblique: 14.0,
+        },
+    ];
+
+    for (i, style) in styles.into_iter().enumerate() {
+        surface.set_synthetic_style(style);
+        surface.draw_text(
+            Point::from_xy(0.0, 40.0 + i as f32 * 40.0),
+            font.clone(),
+            32.0,
+            "Hello",
+            false,
+            TextDirection::Auto,
+        );
+    }
+
+    surface.set_synthetic_style(SyntheticStyle::default());
+}
+
+#[test]
+fn text_synthetic_style() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    text_synthetic_style_impl(&mut surface);
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    // The emboldened text is filled and stroked, while the slanted text is skewed
+    // in addition to the transform of the page.
+    assert_eq!(pdf_count(&pdf, b"2 Tr"), 2);
+    assert_eq!(pdf_count(&pdf, b"1 0 0 -1 0 842 cm"), 2);
+    assert_eq!(pdf_count(&pdf, b"1 0 -0.249328 -1"), 2);
+    // The text is only embedded once.
+    assert_eq!(pdf_count(&pdf, b"/FontFile2"), 1);
+}
+
+#[test]
+fn text_glyph_text_override() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    let glyph = |id, range| KrillaGlyph::new(GlyphId::new(id), 0.5, 0.0, 0.0, 0.0, range, None);
+    let glyphs = vec![
+        glyph(10, 0..3).with_text_override("fi"),
+        glyph(20, 3..4),
+        glyph(30, 4..7).with_text_override("A"),
+    ];
+    surface.draw_glyphs(
+        Point::from_xy(0.0, 50.0),
+        &glyphs,
+        Font::new(NOTO_SANS.clone(), 0).unwrap(),
+        "\u{FB01}n\u{E000}",
+        12.0,
+        false,
+    );
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    // The overrides are used in the `ToUnicode` CMap instead of the text.
+    assert!(pdf_contains(&pdf, b"<00660069>"));
+    assert!(pdf_contains(&pdf, b"<0041>"));
+    assert!(!pdf_contains(&pdf, b"<FB01>"));
+    assert!(!pdf_contains(&pdf, b"<E000>"));
+    // Only the glyph that maps to multiple codepoints is wrapped in an actual text.
+    assert_eq!(pdf_count(&pdf, b"/ActualText"), 1);
+    assert!(pdf_contains(&pdf, b"/ActualText (fi)"));
+}
+
+#[test]
+fn text_actual_text() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    surface.start_actual_text("finance");
+    surface.draw_text(
+        Point::from_xy(0.0, 50.0),
+        Font::new(NOTO_SANS.clone(), 0).unwrap(),
+        12.0,
+        "\u{FB01}nance",
+        false,
+        TextDirection::Auto,
+    );
+    surface.end_actual_text();
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/ActualText (finance)"));
+    assert!(pdf_contains(&pdf, b"EMC"));
+}
+
+#[test]
+fn text_notdef_warnings() {
+    let mut d = Document::new_with(settings_1());
+    d.start_page().finish();
+
+    let font = Font::new(NOTO_SANS.clone(), 0).unwrap();
+    let (text, glyphs) = dummy_text_with_spans();
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    surface.draw_glyphs(
+        Point::from_xy(0.0, 50.0),
+        &glyphs,
+        font.clone(),
+        &text,
+        12.0,
+        false,
+    );
+    surface.finish();
+    page.finish();
+
+    assert_eq!(
+        d.take_warnings(),
+        vec![KrillaWarning::NotDefGlyph(font, 1..2, Some(1), Some(4))]
+    );
+
+    // Warnings that have already been taken aren't returned again.
+    let (_, warnings) = d.finish_with_warnings().unwrap();
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn text_simple_text_to_unicode() {
+    let mut d = Document::new_with(settings_1());
+    let mut page = d.start_page();
+    let mut surface = page.surface();
+    surface.draw_text(
+        Point::from_xy(0.0, 50.0),
+        Font::new(NOTO_SANS.clone(), 0).unwrap(),
+        12.0,
+        "hello",
+        false,
+        TextDirection::Auto,
+    );
+    surface.finish();
+    page.finish();
+
+    let pdf = d.finish().unwrap();
+    // Each glyph is mapped to its own cluster, and glyphs that are used
+    // multiple times only have one entry.
+    assert_eq!(pdf_count(&pdf, b"<0068>"), 1);
+    assert_eq!(pdf_count(&pdf, b"<0065>"), 1);
+    assert_eq!(pdf_count(&pdf, b"<006C>"), 1);
+    assert_eq!(pdf_count(&pdf, b"<006F>"), 1);
+}
+
+#[test]
+fn text_draw_glyphs_with_fallback() {
+    let fonts = [
+        Font::new(NOTO_SANS.clone(), 0).unwrap(),
+        Font::new(LATIN_MODERN_ROMAN.clone(), 0).unwrap(),
+    ];
+    let glyph = |id, range, font_index| {
+        KrillaGlyph::new(GlyphId::new(id), 0.5, 0.0, 0.0, 0.0, range, None)
+            .with_font_index(font_index)
+    };
+    let glyphs = vec![
+        glyph(10, 0..1, 0),
+        glyph(20, 1..2, 0),
+        glyph(30, 2..3, 1),
+        glyph(40, 3..4, 0),
+    ];
+
+    let pdf = |draw: &dyn Fn(&mut Surface)| {
+        let mut d = Document::new_with(settings_1());
+        let mut page = d.start_page();
+        let mut surface = page.surface();
+        draw(&mut surface);
+        surface.finish();
+        page.finish();
+        d.finish().unwrap()
+    };
+
+    let with_fallback = pdf(&|surface| {
+        surface.draw_glyphs_with_fallback(
+            Point::from_xy(0.0, 50.0),
+            &glyphs,
+            &fonts,
+            "abcd",
+            10.0,
+            false,
+        );
+    });
+
+    // Drawing the glyphs with fallback is the same as splitting them into runs
+    // and drawing each run at the end of the previous one.
+    let split = pdf(&|surface| {
+        for (start, range) in [(0.0, 0..2), (10.0, 2..3), (15.0, 3..4)] {
+            let run = &glyphs[range.clone()];
+            surface.draw_glyphs(
+                Point::from_xy(start, 50.0),
+                run,
+                fonts[run[0].font_index].clone(),
+                "abcd",
+                10.0,
+                false,
+            );
+        }
+    });
+
+    assert_eq!(with_fallback, split);
+
+    // Each font is embedded with its own `ToUnicode` CMap.
+    assert_eq!(pdf_count(&with_fallback, b"/FontFile2"), 1);
+    assert_eq!(pdf_count(&with_fallback, b"/FontFile3"), 1);
+    assert_eq!(pdf_count(&with_fallback, b"/ToUnicode"), 2);
+}
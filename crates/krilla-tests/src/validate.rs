@@ -1,27 +1,42 @@
-use krilla::action::LinkAction;
-use krilla::annotation::{Annotation, LinkAnnotation, Target};
+use std::num::NonZeroU32;
+
+use krilla::action::{DocumentActions, JavaScriptAction, LaunchAction, LinkAction, NamedAction};
+use krilla::annotation::{
+    Annotation, AnnotationFlags, FileAttachmentAnnotation, FileAttachmentIcon, HighlightAnnotation,
+    LinkAnnotation, StampAnnotation, StampAppearance, StandardStamp, Target,
+};
+use krilla::blend::{BlendMode, OverprintMode};
+use krilla::color::rgb;
 use krilla::configure::ValidationError;
+use krilla::destination::XyzDestination;
 use krilla::embed::EmbedError;
 use krilla::error::KrillaError;
 use krilla::geom::{Point, Rect, Size};
-use krilla::metadata::{DateTime, Metadata};
-use krilla::outline::Outline;
-use krilla::page::Page;
+use krilla::image::Image;
+use krilla::metadata::{DateTime, Metadata, ViewerPreferences};
+use krilla::num::NormalizedF32;
+use krilla::outline::{Outline, OutlineNode};
+use krilla::page::{Page, PageSettings, TabOrder};
 use krilla::paint::{Fill, FillRule, LinearGradient, SpreadMethod};
+use krilla::surface::Surface;
 use krilla::tagging::{
-    ArtifactType, ContentTag, ListNumbering, SpanTag, TableHeaderScope, Tag, TagGroup, TagTree,
+    ArtifactType, ContentTag, CustomTag, ListNumbering, SpanTag, TableDataCell, TableHeaderCell,
+    TableHeaderScope, Tag, TagGroup, TagTree,
 };
 use krilla::text::{Font, TextDirection};
 use krilla::text::{GlyphId, KrillaGlyph};
 use krilla_macros::snapshot;
 
+use crate::annotation::multi_line_quads;
 use crate::embed::{embedded_file_impl, file_1};
+use crate::image::jp2_data;
 use crate::{
     blue_fill, cmyk_fill, dummy_text_with_spans, green_fill, load_jpg_image, load_png_image,
-    metadata_1, rect_to_path, red_fill, settings_13, settings_15, settings_19, settings_23,
-    settings_24, settings_7, settings_8, settings_9, stops_with_2_solid_1, youtube_link, NOTO_SANS,
+    metadata_1, rect_to_path, red_fill, settings_1, settings_13, settings_15, settings_17,
+    settings_19, settings_23, settings_24, settings_25, settings_7, settings_8, settings_9,
+    stops_with_2_solid_1, youtube_link, NOTO_SANS,
 };
-use crate::{Document, SerializeSettings};
+use crate::{pdf_contains, pdf_count, Document, SerializeSettings};
 
 fn pdfa_document() -> Document {
     Document::new_with(settings_7())
@@ -167,6 +182,18 @@ fn validate_pdf_a_missing_cmyk() {
     )
 }
 
+#[test]
+fn validate_pdf_a_convert_cmyk_to_rgb() {
+    let mut document = Document::new_with(SerializeSettings {
+        convert_cmyk_to_rgb: true,
+        ..settings_7()
+    });
+    cmyk_document_impl(&mut document);
+
+    let pdf = document.finish().unwrap();
+    assert!(!pdf_contains(&pdf, b"/DeviceCMYK"));
+}
+
 #[test]
 fn validate_pdf_a_existing_cmyk() {
     let mut document = Document::new_with(settings_8());
@@ -175,6 +202,77 @@ fn validate_pdf_a_existing_cmyk() {
     assert!(document.finish().is_ok())
 }
 
+#[test]
+fn validate_pdf_a_cmyk_nonzero_overprint() {
+    let mut document = Document::new_with(settings_8());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    surface.push_overprint(true, true, OverprintMode::NonZero);
+    surface.draw_path(&rect_to_path(20.0, 20.0, 180.0, 180.0));
+    surface.pop();
+    surface.push_overprint(true, true, OverprintMode::Standard);
+    surface.set_fill(Some(cmyk_fill(1.0)));
+    surface.draw_path(&rect_to_path(20.0, 20.0, 180.0, 180.0));
+    surface.pop();
+    surface.finish();
+    page.finish();
+
+    assert!(document.finish().is_ok());
+
+    let mut document = Document::new_with(settings_8());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    surface.push_overprint(true, false, OverprintMode::NonZero);
+    surface.set_fill(Some(cmyk_fill(1.0)));
+    surface.draw_path(&rect_to_path(20.0, 20.0, 180.0, 180.0));
+    surface.pop();
+    surface.finish();
+    page.finish();
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::Validation(vec![
+            ValidationError::CmykOverprintMode(None)
+        ]))
+    );
+}
+
+#[test]
+fn validate_pdf_a_cmyk_output_intent() {
+    let mut document = Document::new_with(settings_8());
+    cmyk_document_impl(&mut document);
+
+    let pdf = document.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"(CMYK v2.4)"));
+    assert!(!pdf_contains(&pdf, b"(sRGB"));
+    assert!(!pdf_contains(&pdf, b"/DeviceCMYK"));
+}
+
+#[test]
+fn cmyk_profile_without_validator() {
+    let mut document = Document::new_with(SerializeSettings {
+        cmyk_profile: settings_8().cmyk_profile,
+        ..settings_1()
+    });
+    cmyk_document_impl(&mut document);
+
+    let pdf = document.finish().unwrap();
+    assert!(pdf_contains(&pdf, b"/ICCBased"));
+    assert!(!pdf_contains(&pdf, b"/DeviceCMYK"));
+    assert!(!pdf_contains(&pdf, b"/OutputIntents"));
+}
+
+#[test]
+fn cmyk_profile_unsupported_version() {
+    let mut document = Document::new_with(SerializeSettings {
+        cmyk_profile: settings_8().cmyk_profile,
+        ..settings_17()
+    });
+    cmyk_document_impl(&mut document);
+
+    assert_eq!(document.finish(), Err(KrillaError::UnsupportedCMYKProfile));
+}
+
 #[test]
 fn validate_pdf_a_notdef_glyph() {
     let mut document = pdfa_document();
@@ -325,7 +423,7 @@ pub(crate) fn validate_pdf_tagged_full_example(document: &mut Document) {
     );
     surface.end_tagged();
 
-    let id2 = surface.start_tagged(ContentTag::Artifact(ArtifactType::Header));
+    let id2 = surface.start_tagged(ContentTag::Artifact(ArtifactType::Header, None));
     surface.set_fill(Some(red_fill(1.0)));
     surface.draw_path(&rect_to_path(30.0, 30.0, 70.0, 70.0));
     surface.end_tagged();
@@ -572,7 +670,7 @@ fn validate_pdf_ua1_attributes(document: &mut Document) {
     let mut group1 = TagGroup::new(Tag::L(ListNumbering::Circle));
     group1.push(id1);
 
-    let mut group2 = TagGroup::new(Tag::TH(TableHeaderScope::Row));
+    let mut group2 = TagGroup::new(Tag::TH(TableHeaderCell::new(TableHeaderScope::Row)));
     group2.push(id2);
 
     tag_tree.push(group1);
@@ -588,6 +686,493 @@ fn validate_pdf_ua1_attributes(document: &mut Document) {
     document.set_outline(outline);
 }
 
+#[test]
+fn validate_pdf_ua1_outline_with_actions() {
+    let mut document = Document::new_with(settings_15());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+
+    let id1 = surface.start_tagged(ContentTag::Span(SpanTag::empty()));
+    surface.set_fill(Some(red_fill(1.0)));
+    surface.draw_path(&rect_to_path(0.0, 0.0, 100.0, 100.0));
+    surface.end_tagged();
+
+    surface.finish();
+    page.finish();
+
+    let mut tag_tree = TagTree::new();
+    let mut par = TagGroup::new(Tag::P);
+    par.push(id1);
+    tag_tree.push(par);
+    document.set_tag_tree(tag_tree);
+
+    let metadata = Metadata::new()
+        .language("en".to_string())
+        .title("a nice title".to_string());
+    document.set_metadata(metadata);
+
+    let mut outline = Outline::new();
+    outline.push_child(OutlineNode::new(
+        "Start".to_string(),
+        XyzDestination::new(0, Point::from_xy(0.0, 0.0)),
+    ));
+    outline.push_child(OutlineNode::new_with_action(
+        "Project homepage".to_string(),
+        LinkAction::new("https://github.com/LaurenzV/krilla".to_string()).into(),
+    ));
+    document.set_outline(outline);
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Title (Start)"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Title (Project homepage)"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Dest "), 1);
+    assert_eq!(pdf_count(&pdf, b"/S /URI"), 1);
+}
+
+#[test]
+fn validate_pdf_ua1_display_doc_title_disabled() {
+    let mut document = Document::new_with(settings_15());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+
+    let id1 = surface.start_tagged(ContentTag::Span(SpanTag::empty()));
+    surface.set_fill(Some(red_fill(1.0)));
+    surface.draw_path(&rect_to_path(0.0, 0.0, 100.0, 100.0));
+    surface.end_tagged();
+
+    surface.finish();
+    page.finish();
+
+    let mut tag_tree = TagTree::new();
+    let mut par = TagGroup::new(Tag::P);
+    par.push(id1);
+    tag_tree.push(par);
+    document.set_tag_tree(tag_tree);
+
+    let metadata = Metadata::new()
+        .language("en".to_string())
+        .title("a nice title".to_string())
+        .viewer_preferences(ViewerPreferences::new().display_doc_title(false));
+    document.set_metadata(metadata);
+    document.set_outline(Outline::new());
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::Validation(vec![
+            ValidationError::MissingDisplayDocTitle
+        ]))
+    )
+}
+
+fn validate_pdf_ua1_table(document: &mut Document, data_cell: TableDataCell) {
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+
+    let mut ids = vec![];
+
+    for _ in 0..3 {
+        ids.push(surface.start_tagged(ContentTag::Span(SpanTag::empty())));
+        surface.set_fill(Some(red_fill(1.0)));
+        surface.draw_path(&rect_to_path(0.0, 0.0, 100.0, 100.0));
+        surface.end_tagged();
+    }
+
+    surface.finish();
+    page.finish();
+
+    let mut header = TagGroup::new(Tag::TH(
+        TableHeaderCell::new(TableHeaderScope::Row)
+            .with_span(NonZeroU32::new(2).unwrap(), NonZeroU32::MIN),
+    ));
+    header.set_id("header");
+    header.push(ids[0]);
+
+    let mut row1 = TagGroup::new(Tag::TR);
+    row1.push(header);
+    let mut cell1 = TagGroup::new(Tag::TD(TableDataCell::new()));
+    cell1.push(ids[1]);
+    row1.push(cell1);
+
+    // The header cell spans both rows, so the data cell is in the second column.
+    let mut row2 = TagGroup::new(Tag::TR);
+    let mut cell2 = TagGroup::new(Tag::TD(data_cell));
+    cell2.push(ids[2]);
+    row2.push(cell2);
+
+    let mut table = TagGroup::new(Tag::Table);
+    table.push(row1);
+    table.push(row2);
+
+    let mut tag_tree = TagTree::new();
+    tag_tree.push(table);
+    document.set_tag_tree(tag_tree);
+
+    let metadata = Metadata::new()
+        .language("en".to_string())
+        .title("a nice title".to_string());
+    document.set_metadata(metadata);
+    document.set_outline(Outline::new());
+}
+
+#[test]
+fn validate_pdf_ua1_table_headers() {
+    let mut document = Document::new_with(settings_15());
+    validate_pdf_ua1_table(&mut document, TableDataCell::new());
+    let pdf = document.finish().unwrap();
+
+    assert!(pdf_contains(&pdf, b"/RowSpan 2"));
+    assert!(pdf_contains(&pdf, b"/Scope /Row"));
+    assert!(pdf_contains(&pdf, b"/ID (header)"));
+}
+
+#[test]
+fn validate_pdf_ua1_table_headers_ids() {
+    let mut document = Document::new_with(settings_15());
+    let cell = TableDataCell::new()
+        .with_span(NonZeroU32::MIN, NonZeroU32::new(2).unwrap())
+        .with_headers(vec!["header".to_string()]);
+    validate_pdf_ua1_table(&mut document, cell);
+    let pdf = document.finish().unwrap();
+
+    assert!(pdf_contains(&pdf, b"/ColSpan 2"));
+    assert!(pdf_contains(&pdf, b"/Headers [(header)]"));
+}
+
+#[test]
+fn validate_pdf_ua1_table_missing_headers() {
+    let mut document = Document::new_with(settings_15());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+
+    let id1 = surface.start_tagged(ContentTag::Span(SpanTag::empty()));
+    surface.set_fill(Some(red_fill(1.0)));
+    surface.draw_path(&rect_to_path(0.0, 0.0, 100.0, 100.0));
+    surface.end_tagged();
+
+    surface.finish();
+    page.finish();
+
+    let mut cell = TagGroup::new(Tag::TD(TableDataCell::new()));
+    cell.push(id1);
+    let mut row = TagGroup::new(Tag::TR);
+    row.push(cell);
+    let mut table = TagGroup::new(Tag::Table);
+    table.push(row);
+
+    let mut tag_tree = TagTree::new();
+    tag_tree.push(table);
+    document.set_tag_tree(tag_tree);
+
+    let metadata = Metadata::new()
+        .language("en".to_string())
+        .title("a nice title".to_string());
+    document.set_metadata(metadata);
+    document.set_outline(Outline::new());
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::Validation(vec![
+            ValidationError::MissingTableHeaders
+        ]))
+    )
+}
+
+fn validate_pdf_ua1_list(document: &mut Document, inner_label: bool) {
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+
+    let mut ids = vec![];
+
+    for _ in 0..4 {
+        ids.push(surface.start_tagged(ContentTag::Span(SpanTag::empty())));
+        surface.set_fill(Some(red_fill(1.0)));
+        surface.draw_path(&rect_to_path(0.0, 0.0, 100.0, 100.0));
+        surface.end_tagged();
+    }
+
+    surface.finish();
+    page.finish();
+
+    let mut inner_item = TagGroup::new(Tag::LI);
+    if inner_label {
+        inner_item.push(TagGroup::with_children(Tag::Lbl, vec![ids[2].into()]));
+        inner_item.push(TagGroup::with_children(Tag::LBody, vec![ids[3].into()]));
+    } else {
+        // All content needs to be part of the tag tree, so it's moved into the body.
+        inner_item.push(TagGroup::with_children(
+            Tag::LBody,
+            vec![ids[2].into(), ids[3].into()],
+        ));
+    }
+    let inner = TagGroup::with_children(Tag::L(ListNumbering::LowerAlpha), vec![inner_item.into()]);
+
+    let mut body = TagGroup::new(Tag::LBody);
+    body.push(ids[1]);
+    body.push(inner);
+
+    let mut item = TagGroup::new(Tag::LI);
+    item.push(TagGroup::with_children(Tag::Lbl, vec![ids[0].into()]));
+    item.push(body);
+
+    let list = TagGroup::with_children(Tag::L(ListNumbering::Decimal), vec![item.into()]);
+
+    let mut tag_tree = TagTree::new();
+    tag_tree.push(list);
+    document.set_tag_tree(tag_tree);
+
+    let metadata = Metadata::new()
+        .language("en".to_string())
+        .title("a nice title".to_string());
+    document.set_metadata(metadata);
+    document.set_outline(Outline::new());
+}
+
+#[test]
+fn validate_pdf_ua1_nested_list() {
+    let mut document = Document::new_with(settings_15());
+    validate_pdf_ua1_list(&mut document, true);
+    let pdf = document.finish().unwrap();
+
+    assert_eq!(pdf_count(&pdf, b"/ListNumbering /Decimal"), 1);
+    assert_eq!(pdf_count(&pdf, b"/ListNumbering /LowerAlpha"), 1);
+    assert_eq!(pdf_count(&pdf, b"/S /Lbl"), 2);
+    assert_eq!(pdf_count(&pdf, b"/S /LBody"), 2);
+}
+
+#[test]
+fn validate_pdf_ua1_list_missing_label() {
+    let mut document = Document::new_with(settings_15());
+    validate_pdf_ua1_list(&mut document, false);
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::Validation(vec![
+            ValidationError::MissingListLabel
+        ]))
+    )
+}
+
+fn validate_footnote_impl(document: &mut Document) {
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+
+    let font = Font::new(NOTO_SANS.clone(), 0).unwrap();
+    let draw = |surface: &mut Surface, point: Point, size: f32, text: &str| {
+        let id = surface.start_tagged(ContentTag::Span(SpanTag::empty()));
+        surface.draw_text(point, font.clone(), size, text, false, TextDirection::Auto);
+        surface.end_tagged();
+        id
+    };
+
+    let body = draw(&mut surface, Point::from_xy(0.0, 100.0), 20.0, "Some text");
+    let marker = draw(&mut surface, Point::from_xy(90.0, 92.0), 12.0, "1");
+    let label = draw(&mut surface, Point::from_xy(0.0, 180.0), 12.0, "1");
+    let note_text = draw(
+        &mut surface,
+        Point::from_xy(10.0, 180.0),
+        12.0,
+        "A footnote.",
+    );
+
+    surface.finish();
+    page.finish();
+
+    let mut reference = TagGroup::new(Tag::Reference);
+    reference.add_ref("footnote-1");
+    reference.push(marker);
+
+    let mut par = TagGroup::new(Tag::P);
+    par.push(body);
+    par.push(reference);
+
+    let mut note = TagGroup::new(Tag::Note);
+    note.set_id("footnote-1");
+    note.push(TagGroup::with_children(Tag::Lbl, vec![label.into()]));
+    note.push(TagGroup::with_children(Tag::P, vec![note_text.into()]));
+
+    let mut tag_tree = TagTree::new();
+    tag_tree.push(par);
+    tag_tree.push(note);
+    document.set_tag_tree(tag_tree);
+
+    let metadata = Metadata::new()
+        .language("en".to_string())
+        .title("a nice title".to_string());
+    document.set_metadata(metadata);
+    document.set_outline(Outline::new());
+}
+
+#[test]
+fn validate_pdf_ua1_footnote() {
+    let mut document = Document::new_with(settings_15());
+    validate_footnote_impl(&mut document);
+    let pdf = document.finish().unwrap();
+
+    assert!(pdf_contains(&pdf, b"/ID (footnote-1)"));
+    assert!(pdf_contains(&pdf, b"/S /Reference"));
+    // References are only written in PDF 2.0.
+    assert!(!pdf_contains(&pdf, b"/Ref ["));
+}
+
+#[test]
+fn pdf_version_20_footnote() {
+    let mut document = Document::new_with(settings_25());
+    validate_footnote_impl(&mut document);
+    let pdf = document.finish().unwrap();
+
+    assert!(pdf_contains(&pdf, b"/ID (footnote-1)"));
+    assert!(pdf_contains(&pdf, b"/Ref ["));
+}
+
+#[test]
+fn validate_pdf_ua1_ruby() {
+    let mut document = Document::new_with(settings_15());
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+
+    let mut ids = vec![];
+
+    for _ in 0..4 {
+        ids.push(surface.start_tagged(ContentTag::Span(SpanTag::empty())));
+        surface.set_fill(Some(red_fill(1.0)));
+        surface.draw_path(&rect_to_path(0.0, 0.0, 100.0, 100.0));
+        surface.end_tagged();
+    }
+
+    surface.finish();
+    page.finish();
+
+    let ruby = TagGroup::with_children(
+        Tag::Ruby,
+        vec![
+            TagGroup::with_children(Tag::RB, vec![ids[0].into()]).into(),
+            TagGroup::with_children(Tag::RP, vec![ids[1].into()]).into(),
+            TagGroup::with_children(Tag::RT, vec![ids[2].into()]).into(),
+            TagGroup::with_children(Tag::RP, vec![ids[3].into()]).into(),
+        ],
+    );
+
+    let mut tag_tree = TagTree::new();
+    tag_tree.push(TagGroup::with_children(Tag::P, vec![ruby.into()]));
+    document.set_tag_tree(tag_tree);
+
+    let metadata = Metadata::new()
+        .language("ja".to_string())
+        .title("a nice title".to_string());
+    document.set_metadata(metadata);
+    document.set_outline(Outline::new());
+
+    let pdf = document.finish().unwrap();
+
+    assert_eq!(pdf_count(&pdf, b"/S /Ruby"), 1);
+    assert_eq!(pdf_count(&pdf, b"/S /RB"), 1);
+    assert_eq!(pdf_count(&pdf, b"/S /RT"), 1);
+    assert_eq!(pdf_count(&pdf, b"/S /RP"), 2);
+}
+
+fn validate_custom_tags_impl(document: &mut Document, role: Option<Tag>) {
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+
+    let id1 = surface.start_tagged(ContentTag::Span(SpanTag::empty()));
+    surface.set_fill(Some(red_fill(1.0)));
+    surface.draw_path(&rect_to_path(0.0, 0.0, 100.0, 100.0));
+    surface.end_tagged();
+
+    surface.finish();
+    page.finish();
+
+    let mut ingredient = CustomTag::new("Ingredient");
+    if let Some(role) = role {
+        ingredient = ingredient.with_role(role);
+    }
+
+    let recipe = TagGroup::with_children(
+        Tag::Custom(CustomTag::new("Recipe").with_role(Tag::Section)),
+        vec![TagGroup::with_children(Tag::Custom(ingredient), vec![id1.into()]).into()],
+    );
+
+    let mut tag_tree = TagTree::new();
+    tag_tree.push(recipe);
+    document.set_tag_tree(tag_tree);
+
+    let metadata = Metadata::new()
+        .language("en".to_string())
+        .title("a nice title".to_string());
+    document.set_metadata(metadata);
+    document.set_outline(Outline::new());
+}
+
+#[test]
+fn validate_pdf_ua1_custom_tags() {
+    let mut document = Document::new_with(settings_15());
+    validate_custom_tags_impl(&mut document, Some(Tag::P));
+    let pdf = document.finish().unwrap();
+
+    assert!(pdf_contains(&pdf, b"/S /Recipe"));
+    assert!(pdf_contains(&pdf, b"/S /Ingredient"));
+    assert!(pdf_contains(&pdf, b"/Recipe /Sect"));
+    assert!(pdf_contains(&pdf, b"/Ingredient /P"));
+}
+
+#[test]
+fn validate_pdf_ua1_custom_tag_without_role() {
+    let mut document = Document::new_with(settings_15());
+    validate_custom_tags_impl(&mut document, None);
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::Validation(vec![
+            ValidationError::MissingCustomTagRole("Ingredient".to_string())
+        ]))
+    )
+}
+
+fn validate_tab_order_impl(document: &mut Document, tab_order: Option<TabOrder>) {
+    let mut page = document.start_page_with(PageSettings::default().with_tab_order(tab_order));
+    let annotation = page.add_tagged_annotation(Annotation::new_link(
+        LinkAnnotation::new(
+            Rect::from_xywh(50.0, 50.0, 100.0, 100.0).unwrap(),
+            None,
+            Target::Action(LinkAction::new("https://www.youtube.com".to_string()).into()),
+        ),
+        Some("A link to youtube".to_string()),
+    ));
+    page.finish();
+
+    let mut tag_tree = TagTree::new();
+    tag_tree.push(annotation);
+    document.set_tag_tree(tag_tree);
+
+    let metadata = Metadata::new()
+        .language("en".to_string())
+        .title("a nice title".to_string());
+    document.set_metadata(metadata);
+    document.set_outline(Outline::new());
+}
+
+#[test]
+fn validate_pdf_ua1_tab_order() {
+    let mut document = Document::new_with(settings_15());
+    validate_tab_order_impl(&mut document, None);
+    let pdf = document.finish().unwrap();
+
+    assert!(pdf_contains(&pdf, b"/Tabs /S"));
+}
+
+#[test]
+fn validate_pdf_ua1_invalid_tab_order() {
+    let mut document = Document::new_with(settings_15());
+    validate_tab_order_impl(&mut document, Some(TabOrder::Row));
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::Validation(vec![
+            ValidationError::InvalidTabOrder(0)
+        ]))
+    )
+}
+
 #[snapshot(document, settings_16)]
 fn pdf_version_14_tagged(document: &mut Document) {
     validate_pdf_tagged_full_example(document);
@@ -613,6 +1198,188 @@ fn validate_pdf_a1_no_transparency() {
     )
 }
 
+#[test]
+fn validate_pdf_a1_no_blend_mode() {
+    let mut document = Document::new_with(settings_19());
+    let metadata = metadata_1();
+    document.set_metadata(metadata);
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    surface.set_fill(Some(red_fill(1.0)));
+    surface.push_blend_mode(BlendMode::Normal);
+    surface.draw_path(&rect_to_path(0.0, 0.0, 100.0, 100.0));
+    surface.pop();
+    surface.set_location(1);
+    surface.push_blend_mode(BlendMode::Luminosity);
+    surface.draw_path(&rect_to_path(0.0, 0.0, 100.0, 100.0));
+    surface.pop();
+    surface.finish();
+    page.finish();
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::Validation(vec![
+            ValidationError::Transparency(Some(1))
+        ]))
+    )
+}
+
+fn jpeg2000_document(document: &mut Document) {
+    let mut page = document.start_page();
+    let mut surface = page.surface();
+    surface.set_location(1);
+    let image = Image::from_jpeg2000(jp2_data(30, 20, 3).into(), false).unwrap();
+    surface.draw_image(image, Size::from_wh(30.0, 20.0).unwrap());
+    surface.finish();
+    page.finish();
+}
+
+#[test]
+fn validate_pdf_a1_no_jpeg2000_image() {
+    let mut document = Document::new_with(settings_19());
+    document.set_metadata(metadata_1());
+    jpeg2000_document(&mut document);
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::Validation(vec![
+            ValidationError::Jpeg2000Image(Some(1))
+        ]))
+    )
+}
+
+#[test]
+fn validate_pdf_a2_jpeg2000_image() {
+    let mut document = Document::new_with(settings_7());
+    document.set_metadata(metadata_1());
+    jpeg2000_document(&mut document);
+
+    assert!(document.finish().is_ok());
+}
+
+#[test]
+fn validate_pdf_a1_no_highlight_annotation() {
+    let mut document = Document::new_with(settings_19());
+    let metadata = metadata_1();
+    document.set_metadata(metadata);
+    let mut page = document.start_page();
+    page.add_annotation(
+        HighlightAnnotation::new(
+            multi_line_quads(20.0),
+            rgb::Color::new(255, 255, 0),
+            NormalizedF32::ONE,
+            None,
+        )
+        .into(),
+    );
+    page.finish();
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::Validation(vec![
+            ValidationError::Transparency(None)
+        ]))
+    )
+}
+
+#[test]
+fn validate_pdf_a2_no_standard_stamp() {
+    let mut document = pdfa_document();
+    let mut page = document.start_page();
+    page.add_annotation(
+        StampAnnotation::new(
+            Rect::from_xywh(20.0, 20.0, 100.0, 40.0).unwrap(),
+            StampAppearance::Standard(StandardStamp::Approved),
+            Some("Approved".to_string()),
+        )
+        .into(),
+    );
+    page.finish();
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::Validation(vec![
+            ValidationError::MissingAnnotationAppearance
+        ]))
+    )
+}
+
+#[test]
+fn validate_pdf_a2_no_launch_action() {
+    let mut document = pdfa_document();
+    let mut page = document.start_page();
+    page.add_annotation(
+        LinkAnnotation::new(
+            Rect::from_xywh(50.0, 50.0, 100.0, 100.0).unwrap(),
+            None,
+            Target::Action(LaunchAction::new("data.csv".to_string(), false).into()),
+        )
+        .into(),
+    );
+    page.finish();
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::Validation(vec![ValidationError::LaunchAction]))
+    )
+}
+
+#[test]
+fn validate_pdf_a2_no_javascript_action() {
+    let mut document = pdfa_document();
+    let mut page = document.start_page();
+    page.add_annotation(
+        LinkAnnotation::new(
+            Rect::from_xywh(50.0, 50.0, 100.0, 100.0).unwrap(),
+            None,
+            Target::Action(JavaScriptAction::new("app.alert('Hello');".to_string()).into()),
+        )
+        .into(),
+    );
+    page.finish();
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::Validation(vec![
+            ValidationError::JavaScriptAction
+        ]))
+    )
+}
+
+#[test]
+fn validate_pdf_a2_no_document_actions() {
+    let mut document = pdfa_document();
+    document.start_page().finish();
+    document.set_document_actions(
+        DocumentActions::new().with_will_print(NamedAction::FirstPage.into()),
+    );
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::Validation(vec![
+            ValidationError::DocumentActions
+        ]))
+    )
+}
+
+#[test]
+fn validate_pdf_a2_hidden_annotation() {
+    let mut document = pdfa_document();
+    let mut page = document.start_page();
+    page.add_annotation(
+        youtube_link(50.0, 50.0, 100.0, 100.0)
+            .with_flags(AnnotationFlags::default().with_hidden(true)),
+    );
+    page.finish();
+
+    assert_eq!(
+        document.finish(),
+        Err(KrillaError::Validation(vec![
+            ValidationError::InvalidAnnotationFlags
+        ]))
+    )
+}
+
 #[test]
 fn validate_pdf_a1_no_image_transparency() {
     let mut document = Document::new_with(settings_19());
@@ -697,6 +1464,49 @@ fn validate_pdf_a3_with_embedded_file(d: &mut Document) {
     embedded_file_impl(d)
 }
 
+fn file_attachment_page(d: &mut Document) {
+    let mut page = d.start_page();
+    page.add_annotation(
+        FileAttachmentAnnotation::new(
+            Rect::from_xywh(50.0, 50.0, 30.0, 30.0).unwrap(),
+            FileAttachmentIcon::Paperclip,
+            rgb::Color::new(0, 0, 0),
+            file_1(),
+            Some("An attached file.".to_string()),
+        )
+        .into(),
+    );
+    page.finish();
+}
+
+#[test]
+fn validate_pdf_a3_with_file_attachment_annotation() {
+    let mut document = Document::new_with(settings_23());
+    // The file is both part of the embedded files and attached to the annotation.
+    embedded_file_impl(&mut document);
+    file_attachment_page(&mut document);
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /FileAttachment"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Name /Paperclip"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Type /Filespec"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Type /EmbeddedFile"), 1);
+    assert!(pdf_count(&pdf, b"/AF [") > 0);
+}
+
+#[test]
+fn validate_pdf_a3_with_file_attachment_annotation_only() {
+    let mut document = Document::new_with(settings_23());
+    document.set_metadata(metadata_1());
+    file_attachment_page(&mut document);
+
+    let pdf = document.finish().unwrap();
+    assert_eq!(pdf_count(&pdf, b"/Subtype /FileAttachment"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Type /Filespec"), 1);
+    assert_eq!(pdf_count(&pdf, b"/Type /EmbeddedFile"), 1);
+    assert!(pdf_count(&pdf, b"/AF [") > 0);
+}
+
 #[snapshot(document, settings_27)]
 fn validate_pdf_a4_f_with_embedded_file(d: &mut Document) {
     embedded_file_impl(d)
@@ -1,12 +1,16 @@
 use std::collections::HashMap;
 
-use pdf_writer::{Chunk, Finish, Name, Pdf, Ref, Str, TextStr};
+use pdf_writer::{Chunk, Finish, Name, Pdf, Ref, Str};
 use xmp_writer::{RenditionClass, XmpWriter};
 
 use crate::configure::{PdfVersion, ValidationError};
-use crate::error::KrillaResult;
+use crate::encryption::encrypt_bytes;
+use crate::error::{KrillaError, KrillaResult};
+use crate::interactive::form::{
+    fill_signature_placeholders, SignatureChunk, SignatureLocation, SignaturePlaceholder,
+};
 use crate::interchange::metadata::Metadata;
-use crate::metadata::PageLayout;
+use crate::metadata::{PageLayout, PageMode, ViewerPreferences};
 use crate::serialize::SerializeContext;
 use crate::util::{hash_base64, Deferred};
 
@@ -23,10 +27,14 @@ pub(crate) struct ChunkContainer {
     pub(crate) page_label_tree: Option<(Ref, Chunk)>,
     pub(crate) destination_profiles: Option<(Ref, Chunk)>,
     pub(crate) struct_tree_root: Option<(Ref, Chunk)>,
+    pub(crate) acro_form: Option<(Ref, Chunk)>,
+    pub(crate) open_action: Option<(Ref, Chunk)>,
+    pub(crate) document_actions: Option<(Ref, Chunk)>,
 
     pub(crate) struct_elements: Vec<Chunk>,
     pub(crate) page_labels: Vec<Chunk>,
     pub(crate) annotations: Vec<Chunk>,
+    pub(crate) form_fields: Vec<Chunk>,
     pub(crate) fonts: Vec<Chunk>,
     pub(crate) color_spaces: Vec<DChunk>,
     pub(crate) icc_profiles: Vec<DChunk>,
@@ -39,6 +47,10 @@ pub(crate) struct ChunkContainer {
     pub(crate) pages: Vec<DChunk>,
     pub(crate) images: Vec<Deferred<KrillaResult<Chunk>>>,
     pub(crate) embedded_files: Vec<DChunk>,
+    pub(crate) scripts: Vec<DChunk>,
+    /// Signature dictionaries are not visited with the other chunks, since they need
+    /// to be written separately.
+    pub(crate) signatures: Vec<SignatureChunk>,
 
     pub(crate) metadata: Option<Metadata>,
 }
@@ -48,7 +60,7 @@ impl ChunkContainer {
         Self::default()
     }
 
-    pub(crate) fn finish(self, sc: &mut SerializeContext) -> KrillaResult<Pdf> {
+    pub(crate) fn finish(mut self, sc: &mut SerializeContext) -> KrillaResult<UnfinishedPdf> {
         let mut remapped_ref = Ref::new(1);
         let mut remapper = HashMap::new();
 
@@ -62,13 +74,15 @@ impl ChunkContainer {
         // easier down the road.
         //
         // It also allows us to estimate the capacity we will need for the new PDF.
-        self.visit(&mut |chunk| {
+        let mut remap = |chunk: &Chunk| {
             for object_ref in chunk.refs() {
                 let existing = remapper.insert(object_ref, remapped_ref.bump());
                 debug_assert!(existing.is_none());
             }
             chunks_byte_len += chunk.len();
-        })?;
+        };
+        self.visit(&mut remap)?;
+        self.signatures.iter().for_each(|s| remap(&s.chunk));
 
         // Chunk length is not an exact number because the length might change as we renumber,
         // so we add a bit of a padding by multiplying with 1.1. The 200 is additional padding
@@ -84,10 +98,35 @@ impl ChunkContainer {
             pdf.set_binary_marker(b"AAAA")
         }
 
-        // Write the chunks in all the fields.
-        self.visit(&mut |chunk| {
+        // Write the chunks in all the fields. Images are usually by far the largest chunks,
+        // so we drop each of them as soon as it has been written, instead of keeping the
+        // encoded data around until the end. The fields after the images are taken out as
+        // well, so that the order in which the chunks are written stays the same.
+        let image_chunks = std::mem::take(&mut self.images);
+        let embedded_file_chunks = std::mem::take(&mut self.embedded_files);
+        let script_chunks = std::mem::take(&mut self.scripts);
+
+        let mut write = |chunk: &Chunk| {
             chunk.renumber_into(&mut pdf, |old| remapper[&old]);
-        })?;
+        };
+        self.visit(&mut write)?;
+        for image in image_chunks {
+            image.visit(&mut write)?;
+        }
+        embedded_file_chunks.visit(&mut write)?;
+        script_chunks.visit(&mut write)?;
+
+        // Keep track of where the placeholders of the signatures end up.
+        let signatures: Vec<_> = self
+            .signatures
+            .iter()
+            .map(|signature| {
+                signature
+                    .chunk
+                    .renumber_into(&mut pdf, |old| remapper[&old]);
+                signature.locate(pdf.as_bytes().len())
+            })
+            .collect();
 
         let missing_title = self.metadata.as_ref().is_none_or(|m| m.title.is_none());
 
@@ -101,6 +140,7 @@ impl ChunkContainer {
                 &mut remapped_ref,
                 &mut pdf,
                 sc.serialize_settings().configuration,
+                sc.encryptor(),
             );
         }
 
@@ -127,7 +167,9 @@ impl ChunkContainer {
             metadata.serialize_xmp_metadata(&mut xmp, sc, &instance_id);
         }
 
-        sc.serialize_settings().validator().write_xmp(&mut xmp);
+        sc.serialize_settings()
+            .validator()
+            .write_xmp(&mut xmp, sc.global_objects.facturx.as_ref());
 
         xmp.num_pages(self.pages.len() as u32);
         xmp.format("application/pdf");
@@ -152,11 +194,14 @@ impl ChunkContainer {
             || self.page_label_tree.is_some()
             || self.destination_profiles.is_some()
             || self.struct_tree_root.is_some()
+            || self.acro_form.is_some()
+            || self.open_action.is_some()
+            || self.document_actions.is_some()
         {
             let meta_ref = if sc.serialize_settings().xmp_metadata {
                 let meta_ref = remapped_ref.bump();
                 let xmp_buf = xmp.finish(None);
-                pdf.stream(meta_ref, xmp_buf.as_bytes())
+                pdf.stream(meta_ref, &encrypt_bytes(xmp_buf.as_bytes(), sc.encryptor()))
                     .pair(Name(b"Type"), Name(b"Metadata"))
                     .pair(Name(b"Subtype"), Name(b"XML"));
                 Some(meta_ref)
@@ -185,7 +230,7 @@ impl ChunkContainer {
             }
 
             if let Some(lang) = self.metadata.as_ref().and_then(|m| m.language.as_ref()) {
-                catalog.lang(TextStr(lang));
+                catalog.pair(Name(b"Lang"), sc.text_str(lang));
             } else {
                 sc.register_validation_error(ValidationError::NoDocumentLanguage);
             }
@@ -203,19 +248,83 @@ impl ChunkContainer {
                 mark_info.finish();
             }
 
-            let write_doc_title = sc
+            let requires_doc_title = sc
                 .serialize_settings()
                 .validator()
                 .requires_display_doc_title();
-            let text_direction = self.metadata.as_ref().and_then(|m| m.text_direction);
+            let preferences = self
+                .metadata
+                .as_ref()
+                .and_then(|m| m.viewer_preferences.clone())
+                .unwrap_or_default();
+            let text_direction = preferences
+                .direction
+                .or(self.metadata.as_ref().and_then(|m| m.text_direction));
+            let write_doc_title = preferences.display_doc_title.unwrap_or(requires_doc_title);
+
+            if requires_doc_title && !write_doc_title {
+                sc.register_validation_error(ValidationError::MissingDisplayDocTitle);
+            }
+
+            let pdf_version = sc.serialize_settings().pdf_version();
+            if (preferences.has_pdf17_preferences() && pdf_version < PdfVersion::Pdf17)
+                || (preferences.print_scaling.is_some() && pdf_version < PdfVersion::Pdf16)
+            {
+                return Err(KrillaError::PrintPreferences);
+            }
 
-            if write_doc_title || text_direction.is_some() {
+            if write_doc_title
+                || text_direction.is_some()
+                || preferences != ViewerPreferences::default()
+            {
                 let mut vp = catalog.viewer_preferences();
 
+                if preferences.hide_toolbar {
+                    vp.hide_toolbar(true);
+                }
+
+                if preferences.hide_menubar {
+                    vp.hide_menubar(true);
+                }
+
+                if preferences.hide_window_ui {
+                    vp.pair(Name(b"HideWindowUI"), true);
+                }
+
+                if preferences.fit_window {
+                    vp.fit_window(true);
+                }
+
+                if preferences.center_window {
+                    vp.center_window(true);
+                }
+
                 if write_doc_title {
                     vp.display_doc_title(true);
                 }
 
+                if let Some(duplex) = preferences.duplex {
+                    vp.pair(Name(b"Duplex"), duplex.to_name());
+                }
+
+                if let Some(print_scaling) = preferences.print_scaling {
+                    vp.pair(Name(b"PrintScaling"), print_scaling.to_name());
+                }
+
+                if !preferences.print_page_range.is_empty() {
+                    // Page numbers start at 1 in PDF.
+                    vp.insert(Name(b"PrintPageRange")).array().items(
+                        preferences
+                            .print_page_range
+                            .iter()
+                            .flat_map(|r| [*r.start() as i32 + 1, *r.end() as i32 + 1]),
+                    );
+                }
+
+                if let Some(num_copies) = preferences.num_copies {
+                    vp.pair(Name(b"NumCopies"), num_copies.get() as i32);
+                }
+
                 if let Some(dir) = text_direction {
                     vp.direction(dir.to_pdf());
                 }
@@ -224,21 +333,61 @@ impl ChunkContainer {
             let page_layout = self.metadata.as_ref().and_then(|m| m.page_layout);
             if let Some(layout) = page_layout {
                 // TwoPageLeft and TwoPageRight are only available PDF 1.5+
-                if sc.serialize_settings().pdf_version() >= PdfVersion::Pdf15
-                    || !matches!(layout, PageLayout::TwoPageLeft | PageLayout::TwoPageRight)
+                if sc.serialize_settings().pdf_version() < PdfVersion::Pdf15
+                    && matches!(layout, PageLayout::TwoPageLeft | PageLayout::TwoPageRight)
                 {
-                    catalog.page_layout(layout.to_pdf());
+                    return Err(KrillaError::PageLayout(layout));
                 }
+
+                catalog.page_layout(layout.to_pdf());
+            }
+
+            let page_mode = self.metadata.as_ref().and_then(|m| m.page_mode);
+            if let Some(mode) = page_mode {
+                // UseAttachments is only available in PDF 1.6+
+                if sc.serialize_settings().pdf_version() < PdfVersion::Pdf16
+                    && mode == PageMode::UseAttachments
+                {
+                    return Err(KrillaError::PageMode(mode));
+                }
+
+                catalog.page_mode(mode.to_pdf());
             }
 
             if let Some(ol) = &self.outline {
                 catalog.outlines(remapper[&ol.0]);
             }
 
+            if let Some(af) = &self.acro_form {
+                catalog.pair(Name(b"AcroForm"), remapper[&af.0]);
+            }
+
+            if let Some(oa) = &self.open_action {
+                catalog.pair(Name(b"OpenAction"), remapper[&oa.0]);
+            }
+
+            if let Some(aa) = &self.document_actions {
+                catalog.pair(Name(b"AA"), remapper[&aa.0]);
+            }
+
+            // AES-256 encryption is only part of PDF 2.0, for older versions we need
+            // to declare the Adobe extension level that introduced it.
+            if sc.serialize_settings().encryption.is_some()
+                && sc.serialize_settings().pdf_version() < PdfVersion::Pdf20
+            {
+                let mut extensions = catalog.insert(Name(b"Extensions")).dict();
+                extensions
+                    .insert(Name(b"ADBE"))
+                    .dict()
+                    .pair(Name(b"BaseVersion"), Name(b"1.7"))
+                    .pair(Name(b"ExtensionLevel"), 8);
+                extensions.finish();
+            }
+
             let write_embedded_files = sc
                 .serialize_settings()
                 .validator()
-                .write_embedded_files(self.embedded_files.is_empty());
+                .write_embedded_files(embedded_file_chunks.is_empty());
 
             if !named_destinations.is_empty() || write_embedded_files {
                 // Cannot use pdf-writer API here because it requires Ref's, while
@@ -254,7 +403,8 @@ impl ChunkContainer {
                     sorted.sort_by(|a, b| a.1.cmp(&b.1));
 
                     for (name, dest_ref) in sorted {
-                        dest_name_entries.insert(Str(name.name.as_bytes()), remapper[&dest_ref]);
+                        let name = encrypt_bytes(name.as_bytes(), sc.encryptor());
+                        dest_name_entries.insert(Str(&name), remapper[&dest_ref]);
                     }
 
                     dest_name_entries.finish();
@@ -266,27 +416,84 @@ impl ChunkContainer {
                     let mut embedded_name_entries = embedded_files_name_tree.names();
 
                     for (name, _ref) in &embedded_files {
-                        embedded_name_entries.insert(Str(name.as_bytes()), remapper[_ref]);
+                        let name = encrypt_bytes(name.as_bytes(), sc.encryptor());
+                        embedded_name_entries.insert(Str(&name), remapper[_ref]);
                     }
                 }
             }
 
-            if !embedded_files.is_empty()
-                && sc
-                    .serialize_settings()
-                    .validator()
-                    .allows_associated_files()
+            // Some standards require all embedded files to be associated with the
+            // document, otherwise, we only write the files that were associated explicitly.
+            let associated_files = if sc
+                .serialize_settings()
+                .validator()
+                .allows_associated_files()
             {
-                let mut associated_files = catalog.insert(Name(b"AF")).array().typed();
-                for _ref in embedded_files.values() {
-                    associated_files.item(remapper[_ref]).finish();
+                embedded_files.values().copied().collect::<Vec<_>>()
+            } else {
+                sc.global_objects.associated_files.clone()
+            };
+
+            if !associated_files.is_empty() {
+                let mut associated_files_array = catalog.insert(Name(b"AF")).array().typed();
+                for _ref in &associated_files {
+                    associated_files_array.item(remapper[_ref]).finish();
                 }
             }
 
             catalog.finish();
         }
 
-        Ok(pdf)
+        let encrypt_ref = sc.security_handler().map(|handler| {
+            let encrypt_ref = remapped_ref.bump();
+            handler.serialize(&mut pdf, encrypt_ref);
+            encrypt_ref
+        });
+
+        Ok(UnfinishedPdf {
+            pdf,
+            encrypt_ref,
+            signatures,
+        })
+    }
+}
+
+/// A PDF whose objects have all been written, but without the cross-reference
+/// table and the trailer.
+pub(crate) struct UnfinishedPdf {
+    pub(crate) pdf: Pdf,
+    /// The reference of the `Encrypt` dictionary, if the document is encrypted.
+    encrypt_ref: Option<Ref>,
+    /// The location of the placeholders of all signatures, in the order they have been added.
+    signatures: Vec<SignatureLocation>,
+}
+
+impl UnfinishedPdf {
+    /// Write the cross-reference table and the trailer, and fill in the byte ranges
+    /// of all signatures.
+    pub(crate) fn finish(self) -> (Vec<u8>, Vec<SignaturePlaceholder>) {
+        let start = self.pdf.as_bytes().len();
+        let mut pdf = self.pdf.finish();
+
+        // pdf-writer doesn't support custom trailer entries, so we insert the reference
+        // to the `Encrypt` dictionary ourselves. Everything after `start` was written by
+        // `Pdf::finish` and only contains the cross-reference table and the trailer, whose
+        // insertion doesn't move any objects.
+        if let Some(encrypt_ref) = self.encrypt_ref {
+            let trailer = b"trailer\n<<";
+            let pos = pdf[start..]
+                .windows(trailer.len())
+                .position(|w| w == trailer)
+                .map(|pos| start + pos + trailer.len())
+                .unwrap();
+            let entry = format!("\n  /Encrypt {} 0 R", encrypt_ref.get());
+            pdf.splice(pos..pos, entry.into_bytes());
+        }
+
+        // The byte ranges need to cover the whole file, so this can only happen at the very end.
+        let placeholders = fill_signature_placeholders(&mut pdf, self.signatures);
+
+        (pdf, placeholders)
     }
 }
 
@@ -302,9 +509,13 @@ impl Visit for ChunkContainer {
         self.page_label_tree.visit(f)?;
         self.destination_profiles.visit(f)?;
         self.struct_tree_root.visit(f)?;
+        self.acro_form.visit(f)?;
+        self.open_action.visit(f)?;
+        self.document_actions.visit(f)?;
         self.struct_elements.visit(f)?;
         self.page_labels.visit(f)?;
         self.annotations.visit(f)?;
+        self.form_fields.visit(f)?;
         self.fonts.visit(f)?;
         self.color_spaces.visit(f)?;
         self.icc_profiles.visit(f)?;
@@ -317,6 +528,7 @@ impl Visit for ChunkContainer {
         self.pages.visit(f)?;
         self.images.visit(f)?;
         self.embedded_files.visit(f)?;
+        self.scripts.visit(f)?;
         Ok(())
     }
 }
@@ -11,6 +11,7 @@ pub use version::PdfVersion;
 pub struct Configuration {
     validator: Validator,
     version: PdfVersion,
+    black_point_compensation: bool,
 }
 
 impl Default for Configuration {
@@ -25,7 +26,11 @@ impl Configuration {
     /// Returns `None` if the configuration is invalid.
     pub fn new_with(validator: Validator, version: PdfVersion) -> Option<Self> {
         if validator.compatible_with_version(version) {
-            Some(Self { validator, version })
+            Some(Self {
+                validator,
+                version,
+                black_point_compensation: false,
+            })
         } else {
             None
         }
@@ -47,6 +52,19 @@ impl Configuration {
         Self::new_with_validator(Validator::None)
     }
 
+    /// Enable or disable black point compensation for all pages of the document.
+    ///
+    /// Returns `None` if black point compensation is enabled, but the PDF version of the
+    /// configuration doesn't support it (only available in PDF 2.0).
+    pub fn with_black_point_compensation(mut self, black_point_compensation: bool) -> Option<Self> {
+        if black_point_compensation && self.version < PdfVersion::Pdf20 {
+            return None;
+        }
+
+        self.black_point_compensation = black_point_compensation;
+        Some(self)
+    }
+
     /// Return the validator of the configuration.
     pub fn validator(&self) -> Validator {
         self.validator
@@ -56,6 +74,11 @@ impl Configuration {
     pub fn version(&self) -> PdfVersion {
         self.version
     }
+
+    /// Return whether black point compensation is enabled for all pages.
+    pub fn black_point_compensation(&self) -> bool {
+        self.black_point_compensation
+    }
 }
 
 #[cfg(test)]
@@ -69,4 +92,15 @@ mod tests {
             None
         );
     }
+
+    #[test]
+    fn black_point_compensation_unsupported_version() {
+        assert_eq!(
+            Configuration::new_with_version(PdfVersion::Pdf17).with_black_point_compensation(true),
+            None
+        );
+        assert!(Configuration::new_with_version(PdfVersion::Pdf20)
+            .with_black_point_compensation(true)
+            .is_some_and(|c| c.black_point_compensation()));
+    }
 }
@@ -27,6 +27,7 @@ use xmp_writer::XmpWriter;
 
 use crate::configure::PdfVersion;
 use crate::interchange::embed::EmbedError;
+use crate::interchange::facturx::FacturX;
 use crate::surface::Location;
 use crate::text::Font;
 use crate::text::GlyphId;
@@ -104,6 +105,16 @@ pub enum ValidationError {
     MissingAltText,
     /// A heading is missing a title.
     MissingHeadingTitle,
+    /// A table data cell is not associated with any header cells, neither by the
+    /// scope of a header cell in the same row or column nor by the IDs of header cells.
+    MissingTableHeaders,
+    /// A list item of a list with numbering is missing a label.
+    MissingListLabel,
+    /// A custom tag is not mapped to a standard tag. Contains the name of the tag.
+    MissingCustomTagRole(String),
+    /// A page with annotations doesn't use the structure tab order. Contains the
+    /// index of the page.
+    InvalidTabOrder(usize),
     /// The document does not contain an outline.
     MissingDocumentOutline,
     /// An annotation is missing an alt text.
@@ -120,6 +131,31 @@ pub enum ValidationError {
     EmbeddedFile(EmbedError, Option<Location>),
     /// The PDF contains no tagging.
     MissingTagging,
+    /// The PDF is encrypted, which is forbidden by some standards (e.g. PDF/A).
+    Encryption,
+    /// An annotation has no appearance stream, for example because it uses one of the
+    /// standard stamps, whose appearance is provided by the viewer.
+    MissingAnnotationAppearance,
+    /// The PDF contains an annotation that is not printable, or that is hidden.
+    InvalidAnnotationFlags,
+    /// The PDF contains a launch action, which is forbidden by some standards (e.g. PDF/A).
+    LaunchAction,
+    /// The PDF contains a JavaScript action, which is forbidden by some standards (e.g. PDF/A).
+    JavaScriptAction,
+    /// The PDF contains actions for document events like closing or printing, which are
+    /// forbidden by some standards (e.g. PDF/A).
+    DocumentActions,
+    /// The viewer preferences of the document disable displaying the document title
+    /// in the title bar of the viewer, even though it is required by the standard.
+    MissingDisplayDocTitle,
+    /// A CMYK color was overprinted with the nonzero overprint mode, even though
+    /// it is ICC-based.
+    ///
+    /// Occurs if a CMYK profile was provided and a CMYK color was used while
+    /// `Surface::push_overprint` with `OverprintMode::NonZero` is active.
+    CmykOverprintMode(Option<Location>),
+    /// A JPEG 2000 image was used, which is forbidden by some standards (e.g. PDF/A1).
+    Jpeg2000Image(Option<Location>),
 }
 
 /// A validator for exporting PDF documents to a specific subset of PDF.
@@ -199,12 +235,11 @@ pub enum Validator {
     /// - Information should not be conveyed by contrast, color, format
     ///   or layout.
     /// - All "best practice" notes in [`Tag`] need to be complied with.
+    /// - Custom tags need to be mapped to a standard tag.
     ///
     /// Text:
     /// - You should make use of the `Alt`, `ActualText`, `Lang` and `Expansion` attributes
     ///   whenever possible.
-    /// - Usually, you can provide an empty string as `Lang` to indicate that a language is unknown.
-    ///   You should not do that in PDF-UA.
     /// - Stretchable characters (such as brackets, which often consist of several glyphs)
     ///   should be marked accordingly with `ActualText`.
     ///
@@ -221,12 +256,15 @@ pub enum Validator {
     ///   heading.
     ///
     /// Tables:
-    /// - Tables should include headers and be tagged accordingly.
+    /// - Tables should include headers and be tagged accordingly. Each data cell needs
+    ///   to be associated with header cells, either using the scope of header cells or
+    ///   by referring to the IDs of header cells.
     /// - Tables should only be used to represent content within logical row/column relationship.
     ///
     /// Lists:
     /// - List items should be tagged with Li tags, if necessary also with
     ///   Lbl and LBody tags.
+    /// - List items of lists with a numbering need to have a Lbl tag.
     /// - Lists should only be used when the content is intended to be read
     ///   as a list.
     ///
@@ -298,6 +336,10 @@ impl Validator {
                 ValidationError::NoDocumentTitle => false,
                 ValidationError::MissingAltText => false,
                 ValidationError::MissingHeadingTitle => false,
+                ValidationError::MissingTableHeaders => false,
+                ValidationError::MissingListLabel => false,
+                ValidationError::MissingCustomTagRole(_) => false,
+                ValidationError::InvalidTabOrder(_) => false,
                 ValidationError::MissingDocumentOutline => false,
                 ValidationError::MissingAnnotationAltText => false,
                 ValidationError::Transparency(_) => true,
@@ -315,6 +357,15 @@ impl Validator {
                 },
                 ValidationError::MissingTagging => *self == Validator::A1_A,
                 ValidationError::MissingDocumentDate => true,
+                ValidationError::Encryption => true,
+                ValidationError::MissingAnnotationAppearance => false,
+                ValidationError::InvalidAnnotationFlags => true,
+                ValidationError::LaunchAction => true,
+                ValidationError::JavaScriptAction => true,
+                ValidationError::DocumentActions => true,
+                ValidationError::MissingDisplayDocTitle => false,
+                ValidationError::CmykOverprintMode(_) => true,
+                ValidationError::Jpeg2000Image(_) => true,
             },
             Validator::A2_A | Validator::A2_B | Validator::A2_U => match validation_error {
                 ValidationError::TooLongString => true,
@@ -335,6 +386,10 @@ impl Validator {
                 ValidationError::NoDocumentTitle => false,
                 ValidationError::MissingAltText => false,
                 ValidationError::MissingHeadingTitle => false,
+                ValidationError::MissingTableHeaders => false,
+                ValidationError::MissingListLabel => false,
+                ValidationError::MissingCustomTagRole(_) => false,
+                ValidationError::InvalidTabOrder(_) => false,
                 ValidationError::MissingDocumentOutline => false,
                 ValidationError::MissingAnnotationAltText => false,
                 ValidationError::Transparency(_) => false,
@@ -352,6 +407,15 @@ impl Validator {
                 },
                 ValidationError::MissingTagging => *self == Validator::A2_A,
                 ValidationError::MissingDocumentDate => true,
+                ValidationError::Encryption => true,
+                ValidationError::MissingAnnotationAppearance => true,
+                ValidationError::InvalidAnnotationFlags => true,
+                ValidationError::LaunchAction => true,
+                ValidationError::JavaScriptAction => true,
+                ValidationError::DocumentActions => true,
+                ValidationError::MissingDisplayDocTitle => false,
+                ValidationError::CmykOverprintMode(_) => true,
+                ValidationError::Jpeg2000Image(_) => false,
             },
             Validator::A3_A | Validator::A3_B | Validator::A3_U => match validation_error {
                 ValidationError::TooLongString => true,
@@ -372,6 +436,10 @@ impl Validator {
                 ValidationError::NoDocumentTitle => false,
                 ValidationError::MissingAltText => false,
                 ValidationError::MissingHeadingTitle => false,
+                ValidationError::MissingTableHeaders => false,
+                ValidationError::MissingListLabel => false,
+                ValidationError::MissingCustomTagRole(_) => false,
+                ValidationError::InvalidTabOrder(_) => false,
                 ValidationError::MissingDocumentOutline => false,
                 ValidationError::MissingAnnotationAltText => false,
                 ValidationError::Transparency(_) => false,
@@ -384,6 +452,15 @@ impl Validator {
                 },
                 ValidationError::MissingTagging => *self == Validator::A3_A,
                 ValidationError::MissingDocumentDate => true,
+                ValidationError::Encryption => true,
+                ValidationError::MissingAnnotationAppearance => true,
+                ValidationError::InvalidAnnotationFlags => true,
+                ValidationError::LaunchAction => true,
+                ValidationError::JavaScriptAction => true,
+                ValidationError::DocumentActions => true,
+                ValidationError::MissingDisplayDocTitle => false,
+                ValidationError::CmykOverprintMode(_) => true,
+                ValidationError::Jpeg2000Image(_) => false,
             },
             Validator::A4 | Validator::A4F | Validator::A4E => match validation_error {
                 ValidationError::TooLongString => false,
@@ -404,6 +481,10 @@ impl Validator {
                 ValidationError::NoDocumentTitle => false,
                 ValidationError::MissingAltText => false,
                 ValidationError::MissingHeadingTitle => false,
+                ValidationError::MissingTableHeaders => false,
+                ValidationError::MissingListLabel => false,
+                ValidationError::MissingCustomTagRole(_) => false,
+                ValidationError::InvalidTabOrder(_) => false,
                 ValidationError::MissingDocumentOutline => false,
                 ValidationError::MissingAnnotationAltText => false,
                 ValidationError::Transparency(_) => false,
@@ -422,6 +503,15 @@ impl Validator {
                 // Only recommended, not required.
                 ValidationError::MissingTagging => false,
                 ValidationError::MissingDocumentDate => true,
+                ValidationError::Encryption => true,
+                ValidationError::MissingAnnotationAppearance => true,
+                ValidationError::InvalidAnnotationFlags => true,
+                ValidationError::LaunchAction => true,
+                ValidationError::JavaScriptAction => true,
+                ValidationError::DocumentActions => true,
+                ValidationError::MissingDisplayDocTitle => false,
+                ValidationError::CmykOverprintMode(_) => true,
+                ValidationError::Jpeg2000Image(_) => false,
             },
             Validator::UA1 => match validation_error {
                 ValidationError::TooLongString => false,
@@ -442,6 +532,10 @@ impl Validator {
                 ValidationError::NoDocumentTitle => true,
                 ValidationError::MissingAltText => true,
                 ValidationError::MissingHeadingTitle => true,
+                ValidationError::MissingTableHeaders => true,
+                ValidationError::MissingListLabel => true,
+                ValidationError::MissingCustomTagRole(_) => true,
+                ValidationError::InvalidTabOrder(_) => true,
                 ValidationError::MissingDocumentOutline => true,
                 ValidationError::MissingAnnotationAltText => true,
                 ValidationError::Transparency(_) => false,
@@ -454,6 +548,15 @@ impl Validator {
                 },
                 ValidationError::MissingTagging => true,
                 ValidationError::MissingDocumentDate => false,
+                ValidationError::Encryption => false,
+                ValidationError::MissingAnnotationAppearance => false,
+                ValidationError::InvalidAnnotationFlags => false,
+                ValidationError::LaunchAction => false,
+                ValidationError::JavaScriptAction => false,
+                ValidationError::DocumentActions => false,
+                ValidationError::MissingDisplayDocTitle => true,
+                ValidationError::CmykOverprintMode(_) => false,
+                ValidationError::Jpeg2000Image(_) => false,
             },
         }
     }
@@ -500,7 +603,7 @@ impl Validator {
         )
     }
 
-    pub(crate) fn write_xmp(&self, xmp: &mut XmpWriter) {
+    pub(crate) fn write_xmp(&self, xmp: &mut XmpWriter, facturx: Option<&FacturX>) {
         // TODO: Also needed for PDF/UA?
         if self.is_pdf_a() {
             let mut extension_schemas = xmp.extension_schemas();
@@ -509,7 +612,16 @@ impl Validator {
                 .properties()
                 .describe_instance_id();
             extension_schemas.pdf().properties().describe_all();
+
+            if let Some(facturx) = facturx {
+                facturx.describe_schema(&mut extension_schemas);
+            }
+
             extension_schemas.finish();
+
+            if let Some(facturx) = facturx {
+                facturx.write_xmp(xmp);
+            }
         }
 
         match self {
@@ -704,6 +816,10 @@ impl Validator {
         }
     }
 
+    pub(crate) fn is_pdf_a3(&self) -> bool {
+        matches!(self, Validator::A3_A | Validator::A3_B | Validator::A3_U)
+    }
+
     pub(crate) fn allows_associated_files(&self) -> bool {
         match self {
             // PDF 2.0 _does_ support associated files. However, in this case the document has to
@@ -64,23 +64,33 @@ impl PdfVersion {
     }
 
     pub(crate) fn supports_icc(&self, metadata: &ICCMetadata) -> bool {
-        match self {
-            PdfVersion::Pdf14 => metadata.major <= 2 && metadata.minor <= 2,
-            PdfVersion::Pdf15 => metadata.major <= 4,
-            PdfVersion::Pdf16 => metadata.major <= 4 && metadata.minor <= 1,
-            PdfVersion::Pdf17 => metadata.major <= 4 && metadata.minor <= 2,
-            PdfVersion::Pdf20 => metadata.major <= 4 && metadata.minor <= 2,
-        }
+        let max = match self {
+            PdfVersion::Pdf14 => (2, 2),
+            PdfVersion::Pdf15 => (4, 0),
+            PdfVersion::Pdf16 => (4, 1),
+            PdfVersion::Pdf17 => (4, 2),
+            PdfVersion::Pdf20 => (4, 2),
+        };
+
+        (metadata.major, metadata.minor) <= max
     }
 
     #[cfg(feature = "raster-images")]
     pub(crate) fn supports_bit_depth(&self, bits_per_component: BitsPerComponent) -> bool {
         match bits_per_component {
-            BitsPerComponent::Eight => true,
+            BitsPerComponent::One
+            | BitsPerComponent::Two
+            | BitsPerComponent::Four
+            | BitsPerComponent::Eight => true,
             BitsPerComponent::Sixteen => *self >= PdfVersion::Pdf15,
         }
     }
 
+    #[cfg(feature = "raster-images")]
+    pub(crate) fn supports_jpx(&self) -> bool {
+        *self >= PdfVersion::Pdf15
+    }
+
     pub(crate) fn set_version(&self, pdf: &mut Pdf) {
         match self {
             PdfVersion::Pdf14 => pdf.set_version(1, 4),
@@ -10,12 +10,12 @@ use pdf_writer::types::TextRenderingMode;
 use pdf_writer::{Content, Finish, Name, Str, TextStr};
 use tiny_skia_path::{Path, PathSegment};
 
-use crate::color::rgb;
 use crate::configure::ValidationError;
 #[cfg(feature = "raster-images")]
 use crate::geom::Size;
 use crate::geom::{Point, Rect, Transform};
-use crate::graphics::color::{Color, ColorSpace};
+use crate::graphics::blend::{GroupColorSpace, OverprintMode};
+use crate::graphics::color::{Color, ColorSpace, RenderingIntent};
 use crate::graphics::graphics_state::{ExtGState, GraphicsStates};
 #[cfg(feature = "raster-images")]
 use crate::graphics::image::Image;
@@ -25,20 +25,27 @@ use crate::graphics::shading_function::{
     GradientProperties, GradientPropertiesExt, ShadingFunction,
 };
 use crate::graphics::shading_pattern::ShadingPattern;
-use crate::graphics::tiling_pattern::TilingPattern;
+use crate::graphics::tiling_pattern::{TilingPattern, UncoloredPatternColorSpace};
 use crate::graphics::xobject::XObject;
-use crate::interchange::tagging::ContentTag;
+use crate::interchange::tagging::{ArtifactType, ContentTag};
 use crate::num::NormalizedF32;
 use crate::resource;
 use crate::resource::{Resource, ResourceDictionaryBuilder};
 use crate::serialize::{MaybeDeviceColorSpace, SerializeContext};
 use crate::stream::Stream;
-use crate::text::group::{use_text_spanner, GlyphGroup, GlyphGrouper, GlyphSpan, GlyphSpanner};
-use crate::text::type3::ColoredGlyph;
+use crate::text::cid::vertical_metrics;
+use crate::text::group::{
+    glyph_text, use_text_spanner, GlyphGroup, GlyphGrouper, GlyphSpan, GlyphSpanner,
+};
+use crate::text::type3::{ColoredGlyph, GlyphColors};
 use crate::text::{Font, FontContainer, FontIdentifier, PdfFont, PDF_UNITS_PER_EM};
-use crate::text::{Glyph, GlyphId};
+use crate::text::{Glyph, GlyphId, TextSpacing, WritingMode};
 use crate::util::{calculate_stroke_bbox, NameExt};
 
+/// A line of glyphs in the vertical writing mode, consisting of its start and the
+/// encoded glyphs of each run, together with the adjustment that precedes them.
+type VerticalLine = (Point, Vec<(f32, Vec<u8>)>);
+
 pub(crate) struct ContentBuilder {
     rd_builder: ResourceDictionaryBuilder,
     content: Content,
@@ -52,6 +59,13 @@ pub(crate) struct ContentBuilder {
     // need it if automatic size detection is enabled.
     bbox_important: bool,
     pub(crate) active_marked_content: bool,
+    /// The bbox of the content drawn in the current marked content section.
+    marked_content_bbox: Option<Rect>,
+    /// Whether a section with an actual text has been started.
+    active_actual_text: bool,
+    /// Whether the content stream defines the tile of an uncolored tiling pattern,
+    /// in which case it must not specify any colors.
+    pub(crate) uncolored: bool,
 }
 
 /// Stores either a device-specific color space,
@@ -73,6 +87,9 @@ impl ContentBuilder {
             graphics_states: GraphicsStates::new(),
             bbox: None,
             active_marked_content: false,
+            marked_content_bbox: None,
+            active_actual_text: false,
+            uncolored: false,
         }
     }
 
@@ -104,6 +121,7 @@ impl ContentBuilder {
         }
 
         self.active_marked_content = true;
+        self.marked_content_bbox = None;
     }
 
     #[track_caller]
@@ -133,19 +151,49 @@ impl ContentBuilder {
         tag.write_properties(sc, properties);
     }
 
-    pub(crate) fn end_marked_content(&mut self) {
+    /// End the current marked content section and return the bbox of the content
+    /// drawn in it, if any.
+    pub(crate) fn end_marked_content(&mut self) -> Option<Rect> {
         if !self.active_marked_content {
             panic!("can't end marked content when none has been started");
         }
 
         self.content.end_marked_content();
         self.active_marked_content = false;
+        self.marked_content_bbox.take()
+    }
+
+    // In contrast to tagged content, actual text can be nested inside of other marked
+    // content, so it is tracked separately.
+    pub(crate) fn start_actual_text(&mut self, text: &str) {
+        if self.active_actual_text {
+            panic!("can't start actual text twice");
+        }
+
+        let mut mc = self
+            .content
+            .begin_marked_content_with_properties(Name(b"Span"));
+        mc.properties().actual_text(TextStr(text));
+        self.active_actual_text = true;
+    }
+
+    pub(crate) fn end_actual_text(&mut self) {
+        if !self.active_actual_text {
+            panic!("can't end actual text when none has been started");
+        }
+
+        self.content.end_marked_content();
+        self.active_actual_text = false;
     }
 
     pub(crate) fn concat_transform(&mut self, transform: &Transform) {
         self.graphics_states.transform(*transform);
     }
 
+    pub(crate) fn root_transform(&self) -> Transform {
+        self.root_transform
+    }
+
     fn cur_transform_with_root_transform(&self) -> Transform {
         self.root_transform.pre_concat(self.cur_transform())
     }
@@ -169,6 +217,21 @@ impl ContentBuilder {
         }
     }
 
+    pub(crate) fn set_rendering_intent(&mut self, rendering_intent: RenderingIntent) {
+        let state = ExtGState::new().rendering_intent(rendering_intent);
+        self.graphics_states.combine(&state);
+    }
+
+    pub(crate) fn set_overprint(&mut self, fill: bool, stroke: bool, mode: OverprintMode) {
+        let state = ExtGState::new().overprint(fill, stroke, mode);
+        self.graphics_states.combine(&state);
+    }
+
+    pub(crate) fn set_black_point_compensation(&mut self, black_point_compensation: bool) {
+        let state = ExtGState::new().black_point_compensation(black_point_compensation);
+        self.graphics_states.combine(&state);
+    }
+
     pub(crate) fn expand_bbox(&mut self, new_bbox: Rect) {
         let new_bbox = self.graphics_states.transform_bbox(new_bbox);
         if let Some(bbox) = &mut self.bbox {
@@ -176,6 +239,14 @@ impl ContentBuilder {
         } else {
             self.bbox = Some(new_bbox);
         }
+
+        if self.active_marked_content {
+            if let Some(bbox) = &mut self.marked_content_bbox {
+                bbox.expand(&new_bbox);
+            } else {
+                self.marked_content_bbox = Some(new_bbox);
+            }
+        }
     }
 
     pub(crate) fn draw_path(
@@ -212,7 +283,10 @@ impl ContentBuilder {
         let calculate_bbox = |is_solid: bool| bbox_important || !is_solid;
 
         let stroke_bbox = |stroke: &Stroke| {
-            if calculate_bbox(matches!(&stroke.paint.0, InnerPaint::Color(_))) {
+            if calculate_bbox(matches!(
+                &stroke.paint.0,
+                InnerPaint::Color(_) | InnerPaint::Spot(_)
+            )) {
                 calculate_stroke_bbox(stroke, path).unwrap_or(Rect::from_tsp(path.bounds()))
             } else {
                 Rect::from_tsp(path.bounds())
@@ -338,6 +412,107 @@ impl ContentBuilder {
         self.content.end_path();
     }
 
+    /// Intersect the current clip path with the outlines of the given glyphs, by
+    /// drawing them in the clipping text rendering mode.
+    ///
+    /// Like clip paths, the clip remains active until `pop_clip_path` is called.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn push_glyph_clip(
+        &mut self,
+        start: Point,
+        sc: &mut SerializeContext,
+        colors: GlyphColors,
+        glyphs: &[impl Glyph],
+        font: Font,
+        text: &str,
+        font_size: f32,
+        spacing: TextSpacing,
+        writing_mode: WritingMode,
+        mark_artifact: bool,
+    ) {
+        self.content_save_state();
+
+        let transform = self.cur_transform_with_root_transform();
+
+        // An empty text object doesn't clip anything at all, but we want everything
+        // to become invisible if there is nothing to clip to, same as an empty clip path.
+        let inverse = match transform.invert() {
+            Some(inverse) if !glyphs.is_empty() => inverse,
+            _ => {
+                self.content.rect(0.0, 0.0, 0.0, 0.0);
+                self.content.clip_nonzero();
+                self.content.end_path();
+                return;
+            }
+        };
+
+        if mark_artifact {
+            self.start_marked_content(ContentTag::Artifact(ArtifactType::Other, None).name());
+        }
+
+        // In contrast to other drawing operations, we can't wrap the text in its own
+        // save/restore pair, since that would also reset the clip. Because of this,
+        // the transform is undone manually afterward.
+        if transform != Transform::identity() {
+            self.content.transform(transform.to_pdf_transform());
+        }
+
+        self.content.begin_text();
+        self.encode_glyph_spans(
+            start.x,
+            start.y,
+            sc,
+            TextRenderingMode::Clip,
+            glyphs,
+            font,
+            colors,
+            text,
+            font_size,
+            spacing,
+            writing_mode,
+        );
+        self.content.end_text();
+
+        if transform != Transform::identity() {
+            self.content.transform(inverse.to_pdf_transform());
+        }
+
+        if mark_artifact {
+            self.end_marked_content();
+        }
+    }
+
+    /// Draw the given glyphs in the invisible text rendering mode, meaning that they
+    /// are neither filled nor stroked, but can still be selected and extracted.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn draw_invisible_glyphs(
+        &mut self,
+        start: Point,
+        sc: &mut SerializeContext,
+        colors: GlyphColors,
+        glyphs: &[impl Glyph],
+        font: Font,
+        text: &str,
+        font_size: f32,
+        spacing: TextSpacing,
+        writing_mode: WritingMode,
+    ) {
+        self.fill_stroke_glyph_run(
+            start.x,
+            start.y,
+            sc,
+            TextRenderingMode::Invisible,
+            |_, _| {},
+            glyphs,
+            font,
+            colors,
+            text,
+            font_size,
+            spacing,
+            writing_mode,
+        );
+    }
+
     pub(crate) fn pop_clip_path(&mut self) {
         self.content.restore_state();
     }
@@ -349,11 +524,13 @@ impl ContentBuilder {
         sc: &mut SerializeContext,
         fill: Option<&Fill>,
         stroke: Option<&Stroke>,
-        context_color: rgb::Color,
+        colors: GlyphColors,
         glyphs: &[impl Glyph],
         font: Font,
         text: &str,
         font_size: f32,
+        spacing: TextSpacing,
+        writing_mode: WritingMode,
     ) {
         if fill.is_none() && stroke.is_none() {
             return;
@@ -363,13 +540,18 @@ impl ContentBuilder {
         self.graphics_states.save_state();
 
         // Calculating the glyphs bbox is very expensive but not always necessary, so omit
-        // if not needed.
-        let bbox_important = self.bbox_important;
+        // if not needed. Tagged content always needs it, since tag groups can have
+        // a bbox that is computed from their content.
+        let bbox_important = self.bbox_important || self.active_marked_content;
         let calculate_bbox = |is_solid: bool| bbox_important || !is_solid;
+        let glyphs_bbox = || get_glyphs_bbox(glyphs, x, y, font_size, font.clone(), text, spacing);
 
         let fill_action = |sb: &mut ContentBuilder, sc: &mut SerializeContext, fill: &Fill| {
-            let bbox = if calculate_bbox(matches!(&fill.paint.0, InnerPaint::Color(_))) {
-                let bbox = get_glyphs_bbox(glyphs, x, y, font_size, font.clone());
+            let bbox = if calculate_bbox(matches!(
+                &fill.paint.0,
+                InnerPaint::Color(_) | InnerPaint::Spot(_)
+            )) {
+                let bbox = glyphs_bbox();
                 sb.expand_bbox(bbox);
                 bbox
             } else {
@@ -381,9 +563,12 @@ impl ContentBuilder {
 
         let stroke_action =
             |sb: &mut ContentBuilder, sc: &mut SerializeContext, stroke: &Stroke| {
-                let bbox = if calculate_bbox(matches!(&stroke.paint.0, InnerPaint::Color(_))) {
+                let bbox = if calculate_bbox(matches!(
+                    &stroke.paint.0,
+                    InnerPaint::Color(_) | InnerPaint::Spot(_)
+                )) {
                     // TODO: Bbox should also account for stroke.
-                    let bbox = get_glyphs_bbox(glyphs, x, y, font_size, font.clone());
+                    let bbox = glyphs_bbox();
                     sb.expand_bbox(bbox);
                     bbox
                 } else {
@@ -430,9 +615,11 @@ impl ContentBuilder {
                     },
                     glyphs,
                     font.clone(),
-                    context_color,
+                    colors,
                     text,
                     font_size,
+                    spacing,
+                    writing_mode,
                 );
             }
             (Some(f), None) => {
@@ -448,9 +635,11 @@ impl ContentBuilder {
                     },
                     glyphs,
                     font.clone(),
-                    context_color,
+                    colors,
                     text,
                     font_size,
+                    spacing,
+                    writing_mode,
                 );
             }
             (None, Some(s)) => {
@@ -466,9 +655,11 @@ impl ContentBuilder {
                     },
                     glyphs,
                     font.clone(),
-                    context_color,
+                    colors,
                     text,
                     font_size,
+                    spacing,
+                    writing_mode,
                 );
             }
             (None, None) => unreachable!(),
@@ -486,45 +677,57 @@ impl ContentBuilder {
         sc: &mut SerializeContext,
         cur_x: &mut f32,
         cur_y: f32,
+        rise: f32,
+        state: &mut TextObjectState,
         font_identifier: FontIdentifier,
         pdf_font: &dyn PdfFont,
         size: f32,
-        context_color: rgb::Color,
+        colors: GlyphColors,
         glyphs: &[impl Glyph],
         text: &str,
+        spacing: TextSpacing,
     ) {
         let font_name = self
             .rd_builder
-            .register_resource(sc.register_font_identifier(font_identifier));
-        self.content.set_font(font_name.to_pdf_name(), size);
-        self.content.set_text_matrix(
-            Transform::from_row(1.0, 0.0, 0.0, -1.0, *cur_x, cur_y).to_pdf_transform(),
-        );
+            .register_resource(sc.register_font_identifier(font_identifier.clone()));
+
+        // If the previous run ended on the same baseline and was only separated
+        // because of a different y offset, we continue where it left off and shift
+        // the glyphs using the text rise instead, so that they stay in the same line
+        // for text extraction.
+        let font = (font_identifier, size);
+        let continues = state.baseline == Some(cur_y) && state.rise != rise;
+
+        if !continues || state.font.as_ref() != Some(&font) {
+            self.content.set_font(font_name.to_pdf_name(), size);
+        }
+
+        if !continues {
+            self.content.set_text_matrix(
+                Transform::from_row(1.0, 0.0, 0.0, -1.0, *cur_x, cur_y).to_pdf_transform(),
+            );
+            state.adjustment = 0.0;
+        }
+
+        state.font = Some(font);
+        state.baseline = Some(cur_y);
+
+        if state.rise != rise {
+            self.content.set_rise(rise);
+            state.rise = rise;
+        }
 
         let mut positioned = self.content.show_positioned();
         let mut items = positioned.items();
 
-        let mut adjustment = 0.0;
+        let mut adjustment = state.adjustment;
         let mut encoded = vec![];
 
         for glyph in glyphs {
-            match glyph.location() {
-                None => sc.reset_location(),
-                Some(l) => sc.set_location(l),
-            };
-
-            if glyph.glyph_id() == GlyphId::new(0)
-                || pdf_font.font().postscript_name() == Some("LastResort")
-            {
-                sc.register_validation_error(ValidationError::ContainsNotDefGlyph(
-                    pdf_font.font(),
-                    sc.location,
-                    text[glyph.text_range()].to_string(),
-                ));
-            }
+            validate_glyph(sc, pdf_font, glyph, text);
 
             let pdf_glyph = pdf_font
-                .get_gid(ColoredGlyph::new(glyph.glyph_id(), context_color))
+                .get_gid(ColoredGlyph::new(glyph.glyph_id(), colors))
                 .unwrap();
 
             let scale = |val| val * pdf_font.units_per_em();
@@ -556,9 +759,17 @@ impl ContentBuilder {
                 adjustment += x_advance - font_advance;
             }
 
+            // The word spacing operator only applies to the single-byte character
+            // code 32, which is never used for spaces in our fonts. Because of this,
+            // word spacing is applied using adjustments instead.
+            let word_spacing = spacing.word_spacing(glyph, text);
+            if word_spacing != 0.0 && size != 0.0 {
+                adjustment += scale(word_spacing / size);
+            }
+
             adjustment -= x_offset;
             // cur_x/cur_y and glyph metrics are in user space units.
-            *cur_x += glyph.x_advance(size);
+            *cur_x += spacing.x_advance(glyph, text, size);
 
             sc.reset_location();
         }
@@ -569,6 +780,119 @@ impl ContentBuilder {
 
         items.finish();
         positioned.finish();
+
+        // The remaining adjustment is applied at the start of the next run, in case
+        // it continues on the same baseline.
+        state.adjustment = adjustment;
+    }
+
+    /// Encode a sequence of glyphs that use a font in the vertical writing mode.
+    ///
+    /// Each glyph is placed at its vertical origin, from which the PDF viewer moves
+    /// down by the vertical advance stored in the font. Deviating y advances and
+    /// offsets are applied using adjustments, while glyphs with a different x
+    /// position require a new text matrix, since adjustments in the vertical writing
+    /// mode only move the glyphs vertically.
+    #[allow(clippy::too_many_arguments)]
+    fn encode_vertical_glyph_run(
+        &mut self,
+        sc: &mut SerializeContext,
+        cur_x: &mut f32,
+        cur_y: &mut f32,
+        state: &mut TextObjectState,
+        font_identifier: FontIdentifier,
+        pdf_font: &dyn PdfFont,
+        size: f32,
+        colors: GlyphColors,
+        glyphs: &[impl Glyph],
+        text: &str,
+    ) {
+        let font_name = self
+            .rd_builder
+            .register_resource(sc.register_font_identifier(font_identifier.clone()));
+
+        let font = (font_identifier, size);
+        if state.font.as_ref() != Some(&font) {
+            self.content.set_font(font_name.to_pdf_name(), size);
+        }
+
+        // Since the text position doesn't end on a baseline, the next horizontal run
+        // can't continue this one.
+        state.font = Some(font);
+        state.baseline = None;
+        state.adjustment = 0.0;
+
+        if state.rise != 0.0 {
+            self.content.set_rise(0.0);
+            state.rise = 0.0;
+        }
+
+        let otf_font = pdf_font.font();
+        let to_user_units = |val: f32| val / otf_font.units_per_em() * size;
+
+        // The glyphs of each line, together with the adjustment that precedes them, where
+        // a line consists of the glyphs that can be shown with the same text matrix.
+        let mut lines: Vec<VerticalLine> = vec![];
+        // The position the PDF viewer will move to after the last glyph.
+        let mut position = None;
+
+        for glyph in glyphs {
+            validate_glyph(sc, pdf_font, glyph, text);
+
+            let pdf_glyph = pdf_font
+                .get_gid(ColoredGlyph::new(glyph.glyph_id(), colors))
+                .unwrap();
+
+            let (advance, origin) = vertical_metrics(&otf_font, glyph.glyph_id());
+            let width = otf_font.advance_width(glyph.glyph_id()).unwrap_or(0.0);
+
+            // The offsets of the glyphs move their horizontal origin away from the pen
+            // position, which the shapers treat as the vertical origin.
+            let x = *cur_x + glyph.x_offset(size) + to_user_units(width / 2.0);
+            let y = *cur_y - glyph.y_offset(size) - to_user_units(origin);
+
+            match position {
+                Some(Point { x: px, y: py }) if approx_eq!(f32, px, x, epsilon = 0.001) => {
+                    let (_, items) = lines.last_mut().unwrap();
+                    let adjustment = y - py;
+
+                    if !approx_eq!(f32, adjustment, 0.0, epsilon = 0.001) && size != 0.0 {
+                        // Positive adjustments move down in the vertical writing mode.
+                        items.push((adjustment / size * PDF_UNITS_PER_EM, vec![]));
+                    }
+                }
+                _ => lines.push((Point::from_xy(x, y), vec![(0.0, vec![])])),
+            }
+
+            let (_, items) = lines.last_mut().unwrap();
+            pdf_glyph.encode_into(&mut items.last_mut().unwrap().1);
+
+            position = Some(Point::from_xy(x, y + to_user_units(advance)));
+            *cur_x += glyph.x_advance(size);
+            *cur_y -= glyph.y_advance(size);
+
+            sc.reset_location();
+        }
+
+        for (start, items) in lines {
+            self.content.set_text_matrix(
+                Transform::from_row(1.0, 0.0, 0.0, -1.0, start.x, start.y).to_pdf_transform(),
+            );
+
+            let mut positioned = self.content.show_positioned();
+            let mut writer = positioned.items();
+
+            for (adjustment, encoded) in items {
+                if adjustment != 0.0 {
+                    writer.adjust(adjustment);
+                }
+
+                writer.show(Str(&encoded));
+            }
+
+            writer.finish();
+            positioned.finish();
+        }
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -581,9 +905,11 @@ impl ContentBuilder {
         action: impl FnOnce(&mut ContentBuilder, &mut SerializeContext),
         glyphs: &[impl Glyph],
         font: Font,
-        context_color: rgb::Color,
+        colors: GlyphColors,
         text: &str,
         font_size: f32,
+        spacing: TextSpacing,
+        writing_mode: WritingMode,
     ) {
         if glyphs.is_empty() {
             return;
@@ -592,80 +918,147 @@ impl ContentBuilder {
         self.apply_isolated_op(
             |_, _| {},
             |sb, sc| {
-                let mut cur_x = x;
-                let mut cur_y = ys;
-
                 action(sb, sc);
                 sb.content.begin_text();
-
-                let font_container = sc.register_font_container(font.clone());
-                let do_text_span = use_text_spanner(
+                sb.encode_glyph_spans(
+                    x,
+                    ys,
+                    sc,
+                    fill_render_mode,
                     glyphs,
+                    font,
+                    colors,
                     text,
-                    context_color,
-                    &mut font_container.borrow_mut(),
+                    font_size,
+                    spacing,
+                    writing_mode,
                 );
-
-                if do_text_span {
-                    // Separate into distinct glyph runs that either are encoded using actual text, or are
-                    // not.
-                    let spanned = GlyphSpanner::new(
-                        glyphs,
-                        text,
-                        sc.serialize_settings()
-                            .validator()
-                            .requires_codepoint_mappings(),
-                        context_color,
-                        font_container.clone(),
-                    );
-
-                    for fragment in spanned {
-                        sb.fill_stroke_glyph_span(
-                            &mut cur_x,
-                            &mut cur_y,
-                            fragment,
-                            sc,
-                            fill_render_mode,
-                            font_container.clone(),
-                            context_color,
-                            text,
-                            font_size,
-                        )
-                    }
-                } else {
-                    let glyph_span = GlyphSpan::Unspanned(glyphs);
-
-                    sb.fill_stroke_glyph_span(
-                        &mut cur_x,
-                        &mut cur_y,
-                        glyph_span,
-                        sc,
-                        fill_render_mode,
-                        font_container.clone(),
-                        context_color,
-                        text,
-                        font_size,
-                    )
-                }
-
                 sb.content.end_text();
             },
             sc,
         )
     }
 
+    /// Encode the text showing operators for the given glyphs, which must happen
+    /// inside of a text object.
+    #[allow(clippy::too_many_arguments)]
+    fn encode_glyph_spans(
+        &mut self,
+        x: f32,
+        y: f32,
+        sc: &mut SerializeContext,
+        fill_render_mode: TextRenderingMode,
+        glyphs: &[impl Glyph],
+        font: Font,
+        colors: GlyphColors,
+        text: &str,
+        font_size: f32,
+        spacing: TextSpacing,
+        writing_mode: WritingMode,
+    ) {
+        let mut cur_x = x;
+        let mut cur_y = y;
+        let mut state = TextObjectState::default();
+
+        // Character spacing and horizontal scaling are applied by the PDF viewer and
+        // are already accounted for in `cur_x`, so they aren't part of the adjustments.
+        if spacing.char_spacing != 0.0 {
+            self.content.set_char_spacing(spacing.char_spacing);
+        }
+
+        if spacing.horizontal_scaling != 1.0 {
+            self.content
+                .set_horizontal_scaling(spacing.horizontal_scaling * 100.0);
+        }
+
+        let font_container = sc.register_font_container(font);
+        let do_text_span = use_text_spanner(
+            glyphs,
+            text,
+            colors,
+            writing_mode,
+            &mut font_container.borrow_mut(),
+        );
+
+        if do_text_span {
+            // Separate into distinct glyph runs that either are encoded using actual text, or are
+            // not.
+            let spanned = GlyphSpanner::new(
+                glyphs,
+                text,
+                sc.serialize_settings()
+                    .validator()
+                    .requires_codepoint_mappings(),
+                colors,
+                font_container.clone(),
+                writing_mode,
+            );
+
+            for fragment in spanned {
+                self.fill_stroke_glyph_span(
+                    &mut cur_x,
+                    &mut cur_y,
+                    &mut state,
+                    fragment,
+                    sc,
+                    fill_render_mode,
+                    font_container.clone(),
+                    colors,
+                    text,
+                    font_size,
+                    spacing,
+                    writing_mode,
+                )
+            }
+        } else {
+            let glyph_span = GlyphSpan::Unspanned(glyphs);
+
+            self.fill_stroke_glyph_span(
+                &mut cur_x,
+                &mut cur_y,
+                &mut state,
+                glyph_span,
+                sc,
+                fill_render_mode,
+                font_container.clone(),
+                colors,
+                text,
+                font_size,
+                spacing,
+                writing_mode,
+            )
+        }
+
+        // The text state isn't always restored afterward, for example when clipping,
+        // so it needs to be reset manually.
+        if state.rise != 0.0 {
+            self.content.set_rise(0.0);
+        }
+
+        if spacing.char_spacing != 0.0 {
+            self.content.set_char_spacing(0.0);
+        }
+
+        if spacing.horizontal_scaling != 1.0 {
+            self.content.set_horizontal_scaling(100.0);
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn fill_stroke_glyph_span(
         &mut self,
         cur_x: &mut f32,
         cur_y: &mut f32,
+        state: &mut TextObjectState,
         fragment: GlyphSpan<'_, impl Glyph>,
         sc: &mut SerializeContext,
         fill_render_mode: TextRenderingMode,
         font_container: Rc<RefCell<FontContainer>>,
-        context_color: rgb::Color,
+        colors: GlyphColors,
         text: &str,
         font_size: f32,
+        spacing: TextSpacing,
+        writing_mode: WritingMode,
     ) {
         if let Some(text) = fragment.actual_text() {
             let mut actual_text = self
@@ -676,19 +1069,26 @@ impl ContentBuilder {
 
         // Segment into glyph runs that can be encoded in one go using a PDF
         // text showing operator (i.e. no y shift, same Type3 font, etc.)
-        let segmented = GlyphGrouper::new(font_container.clone(), context_color, fragment.glyphs());
+        let segmented = GlyphGrouper::new(
+            font_container.clone(),
+            colors,
+            writing_mode,
+            fragment.glyphs(),
+        );
 
         for glyph_group in segmented {
             self.fill_stroke_glyph_group(
                 cur_x,
                 cur_y,
+                state,
                 glyph_group,
                 sc,
                 fill_render_mode,
                 font_container.clone(),
-                context_color,
+                colors,
                 text,
                 font_size,
+                spacing,
             )
         }
 
@@ -702,20 +1102,29 @@ impl ContentBuilder {
         &mut self,
         cur_x: &mut f32,
         cur_y: &mut f32,
+        state: &mut TextObjectState,
         glyph_group: GlyphGroup<'_, impl Glyph>,
         sc: &mut SerializeContext,
         fill_render_mode: TextRenderingMode,
         font_container: Rc<RefCell<FontContainer>>,
-        context_color: rgb::Color,
+        colors: GlyphColors,
         text: &str,
         font_size: f32,
+        spacing: TextSpacing,
     ) {
         let borrowed = font_container.borrow();
         let pdf_font = borrowed
             .get_from_identifier(glyph_group.font_identifier.clone())
             .unwrap();
 
-        if fill_render_mode == TextRenderingMode::Fill || pdf_font.force_fill() {
+        if matches!(
+            fill_render_mode,
+            TextRenderingMode::Clip | TextRenderingMode::Invisible
+        ) {
+            // Type 3 glyphs can't be forced to fill here, since that would paint them
+            // instead of only using them for clipping or leaving them invisible.
+            self.content.set_text_rendering_mode(fill_render_mode);
+        } else if fill_render_mode == TextRenderingMode::Fill || pdf_font.force_fill() {
             self.content
                 .set_text_rendering_mode(TextRenderingMode::Fill);
         } else if fill_render_mode == TextRenderingMode::FillStroke {
@@ -726,19 +1135,37 @@ impl ContentBuilder {
                 .set_text_rendering_mode(TextRenderingMode::Stroke);
         }
 
-        self.encode_consecutive_glyph_run(
-            sc,
-            cur_x,
-            *cur_y - glyph_group.y_offset * font_size,
-            glyph_group.font_identifier,
-            pdf_font,
-            font_size,
-            context_color,
-            glyph_group.glyphs,
-            text,
-        );
+        if glyph_group.font_identifier.is_vertical() {
+            self.encode_vertical_glyph_run(
+                sc,
+                cur_x,
+                cur_y,
+                state,
+                glyph_group.font_identifier,
+                pdf_font,
+                font_size,
+                colors,
+                glyph_group.glyphs,
+                text,
+            );
+        } else {
+            self.encode_consecutive_glyph_run(
+                sc,
+                cur_x,
+                *cur_y,
+                glyph_group.y_offset * font_size,
+                state,
+                glyph_group.font_identifier,
+                pdf_font,
+                font_size,
+                colors,
+                glyph_group.glyphs,
+                text,
+                spacing,
+            );
 
-        *cur_y -= glyph_group.y_advance * font_size;
+            *cur_y -= glyph_group.y_advance * font_size;
+        }
     }
 
     pub(crate) fn draw_xobject(
@@ -788,13 +1215,65 @@ impl ContentBuilder {
         self.draw_xobject(sc, x_object, &state);
     }
 
+    pub(crate) fn draw_transparency_group(
+        &mut self,
+        sc: &mut SerializeContext,
+        isolated: bool,
+        knockout: bool,
+        color_space: GroupColorSpace,
+        stream: Stream,
+    ) {
+        let state = ExtGState::new();
+        let x_object = XObject::new_transparency_group(stream, isolated, knockout, color_space);
+        self.draw_xobject(sc, x_object, &state);
+    }
+
     #[cfg(feature = "raster-images")]
-    pub(crate) fn draw_image(&mut self, image: Image, size: Size, sc: &mut SerializeContext) {
+    pub(crate) fn draw_image(
+        &mut self,
+        image: Image,
+        size: Size,
+        fill: Option<&Fill>,
+        sc: &mut SerializeContext,
+    ) {
+        // Scale the image from 1x1 to the actual dimensions.
+        let transform = image.transform(size);
+
+        if image.is_stencil() {
+            let fill = fill.cloned().unwrap_or_default();
+            let bounds = Rect::from_xywh(0.0, 0.0, size.width(), size.height()).unwrap();
+            // See the comment in `draw_path`.
+            let fill_opacity =
+                (!matches!(fill.paint.0, InnerPaint::Pattern(_))).then_some(fill.opacity);
+
+            self.apply_isolated_op(
+                |sb, _| {
+                    sb.expand_bbox(bounds);
+
+                    if let Some(fill_opacity) = fill_opacity {
+                        sb.set_fill_opacity(fill_opacity);
+                    }
+                },
+                move |sb, sc| {
+                    // The fill needs to be set before scaling the image, so that gradients and
+                    // patterns are positioned relative to the surface and not the image.
+                    sb.content_set_fill_properties(bounds, &fill, sc);
+                    sb.content.transform(transform.to_pdf_transform());
+
+                    let image_name = sb
+                        .rd_builder
+                        .register_resource(resource::XObject::new(sc.register_image(image)));
+
+                    sb.content.x_object(image_name.to_pdf_name());
+                },
+                sc,
+            );
+
+            return;
+        }
+
         self.apply_isolated_op(
             |sb, _| {
-                // Scale the image from 1x1 to the actual dimensions.
-                let transform =
-                    Transform::from_row(size.width(), 0.0, 0.0, -size.height(), 0.0, size.height());
                 sb.concat_transform(&transform);
                 sb.expand_bbox(Rect::from_xywh(0.0, 0.0, 1.0, 1.0).unwrap());
             },
@@ -868,15 +1347,23 @@ impl ContentBuilder {
         self.restore_graphics_state();
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn content_set_fill_stroke_properties(
         &mut self,
         bounds: Rect,
         paint: &Paint,
         opacity: NormalizedF32,
         sc: &mut SerializeContext,
-        mut set_pattern_fn: impl FnMut(&mut Content, String),
+        mut set_pattern_fn: impl FnMut(&mut Content, Option<(String, Color)>, String),
         mut set_solid_fn: impl FnMut(&mut Content, ContentColorSpace, Color),
+        mut set_tint_fn: impl FnMut(&mut Content, String, &[f32]),
     ) {
+        // The content of uncolored tiling patterns must not specify any colors, since
+        // the color is only chosen once the pattern is used.
+        if self.uncolored {
+            return;
+        }
+
         let pattern_transform = |transform: Transform| -> Transform {
             transform.post_concat(self.cur_transform_with_root_transform())
         };
@@ -919,7 +1406,7 @@ impl ContentBuilder {
                         content_builder.content.set_parameters(ext.to_pdf_name());
                     }
 
-                    set_pattern_fn(&mut content_builder.content, color_space);
+                    set_pattern_fn(&mut content_builder.content, None, color_space);
                 }
             };
 
@@ -929,6 +1416,18 @@ impl ContentBuilder {
                 let color_space_resource = Self::cs_to_content_cs(self, sc, cs);
                 set_solid_fn(&mut self.content, color_space_resource, *c);
             }
+            InnerPaint::Spot(s) => {
+                let color_space = self
+                    .rd_builder
+                    .register_resource(sc.register_resourceable(s.color_space()));
+                set_tint_fn(&mut self.content, color_space, &[s.to_pdf_color()]);
+            }
+            InnerPaint::DeviceN(d) => {
+                let color_space = self
+                    .rd_builder
+                    .register_resource(sc.register_resourceable(d.color_space.clone()));
+                set_tint_fn(&mut self.content, color_space, &d.to_pdf_color());
+            }
             InnerPaint::LinearGradient(lg) => {
                 let (gradient_props, transform) = lg.clone().gradient_properties(bounds);
                 write_gradient(gradient_props, sc, transform, self);
@@ -941,23 +1440,31 @@ impl ContentBuilder {
                 let (gradient_props, transform) = sg.clone().gradient_properties(bounds);
                 write_gradient(gradient_props, sc, transform, self);
             }
+            InnerPaint::MeshGradient(mg) => {
+                let (gradient_props, transform) = mg.clone().gradient_properties(bounds);
+                write_gradient(gradient_props, sc, transform, self);
+            }
             InnerPaint::Pattern(pat) => {
                 let mut pat = Arc::unwrap_or_clone(pat.clone());
                 pat.transform = pattern_transform(pat.transform);
+                let color = pat.color;
 
-                let tiling_pattern = TilingPattern::new(
-                    pat.stream,
-                    pat.transform,
-                    opacity,
-                    pat.width,
-                    pat.height,
-                    sc,
-                );
-
-                let color_space = self.rd_builder.register_resource::<resource::Pattern>(
+                let tiling_pattern = TilingPattern::new(pat, opacity, sc);
+                let pattern = self.rd_builder.register_resource::<resource::Pattern>(
                     sc.register_resourceable(tiling_pattern),
                 );
-                set_pattern_fn(&mut self.content, color_space);
+
+                // Uncolored patterns need a pattern color space with the color space
+                // of the color they are painted with as the base.
+                let color = color.map(|color| {
+                    let cs = UncoloredPatternColorSpace(color.color_space(sc));
+                    let color_space = self
+                        .rd_builder
+                        .register_resource(sc.register_resourceable(cs));
+                    (color_space, color)
+                });
+
+                set_pattern_fn(&mut self.content, color, pattern);
             }
         }
     }
@@ -977,15 +1484,40 @@ impl ContentBuilder {
         }
     }
 
+    /// PDF/A forbids the nonzero overprint mode for ICC-based CMYK colors
+    /// that are overprinted.
+    fn check_overprint(&self, paint: &Paint, stroke: bool, sc: &mut SerializeContext) {
+        if self
+            .graphics_states
+            .cur()
+            .ext_g_state()
+            .nonzero_overprint(stroke)
+            && sc.serialize_settings().cmyk_profile.is_some()
+            && paint.has_cmyk()
+        {
+            sc.register_validation_error(ValidationError::CmykOverprintMode(sc.location));
+        }
+    }
+
     fn content_set_fill_properties(
         &mut self,
         bounds: Rect,
         fill: &Fill,
         serializer_context: &mut SerializeContext,
     ) {
-        fn set_pattern_fn(content: &mut Content, color_space: String) {
-            content.set_fill_color_space(pdf_writer::types::ColorSpaceOperand::Pattern);
-            content.set_fill_pattern(None, color_space.to_pdf_name());
+        self.check_overprint(&fill.paint, false, serializer_context);
+
+        fn set_pattern_fn(content: &mut Content, color: Option<(String, Color)>, pattern: String) {
+            match color {
+                None => {
+                    content.set_fill_color_space(pdf_writer::types::ColorSpaceOperand::Pattern);
+                    content.set_fill_pattern(None, pattern.to_pdf_name());
+                }
+                Some((color_space, color)) => {
+                    content.set_fill_color_space(color_space.to_pdf_name());
+                    content.set_fill_pattern(color.to_pdf_color(), pattern.to_pdf_name());
+                }
+            }
         }
 
         fn set_solid_fn(content: &mut Content, color_space: ContentColorSpace, color: Color) {
@@ -1002,6 +1534,7 @@ impl ContentBuilder {
                         let comps = c.to_pdf_color();
                         content.set_fill_cmyk(comps[0], comps[1], comps[2], comps[3]);
                     }
+                    Color::Lab(_) => unreachable!("lab colors are never device colors"),
                 },
                 ContentColorSpace::Named(n) => {
                     content.set_fill_color_space(n.to_pdf_name());
@@ -1010,6 +1543,11 @@ impl ContentBuilder {
             }
         }
 
+        fn set_tint_fn(content: &mut Content, color_space: String, tints: &[f32]) {
+            content.set_fill_color_space(color_space.to_pdf_name());
+            content.set_fill_color(tints.iter().copied());
+        }
+
         self.content_set_fill_stroke_properties(
             bounds,
             &fill.paint,
@@ -1017,6 +1555,7 @@ impl ContentBuilder {
             serializer_context,
             set_pattern_fn,
             set_solid_fn,
+            set_tint_fn,
         );
     }
 
@@ -1026,9 +1565,19 @@ impl ContentBuilder {
         stroke: &Stroke,
         serializer_context: &mut SerializeContext,
     ) {
-        fn set_pattern_fn(content: &mut Content, color_space: String) {
-            content.set_stroke_color_space(pdf_writer::types::ColorSpaceOperand::Pattern);
-            content.set_stroke_pattern(None, color_space.to_pdf_name());
+        self.check_overprint(&stroke.paint, true, serializer_context);
+
+        fn set_pattern_fn(content: &mut Content, color: Option<(String, Color)>, pattern: String) {
+            match color {
+                None => {
+                    content.set_stroke_color_space(pdf_writer::types::ColorSpaceOperand::Pattern);
+                    content.set_stroke_pattern(None, pattern.to_pdf_name());
+                }
+                Some((color_space, color)) => {
+                    content.set_stroke_color_space(color_space.to_pdf_name());
+                    content.set_stroke_pattern(color.to_pdf_color(), pattern.to_pdf_name());
+                }
+            }
         }
 
         fn set_solid_fn(content: &mut Content, color_space: ContentColorSpace, color: Color) {
@@ -1045,6 +1594,7 @@ impl ContentBuilder {
                         let comps = c.to_pdf_color();
                         content.set_stroke_cmyk(comps[0], comps[1], comps[2], comps[3]);
                     }
+                    Color::Lab(_) => unreachable!("lab colors are never device colors"),
                 },
                 ContentColorSpace::Named(n) => {
                     content.set_stroke_color_space(n.to_pdf_name());
@@ -1053,6 +1603,11 @@ impl ContentBuilder {
             }
         }
 
+        fn set_tint_fn(content: &mut Content, color_space: String, tints: &[f32]) {
+            content.set_stroke_color_space(color_space.to_pdf_name());
+            content.set_stroke_color(tints.iter().copied());
+        }
+
         self.content_set_fill_stroke_properties(
             bounds,
             &stroke.paint,
@@ -1060,6 +1615,7 @@ impl ContentBuilder {
             serializer_context,
             set_pattern_fn,
             set_solid_fn,
+            set_tint_fn,
         );
 
         // Only write if they don't correspond to the default values as defined in the
@@ -1082,8 +1638,12 @@ impl ContentBuilder {
         }
 
         if let Some(stroke_dash) = &stroke.dash {
-            self.content
-                .set_dash_pattern(stroke_dash.array.iter().copied(), stroke_dash.offset);
+            if !stroke_dash.is_valid() {
+                serializer_context.register_invalid_stroke_dash();
+            } else if let Some(stroke_dash) = stroke_dash.normalized() {
+                self.content
+                    .set_dash_pattern(stroke_dash.array.iter().copied(), stroke_dash.offset);
+            }
         }
     }
 
@@ -1131,14 +1691,60 @@ impl ContentBuilder {
     }
 }
 
+/// Register validation errors for glyphs that shouldn't be used in the text.
+fn validate_glyph(
+    sc: &mut SerializeContext,
+    pdf_font: &dyn PdfFont,
+    glyph: &impl Glyph,
+    text: &str,
+) {
+    match glyph.location() {
+        None => sc.reset_location(),
+        Some(l) => sc.set_location(l),
+    };
+
+    if glyph.glyph_id() == GlyphId::new(0)
+        || pdf_font.font().postscript_name() == Some("LastResort")
+    {
+        sc.register_validation_error(ValidationError::ContainsNotDefGlyph(
+            pdf_font.font(),
+            sc.location,
+            glyph_text(glyph, text).to_string(),
+        ));
+    }
+}
+
+/// The state of the current text object, which allows successive glyph runs to
+/// continue where the previous one ended instead of starting at a new position.
+#[derive(Default)]
+struct TextObjectState {
+    /// The font and font size that are currently set.
+    font: Option<(FontIdentifier, f32)>,
+    /// The baseline on which the previous run ended.
+    baseline: Option<f32>,
+    /// The adjustment that has not been applied yet at the end of the previous run.
+    adjustment: f32,
+    /// The current text rise.
+    rise: f32,
+}
+
 // Note that this isn't a 100% accurate calculation, it can overestimate (and in a few cases
 // even underestimate), but it should be good enough for the majority of the cases.
 // TODO: Improve this so that `zalgo_text` test case shows up fully in the reference image.
-fn get_glyphs_bbox(glyphs: &[impl Glyph], x: f32, y: f32, size: f32, font: Font) -> Rect {
+
+fn get_glyphs_bbox(
+    glyphs: &[impl Glyph],
+    x: f32,
+    y: f32,
+    size: f32,
+    font: Font,
+    text: &str,
+    spacing: TextSpacing,
+) -> Rect {
     let font_bbox = font.bbox();
     let (mut bl, mut bt, mut br, mut bb) = font_bbox
         .transform(Transform::from_scale(
-            size / font.units_per_em(),
+            spacing.horizontal_scaling * size / font.units_per_em(),
             -size / font.units_per_em(),
         ))
         .and_then(|b| b.transform(Transform::from_translate(x, y)))
@@ -1149,8 +1755,8 @@ fn get_glyphs_bbox(glyphs: &[impl Glyph], x: f32, y: f32, size: f32, font: Font)
     let mut y = y;
 
     for glyph in glyphs {
-        let xo = glyph.x_offset(size);
-        let xa = glyph.x_advance(size);
+        let xo = glyph.x_offset(size) * spacing.horizontal_scaling;
+        let xa = spacing.x_advance(glyph, text, size);
         let yo = glyph.y_offset(size);
         let ya = glyph.y_advance(size);
 
@@ -14,13 +14,29 @@
 //!
 //! [`Page`]: Page
 
-use crate::error::KrillaResult;
+use crate::error::{KrillaResult, KrillaWarning};
+use crate::geom::{Size, Transform};
+use crate::graphics::mask::{Mask, MaskType};
+use crate::graphics::paint::TilingPattern;
+use crate::graphics::template::Template;
+use crate::interactive::action::DocumentActions;
+use crate::interactive::annotation::Target;
+use crate::interactive::destination::Destination;
+use crate::interactive::form::{
+    CheckBox, CheckBoxSettings, ChoiceField, InternalChoiceField, InternalField,
+    InternalPushButton, InternalTextField, PushButton, RadioGroup, RadioGroupSettings,
+    SignatureField, SignatureFieldSettings, SignaturePlaceholder, TextField,
+};
 use crate::interchange::embed::EmbeddedFile;
+use crate::interchange::facturx::{ConformanceLevel, FacturX};
 use crate::interchange::metadata::Metadata;
 use crate::interchange::outline::Outline;
 use crate::interchange::tagging::TagTree;
-use crate::page::{Page, PageSettings};
+use crate::page::{Page, PageLabel, PageSettings, TabOrder};
 use crate::serialize::{SerializeContext, SerializeSettings};
+use crate::stream::StreamBuilder;
+use crate::surface::Surface;
+use crate::Data;
 
 /// A PDF document.
 pub struct Document {
@@ -50,25 +66,201 @@ impl Document {
 
     /// Start a new page with default settings.
     pub fn start_page(&mut self) -> Page {
-        let page_index = self.serializer_context.page_infos().iter().len();
-        Page::new(
-            &mut self.serializer_context,
-            page_index,
-            PageSettings::default(),
-        )
+        self.start_page_with(PageSettings::default())
     }
 
     /// Start a new page with specific page settings.
     pub fn start_page_with(&mut self, page_settings: PageSettings) -> Page {
-        let page_index = self.serializer_context.page_infos().iter().len();
+        let index = self.serializer_context.num_pages();
+        self.insert_page_at(index, page_settings)
+    }
+
+    /// Start a new page with specific page settings, which will be inserted at
+    /// the given index instead of at the end of the document.
+    ///
+    /// All page indices that are used in the document, for example in destinations,
+    /// refer to the final order of the pages.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is larger than the number of pages in the document.
+    pub fn insert_page_at(&mut self, index: usize, page_settings: PageSettings) -> Page {
+        let page_index = self.serializer_context.page_infos().len();
+        self.serializer_context
+            .insert_page_position(index, page_index);
         Page::new(&mut self.serializer_context, page_index, page_settings)
     }
 
+    /// Move the page at index `from` to index `to`, shifting all pages in between.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either of the indices is out of bounds.
+    pub fn move_page(&mut self, from: usize, to: usize) {
+        self.serializer_context.move_page(from, to);
+    }
+
+    /// Append a duplicate of the page at the given index to the end of the document.
+    ///
+    /// The duplicate shares the content stream of the original page and inherits
+    /// its page label, but doesn't contain any of its annotations or form fields.
+    /// Since the content of the duplicate is not part of the tag tree, duplicating
+    /// pages should be avoided in tagged documents.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of bounds.
+    pub fn duplicate_page(&mut self, index: usize) {
+        self.serializer_context.duplicate_page(index);
+    }
+
+    /// Create a new template of the given size, which can be drawn onto multiple
+    /// pages while only being written once. See [`Template`] for more information.
+    pub fn create_template(&mut self, size: Size, f: impl FnOnce(&mut Surface)) -> Template {
+        let mut stream_builder = StreamBuilder::new(&mut self.serializer_context);
+        let mut surface = stream_builder.surface();
+        f(&mut surface);
+        surface.finish();
+
+        Template::new(stream_builder.finish(), size)
+    }
+
+    /// Create a new mask, whose content is drawn by `f`.
+    /// See [`Surface::create_mask`] for more information.
+    pub fn create_mask(&mut self, mask_type: MaskType, f: impl FnOnce(&mut Surface)) -> Mask {
+        Mask::from_surface(&mut self.serializer_context, mask_type, f)
+    }
+
+    /// Create a new tiling pattern, whose tile of the given size is drawn by `f`.
+    /// See [`Surface::create_tiling_pattern`] for more information.
+    ///
+    /// # Panics
+    /// Panics if a spacing is smaller than or equal to the negative size of the tile.
+    pub fn create_tiling_pattern(
+        &mut self,
+        size: Size,
+        x_spacing: f32,
+        y_spacing: f32,
+        transform: Transform,
+        f: impl FnOnce(&mut Surface),
+    ) -> TilingPattern {
+        TilingPattern::new(
+            &mut self.serializer_context,
+            size,
+            x_spacing,
+            y_spacing,
+            transform,
+            false,
+            f,
+        )
+    }
+
+    /// Create a new uncolored tiling pattern, whose tile of the given size is drawn by `f`.
+    /// See [`Surface::create_uncolored_tiling_pattern`] for more information.
+    ///
+    /// # Panics
+    /// Panics if a spacing is smaller than or equal to the negative size of the tile.
+    pub fn create_uncolored_tiling_pattern(
+        &mut self,
+        size: Size,
+        x_spacing: f32,
+        y_spacing: f32,
+        transform: Transform,
+        f: impl FnOnce(&mut Surface),
+    ) -> TilingPattern {
+        TilingPattern::new(
+            &mut self.serializer_context,
+            size,
+            x_spacing,
+            y_spacing,
+            transform,
+            true,
+            f,
+        )
+    }
+
     /// Set the outline of the document.
     pub fn set_outline(&mut self, outline: Outline) {
         self.serializer_context.set_outline(outline);
     }
 
+    /// Add a new named destination to the document.
+    ///
+    /// Link annotations and actions can refer to it via [`NamedDestination::by_name`],
+    /// regardless of whether they were created before or after the destination was added.
+    /// If a destination with the same name has already been added, it will be replaced.
+    /// Destinations added this way take precedence over destinations created with
+    /// [`NamedDestination::new`] that use the same name.
+    ///
+    /// # Panics
+    /// Panics if `destination` is a named destination itself.
+    ///
+    /// [`NamedDestination::by_name`]: crate::destination::NamedDestination::by_name
+    /// [`NamedDestination::new`]: crate::destination::NamedDestination::new
+    pub fn add_named_destination(&mut self, name: String, destination: impl Into<Destination>) {
+        let destination = destination.into();
+        assert!(
+            !matches!(destination, Destination::Named(_)),
+            "a named destination cannot point to another named destination"
+        );
+
+        self.serializer_context
+            .add_named_destination(name, destination);
+    }
+
+    /// Generate the outline of the document from the headings in its tag tree.
+    ///
+    /// Once the document is finished, krilla will walk the tag tree and create an
+    /// outline entry for each heading, nested according to the heading levels. Each
+    /// entry jumps to the first content that has been drawn as part of the heading.
+    ///
+    /// This only has an effect if tagging is enabled and a tag tree has been set.
+    /// If you set an outline manually via [`Document::set_outline`], it takes precedence.
+    pub fn outline_from_headings(&mut self) {
+        self.serializer_context.set_outline_from_headings();
+    }
+
+    /// Set the page label of all pages starting at the page with index `start_index`,
+    /// until the start of the next range.
+    ///
+    /// The numeric value of the label is incremented with each page of the range,
+    /// starting at the offset of the label. For example, this allows you to number the
+    /// pages of the front matter with roman numerals and to restart the numbering with
+    /// arabic numerals for the main body. Page labels set for individual pages via
+    /// [`PageSettings::with_page_label`] take precedence.
+    ///
+    /// Ranges need to be added in ascending order of their start index, and each
+    /// range must start on a page that exists once the document is finished. Otherwise,
+    /// export will fail with [`KrillaError::InvalidPageLabelRange`].
+    ///
+    /// [`KrillaError::InvalidPageLabelRange`]: crate::error::KrillaError::InvalidPageLabelRange
+    pub fn set_page_label_range(&mut self, start_index: usize, label: PageLabel) {
+        self.serializer_context
+            .add_page_label_range(start_index, label);
+    }
+
+    /// Set the destination that should be jumped to or the action that should be
+    /// performed when the document is opened.
+    pub fn set_open_action(&mut self, open_action: Target) {
+        self.serializer_context.set_open_action(open_action);
+    }
+
+    /// Set the actions that should be performed when certain events happen to the
+    /// document, like closing or printing it.
+    pub fn set_document_actions(&mut self, actions: DocumentActions) {
+        self.serializer_context.set_document_actions(actions);
+    }
+
+    /// Set the order in which the annotations of pages are visited when navigating
+    /// with the keyboard, for all pages that don't set their own tab order using
+    /// [`PageSettings::with_tab_order`].
+    ///
+    /// PDF/UA requires [`TabOrder::Structure`] for all pages with annotations, which
+    /// is also used for tagged pages by default.
+    pub fn set_tab_order(&mut self, tab_order: TabOrder) {
+        self.serializer_context.set_tab_order(tab_order);
+    }
+
     /// Set the metadata of the document.
     pub fn set_metadata(&mut self, metadata: Metadata) {
         self.serializer_context.set_metadata(metadata);
@@ -87,13 +279,122 @@ impl Document {
         self.serializer_context.embed_file(file)
     }
 
+    /// Embed a new file in the PDF document and associate it with the whole document,
+    /// using the association kind of the file.
+    ///
+    /// Associated files require PDF 2.0 or PDF/A-3 and later. Otherwise, the file is
+    /// only embedded, just like with [`Document::embed_file`]. Note that when exporting
+    /// to PDF/A-3 or PDF/A-4, all embedded files are associated with the document anyway.
+    ///
+    /// Returns `None` if the file couldn't be embedded because a file
+    /// with the same name has already been embedded.
+    pub fn add_associated_file(&mut self, file: EmbeddedFile) -> Option<()> {
+        self.serializer_context.add_associated_file(file)
+    }
+
+    /// Turn the document into a Factur-X (or ZUGFeRD) electronic invoice.
+    ///
+    /// `xml` is the XML data of the invoice with the given conformance level. It is
+    /// embedded as an associated file with the name and association kind required by
+    /// the standard, and the conformance level is declared in the XMP metadata. Setting
+    /// a new invoice replaces the previous one.
+    ///
+    /// The document needs to be exported as PDF/A-3, and no other file with the name
+    /// of the invoice may be embedded. Otherwise, export will fail with
+    /// [`KrillaError::FacturX`].
+    ///
+    /// [`KrillaError::FacturX`]: crate::error::KrillaError::FacturX
+    pub fn set_facturx(&mut self, xml: Data, conformance_level: ConformanceLevel) {
+        self.serializer_context.set_facturx(FacturX {
+            data: xml,
+            conformance_level,
+        });
+    }
+
+    /// Add a new text field to the document.
+    pub fn add_text_field(&mut self, field: TextField) {
+        let field = InternalTextField::new(&mut self.serializer_context, field);
+        self.serializer_context
+            .register_form_field(InternalField::Text(field));
+    }
+
+    /// Add a new choice field to the document.
+    pub fn add_choice_field(&mut self, field: ChoiceField) {
+        let field = InternalChoiceField::new(&mut self.serializer_context, field);
+        self.serializer_context
+            .register_form_field(InternalField::Choice(field));
+    }
+
+    /// Add a new check box to the document.
+    pub fn add_check_box(&mut self, settings: CheckBoxSettings) -> CheckBox {
+        CheckBox::new(&mut self.serializer_context, settings)
+    }
+
+    /// Add a new group of radio buttons to the document.
+    pub fn add_radio_group(&mut self, settings: RadioGroupSettings) -> RadioGroup {
+        RadioGroup::new(&mut self.serializer_context, settings)
+    }
+
+    /// Add a new push button to the document.
+    pub fn add_push_button(&mut self, button: PushButton) {
+        let button = InternalPushButton::new(&mut self.serializer_context, button);
+        self.serializer_context
+            .register_form_field(InternalField::PushButton(button));
+    }
+
+    /// Add a new signature field to the document.
+    ///
+    /// The signature itself needs to be added after the document has been exported. See
+    /// [`Document::finish_with_signatures`] for more information.
+    pub fn add_signature_field(&mut self, settings: SignatureFieldSettings) -> SignatureField {
+        SignatureField::new(&mut self.serializer_context, settings)
+    }
+
+    /// Take all warnings that have been collected while drawing so far.
+    ///
+    /// Warnings that can only be detected while exporting the document, such as
+    /// glyphs whose outlines can't be loaded, are returned by
+    /// [`Document::finish_with_warnings`] and [`Document::finish_with_signatures`]
+    /// instead.
+    pub fn take_warnings(&mut self) -> Vec<KrillaWarning> {
+        self.serializer_context.take_warnings()
+    }
+
     /// Attempt to export the document to a PDF file.
-    pub fn finish(mut self) -> KrillaResult<Vec<u8>> {
+    pub fn finish(self) -> KrillaResult<Vec<u8>> {
+        self.finish_impl().map(|(pdf, _, _)| pdf)
+    }
+
+    /// Attempt to export the document to a PDF file, and return all warnings that
+    /// have been collected and not taken with [`Document::take_warnings`] yet.
+    pub fn finish_with_warnings(self) -> KrillaResult<(Vec<u8>, Vec<KrillaWarning>)> {
+        self.finish_impl().map(|(pdf, _, warnings)| (pdf, warnings))
+    }
+
+    /// Attempt to export the document to a PDF file, and return the location of the
+    /// placeholders of all signature fields in the file, in the order they have been added,
+    /// as well as all warnings, the same as [`Document::finish_with_warnings`].
+    ///
+    /// To sign the document, you need to create a signature over all bytes described by the
+    /// byte range of the placeholder and then write the hex-encoded signature into the
+    /// contents range of the placeholder.
+    pub fn finish_with_signatures(
+        self,
+    ) -> KrillaResult<(Vec<u8>, Vec<SignaturePlaceholder>, Vec<KrillaWarning>)> {
+        self.finish_impl()
+    }
+
+    fn finish_impl(
+        mut self,
+    ) -> KrillaResult<(Vec<u8>, Vec<SignaturePlaceholder>, Vec<KrillaWarning>)> {
         // Write empty page if none has been created yet.
-        if self.serializer_context.page_infos().is_empty() {
+        if self.serializer_context.num_pages() == 0 {
             self.start_page();
         }
 
-        Ok(self.serializer_context.finish()?.finish())
+        let (pdf, warnings) = self.serializer_context.finish()?;
+        let (pdf, placeholders) = pdf.finish();
+
+        Ok((pdf, placeholders, warnings))
     }
 }
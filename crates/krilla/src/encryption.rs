@@ -0,0 +1,677 @@
+//! Encrypting PDF documents.
+//!
+//! krilla can encrypt documents using the standard security handler of PDF, so that
+//! viewers require a password before displaying the contents of the document. You can
+//! enable encryption by setting the `encryption` field of [`SerializeSettings`].
+//!
+//! When encryption is enabled, all strings and streams of the document are encrypted
+//! while they are written, and the corresponding `Encrypt` dictionary is written into
+//! the trailer of the file.
+//!
+//! AES-256 encryption requires at least PDF 1.7, using an older PDF version will result
+//! in an error when creating the document.
+//!
+//! Note that the PDF/A standards forbid encryption, so combining encryption with one of the
+//! PDF/A validators will result in a validation error instead of an invalid file.
+//!
+//! [`SerializeSettings`]: crate::SerializeSettings
+
+use std::borrow::Cow;
+
+use aes::cipher::block_padding::{NoPadding, Pkcs7};
+use aes::cipher::{BlockEncryptMut, KeyIvInit};
+use aes::{Aes128, Aes256};
+use pdf_writer::{Buf, Chunk, Date, Finish, Name, Primitive, Ref, Str, TextStr};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::configure::PdfVersion;
+use crate::interchange::metadata::{pdf_date, pdf_date_string, DateTime};
+
+type Aes128CbcEnc = cbc::Encryptor<Aes128>;
+type Aes256CbcEnc = cbc::Encryptor<Aes256>;
+
+/// The algorithm that should be used for encrypting a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EncryptionAlgorithm {
+    /// AES with a key length of 256 bits, as defined in PDF 2.0 (revision 6 of
+    /// the standard security handler).
+    ///
+    /// When exporting with PDF 1.7, krilla will declare the corresponding Adobe
+    /// extension in the document catalog. Older PDF versions are not supported.
+    #[default]
+    Aes256,
+}
+
+/// Settings for encrypting a document.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Encryption {
+    /// The password that is required to open the document. Can be empty, in which
+    /// case the document can be opened without entering a password.
+    ///
+    /// Passwords are normalized with SASLprep and truncated to 127 bytes, as required
+    /// by the PDF specification.
+    pub user_password: String,
+    /// The password that grants full access to the document.
+    pub owner_password: String,
+    /// The algorithm to use for encryption.
+    pub algorithm: EncryptionAlgorithm,
+    /// The operations that viewers should allow when the document is opened
+    /// with the user password.
+    pub permissions: Permissions,
+}
+
+impl Encryption {
+    /// Create new encryption settings with the given user and owner password, using
+    /// AES-256 as the encryption algorithm and granting all permissions.
+    pub fn new(user_password: String, owner_password: String) -> Self {
+        Self {
+            user_password,
+            owner_password,
+            algorithm: EncryptionAlgorithm::default(),
+            permissions: Permissions::default(),
+        }
+    }
+
+    /// Check whether a document with the given PDF version can be encrypted
+    /// with these settings.
+    pub(crate) fn check(&self, pdf_version: PdfVersion) -> Result<(), EncryptionError> {
+        match self.algorithm {
+            EncryptionAlgorithm::Aes256 if pdf_version < PdfVersion::Pdf17 => {
+                return Err(EncryptionError::UnsupportedPdfVersion);
+            }
+            EncryptionAlgorithm::Aes256 => {}
+        }
+
+        prepare_password(&self.user_password)?;
+        prepare_password(&self.owner_password)?;
+
+        Ok(())
+    }
+}
+
+/// An error while encrypting a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EncryptionError {
+    /// The encryption algorithm isn't supported by the used PDF version
+    /// (AES-256 is only available in PDF 1.7+).
+    UnsupportedPdfVersion,
+    /// A password contains characters that are prohibited by SASLprep, such as
+    /// control characters.
+    InvalidPassword,
+}
+
+/// The operations that viewers should allow for an encrypted document.
+///
+/// Note that the permissions are only enforced by conforming viewers and only
+/// apply when the document is opened with the user password. When opening the
+/// document with the owner password, all operations are allowed.
+///
+/// By default, all permissions are granted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Permissions(u32);
+
+impl Permissions {
+    // Bit positions as defined in table 22 of the PDF specification,
+    // where the lowest bit is bit 1.
+    const PRINT: u32 = 1 << 2;
+    const MODIFY: u32 = 1 << 3;
+    const COPY: u32 = 1 << 4;
+    const ANNOTATE: u32 = 1 << 5;
+    const FILL_FORMS: u32 = 1 << 8;
+    const ACCESSIBILITY: u32 = 1 << 9;
+    const ASSEMBLE: u32 = 1 << 10;
+    const PRINT_HIGH_QUALITY: u32 = 1 << 11;
+
+    /// Bits 7, 8 and 13-32 are reserved and must be set.
+    const RESERVED: u32 = 0xFFFF_F0C0;
+
+    /// Create new permissions that grant all operations.
+    pub fn all() -> Self {
+        Self(
+            Self::PRINT
+                | Self::MODIFY
+                | Self::COPY
+                | Self::ANNOTATE
+                | Self::FILL_FORMS
+                | Self::ACCESSIBILITY
+                | Self::ASSEMBLE
+                | Self::PRINT_HIGH_QUALITY,
+        )
+    }
+
+    /// Create new permissions that don't grant any operations.
+    pub fn none() -> Self {
+        Self(0)
+    }
+
+    fn set(mut self, flag: u32, allow: bool) -> Self {
+        if allow {
+            self.0 |= flag;
+        } else {
+            self.0 &= !flag;
+        }
+
+        self
+    }
+
+    fn has(&self, flag: u32) -> bool {
+        self.0 & flag != 0
+    }
+
+    /// Whether printing the document should be allowed. If `allow_high_quality_printing`
+    /// is not set, viewers will only print a low-resolution version of the document.
+    pub fn allow_printing(self, allow: bool) -> Self {
+        self.set(Self::PRINT, allow)
+    }
+
+    /// Whether printing the document in high quality should be allowed.
+    /// Has no effect if printing is not allowed in the first place.
+    pub fn allow_high_quality_printing(self, allow: bool) -> Self {
+        self.set(Self::PRINT_HIGH_QUALITY, allow)
+    }
+
+    /// Whether modifying the contents of the document should be allowed.
+    pub fn allow_modification(self, allow: bool) -> Self {
+        self.set(Self::MODIFY, allow)
+    }
+
+    /// Whether copying or otherwise extracting text and graphics should be allowed.
+    pub fn allow_copying(self, allow: bool) -> Self {
+        self.set(Self::COPY, allow)
+    }
+
+    /// Whether adding or modifying annotations and filling in form fields should be allowed.
+    pub fn allow_annotations(self, allow: bool) -> Self {
+        self.set(Self::ANNOTATE, allow)
+    }
+
+    /// Whether filling in existing form fields should be allowed, even if
+    /// `allow_annotations` is not set.
+    pub fn allow_form_filling(self, allow: bool) -> Self {
+        self.set(Self::FILL_FORMS, allow)
+    }
+
+    /// Whether extracting text and graphics for accessibility purposes should be allowed.
+    ///
+    /// Note that PDF 2.0 deprecates this flag and requires viewers to always
+    /// allow extraction for accessibility purposes.
+    pub fn allow_accessibility(self, allow: bool) -> Self {
+        self.set(Self::ACCESSIBILITY, allow)
+    }
+
+    /// Whether assembling the document (inserting, rotating or deleting pages and
+    /// creating outline items or thumbnails) should be allowed.
+    pub fn allow_assembly(self, allow: bool) -> Self {
+        self.set(Self::ASSEMBLE, allow)
+    }
+
+    /// Whether printing is allowed.
+    pub fn printing(&self) -> bool {
+        self.has(Self::PRINT)
+    }
+
+    /// Whether high-quality printing is allowed.
+    pub fn high_quality_printing(&self) -> bool {
+        self.has(Self::PRINT_HIGH_QUALITY)
+    }
+
+    /// Whether modification is allowed.
+    pub fn modification(&self) -> bool {
+        self.has(Self::MODIFY)
+    }
+
+    /// Whether copying is allowed.
+    pub fn copying(&self) -> bool {
+        self.has(Self::COPY)
+    }
+
+    /// Whether annotating is allowed.
+    pub fn annotations(&self) -> bool {
+        self.has(Self::ANNOTATE)
+    }
+
+    /// Whether form filling is allowed.
+    pub fn form_filling(&self) -> bool {
+        self.has(Self::FILL_FORMS)
+    }
+
+    /// Whether extraction for accessibility is allowed.
+    pub fn accessibility(&self) -> bool {
+        self.has(Self::ACCESSIBILITY)
+    }
+
+    /// Whether assembly is allowed.
+    pub fn assembly(&self) -> bool {
+        self.has(Self::ASSEMBLE)
+    }
+
+    /// Return the value of the `P` entry. The entry is a 32-bit signed integer, and since
+    /// all high-order reserved bits are set, it will always be negative.
+    pub(crate) fn to_pdf(self) -> i32 {
+        (self.0 | Self::RESERVED) as i32
+    }
+
+    /// Recover the permissions from the value of a `P` entry.
+    #[cfg(test)]
+    fn from_pdf(p: i32) -> Self {
+        Self(p as u32 & !Self::RESERVED & !0b11)
+    }
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// A standard security handler of revision 6.
+pub(crate) struct SecurityHandler {
+    encryptor: Encryptor,
+    o: Vec<u8>,
+    u: Vec<u8>,
+    oe: Vec<u8>,
+    ue: Vec<u8>,
+    perms: Vec<u8>,
+    p: i32,
+}
+
+impl SecurityHandler {
+    pub(crate) fn new(encryption: &Encryption) -> Result<Self, EncryptionError> {
+        let user_password = &prepare_password(&encryption.user_password)?;
+        let owner_password = &prepare_password(&encryption.owner_password)?;
+        let p = encryption.permissions.to_pdf();
+
+        let file_key = random_bytes::<32>();
+
+        // Algorithm 8: Computing the encryption dictionary's U and UE values.
+        let user_validation_salt = random_bytes::<8>();
+        let user_key_salt = random_bytes::<8>();
+
+        let mut u = hash(user_password, &user_validation_salt, &[]).to_vec();
+        u.extend_from_slice(&user_validation_salt);
+        u.extend_from_slice(&user_key_salt);
+
+        let ue = aes256_cbc_no_iv(&hash(user_password, &user_key_salt, &[]), &file_key);
+
+        // Algorithm 9: Computing the encryption dictionary's O and OE values.
+        let owner_validation_salt = random_bytes::<8>();
+        let owner_key_salt = random_bytes::<8>();
+
+        let mut o = hash(owner_password, &owner_validation_salt, &u).to_vec();
+        o.extend_from_slice(&owner_validation_salt);
+        o.extend_from_slice(&owner_key_salt);
+
+        let oe = aes256_cbc_no_iv(&hash(owner_password, &owner_key_salt, &u), &file_key);
+
+        // Algorithm 10: Computing the encryption dictionary's Perms value.
+        let mut perms = [0; 16];
+        perms[0..4].copy_from_slice(&p.to_le_bytes());
+        perms[4..8].copy_from_slice(&[0xFF; 4]);
+        // We always encrypt the metadata, too.
+        perms[8] = b'T';
+        perms[9..12].copy_from_slice(b"adb");
+        perms[12..16].copy_from_slice(&random_bytes::<4>());
+        let perms = aes256_cbc_no_iv(&file_key, &perms);
+
+        Ok(Self {
+            encryptor: Encryptor { file_key },
+            o,
+            u,
+            oe,
+            ue,
+            perms,
+            p,
+        })
+    }
+
+    pub(crate) fn encryptor(&self) -> Encryptor {
+        self.encryptor
+    }
+
+    /// Write the `Encrypt` dictionary.
+    pub(crate) fn serialize(&self, chunk: &mut Chunk, root_ref: Ref) {
+        let mut dict = chunk.indirect(root_ref).dict();
+        dict.pair(Name(b"Filter"), Name(b"Standard"));
+        dict.pair(Name(b"V"), 5);
+        dict.pair(Name(b"R"), 6);
+        dict.pair(Name(b"Length"), 256);
+
+        let mut crypt_filters = dict.insert(Name(b"CF")).dict();
+        crypt_filters
+            .insert(Name(b"StdCF"))
+            .dict()
+            .pair(Name(b"Type"), Name(b"CryptFilter"))
+            .pair(Name(b"CFM"), Name(b"AESV3"))
+            .pair(Name(b"AuthEvent"), Name(b"DocOpen"))
+            .pair(Name(b"Length"), 32);
+        crypt_filters.finish();
+
+        dict.pair(Name(b"StmF"), Name(b"StdCF"));
+        dict.pair(Name(b"StrF"), Name(b"StdCF"));
+        dict.pair(Name(b"O"), Str(&self.o));
+        dict.pair(Name(b"U"), Str(&self.u));
+        dict.pair(Name(b"OE"), Str(&self.oe));
+        dict.pair(Name(b"UE"), Str(&self.ue));
+        dict.pair(Name(b"P"), self.p);
+        dict.pair(Name(b"Perms"), Str(&self.perms));
+        dict.pair(Name(b"EncryptMetadata"), true);
+        dict.finish();
+    }
+}
+
+/// Encrypts the strings and streams of a document with the file encryption key.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Encryptor {
+    file_key: [u8; 32],
+}
+
+impl Encryptor {
+    /// Encrypt a string or the data of a stream. The random initialization vector
+    /// is prepended to the encrypted data.
+    pub(crate) fn encrypt(&self, data: &[u8]) -> Vec<u8> {
+        let iv = random_bytes::<16>();
+        let mut encrypted = iv.to_vec();
+        encrypted.extend(
+            Aes256CbcEnc::new_from_slices(&self.file_key, &iv)
+                .unwrap()
+                .encrypt_padded_vec_mut::<Pkcs7>(data),
+        );
+
+        encrypted
+    }
+}
+
+/// Encrypt a string or the data of a stream, if the document is encrypted. Only
+/// needed for strings and streams that can't be written with `PdfStr` or a
+/// `FilterStreamBuilder`.
+pub(crate) fn encrypt_bytes(data: &[u8], encryptor: Option<Encryptor>) -> Cow<'_, [u8]> {
+    match encryptor {
+        Some(encryptor) => Cow::Owned(encryptor.encrypt(data)),
+        None => Cow::Borrowed(data),
+    }
+}
+
+/// A string in a dictionary or an array, which is encrypted when writing it if the
+/// document is encrypted.
+///
+/// Strings in content streams must not use this, since they are encrypted as part of
+/// the whole stream.
+pub(crate) enum PdfStr<'a> {
+    Str(Str<'a>),
+    TextStr(TextStr<'a>),
+    Date(Date),
+    Encrypted(Vec<u8>),
+}
+
+impl<'a> PdfStr<'a> {
+    pub(crate) fn str(bytes: &'a [u8], encryptor: Option<Encryptor>) -> Self {
+        match encryptor {
+            Some(encryptor) => PdfStr::Encrypted(encryptor.encrypt(bytes)),
+            None => PdfStr::Str(Str(bytes)),
+        }
+    }
+
+    pub(crate) fn text_str(text: &'a str, encryptor: Option<Encryptor>) -> Self {
+        match encryptor {
+            Some(encryptor) => PdfStr::Encrypted(encryptor.encrypt(&encode_text_str(text))),
+            None => PdfStr::TextStr(TextStr(text)),
+        }
+    }
+
+    pub(crate) fn date(date_time: DateTime, encryptor: Option<Encryptor>) -> Self {
+        match encryptor {
+            Some(encryptor) => {
+                PdfStr::Encrypted(encryptor.encrypt(pdf_date_string(date_time).as_bytes()))
+            }
+            None => PdfStr::Date(pdf_date(date_time)),
+        }
+    }
+}
+
+impl Primitive for PdfStr<'_> {
+    fn write(self, buf: &mut Buf) {
+        match self {
+            PdfStr::Str(str) => str.write(buf),
+            PdfStr::TextStr(text_str) => text_str.write(buf),
+            PdfStr::Date(date) => date.write(buf),
+            PdfStr::Encrypted(data) => Str(&data).write(buf),
+        }
+    }
+}
+
+/// Encode a text string in the same way as pdf-writer does, i.e. using PDFDocEncoding
+/// if it only consists of printable ASCII characters, and UTF-16BE otherwise.
+fn encode_text_str(text: &str) -> Vec<u8> {
+    if text.bytes().all(|b| matches!(b, 32..=126)) {
+        return text.as_bytes().to_vec();
+    }
+
+    let mut encoded = vec![0xFE, 0xFF];
+    for unit in text.encode_utf16() {
+        encoded.extend_from_slice(&unit.to_be_bytes());
+    }
+
+    encoded
+}
+
+/// Prepare a password as described in section 7.6.4.3.3 of the PDF 2.0 specification,
+/// i.e., normalize it with SASLprep and truncate the UTF-8 representation to 127 bytes.
+fn prepare_password(password: &str) -> Result<Vec<u8>, EncryptionError> {
+    let prepared = stringprep::saslprep(password).map_err(|_| EncryptionError::InvalidPassword)?;
+    let bytes = prepared.as_bytes();
+
+    Ok(bytes[..bytes.len().min(127)].to_vec())
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut buf = [0; N];
+    getrandom::getrandom(&mut buf).expect("failed to generate random bytes");
+    buf
+}
+
+fn aes256_cbc_no_iv(key: &[u8], data: &[u8]) -> Vec<u8> {
+    Aes256CbcEnc::new_from_slices(key, &[0; 16])
+        .unwrap()
+        .encrypt_padded_vec_mut::<NoPadding>(data)
+}
+
+/// Algorithm 2.B: Computing a hash (revision 6).
+fn hash(password: &[u8], salt: &[u8], user_data: &[u8]) -> [u8; 32] {
+    let mut k = Sha256::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(user_data)
+        .finalize()
+        .to_vec();
+
+    let mut round = 0;
+
+    loop {
+        let mut k1 = Vec::with_capacity(64 * (password.len() + k.len() + user_data.len()));
+        for _ in 0..64 {
+            k1.extend_from_slice(password);
+            k1.extend_from_slice(&k);
+            k1.extend_from_slice(user_data);
+        }
+
+        let e = Aes128CbcEnc::new_from_slices(&k[0..16], &k[16..32])
+            .unwrap()
+            .encrypt_padded_vec_mut::<NoPadding>(&k1);
+
+        // Taking the first 16 bytes as a big-endian integer modulo 3 is the same
+        // as taking the sum of those bytes modulo 3.
+        k = match e[0..16].iter().map(|b| *b as u32).sum::<u32>() % 3 {
+            0 => Sha256::digest(&e).to_vec(),
+            1 => Sha384::digest(&e).to_vec(),
+            _ => Sha512::digest(&e).to_vec(),
+        };
+
+        round += 1;
+
+        if round >= 64 && (*e.last().unwrap() as u32) <= round - 32 {
+            break;
+        }
+    }
+
+    k[0..32].try_into().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use aes::cipher::block_padding::{NoPadding, Pkcs7};
+    use aes::cipher::{BlockDecryptMut, KeyIvInit};
+    use aes::Aes256;
+
+    use crate::encryption::{
+        hash, Encryption, EncryptionError, PdfStr, Permissions, SecurityHandler,
+    };
+
+    type Aes256CbcDec = cbc::Decryptor<Aes256>;
+
+    fn aes256_cbc_decrypt_no_iv(key: &[u8], data: &[u8]) -> Vec<u8> {
+        Aes256CbcDec::new_from_slices(key, &[0; 16])
+            .unwrap()
+            .decrypt_padded_vec_mut::<NoPadding>(data)
+            .unwrap()
+    }
+
+    fn decrypt(key: &[u8], data: &[u8]) -> Vec<u8> {
+        Aes256CbcDec::new_from_slices(key, &data[..16])
+            .unwrap()
+            .decrypt_padded_vec_mut::<Pkcs7>(&data[16..])
+            .unwrap()
+    }
+
+    /// Algorithm 2.A: Retrieving the file encryption key with the user password.
+    fn user_file_key(handler: &SecurityHandler, password: &[u8]) -> Option<Vec<u8>> {
+        let u = &handler.u;
+        if hash(password, &u[32..40], &[]) != u[0..32] {
+            return None;
+        }
+
+        Some(aes256_cbc_decrypt_no_iv(
+            &hash(password, &u[40..48], &[]),
+            &handler.ue,
+        ))
+    }
+
+    /// Algorithm 2.A: Retrieving the file encryption key with the owner password.
+    fn owner_file_key(handler: &SecurityHandler, password: &[u8]) -> Option<Vec<u8>> {
+        let (o, u) = (&handler.o, &handler.u);
+        if hash(password, &o[32..40], u) != o[0..32] {
+            return None;
+        }
+
+        Some(aes256_cbc_decrypt_no_iv(
+            &hash(password, &o[40..48], u),
+            &handler.oe,
+        ))
+    }
+
+    fn encryption_1() -> Encryption {
+        Encryption::new("user".to_string(), "owner".to_string())
+    }
+
+    #[test]
+    fn permissions_all() {
+        assert_eq!(Permissions::all().to_pdf(), -4);
+    }
+
+    #[test]
+    fn permissions_none() {
+        assert_eq!(Permissions::none().to_pdf(), -3904);
+    }
+
+    #[test]
+    fn permissions_round_trip() {
+        let permissions = Permissions::all()
+            .allow_printing(false)
+            .allow_copying(false)
+            .allow_annotations(false);
+        let p = permissions.to_pdf();
+
+        assert_eq!(p, -56);
+        assert_eq!(Permissions::from_pdf(p), permissions);
+
+        let decoded = Permissions::from_pdf(p);
+        assert!(!decoded.printing());
+        assert!(!decoded.copying());
+        assert!(!decoded.annotations());
+        assert!(decoded.modification());
+        assert!(decoded.form_filling());
+    }
+
+    #[test]
+    fn encryption_file_key() {
+        let handler = SecurityHandler::new(&encryption_1()).unwrap();
+        let file_key = handler.encryptor().file_key.to_vec();
+
+        assert_eq!(user_file_key(&handler, b"user"), Some(file_key.clone()));
+        assert_eq!(owner_file_key(&handler, b"owner"), Some(file_key));
+        assert_eq!(user_file_key(&handler, b"owner"), None);
+        assert_eq!(owner_file_key(&handler, b"user"), None);
+    }
+
+    #[test]
+    fn encryption_round_trip() {
+        let handler = SecurityHandler::new(&encryption_1()).unwrap();
+        let file_key = user_file_key(&handler, b"user").unwrap();
+
+        let encrypted = handler.encryptor().encrypt(b"An awesome title");
+        assert_eq!(decrypt(&file_key, &encrypted), b"An awesome title");
+
+        let PdfStr::Encrypted(encrypted) = PdfStr::text_str("Grüße", Some(handler.encryptor()))
+        else {
+            panic!("string should be encrypted");
+        };
+        assert_eq!(
+            decrypt(&file_key, &encrypted),
+            [0xFE, 0xFF, 0, b'G', 0, b'r', 0, 0xFC, 0, 0xDF, 0, b'e']
+        );
+    }
+
+    #[test]
+    fn encryption_perms() {
+        let permissions = Permissions::none()
+            .allow_printing(true)
+            .allow_form_filling(true);
+        let encryption = Encryption {
+            permissions,
+            ..encryption_1()
+        };
+        let handler = SecurityHandler::new(&encryption).unwrap();
+        let file_key = user_file_key(&handler, b"user").unwrap();
+
+        let perms = aes256_cbc_decrypt_no_iv(&file_key, &handler.perms);
+        let p = i32::from_le_bytes(perms[0..4].try_into().unwrap());
+
+        assert_eq!(p, handler.p);
+        assert_eq!(Permissions::from_pdf(p), permissions);
+        assert_eq!(&perms[4..8], &[0xFF; 4]);
+        // Metadata is always encrypted.
+        assert_eq!(perms[8], b'T');
+        assert_eq!(&perms[9..12], b"adb");
+    }
+
+    #[test]
+    fn encryption_saslprep() {
+        // The soft hyphen is mapped to nothing, and the roman numeral is
+        // normalized to its compatibility decomposition.
+        let encryption = Encryption::new("I\u{00AD}X".to_string(), "\u{2168}".to_string());
+        let handler = SecurityHandler::new(&encryption).unwrap();
+        let file_key = handler.encryptor().file_key.to_vec();
+
+        assert_eq!(user_file_key(&handler, b"IX"), Some(file_key.clone()));
+        assert_eq!(owner_file_key(&handler, b"IX"), Some(file_key));
+    }
+
+    #[test]
+    fn encryption_invalid_password() {
+        let encryption = Encryption::new("\u{0007}".to_string(), "owner".to_string());
+
+        assert_eq!(
+            SecurityHandler::new(&encryption).err(),
+            Some(EncryptionError::InvalidPassword)
+        );
+    }
+}
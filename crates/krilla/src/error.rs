@@ -3,11 +3,16 @@
 //! There are a lot of things that can go wrong when writing a PDF, like for example when
 //! invalid fonts are provided. This module provides the basic error types krilla uses.
 
+use std::ops::Range;
+
 use crate::configure::ValidationError;
+use crate::encryption::EncryptionError;
 #[cfg(feature = "raster-images")]
 use crate::graphics::image::Image;
+use crate::interchange::facturx::FacturXError;
+use crate::interchange::metadata::{PageLayout, PageMode};
 use crate::surface::Location;
-use crate::text::Font;
+use crate::text::{Font, GlyphId};
 
 /// A wrapper type for krilla errors.
 pub type KrillaResult<T> = Result<T, KrillaError>;
@@ -29,4 +34,90 @@ pub enum KrillaError {
     /// supported by the used PDF version (only available in PDF 1.5+).
     #[cfg(feature = "raster-images")]
     SixteenBitImage(Image, Option<Location>),
+    /// A JPEG 2000 image was used, even though it isn't
+    /// supported by the used PDF version (only available in PDF 1.5+).
+    #[cfg(feature = "raster-images")]
+    Jpeg2000Image(Image, Option<Location>),
+    /// Some named destinations were referenced by name, but no destination
+    /// with that name has been added to the document. Contains the names
+    /// of all unresolved destinations.
+    UnresolvedNamedDestinations(Vec<String>),
+    /// A page label range doesn't start after the previous range, or it starts
+    /// after the last page of the document. Contains the start index of the range.
+    InvalidPageLabelRange(usize),
+    /// The crop, bleed, trim or art box of a page is not contained within
+    /// its media box. Contains the index of the page.
+    PageBoxOutsideMediaBox(usize),
+    /// A user unit was set for a page, even though it isn't supported
+    /// by the used PDF version (only available in PDF 1.6+). Contains
+    /// the index of the page.
+    UserUnit(usize),
+    /// The media box of a page is wider or taller than 14,400 units. Contains
+    /// the index of the page.
+    MediaBoxTooLarge(usize),
+    /// A page layout was used, even though it isn't supported by
+    /// the used PDF version (`TwoPageLeft` and `TwoPageRight` are only
+    /// available in PDF 1.5+).
+    PageLayout(PageLayout),
+    /// A page mode was used, even though it isn't supported by
+    /// the used PDF version (`UseAttachments` is only available in PDF 1.6+).
+    PageMode(PageMode),
+    /// Print preferences were set in the viewer preferences, even though they
+    /// aren't supported by the used PDF version (the print scaling is only available
+    /// in PDF 1.6+, all other print preferences in PDF 1.7+).
+    PrintPreferences,
+    /// The same colorant name was used for spot colors or DeviceN colors with different
+    /// alternate colors. Contains the name of the colorant.
+    ConflictingSpotColor(String),
+    /// The CMYK profile in the [`SerializeSettings`] has a version that isn't
+    /// supported by the used PDF version.
+    ///
+    /// [`SerializeSettings`]: crate::SerializeSettings
+    UnsupportedCMYKProfile,
+    /// A stroke with an invalid dash was drawn, i.e. a dash array with negative or
+    /// non-finite values, or whose values are all zero. Contains the location of the
+    /// first such stroke.
+    InvalidStrokeDash(Option<Location>),
+    /// A bounding box was set for a tag group whose content is spread across multiple
+    /// pages, even though a bounding box can only refer to a single page.
+    MultiPageBBox,
+    /// An empty string was used as the language of a tag group or a span. Contains
+    /// the location of the span, if any.
+    EmptyLanguage(Option<Location>),
+    /// A Factur-X invoice couldn't be created.
+    FacturX(FacturXError),
+    /// The document couldn't be encrypted with the chosen settings.
+    Encryption(EncryptionError),
+    /// `Off` was used as the export value of a check box or radio button, even though
+    /// it is reserved for the unchecked state. Contains the name of the field.
+    InvalidExportValue(String),
+    /// The name of a form field contains a period, which is reserved for separating
+    /// the names of nested fields. Contains the name of the field.
+    InvalidFieldName(String),
+    /// Multiple form fields with the same name have been added. Contains the name
+    /// of the fields.
+    DuplicateFieldName(String),
+}
+
+/// A warning in krilla.
+///
+/// In contrast to errors, warnings don't prevent the document from being exported,
+/// but point to problems that most likely lead to unintended output. They can be
+/// retrieved with [`Document::take_warnings`] and [`Document::finish_with_warnings`].
+///
+/// [`Document::take_warnings`]: crate::Document::take_warnings
+/// [`Document::finish_with_warnings`]: crate::Document::finish_with_warnings
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum KrillaWarning {
+    /// The `.notdef` glyph (i.e. the glyph with ID 0) was drawn, which usually shows up
+    /// as a box in the output. This often means that font fallback failed for the
+    /// text.
+    ///
+    /// Contains the font, the text range of the glyph, the index of the page the
+    /// glyph was drawn on (or `None` if it wasn't drawn on a page, for example in
+    /// a template), and the location of the glyph.
+    NotDefGlyph(Font, Range<usize>, Option<usize>, Option<Location>),
+    /// The outline of a glyph couldn't be loaded, so it is most likely missing
+    /// in the embedded font. Contains the font and the glyph ID.
+    MissingGlyphOutline(Font, GlyphId),
 }
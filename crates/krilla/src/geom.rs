@@ -218,11 +218,20 @@ impl Transform {
         Some(Self(self.0.invert()?))
     }
 
-    pub(crate) fn pre_concat(&self, other: Self) -> Self {
+    /// Apply the transform to a point.
+    pub fn map_point(&self, point: Point) -> Point {
+        let mut point = point.to_tsp();
+        self.0.map_point(&mut point);
+        Point::from_xy(point.x, point.y)
+    }
+
+    /// Returns the transform that first applies `other` and then `self`.
+    pub fn pre_concat(&self, other: Self) -> Self {
         Self(self.0.pre_concat(other.0))
     }
 
-    pub(crate) fn post_concat(&self, other: Self) -> Self {
+    /// Returns the transform that first applies `self` and then `other`.
+    pub fn post_concat(&self, other: Self) -> Self {
         Self(self.0.post_concat(other.0))
     }
 
@@ -262,9 +271,42 @@ impl Path {
     }
 }
 
+/// The distance of the control points of a cubic bézier curve approximating a
+/// quarter circle with radius 1.
+pub(crate) const KAPPA: f32 = 0.552_284_8;
+
+/// The radii of the corners of a rounded rectangle.
+///
+/// Note that "top" refers to the side with the smaller y coordinate, since the
+/// y-axis points down in krilla.
+#[allow(missing_docs)]
+#[derive(Copy, Clone, PartialEq, Default, Debug)]
+pub struct CornerRadii {
+    pub top_left: f32,
+    pub top_right: f32,
+    pub bottom_right: f32,
+    pub bottom_left: f32,
+}
+
+impl CornerRadii {
+    /// Create new corner radii where all corners have the same radius.
+    pub fn uniform(radius: f32) -> Self {
+        Self {
+            top_left: radius,
+            top_right: radius,
+            bottom_right: radius,
+            bottom_left: radius,
+        }
+    }
+}
+
 /// A path builder.
 #[derive(Default)]
-pub struct PathBuilder(tiny_skia_path::PathBuilder);
+pub struct PathBuilder {
+    inner: tiny_skia_path::PathBuilder,
+    /// The current point, as well as the start point of the current contour.
+    cur: Option<(Point, Point)>,
+}
 
 impl PathBuilder {
     /// Create a new path.
@@ -274,35 +316,497 @@ impl PathBuilder {
 
     /// Adds beginning of a contour.
     pub fn move_to(&mut self, x: f32, y: f32) {
-        self.0.move_to(x, y)
+        self.inner.move_to(x, y);
+        self.cur = Some((Point::from_xy(x, y), Point::from_xy(x, y)));
     }
 
     /// Adds a line from the last point.
     pub fn line_to(&mut self, x: f32, y: f32) {
-        self.0.line_to(x, y)
+        self.inner.line_to(x, y);
+        self.set_cur(x, y);
     }
 
     /// Adds a quad curve from the last point to `x`, `y`.
     pub fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
-        self.0.quad_to(x1, y1, x, y)
+        self.inner.quad_to(x1, y1, x, y);
+        self.set_cur(x, y);
     }
     /// Adds a cubic curve from the last point to `x`, `y`.
     pub fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
-        self.0.cubic_to(x1, y1, x2, y2, x, y)
+        self.inner.cubic_to(x1, y1, x2, y2, x, y);
+        self.set_cur(x, y);
+    }
+
+    /// Adds an elliptical arc from the last point to `x`, `y`, in the same way as the
+    /// arc command of SVG paths.
+    ///
+    /// The ellipse has the radii `rx` and `ry` and is rotated by `x_axis_rotation`
+    /// degrees. Out of the four possible arcs, `large_arc` selects whether the one
+    /// spanning more than 180 degrees is chosen, and `sweep` whether the one going
+    /// in the direction of positive angles is chosen. If the radii are too small to
+    /// reach the end point, they are scaled up, and if one of them is zero, a straight
+    /// line is added instead.
+    ///
+    /// The arc is approximated with one cubic curve per quarter of the ellipse.
+    #[allow(clippy::too_many_arguments)]
+    pub fn arc_to(
+        &mut self,
+        rx: f32,
+        ry: f32,
+        x_axis_rotation: f32,
+        large_arc: bool,
+        sweep: bool,
+        x: f32,
+        y: f32,
+    ) {
+        let from = self.cur.map(|(cur, _)| cur).unwrap_or_default();
+
+        if from == Point::from_xy(x, y) {
+            return;
+        }
+
+        if rx == 0.0 || ry == 0.0 {
+            self.line_to(x, y);
+            return;
+        }
+
+        for [c1, c2, p] in arc_to_cubics(
+            from,
+            rx.abs(),
+            ry.abs(),
+            x_axis_rotation,
+            large_arc,
+            sweep,
+            Point::from_xy(x, y),
+        ) {
+            self.cubic_to(c1.x, c1.y, c2.x, c2.y, p.x, p.y);
+        }
     }
 
     /// Close the current contour.
     pub fn close(&mut self) {
-        self.0.close()
+        self.inner.close();
+
+        if let Some((_, start)) = self.cur {
+            self.cur = Some((start, start));
+        }
     }
 
     /// Push a rectangle to the path.
     pub fn push_rect(&mut self, rect: Rect) {
-        self.0.push_rect(rect.to_tsp())
+        self.inner.push_rect(rect.to_tsp());
+        let start = Point::from_xy(rect.left(), rect.top());
+        self.cur = Some((start, start));
+    }
+
+    /// Push a closed ellipse with the center `cx`, `cy` and the radii `rx` and `ry`
+    /// to the path.
+    ///
+    /// Nothing is added if one of the radii isn't positive.
+    pub fn push_ellipse(&mut self, cx: f32, cy: f32, rx: f32, ry: f32) {
+        if !(rx > 0.0 && ry > 0.0) {
+            return;
+        }
+
+        let (kx, ky) = (KAPPA * rx, KAPPA * ry);
+
+        self.move_to(cx + rx, cy);
+        self.cubic_to(cx + rx, cy + ky, cx + kx, cy + ry, cx, cy + ry);
+        self.cubic_to(cx - kx, cy + ry, cx - rx, cy + ky, cx - rx, cy);
+        self.cubic_to(cx - rx, cy - ky, cx - kx, cy - ry, cx, cy - ry);
+        self.cubic_to(cx + kx, cy - ry, cx + rx, cy - ky, cx + rx, cy);
+        self.close();
+    }
+
+    /// Push a closed circle with the center `cx`, `cy` and the radius `r` to the path.
+    ///
+    /// Nothing is added if the radius isn't positive.
+    pub fn push_circle(&mut self, cx: f32, cy: f32, r: f32) {
+        self.push_ellipse(cx, cy, r, r);
+    }
+
+    /// Push a rectangle with rounded corners to the path.
+    ///
+    /// Negative radii are treated as zero. If the radii of two adjacent corners add
+    /// up to more than the length of the side between them, all radii are scaled down
+    /// proportionally until they fit.
+    pub fn push_rounded_rect(&mut self, rect: Rect, radii: CornerRadii) {
+        let (l, t, r, b) = (rect.left(), rect.top(), rect.right(), rect.bottom());
+        let (w, h) = (rect.width(), rect.height());
+
+        let mut tl = radii.top_left.max(0.0);
+        let mut tr = radii.top_right.max(0.0);
+        let mut br = radii.bottom_right.max(0.0);
+        let mut bl = radii.bottom_left.max(0.0);
+
+        let mut factor: f32 = 1.0;
+        for (length, sum) in [(w, tl + tr), (h, tr + br), (w, br + bl), (h, bl + tl)] {
+            if sum > length {
+                factor = factor.min(length / sum);
+            }
+        }
+
+        tl *= factor;
+        tr *= factor;
+        br *= factor;
+        bl *= factor;
+
+        self.move_to(l + tl, t);
+        self.line_to(r - tr, t);
+        if tr > 0.0 {
+            let k = KAPPA * tr;
+            self.cubic_to(r - tr + k, t, r, t + tr - k, r, t + tr);
+        }
+        self.line_to(r, b - br);
+        if br > 0.0 {
+            let k = KAPPA * br;
+            self.cubic_to(r, b - br + k, r - br + k, b, r - br, b);
+        }
+        self.line_to(l + bl, b);
+        if bl > 0.0 {
+            let k = KAPPA * bl;
+            self.cubic_to(l + bl - k, b, l, b - bl + k, l, b - bl);
+        }
+        self.line_to(l, t + tl);
+        if tl > 0.0 {
+            let k = KAPPA * tl;
+            self.cubic_to(l, t + tl - k, l + tl - k, t, l + tl, t);
+        }
+        self.close();
     }
 
     /// Finish the current path.
     pub fn finish(self) -> Option<Path> {
-        Some(Path(self.0.finish()?))
+        Some(Path(self.inner.finish()?))
+    }
+
+    fn set_cur(&mut self, x: f32, y: f32) {
+        let point = Point::from_xy(x, y);
+        // Drawing without a preceding `move_to` implicitly starts a new contour.
+        let start = self.cur.map(|(_, start)| start).unwrap_or_default();
+        self.cur = Some((point, start));
+    }
+}
+
+/// Convert an SVG-style elliptical arc into cubic curves, each described by its two
+/// control points and its end point. See
+/// <https://www.w3.org/TR/SVG11/implnote.html#ArcImplementationNotes>.
+fn arc_to_cubics(
+    from: Point,
+    mut rx: f32,
+    mut ry: f32,
+    x_axis_rotation: f32,
+    large_arc: bool,
+    sweep: bool,
+    to: Point,
+) -> Vec<[Point; 3]> {
+    use std::f32::consts::{FRAC_PI_2, TAU};
+
+    let (sin_phi, cos_phi) = x_axis_rotation.to_radians().sin_cos();
+
+    // Compute the position of the start point in the coordinate system of the ellipse.
+    let dx = (from.x - to.x) / 2.0;
+    let dy = (from.y - to.y) / 2.0;
+    let x1 = cos_phi * dx + sin_phi * dy;
+    let y1 = -sin_phi * dx + cos_phi * dy;
+
+    // Scale up the radii if they are too small.
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    let (rx2, ry2) = (rx * rx, ry * ry);
+    let num = rx2 * ry2 - rx2 * y1 * y1 - ry2 * x1 * x1;
+    let den = rx2 * y1 * y1 + ry2 * x1 * x1;
+    let sign = if large_arc == sweep { -1.0 } else { 1.0 };
+    let coef = sign * (num / den).max(0.0).sqrt();
+
+    let cx1 = coef * rx * y1 / ry;
+    let cy1 = -coef * ry * x1 / rx;
+    let cx = cos_phi * cx1 - sin_phi * cy1 + (from.x + to.x) / 2.0;
+    let cy = sin_phi * cx1 + cos_phi * cy1 + (from.y + to.y) / 2.0;
+
+    let angle = |ux: f32, uy: f32, vx: f32, vy: f32| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+
+    let ux = (x1 - cx1) / rx;
+    let uy = (y1 - cy1) / ry;
+    let vx = (-x1 - cx1) / rx;
+    let vy = (-y1 - cy1) / ry;
+
+    let start_angle = angle(1.0, 0.0, ux, uy);
+    let mut sweep_angle = angle(ux, uy, vx, vy) % TAU;
+
+    if !sweep && sweep_angle > 0.0 {
+        sweep_angle -= TAU;
+    } else if sweep && sweep_angle < 0.0 {
+        sweep_angle += TAU;
+    }
+
+    // Approximate the arc with segments spanning at most a quarter of the ellipse.
+    let num_segments = (sweep_angle.abs() / FRAC_PI_2 - 0.001).ceil().max(1.0) as usize;
+    let segment_angle = sweep_angle / num_segments as f32;
+    let k = 4.0 / 3.0 * (segment_angle / 4.0).tan();
+
+    let map = |x: f32, y: f32| {
+        Point::from_xy(
+            cx + rx * cos_phi * x - ry * sin_phi * y,
+            cy + rx * sin_phi * x + ry * cos_phi * y,
+        )
+    };
+
+    (0..num_segments)
+        .map(|i| {
+            let a0 = start_angle + i as f32 * segment_angle;
+            let a1 = a0 + segment_angle;
+            let (sin0, cos0) = a0.sin_cos();
+            let (sin1, cos1) = a1.sin_cos();
+
+            let c1 = map(cos0 - k * sin0, sin0 + k * cos0);
+            let c2 = map(cos1 + k * sin1, sin1 - k * cos1);
+            let end = if i == num_segments - 1 {
+                to
+            } else {
+                map(cos1, sin1)
+            };
+
+            [c1, c2, end]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tiny_skia_path::PathSegment;
+
+    use crate::geom::{CornerRadii, Path, PathBuilder, Point, Rect, Transform};
+
+    /// Flatten the path into points, sampling each cubic curve at several positions.
+    fn flatten(path: &Path) -> (Vec<Point>, usize) {
+        let mut points = vec![];
+        let mut num_cubics = 0;
+        let mut last = Point::default();
+
+        for segment in path.0.segments() {
+            match segment {
+                PathSegment::MoveTo(p) | PathSegment::LineTo(p) => {
+                    last = Point::from_xy(p.x, p.y);
+                    points.push(last);
+                }
+                PathSegment::CubicTo(c1, c2, p) => {
+                    num_cubics += 1;
+
+                    for i in 1..=16 {
+                        let t = i as f32 / 16.0;
+                        let mt = 1.0 - t;
+                        let eval = |p0: f32, p1: f32, p2: f32, p3: f32| {
+                            mt * mt * mt * p0
+                                + 3.0 * mt * mt * t * p1
+                                + 3.0 * mt * t * t * p2
+                                + t * t * t * p3
+                        };
+
+                        points.push(Point::from_xy(
+                            eval(last.x, c1.x, c2.x, p.x),
+                            eval(last.y, c1.y, c2.y, p.y),
+                        ));
+                    }
+
+                    last = Point::from_xy(p.x, p.y);
+                }
+                PathSegment::QuadTo(..) => unreachable!(),
+                PathSegment::Close => {}
+            }
+        }
+
+        (points, num_cubics)
+    }
+
+    fn assert_on_circle(points: &[Point], cx: f32, cy: f32, r: f32) {
+        for p in points {
+            let dist = ((p.x - cx).powi(2) + (p.y - cy).powi(2)).sqrt();
+            // The error of the cubic approximation of a quarter circle is
+            // about 0.027% of the radius.
+            assert!(
+                (dist - r).abs() <= 3e-4 * r + 1e-3,
+                "{p:?} is not on the circle"
+            );
+        }
+    }
+
+    fn assert_close(a: Point, b: Point) {
+        assert!(
+            (a.x - b.x).abs() < 1e-3 && (a.y - b.y).abs() < 1e-3,
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn transform_map_point() {
+        let transform = Transform::from_rotate_at(90.0, 10.0, 10.0);
+        let point = transform.map_point(Point::from_xy(20.0, 10.0));
+        assert_close(point, Point::from_xy(10.0, 20.0));
+        assert_close(
+            transform.invert().unwrap().map_point(point),
+            Point::from_xy(20.0, 10.0),
+        );
+    }
+
+    #[test]
+    fn circle() {
+        let mut pb = PathBuilder::new();
+        pb.push_circle(50.0, 60.0, 40.0);
+        let (points, num_cubics) = flatten(&pb.finish().unwrap());
+
+        assert_eq!(num_cubics, 4);
+        assert_close(points[0], Point::from_xy(90.0, 60.0));
+        assert_on_circle(&points, 50.0, 60.0, 40.0);
+    }
+
+    #[test]
+    fn ellipse() {
+        let mut pb = PathBuilder::new();
+        pb.push_ellipse(0.0, 0.0, 80.0, 20.0);
+        let (points, num_cubics) = flatten(&pb.finish().unwrap());
+
+        assert_eq!(num_cubics, 4);
+        for p in points {
+            let val = (p.x / 80.0).powi(2) + (p.y / 20.0).powi(2);
+            assert!((val - 1.0).abs() < 1e-3, "{p:?} is not on the ellipse");
+        }
+    }
+
+    #[test]
+    fn ellipse_non_positive_radius() {
+        let mut pb = PathBuilder::new();
+        pb.push_ellipse(0.0, 0.0, 0.0, 20.0);
+        pb.push_circle(0.0, 0.0, -10.0);
+        assert!(pb.finish().is_none());
+    }
+
+    #[test]
+    fn arc_half_circle() {
+        for (sweep, sign) in [(true, -1.0), (false, 1.0)] {
+            let mut pb = PathBuilder::new();
+            pb.move_to(0.0, 0.0);
+            pb.arc_to(50.0, 50.0, 0.0, false, sweep, 100.0, 0.0);
+            let (points, num_cubics) = flatten(&pb.finish().unwrap());
+
+            assert_eq!(num_cubics, 2);
+            assert_close(*points.last().unwrap(), Point::from_xy(100.0, 0.0));
+            assert_on_circle(&points, 50.0, 0.0, 50.0);
+            assert_close(points[16], Point::from_xy(50.0, sign * 50.0));
+        }
+    }
+
+    #[test]
+    fn arc_large() {
+        let num_cubics = |large_arc: bool| {
+            let mut pb = PathBuilder::new();
+            pb.move_to(0.0, 0.0);
+            pb.arc_to(50.0, 50.0, 0.0, large_arc, true, 50.0, 50.0);
+            let (points, num_cubics) = flatten(&pb.finish().unwrap());
+            assert_close(*points.last().unwrap(), Point::from_xy(50.0, 50.0));
+            num_cubics
+        };
+
+        assert_eq!(num_cubics(false), 1);
+        assert_eq!(num_cubics(true), 3);
+    }
+
+    #[test]
+    fn arc_scaled_radii() {
+        let mut pb = PathBuilder::new();
+        pb.move_to(0.0, 0.0);
+        pb.arc_to(1.0, 1.0, 0.0, false, true, 100.0, 0.0);
+        let (points, _) = flatten(&pb.finish().unwrap());
+
+        assert_on_circle(&points, 50.0, 0.0, 50.0);
+    }
+
+    #[test]
+    fn arc_rotated() {
+        let mut pb = PathBuilder::new();
+        pb.move_to(0.0, -50.0);
+        pb.arc_to(50.0, 25.0, 90.0, false, true, 0.0, 50.0);
+        let (points, num_cubics) = flatten(&pb.finish().unwrap());
+
+        assert_eq!(num_cubics, 2);
+        for p in points {
+            let val = (p.y / 50.0).powi(2) + (p.x / 25.0).powi(2);
+            assert!((val - 1.0).abs() < 1e-3, "{p:?} is not on the ellipse");
+        }
+    }
+
+    #[test]
+    fn arc_degenerate() {
+        let mut pb = PathBuilder::new();
+        pb.move_to(10.0, 10.0);
+        pb.arc_to(0.0, 20.0, 0.0, false, true, 30.0, 10.0);
+        // An arc to the current point is omitted.
+        pb.arc_to(20.0, 20.0, 0.0, false, true, 30.0, 10.0);
+        let (points, num_cubics) = flatten(&pb.finish().unwrap());
+
+        assert_eq!(num_cubics, 0);
+        assert_eq!(points.len(), 2);
+        assert_close(points[1], Point::from_xy(30.0, 10.0));
+    }
+
+    #[test]
+    fn arc_after_close() {
+        let mut pb = PathBuilder::new();
+        pb.move_to(0.0, 0.0);
+        pb.line_to(100.0, 100.0);
+        pb.close();
+        // The arc starts at the start of the closed contour.
+        pb.arc_to(50.0, 50.0, 0.0, false, true, 100.0, 0.0);
+        let (points, _) = flatten(&pb.finish().unwrap());
+
+        assert_on_circle(&points[3..], 50.0, 0.0, 50.0);
+    }
+
+    #[test]
+    fn rounded_rect() {
+        let rect = Rect::from_xywh(10.0, 20.0, 100.0, 50.0).unwrap();
+        let mut pb = PathBuilder::new();
+        pb.push_rounded_rect(rect, CornerRadii::uniform(10.0));
+        let path = pb.finish().unwrap();
+        let (points, num_cubics) = flatten(&path);
+
+        assert_eq!(num_cubics, 4);
+        assert_close(points[0], Point::from_xy(20.0, 20.0));
+        assert_eq!(Rect::from_tsp(path.0.bounds()), rect);
+        // The top right corner.
+        assert_on_circle(&points[2..18], 100.0, 30.0, 10.0);
+    }
+
+    #[test]
+    fn rounded_rect_clamped_radii() {
+        let rect = Rect::from_xywh(0.0, 0.0, 100.0, 40.0).unwrap();
+        let mut pb = PathBuilder::new();
+        pb.push_rounded_rect(rect, CornerRadii::uniform(30.0));
+        let (points, _) = flatten(&pb.finish().unwrap());
+
+        // The radii are scaled to fit the height of the rectangle.
+        assert_close(points[0], Point::from_xy(20.0, 0.0));
+        assert_on_circle(&points[2..18], 80.0, 20.0, 20.0);
+    }
+
+    #[test]
+    fn rounded_rect_single_corner() {
+        let rect = Rect::from_xywh(0.0, 0.0, 100.0, 40.0).unwrap();
+        let mut pb = PathBuilder::new();
+        pb.push_rounded_rect(
+            rect,
+            CornerRadii {
+                bottom_left: 10.0,
+                top_right: -5.0,
+                ..CornerRadii::default()
+            },
+        );
+        let (_, num_cubics) = flatten(&pb.finish().unwrap());
+
+        assert_eq!(num_cubics, 1);
     }
 }
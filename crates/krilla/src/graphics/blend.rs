@@ -1,6 +1,10 @@
 //! Blending.
 
 /// How to blend source and backdrop.
+///
+/// Contains all the standard blend modes of PDF, with `Hue`, `Saturation`, `Color`
+/// and `Luminosity` being the non-separable ones. Any blend mode other than `Normal`
+/// counts as transparency, which is forbidden in PDF/A-1.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 #[allow(missing_docs)]
 pub enum BlendMode {
@@ -43,4 +47,59 @@ impl BlendMode {
             BlendMode::Luminosity => pdf_writer::types::BlendMode::Luminosity,
         }
     }
+
+    pub(crate) fn to_pdf_name(self) -> pdf_writer::Name<'static> {
+        pdf_writer::Name(match self {
+            BlendMode::Normal => b"Normal",
+            BlendMode::Multiply => b"Multiply",
+            BlendMode::Screen => b"Screen",
+            BlendMode::Overlay => b"Overlay",
+            BlendMode::Darken => b"Darken",
+            BlendMode::Lighten => b"Lighten",
+            BlendMode::ColorDodge => b"ColorDodge",
+            BlendMode::ColorBurn => b"ColorBurn",
+            BlendMode::HardLight => b"HardLight",
+            BlendMode::SoftLight => b"SoftLight",
+            BlendMode::Difference => b"Difference",
+            BlendMode::Exclusion => b"Exclusion",
+            BlendMode::Hue => b"Hue",
+            BlendMode::Saturation => b"Saturation",
+            BlendMode::Color => b"Color",
+            BlendMode::Luminosity => b"Luminosity",
+        })
+    }
+}
+
+/// How overprinting of CMYK colors should behave.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub enum OverprintMode {
+    /// Each component of the color replaces the previous value of that
+    /// component, even if it is zero.
+    #[default]
+    Standard,
+    /// Components of the color that are zero leave the previous value
+    /// of that component unchanged.
+    NonZero,
+}
+
+impl OverprintMode {
+    pub(crate) fn to_pdf(self) -> i32 {
+        match self {
+            OverprintMode::Standard => 0,
+            OverprintMode::NonZero => 1,
+        }
+    }
+}
+
+/// The color space in which the contents of a transparency group are blended.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub enum GroupColorSpace {
+    /// The sRGB color space.
+    #[default]
+    Srgb,
+    /// The grayscale color space.
+    Luma,
+    /// The CMYK color space. Requires a CMYK profile in export modes that
+    /// don't allow device-dependent color spaces.
+    Cmyk,
 }
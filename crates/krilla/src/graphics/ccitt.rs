@@ -0,0 +1,611 @@
+//! Encoding bilevel images with CCITT Group 4 compression, as described in ITU-T T.6.
+
+/// Encode a bilevel image with CCITT Group 4 compression.
+///
+/// The rows of `data` are expected to be packed with one bit per pixel, where a
+/// set bit represents a black pixel. Each row starts at a new byte.
+pub(crate) fn encode_g4(data: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let width = width as usize;
+    let mut writer = BitWriter::default();
+    // The first line is coded with respect to an imaginary white line.
+    let mut reference = vec![false; width];
+    let mut coding = vec![false; width];
+
+    for row in data.chunks_exact(width.div_ceil(8)).take(height as usize) {
+        for (x, pixel) in coding.iter_mut().enumerate() {
+            *pixel = (row[x / 8] >> (7 - x % 8)) & 1 == 1;
+        }
+
+        encode_line(&mut writer, &reference, &coding);
+        std::mem::swap(&mut reference, &mut coding);
+    }
+
+    // End of facsimile block.
+    writer.write(EOL);
+    writer.write(EOL);
+
+    writer.finish()
+}
+
+fn encode_line(writer: &mut BitWriter, reference: &[bool], coding: &[bool]) {
+    let width = coding.len() as isize;
+    // The position -1 represents an imaginary white pixel before the start of the line.
+    let mut a0 = -1;
+    let mut color = false;
+
+    while a0 < width {
+        let a1 = next_change(coding, a0);
+        let mut b1 = next_change(reference, a0);
+
+        // `b1` needs to have the opposite color of `a0`.
+        while b1 < width && reference[b1 as usize] == color {
+            b1 = next_change(reference, b1);
+        }
+
+        let b2 = next_change(reference, b1);
+
+        if b2 < a1 {
+            writer.write(PASS);
+            a0 = b2;
+        } else if (a1 - b1).abs() <= 3 {
+            writer.write(VERTICAL[(a1 - b1 + 3) as usize]);
+            a0 = a1;
+            color = !color;
+        } else {
+            let a2 = next_change(coding, a1);
+            writer.write(HORIZONTAL);
+            write_run(writer, (a1 - a0.max(0)) as usize, color);
+            write_run(writer, (a2 - a1) as usize, !color);
+            a0 = a2;
+        }
+    }
+}
+
+/// Return the position of the first pixel after `pos` whose color is different from
+/// the one of the pixel before it, or the width of the line if there is none.
+fn next_change(line: &[bool], pos: isize) -> isize {
+    let color_at = |pos: isize| pos >= 0 && line[pos as usize];
+
+    ((pos + 1).max(0)..line.len() as isize)
+        .find(|&p| line[p as usize] != color_at(p - 1))
+        .unwrap_or(line.len() as isize)
+}
+
+fn write_run(writer: &mut BitWriter, mut run: usize, black: bool) {
+    let (terminating, makeup) = if black {
+        (&BLACK_TERMINATING, &BLACK_MAKEUP)
+    } else {
+        (&WHITE_TERMINATING, &WHITE_MAKEUP)
+    };
+
+    while run >= 2560 {
+        writer.write(EXTENDED_MAKEUP[EXTENDED_MAKEUP.len() - 1]);
+        run -= 2560;
+    }
+
+    if run >= 64 {
+        let index = run / 64 - 1;
+
+        if index < makeup.len() {
+            writer.write(makeup[index]);
+        } else {
+            writer.write(EXTENDED_MAKEUP[index - makeup.len()]);
+        }
+
+        run %= 64;
+    }
+
+    writer.write(terminating[run]);
+}
+
+#[derive(Default)]
+struct BitWriter {
+    buf: Vec<u8>,
+    cur: u8,
+    len: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, (bits, len): (u16, u8)) {
+        for i in (0..len).rev() {
+            self.cur = (self.cur << 1) | ((bits >> i) & 1) as u8;
+            self.len += 1;
+
+            if self.len == 8 {
+                self.buf.push(self.cur);
+                self.cur = 0;
+                self.len = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.buf.push(self.cur << (8 - self.len));
+        }
+
+        self.buf
+    }
+}
+
+const EOL: (u16, u8) = (0b000000000001, 12);
+const PASS: (u16, u8) = (0b0001, 4);
+const HORIZONTAL: (u16, u8) = (0b001, 3);
+/// The codes for a vertical offset of -3 to 3 between `a1` and `b1`.
+const VERTICAL: [(u16, u8); 7] = [
+    (0b0000010, 7),
+    (0b000010, 6),
+    (0b010, 3),
+    (0b1, 1),
+    (0b011, 3),
+    (0b000011, 6),
+    (0b0000011, 7),
+];
+
+/// The codes for white runs of length 0 to 63.
+const WHITE_TERMINATING: [(u16, u8); 64] = [
+    (0b00110101, 8),
+    (0b000111, 6),
+    (0b0111, 4),
+    (0b1000, 4),
+    (0b1011, 4),
+    (0b1100, 4),
+    (0b1110, 4),
+    (0b1111, 4),
+    (0b10011, 5),
+    (0b10100, 5),
+    (0b00111, 5),
+    (0b01000, 5),
+    (0b001000, 6),
+    (0b000011, 6),
+    (0b110100, 6),
+    (0b110101, 6),
+    (0b101010, 6),
+    (0b101011, 6),
+    (0b0100111, 7),
+    (0b0001100, 7),
+    (0b0001000, 7),
+    (0b0010111, 7),
+    (0b0000011, 7),
+    (0b0000100, 7),
+    (0b0101000, 7),
+    (0b0101011, 7),
+    (0b0010011, 7),
+    (0b0100100, 7),
+    (0b0011000, 7),
+    (0b00000010, 8),
+    (0b00000011, 8),
+    (0b00011010, 8),
+    (0b00011011, 8),
+    (0b00010010, 8),
+    (0b00010011, 8),
+    (0b00010100, 8),
+    (0b00010101, 8),
+    (0b00010110, 8),
+    (0b00010111, 8),
+    (0b00101000, 8),
+    (0b00101001, 8),
+    (0b00101010, 8),
+    (0b00101011, 8),
+    (0b00101100, 8),
+    (0b00101101, 8),
+    (0b00000100, 8),
+    (0b00000101, 8),
+    (0b00001010, 8),
+    (0b00001011, 8),
+    (0b01010010, 8),
+    (0b01010011, 8),
+    (0b01010100, 8),
+    (0b01010101, 8),
+    (0b00100100, 8),
+    (0b00100101, 8),
+    (0b01011000, 8),
+    (0b01011001, 8),
+    (0b01011010, 8),
+    (0b01011011, 8),
+    (0b01001010, 8),
+    (0b01001011, 8),
+    (0b00110010, 8),
+    (0b00110011, 8),
+    (0b00110100, 8),
+];
+
+/// The codes for white runs of length 64 to 1728, in steps of 64.
+const WHITE_MAKEUP: [(u16, u8); 27] = [
+    (0b11011, 5),
+    (0b10010, 5),
+    (0b010111, 6),
+    (0b0110111, 7),
+    (0b00110110, 8),
+    (0b00110111, 8),
+    (0b01100100, 8),
+    (0b01100101, 8),
+    (0b01101000, 8),
+    (0b01100111, 8),
+    (0b011001100, 9),
+    (0b011001101, 9),
+    (0b011010010, 9),
+    (0b011010011, 9),
+    (0b011010100, 9),
+    (0b011010101, 9),
+    (0b011010110, 9),
+    (0b011010111, 9),
+    (0b011011000, 9),
+    (0b011011001, 9),
+    (0b011011010, 9),
+    (0b011011011, 9),
+    (0b010011000, 9),
+    (0b010011001, 9),
+    (0b010011010, 9),
+    (0b011000, 6),
+    (0b010011011, 9),
+];
+
+/// The codes for black runs of length 0 to 63.
+const BLACK_TERMINATING: [(u16, u8); 64] = [
+    (0b0000110111, 10),
+    (0b010, 3),
+    (0b11, 2),
+    (0b10, 2),
+    (0b011, 3),
+    (0b0011, 4),
+    (0b0010, 4),
+    (0b00011, 5),
+    (0b000101, 6),
+    (0b000100, 6),
+    (0b0000100, 7),
+    (0b0000101, 7),
+    (0b0000111, 7),
+    (0b00000100, 8),
+    (0b00000111, 8),
+    (0b000011000, 9),
+    (0b0000010111, 10),
+    (0b0000011000, 10),
+    (0b0000001000, 10),
+    (0b00001100111, 11),
+    (0b00001101000, 11),
+    (0b00001101100, 11),
+    (0b00000110111, 11),
+    (0b00000101000, 11),
+    (0b00000010111, 11),
+    (0b00000011000, 11),
+    (0b000011001010, 12),
+    (0b000011001011, 12),
+    (0b000011001100, 12),
+    (0b000011001101, 12),
+    (0b000001101000, 12),
+    (0b000001101001, 12),
+    (0b000001101010, 12),
+    (0b000001101011, 12),
+    (0b000011010010, 12),
+    (0b000011010011, 12),
+    (0b000011010100, 12),
+    (0b000011010101, 12),
+    (0b000011010110, 12),
+    (0b000011010111, 12),
+    (0b000001101100, 12),
+    (0b000001101101, 12),
+    (0b000011011010, 12),
+    (0b000011011011, 12),
+    (0b000001010100, 12),
+    (0b000001010101, 12),
+    (0b000001010110, 12),
+    (0b000001010111, 12),
+    (0b000001100100, 12),
+    (0b000001100101, 12),
+    (0b000001010010, 12),
+    (0b000001010011, 12),
+    (0b000000100100, 12),
+    (0b000000110111, 12),
+    (0b000000111000, 12),
+    (0b000000100111, 12),
+    (0b000000101000, 12),
+    (0b000001011000, 12),
+    (0b000001011001, 12),
+    (0b000000101011, 12),
+    (0b000000101100, 12),
+    (0b000001011010, 12),
+    (0b000001100110, 12),
+    (0b000001100111, 12),
+];
+
+/// The codes for black runs of length 64 to 1728, in steps of 64.
+const BLACK_MAKEUP: [(u16, u8); 27] = [
+    (0b0000001111, 10),
+    (0b000011001000, 12),
+    (0b000011001001, 12),
+    (0b000001011011, 12),
+    (0b000000110011, 12),
+    (0b000000110100, 12),
+    (0b000000110101, 12),
+    (0b0000001101100, 13),
+    (0b0000001101101, 13),
+    (0b0000001001010, 13),
+    (0b0000001001011, 13),
+    (0b0000001001100, 13),
+    (0b0000001001101, 13),
+    (0b0000001110010, 13),
+    (0b0000001110011, 13),
+    (0b0000001110100, 13),
+    (0b0000001110101, 13),
+    (0b0000001110110, 13),
+    (0b0000001110111, 13),
+    (0b0000001010010, 13),
+    (0b0000001010011, 13),
+    (0b0000001010100, 13),
+    (0b0000001010101, 13),
+    (0b0000001011010, 13),
+    (0b0000001011011, 13),
+    (0b0000001100100, 13),
+    (0b0000001100101, 13),
+];
+
+/// The codes for runs of length 1792 to 2560 of either color, in steps of 64.
+const EXTENDED_MAKEUP: [(u16, u8); 13] = [
+    (0b00000001000, 11),
+    (0b00000001100, 11),
+    (0b00000001101, 11),
+    (0b000000010010, 12),
+    (0b000000010011, 12),
+    (0b000000010100, 12),
+    (0b000000010101, 12),
+    (0b000000010110, 12),
+    (0b000000010111, 12),
+    (0b000000011100, 12),
+    (0b000000011101, 12),
+    (0b000000011110, 12),
+    (0b000000011111, 12),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A minimal Group 4 decoder, used to check that the encoder output round-trips.
+    ///
+    /// Returns the decoded rows packed in the same way as the input of [`encode_g4`],
+    /// together with the number of vertical mode codes used for each offset from
+    /// -3 to 3.
+    fn decode_g4(data: &[u8], width: u32, height: u32) -> (Vec<u8>, [usize; 7]) {
+        let width = width as usize;
+        let mut reader = BitReader { data, pos: 0 };
+        let mut vertical = [0; 7];
+        let mut reference = vec![false; width];
+        let mut out = vec![];
+
+        for _ in 0..height {
+            let mut coding = vec![false; width];
+            let w = width as isize;
+            let mut a0 = -1;
+            let mut color = false;
+
+            while a0 < w {
+                let mut b1 = next_change(&reference, a0);
+                while b1 < w && reference[b1 as usize] == color {
+                    b1 = next_change(&reference, b1);
+                }
+                let b2 = next_change(&reference, b1);
+                let start = a0.max(0) as usize;
+
+                let mode = reader.read_code(&mode_codes()).expect("invalid mode code");
+                match mode {
+                    Mode::Pass => {
+                        coding[start..b2 as usize].fill(color);
+                        a0 = b2;
+                    }
+                    Mode::Vertical(offset) => {
+                        vertical[(offset + 3) as usize] += 1;
+                        let a1 = b1 + offset;
+                        coding[start..a1 as usize].fill(color);
+                        a0 = a1;
+                        color = !color;
+                    }
+                    Mode::Horizontal => {
+                        let first = reader.read_run(color);
+                        let second = reader.read_run(!color);
+                        coding[start..start + first].fill(color);
+                        coding[start + first..start + first + second].fill(!color);
+                        a0 = (start + first + second) as isize;
+                    }
+                }
+            }
+
+            let mut row = vec![0; width.div_ceil(8)];
+            for (x, _) in coding.iter().enumerate().filter(|(_, black)| **black) {
+                row[x / 8] |= 0x80 >> (x % 8);
+            }
+            out.extend(row);
+            reference = coding;
+        }
+
+        // The end of facsimile block.
+        assert_eq!(reader.read_code(&[(EOL, ())]), Some(()));
+        assert_eq!(reader.read_code(&[(EOL, ())]), Some(()));
+
+        (out, vertical)
+    }
+
+    #[derive(Clone, Copy)]
+    enum Mode {
+        Pass,
+        Horizontal,
+        Vertical(isize),
+    }
+
+    fn mode_codes() -> Vec<((u16, u8), Mode)> {
+        let mut codes = vec![(PASS, Mode::Pass), (HORIZONTAL, Mode::Horizontal)];
+        for (i, code) in VERTICAL.iter().enumerate() {
+            codes.push((*code, Mode::Vertical(i as isize - 3)));
+        }
+
+        codes
+    }
+
+    struct BitReader<'a> {
+        data: &'a [u8],
+        pos: usize,
+    }
+
+    impl BitReader<'_> {
+        fn read_bit(&mut self) -> u16 {
+            let bit = (self.data[self.pos / 8] >> (7 - self.pos % 8)) & 1;
+            self.pos += 1;
+            bit as u16
+        }
+
+        /// Read bits until they match one of the codes. Since the codes are
+        /// prefix-free, the first match is the right one.
+        fn read_code<T: Copy>(&mut self, codes: &[((u16, u8), T)]) -> Option<T> {
+            let mut bits = 0;
+
+            for len in 1..=13 {
+                bits = (bits << 1) | self.read_bit();
+
+                if let Some((_, value)) = codes.iter().find(|(c, _)| *c == (bits, len)) {
+                    return Some(*value);
+                }
+            }
+
+            None
+        }
+
+        fn read_run(&mut self, black: bool) -> usize {
+            let (terminating, makeup) = if black {
+                (&BLACK_TERMINATING, &BLACK_MAKEUP)
+            } else {
+                (&WHITE_TERMINATING, &WHITE_MAKEUP)
+            };
+
+            let mut codes = vec![];
+            codes.extend(terminating.iter().enumerate().map(|(i, c)| (*c, (i, true))));
+            codes.extend(
+                makeup
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| (*c, ((i + 1) * 64, false))),
+            );
+            codes.extend(
+                EXTENDED_MAKEUP
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| (*c, ((i + 28) * 64, false))),
+            );
+
+            let mut run = 0;
+            loop {
+                let (len, terminated) = self.read_code(&codes).expect("invalid run code");
+                run += len;
+
+                if terminated {
+                    return run;
+                }
+            }
+        }
+    }
+
+    fn pack(width: u32, height: u32, pixel: impl Fn(u32, u32) -> bool) -> Vec<u8> {
+        let stride = width.div_ceil(8) as usize;
+        let mut data = vec![0; stride * height as usize];
+
+        for y in 0..height {
+            for x in (0..width).filter(|x| pixel(*x, y)) {
+                data[y as usize * stride + x as usize / 8] |= 0x80 >> (x % 8);
+            }
+        }
+
+        data
+    }
+
+    fn round_trip(data: &[u8], width: u32, height: u32) -> [usize; 7] {
+        let (decoded, vertical) = decode_g4(&encode_g4(data, width, height), width, height);
+        assert_eq!(decoded, data);
+
+        vertical
+    }
+
+    #[test]
+    fn white_line() {
+        // A single vertical mode code, followed by the end of facsimile block.
+        assert_eq!(
+            encode_g4(&[0b0000_0000], 8, 1),
+            [0b1000_0000, 0b0000_1000, 0b0000_0000, 0b1000_0000]
+        );
+    }
+
+    #[test]
+    fn black_line() {
+        // Horizontal mode with a white run of 0 and a black run of 8, followed by
+        // the end of facsimile block.
+        assert_eq!(
+            encode_g4(&[0b1111_1111], 8, 1),
+            [
+                0b0010_0110,
+                0b1010_0010,
+                0b1000_0000,
+                0b0000_1000,
+                0b0000_0000,
+                0b1000_0000
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_line() {
+        // The second line is identical to the first line, so only vertical mode
+        // codes with an offset of 0 are needed.
+        let first = encode_g4(&[0b0011_1100], 8, 1);
+        let both = encode_g4(&[0b0011_1100, 0b0011_1100], 8, 2);
+        assert!(both.len() <= first.len() + 1);
+    }
+
+    #[test]
+    fn round_trip_slanted_edges() {
+        // The edges of the shapes move by up to three pixels from one row to the
+        // next, so all offsets of the vertical mode are needed.
+        for shift in 1..=3 {
+            let data = pack(64, 20, |x, y| {
+                let left = 10 + y * shift;
+                let right = 60 - y * shift;
+                x >= left.min(right) && x < right.max(left)
+            });
+            let vertical = round_trip(&data, 64, 20);
+
+            assert!(vertical[(3 - shift) as usize] > 0);
+            assert!(vertical[(3 + shift) as usize] > 0);
+        }
+    }
+
+    #[test]
+    fn round_trip_circles() {
+        let data = pack(197, 150, |x, y| {
+            let (dx, dy) = (x as i32 % 100 - 50, y as i32 % 100 - 50);
+            (900..1600).contains(&(dx * dx + dy * dy)) || (y % 40 < 4 && x % 200 < 150)
+        });
+        let vertical = round_trip(&data, 197, 150);
+
+        assert!(vertical.iter().all(|n| *n > 0));
+    }
+
+    #[test]
+    fn round_trip_noise() {
+        // Pseudo-random pixels exercise the pass and horizontal modes.
+        let mut state = 0x1234_5678_u32;
+        let pixels: Vec<bool> = (0..53 * 41)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                state >> 31 == 1
+            })
+            .collect();
+        let data = pack(53, 41, |x, y| pixels[(y * 53 + x) as usize]);
+
+        round_trip(&data, 53, 41);
+    }
+
+    #[test]
+    fn round_trip_long_runs() {
+        // Runs longer than 2560 pixels need several makeup codes.
+        let data = pack(6000, 3, |x, y| (y == 1 && x < 5000) || (y == 2 && x > 2000));
+
+        round_trip(&data, 6000, 3);
+    }
+}
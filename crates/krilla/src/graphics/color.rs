@@ -2,14 +2,18 @@
 //!
 //! # Color spaces
 //!
-//! krilla currently supports three color spaces:
+//! krilla currently supports four color spaces:
 //! - Rgb
 //! - Luma
 //! - CMYK
+//! - Lab
 //!
 //! Each color space is associated with its specific color type, which you can use to create new
 //! instances of a specific color in that color space.
 //!
+//! In addition to that, you can use [`SpotColor`] to paint with separate colorants, such
+//! as Pantone inks, and [`DeviceN`] to paint with multiple such colorants at the same time.
+//!
 //! # Representation of colors
 //!
 //! When specifying colors, it is important to understand the distinction between device-dependent
@@ -36,16 +40,27 @@
 //! `no_device_cs` property of [`SerializeSettings`] to true, in which case krilla will embed an ICC profile for the
 //! sgrey and srgb color spaces (for luma and rgb colors, respectively). If a CMYK profile
 //! was provided to the serialize settings, this will be used for CMYK colors. Otherwise,
-//! it will fall back to device CMYK.
+//! it will fall back to device CMYK. Lab colors are always device-independent and don't
+//! require an ICC profile.
 //!
 //! [`SerializeSettings`]: crate::SerializeSettings
 
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::ops::DerefMut;
+use std::sync::Arc;
+
+use pdf_writer::{Chunk, Finish, Name, Ref};
 
+use crate::chunk_container::ChunkContainerFn;
 use crate::configure::ValidationError;
 use crate::graphics::icc::ICCBasedColorSpace;
-use crate::serialize::SerializeContext;
+use crate::num::NormalizedF32;
+use crate::resource;
+use crate::resource::{Resource, Resourceable};
+use crate::serialize::{Cacheable, MaybeDeviceColorSpace, SerializeContext};
+use crate::stream::FilterStreamBuilder;
+use crate::util::{Deferred, NameExt, Prehashed};
 
 /// The PDF name for the device RGB color space.
 pub(crate) const DEVICE_RGB: &str = "DeviceRGB";
@@ -63,6 +78,8 @@ pub enum Color {
     Luma(luma::Color),
     /// A device CMYK color.
     Cmyk(cmyk::Color),
+    /// A CIELAB color.
+    Lab(lab::Color),
 }
 
 impl Color {
@@ -71,6 +88,25 @@ impl Color {
             Color::Rgb(rgb) => rgb.to_pdf_color().to_vec(),
             Color::Luma(l) => vec![l.to_pdf_color()],
             Color::Cmyk(cmyk) => cmyk.to_pdf_color().to_vec(),
+            Color::Lab(lab) => lab.to_pdf_color().to_vec(),
+        }
+    }
+
+    /// Convert the color to RGB if it is a CMYK color.
+    pub(crate) fn cmyk_to_rgb(self) -> Color {
+        match self {
+            Color::Cmyk(c) => Color::Rgb(c.to_rgb()),
+            c => c,
+        }
+    }
+
+    /// The range of each component of the color, as written by `to_pdf_color`.
+    pub(crate) fn range(self) -> Vec<f32> {
+        match self {
+            Color::Rgb(_) => [0.0, 1.0].repeat(3),
+            Color::Luma(_) => [0.0, 1.0].to_vec(),
+            Color::Cmyk(_) => [0.0, 1.0].repeat(4),
+            Color::Lab(_) => lab::RANGE.to_vec(),
         }
     }
 
@@ -85,6 +121,7 @@ impl Color {
                 }
                 Some(cs) => cs,
             },
+            Color::Lab(l) => l.color_space(),
         }
     }
 }
@@ -141,7 +178,7 @@ pub mod luma {
 
 /// CMYK colors.
 pub mod cmyk {
-    use crate::graphics::color::ColorSpace;
+    use crate::graphics::color::{rgb, ColorSpace};
     use crate::graphics::icc::ICCBasedColorSpace;
     use crate::SerializeSettings;
 
@@ -155,6 +192,17 @@ pub mod cmyk {
             Color(cyan, magenta, yellow, black)
         }
 
+        /// Convert the color to an RGB color.
+        ///
+        /// The conversion is purely formulaic and doesn't take any color profiles into
+        /// account, so the result is only an approximation.
+        pub fn to_rgb(self) -> rgb::Color {
+            let [c, m, y, k] = self.to_pdf_color();
+            let component = |n: f32| ((1.0 - n) * (1.0 - k) * 255.0).round() as u8;
+
+            rgb::Color::new(component(c), component(m), component(y))
+        }
+
         pub(crate) fn to_pdf_color(self) -> [f32; 4] {
             [
                 self.0 as f32 / 255.0,
@@ -178,19 +226,17 @@ pub mod cmyk {
     }
 
     pub(crate) fn color_space(ss: &SerializeSettings) -> Option<ColorSpace> {
-        if ss.no_device_cs {
-            ss.clone()
-                .cmyk_profile
-                .map(|p| ColorSpace::Cmyk(ICCBasedColorSpace::<4>(p.clone())))
-        } else {
-            Some(ColorSpace::DeviceCmyk)
+        match &ss.cmyk_profile {
+            Some(p) => Some(ColorSpace::Cmyk(ICCBasedColorSpace::<4>(p.clone()))),
+            None if ss.no_device_cs => None,
+            None => Some(ColorSpace::DeviceCmyk),
         }
     }
 }
 
 /// RGB colors.
 pub mod rgb {
-    use crate::graphics::color::ColorSpace;
+    use crate::graphics::color::{cmyk, ColorSpace};
 
     /// An RGB color.
     #[derive(Debug, Hash, Eq, PartialEq, Clone, Copy)]
@@ -238,6 +284,28 @@ pub mod rgb {
             self.2
         }
 
+        /// Convert the color to a CMYK color.
+        ///
+        /// The conversion is purely formulaic and doesn't take any color profiles into
+        /// account, so the result is only an approximation.
+        pub fn to_cmyk(self) -> cmyk::Color {
+            let [r, g, b] = self.to_pdf_color();
+            let k = 1.0 - r.max(g).max(b);
+
+            if k == 1.0 {
+                return cmyk::Color::new(0, 0, 0, 255);
+            }
+
+            let component = |n: f32| ((1.0 - n - k) / (1.0 - k) * 255.0).round() as u8;
+
+            cmyk::Color::new(
+                component(r),
+                component(g),
+                component(b),
+                (k * 255.0).round() as u8,
+            )
+        }
+
         pub(crate) fn to_pdf_color(self) -> [f32; 3] {
             [
                 self.0 as f32 / 255.0,
@@ -266,6 +334,208 @@ pub mod rgb {
     }
 }
 
+/// CIELAB colors.
+pub mod lab {
+    use std::hash::{Hash, Hasher};
+
+    use pdf_writer::{Chunk, Finish, Name, Ref};
+
+    use crate::chunk_container::ChunkContainerFn;
+    use crate::graphics::color::ColorSpace;
+    use crate::resource;
+    use crate::resource::Resourceable;
+    use crate::serialize::{Cacheable, SerializeContext};
+    use crate::util::Deferred;
+
+    /// The range of the L*, a* and b* components.
+    pub(crate) const RANGE: [f32; 6] = [0.0, 100.0, -128.0, 127.0, -128.0, 127.0];
+
+    /// The white point of a Lab color, given in CIE 1931 XYZ coordinates
+    /// with a luminance of 1.
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    pub struct WhitePoint {
+        pub(crate) x: f32,
+        pub(crate) z: f32,
+    }
+
+    impl WhitePoint {
+        /// The CIE standard illuminant D50, which is used by default.
+        pub const D50: Self = Self {
+            x: 0.9642,
+            z: 0.8249,
+        };
+
+        /// The CIE standard illuminant D65.
+        pub const D65: Self = Self {
+            x: 0.9505,
+            z: 1.0888,
+        };
+
+        /// Create a new white point from its X and Z coordinates.
+        ///
+        /// Returns `None` if one of the coordinates is not positive and finite.
+        pub fn new(x: f32, z: f32) -> Option<Self> {
+            let valid = |n: f32| n.is_finite() && n > 0.0;
+
+            (valid(x) && valid(z)).then_some(Self { x, z })
+        }
+    }
+
+    impl Default for WhitePoint {
+        fn default() -> Self {
+            Self::D50
+        }
+    }
+
+    impl Eq for WhitePoint {}
+
+    impl Hash for WhitePoint {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.x.to_bits().hash(state);
+            self.z.to_bits().hash(state);
+        }
+    }
+
+    /// A CIELAB color.
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    pub struct Color {
+        pub(crate) l: f32,
+        pub(crate) a: f32,
+        pub(crate) b: f32,
+        pub(crate) white_point: WhitePoint,
+    }
+
+    impl Color {
+        /// Create a new Lab color with the D50 white point.
+        ///
+        /// `l` is the lightness in the range 0 to 100, while `a` and `b` are in the
+        /// range -128 to 127. Values outside of these ranges will be clamped.
+        pub fn new(l: f32, a: f32, b: f32) -> Self {
+            let clamp = |n: f32, min: f32, max: f32| {
+                if n.is_nan() {
+                    0.0
+                } else {
+                    n.clamp(min, max)
+                }
+            };
+
+            Self {
+                l: clamp(l, RANGE[0], RANGE[1]),
+                a: clamp(a, RANGE[2], RANGE[3]),
+                b: clamp(b, RANGE[4], RANGE[5]),
+                white_point: WhitePoint::D50,
+            }
+        }
+
+        /// Use a different white point for the color.
+        pub fn with_white_point(mut self, white_point: WhitePoint) -> Self {
+            self.white_point = white_point;
+            self
+        }
+
+        /// Create a black Lab color.
+        pub fn black() -> Self {
+            Self::new(0.0, 0.0, 0.0)
+        }
+
+        /// Create a white Lab color.
+        pub fn white() -> Self {
+            Self::new(100.0, 0.0, 0.0)
+        }
+
+        pub(crate) fn to_pdf_color(self) -> [f32; 3] {
+            [self.l, self.a, self.b]
+        }
+
+        pub(super) fn color_space(&self) -> ColorSpace {
+            ColorSpace::Lab(self.white_point)
+        }
+    }
+
+    impl Default for Color {
+        fn default() -> Self {
+            Color::black()
+        }
+    }
+
+    impl Eq for Color {}
+
+    impl Hash for Color {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.l.to_bits().hash(state);
+            self.a.to_bits().hash(state);
+            self.b.to_bits().hash(state);
+            self.white_point.hash(state);
+        }
+    }
+
+    impl From<Color> for super::Color {
+        fn from(val: Color) -> Self {
+            super::Color::Lab(val)
+        }
+    }
+
+    /// A Lab color space with a specific white point.
+    #[derive(Debug, Hash, Eq, PartialEq, Clone, Copy)]
+    pub(crate) struct LabColorSpace(pub(crate) WhitePoint);
+
+    impl Cacheable for LabColorSpace {
+        fn chunk_container(&self) -> ChunkContainerFn {
+            |cc| &mut cc.color_spaces
+        }
+
+        fn serialize(self, _: &mut SerializeContext, root_ref: Ref) -> Deferred<Chunk> {
+            let mut chunk = Chunk::new();
+
+            let mut array = chunk.indirect(root_ref).array();
+            array.item(Name(b"Lab"));
+            let mut dict = array.push().dict();
+            dict.insert(Name(b"WhitePoint"))
+                .array()
+                .items([self.0.x, 1.0, self.0.z]);
+            dict.insert(Name(b"Range"))
+                .array()
+                .items(RANGE[2..].iter().copied());
+            dict.finish();
+            array.finish();
+
+            Deferred::new(|| chunk)
+        }
+    }
+
+    impl Resourceable for LabColorSpace {
+        type Resource = resource::ColorSpace;
+    }
+}
+
+/// How colors should be mapped to the color space of the output device
+/// in case they are not directly reproducible.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum RenderingIntent {
+    /// Reproduce the colors exactly, without adapting the white point.
+    AbsoluteColorimetric,
+    /// Reproduce the colors exactly, but adapt the white point to the one of
+    /// the output device.
+    RelativeColorimetric,
+    /// Preserve the saturation of the colors, which is mostly useful for
+    /// business graphics.
+    Saturation,
+    /// Preserve the visual relationship between the colors, which is mostly
+    /// useful for photographs.
+    Perceptual,
+}
+
+impl RenderingIntent {
+    pub(crate) fn to_pdf_name(self) -> Name<'static> {
+        Name(match self {
+            RenderingIntent::AbsoluteColorimetric => b"AbsoluteColorimetric",
+            RenderingIntent::RelativeColorimetric => b"RelativeColorimetric",
+            RenderingIntent::Saturation => b"Saturation",
+            RenderingIntent::Perceptual => b"Perceptual",
+        })
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
 pub(crate) enum ColorSpace {
     DeviceRgb,
@@ -274,4 +544,275 @@ pub(crate) enum ColorSpace {
     Srgb,
     Luma,
     Cmyk(ICCBasedColorSpace<4>),
+    Lab(lab::WhitePoint),
+}
+
+/// A spot color, which is painted with a separate colorant (for example a Pantone
+/// ink) instead of being mixed from the colorants of the process color space.
+///
+/// Devices that don't support the colorant use the alternate color instead. Note
+/// that all spot colors with the same colorant name must use the same alternate color,
+/// otherwise exporting the document will fail.
+#[derive(Debug, Hash, Eq, PartialEq, Clone)]
+pub struct SpotColor {
+    pub(crate) name: String,
+    pub(crate) alternate: Color,
+    pub(crate) tint: NormalizedF32,
+}
+
+impl SpotColor {
+    /// Create a new spot color.
+    ///
+    /// `name` is the name of the colorant, `alternate` is the color that approximates
+    /// the full tint of the colorant on devices that don't support it, and `tint` is the
+    /// amount of the colorant that should be applied.
+    pub fn new(name: String, alternate: impl Into<Color>, tint: NormalizedF32) -> Self {
+        Self {
+            name,
+            alternate: alternate.into(),
+            tint,
+        }
+    }
+
+    pub(crate) fn color_space(&self) -> SeparationColorSpace {
+        SeparationColorSpace {
+            name: self.name.clone(),
+            alternate: self.alternate,
+        }
+    }
+
+    pub(crate) fn to_pdf_color(&self) -> f32 {
+        self.tint.get()
+    }
+}
+
+/// A separation color space for a single colorant.
+#[derive(Debug, Hash, Eq, PartialEq, Clone)]
+pub(crate) struct SeparationColorSpace {
+    name: String,
+    alternate: Color,
+}
+
+impl Cacheable for SeparationColorSpace {
+    fn chunk_container(&self) -> ChunkContainerFn {
+        |cc| &mut cc.color_spaces
+    }
+
+    fn serialize(self, sc: &mut SerializeContext, root_ref: Ref) -> Deferred<Chunk> {
+        sc.register_spot_color(&self.name, self.alternate);
+
+        let alternate_cs = self.alternate.color_space(sc);
+        let alternate_cs = sc.register_colorspace(alternate_cs);
+
+        // The color that corresponds to a tint of 0, i.e. no colorant at all.
+        let no_colorant = match self.alternate {
+            Color::Rgb(_) => vec![1.0f32, 1.0, 1.0],
+            Color::Luma(_) => vec![1.0],
+            Color::Cmyk(_) => vec![0.0, 0.0, 0.0, 0.0],
+            Color::Lab(_) => vec![100.0, 0.0, 0.0],
+        };
+
+        let mut chunk = Chunk::new();
+
+        let mut array = chunk.indirect(root_ref).array();
+        array.item(Name(b"Separation"));
+        array.item(Name(self.name.as_bytes()));
+
+        write_color_space_item(&mut array, alternate_cs);
+
+        // The tint transform linearly interpolates between no colorant and the
+        // alternate color.
+        let mut function = array.push().dict();
+        function.pair(Name(b"FunctionType"), 2);
+        function
+            .insert(Name(b"Domain"))
+            .array()
+            .items([0.0f32, 1.0]);
+        function.insert(Name(b"C0")).array().items(no_colorant);
+        function
+            .insert(Name(b"C1"))
+            .array()
+            .items(self.alternate.to_pdf_color());
+        function.pair(Name(b"N"), 1);
+        function.finish();
+
+        array.finish();
+
+        Deferred::new(|| chunk)
+    }
+}
+
+impl Resourceable for SeparationColorSpace {
+    type Resource = resource::ColorSpace;
+}
+
+/// A DeviceN color space, which allows painting with multiple colorants at the same
+/// time, for example to print duotones.
+///
+/// Devices that don't support the colorants use the tint transform to convert the
+/// tints of the colorants into a CMYK color instead. Note that the CMYK color of the
+/// full tint of each single colorant must match the alternate color of spot colors
+/// with the same colorant name, otherwise exporting the document will fail.
+///
+/// IMPORTANT: Note that you must only use a DeviceN color space in the document that you created it with!
+/// If you use it in a different document, you will end up with an invalid PDF file.
+#[derive(Debug, Hash, Eq, PartialEq, Clone)]
+pub struct DeviceN(Arc<Prehashed<DeviceNRepr>>);
+
+#[derive(Debug, Hash, Eq, PartialEq)]
+struct DeviceNRepr {
+    names: Vec<String>,
+    grid_size: u32,
+    samples: Vec<u8>,
+}
+
+impl DeviceN {
+    /// Create a new DeviceN color space.
+    ///
+    /// `names` are the names of the colorants. The tint transform is sampled on a grid
+    /// with `grid_size` points per colorant, and should map the tints of all colorants to
+    /// the corresponding cyan, magenta, yellow and black components, all in the range 0 to 1.
+    ///
+    /// Returns `None` if no names or the same name multiple times were provided, or if the
+    /// grid size is smaller than 2.
+    pub fn new(
+        names: Vec<String>,
+        grid_size: u32,
+        tint_transform: impl Fn(&[f32]) -> [f32; 4],
+    ) -> Option<Self> {
+        let has_duplicates = names
+            .iter()
+            .enumerate()
+            .any(|(i, name)| names[..i].contains(name));
+
+        if names.is_empty() || has_duplicates || grid_size < 2 {
+            return None;
+        }
+
+        let num_samples = (grid_size as usize).checked_pow(names.len() as u32)?;
+        let mut samples = Vec::with_capacity(num_samples * 4);
+        let mut tints = vec![0.0; names.len()];
+
+        for index in 0..num_samples {
+            // The first input varies the fastest.
+            let mut remainder = index;
+            for tint in tints.iter_mut() {
+                *tint = (remainder % grid_size as usize) as f32 / (grid_size - 1) as f32;
+                remainder /= grid_size as usize;
+            }
+
+            samples.extend(
+                tint_transform(&tints)
+                    .iter()
+                    .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8),
+            );
+        }
+
+        Some(Self(Arc::new(Prehashed::new(DeviceNRepr {
+            names,
+            grid_size,
+            samples,
+        }))))
+    }
+
+    /// Create a new color in this color space, with one tint for each colorant.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of tints doesn't match the number of colorants.
+    pub fn color(&self, tints: Vec<NormalizedF32>) -> DeviceNColor {
+        assert_eq!(
+            tints.len(),
+            self.0.names.len(),
+            "number of tints doesn't match the number of colorants"
+        );
+
+        DeviceNColor {
+            color_space: self.clone(),
+            tints,
+        }
+    }
+
+    /// The CMYK color of the full tint of the colorant with the given index.
+    fn full_tint(&self, colorant: usize) -> cmyk::Color {
+        let grid_size = self.0.grid_size as usize;
+        let index = (grid_size - 1) * grid_size.pow(colorant as u32) * 4;
+        let s = &self.0.samples[index..index + 4];
+
+        cmyk::Color::new(s[0], s[1], s[2], s[3])
+    }
+}
+
+impl Cacheable for DeviceN {
+    fn chunk_container(&self) -> ChunkContainerFn {
+        |cc| &mut cc.color_spaces
+    }
+
+    fn serialize(self, sc: &mut SerializeContext, root_ref: Ref) -> Deferred<Chunk> {
+        for (i, name) in self.0.names.iter().enumerate() {
+            sc.register_spot_color(name, Color::Cmyk(self.full_tint(i)));
+        }
+
+        // The alternate color space is always CMYK.
+        let alternate_cs = Color::Cmyk(cmyk::Color::default()).color_space(sc);
+        let alternate_cs = sc.register_colorspace(alternate_cs);
+        let function_ref = sc.new_ref();
+        let serialize_settings = sc.serialize_settings();
+        let encryptor = sc.encryptor();
+
+        let mut chunk = Chunk::new();
+
+        let mut array = chunk.indirect(root_ref).array();
+        array.item(Name(b"DeviceN"));
+        array
+            .push()
+            .array()
+            .items(self.0.names.iter().map(|n| Name(n.as_bytes())));
+        write_color_space_item(&mut array, alternate_cs);
+        array.item(function_ref);
+        array.finish();
+
+        Deferred::new(move || {
+            let num_inputs = self.0.names.len();
+            let stream = FilterStreamBuilder::new_from_binary_data(&self.0.samples)
+                .finish(&serialize_settings, encryptor);
+
+            let mut function = chunk.sampled_function(function_ref, stream.encoded_data());
+            stream.write_filters(function.deref_mut().deref_mut());
+            function.domain((0..num_inputs).flat_map(|_| [0.0, 1.0]));
+            function.range((0..4).flat_map(|_| [0.0, 1.0]));
+            function.size((0..num_inputs).map(|_| self.0.grid_size as i32));
+            function.bits_per_sample(8);
+            function.finish();
+
+            chunk
+        })
+    }
+}
+
+impl Resourceable for DeviceN {
+    type Resource = resource::ColorSpace;
+}
+
+/// A color in a [`DeviceN`] color space.
+#[derive(Debug, Hash, Eq, PartialEq, Clone)]
+pub struct DeviceNColor {
+    pub(crate) color_space: DeviceN,
+    pub(crate) tints: Vec<NormalizedF32>,
+}
+
+impl DeviceNColor {
+    pub(crate) fn to_pdf_color(&self) -> Vec<f32> {
+        self.tints.iter().map(|t| t.get()).collect()
+    }
+}
+
+/// Write a color space as an item of an array.
+pub(crate) fn write_color_space_item(array: &mut pdf_writer::Array, cs: MaybeDeviceColorSpace) {
+    match cs {
+        MaybeDeviceColorSpace::DeviceGray => array.item(DEVICE_GRAY.to_pdf_name()),
+        MaybeDeviceColorSpace::DeviceRgb => array.item(DEVICE_RGB.to_pdf_name()),
+        MaybeDeviceColorSpace::DeviceCMYK => array.item(DEVICE_CMYK.to_pdf_name()),
+        MaybeDeviceColorSpace::ColorSpace(cs) => array.item(cs.get_ref()),
+    };
 }
@@ -5,8 +5,10 @@ use pdf_writer::types::BlendMode;
 use pdf_writer::{Chunk, Finish, Name, Ref};
 
 use crate::chunk_container::ChunkContainerFn;
-use crate::configure::ValidationError;
+use crate::configure::{PdfVersion, ValidationError};
 use crate::geom::{Rect, Transform};
+use crate::graphics::blend::OverprintMode;
+use crate::graphics::color::RenderingIntent;
 use crate::graphics::mask::Mask;
 use crate::num::NormalizedF32;
 use crate::resource;
@@ -25,6 +27,12 @@ struct Repr {
     blend_mode: Option<BlendMode>,
     /// An active mask.
     mask: Option<Ref>,
+    /// The rendering intent.
+    rendering_intent: Option<RenderingIntent>,
+    /// Whether fills overprint, whether strokes overprint and the overprint mode.
+    overprint: Option<(bool, bool, OverprintMode)>,
+    /// Whether black point compensation is enabled.
+    black_point_compensation: Option<bool>,
 }
 
 /// A graphics state containing information about
@@ -32,6 +40,9 @@ struct Repr {
 /// - The current non-stroking alpha.
 /// - The current blend mode.
 /// - The current mask.
+/// - The current rendering intent.
+/// - The current overprint settings.
+/// - Whether black point compensation is enabled.
 ///
 /// This struct provides exposes a builder pattern for setting the various properties
 /// individually.
@@ -75,12 +86,51 @@ impl ExtGState {
         self
     }
 
+    /// Create a new graphics state with a rendering intent.
+    #[must_use]
+    pub(crate) fn rendering_intent(mut self, rendering_intent: RenderingIntent) -> Self {
+        Arc::make_mut(&mut self.0).rendering_intent = Some(rendering_intent);
+        self
+    }
+
+    /// Create a new graphics state with overprint settings.
+    #[must_use]
+    pub(crate) fn overprint(mut self, fill: bool, stroke: bool, mode: OverprintMode) -> Self {
+        Arc::make_mut(&mut self.0).overprint = Some((fill, stroke, mode));
+        self
+    }
+
+    /// Create a new graphics state with black point compensation enabled or disabled.
+    #[must_use]
+    pub(crate) fn black_point_compensation(mut self, black_point_compensation: bool) -> Self {
+        Arc::make_mut(&mut self.0).black_point_compensation = Some(black_point_compensation);
+        self
+    }
+
+    /// Check whether fills (or strokes, if `stroke` is true) are overprinted
+    /// with the nonzero overprint mode.
+    pub(crate) fn nonzero_overprint(&self, stroke: bool) -> bool {
+        match self.0.overprint {
+            Some((fill, stroke_op, OverprintMode::NonZero)) => {
+                if stroke {
+                    stroke_op
+                } else {
+                    fill
+                }
+            }
+            _ => false,
+        }
+    }
+
     /// Check whether the graphics state is empty.
     pub(crate) fn empty(&self) -> bool {
         self.0.mask.is_none()
             && self.0.stroking_alpha.is_none()
             && self.0.non_stroking_alpha.is_none()
             && self.0.blend_mode.is_none()
+            && self.0.rendering_intent.is_none()
+            && self.0.overprint.is_none()
+            && self.0.black_point_compensation.is_none()
     }
 
     /// Integrate another graphics state into the current one. This is done by replacing
@@ -102,6 +152,18 @@ impl ExtGState {
         if let Some(mask) = other.0.mask {
             Arc::make_mut(&mut self.0).mask = Some(mask);
         }
+
+        if let Some(rendering_intent) = other.0.rendering_intent {
+            Arc::make_mut(&mut self.0).rendering_intent = Some(rendering_intent);
+        }
+
+        if let Some(overprint) = other.0.overprint {
+            Arc::make_mut(&mut self.0).overprint = Some(overprint);
+        }
+
+        if let Some(black_point_compensation) = other.0.black_point_compensation {
+            Arc::make_mut(&mut self.0).black_point_compensation = Some(black_point_compensation);
+        }
     }
 }
 
@@ -144,6 +206,24 @@ impl Cacheable for ExtGState {
             ext_st.pair(Name(b"SMask"), mask_ref);
         }
 
+        if let Some(ri) = self.0.rendering_intent {
+            ext_st.pair(Name(b"RI"), ri.to_pdf_name());
+        }
+
+        if let Some((fill, stroke, mode)) = self.0.overprint {
+            ext_st.pair(Name(b"OP"), stroke);
+            ext_st.pair(Name(b"op"), fill);
+            ext_st.pair(Name(b"OPM"), mode.to_pdf());
+        }
+
+        // Black point compensation is only available in PDF 2.0.
+        if let Some(bpc) = self.0.black_point_compensation {
+            if sc.serialize_settings().pdf_version() >= PdfVersion::Pdf20 {
+                let value = if bpc { Name(b"ON") } else { Name(b"OFF") };
+                ext_st.pair(Name(b"UseBlackPtComp"), value);
+            }
+        }
+
         ext_st.finish();
 
         Deferred::new(|| chunk)
@@ -49,7 +49,7 @@ impl<const C: u8> Cacheable for ICCProfile<C> {
     fn serialize(self, sc: &mut SerializeContext, root_ref: Ref) -> Deferred<Chunk> {
         let mut chunk = Chunk::new();
         let icc_stream = FilterStreamBuilder::new_from_deflated(&self.0.deref().data)
-            .finish(&sc.serialize_settings());
+            .finish(&sc.serialize_settings(), sc.encryptor());
 
         let mut icc_profile = chunk.icc_profile(root_ref, icc_stream.encoded_data());
         icc_profile.n(C as i32).range([0.0, 1.0].repeat(C as usize));
@@ -6,8 +6,12 @@
 //! - JPG
 //! - GIF
 //! - WEBP
+//! - JPEG 2000 (only available in PDF 1.5+)
+//! - Bilevel images, which are compressed with CCITT Group 4 encoding
+//! - Stencil masks, which are painted with the current fill
 //! - Custom image formats via [`CustomImage`]
 
+use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 use std::hash::{Hash, Hasher};
 use std::ops::DerefMut;
@@ -16,21 +20,31 @@ use std::sync::Arc;
 use pdf_writer::{Chunk, Finish, Name, Ref};
 use png::{BitDepth, ColorType, Transformations};
 use zune_jpeg::zune_core::colorspace::ColorSpace;
+use zune_jpeg::zune_core::options::DecoderOptions;
 use zune_jpeg::JpegDecoder;
 
 use crate::configure::ValidationError;
+use crate::encryption::PdfStr;
 use crate::error::{KrillaError, KrillaResult};
+use crate::geom::{Size, Transform};
+use crate::graphics::ccitt::encode_g4;
 use crate::graphics::color::DEVICE_GRAY;
-use crate::graphics::color::{cmyk, luma, rgb};
+use crate::graphics::color::{cmyk, luma, rgb, write_color_space_item};
 use crate::graphics::icc::{GenericICCProfile, ICCBasedColorSpace, ICCProfile};
 use crate::serialize::SerializeContext;
-use crate::stream::{deflate_encode, FilterStreamBuilder};
+use crate::stream::{deflate_decode, deflate_encode, FilterStreamBuilder};
 use crate::util::{set_colorspace, Deferred, NameExt, SipHashable};
 use crate::Data;
 
 /// The number of bits per color component.
 #[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
 pub enum BitsPerComponent {
+    /// One bit per component.
+    One,
+    /// Two bits per component.
+    Two,
+    /// Four bits per component.
+    Four,
     /// Eight bits per component.
     Eight,
     /// Sixteen bits per component.
@@ -40,10 +54,98 @@ pub enum BitsPerComponent {
 impl BitsPerComponent {
     fn as_u8(&self) -> u8 {
         match self {
+            BitsPerComponent::One => 1,
+            BitsPerComponent::Two => 2,
+            BitsPerComponent::Four => 4,
             BitsPerComponent::Eight => 8,
             BitsPerComponent::Sixteen => 16,
         }
     }
+
+    /// The bit depth of the alpha channel of an image, which is at least eight bits.
+    fn alpha(&self) -> BitsPerComponent {
+        match self {
+            BitsPerComponent::Sixteen => BitsPerComponent::Sixteen,
+            _ => BitsPerComponent::Eight,
+        }
+    }
+
+    /// The number of bytes of a row with the given number of samples, which is
+    /// padded to a byte boundary.
+    fn row_len(&self, samples: usize) -> usize {
+        (samples * self.as_u8() as usize).div_ceil(8)
+    }
+}
+
+/// The format of the pixels of an image created with [`Image::from_raw`].
+///
+/// The components of each pixel are stored next to each other, without any padding
+/// between rows. Sixteen bit components are stored in big-endian byte order.
+#[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
+pub enum PixelFormat {
+    /// One eight bit luma component per pixel.
+    Gray8,
+    /// One sixteen bit luma component per pixel.
+    Gray16,
+    /// Three eight bit RGB components per pixel.
+    Rgb8,
+    /// Three sixteen bit RGB components per pixel.
+    Rgb16,
+    /// Three eight bit RGB components and one eight bit alpha component per pixel.
+    Rgba8,
+    /// Four eight bit CMYK components per pixel.
+    Cmyk8,
+}
+
+impl PixelFormat {
+    fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelFormat::Gray8 => 1,
+            PixelFormat::Gray16 => 2,
+            PixelFormat::Rgb8 => 3,
+            PixelFormat::Rgb16 => 6,
+            PixelFormat::Rgba8 => 4,
+            PixelFormat::Cmyk8 => 4,
+        }
+    }
+
+    fn color_space(&self) -> ImageColorspace {
+        match self {
+            PixelFormat::Gray8 | PixelFormat::Gray16 => ImageColorspace::Luma,
+            PixelFormat::Rgb8 | PixelFormat::Rgb16 | PixelFormat::Rgba8 => ImageColorspace::Rgb,
+            PixelFormat::Cmyk8 => ImageColorspace::Cmyk,
+        }
+    }
+
+    fn bits_per_component(&self) -> BitsPerComponent {
+        match self {
+            PixelFormat::Gray16 | PixelFormat::Rgb16 => BitsPerComponent::Sixteen,
+            _ => BitsPerComponent::Eight,
+        }
+    }
+}
+
+/// An error while creating an image from raw pixels.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RawImageError {
+    /// The width or the height of the image is zero.
+    EmptyImage,
+    /// The length of the data doesn't match the dimensions and the pixel format of
+    /// the image. Contains the expected and the actual length.
+    InvalidLength(usize, usize),
+}
+
+/// The file format of an image created with [`Image::from_lazy`].
+#[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
+pub enum ImageFormat {
+    /// A `.png` file.
+    Png,
+    /// A `.jpg` file.
+    Jpeg,
+    /// A `.gif` file.
+    Gif,
+    /// A `.webp` file.
+    Webp,
 }
 
 /// The color space of the image.
@@ -98,15 +200,134 @@ struct SampledRepr {
     bits_per_component: BitsPerComponent,
 }
 
+impl SampledRepr {
+    /// Try to convert the color channel into a palette of unique colors and
+    /// the indices into that palette.
+    ///
+    /// Returns `None` if the image has more than 256 unique colors, or if the indices
+    /// wouldn't be smaller than the colors themselves.
+    fn palette(&self, color_space: ImageColorspace, size: (u32, u32)) -> Option<Palette> {
+        let bits = self.bits_per_component.as_u8();
+
+        if self.bits_per_component == BitsPerComponent::Sixteen {
+            return None;
+        }
+
+        let num_components = color_space.num_components() as usize;
+        let mut color_channel = deflate_decode(&self.color_channel)?;
+
+        // The colors in the palette always have eight bits per component, so
+        // smaller samples need to be scaled up.
+        if bits < 8 {
+            let samples_per_row = size.0 as usize * num_components;
+            let row_len = self.bits_per_component.row_len(samples_per_row);
+            let mask = (1u8 << bits) - 1;
+
+            color_channel = color_channel
+                .chunks(row_len.max(1))
+                .take(size.1 as usize)
+                .flat_map(|row| {
+                    (0..samples_per_row).map(move |i| {
+                        let offset = i * bits as usize;
+                        let sample = (row[offset / 8] >> (8 - bits as usize - offset % 8)) & mask;
+                        sample * (255 / mask)
+                    })
+                })
+                .collect();
+        }
+
+        let mut lookup: HashMap<&[u8], u8> = HashMap::new();
+        let mut colors = vec![];
+        let mut indices = Vec::with_capacity(color_channel.len() / num_components);
+
+        for color in color_channel.chunks_exact(num_components) {
+            let index = match lookup.get(color) {
+                Some(index) => *index,
+                None => {
+                    let index = u8::try_from(lookup.len()).ok()?;
+                    lookup.insert(color, index);
+                    colors.extend_from_slice(color);
+                    index
+                }
+            };
+
+            indices.push(index);
+        }
+
+        if colors.is_empty() {
+            return None;
+        }
+
+        let bits_per_index: u8 = match lookup.len() {
+            0..=2 => 1,
+            3..=4 => 2,
+            5..=16 => 4,
+            _ => 8,
+        };
+
+        if bits_per_index as usize >= bits as usize * num_components {
+            return None;
+        }
+
+        // Pack the indices, each row starts at a new byte.
+        let indices_per_byte = (8 / bits_per_index) as usize;
+        let row_len = (size.0 as usize).div_ceil(indices_per_byte);
+        let mut packed = Vec::with_capacity(row_len * size.1 as usize);
+
+        for row in indices.chunks(size.0.max(1) as usize) {
+            for chunk in row.chunks(indices_per_byte) {
+                let mut byte = 0;
+                for (i, index) in chunk.iter().enumerate() {
+                    byte |= index << (8 - bits_per_index as usize * (i + 1));
+                }
+                packed.push(byte);
+            }
+        }
+
+        Some(Palette {
+            colors,
+            indices: deflate_encode(&packed),
+            bits_per_index,
+            num_components,
+        })
+    }
+}
+
+/// The palette of an image with an indexed color space.
+struct Palette {
+    /// The components of all unique colors.
+    colors: Vec<u8>,
+    /// The deflate-encoded and packed indices into the palette.
+    indices: Vec<u8>,
+    bits_per_index: u8,
+    num_components: usize,
+}
+
+impl Palette {
+    fn len(&self) -> usize {
+        self.colors.len() / self.num_components
+    }
+}
+
 struct JpegRepr {
     data: Data,
     bits_per_component: BitsPerComponent,
     invert_cmyk: bool,
 }
 
+struct JpxRepr {
+    data: Data,
+}
+
+struct CcittRepr {
+    data: Vec<u8>,
+}
+
 enum Repr {
     Sampled(SampledRepr),
     Jpeg(JpegRepr),
+    Jpx(JpxRepr),
+    Ccitt(CcittRepr),
 }
 
 impl Repr {
@@ -114,6 +335,8 @@ impl Repr {
         match self {
             Repr::Sampled(s) => s.bits_per_component,
             Repr::Jpeg(j) => j.bits_per_component,
+            // Not actually written, see `Image::serialize`.
+            Repr::Jpx(_) | Repr::Ccitt(_) => BitsPerComponent::Eight,
         }
     }
 }
@@ -126,8 +349,14 @@ impl Repr {
 /// hash and clone, otherwise performance might be bad!
 pub trait CustomImage: Hash + Clone + Send + Sync + 'static {
     /// Return the raw bytes of the color channel.
+    ///
+    /// If the image has less than eight bits per component, the samples are packed
+    /// and each row starts at a new byte.
     fn color_channel(&self) -> &[u8];
     /// Return the raw bytes of the alpha channel, if available.
+    ///
+    /// The alpha channel has eight bits per component, unless the image has
+    /// sixteen bits per component.
     fn alpha_channel(&self) -> Option<&[u8]>;
     /// Return the bits per component of the image.
     fn bits_per_component(&self) -> BitsPerComponent;
@@ -139,19 +368,42 @@ pub trait CustomImage: Hash + Clone + Send + Sync + 'static {
     fn color_space(&self) -> ImageColorspace;
 }
 
+#[derive(Clone)]
 struct ImageMetadata {
     size: (u32, u32),
     color_space: ImageColorspace,
     has_alpha: bool,
     bits_per_component: BitsPerComponent,
     icc: Option<GenericICCProfile>,
+    jpx: bool,
+    stencil: bool,
+    /// The EXIF orientation of the image, between 1 and 8.
+    orientation: u8,
+}
+
+/// The decoded data of an image.
+#[derive(Clone)]
+enum ImageData {
+    /// The image is decoded right away.
+    Eager(Arc<Deferred<Option<Repr>>>),
+    /// The image is only loaded and decoded once it is serialized, and dropped
+    /// again afterwards.
+    Lazy(Arc<dyn Fn() -> Option<Repr> + Send + Sync>),
+}
+
+impl ImageData {
+    fn eager(f: impl FnOnce() -> Option<Repr> + Send + Sync + 'static) -> Self {
+        Self::Eager(Arc::new(Deferred::new(f)))
+    }
 }
 
 struct ImageRepr {
-    inner: Deferred<Option<Repr>>,
+    inner: ImageData,
     metadata: ImageMetadata,
     sip: u128,
     interpolate: bool,
+    indexed: bool,
+    respect_orientation: bool,
 }
 
 impl ImageRepr {
@@ -177,12 +429,16 @@ impl Debug for ImageRepr {
 impl Hash for ImageRepr {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.sip.hash(state);
+        self.interpolate.hash(state);
+        self.indexed.hash(state);
     }
 }
 
 impl PartialEq for ImageRepr {
     fn eq(&self, other: &Self) -> bool {
         self.sip == other.sip
+            && self.interpolate == other.interpolate
+            && self.indexed == other.indexed
     }
 }
 
@@ -203,25 +459,55 @@ impl Image {
         let metadata = png_metadata(data.as_ref())?;
 
         Some(Self(Arc::new(ImageRepr {
-            inner: Deferred::new(move || decode_png(data.as_ref())),
+            inner: ImageData::eager(move || decode_png(data.as_ref())),
             metadata,
             sip: hash,
             interpolate,
+            indexed: false,
+            respect_orientation: true,
         })))
     }
 
     /// Create a new bitmap image from a `.jpg` file.
     ///
+    /// The JPEG data is embedded as is, so only its header needs to be parsed. This
+    /// also works for progressive and CMYK JPEGs. If you want the image to be decoded
+    /// and re-encoded instead, use [`Image::from_jpeg_decoded`].
+    ///
     /// Returns `None` if krilla was unable to parse the file.
     pub fn from_jpeg(data: Data, interpolate: bool) -> Option<Image> {
         let hash = data.as_ref().sip_hash();
         let metadata = jpeg_metadata(data.as_ref())?;
 
         Some(Self(Arc::new(ImageRepr {
-            inner: Deferred::new(move || decode_jpeg(data)),
+            inner: ImageData::eager(move || decode_jpeg(data)),
+            metadata,
+            sip: hash,
+            interpolate,
+            indexed: false,
+            respect_orientation: true,
+        })))
+    }
+
+    /// Create a new bitmap image from a `.jpg` file, which is decoded instead of
+    /// being embedded as is.
+    ///
+    /// This usually results in larger files and is slower than [`Image::from_jpeg`],
+    /// but can be useful if the JPEG data is not supported by some PDF consumer.
+    ///
+    /// Returns `None` if krilla was unable to parse the file.
+    pub fn from_jpeg_decoded(data: Data, interpolate: bool) -> Option<Image> {
+        // Make sure the image is deduplicated separately from the embedded version.
+        let hash = (data.as_ref().sip_hash(), true).sip_hash();
+        let metadata = jpeg_metadata(data.as_ref())?;
+
+        Some(Self(Arc::new(ImageRepr {
+            inner: ImageData::eager(move || decode_jpeg_pixels(data)),
             metadata,
             sip: hash,
             interpolate,
+            indexed: false,
+            respect_orientation: true,
         })))
     }
 
@@ -239,10 +525,94 @@ impl Image {
         metadata.icc = icc_profile;
 
         Some(Self(Arc::new(ImageRepr {
-            inner: Deferred::new(move || decode_jpeg(data)),
+            inner: ImageData::eager(move || decode_jpeg(data)),
+            metadata,
+            sip: hash,
+            interpolate,
+            indexed: false,
+            respect_orientation: true,
+        })))
+    }
+
+    /// Create a new bitmap image from a `.jp2` file or a raw JPEG 2000 codestream.
+    ///
+    /// Like with [`Image::from_jpeg`], the data is embedded as is, so only its
+    /// header is parsed. Note that JPEG 2000 images are only supported in PDF 1.5+
+    /// and are forbidden in PDF/A1. Images with an alpha channel are not supported.
+    ///
+    /// Returns `None` if krilla was unable to parse the file.
+    pub fn from_jpeg2000(data: Data, interpolate: bool) -> Option<Image> {
+        let hash = data.as_ref().sip_hash();
+        let metadata = jpx_metadata(data.as_ref())?;
+
+        Some(Self(Arc::new(ImageRepr {
+            inner: ImageData::eager(move || Some(Repr::Jpx(JpxRepr { data }))),
+            metadata,
+            sip: hash,
+            interpolate,
+            indexed: false,
+            respect_orientation: true,
+        })))
+    }
+
+    /// Create a new bitmap image whose data is only loaded when it is needed.
+    ///
+    /// Instead of keeping the decoded image in memory until the document is finished,
+    /// `load` is called once when creating the image to read its metadata, and
+    /// once more when the image is written to the PDF. The decoded image is dropped
+    /// right after it has been encoded, so this can considerably reduce the memory
+    /// footprint of documents with many large images, at the cost of loading them
+    /// twice. The data returned by `load` needs to be the same each time it is called.
+    ///
+    /// For example, to load an image from a file only once it is needed:
+    ///
+    /// ```no_run
+    /// use krilla::image::{Image, ImageFormat};
+    ///
+    /// let image = Image::from_lazy(
+    ///     ImageFormat::Jpeg,
+    ///     || std::fs::read("photo.jpg").ok().map(Into::into),
+    ///     false,
+    /// );
+    /// ```
+    ///
+    /// Returns `None` if krilla was unable to load or parse the file. If loading
+    /// the file fails when writing the image, an error is returned when finishing
+    /// the document.
+    pub fn from_lazy<F>(format: ImageFormat, load: F, interpolate: bool) -> Option<Image>
+    where
+        F: Fn() -> Option<Data> + Send + Sync + 'static,
+    {
+        let (hash, metadata) = {
+            let data = load()?;
+            let metadata = match format {
+                ImageFormat::Png => png_metadata(data.as_ref()),
+                ImageFormat::Jpeg => jpeg_metadata(data.as_ref()),
+                ImageFormat::Gif => gif_metadata(data.as_ref()),
+                ImageFormat::Webp => webp_metadata(data.as_ref()),
+            }?;
+
+            (data.as_ref().sip_hash(), metadata)
+        };
+
+        let decode = move || {
+            let data = load()?;
+
+            match format {
+                ImageFormat::Png => decode_png(data.as_ref()),
+                ImageFormat::Jpeg => decode_jpeg(data),
+                ImageFormat::Gif => decode_gif(data),
+                ImageFormat::Webp => decode_webp(data),
+            }
+        };
+
+        Some(Self(Arc::new(ImageRepr {
+            inner: ImageData::Lazy(Arc::new(decode)),
             metadata,
             sip: hash,
             interpolate,
+            indexed: false,
+            respect_orientation: true,
         })))
     }
 
@@ -254,10 +624,12 @@ impl Image {
         let metadata = gif_metadata(data.as_ref())?;
 
         Some(Self(Arc::new(ImageRepr {
-            inner: Deferred::new(move || decode_gif(data)),
+            inner: ImageData::eager(move || decode_gif(data)),
             metadata,
             sip: hash,
             interpolate,
+            indexed: false,
+            respect_orientation: true,
         })))
     }
 
@@ -269,10 +641,12 @@ impl Image {
         let metadata = webp_metadata(data.as_ref())?;
 
         Some(Self(Arc::new(ImageRepr {
-            inner: Deferred::new(move || decode_webp(data)),
+            inner: ImageData::eager(move || decode_webp(data)),
             metadata,
             sip: hash,
             interpolate,
+            indexed: false,
+            respect_orientation: true,
         })))
     }
 
@@ -290,22 +664,25 @@ impl Image {
             icc: image
                 .icc_profile()
                 .and_then(|d| get_icc_profile_type(d, image.color_space())),
+            jpx: false,
+            stencil: false,
+            orientation: 1,
         };
 
         Some(Self(Arc::new(ImageRepr {
-            inner: Deferred::new(move || {
-                let bytes_per_component = (image.bits_per_component().as_u8() / 8) as u32;
-                let color_channel_len = bytes_per_component
-                    * image.color_space().num_components() as u32
-                    * metadata.size.0
-                    * metadata.size.1;
+            inner: ImageData::eager(move || {
+                let bits_per_component = image.bits_per_component();
+                let (width, height) = (metadata.size.0 as usize, metadata.size.1 as usize);
+                let color_channel_len = bits_per_component
+                    .row_len(image.color_space().num_components() as usize * width)
+                    * height;
                 let color_channel = image.color_channel();
-                assert_eq!(color_channel.len(), color_channel_len as usize);
+                assert_eq!(color_channel.len(), color_channel_len);
 
-                let alpha_channel_len = bytes_per_component * metadata.size.0 * metadata.size.1;
+                let alpha_channel_len = bits_per_component.alpha().row_len(width) * height;
                 let alpha_channel = image.alpha_channel();
                 if let Some(alpha_channel) = alpha_channel {
-                    assert_eq!(alpha_channel.len(), alpha_channel_len as usize);
+                    assert_eq!(alpha_channel.len(), alpha_channel_len);
                 }
 
                 Some(Repr::Sampled(SampledRepr {
@@ -317,6 +694,8 @@ impl Image {
             metadata,
             sip: hash,
             interpolate,
+            indexed: false,
+            respect_orientation: true,
         })))
     }
 
@@ -329,10 +708,13 @@ impl Image {
             bits_per_component: BitsPerComponent::Eight,
             color_space: ImageColorspace::Rgb,
             icc: None,
+            jpx: false,
+            stencil: false,
+            orientation: 1,
         };
 
         Self(Arc::new(ImageRepr {
-            inner: Deferred::new(move || {
+            inner: ImageData::eager(move || {
                 let (color_channel, alpha_channel, bits_per_component) =
                     handle_u8_image(&data, ColorSpace::RGBA);
 
@@ -345,12 +727,253 @@ impl Image {
             metadata,
             sip: hash,
             interpolate: false,
+            indexed: false,
+            respect_orientation: true,
+        }))
+    }
+
+    /// Create a new image from raw pixels.
+    ///
+    /// If the pixel format has an alpha channel, it is written as a separate soft mask.
+    /// If an ICC profile is provided and matches the color space of the pixel format,
+    /// it is embedded with the image.
+    pub fn from_raw(
+        data: Vec<u8>,
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+        icc_profile: Option<Data>,
+    ) -> Result<Image, RawImageError> {
+        if width == 0 || height == 0 {
+            return Err(RawImageError::EmptyImage);
+        }
+
+        let expected_len = format.bytes_per_pixel() * width as usize * height as usize;
+
+        if data.len() != expected_len {
+            return Err(RawImageError::InvalidLength(expected_len, data.len()));
+        }
+
+        let hash = (
+            data.sip_hash(),
+            width,
+            height,
+            format,
+            icc_profile.as_ref().map(|d| d.as_ref().sip_hash()),
+        )
+            .sip_hash();
+        let metadata = ImageMetadata {
+            // Don't write a soft mask for images that are fully opaque anyway.
+            has_alpha: format == PixelFormat::Rgba8 && data.chunks_exact(4).any(|p| p[3] != 255),
+            size: (width, height),
+            bits_per_component: format.bits_per_component(),
+            color_space: format.color_space(),
+            icc: icc_profile.and_then(|d| get_icc_profile_type(d.as_ref(), format.color_space())),
+            jpx: false,
+            stencil: false,
+            orientation: 1,
+        };
+
+        Ok(Self(Arc::new(ImageRepr {
+            inner: ImageData::eager(move || {
+                let (color_channel, alpha_channel, bits_per_component) = match format {
+                    PixelFormat::Gray8 => handle_u8_image(&data, ColorSpace::Luma),
+                    PixelFormat::Gray16 => handle_u16_image(&data, ColorSpace::Luma),
+                    PixelFormat::Rgb8 => handle_u8_image(&data, ColorSpace::RGB),
+                    PixelFormat::Rgb16 => handle_u16_image(&data, ColorSpace::RGB),
+                    PixelFormat::Rgba8 => handle_u8_image(&data, ColorSpace::RGBA),
+                    PixelFormat::Cmyk8 => (deflate_encode(&data), None, BitsPerComponent::Eight),
+                };
+
+                Some(Repr::Sampled(SampledRepr {
+                    color_channel,
+                    alpha_channel,
+                    bits_per_component,
+                }))
+            }),
+            metadata,
+            sip: hash,
+            interpolate: false,
+            indexed: false,
+            respect_orientation: true,
+        })))
+    }
+
+    /// Create a new bilevel image from raw pixels with one bit per pixel.
+    ///
+    /// Each row of `data` needs to start at a new byte, and a set bit represents a black
+    /// pixel. The image is compressed with CCITT Group 4 encoding, which is usually
+    /// much more effective than the default compression for scanned black-and-white
+    /// documents.
+    ///
+    /// Returns `None` if the length of the data doesn't match the dimensions.
+    pub fn from_bilevel(data: Vec<u8>, width: u32, height: u32) -> Option<Image> {
+        if width == 0 || height == 0 || data.len() != width.div_ceil(8) as usize * height as usize {
+            return None;
+        }
+
+        let hash = (data.sip_hash(), width, height).sip_hash();
+        let metadata = ImageMetadata {
+            has_alpha: false,
+            size: (width, height),
+            bits_per_component: BitsPerComponent::Eight,
+            color_space: ImageColorspace::Luma,
+            icc: None,
+            jpx: false,
+            stencil: false,
+            orientation: 1,
+        };
+
+        Some(Self(Arc::new(ImageRepr {
+            inner: ImageData::eager(move || {
+                Some(Repr::Ccitt(CcittRepr {
+                    data: encode_g4(&data, width, height),
+                }))
+            }),
+            metadata,
+            sip: hash,
+            interpolate: false,
+            indexed: false,
+            respect_orientation: true,
+        })))
+    }
+
+    /// Create a new stencil mask from raw pixels with one bit per pixel.
+    ///
+    /// Instead of having colors of its own, a stencil mask is painted with the fill
+    /// that is active when drawing it, which can also be a gradient or a pattern. If no
+    /// fill is active, the stencil mask is painted black.
+    ///
+    /// Each row of `data` needs to start at a new byte. A set bit (1) marks a pixel that
+    /// is painted with the fill, while pixels with a cleared bit (0) are left unchanged.
+    ///
+    /// Returns `None` if the length of the data doesn't match the dimensions.
+    pub fn stencil(data: Vec<u8>, width: u32, height: u32) -> Option<Image> {
+        let image = Self::from_bilevel(data, width, height)?;
+        let mut metadata = image.0.metadata.clone();
+        metadata.stencil = true;
+
+        Some(Self(Arc::new(ImageRepr {
+            inner: image.0.inner.clone(),
+            metadata,
+            // Make sure the image is deduplicated separately from the bilevel version.
+            sip: (image.0.sip, true).sip_hash(),
+            interpolate: false,
+            indexed: false,
+            respect_orientation: true,
+        })))
+    }
+
+    /// Whether the image should be written with an indexed color space if it
+    /// consists of at most 256 unique colors.
+    ///
+    /// This can reduce the size of images with only few distinct colors, such as
+    /// screenshots, considerably. Images with more colors, images where the indices
+    /// wouldn't be smaller than the colors themselves, sixteen bit images and JPEG images
+    /// are written as usual. Note that detecting the unique colors can be expensive for
+    /// large images.
+    pub fn indexed(self, indexed: bool) -> Self {
+        Self(Arc::new(ImageRepr {
+            inner: self.0.inner.clone(),
+            metadata: self.0.metadata.clone(),
+            sip: self.0.sip,
+            interpolate: self.0.interpolate,
+            indexed,
+            respect_orientation: self.0.respect_orientation,
+        }))
+    }
+
+    /// Whether PDF viewers should smooth the image when scaling it up, instead of
+    /// showing its individual pixels.
+    ///
+    /// This is the same as the `interpolate` argument of the constructors, and is
+    /// mostly useful for images created with [`Image::from_rgba8`] or
+    /// [`Image::from_bilevel`]. Note that interpolation is forbidden by some
+    /// standards, like for example PDF/A.
+    pub fn interpolate(self, interpolate: bool) -> Self {
+        Self(Arc::new(ImageRepr {
+            inner: self.0.inner.clone(),
+            metadata: self.0.metadata.clone(),
+            sip: self.0.sip,
+            interpolate,
+            indexed: self.0.indexed,
+            respect_orientation: self.0.respect_orientation,
+        }))
+    }
+
+    /// Whether the EXIF orientation of the image should be applied when drawing it.
+    ///
+    /// Photos taken with a phone or camera are often stored sideways or mirrored,
+    /// together with an EXIF tag indicating how they should be displayed. By default,
+    /// krilla reads this tag from JPEG images and rotates or flips them accordingly
+    /// when they are drawn, so that they appear upright. Disable this if you want the
+    /// pixels to be drawn as they are stored, for example because you are handling the
+    /// orientation yourself. Images without an orientation tag are not affected.
+    pub fn respect_orientation(self, respect_orientation: bool) -> Self {
+        Self(Arc::new(ImageRepr {
+            inner: self.0.inner.clone(),
+            metadata: self.0.metadata.clone(),
+            sip: self.0.sip,
+            interpolate: self.0.interpolate,
+            indexed: self.0.indexed,
+            respect_orientation,
         }))
     }
 
     /// Return the size of the image.
+    ///
+    /// If the orientation of the image is respected and it is rotated by 90 or 270
+    /// degrees, the width and height are swapped, so that this always returns
+    /// the size of the image as it will be drawn.
     pub fn size(&self) -> (u32, u32) {
-        self.0.size()
+        let (width, height) = self.0.size();
+
+        if self.orientation() >= 5 {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// The EXIF orientation that is applied when drawing the image.
+    fn orientation(&self) -> u8 {
+        if self.0.respect_orientation {
+            self.0.metadata.orientation
+        } else {
+            1
+        }
+    }
+
+    /// The transform that maps the unit square of the image to a rectangle with
+    /// the given size, taking the orientation of the image into account.
+    pub(crate) fn transform(&self, size: Size) -> Transform {
+        let (w, h) = (size.width(), size.height());
+
+        // The image space has its origin at the bottom left, while the drawn
+        // image should have its origin at the top left.
+        let (sx, ky, kx, sy, tx, ty) = match self.orientation() {
+            // Mirrored horizontally.
+            2 => (-1.0, 0.0, 0.0, -1.0, 1.0, 1.0),
+            // Rotated by 180 degrees.
+            3 => (-1.0, 0.0, 0.0, 1.0, 1.0, 0.0),
+            // Mirrored vertically.
+            4 => (1.0, 0.0, 0.0, 1.0, 0.0, 0.0),
+            // Transposed, i.e. mirrored along the main diagonal.
+            5 => (0.0, 1.0, -1.0, 0.0, 1.0, 0.0),
+            // Rotated by 90 degrees clockwise.
+            6 => (0.0, 1.0, 1.0, 0.0, 0.0, 0.0),
+            // Transversed, i.e. mirrored along the anti-diagonal.
+            7 => (0.0, -1.0, 1.0, 0.0, 0.0, 1.0),
+            // Rotated by 90 degrees counter-clockwise.
+            8 => (0.0, -1.0, -1.0, 0.0, 1.0, 1.0),
+            _ => (1.0, 0.0, 0.0, -1.0, 0.0, 1.0),
+        };
+
+        Transform::from_row(w * sx, h * ky, w * kx, h * sy, w * tx, h * ty)
+    }
+
+    pub(crate) fn is_stencil(&self) -> bool {
+        self.0.metadata.stencil
     }
 
     fn icc(&self) -> Option<GenericICCProfile> {
@@ -396,9 +1019,15 @@ impl Image {
             sc.register_validation_error(ValidationError::ImageInterpolation(sc.location));
         }
 
+        if self.0.metadata.jpx {
+            sc.register_validation_error(ValidationError::Jpeg2000Image(sc.location));
+        }
+
         let serialize_settings = sc.serialize_settings().clone();
+        let encryptor = sc.encryptor();
 
-        let cs = {
+        // Stencil masks don't have a color space.
+        let cs = (!self.is_stencil()).then(|| {
             let cs = match self.color_space() {
                 ImageColorspace::Rgb => rgb::color_space(sc.serialize_settings().no_device_cs),
                 ImageColorspace::Luma => luma::color_space(sc.serialize_settings().no_device_cs),
@@ -412,13 +1041,24 @@ impl Image {
             };
 
             sc.register_colorspace(cs)
-        };
+        });
 
         let supports_bit_depth = sc
             .serialize_settings()
             .configuration
             .version()
             .supports_bit_depth(self.0.metadata.bits_per_component);
+        // If the validator prohibits JPEG 2000 images, the validation error registered
+        // above is more helpful than an error about the PDF version.
+        let unsupported_jpx = !sc
+            .serialize_settings()
+            .configuration
+            .version()
+            .supports_jpx()
+            && !sc
+                .serialize_settings()
+                .validator()
+                .prohibits(&ValidationError::Jpeg2000Image(None));
         let location = sc.location;
 
         Deferred::new(move || {
@@ -426,24 +1066,31 @@ impl Image {
                 return Err(KrillaError::SixteenBitImage(self.clone(), location));
             }
 
+            if self.0.metadata.jpx && unsupported_jpx {
+                return Err(KrillaError::Jpeg2000Image(self.clone(), location));
+            }
+
             let mut chunk = Chunk::new();
 
-            let repr = self
-                .0
-                .inner
-                .wait()
-                .as_ref()
-                .ok_or(KrillaError::Image(self.clone(), location))?;
+            let lazy_repr;
+            let repr = match &self.0.inner {
+                ImageData::Eager(repr) => repr.wait().as_ref(),
+                ImageData::Lazy(load) => {
+                    lazy_repr = load();
+                    lazy_repr.as_ref()
+                }
+            }
+            .ok_or(KrillaError::Image(self.clone(), location))?;
 
             let alpha_mask = match repr {
                 Repr::Sampled(sampled) => sampled.alpha_channel.as_ref().map(|mask_data| {
                     let soft_mask_id = soft_mask_id.unwrap();
                     let mask_stream = FilterStreamBuilder::new_from_deflated(mask_data)
-                        .finish(&serialize_settings);
+                        .finish(&serialize_settings, encryptor);
                     let mut s_mask = chunk.image_xobject(soft_mask_id, mask_stream.encoded_data());
                     mask_stream.write_filters(s_mask.deref_mut().deref_mut());
-                    s_mask.width(self.size().0 as i32);
-                    s_mask.height(self.size().1 as i32);
+                    s_mask.width(self.0.size().0 as i32);
+                    s_mask.height(self.0.size().1 as i32);
                     s_mask.pair(
                         Name(b"ColorSpace"),
                         // Mask color space must be device gray -- see Table 145.
@@ -454,28 +1101,66 @@ impl Image {
                         s_mask.interpolate(true);
                     }
 
-                    s_mask.bits_per_component(repr.bits_per_component().as_u8() as i32);
+                    s_mask.bits_per_component(repr.bits_per_component().alpha().as_u8() as i32);
                     soft_mask_id
                 }),
-                Repr::Jpeg(_) => None,
+                Repr::Jpeg(_) | Repr::Jpx(_) | Repr::Ccitt(_) => None,
+            };
+
+            let palette = match repr {
+                Repr::Sampled(s) if self.0.indexed => s.palette(self.color_space(), self.0.size()),
+                _ => None,
             };
 
-            let filter_stream = match repr {
-                Repr::Sampled(s) => FilterStreamBuilder::new_from_deflated(&s.color_channel)
-                    .finish(&serialize_settings),
-                Repr::Jpeg(j) => FilterStreamBuilder::new_from_jpeg_data(j.data.as_ref())
-                    .finish(&serialize_settings),
+            let filter_stream = match (repr, &palette) {
+                (_, Some(palette)) => FilterStreamBuilder::new_from_deflated(&palette.indices)
+                    .finish(&serialize_settings, encryptor),
+                (Repr::Sampled(s), None) => {
+                    FilterStreamBuilder::new_from_deflated(&s.color_channel)
+                        .finish(&serialize_settings, encryptor)
+                }
+                (Repr::Jpeg(j), None) => FilterStreamBuilder::new_from_jpeg_data(j.data.as_ref())
+                    .finish(&serialize_settings, encryptor),
+                (Repr::Jpx(j), None) => FilterStreamBuilder::new_from_jpx_data(j.data.as_ref())
+                    .finish(&serialize_settings, encryptor),
+                (Repr::Ccitt(c), None) => FilterStreamBuilder::new_from_ccitt_data(
+                    &c.data,
+                    self.0.size().0,
+                    self.0.size().1,
+                )
+                .finish(&serialize_settings, encryptor),
             };
 
             let mut image_x_object = chunk.image_xobject(root_ref, filter_stream.encoded_data());
             filter_stream.write_filters(image_x_object.deref_mut().deref_mut());
-            image_x_object.width(self.size().0 as i32);
-            image_x_object.height(self.size().1 as i32);
-
-            if let Some(icc_ref) = icc_ref {
-                image_x_object.pair(Name(b"ColorSpace"), icc_ref);
-            } else {
-                set_colorspace(cs, image_x_object.deref_mut());
+            image_x_object.width(self.0.size().0 as i32);
+            image_x_object.height(self.0.size().1 as i32);
+
+            match (cs, &palette) {
+                // With the default decode array, the cleared bits of the decoded data are
+                // painted, which correspond to the set bits of the original data.
+                (None, _) => {
+                    image_x_object.image_mask(true);
+                }
+                (Some(cs), Some(palette)) => {
+                    let mut indexed = image_x_object.insert(Name(b"ColorSpace")).array();
+                    indexed.item(Name(b"Indexed"));
+                    if let Some(icc_ref) = icc_ref {
+                        indexed.item(icc_ref);
+                    } else {
+                        write_color_space_item(&mut indexed, cs);
+                    }
+                    indexed.item(palette.len() as i32 - 1);
+                    indexed.item(PdfStr::str(&palette.colors, encryptor));
+                    indexed.finish();
+                }
+                (Some(cs), None) => {
+                    if let Some(icc_ref) = icc_ref {
+                        image_x_object.pair(Name(b"ColorSpace"), icc_ref);
+                    } else {
+                        set_colorspace(cs, image_x_object.deref_mut());
+                    }
+                }
             }
 
             if self.0.interpolate {
@@ -484,9 +1169,7 @@ impl Image {
 
             // Photoshop CMYK images need to be inverted, see
             // https://github.com/sile-typesetter/libtexpdf/blob/1891bee5e0b73165e4a259f910d3ea3fe1df0b42/jpegimage.c#L25-L51
-            // I'm not sure if this applies to all JPEG CMYK images out there, but for now we just
-            // always do it. In libtexpdf, they only seem to do it if they can find the Adobe APP
-            // marker.
+            // Like libtexpdf, we only do so if the Adobe APP14 marker is present.
             if let Repr::Jpeg(j) = repr {
                 if j.invert_cmyk {
                     image_x_object
@@ -494,7 +1177,20 @@ impl Image {
                 }
             }
 
-            image_x_object.bits_per_component(repr.bits_per_component().as_u8() as i32);
+            // The bit depth of JPEG 2000 images is stored in the codestream.
+            match (&palette, repr) {
+                (Some(palette), _) => {
+                    image_x_object.bits_per_component(palette.bits_per_index as i32);
+                }
+                (None, Repr::Jpx(_)) => {}
+                (None, Repr::Ccitt(_)) => {
+                    image_x_object.bits_per_component(1);
+                }
+                (None, _) => {
+                    image_x_object.bits_per_component(repr.bits_per_component().as_u8() as i32);
+                }
+            };
+
             if let Some(soft_mask_id) = alpha_mask {
                 image_x_object.s_mask(soft_mask_id);
             }
@@ -539,6 +1235,9 @@ fn png_metadata(data: &[u8]) -> Option<ImageMetadata> {
         bits_per_component,
         color_space: image_color_space,
         icc,
+        jpx: false,
+        stencil: false,
+        orientation: 1,
     })
 }
 
@@ -593,6 +1292,9 @@ fn jpeg_metadata(data: &[u8]) -> Option<ImageMetadata> {
         bits_per_component: BitsPerComponent::Eight,
         color_space: image_color_space,
         icc,
+        jpx: false,
+        stencil: false,
+        orientation: jpeg_orientation(data).unwrap_or(1),
     })
 }
 
@@ -611,9 +1313,10 @@ fn decode_jpeg(data: Data) -> Option<Repr> {
             | ColorSpace::YCCK
     ) {
         Some(Repr::Jpeg(JpegRepr {
-            data,
             bits_per_component: BitsPerComponent::Eight,
-            invert_cmyk: matches!(input_color_space, ColorSpace::YCCK | ColorSpace::CMYK),
+            invert_cmyk: matches!(input_color_space, ColorSpace::YCCK | ColorSpace::CMYK)
+                && has_adobe_marker(data.as_ref()),
+            data,
         }))
     } else {
         // JPEGs shouldn't be able to have a different color space?
@@ -621,6 +1324,213 @@ fn decode_jpeg(data: Data) -> Option<Repr> {
     }
 }
 
+fn decode_jpeg_pixels(data: Data) -> Option<Repr> {
+    let mut decoder = JpegDecoder::new(data.as_ref());
+    decoder.decode_headers().ok()?;
+
+    let input_color_space = decoder.get_input_colorspace()?;
+    let output_color_space = match input_color_space {
+        ColorSpace::Luma => ColorSpace::Luma,
+        ColorSpace::YCbCr | ColorSpace::RGB => ColorSpace::RGB,
+        ColorSpace::CMYK | ColorSpace::YCCK => ColorSpace::CMYK,
+        _ => return None,
+    };
+
+    let options = DecoderOptions::default().jpeg_set_out_colorspace(output_color_space);
+    let mut decoder = JpegDecoder::new_with_options(data.as_ref(), options);
+    let mut pixels = decoder.decode().ok()?;
+
+    // See the comment in `Image::serialize`.
+    if output_color_space == ColorSpace::CMYK && has_adobe_marker(data.as_ref()) {
+        pixels.iter_mut().for_each(|p| *p = 255 - *p);
+    }
+
+    Some(Repr::Sampled(SampledRepr {
+        color_channel: deflate_encode(&pixels),
+        alpha_channel: None,
+        bits_per_component: BitsPerComponent::Eight,
+    }))
+}
+
+/// Whether the JPEG contains an Adobe APP14 marker, which indicates that CMYK
+/// data is stored inverted.
+fn has_adobe_marker(data: &[u8]) -> bool {
+    jpeg_segment(data, 0xEE, b"Adobe").is_some()
+}
+
+/// Read the orientation tag from the EXIF data in the APP1 segment of a JPEG.
+fn jpeg_orientation(data: &[u8]) -> Option<u8> {
+    const ORIENTATION_TAG: u16 = 0x0112;
+
+    let tiff = jpeg_segment(data, 0xE1, b"Exif\0\0")?;
+    let big_endian = match tiff.get(0..2)? {
+        b"II" => false,
+        b"MM" => true,
+        _ => return None,
+    };
+
+    let read_u16 = |pos: usize| {
+        let bytes = [*tiff.get(pos)?, *tiff.get(pos + 1)?];
+        Some(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+    let read_u32 = |pos: usize| {
+        let first = read_u16(pos)? as u32;
+        let second = read_u16(pos + 2)? as u32;
+        Some(if big_endian {
+            (first << 16) | second
+        } else {
+            (second << 16) | first
+        })
+    };
+
+    let ifd = read_u32(4)? as usize;
+    let num_entries = read_u16(ifd)? as usize;
+
+    for i in 0..num_entries {
+        let entry = ifd + 2 + i * 12;
+
+        if read_u16(entry)? == ORIENTATION_TAG {
+            let orientation = read_u16(entry + 8)?;
+            return (1..=8).contains(&orientation).then_some(orientation as u8);
+        }
+    }
+
+    None
+}
+
+/// Find the first segment with the given marker whose data starts with the given
+/// identifier, and return the data after the identifier.
+fn jpeg_segment<'a>(data: &'a [u8], marker: u8, identifier: &[u8]) -> Option<&'a [u8]> {
+    // Skip the SOI marker.
+    let mut pos = 2;
+
+    while pos + 4 <= data.len() && data[pos] == 0xFF {
+        let cur_marker = data[pos + 1];
+
+        // Fill bytes.
+        if cur_marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+
+        // The image data starts after the SOS marker.
+        if cur_marker == 0xDA {
+            break;
+        }
+
+        let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+
+        if cur_marker == marker {
+            if let Some(segment) = data.get(pos + 4..pos + 2 + len) {
+                if let Some(rest) = segment.strip_prefix(identifier) {
+                    return Some(rest);
+                }
+            }
+        }
+
+        pos += 2 + len;
+    }
+
+    None
+}
+
+fn jpx_metadata(data: &[u8]) -> Option<ImageMetadata> {
+    const JP2_SIGNATURE: &[u8] = b"\x00\x00\x00\x0CjP  \r\n\x87\n";
+    const CODESTREAM_SIGNATURE: &[u8] = b"\xFF\x4F\xFF\x51";
+
+    let (size, num_components, icc) = if data.starts_with(JP2_SIGNATURE) {
+        let header = jp2_box(data, b"jp2h")?;
+        let ihdr = jp2_box(header, b"ihdr")?;
+        let height = u32::from_be_bytes(ihdr.get(0..4)?.try_into().ok()?);
+        let width = u32::from_be_bytes(ihdr.get(4..8)?.try_into().ok()?);
+        let num_components = u16::from_be_bytes(ihdr.get(8..10)?.try_into().ok()?);
+
+        // Channel definitions can mark channels as opacity channels, which we don't support.
+        if let Some(cdef) = jp2_box(header, b"cdef") {
+            let num_channels = u16::from_be_bytes(cdef.get(0..2)?.try_into().ok()?) as usize;
+
+            for i in 0..num_channels {
+                let channel = cdef.get(2 + i * 6..2 + (i + 1) * 6)?;
+
+                if matches!(u16::from_be_bytes([channel[2], channel[3]]), 1 | 2) {
+                    return None;
+                }
+            }
+        }
+
+        // Method 2 and 3 indicate an embedded ICC profile.
+        let icc = jp2_box(header, b"colr")
+            .filter(|colr| matches!(colr.first(), Some(2 | 3)))
+            .and_then(|colr| colr.get(3..));
+
+        ((width, height), num_components, icc)
+    } else if data.starts_with(CODESTREAM_SIGNATURE) {
+        // The SIZ marker segment directly follows the SOC marker.
+        let siz = data.get(6..)?;
+        let read_u32 =
+            |pos: usize| Some(u32::from_be_bytes(siz.get(pos..pos + 4)?.try_into().ok()?));
+        let width = read_u32(2)?.checked_sub(read_u32(10)?)?;
+        let height = read_u32(6)?.checked_sub(read_u32(14)?)?;
+        let num_components = u16::from_be_bytes(siz.get(34..36)?.try_into().ok()?);
+
+        ((width, height), num_components, None)
+    } else {
+        return None;
+    };
+
+    let color_space = match num_components {
+        1 => ImageColorspace::Luma,
+        3 => ImageColorspace::Rgb,
+        4 => ImageColorspace::Cmyk,
+        _ => return None,
+    };
+
+    if size.0 == 0 || size.1 == 0 {
+        return None;
+    }
+
+    Some(ImageMetadata {
+        size,
+        color_space,
+        has_alpha: false,
+        bits_per_component: BitsPerComponent::Eight,
+        icc: icc.and_then(|d| get_icc_profile_type(d, color_space)),
+        jpx: true,
+        stencil: false,
+        orientation: 1,
+    })
+}
+
+/// Find the contents of the first JP2 box with the given type.
+fn jp2_box<'a>(mut data: &'a [u8], box_type: &[u8; 4]) -> Option<&'a [u8]> {
+    while data.len() >= 8 {
+        let len = u32::from_be_bytes(data[0..4].try_into().ok()?) as u64;
+
+        let (header_len, len) = match len {
+            // The box extends to the end of the data.
+            0 => (8, data.len() as u64),
+            // The length is stored as a 64-bit integer after the type.
+            1 => (16, u64::from_be_bytes(data.get(8..16)?.try_into().ok()?)),
+            _ => (8, len),
+        };
+
+        let len = usize::try_from(len).ok()?;
+        let contents = data.get(header_len..len)?;
+
+        if &data[4..8] == box_type {
+            return Some(contents);
+        }
+
+        data = &data[len..];
+    }
+
+    None
+}
+
 fn decode_gif(data: Data) -> Option<Repr> {
     let mut decoder = gif::DecodeOptions::new();
     decoder.set_color_output(gif::ColorOutput::RGBA);
@@ -647,6 +1557,9 @@ fn gif_metadata(data: &[u8]) -> Option<ImageMetadata> {
         size: (size.width as u32, size.height as u32),
         color_space: ImageColorspace::Rgb,
         icc: None,
+        jpx: false,
+        stencil: false,
+        orientation: 1,
     })
 }
 
@@ -665,6 +1578,9 @@ fn webp_metadata(data: &[u8]) -> Option<ImageMetadata> {
         size,
         color_space,
         icc,
+        jpx: false,
+        stencil: false,
+        orientation: 1,
     })
 }
 
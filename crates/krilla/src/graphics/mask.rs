@@ -1,16 +1,21 @@
 //! Alpha and luminosity masks.
 
+use std::hash::{Hash, Hasher};
+use std::ops::DerefMut;
+
 use pdf_writer::{Chunk, Finish, Name, Ref};
 
 use crate::chunk_container::ChunkContainerFn;
 use crate::geom::{Rect, Transform};
+use crate::graphics::color::rgb;
 use crate::graphics::shading_function::{GradientProperties, ShadingFunction};
 use crate::graphics::xobject::XObject;
 use crate::resource;
 use crate::resource::Resourceable;
 use crate::serialize::{Cacheable, SerializeContext};
-use crate::stream::Stream;
 use crate::stream::StreamBuilder;
+use crate::stream::{FilterStreamBuilder, Stream};
+use crate::surface::Surface;
 use crate::util::Deferred;
 
 /// A mask. Can be a luminance mask or an alpha mask.
@@ -29,6 +34,10 @@ pub struct Mask {
     /// we want to manually set the bbox of the underlying XObject to match the shape that the
     /// gradient is being applied to.
     custom_bbox: Option<Rect>,
+    /// The backdrop color of a luminosity mask.
+    backdrop: Option<rgb::Color>,
+    /// The transfer function that is applied to the values of the mask.
+    transfer_function: Option<TransferFunction>,
 }
 
 impl Mask {
@@ -39,9 +48,43 @@ impl Mask {
             stream,
             mask_type,
             custom_bbox: None,
+            backdrop: None,
+            transfer_function: None,
         }
     }
 
+    pub(crate) fn from_surface(
+        sc: &mut SerializeContext,
+        mask_type: MaskType,
+        f: impl FnOnce(&mut Surface),
+    ) -> Self {
+        let mut stream_builder = StreamBuilder::new(sc);
+        let mut surface = stream_builder.surface();
+        f(&mut surface);
+        surface.finish();
+
+        Self::new(stream_builder.finish(), mask_type)
+    }
+
+    /// Set the backdrop color of the mask.
+    ///
+    /// The backdrop is the color that areas of a luminosity mask which aren't
+    /// covered by its content are treated as. By default, it is black, meaning
+    /// that those areas are fully transparent. It has no effect on alpha masks.
+    pub fn with_backdrop(mut self, color: rgb::Color) -> Self {
+        self.backdrop = Some(color);
+        self
+    }
+
+    /// Set a transfer function that maps the values of the mask to the resulting
+    /// opacities.
+    ///
+    /// Transfer functions that don't change any values are omitted.
+    pub fn with_transfer_function(mut self, transfer_function: TransferFunction) -> Self {
+        self.transfer_function = (!transfer_function.is_identity()).then_some(transfer_function);
+        self
+    }
+
     /// Create a new mask for a shading to encode the opacity channels.
     pub(crate) fn new_from_shading(
         gradient_properties: GradientProperties,
@@ -60,6 +103,15 @@ impl Mask {
                     return None;
                 }
             }
+            GradientProperties::MeshGradient(mg) => {
+                if mg
+                    .patches
+                    .iter()
+                    .all(|p| p.opacities.iter().all(|o| o.get() == 1.0))
+                {
+                    return None;
+                }
+            }
         }
 
         let shading_function = ShadingFunction::new(gradient_properties, true);
@@ -78,10 +130,126 @@ impl Mask {
             stream: shading_stream,
             mask_type: MaskType::Luminosity,
             custom_bbox: Some(bbox),
+            backdrop: None,
+            transfer_function: None,
         })
     }
 }
 
+/// A transfer function of a mask, which maps each value of the mask
+/// in the range from 0 to 1 to the resulting opacity.
+#[derive(Debug, Clone)]
+pub struct TransferFunction(TransferFunctionRepr);
+
+#[derive(Debug, Clone)]
+enum TransferFunctionRepr {
+    Exponential(f32),
+    Sampled(Vec<u8>),
+}
+
+impl TransferFunction {
+    /// Create a new transfer function that maps each value `x` to `x^exponent`.
+    ///
+    /// Returns `None` if the exponent isn't finite and positive.
+    pub fn exponential(exponent: f32) -> Option<Self> {
+        if !exponent.is_finite() || exponent <= 0.0 {
+            return None;
+        }
+
+        Some(Self(TransferFunctionRepr::Exponential(exponent)))
+    }
+
+    /// Create a new transfer function from a lookup table. The first sample
+    /// corresponds to a value of 0 and the last one to a value of 1, values
+    /// in-between are interpolated linearly.
+    ///
+    /// Returns `None` if there are fewer than two samples.
+    pub fn sampled(samples: Vec<u8>) -> Option<Self> {
+        if samples.len() < 2 {
+            return None;
+        }
+
+        Some(Self(TransferFunctionRepr::Sampled(samples)))
+    }
+
+    /// Create a new transfer function that inverts the values of the mask.
+    pub fn inverted() -> Self {
+        Self(TransferFunctionRepr::Sampled(vec![255, 0]))
+    }
+
+    /// Whether the transfer function maps each value to itself.
+    pub(crate) fn is_identity(&self) -> bool {
+        match &self.0 {
+            TransferFunctionRepr::Exponential(exponent) => *exponent == 1.0,
+            TransferFunctionRepr::Sampled(samples) => {
+                let last = (samples.len() - 1) as f32;
+
+                samples
+                    .iter()
+                    .enumerate()
+                    .all(|(i, s)| *s == (i as f32 / last * 255.0).round() as u8)
+            }
+        }
+    }
+
+    fn serialize(&self, chunk: &mut Chunk, root_ref: Ref, sc: &SerializeContext) {
+        match &self.0 {
+            TransferFunctionRepr::Exponential(exponent) => {
+                let mut exp = chunk.exponential_function(root_ref);
+                exp.domain([0.0, 1.0]);
+                exp.range([0.0, 1.0]);
+                exp.c0([0.0]);
+                exp.c1([1.0]);
+                exp.n(*exponent);
+                exp.finish();
+            }
+            TransferFunctionRepr::Sampled(samples) => {
+                let serialize_settings = sc.serialize_settings();
+                let encryptor = sc.encryptor();
+                let stream = FilterStreamBuilder::new_from_binary_data(samples)
+                    .finish(&serialize_settings, encryptor);
+
+                let mut function = chunk.sampled_function(root_ref, stream.encoded_data());
+                stream.write_filters(function.deref_mut().deref_mut());
+                function.domain([0.0, 1.0]);
+                function.range([0.0, 1.0]);
+                function.size([samples.len() as i32]);
+                function.bits_per_sample(8);
+                function.finish();
+            }
+        }
+    }
+}
+
+impl PartialEq for TransferFunction {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (TransferFunctionRepr::Exponential(a), TransferFunctionRepr::Exponential(b)) => {
+                a.to_bits() == b.to_bits()
+            }
+            (TransferFunctionRepr::Sampled(a), TransferFunctionRepr::Sampled(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for TransferFunction {}
+
+impl Hash for TransferFunction {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match &self.0 {
+            TransferFunctionRepr::Exponential(exponent) => {
+                0u8.hash(state);
+                exponent.to_bits().hash(state);
+            }
+            TransferFunctionRepr::Sampled(samples) => {
+                1u8.hash(state);
+                samples.hash(state);
+            }
+        }
+    }
+}
+
 /// A mask type.
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
 pub enum MaskType {
@@ -117,8 +285,28 @@ impl Cacheable for Mask {
         dict.pair(Name(b"S"), self.mask_type.to_name());
         dict.pair(Name(b"G"), x_object);
 
+        if self.mask_type == MaskType::Luminosity {
+            if let Some(backdrop) = self.backdrop {
+                dict.insert(Name(b"BC"))
+                    .array()
+                    .items(backdrop.to_pdf_color());
+            }
+        }
+
+        let transfer_function_ref = self.transfer_function.as_ref().map(|_| sc.new_ref());
+
+        if let Some(transfer_function_ref) = transfer_function_ref {
+            dict.pair(Name(b"TR"), transfer_function_ref);
+        }
+
         dict.finish();
 
+        if let (Some(transfer_function), Some(transfer_function_ref)) =
+            (&self.transfer_function, transfer_function_ref)
+        {
+            transfer_function.serialize(&mut chunk, transfer_function_ref, sc);
+        }
+
         Deferred::new(|| chunk)
     }
 }
@@ -1,4 +1,6 @@
 pub mod blend;
+#[cfg(feature = "raster-images")]
+pub(crate) mod ccitt;
 pub mod color;
 pub mod graphic;
 pub(crate) mod graphics_state;
@@ -9,5 +11,6 @@ pub mod mask;
 pub mod paint;
 pub(crate) mod shading_function;
 pub(crate) mod shading_pattern;
+pub mod template;
 pub(crate) mod tiling_pattern;
 pub(crate) mod xobject;
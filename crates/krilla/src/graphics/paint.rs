@@ -5,10 +5,12 @@ use std::sync::Arc;
 
 use pdf_writer::types::{LineCapStyle, LineJoinStyle};
 
-use crate::geom::Transform;
-use crate::graphics::color::{cmyk, luma, rgb, Color};
+use crate::geom::{Point, Size, Transform};
+use crate::graphics::color::{cmyk, lab, luma, rgb, Color, DeviceNColor, SpotColor};
 use crate::num::NormalizedF32;
-use crate::stream::Stream;
+use crate::serialize::SerializeContext;
+use crate::stream::{Stream, StreamBuilder};
+use crate::surface::Surface;
 
 /// A linear gradient.
 #[derive(Debug, Clone, PartialEq)]
@@ -135,6 +137,164 @@ impl Hash for SweepGradient {
     }
 }
 
+/// A patch of a mesh gradient.
+///
+/// A patch is bounded by four cubic Bézier curves, which connect its four corners.
+/// The colors of the corners are smoothly interpolated across the whole patch.
+/// Two corners of a patch may coincide, for example to create a triangular patch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshPatch {
+    /// The corners of the patch. The edge `i` of the patch goes from corner `i`
+    /// to corner `(i + 1) % 4`.
+    pub corners: [Point; 4],
+    /// The two control points of each edge, in the direction of the edge.
+    pub control_points: [[Point; 2]; 4],
+    /// The colors of the corners.
+    ///
+    /// Note that all colors of all patches of a mesh gradient need to be in the
+    /// same color space.
+    pub colors: [Color; 4],
+    /// The opacities of the corners.
+    pub opacities: [NormalizedF32; 4],
+}
+
+impl MeshPatch {
+    /// Create a new opaque patch with straight edges.
+    pub fn new(corners: [Point; 4], colors: [Color; 4]) -> Self {
+        let control_points = std::array::from_fn(|i| {
+            let start = corners[i];
+            let end = corners[(i + 1) % 4];
+            let lerp = |t: f32| {
+                Point::from_xy(
+                    start.x + (end.x - start.x) * t,
+                    start.y + (end.y - start.y) * t,
+                )
+            };
+
+            [lerp(1.0 / 3.0), lerp(2.0 / 3.0)]
+        });
+
+        Self {
+            corners,
+            control_points,
+            colors,
+            opacities: [NormalizedF32::ONE; 4],
+        }
+    }
+
+    /// The points of the edge `i`, including both corners.
+    pub(crate) fn edge(&self, i: usize) -> [Point; 4] {
+        [
+            self.corners[i],
+            self.control_points[i][0],
+            self.control_points[i][1],
+            self.corners[(i + 1) % 4],
+        ]
+    }
+
+    /// Whether all points of the patch coincide, meaning that it doesn't cover any area.
+    pub(crate) fn is_degenerate(&self) -> bool {
+        let first = self.corners[0];
+
+        self.corners
+            .iter()
+            .chain(self.control_points.iter().flatten())
+            .all(|p| *p == first)
+    }
+}
+
+impl Eq for MeshPatch {}
+
+impl Hash for MeshPatch {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for p in self
+            .corners
+            .iter()
+            .chain(self.control_points.iter().flatten())
+        {
+            p.x.to_bits().hash(state);
+            p.y.to_bits().hash(state);
+        }
+
+        self.colors.hash(state);
+        self.opacities.hash(state);
+    }
+}
+
+/// A mesh gradient, which consists of a number of patches that each smoothly
+/// interpolate between the colors of their four corners.
+///
+/// Mesh gradients are written as Coons patch meshes. Adjacent patches
+/// that share an edge with the same colors are written more compactly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshGradient {
+    /// The patches of the mesh gradient.
+    pub patches: Vec<MeshPatch>,
+    /// A transform that should be applied to the mesh gradient.
+    pub transform: Transform,
+    /// Whether the gradient should be anti-aliased.
+    pub anti_alias: bool,
+}
+
+impl Eq for MeshGradient {}
+
+impl Hash for MeshGradient {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.patches.hash(state);
+        self.transform.hash(state);
+        self.anti_alias.hash(state);
+    }
+}
+
+impl MeshGradient {
+    /// Create a new mesh gradient from a grid of corners with straight edges.
+    ///
+    /// Each item of `rows` is one row of the grid, and contains the position and
+    /// color of each corner in that row. Neighboring corners are connected to form
+    /// the patches of the gradient.
+    ///
+    /// Returns `None` if there are fewer than two rows or columns, or if not all
+    /// rows have the same length.
+    pub fn from_grid(rows: Vec<Vec<(Point, Color)>>) -> Option<Self> {
+        let columns = rows.first()?.len();
+
+        if rows.len() < 2 || columns < 2 || rows.iter().any(|r| r.len() != columns) {
+            return None;
+        }
+
+        let mut patches = vec![];
+
+        for (top, bottom) in rows.iter().zip(rows.iter().skip(1)) {
+            for c in 0..columns - 1 {
+                let tl = top[c];
+                let tr = top[c + 1];
+                let bl = bottom[c];
+                let br = bottom[c + 1];
+
+                // Alternate the orientation of the patches in a row, so that the first
+                // edge of each patch is the same as the third edge of the previous one,
+                // which allows writing them more compactly.
+                let corners = if c % 2 == 0 {
+                    [tl, bl, br, tr]
+                } else {
+                    [bl, tl, tr, br]
+                };
+
+                patches.push(MeshPatch::new(
+                    corners.map(|(p, _)| p),
+                    corners.map(|(_, color)| color),
+                ));
+            }
+        }
+
+        Some(Self {
+            patches,
+            transform: Transform::default(),
+            anti_alias: false,
+        })
+    }
+}
+
 /// A pattern.
 ///
 /// IMPORTANT: Note that you must only use a mask in the document that you created it with!
@@ -162,13 +322,128 @@ impl Hash for Pattern {
     }
 }
 
+/// A tiling pattern, which repeats a single tile across the filled or stroked area.
+///
+/// You can create a tiling pattern by calling [`Document::create_tiling_pattern`]
+/// or [`Surface::create_tiling_pattern`]. The tile is only written once to the PDF,
+/// no matter how often the pattern is used, and all resources that are used
+/// inside of the tile, such as fonts or images, are shared with the rest of
+/// the document.
+///
+/// Uncolored tiling patterns only define the shape of the tile, while its color
+/// is chosen whenever the pattern is used, see [`TilingPattern::with_color`].
+///
+/// IMPORTANT: Note that you must only use a tiling pattern in the document that you created it with!
+/// If you use it in a different document, you will end up with an invalid PDF file.
+///
+/// [`Document::create_tiling_pattern`]: crate::Document::create_tiling_pattern
+#[derive(Debug, PartialEq, Clone)]
+pub struct TilingPattern {
+    pub(crate) stream: Stream,
+    pub(crate) transform: Transform,
+    pub(crate) width: f32,
+    pub(crate) height: f32,
+    pub(crate) x_step: f32,
+    pub(crate) y_step: f32,
+    /// The color of the tile, if the pattern is uncolored.
+    pub(crate) color: Option<Color>,
+}
+
+impl Eq for TilingPattern {}
+
+impl Hash for TilingPattern {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.stream.hash(state);
+        self.transform.hash(state);
+        self.width.to_bits().hash(state);
+        self.height.to_bits().hash(state);
+        self.x_step.to_bits().hash(state);
+        self.y_step.to_bits().hash(state);
+        self.color.hash(state);
+    }
+}
+
+impl TilingPattern {
+    pub(crate) fn new(
+        sc: &mut SerializeContext,
+        size: Size,
+        x_spacing: f32,
+        y_spacing: f32,
+        transform: Transform,
+        uncolored: bool,
+        f: impl FnOnce(&mut Surface),
+    ) -> Self {
+        let x_step = size.width() + x_spacing;
+        let y_step = size.height() + y_spacing;
+
+        assert!(
+            x_step > 0.0 && y_step > 0.0,
+            "the spacing of a tiling pattern must be larger than the negative tile size"
+        );
+
+        let mut stream_builder = StreamBuilder::new(sc);
+        stream_builder.uncolored = uncolored;
+        let mut surface = stream_builder.surface();
+        f(&mut surface);
+        surface.finish();
+
+        Self {
+            stream: stream_builder.finish(),
+            transform,
+            width: size.width(),
+            height: size.height(),
+            x_step,
+            y_step,
+            color: uncolored.then(|| rgb::Color::black().into()),
+        }
+    }
+
+    /// Set the color that an uncolored tiling pattern should be painted with.
+    ///
+    /// The default color is black. Has no effect on colored tiling patterns.
+    pub fn with_color(mut self, color: impl Into<Color>) -> Self {
+        if self.color.is_some() {
+            self.color = Some(color.into());
+        }
+
+        self
+    }
+
+    /// Whether the tiling pattern is uncolored.
+    pub fn is_uncolored(&self) -> bool {
+        self.color.is_some()
+    }
+
+    /// The size of a single tile.
+    pub fn size(&self) -> Size {
+        Size::from_wh(self.width, self.height).unwrap()
+    }
+}
+
+impl From<Pattern> for TilingPattern {
+    fn from(value: Pattern) -> Self {
+        Self {
+            stream: value.stream,
+            transform: value.transform,
+            width: value.width,
+            height: value.height,
+            x_step: value.width,
+            y_step: value.height,
+            color: None,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub(crate) enum InnerPaint {
     Color(Color),
+    Spot(SpotColor),
+    DeviceN(DeviceNColor),
     LinearGradient(LinearGradient),
     RadialGradient(RadialGradient),
     SweepGradient(SweepGradient),
-    Pattern(Arc<Pattern>),
+    MeshGradient(MeshGradient),
+    Pattern(Arc<TilingPattern>),
 }
 
 /// A paint.
@@ -186,10 +461,75 @@ impl Paint {
                 Color::Rgb(rgb) => Some(rgb),
                 Color::Luma(l) => Some(rgb::Color::new(l.0, l.0, l.0)),
                 Color::Cmyk(_) => None,
+                Color::Lab(_) => None,
             },
             _ => None,
         }
     }
+
+    /// Convert all CMYK colors used by the paint to RGB.
+    pub(crate) fn cmyk_to_rgb(self) -> Paint {
+        let convert_stops = |stops: Vec<Stop>| {
+            stops
+                .into_iter()
+                .map(|stop| Stop {
+                    color: stop.color.cmyk_to_rgb(),
+                    ..stop
+                })
+                .collect::<Vec<_>>()
+        };
+
+        Paint(match self.0 {
+            InnerPaint::Color(c) => InnerPaint::Color(c.cmyk_to_rgb()),
+            InnerPaint::Spot(mut s) => {
+                s.alternate = s.alternate.cmyk_to_rgb();
+                InnerPaint::Spot(s)
+            }
+            InnerPaint::LinearGradient(mut lg) => {
+                lg.stops = convert_stops(lg.stops);
+                InnerPaint::LinearGradient(lg)
+            }
+            InnerPaint::RadialGradient(mut rg) => {
+                rg.stops = convert_stops(rg.stops);
+                InnerPaint::RadialGradient(rg)
+            }
+            InnerPaint::SweepGradient(mut sg) => {
+                sg.stops = convert_stops(sg.stops);
+                InnerPaint::SweepGradient(sg)
+            }
+            InnerPaint::MeshGradient(mut mg) => {
+                for patch in &mut mg.patches {
+                    patch.colors = patch.colors.map(Color::cmyk_to_rgb);
+                }
+
+                InnerPaint::MeshGradient(mg)
+            }
+            InnerPaint::Pattern(p) if p.color.is_some() => {
+                let mut p = Arc::unwrap_or_clone(p);
+                p.color = p.color.map(Color::cmyk_to_rgb);
+                InnerPaint::Pattern(Arc::new(p))
+            }
+            paint @ (InnerPaint::DeviceN(_) | InnerPaint::Pattern(_)) => paint,
+        })
+    }
+
+    /// Whether the paint uses a CMYK color, either directly or in a gradient stop.
+    pub(crate) fn has_cmyk(&self) -> bool {
+        let is_cmyk = |stop: &Stop| matches!(stop.color, Color::Cmyk(_));
+
+        match &self.0 {
+            InnerPaint::Color(c) => matches!(c, Color::Cmyk(_)),
+            InnerPaint::LinearGradient(lg) => lg.stops.iter().any(is_cmyk),
+            InnerPaint::RadialGradient(rg) => rg.stops.iter().any(is_cmyk),
+            InnerPaint::SweepGradient(sg) => sg.stops.iter().any(is_cmyk),
+            InnerPaint::MeshGradient(mg) => mg
+                .patches
+                .iter()
+                .any(|p| p.colors.iter().any(|c| matches!(c, Color::Cmyk(_)))),
+            InnerPaint::Pattern(p) => matches!(p.color, Some(Color::Cmyk(_))),
+            InnerPaint::Spot(_) | InnerPaint::DeviceN(_) => false,
+        }
+    }
 }
 
 impl From<rgb::Color> for Paint {
@@ -210,12 +550,30 @@ impl From<cmyk::Color> for Paint {
     }
 }
 
+impl From<lab::Color> for Paint {
+    fn from(value: lab::Color) -> Self {
+        Paint(InnerPaint::Color(value.into()))
+    }
+}
+
 impl From<Color> for Paint {
     fn from(value: Color) -> Self {
         Paint(InnerPaint::Color(value))
     }
 }
 
+impl From<SpotColor> for Paint {
+    fn from(value: SpotColor) -> Self {
+        Paint(InnerPaint::Spot(value))
+    }
+}
+
+impl From<DeviceNColor> for Paint {
+    fn from(value: DeviceNColor) -> Self {
+        Paint(InnerPaint::DeviceN(value))
+    }
+}
+
 impl From<LinearGradient> for Paint {
     fn from(value: LinearGradient) -> Self {
         Paint(InnerPaint::LinearGradient(value))
@@ -234,8 +592,20 @@ impl From<SweepGradient> for Paint {
     }
 }
 
+impl From<MeshGradient> for Paint {
+    fn from(value: MeshGradient) -> Self {
+        Paint(InnerPaint::MeshGradient(value))
+    }
+}
+
 impl From<Pattern> for Paint {
     fn from(value: Pattern) -> Self {
+        Paint(InnerPaint::Pattern(Arc::new(value.into())))
+    }
+}
+
+impl From<TilingPattern> for Paint {
+    fn from(value: TilingPattern) -> Self {
         Paint(InnerPaint::Pattern(Arc::new(value)))
     }
 }
@@ -314,14 +684,65 @@ impl LineJoin {
 }
 
 /// A stroke dash.
+///
+/// A dash array whose values are all zero is invalid, since PDF viewers render it
+/// inconsistently. Using such a dash will result in an error when finishing the document.
 #[derive(Debug, Clone, PartialEq)]
 pub struct StrokeDash {
-    /// The dash array.
+    /// The dash array, which contains the lengths of alternating dashes and gaps.
     pub array: Vec<f32>,
-    /// The offset of the dash.
+    /// The offset of the dash, i.e. the distance into the dash pattern at which
+    /// the stroke starts.
     pub offset: f32,
 }
 
+impl StrokeDash {
+    /// Create a new stroke dash.
+    ///
+    /// Returns `None` if the dash is invalid, i.e. if one of the values is negative
+    /// or not finite, or if all values of a non-empty dash array are zero.
+    pub fn new(array: Vec<f32>, offset: f32) -> Option<Self> {
+        let dash = Self { array, offset };
+        dash.is_valid().then_some(dash)
+    }
+
+    /// Whether the stroke dash is valid. See [`StrokeDash::new`] for more information.
+    pub fn is_valid(&self) -> bool {
+        self.offset.is_finite()
+            && self.array.iter().all(|v| v.is_finite() && *v >= 0.0)
+            && (self.array.is_empty() || self.array.iter().any(|v| *v > 0.0))
+    }
+
+    /// Return the dash as it will be written to the PDF.
+    ///
+    /// Dash arrays with an odd number of values are repeated once, so that each value
+    /// is used both as a dash and a gap, and the offset is reduced to lie within the
+    /// length of a single dash pattern.
+    ///
+    /// Returns `None` if the dash is invalid, or if the dash array is empty, in which
+    /// case the stroke is solid.
+    pub fn normalized(&self) -> Option<StrokeDash> {
+        if !self.is_valid() || self.array.is_empty() {
+            return None;
+        }
+
+        let mut array = self.array.clone();
+
+        if array.len() % 2 == 1 {
+            array.extend_from_within(..);
+        }
+
+        let length = array.iter().sum::<f32>();
+        let offset = if (0.0..length).contains(&self.offset) {
+            self.offset
+        } else {
+            self.offset.rem_euclid(length)
+        };
+
+        Some(StrokeDash { array, offset })
+    }
+}
+
 impl Eq for StrokeDash {}
 
 impl Hash for StrokeDash {
@@ -382,6 +803,13 @@ impl Default for Stroke {
 }
 
 impl Stroke {
+    /// Return the dash pattern that will be written for the stroke, or `None` if the
+    /// stroke is solid or its dash is invalid. See [`StrokeDash::normalized`] for more
+    /// information.
+    pub fn dash_pattern(&self) -> Option<StrokeDash> {
+        self.dash.as_ref().and_then(StrokeDash::normalized)
+    }
+
     pub(crate) fn into_tiny_skia(self) -> tiny_skia_path::Stroke {
         let mut stroke = tiny_skia_path::Stroke {
             width: self.width,
@@ -399,7 +827,7 @@ impl Stroke {
             dash: None,
         };
 
-        if let Some(stroke_dash) = self.dash {
+        if let Some(stroke_dash) = self.dash_pattern() {
             stroke.dash = tiny_skia_path::StrokeDash::new(stroke_dash.array, stroke_dash.offset);
         }
 
@@ -4,20 +4,25 @@ use std::sync::Arc;
 
 use bumpalo::Bump;
 use pdf_writer::types::{FunctionShadingType, PostScriptOp};
-use pdf_writer::{Chunk, Finish, Ref};
+use pdf_writer::{Chunk, Finish, Name, Ref};
 use tiny_skia_path::Point;
 
 use crate::chunk_container::ChunkContainerFn;
 use crate::configure::ValidationError;
+use crate::encryption::encrypt_bytes;
+use crate::geom;
 use crate::geom::{Rect, Transform};
 use crate::graphics::color::luma;
 use crate::graphics::color::Color;
-use crate::graphics::paint::{LinearGradient, RadialGradient, SweepGradient};
+use crate::graphics::paint::{
+    LinearGradient, MeshGradient, MeshPatch, RadialGradient, SweepGradient,
+};
 use crate::graphics::paint::{SpreadMethod, Stop};
 use crate::num::NormalizedF32;
 use crate::resource;
 use crate::resource::Resourceable;
 use crate::serialize::{Cacheable, SerializeContext};
+use crate::stream::FilterStreamBuilder;
 use crate::util::{set_colorspace, Deferred};
 
 #[derive(Debug, Hash, Eq, PartialEq, Clone, Copy)]
@@ -32,6 +37,10 @@ pub(crate) struct RadialAxialGradient {
     pub(crate) shading_type: FunctionShadingType,
     pub(crate) stops: Vec<Stop>,
     pub(crate) anti_alias: bool,
+    pub(crate) spread_method: SpreadMethod,
+    // The range of the stops that is spanned by the coordinates. Only differs from
+    // `[0, 1]` if the stops are repeated or reflected.
+    pub(crate) domain: [f32; 2],
 }
 
 impl Eq for RadialAxialGradient {}
@@ -45,6 +54,9 @@ impl Hash for RadialAxialGradient {
         self.shading_type.hash(state);
         self.stops.hash(state);
         self.anti_alias.hash(state);
+        self.spread_method.hash(state);
+        self.domain[0].to_bits().hash(state);
+        self.domain[1].to_bits().hash(state);
     }
 }
 
@@ -78,9 +90,18 @@ impl Hash for PostScriptGradient {
 }
 
 #[derive(Debug, Hash, Eq, PartialEq, Clone)]
+pub(crate) struct MeshGradientProperties {
+    // Only contains patches that are not degenerate.
+    pub(crate) patches: Vec<MeshPatch>,
+    pub(crate) anti_alias: bool,
+}
+
+#[derive(Debug, Hash, Eq, PartialEq, Clone)]
+#[allow(clippy::enum_variant_names)]
 pub(crate) enum GradientProperties {
     RadialAxialGradient(RadialAxialGradient),
     PostScriptGradient(PostScriptGradient),
+    MeshGradient(MeshGradientProperties),
 }
 
 impl GradientProperties {
@@ -97,6 +118,9 @@ impl GradientProperties {
                     return Some((psg.stops[0].color, psg.stops[0].opacity));
                 }
             }
+            // Even if all corners have the same color, a mesh only covers the area
+            // of its patches.
+            GradientProperties::MeshGradient(_) => {}
         }
 
         None
@@ -141,6 +165,8 @@ impl GradientPropertiesExt for LinearGradient {
                     shading_type: FunctionShadingType::Axial,
                     stops: self.stops,
                     anti_alias: self.anti_alias,
+                    spread_method: SpreadMethod::Pad,
+                    domain: [0.0, 1.0],
                 }),
                 self.transform,
             )
@@ -191,15 +217,107 @@ impl GradientPropertiesExt for SweepGradient {
     }
 }
 
+/// The maximum number of times the stops of a gradient are repeated or reflected.
+const MAX_SPREAD_REPETITIONS: f32 = 256.0;
+
+/// Calculate the range of the stops that needs to be spanned by the circles of a
+/// radial gradient, so that repeating or reflecting it covers the whole bbox.
+fn radial_spread_domain(start: (Point, f32), end: (Point, f32), bbox: Rect) -> [f32; 2] {
+    let (c0, r0) = start;
+    let (c1, r1) = end;
+
+    let dr = r1 - r0;
+    let dc = c0.distance(c1);
+
+    let max_distance = [
+        Point::from_xy(bbox.left(), bbox.top()),
+        Point::from_xy(bbox.right(), bbox.top()),
+        Point::from_xy(bbox.left(), bbox.bottom()),
+        Point::from_xy(bbox.right(), bbox.bottom()),
+    ]
+    .iter()
+    .map(|p| c0.distance(*p))
+    .fold(0.0f32, f32::max);
+
+    // Each step, the radius grows by `growth`, while the center moves by at most `dc`.
+    // If the radius doesn't outgrow the center, the circles never cover the whole bbox,
+    // so we only repeat the stops as often as we allow.
+    let steps_to_cover = |growth: f32| {
+        if growth - dc > f32::EPSILON {
+            ((max_distance - r0) / (growth - dc)).max(0.0)
+        } else {
+            MAX_SPREAD_REPETITIONS
+        }
+    };
+
+    if dr > 0.0 {
+        // Start at the point where the radius becomes zero.
+        let t0 = if r0 > 0.0 { -r0 / dr } else { 0.0 };
+        let t1 = steps_to_cover(dr).max(1.0).min(t0 + MAX_SPREAD_REPETITIONS);
+        [t0, t1]
+    } else if dr < 0.0 {
+        // End at the point where the radius becomes zero.
+        let t1 = -r0 / dr;
+        let t0 = (-steps_to_cover(-dr)).max(t1 - MAX_SPREAD_REPETITIONS);
+        [t0, t1]
+    } else {
+        // If both circles have the same radius, the circles never grow.
+        [0.0, 1.0]
+    }
+}
+
 impl GradientPropertiesExt for RadialGradient {
-    fn gradient_properties(self, _: Rect) -> (GradientProperties, Transform) {
-        // TODO: Support other spread methods
+    fn gradient_properties(self, bbox: Rect) -> (GradientProperties, Transform) {
+        let start = (Point::from_xy(self.fx, self.fy), self.fr);
+        let end = (Point::from_xy(self.cx, self.cy), self.cr);
+
+        let (coords, domain) = if self.spread_method == SpreadMethod::Pad {
+            (
+                vec![self.fx, self.fy, self.fr, self.cx, self.cy, self.cr],
+                [0.0, 1.0],
+            )
+        } else {
+            let domain = radial_spread_domain(start, end, get_expanded_bbox(bbox, self.transform));
+
+            // Extrapolate the circles that correspond to the start and end of the domain.
+            let circle = |t: f32| {
+                [
+                    start.0.x + (end.0.x - start.0.x) * t,
+                    start.0.y + (end.0.y - start.0.y) * t,
+                    (start.1 + (end.1 - start.1) * t).max(0.0),
+                ]
+            };
+
+            let mut coords = circle(domain[0]).to_vec();
+            coords.extend(circle(domain[1]));
+
+            (coords, domain)
+        };
+
         (
             GradientProperties::RadialAxialGradient(RadialAxialGradient {
-                coords: vec![self.fx, self.fy, self.fr, self.cx, self.cy, self.cr],
+                coords,
                 shading_type: FunctionShadingType::Radial,
                 stops: self.stops,
                 anti_alias: self.anti_alias,
+                spread_method: self.spread_method,
+                domain,
+            }),
+            self.transform,
+        )
+    }
+}
+
+impl GradientPropertiesExt for MeshGradient {
+    fn gradient_properties(self, _: Rect) -> (GradientProperties, Transform) {
+        (
+            GradientProperties::MeshGradient(MeshGradientProperties {
+                patches: self
+                    .patches
+                    .into_iter()
+                    .filter(|p| !p.is_degenerate())
+                    .collect(),
+                anti_alias: self.anti_alias,
             }),
             self.transform,
         )
@@ -240,6 +358,9 @@ impl Cacheable for ShadingFunction {
                 sc.register_validation_error(ValidationError::ContainsPostScript(sc.location));
                 serialize_postscript_shading(sc, &mut chunk, root_ref, psg, self.0.use_opacities)
             }
+            GradientProperties::MeshGradient(mg) => {
+                return serialize_mesh_shading(sc, chunk, root_ref, mg, self.0.use_opacities)
+            }
         }
 
         Deferred::new(|| chunk)
@@ -283,6 +404,143 @@ fn serialize_postscript_shading(
     shading.finish();
 }
 
+fn serialize_mesh_shading(
+    sc: &mut SerializeContext,
+    mut chunk: Chunk,
+    root_ref: Ref,
+    mesh_gradient: &MeshGradientProperties,
+    use_opacities: bool,
+) -> Deferred<Chunk> {
+    let patches = &mesh_gradient.patches;
+
+    let (cs, color_range) = match patches.first() {
+        Some(patch) if !use_opacities => (patch.colors[0].color_space(sc), patch.colors[0].range()),
+        _ => (
+            luma::color_space(sc.serialize_settings().no_device_cs),
+            vec![0.0, 1.0],
+        ),
+    };
+    let cs = sc.register_colorspace(cs);
+
+    let components = |patch: &MeshPatch| -> [Vec<f32>; 4] {
+        if use_opacities {
+            patch.opacities.map(|o| vec![o.get()])
+        } else {
+            patch.colors.map(|c| c.to_pdf_color())
+        }
+    };
+
+    // The coordinates are encoded relative to the bounds of all points.
+    let (mut x_min, mut x_max, mut y_min, mut y_max) = patches
+        .iter()
+        .flat_map(|p| p.corners.iter().chain(p.control_points.iter().flatten()))
+        .fold(
+            (f32::MAX, f32::MIN, f32::MAX, f32::MIN),
+            |(x_min, x_max, y_min, y_max), p| {
+                (
+                    x_min.min(p.x),
+                    x_max.max(p.x),
+                    y_min.min(p.y),
+                    y_max.max(p.y),
+                )
+            },
+        );
+
+    if patches.is_empty() {
+        (x_min, x_max, y_min, y_max) = (0.0, 1.0, 0.0, 1.0);
+    }
+
+    // Avoid an empty range if all points are on the same line.
+    if x_max - x_min <= f32::EPSILON {
+        x_max = x_min + 1.0;
+    }
+
+    if y_max - y_min <= f32::EPSILON {
+        y_max = y_min + 1.0;
+    }
+
+    let mut data = vec![];
+
+    let write_point = |data: &mut Vec<u8>, p: &geom::Point| {
+        let encode = |v: f32, min: f32, max: f32| {
+            (((v - min) as f64 / (max - min) as f64) * u32::MAX as f64).round() as u32
+        };
+
+        data.extend(encode(p.x, x_min, x_max).to_be_bytes());
+        data.extend(encode(p.y, y_min, y_max).to_be_bytes());
+    };
+
+    let write_color = |data: &mut Vec<u8>, color: &[f32]| {
+        for (v, range) in color.iter().zip(color_range.chunks_exact(2)) {
+            let normalized = ((v - range[0]) / (range[1] - range[0])).clamp(0.0, 1.0);
+            data.extend(((normalized * u16::MAX as f32).round() as u16).to_be_bytes());
+        }
+    };
+
+    let mut previous: Option<(&MeshPatch, [Vec<f32>; 4])> = None;
+
+    for patch in patches {
+        let colors = components(patch);
+
+        // If the first edge of the patch is the same as one of the other edges of the
+        // previous patch, we can use the corresponding edge flag to only write the
+        // remaining points and colors.
+        let flag = previous
+            .as_ref()
+            .and_then(|(prev, prev_colors)| {
+                (1..4).find(|&i| {
+                    prev.edge(i) == patch.edge(0)
+                        && prev_colors[i] == colors[0]
+                        && prev_colors[(i + 1) % 4] == colors[1]
+                })
+            })
+            .unwrap_or(0);
+
+        data.push(flag as u8);
+
+        let points = (0..4).flat_map(|i| {
+            let edge = patch.edge(i);
+            [edge[0], edge[1], edge[2]]
+        });
+        let skip = if flag == 0 { 0 } else { 4 };
+
+        for point in points.skip(skip) {
+            write_point(&mut data, &point);
+        }
+
+        for color in colors.iter().skip(skip / 2) {
+            write_color(&mut data, color);
+        }
+
+        previous = Some((patch, colors));
+    }
+
+    let mut decode = vec![x_min, x_max, y_min, y_max];
+    decode.extend(color_range);
+
+    let anti_alias = mesh_gradient.anti_alias;
+    let serialize_settings = sc.serialize_settings();
+    let encryptor = sc.encryptor();
+
+    Deferred::new(move || {
+        let stream =
+            FilterStreamBuilder::new_from_binary_data(&data).finish(&serialize_settings, encryptor);
+
+        let mut shading = chunk.stream(root_ref, stream.encoded_data());
+        stream.write_filters(shading.deref_mut());
+        shading.pair(Name(b"ShadingType"), 6);
+        set_colorspace(cs, shading.deref_mut());
+        shading.pair(Name(b"BitsPerCoordinate"), 32);
+        shading.pair(Name(b"BitsPerComponent"), 16);
+        shading.pair(Name(b"BitsPerFlag"), 8);
+        shading.insert(Name(b"Decode")).array().items(decode);
+        shading.pair(Name(b"AntiAlias"), anti_alias);
+        shading.finish();
+
+        chunk
+    })
+}
+
 fn serialize_axial_radial_shading(
     sc: &mut SerializeContext,
     chunk: &mut Chunk,
@@ -310,6 +568,15 @@ fn serialize_axial_radial_shading(
     shading.anti_alias(radial_axial_gradient.anti_alias);
     shading.function(function_ref);
     shading.coords(radial_axial_gradient.coords.iter().copied());
+
+    if radial_axial_gradient.spread_method != SpreadMethod::Pad {
+        // pdf-writer only supports the four-element domain of function-based shadings.
+        shading
+            .insert(Name(b"Domain"))
+            .array()
+            .items(radial_axial_gradient.domain);
+    }
+
     shading.extend([true, true]);
     shading.finish();
 }
@@ -340,11 +607,12 @@ fn select_axial_radial_function(
         }
     }
 
-    if stops.len() == 2 {
+    let function = if stops.len() == 2 {
         if use_opacities {
             serialize_exponential(
                 vec![stops[0].opacity.get()],
                 vec![stops[1].opacity.get()],
+                vec![0.0, 1.0],
                 chunk,
                 sc,
             )
@@ -360,13 +628,80 @@ fn select_axial_radial_function(
                     .to_pdf_color()
                     .into_iter()
                     .collect::<Vec<_>>(),
+                stops[0].color.range(),
                 chunk,
                 sc,
             )
         }
     } else {
         serialize_stitching(&stops, chunk, sc, use_opacities)
+    };
+
+    if properties.spread_method == SpreadMethod::Pad {
+        function
+    } else {
+        let range = if use_opacities {
+            vec![0.0, 1.0]
+        } else {
+            stops[0].color.range()
+        };
+
+        serialize_spread_stitching(
+            function,
+            properties.domain,
+            properties.spread_method,
+            range,
+            chunk,
+            sc,
+        )
+    }
+}
+
+/// Repeat or reflect a function with the domain `[0, 1]` across the given domain.
+fn serialize_spread_stitching(
+    function: Ref,
+    domain: [f32; 2],
+    spread_method: SpreadMethod,
+    range: Vec<f32>,
+    chunk: &mut Chunk,
+    sc: &mut SerializeContext,
+) -> Ref {
+    let root_ref = sc.new_ref();
+    let [t0, t1] = domain;
+
+    let mut functions = vec![];
+    let mut bounds = vec![];
+    let mut encode = vec![];
+
+    let mut i = t0.floor();
+
+    while i < t1 {
+        let start = t0.max(i) - i;
+        let end = t1.min(i + 1.0) - i;
+
+        if spread_method == SpreadMethod::Reflect && (i as i32).rem_euclid(2) == 1 {
+            encode.extend([1.0 - start, 1.0 - end]);
+        } else {
+            encode.extend([start, end]);
+        }
+
+        functions.push(function);
+        i += 1.0;
+
+        if i < t1 {
+            bounds.push(i);
+        }
     }
+
+    let mut stitching_function = chunk.stitching_function(root_ref);
+    stitching_function.domain(domain);
+    stitching_function.range(range);
+    stitching_function.functions(functions);
+    stitching_function.bounds(bounds);
+    stitching_function.encode(encode);
+    stitching_function.finish();
+
+    root_ref
 }
 
 fn select_postscript_function(
@@ -461,6 +796,7 @@ fn serialize_sweep_postscript(
 
     let encoded = PostScriptOp::encode(&code);
     sc.register_limits(encoded.limits());
+    let encoded = encrypt_bytes(&encoded, sc.encryptor());
     let mut postscript_function = chunk.post_script_function(root_ref, &encoded);
     postscript_function.domain([
         properties.domain.left(),
@@ -472,7 +808,7 @@ fn serialize_sweep_postscript(
     if use_opacities {
         postscript_function.range([0.0, 1.0]);
     } else {
-        postscript_function.range([0.0, 1.0, 0.0, 1.0, 0.0, 1.0]);
+        postscript_function.range(properties.stops[0].color.range());
     }
 
     root_ref
@@ -524,6 +860,7 @@ fn serialize_linear_postscript(
 
     let encoded = PostScriptOp::encode(&code);
     sc.register_limits(encoded.limits());
+    let encoded = encrypt_bytes(&encoded, sc.encryptor());
     let mut postscript_function = chunk.post_script_function(root_ref, &encoded);
     postscript_function.domain([
         properties.domain.left(),
@@ -535,7 +872,7 @@ fn serialize_linear_postscript(
     if use_opacities {
         postscript_function.range([0.0, 1.0]);
     } else {
-        postscript_function.range([0.0, 1.0, 0.0, 1.0, 0.0, 1.0]);
+        postscript_function.range(properties.stops[0].color.range());
     }
 
     root_ref
@@ -774,7 +1111,11 @@ fn serialize_stitching(
     let mut functions = vec![];
     let mut bounds = vec![];
     let mut encode = vec![];
-    let mut count = 0;
+    let range = if use_opacities {
+        vec![0.0, 1.0]
+    } else {
+        stops[0].color.range()
+    };
 
     for window in stops.windows(2) {
         let (first, second) = (&window[0], &window[1]);
@@ -789,9 +1130,7 @@ fn serialize_stitching(
             )
         };
 
-        count = c0_components.len();
-
-        let exp_ref = serialize_exponential(c0_components, c1_components, chunk, sc);
+        let exp_ref = serialize_exponential(c0_components, c1_components, range.clone(), chunk, sc);
 
         functions.push(exp_ref);
         encode.extend([0.0, 1.0]);
@@ -800,7 +1139,7 @@ fn serialize_stitching(
     bounds.pop();
     let mut stitching_function = chunk.stitching_function(root_ref);
     stitching_function.domain([0.0, 1.0]);
-    stitching_function.range([0.0, 1.0].repeat(count));
+    stitching_function.range(range);
     stitching_function.functions(functions);
     stitching_function.bounds(bounds);
     stitching_function.encode(encode);
@@ -811,6 +1150,7 @@ fn serialize_stitching(
 fn serialize_exponential(
     c0: Vec<f32>,
     c1: Vec<f32>,
+    range: Vec<f32>,
     chunk: &mut Chunk,
     sc: &mut SerializeContext,
 ) -> Ref {
@@ -820,11 +1160,10 @@ fn serialize_exponential(
         c1.len(),
         "cannot create gradient with stops from different color spaces"
     );
-    let num_components = c0.len();
 
     let mut exp = chunk.exponential_function(root_ref);
 
-    exp.range([0.0, 1.0].repeat(num_components));
+    exp.range(range);
     exp.c0(c0);
     exp.c1(c1);
     exp.domain([0.0, 1.0]);
@@ -0,0 +1,48 @@
+//! Reusable page content templates.
+//!
+//! Templates allow you to record drawing commands once and then draw them onto
+//! many different pages, while only writing them to the PDF a single time. This
+//! is useful for content that is repeated on each page, such as headers with a logo.
+
+use crate::geom::{Rect, Size};
+use crate::graphics::xobject::XObject;
+use crate::stream::Stream;
+
+/// A reusable template of page content.
+///
+/// You can create a template by calling [`Document::create_template`], and
+/// draw it with [`Surface::draw_template`]. All resources that are used
+/// inside of the template, such as fonts or images, are shared with the rest of
+/// the document.
+///
+/// Since the content of a template is shared between different pages, it cannot
+/// be part of the tag tree. Any tags that are started on the surface of a template
+/// are therefore ignored, and on tagged pages the template is drawn as an artifact,
+/// unless it is drawn inside of an already started tag.
+///
+/// IMPORTANT: Note that you must only use a template in the document that you created it with!
+/// If you use it in a different document, you will end up with an invalid PDF file.
+///
+/// [`Document::create_template`]: crate::Document::create_template
+/// [`Surface::draw_template`]: crate::surface::Surface::draw_template
+#[derive(PartialEq, Eq, Debug, Hash, Clone)]
+pub struct Template {
+    /// The stream of the template.
+    pub(crate) x_object: XObject,
+}
+
+impl Template {
+    pub(crate) fn new(stream: Stream, size: Size) -> Self {
+        let bbox = Rect::from_xywh(0.0, 0.0, size.width(), size.height()).unwrap();
+
+        Self {
+            x_object: XObject::new(stream, false, false, Some(bbox)),
+        }
+    }
+
+    /// The size of the template.
+    pub fn size(&self) -> Size {
+        let bbox = self.x_object.bbox();
+        Size::from_wh(bbox.width(), bbox.height()).unwrap()
+    }
+}
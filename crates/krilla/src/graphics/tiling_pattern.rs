@@ -4,10 +4,12 @@ use std::hash::{Hash, Hasher};
 use std::ops::DerefMut;
 
 use pdf_writer::types::{PaintType, TilingType};
-use pdf_writer::{Chunk, Finish, Ref};
+use pdf_writer::{Chunk, Finish, Name, Ref};
 
 use crate::chunk_container::ChunkContainerFn;
 use crate::geom::Transform;
+use crate::graphics::color::{write_color_space_item, ColorSpace};
+use crate::graphics::paint;
 use crate::num::NormalizedF32;
 use crate::resource;
 use crate::resource::Resourceable;
@@ -23,6 +25,9 @@ pub(crate) struct TilingPattern {
     base_opacity: NormalizedF32,
     width: f32,
     height: f32,
+    x_step: f32,
+    y_step: f32,
+    uncolored: bool,
 }
 
 impl Eq for TilingPattern {}
@@ -34,18 +39,20 @@ impl Hash for TilingPattern {
         self.base_opacity.hash(state);
         self.width.to_bits().hash(state);
         self.height.to_bits().hash(state);
+        self.x_step.to_bits().hash(state);
+        self.y_step.to_bits().hash(state);
+        self.uncolored.hash(state);
     }
 }
 
 impl TilingPattern {
     pub(crate) fn new(
-        stream: Stream,
-        transform: Transform,
+        pattern: paint::TilingPattern,
         base_opacity: NormalizedF32,
-        width: f32,
-        height: f32,
         serializer_context: &mut SerializeContext,
     ) -> Self {
+        let stream = pattern.stream;
+
         // stroke/fill opacity doesn't work consistently across different viewers for patterns,
         // so instead we simulate it ourselves.
         let pattern_stream = if base_opacity == NormalizedF32::ONE {
@@ -64,10 +71,13 @@ impl TilingPattern {
 
         Self {
             stream: pattern_stream,
-            transform,
+            transform: pattern.transform,
             base_opacity,
-            width,
-            height,
+            width: pattern.width,
+            height: pattern.height,
+            x_step: pattern.x_step,
+            y_step: pattern.y_step,
+            uncolored: pattern.color.is_some(),
         }
     }
 }
@@ -88,7 +98,7 @@ impl Cacheable for TilingPattern {
             &self.stream.content,
             &sc.serialize_settings(),
         )
-        .finish(&sc.serialize_settings());
+        .finish(&sc.serialize_settings(), sc.encryptor());
         let mut tiling_pattern = chunk.tiling_pattern(root_ref, pattern_stream.encoded_data());
         pattern_stream.write_filters(tiling_pattern.deref_mut().deref_mut());
 
@@ -98,13 +108,19 @@ impl Cacheable for TilingPattern {
 
         let final_bbox = pdf_writer::Rect::new(0.0, 0.0, self.width, self.height);
 
+        let paint_type = if self.uncolored {
+            PaintType::Uncolored
+        } else {
+            PaintType::Colored
+        };
+
         tiling_pattern
             .tiling_type(TilingType::ConstantSpacing)
-            .paint_type(PaintType::Colored)
+            .paint_type(paint_type)
             .bbox(final_bbox)
             .matrix(self.transform.to_pdf_transform())
-            .x_step(final_bbox.x2 - final_bbox.x1)
-            .y_step(final_bbox.y2 - final_bbox.y1);
+            .x_step(self.x_step)
+            .y_step(self.y_step);
 
         tiling_pattern.finish();
 
@@ -115,3 +131,30 @@ impl Cacheable for TilingPattern {
 impl Resourceable for TilingPattern {
     type Resource = resource::Pattern;
 }
+
+/// The color space of an uncolored tiling pattern, which determines the color space
+/// of the color the pattern is painted with.
+#[derive(Debug, Hash, Eq, PartialEq, Clone)]
+pub(crate) struct UncoloredPatternColorSpace(pub(crate) ColorSpace);
+
+impl Cacheable for UncoloredPatternColorSpace {
+    fn chunk_container(&self) -> ChunkContainerFn {
+        |cc| &mut cc.color_spaces
+    }
+
+    fn serialize(self, sc: &mut SerializeContext, root_ref: Ref) -> Deferred<Chunk> {
+        let base = sc.register_colorspace(self.0);
+
+        let mut chunk = Chunk::new();
+        let mut array = chunk.indirect(root_ref).array();
+        array.item(Name(b"Pattern"));
+        write_color_space_item(&mut array, base);
+        array.finish();
+
+        Deferred::new(|| chunk)
+    }
+}
+
+impl Resourceable for UncoloredPatternColorSpace {
+    type Resource = resource::ColorSpace;
+}
@@ -6,7 +6,8 @@ use pdf_writer::{Chunk, Finish, Name, Ref};
 use crate::chunk_container::ChunkContainerFn;
 use crate::configure::ValidationError;
 use crate::geom::Rect;
-use crate::graphics::color::{rgb, DEVICE_RGB};
+use crate::graphics::blend::GroupColorSpace;
+use crate::graphics::color::{cmyk, luma, rgb, ColorSpace, DEVICE_CMYK, DEVICE_GRAY, DEVICE_RGB};
 use crate::resource;
 use crate::resource::{Resource, Resourceable};
 use crate::serialize::{Cacheable, MaybeDeviceColorSpace, SerializeContext};
@@ -17,7 +18,8 @@ use crate::util::{Deferred, NameExt, Prehashed};
 struct Repr {
     stream: Stream,
     isolated: bool,
-    transparency_group_color_space: bool,
+    knockout: bool,
+    group_color_space: Option<GroupColorSpace>,
     custom_bbox: Option<Rect>,
 }
 
@@ -34,11 +36,29 @@ impl XObject {
         Self(Arc::new(Prehashed::new(Repr {
             stream,
             isolated,
-            transparency_group_color_space,
+            knockout: false,
+            group_color_space: transparency_group_color_space.then_some(GroupColorSpace::Srgb),
             custom_bbox,
         })))
     }
 
+    /// Create a new XObject that is drawn as a transparency group with the given
+    /// properties.
+    pub(crate) fn new_transparency_group(
+        stream: Stream,
+        isolated: bool,
+        knockout: bool,
+        color_space: GroupColorSpace,
+    ) -> Self {
+        Self(Arc::new(Prehashed::new(Repr {
+            stream,
+            isolated,
+            knockout,
+            group_color_space: Some(color_space),
+            custom_bbox: None,
+        })))
+    }
+
     pub(crate) fn is_empty(&self) -> bool {
         self.0.stream.is_empty()
     }
@@ -60,24 +80,37 @@ impl Cacheable for XObject {
             sc.register_validation_error(validation_error.clone());
         }
 
-        if self.0.isolated || self.0.transparency_group_color_space {
+        let is_group = self.0.isolated || self.0.knockout || self.0.group_color_space.is_some();
+
+        if is_group {
             sc.register_validation_error(ValidationError::Transparency(sc.location));
         }
 
         let serialize_settings = sc.serialize_settings();
+        let encryptor = sc.encryptor();
+
+        let transparency_group_cs = self.0.group_color_space.map(|cs| {
+            let color_space = match cs {
+                GroupColorSpace::Srgb => rgb::color_space(serialize_settings.no_device_cs),
+                GroupColorSpace::Luma => luma::color_space(serialize_settings.no_device_cs),
+                GroupColorSpace::Cmyk => match cmyk::color_space(&serialize_settings) {
+                    Some(cs) => cs,
+                    None => {
+                        sc.register_validation_error(ValidationError::MissingCMYKProfile);
+                        ColorSpace::DeviceCmyk
+                    }
+                },
+            };
 
-        let transparency_group_cs = if self.0.transparency_group_color_space {
-            Some(sc.register_colorspace(rgb::color_space(serialize_settings.no_device_cs)))
-        } else {
-            None
-        };
+            sc.register_colorspace(color_space)
+        });
 
         Deferred::new(move || {
             let x_object_stream = FilterStreamBuilder::new_from_content_stream(
                 &self.0.stream.content,
                 &serialize_settings,
             )
-            .finish(&serialize_settings);
+            .finish(&serialize_settings, encryptor);
             let mut x_object = chunk.form_xobject(root_ref, x_object_stream.encoded_data());
             x_object_stream.write_filters(x_object.deref_mut().deref_mut());
 
@@ -92,7 +125,7 @@ impl Cacheable for XObject {
                     .to_pdf_rect(),
             );
 
-            if self.0.isolated || self.0.transparency_group_color_space {
+            if is_group {
                 let mut group = x_object.group();
                 let transparency = group.transparency();
 
@@ -100,6 +133,10 @@ impl Cacheable for XObject {
                     transparency.isolated(self.0.isolated);
                 }
 
+                if self.0.knockout {
+                    transparency.knockout(self.0.knockout);
+                }
+
                 if let Some(transparency_group_cs) = transparency_group_cs {
                     let pdf_cs = transparency.insert(Name(b"CS"));
 
@@ -107,9 +144,13 @@ impl Cacheable for XObject {
                         MaybeDeviceColorSpace::DeviceRgb => {
                             pdf_cs.primitive(DEVICE_RGB.to_pdf_name())
                         }
-                        // Can only be SRGB
+                        MaybeDeviceColorSpace::DeviceGray => {
+                            pdf_cs.primitive(DEVICE_GRAY.to_pdf_name())
+                        }
+                        MaybeDeviceColorSpace::DeviceCMYK => {
+                            pdf_cs.primitive(DEVICE_CMYK.to_pdf_name())
+                        }
                         MaybeDeviceColorSpace::ColorSpace(cs) => pdf_cs.primitive(cs.get_ref()),
-                        _ => unreachable!(),
                     }
                 }
 
@@ -3,43 +3,142 @@
 //! PDF has the concept of "actions", which encompass things like navigating to a URL,
 //! opening some file on the system, and so on. The PDF reference defines a whole bunch
 //! of actions, but krilla does not expose nearly all of them, and never will. As of right now,
-//! the only available action is the link action, which allows you to specify a link that
-//! should be opened, when activating the action.
+//! the available actions are the link action, which allows you to specify a link that
+//! should be opened, when activating the action, the go-to action for jumping to a destination
+//! in the document, the remote go-to action for jumping to a destination in another PDF file,
+//! the launch action for opening arbitrary files, named actions for navigating between pages,
+//! JavaScript actions, as well as actions for submitting and resetting [forms]. Multiple actions
+//! can be chained with [`Action::then`], so that they are performed one after the other.
+//!
+//! [forms]: crate::interactive::form
+
+use std::ops::DerefMut;
 
 use pdf_writer::types::ActionType;
-use pdf_writer::{Name, Str};
+use pdf_writer::{Chunk, Finish, Name, Ref};
 
+use crate::chunk_container::ChunkContainerFn;
+use crate::configure::{PdfVersion, ValidationError};
 use crate::error::KrillaResult;
+use crate::interactive::annotation::Target;
 use crate::interactive::destination::Destination;
-use crate::serialize::SerializeContext;
+use crate::serialize::{Cacheable, SerializeContext};
+use crate::stream::FilterStreamBuilder;
+use crate::util::Deferred;
 
 /// A type of action.
+#[derive(Debug, Clone)]
 pub enum Action {
     /// A link action.
     Link(LinkAction),
     /// A go-to action.
     Goto(Destination),
+    /// A submit-form action.
+    SubmitForm(SubmitFormAction),
+    /// A reset-form action.
+    ResetForm(ResetFormAction),
+    /// A remote go-to action.
+    RemoteGoto(RemoteGoToAction),
+    /// A launch action.
+    Launch(LaunchAction),
+    /// A named action.
+    Named(NamedAction),
+    /// A JavaScript action.
+    JavaScript(JavaScriptAction),
+    /// A chain of actions, created with [`Action::then`].
+    Chain(ActionChain),
 }
 
 impl Action {
+    /// Perform another action after this one.
+    ///
+    /// Chains are always kept flat, so chaining an action to the result of another
+    /// chain just appends it to the list of actions.
+    pub fn then(self, next: impl Into<Action>) -> Action {
+        let mut actions = self.into_actions();
+        actions.extend(next.into().into_actions());
+        Action::Chain(ActionChain { actions })
+    }
+
+    fn into_actions(self) -> Vec<Action> {
+        match self {
+            Action::Chain(chain) => chain.actions,
+            action => vec![action],
+        }
+    }
+
     pub(crate) fn serialize(
         &self,
         sc: &mut SerializeContext,
         mut action: pdf_writer::writers::Action,
+    ) -> KrillaResult<()> {
+        let Action::Chain(chain) = self else {
+            return self.serialize_single(sc, action);
+        };
+
+        // Since chains are flat, the follow-up actions don't have a `Next` entry of their
+        // own, so we don't need to recurse any further, regardless of the length of the chain.
+        match &chain.actions[1..] {
+            [] => {}
+            [next] => next.serialize_single(
+                sc,
+                action
+                    .insert(Name(b"Next"))
+                    .start::<pdf_writer::writers::Action>(),
+            )?,
+            next => {
+                let mut array = action.insert(Name(b"Next")).array();
+                for next in next {
+                    next.serialize_single(sc, array.push().start::<pdf_writer::writers::Action>())?;
+                }
+            }
+        }
+
+        chain.actions[0].serialize_single(sc, action)
+    }
+
+    fn serialize_single(
+        &self,
+        sc: &mut SerializeContext,
+        mut action: pdf_writer::writers::Action,
     ) -> KrillaResult<()> {
         match self {
-            Action::Link(link) => link.serialize(action),
+            Action::Link(link) => link.serialize(sc, action),
             Action::Goto(dest) => {
                 let dest_entry = action.action_type(ActionType::GoTo).insert(Name(b"D"));
-                dest.serialize(sc, dest_entry)?
+                dest.serialize(sc, dest_entry)?;
+
+                if let Destination::Structure(structure) = dest {
+                    if structure.is_written(sc) {
+                        let ref_ = sc.register_structure_destination(structure.clone());
+                        action.pair(Name(b"SD"), ref_);
+                    }
+                }
             }
+            Action::SubmitForm(submit) => submit.serialize(sc, action),
+            Action::ResetForm(reset) => reset.serialize(sc, action),
+            Action::RemoteGoto(remote) => remote.serialize(sc, action),
+            Action::Launch(launch) => launch.serialize(sc, action),
+            Action::Named(named) => named.serialize(action),
+            Action::JavaScript(js) => js.serialize(sc, action),
+            Action::Chain(_) => unreachable!("chains never contain other chains"),
         };
 
         Ok(())
     }
 }
 
+/// A chain of actions that are performed one after the other.
+///
+/// Chains can be created with [`Action::then`]. They always contain at least two
+/// actions, none of which is a chain itself.
+#[derive(Debug, Clone)]
+pub struct ActionChain {
+    actions: Vec<Action>,
+}
+
 /// A link action. Will open a link when clicked.
+#[derive(Debug, Clone)]
 pub struct LinkAction {
     uri: String,
 }
@@ -58,9 +157,474 @@ impl LinkAction {
 }
 
 impl LinkAction {
-    fn serialize(&self, mut action: pdf_writer::writers::Action) {
+    fn serialize(&self, sc: &mut SerializeContext, mut action: pdf_writer::writers::Action) {
         action
             .action_type(ActionType::Uri)
-            .uri(Str(self.uri.as_bytes()));
+            .pair(Name(b"URI"), sc.str(self.uri.as_bytes()));
     }
 }
+
+/// A selection of the fields of a form.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum FieldSelection {
+    /// All fields of the form.
+    #[default]
+    All,
+    /// Only the fields with the given names.
+    Include(Vec<String>),
+    /// All fields except for the ones with the given names.
+    Exclude(Vec<String>),
+}
+
+impl FieldSelection {
+    /// Write the `Fields` entry of the action and return the flags that need to be set.
+    fn serialize(
+        &self,
+        sc: &mut SerializeContext,
+        action: &mut pdf_writer::writers::Action,
+    ) -> i32 {
+        let (names, flags) = match self {
+            FieldSelection::All => return 0,
+            FieldSelection::Include(names) => (names, 0),
+            // Include/Exclude
+            FieldSelection::Exclude(names) => (names, 1),
+        };
+
+        action
+            .insert(Name(b"Fields"))
+            .array()
+            .items(names.iter().map(|n| sc.text_str(n)));
+
+        flags
+    }
+}
+
+/// The format in which the data of a form is submitted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SubmitFormat {
+    /// Forms Data Format.
+    #[default]
+    Fdf,
+    /// HTML form format, as used by the `application/x-www-form-urlencoded` content type.
+    Html,
+}
+
+/// A submit-form action. Will send the values of the fields of the form to a URL when
+/// clicked.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SubmitFormAction {
+    url: String,
+    format: SubmitFormat,
+    fields: FieldSelection,
+}
+
+impl From<SubmitFormAction> for Action {
+    fn from(value: SubmitFormAction) -> Self {
+        Action::SubmitForm(value)
+    }
+}
+
+impl SubmitFormAction {
+    /// Create a new submit-form action that will submit the given fields of the form to a
+    /// URL in the given format.
+    pub fn new(url: String, format: SubmitFormat, fields: FieldSelection) -> Self {
+        Self {
+            url,
+            format,
+            fields,
+        }
+    }
+}
+
+impl SubmitFormAction {
+    fn serialize(&self, sc: &mut SerializeContext, mut action: pdf_writer::writers::Action) {
+        action.action_type(ActionType::SubmitForm);
+
+        let mut file_spec = action.insert(Name(b"F")).dict();
+        file_spec.pair(Name(b"FS"), Name(b"URL"));
+        file_spec.pair(Name(b"F"), sc.str(self.url.as_bytes()));
+        file_spec.finish();
+
+        let mut flags = self.fields.serialize(sc, &mut action);
+
+        if self.format == SubmitFormat::Html {
+            // ExportFormat
+            flags |= 1 << 2;
+        }
+
+        action.pair(Name(b"Flags"), flags);
+    }
+}
+
+/// A reset-form action. Will reset the fields of the form to their default values
+/// when clicked.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResetFormAction {
+    fields: FieldSelection,
+}
+
+impl From<ResetFormAction> for Action {
+    fn from(value: ResetFormAction) -> Self {
+        Action::ResetForm(value)
+    }
+}
+
+impl ResetFormAction {
+    /// Create a new reset-form action that will reset the given fields of the form.
+    pub fn new(fields: FieldSelection) -> Self {
+        Self { fields }
+    }
+}
+
+impl ResetFormAction {
+    fn serialize(&self, sc: &mut SerializeContext, mut action: pdf_writer::writers::Action) {
+        action.action_type(ActionType::ResetForm);
+        let flags = self.fields.serialize(sc, &mut action);
+        action.pair(Name(b"Flags"), flags);
+    }
+}
+
+/// Write a file specification for a file on the system. Paths that are not pure ASCII
+/// are additionally written as a text string, so that viewers can decode them properly.
+fn write_file_spec(
+    sc: &mut SerializeContext,
+    action: &mut pdf_writer::writers::Action,
+    path: &str,
+) {
+    let mut file_spec = action
+        .insert(Name(b"F"))
+        .start::<pdf_writer::writers::FileSpec>();
+    file_spec.pair(Name(b"F"), sc.str(path.as_bytes()));
+
+    if !path.is_ascii() && sc.serialize_settings().pdf_version() >= PdfVersion::Pdf17 {
+        file_spec.pair(Name(b"UF"), sc.text_str(path));
+    }
+
+    file_spec.finish();
+}
+
+/// A destination in another PDF file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RemoteDestination {
+    /// The page with the given index, starting from zero. The page will be
+    /// fitted into the window.
+    Page(usize),
+    /// A named destination of the file.
+    Named(String),
+}
+
+/// A remote go-to action. Will open another PDF file at a specific destination
+/// when clicked.
+#[derive(Debug, Clone)]
+pub struct RemoteGoToAction {
+    file: String,
+    destination: RemoteDestination,
+    new_window: bool,
+}
+
+impl From<RemoteGoToAction> for Action {
+    fn from(value: RemoteGoToAction) -> Self {
+        Action::RemoteGoto(value)
+    }
+}
+
+impl RemoteGoToAction {
+    /// Create a new remote go-to action.
+    ///
+    /// `file`: The path of the PDF file, relative to the current document.
+    /// `destination`: The destination in the file that should be jumped to.
+    /// `new_window`: Whether the file should be opened in a new window. If not set,
+    /// the viewer decides whether to replace the current document or not.
+    pub fn new(file: String, destination: RemoteDestination, new_window: bool) -> Self {
+        Self {
+            file,
+            destination,
+            new_window,
+        }
+    }
+}
+
+impl RemoteGoToAction {
+    fn serialize(&self, sc: &mut SerializeContext, mut action: pdf_writer::writers::Action) {
+        action.action_type(ActionType::RemoteGoTo);
+        write_file_spec(sc, &mut action, &self.file);
+
+        match &self.destination {
+            RemoteDestination::Page(index) => {
+                // Destinations in other files refer to pages by their index instead of
+                // by a reference.
+                let mut dest = action.insert(Name(b"D")).array();
+                dest.item(i32::try_from(*index).unwrap_or(i32::MAX));
+                dest.item(Name(b"Fit"));
+                dest.finish();
+            }
+            RemoteDestination::Named(name) => {
+                action.pair(Name(b"D"), sc.str(name.as_bytes()));
+            }
+        }
+
+        if self.new_window {
+            action.pair(Name(b"NewWindow"), true);
+        }
+    }
+}
+
+/// A launch action. Will open an arbitrary file with its associated application
+/// when clicked.
+///
+/// Note that many viewers will ask for confirmation before opening the file, or
+/// refuse to do so altogether.
+#[derive(Debug, Clone)]
+pub struct LaunchAction {
+    file: String,
+    new_window: bool,
+}
+
+impl From<LaunchAction> for Action {
+    fn from(value: LaunchAction) -> Self {
+        Action::Launch(value)
+    }
+}
+
+impl LaunchAction {
+    /// Create a new launch action.
+    ///
+    /// `file`: The path of the file, relative to the current document.
+    /// `new_window`: Whether the file should be opened in a new window, in case it is
+    /// a PDF file. If not set, the viewer decides whether to replace the current document
+    /// or not.
+    pub fn new(file: String, new_window: bool) -> Self {
+        Self { file, new_window }
+    }
+}
+
+impl LaunchAction {
+    fn serialize(&self, sc: &mut SerializeContext, mut action: pdf_writer::writers::Action) {
+        sc.register_validation_error(ValidationError::LaunchAction);
+
+        action.action_type(ActionType::Launch);
+        write_file_spec(sc, &mut action, &self.file);
+
+        if self.new_window {
+            action.pair(Name(b"NewWindow"), true);
+        }
+    }
+}
+
+/// A predefined action for navigating between the pages of the document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NamedAction {
+    /// Go to the next page.
+    NextPage,
+    /// Go to the previous page.
+    PrevPage,
+    /// Go to the first page.
+    FirstPage,
+    /// Go to the last page.
+    LastPage,
+}
+
+impl From<NamedAction> for Action {
+    fn from(value: NamedAction) -> Self {
+        Action::Named(value)
+    }
+}
+
+impl NamedAction {
+    fn serialize(&self, mut action: pdf_writer::writers::Action) {
+        // pdf-writer has no action type for named actions.
+        action.pair(Name(b"S"), Name(b"Named"));
+        action.pair(
+            Name(b"N"),
+            Name(match self {
+                NamedAction::NextPage => b"NextPage",
+                NamedAction::PrevPage => b"PrevPage",
+                NamedAction::FirstPage => b"FirstPage",
+                NamedAction::LastPage => b"LastPage",
+            }),
+        );
+    }
+}
+
+/// A JavaScript action. Will execute a script when clicked.
+///
+/// Note that many viewers don't support JavaScript or disable it by default.
+#[derive(Debug, Clone)]
+pub struct JavaScriptAction {
+    script: String,
+}
+
+impl From<JavaScriptAction> for Action {
+    fn from(value: JavaScriptAction) -> Self {
+        Action::JavaScript(value)
+    }
+}
+
+impl JavaScriptAction {
+    /// Create a new JavaScript action that will execute the given script.
+    pub fn new(script: String) -> Self {
+        Self { script }
+    }
+}
+
+impl JavaScriptAction {
+    /// Scripts longer than this are written as a stream instead of a string.
+    const MAX_STRING_LEN: usize = 256;
+
+    fn serialize(&self, sc: &mut SerializeContext, mut action: pdf_writer::writers::Action) {
+        sc.register_validation_error(ValidationError::JavaScriptAction);
+
+        action.action_type(ActionType::JavaScript);
+
+        if self.script.len() > Self::MAX_STRING_LEN {
+            let stream_ref = sc.register_cacheable(JavaScriptStream(self.script.clone()));
+            action.pair(Name(b"JS"), stream_ref);
+        } else {
+            action.pair(Name(b"JS"), sc.text_str(&self.script));
+        }
+    }
+}
+
+/// The script of a JavaScript action, written as a stream.
+#[derive(Hash)]
+struct JavaScriptStream(String);
+
+impl Cacheable for JavaScriptStream {
+    fn chunk_container(&self) -> ChunkContainerFn {
+        |cc| &mut cc.scripts
+    }
+
+    fn serialize(self, sc: &mut SerializeContext, root_ref: Ref) -> Deferred<Chunk> {
+        let mut chunk = Chunk::new();
+
+        // Like text strings, scripts are either encoded in PDFDocEncoding or in UTF-16BE.
+        let data = if self.0.is_ascii() {
+            self.0.into_bytes()
+        } else {
+            [0xFE, 0xFF]
+                .into_iter()
+                .chain(self.0.encode_utf16().flat_map(|c| c.to_be_bytes()))
+                .collect()
+        };
+
+        let serialize_settings = sc.serialize_settings();
+        let encryptor = sc.encryptor();
+        let stream = FilterStreamBuilder::new_from_content_stream(&data, &serialize_settings)
+            .finish(&serialize_settings, encryptor);
+        let mut script = chunk.stream(root_ref, stream.encoded_data());
+        stream.write_filters(script.deref_mut());
+        script.finish();
+
+        Deferred::new(|| chunk)
+    }
+}
+
+/// Actions that should be performed when certain events happen to the document.
+///
+/// Note that many viewers only support a subset of the events, and that some
+/// standards (e.g. PDF/A) forbid document actions altogether.
+#[derive(Debug, Clone, Default)]
+pub struct DocumentActions {
+    will_close: Option<Action>,
+    will_save: Option<Action>,
+    did_save: Option<Action>,
+    will_print: Option<Action>,
+    did_print: Option<Action>,
+}
+
+impl DocumentActions {
+    /// Create new document actions without any actions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The action that should be performed before the document is closed.
+    pub fn with_will_close(mut self, action: Action) -> Self {
+        self.will_close = Some(action);
+        self
+    }
+
+    /// The action that should be performed before the document is saved.
+    pub fn with_will_save(mut self, action: Action) -> Self {
+        self.will_save = Some(action);
+        self
+    }
+
+    /// The action that should be performed after the document has been saved.
+    pub fn with_did_save(mut self, action: Action) -> Self {
+        self.did_save = Some(action);
+        self
+    }
+
+    /// The action that should be performed before the document is printed.
+    pub fn with_will_print(mut self, action: Action) -> Self {
+        self.will_print = Some(action);
+        self
+    }
+
+    /// The action that should be performed after the document has been printed.
+    pub fn with_did_print(mut self, action: Action) -> Self {
+        self.did_print = Some(action);
+        self
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.will_close.is_none()
+            && self.will_save.is_none()
+            && self.did_save.is_none()
+            && self.will_print.is_none()
+            && self.did_print.is_none()
+    }
+
+    pub(crate) fn serialize(
+        &self,
+        sc: &mut SerializeContext,
+        root_ref: Ref,
+    ) -> KrillaResult<Chunk> {
+        sc.register_validation_error(ValidationError::DocumentActions);
+
+        let mut chunk = Chunk::new();
+        let mut dict = chunk.indirect(root_ref).dict();
+
+        let events = [
+            (Name(b"WC"), &self.will_close),
+            (Name(b"WS"), &self.will_save),
+            (Name(b"DS"), &self.did_save),
+            (Name(b"WP"), &self.will_print),
+            (Name(b"DP"), &self.did_print),
+        ];
+
+        for (name, action) in events {
+            if let Some(action) = action {
+                action.serialize(sc, dict.insert(name).start::<pdf_writer::writers::Action>())?;
+            }
+        }
+
+        dict.finish();
+
+        Ok(chunk)
+    }
+}
+
+/// Serialize the action that should be performed when the document is opened.
+pub(crate) fn serialize_open_action(
+    sc: &mut SerializeContext,
+    target: &Target,
+    root_ref: Ref,
+) -> KrillaResult<Chunk> {
+    let mut chunk = Chunk::new();
+    let action = chunk
+        .indirect(root_ref)
+        .start::<pdf_writer::writers::Action>();
+
+    match target {
+        Target::Action(a) => a.serialize(sc, action)?,
+        // Named destinations can't be used as an open action directly, so we always
+        // wrap destinations in a go-to action.
+        Target::Destination(destination) => {
+            Action::Goto(destination.clone()).serialize(sc, action)?
+        }
+    }
+
+    Ok(chunk)
+}
@@ -2,83 +2,2314 @@
 //!
 //! PDF has the concept of annotations, which allow you to associate certain regions of
 //! a page with an "annotation". The PDF reference defines many different actions, however,
-//! krilla does not and never will expose all of them. As of right now, the only annotations
-//! that are supported are "link annotations", which allow you associate a certain region of
-//! the page with a link.
+//! krilla does not and never will expose all of them. As of right now, the following annotations
+//! are supported:
+//! - Link annotations, which allow you associate a certain region of the page with a link.
+//! - Text markup annotations, which allow you to highlight, underline, strike out or
+//!   squiggly-underline text on the page.
+//! - Text annotations, which allow you to add sticky notes with comments to the page.
+//! - Free text annotations, which allow you to display editable text on the page, for
+//!   example in a callout box.
+//! - Ink annotations, which allow you to add freehand drawings to the page.
+//! - Stamp annotations, which allow you to add movable stamps with a standard or a custom
+//!   appearance to the page.
+//! - Geometric annotations, which allow you to draw squares, circles, lines, polygons and
+//!   polylines on the page.
+//! - File attachment annotations, which allow you to attach embedded files to a region
+//!   of the page.
 
-use pdf_writer::types::AnnotationFlags;
-use pdf_writer::{Chunk, Finish, Name, Ref, TextStr};
+use std::ops::DerefMut;
 
+use pdf_writer::types::{LineCapStyle, LineJoinStyle};
+use pdf_writer::{Chunk, Content, Finish, Name, Ref};
+
+use crate::color::rgb;
 use crate::configure::{PdfVersion, ValidationError};
+use crate::content::ContentBuilder;
 use crate::error::KrillaResult;
-use crate::geom::{Point, Rect};
+use crate::geom::{PathBuilder, Point, Rect, Size, Transform, KAPPA};
+use crate::graphics::blend::BlendMode;
+use crate::graphics::paint::{Fill, Stroke, StrokeDash};
+use crate::graphics::xobject::XObject;
 use crate::interactive::action::Action;
 use crate::interactive::destination::Destination;
+use crate::interchange::embed::EmbeddedFile;
+use crate::interchange::metadata::DateTime;
+use crate::num::NormalizedF32;
 use crate::page::page_root_transform;
-use crate::serialize::SerializeContext;
+use crate::resource::Resource;
+use crate::serialize::{MaybeDeviceColorSpace, SerializeContext};
+use crate::stream::{FilterStreamBuilder, Stream, StreamBuilder};
+use crate::surface::Surface;
+#[cfg(feature = "simple-text")]
+use crate::text::shape::{naive_shape, TextDirection};
+use crate::text::{CIDIdentifier, Font, FontIdentifier, GlyphId, KrillaGlyph, WritingMode};
+
+/// An annotation.
+pub struct Annotation {
+    pub(crate) annotation_type: AnnotationType,
+    pub(crate) alt: Option<String>,
+    pub(crate) struct_parent: Option<i32>,
+    pub(crate) flags: AnnotationFlags,
+    pub(crate) opacity: NormalizedF32,
+    pub(crate) blend_mode: BlendMode,
+}
+
+impl Annotation {
+    /// Create a new annotation of the given type with some alt text.
+    ///
+    /// Note that the alt text might be required in some cases, for example
+    /// when exporting to PDF/UA.
+    pub fn from_type(annotation_type: AnnotationType, alt: Option<String>) -> Self {
+        Self {
+            annotation_type,
+            alt,
+            struct_parent: None,
+            flags: AnnotationFlags::default(),
+            opacity: NormalizedF32::ONE,
+            blend_mode: BlendMode::Normal,
+        }
+    }
+
+    /// Create a new link annotation with some alt text.
+    ///
+    /// Note that the alt text might be required in some cases, for example
+    /// when exporting to PDF/UA.
+    pub fn new_link(annotation: LinkAnnotation, alt_text: Option<String>) -> Self {
+        Self::from_type(AnnotationType::Link(annotation), alt_text)
+    }
+
+    /// Set the flags of the annotation.
+    pub fn with_flags(mut self, flags: AnnotationFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Set the opacity of the annotation. For annotations that have an opacity of
+    /// their own, the two opacities are multiplied.
+    pub fn with_opacity(mut self, opacity: NormalizedF32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    /// Set the blend mode that should be used when compositing the annotation
+    /// with the page.
+    ///
+    /// Note that this is only supported in PDF 2.0, for earlier versions the blend
+    /// mode is ignored.
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+}
+
+/// The flags of an annotation, which control how it is displayed and whether it can be
+/// interacted with.
+///
+/// By default, only the print flag is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AnnotationFlags(u32);
+
+impl AnnotationFlags {
+    // Bit positions as defined in table 165 of the PDF specification,
+    // where the lowest bit is bit 1.
+    const HIDDEN: u32 = 1 << 1;
+    const PRINT: u32 = 1 << 2;
+    const NO_VIEW: u32 = 1 << 5;
+    const READ_ONLY: u32 = 1 << 6;
+    const LOCKED: u32 = 1 << 7;
+
+    /// Create new annotation flags where no flag is set.
+    pub fn empty() -> Self {
+        Self(0)
+    }
+
+    fn set(mut self, flag: u32, enabled: bool) -> Self {
+        if enabled {
+            self.0 |= flag;
+        } else {
+            self.0 &= !flag;
+        }
+
+        self
+    }
+
+    fn has(&self, flag: u32) -> bool {
+        self.0 & flag != 0
+    }
+
+    /// Whether the annotation should be printed when the page is printed.
+    pub fn with_print(self, print: bool) -> Self {
+        self.set(Self::PRINT, print)
+    }
+
+    /// Whether the annotation should neither be displayed nor printed, nor allow
+    /// any interaction.
+    pub fn with_hidden(self, hidden: bool) -> Self {
+        self.set(Self::HIDDEN, hidden)
+    }
+
+    /// Whether the annotation should not be displayed on the screen or allow any
+    /// interaction. It might still be printed if the print flag is set.
+    pub fn with_no_view(self, no_view: bool) -> Self {
+        self.set(Self::NO_VIEW, no_view)
+    }
+
+    /// Whether the annotation should not allow any interaction. It is still
+    /// displayed and printed as usual.
+    pub fn with_read_only(self, read_only: bool) -> Self {
+        self.set(Self::READ_ONLY, read_only)
+    }
+
+    /// Whether the annotation should not be deleted or moved, and whether its
+    /// properties should not be modified. Its contents can still be changed.
+    pub fn with_locked(self, locked: bool) -> Self {
+        self.set(Self::LOCKED, locked)
+    }
+
+    /// Whether the print flag is set.
+    pub fn print(&self) -> bool {
+        self.has(Self::PRINT)
+    }
+
+    /// Whether the hidden flag is set.
+    pub fn hidden(&self) -> bool {
+        self.has(Self::HIDDEN)
+    }
+
+    /// Whether the no-view flag is set.
+    pub fn no_view(&self) -> bool {
+        self.has(Self::NO_VIEW)
+    }
+
+    /// Whether the read-only flag is set.
+    pub fn read_only(&self) -> bool {
+        self.has(Self::READ_ONLY)
+    }
+
+    /// Whether the locked flag is set.
+    pub fn locked(&self) -> bool {
+        self.has(Self::LOCKED)
+    }
+}
+
+impl Default for AnnotationFlags {
+    fn default() -> Self {
+        Self(Self::PRINT)
+    }
+}
+
+impl From<LinkAnnotation> for Annotation {
+    fn from(value: LinkAnnotation) -> Self {
+        Self::from_type(AnnotationType::Link(value), None)
+    }
+}
+
+macro_rules! text_markup_annotation {
+    ($name:ident, $variant:ident, $description:literal) => {
+        #[doc = concat!("A ", $description, " annotation.")]
+        pub struct $name(TextMarkup);
+
+        impl $name {
+            #[doc = concat!("Create a new ", $description, " annotation.")]
+            ///
+            /// `quad_points`: The quadrilaterals of the text that should be marked up, one for
+            /// each line fragment. The points of each quadrilateral need to be in the order top
+            /// left, top right, bottom left, bottom right, relative to the direction of the text.
+            /// `color`: The color of the markup.
+            /// `opacity`: The opacity of the markup.
+            /// `contents`: The text that should be displayed for the annotation, for example
+            /// a review comment. Note that this might be required in some cases, for example
+            /// when exporting to PDF/UA.
+            ///
+            /// Panics if `quad_points` is empty.
+            pub fn new(
+                quad_points: Vec<Quad>,
+                color: rgb::Color,
+                opacity: NormalizedF32,
+                contents: Option<String>,
+            ) -> Self {
+                assert!(
+                    !quad_points.is_empty(),
+                    concat!(
+                        "a ",
+                        $description,
+                        " annotation needs to contain at least one quadrilateral"
+                    )
+                );
+
+                Self(TextMarkup {
+                    kind: TextMarkupKind::$variant,
+                    quad_points,
+                    color,
+                    opacity,
+                    contents,
+                })
+            }
+        }
+
+        impl From<$name> for Annotation {
+            fn from(mut value: $name) -> Self {
+                let alt = value.0.contents.take();
+                Self::from_type(AnnotationType::$variant(value), alt)
+            }
+        }
+    };
+}
+
+text_markup_annotation!(HighlightAnnotation, Highlight, "highlight");
+text_markup_annotation!(UnderlineAnnotation, Underline, "underline");
+text_markup_annotation!(StrikeOutAnnotation, StrikeOut, "strike-out");
+text_markup_annotation!(SquigglyAnnotation, Squiggly, "squiggly-underline");
+
+/// The icon of a [`TextAnnotation`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TextIcon {
+    /// A speech bubble.
+    Comment,
+    /// A key.
+    Key,
+    /// A note.
+    #[default]
+    Note,
+    /// A question mark.
+    Help,
+    /// A new paragraph symbol.
+    NewParagraph,
+    /// A paragraph symbol.
+    Paragraph,
+    /// An insertion caret.
+    Insert,
+}
+
+impl TextIcon {
+    fn to_pdf(self) -> Name<'static> {
+        Name(match self {
+            TextIcon::Comment => b"Comment",
+            TextIcon::Key => b"Key",
+            TextIcon::Note => b"Note",
+            TextIcon::Help => b"Help",
+            TextIcon::NewParagraph => b"NewParagraph",
+            TextIcon::Paragraph => b"Paragraph",
+            TextIcon::Insert => b"Insert",
+        })
+    }
+}
+
+/// A text annotation, also known as a sticky note. It is displayed as an icon on the page,
+/// which opens a popup with its contents when clicked.
+pub struct TextAnnotation {
+    pub(crate) rect: Rect,
+    pub(crate) icon: TextIcon,
+    pub(crate) color: rgb::Color,
+    pub(crate) contents: Option<String>,
+    pub(crate) author: Option<String>,
+    pub(crate) popup_rect: Option<Rect>,
+    pub(crate) creation_date: Option<DateTime>,
+    pub(crate) modification_date: Option<DateTime>,
+}
+
+impl TextAnnotation {
+    /// Create a new text annotation.
+    ///
+    /// `rect`: The region of the icon on the page.
+    /// `icon`: The icon that should be displayed.
+    /// `color`: The color of the icon and of the title bar of the popup.
+    /// `contents`: The text of the note. Note that this might be required in some cases, for
+    /// example when exporting to PDF/UA.
+    pub fn new(rect: Rect, icon: TextIcon, color: rgb::Color, contents: Option<String>) -> Self {
+        Self {
+            rect,
+            icon,
+            color,
+            contents,
+            author: None,
+            popup_rect: None,
+            creation_date: None,
+            modification_date: None,
+        }
+    }
+
+    /// Set the author of the note.
+    pub fn with_author(mut self, author: String) -> Self {
+        self.author = Some(author);
+        self
+    }
+
+    /// Set the region of the popup on the page. By default, the popup will be placed
+    /// next to the icon.
+    pub fn with_popup_rect(mut self, popup_rect: Rect) -> Self {
+        self.popup_rect = Some(popup_rect);
+        self
+    }
+
+    /// Set the date at which the note was created.
+    pub fn with_creation_date(mut self, creation_date: DateTime) -> Self {
+        self.creation_date = Some(creation_date);
+        self
+    }
+
+    /// Set the date at which the note was last modified.
+    pub fn with_modification_date(mut self, modification_date: DateTime) -> Self {
+        self.modification_date = Some(modification_date);
+        self
+    }
+
+    fn serialize_type(
+        &self,
+        sc: &mut SerializeContext,
+        annotation: &mut pdf_writer::writers::Annotation,
+        root_ref: Ref,
+        page_height: f32,
+        extra: &mut Chunk,
+    ) -> Ref {
+        let transform = page_root_transform(page_height);
+        let popup_ref = sc.new_ref();
+
+        annotation.subtype(pdf_writer::types::AnnotationType::Text);
+        // We leave the appearance of the icon up to the viewer.
+        sc.register_validation_error(ValidationError::MissingAnnotationAppearance);
+        annotation.rect(self.rect.transform(transform).unwrap().to_pdf_rect());
+        annotation.pair(Name(b"Name"), self.icon.to_pdf());
+        let color = self.color.to_pdf_color();
+        annotation.color_rgb(color[0], color[1], color[2]);
+        annotation.pair(Name(b"Open"), false);
+        annotation.pair(Name(b"Popup"), popup_ref);
+
+        if let Some(author) = &self.author {
+            annotation.pair(Name(b"T"), sc.text_str(author));
+        }
+
+        if let Some(creation_date) = self.creation_date {
+            annotation.pair(Name(b"CreationDate"), sc.date(creation_date));
+        }
+
+        if let Some(modification_date) = self.modification_date {
+            annotation.pair(Name(b"M"), sc.date(modification_date));
+        }
+
+        let popup_rect = self.popup_rect.unwrap_or_else(|| {
+            Rect::from_xywh(self.rect.right(), self.rect.top(), 180.0, 120.0).unwrap()
+        });
+
+        let mut popup = extra
+            .indirect(popup_ref)
+            .start::<pdf_writer::writers::Annotation>();
+        popup.pair(Name(b"Subtype"), Name(b"Popup"));
+        popup.rect(popup_rect.transform(transform).unwrap().to_pdf_rect());
+        popup.pair(Name(b"Parent"), root_ref);
+        popup.pair(Name(b"Open"), false);
+        popup.finish();
+
+        popup_ref
+    }
+}
+
+impl From<TextAnnotation> for Annotation {
+    fn from(mut value: TextAnnotation) -> Self {
+        let alt = value.contents.take();
+        Self::from_type(AnnotationType::Text(value), alt)
+    }
+}
+
+/// The style of the border of an annotation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BorderStyle {
+    /// The width of the border. A width of zero means that no border is drawn.
+    pub width: f32,
+    /// The (optional) dash array of the border.
+    pub dash: Option<Vec<f32>>,
+}
+
+impl Default for BorderStyle {
+    fn default() -> Self {
+        Self {
+            width: 1.0,
+            dash: None,
+        }
+    }
+}
+
+impl BorderStyle {
+    fn serialize(&self, annotation: &mut pdf_writer::writers::Annotation) {
+        let mut border_style = annotation.insert(Name(b"BS")).dict();
+        border_style.pair(Name(b"W"), self.width);
+        if let Some(dash) = &self.dash {
+            border_style.pair(Name(b"S"), Name(b"D"));
+            border_style
+                .insert(Name(b"D"))
+                .array()
+                .items(dash.iter().copied());
+        } else {
+            border_style.pair(Name(b"S"), Name(b"S"));
+        }
+        border_style.finish();
+    }
+
+    fn stroke(&self, color: rgb::Color) -> Option<Stroke> {
+        (self.width > 0.0).then(|| Stroke {
+            paint: color.into(),
+            width: self.width,
+            dash: self.dash.as_ref().map(|array| StrokeDash {
+                array: array.clone(),
+                offset: 0.0,
+            }),
+            ..Stroke::default()
+        })
+    }
+}
+
+/// The style of the ending of a line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LineEnding {
+    /// No special line ending.
+    #[default]
+    None,
+    /// Two short lines forming an arrowhead.
+    OpenArrow,
+    /// A triangular arrowhead, filled with the interior color.
+    ClosedArrow,
+    /// Two short lines forming an arrowhead in the reverse direction.
+    ROpenArrow,
+    /// A triangular arrowhead in the reverse direction, filled with the interior color.
+    RClosedArrow,
+    /// A square centered on the end of the line, filled with the interior color.
+    Square,
+    /// A circle centered on the end of the line, filled with the interior color.
+    Circle,
+    /// A diamond centered on the end of the line, filled with the interior color.
+    Diamond,
+    /// A short line perpendicular to the line.
+    Butt,
+    /// A short line at an angle of 60 degrees to the line.
+    Slash,
+}
+
+impl LineEnding {
+    fn to_pdf(self) -> Name<'static> {
+        Name(match self {
+            LineEnding::None => b"None",
+            LineEnding::OpenArrow => b"OpenArrow",
+            LineEnding::ClosedArrow => b"ClosedArrow",
+            LineEnding::ROpenArrow => b"ROpenArrow",
+            LineEnding::RClosedArrow => b"RClosedArrow",
+            LineEnding::Square => b"Square",
+            LineEnding::Circle => b"Circle",
+            LineEnding::Diamond => b"Diamond",
+            LineEnding::Butt => b"Butt",
+            LineEnding::Slash => b"Slash",
+        })
+    }
+
+    /// Whether the ending is a closed shape, which is filled with the interior color.
+    fn is_closed(self) -> bool {
+        matches!(
+            self,
+            LineEnding::ClosedArrow
+                | LineEnding::RClosedArrow
+                | LineEnding::Square
+                | LineEnding::Circle
+                | LineEnding::Diamond
+        )
+    }
+
+    /// The distance by which the ending extends beyond the end of a line with the given width.
+    fn extent(self, line_width: f32) -> f32 {
+        match self {
+            LineEnding::None => line_width / 2.0,
+            // None of the shapes extend further than the sides of an arrowhead.
+            _ => ending_size(line_width) + line_width,
+        }
+    }
+
+    /// Add the path of the ending at `tip` to `sink`, for a line that arrives at `tip`
+    /// coming from `from`. Returns whether anything was added.
+    fn add_path(
+        self,
+        sink: &mut impl PathSink,
+        from: (f32, f32),
+        tip: (f32, f32),
+        line_width: f32,
+    ) -> bool {
+        let (dx, dy) = (tip.0 - from.0, tip.1 - from.1);
+        let length = (dx * dx + dy * dy).sqrt();
+
+        if self == LineEnding::None || length == 0.0 {
+            return false;
+        }
+
+        // The unit vector along the line and the one perpendicular to it.
+        let (ux, uy) = (dx / length, dy / length);
+        let (nx, ny) = (-uy, ux);
+        let size = ending_size(line_width);
+        let half = size / 2.0;
+        // A point relative to the tip, given in terms of the two unit vectors.
+        let at = |along: f32, across: f32| {
+            (
+                tip.0 + along * ux + across * nx,
+                tip.1 + along * uy + across * ny,
+            )
+        };
+        let polyline = |sink: &mut dyn PathSink, points: &[(f32, f32)], closed: bool| {
+            sink.move_to(points[0].0, points[0].1);
+            for p in &points[1..] {
+                sink.line_to(p.0, p.1);
+            }
+            if closed {
+                sink.close();
+            }
+        };
+
+        let (sin, cos) = ARROW_HALF_ANGLE.sin_cos();
+
+        match self {
+            LineEnding::None => unreachable!(),
+            LineEnding::OpenArrow | LineEnding::ClosedArrow => {
+                let points = [
+                    at(-size * cos, size * sin),
+                    tip,
+                    at(-size * cos, -size * sin),
+                ];
+                polyline(sink, &points, self.is_closed());
+            }
+            LineEnding::ROpenArrow | LineEnding::RClosedArrow => {
+                let points = [at(size * cos, size * sin), tip, at(size * cos, -size * sin)];
+                polyline(sink, &points, self.is_closed());
+            }
+            LineEnding::Square => {
+                let points = [
+                    at(-half, -half),
+                    at(half, -half),
+                    at(half, half),
+                    at(-half, half),
+                ];
+                polyline(sink, &points, true);
+            }
+            LineEnding::Diamond => {
+                let points = [at(-half, 0.0), at(0.0, -half), at(half, 0.0), at(0.0, half)];
+                polyline(sink, &points, true);
+            }
+            LineEnding::Circle => add_ellipse(sink, tip, half, half),
+            LineEnding::Butt => polyline(sink, &[at(0.0, -half), at(0.0, half)], false),
+            LineEnding::Slash => {
+                // Rotate the perpendicular line by 30 degrees.
+                let (sin, cos) = (std::f32::consts::PI / 6.0).sin_cos();
+                let points = [at(-half * sin, -half * cos), at(half * sin, half * cos)];
+                polyline(sink, &points, false);
+            }
+        }
+
+        true
+    }
+}
+
+/// Half of the opening angle of arrowheads, in radians.
+const ARROW_HALF_ANGLE: f32 = std::f32::consts::PI / 6.0;
+
+/// The size of line endings for a line with the given width. For arrowheads,
+/// this is the length of their sides.
+fn ending_size(line_width: f32) -> f32 {
+    (line_width * 6.0).max(4.0)
+}
+
+/// Something that paths can be drawn into, so that we can use the same geometry
+/// for drawing with a surface and for writing content streams directly.
+trait PathSink {
+    fn move_to(&mut self, x: f32, y: f32);
+    fn line_to(&mut self, x: f32, y: f32);
+    fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32);
+    fn close(&mut self);
+}
+
+impl PathSink for Content {
+    fn move_to(&mut self, x: f32, y: f32) {
+        Content::move_to(self, x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        Content::line_to(self, x, y);
+    }
+
+    fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        Content::cubic_to(self, x1, y1, x2, y2, x, y);
+    }
+
+    fn close(&mut self) {
+        self.close_path();
+    }
+}
+
+impl PathSink for PathBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        PathBuilder::move_to(self, x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        PathBuilder::line_to(self, x, y);
+    }
+
+    fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        PathBuilder::cubic_to(self, x1, y1, x2, y2, x, y);
+    }
+
+    fn close(&mut self) {
+        PathBuilder::close(self);
+    }
+}
+
+/// Add an ellipse with the given center and radii to `sink`.
+fn add_ellipse(sink: &mut (impl PathSink + ?Sized), center: (f32, f32), rx: f32, ry: f32) {
+    let (cx, cy) = center;
+    let (kx, ky) = (KAPPA * rx, KAPPA * ry);
+
+    sink.move_to(cx + rx, cy);
+    sink.cubic_to(cx + rx, cy + ky, cx + kx, cy + ry, cx, cy + ry);
+    sink.cubic_to(cx - kx, cy + ry, cx - rx, cy + ky, cx - rx, cy);
+    sink.cubic_to(cx - rx, cy - ky, cx - kx, cy - ry, cx, cy - ry);
+    sink.cubic_to(cx + kx, cy - ry, cx + rx, cy - ky, cx + rx, cy);
+    sink.close();
+}
+
+/// The justification of text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Justification {
+    /// Left-justified text.
+    #[default]
+    Left,
+    /// Centered text.
+    Center,
+    /// Right-justified text.
+    Right,
+}
+
+impl Justification {
+    fn to_pdf(self) -> i32 {
+        match self {
+            Justification::Left => 0,
+            Justification::Center => 1,
+            Justification::Right => 2,
+        }
+    }
+}
+
+/// The callout line of a [`FreeTextAnnotation`], which points from the text box to
+/// some region of the page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Callout {
+    pub(crate) start: Point,
+    pub(crate) knee: Option<Point>,
+    pub(crate) end: Point,
+    pub(crate) ending: LineEnding,
+}
+
+impl Callout {
+    /// Create a new callout line.
+    ///
+    /// `start`: The point the callout line points to.
+    /// `knee`: An optional point at which the callout line bends.
+    /// `end`: The point where the callout line touches the text box.
+    /// `ending`: The line ending drawn at `start`.
+    pub fn new(start: Point, knee: Option<Point>, end: Point, ending: LineEnding) -> Self {
+        Self {
+            start,
+            knee,
+            end,
+            ending,
+        }
+    }
+
+    fn points(&self) -> impl Iterator<Item = Point> + '_ {
+        std::iter::once(self.start)
+            .chain(self.knee)
+            .chain(std::iter::once(self.end))
+    }
+}
+
+/// A free text annotation, which displays text directly on the page, optionally with
+/// a callout line. In contrast to text drawn onto the page, it can still be edited
+/// in viewers.
+///
+/// The text is drawn in a single font, and no line breaking will be performed apart
+/// from explicit newlines.
+pub struct FreeTextAnnotation {
+    pub(crate) rect: Rect,
+    pub(crate) text: String,
+    pub(crate) lines: Vec<Vec<KrillaGlyph>>,
+    pub(crate) font: Font,
+    pub(crate) font_size: f32,
+    pub(crate) color: rgb::Color,
+    pub(crate) border: BorderStyle,
+    pub(crate) callout: Option<Callout>,
+    pub(crate) justification: Justification,
+    /// The generated appearance. Since fonts are written before pages, this needs to be
+    /// created once the annotation is added to a page.
+    pub(crate) appearance: Option<FreeTextAppearance>,
+}
+
+/// The generated appearance of a free text annotation, as well as the resources of
+/// its default appearance.
+pub(crate) struct FreeTextAppearance {
+    stream: Stream,
+    /// The region covered by the appearance on the page.
+    rect: Rect,
+    /// The font resources of the default appearance, which need to contain the font
+    /// resources of the generated appearance.
+    fonts: Vec<(String, Ref)>,
+    /// The name of the font of the annotation in `fonts`.
+    font_name: String,
+    /// The ICC-based color space of the text, if device color spaces can't be used.
+    color_space: Option<Ref>,
+}
+
+impl FreeTextAnnotation {
+    /// Create a new free text annotation.
+    ///
+    /// `rect`: The region of the text box on the page.
+    /// `text`: The text that should be displayed. Lines need to be separated by `\n`.
+    /// `font`: The font of the text.
+    /// `font_size`: The size of the text.
+    /// `color`: The color of the text, the border and the callout line.
+    #[cfg(feature = "simple-text")]
+    pub fn new(rect: Rect, text: String, font: Font, font_size: f32, color: rgb::Color) -> Self {
+        let mut offset = 0;
+        let lines = text
+            .split('\n')
+            .map(|line| {
+                let mut glyphs = naive_shape(line, font.clone(), TextDirection::Auto);
+                // The ranges of the glyphs need to refer to the whole text.
+                for glyph in &mut glyphs {
+                    glyph.text_range =
+                        glyph.text_range.start + offset..glyph.text_range.end + offset;
+                }
+                offset += line.len() + 1;
+
+                glyphs
+            })
+            .collect();
+
+        Self::new_with_glyphs(rect, text, lines, font, font_size, color)
+    }
+
+    /// Create a new free text annotation from text that has already been shaped.
+    ///
+    /// `rect`: The region of the text box on the page.
+    /// `text`: The text that should be displayed. Lines need to be separated by `\n`.
+    /// `lines`: The glyphs of each line of the text. The text ranges of the glyphs refer
+    /// to `text`.
+    /// `font`: The font of the text.
+    /// `font_size`: The size of the text.
+    /// `color`: The color of the text, the border and the callout line.
+    pub fn new_with_glyphs(
+        rect: Rect,
+        text: String,
+        lines: Vec<Vec<KrillaGlyph>>,
+        font: Font,
+        font_size: f32,
+        color: rgb::Color,
+    ) -> Self {
+        Self {
+            rect,
+            text,
+            lines,
+            font,
+            font_size,
+            color,
+            border: BorderStyle::default(),
+            callout: None,
+            justification: Justification::default(),
+            appearance: None,
+        }
+    }
+
+    /// Set the style of the border of the text box.
+    pub fn with_border_style(mut self, border: BorderStyle) -> Self {
+        self.border = border;
+        self
+    }
+
+    /// Add a callout line to the annotation.
+    pub fn with_callout(mut self, callout: Callout) -> Self {
+        self.callout = Some(callout);
+        self
+    }
+
+    /// Set the justification of the text.
+    pub fn with_justification(mut self, justification: Justification) -> Self {
+        self.justification = justification;
+        self
+    }
+
+    /// The width of the callout line.
+    fn callout_width(&self) -> f32 {
+        if self.border.width > 0.0 {
+            self.border.width
+        } else {
+            1.0
+        }
+    }
+
+    /// The region covered by the annotation, including its callout line.
+    fn outer_rect(&self) -> Rect {
+        let (mut left, mut top, mut right, mut bottom) = (
+            self.rect.left(),
+            self.rect.top(),
+            self.rect.right(),
+            self.rect.bottom(),
+        );
+
+        if let Some(callout) = &self.callout {
+            let width = self.callout_width();
+            let start_padding = callout.ending.extent(width);
+
+            for (i, point) in callout.points().enumerate() {
+                let padding = if i == 0 { start_padding } else { width / 2.0 };
+                left = left.min(point.x - padding);
+                top = top.min(point.y - padding);
+                right = right.max(point.x + padding);
+                bottom = bottom.max(point.y + padding);
+            }
+        }
+
+        Rect::from_ltrb(left, top, right, bottom).unwrap()
+    }
+
+    /// Register the font of the annotation and create its appearance.
+    fn register(&mut self, sc: &mut SerializeContext) {
+        let outer = self.outer_rect();
+        let size = Size::from_wh(outer.width(), outer.height()).unwrap();
+        let font = &self.font;
+
+        // Make sure the font is written even if the text is empty, since the default
+        // appearance always refers to it.
+        sc.register_font_container(font.clone())
+            .borrow_mut()
+            .cid_font_mut()
+            .add_glyph(GlyphId::new(0));
+        let font_ref = sc
+            .register_font_identifier(FontIdentifier::Cid(CIDIdentifier(
+                font.clone(),
+                WritingMode::Horizontal,
+            )))
+            .get_ref();
+        let color_space =
+            match sc.register_colorspace(rgb::color_space(sc.serialize_settings().no_device_cs)) {
+                MaybeDeviceColorSpace::ColorSpace(cs) => Some(cs.get_ref()),
+                _ => None,
+            };
+
+        let mut appearance = None;
+        let root_builder = ContentBuilder::new(page_root_transform(size.height()), true);
+        let finish_fn = Box::new(|stream, _| {
+            appearance = Some(stream);
+        });
+        let mut surface = Surface::new(sc, root_builder, None, finish_fn);
+        // Allows us to draw everything using the coordinates of the page.
+        surface.push_transform(&Transform::from_translate(-outer.left(), -outer.top()));
+
+        if let Some(stroke) = self.border.stroke(self.color) {
+            let inset = self.border.width / 2.0;
+            let mut builder = PathBuilder::new();
+            builder.push_rect(
+                Rect::from_ltrb(
+                    self.rect.left() + inset,
+                    self.rect.top() + inset,
+                    self.rect.right() - inset,
+                    self.rect.bottom() - inset,
+                )
+                .unwrap_or(self.rect),
+            );
+            surface.set_fill(None);
+            surface.set_stroke(Some(stroke));
+            surface.draw_path(&builder.finish().unwrap());
+        }
+
+        if let Some(callout) = &self.callout {
+            let stroke = Stroke {
+                paint: self.color.into(),
+                width: self.callout_width(),
+                ..Stroke::default()
+            };
+
+            let mut builder = PathBuilder::new();
+            for (i, point) in callout.points().enumerate() {
+                if i == 0 {
+                    builder.move_to(point.x, point.y);
+                } else {
+                    builder.line_to(point.x, point.y);
+                }
+            }
+            surface.set_fill(None);
+            surface.set_stroke(Some(stroke.clone()));
+            surface.draw_path(&builder.finish().unwrap());
+
+            let from = callout.knee.unwrap_or(callout.end);
+            let mut builder = PathBuilder::new();
+            if callout.ending.add_path(
+                &mut builder,
+                (from.x, from.y),
+                (callout.start.x, callout.start.y),
+                stroke.width,
+            ) {
+                // Since there is no interior color, closed endings are filled with the
+                // color of the line.
+                let fill = callout.ending.is_closed().then(|| Fill {
+                    paint: stroke.paint.clone(),
+                    ..Fill::default()
+                });
+                surface.set_fill(fill);
+                surface.set_stroke(Some(stroke));
+                surface.draw_path(&builder.finish().unwrap());
+            }
+        }
+
+        let font_size = self.font_size;
+        let ascent = font.ascent() / font.units_per_em() * font_size;
+        let descent = font.descent() / font.units_per_em() * font_size;
+        // The padding Acrobat uses for the text of free text annotations.
+        let padding = 2.0 + self.border.width;
+        let mut y = self.rect.top() + padding + ascent;
+
+        surface.set_fill(Some(Fill {
+            paint: self.color.into(),
+            ..Fill::default()
+        }));
+        surface.set_stroke(None);
+
+        for glyphs in &self.lines {
+            let width = glyphs.iter().map(|g| g.x_advance).sum::<f32>() * font_size;
+            let x = match self.justification {
+                Justification::Left => self.rect.left() + padding,
+                Justification::Center => self.rect.left() + (self.rect.width() - width) / 2.0,
+                Justification::Right => self.rect.right() - padding - width,
+            };
+
+            surface.draw_glyphs(
+                Point::from_xy(x, y),
+                glyphs,
+                font.clone(),
+                &self.text,
+                font_size,
+                false,
+            );
+            y += ascent - descent;
+        }
+
+        surface.pop();
+        surface.finish();
+
+        let stream = appearance.unwrap();
+        // The default appearance needs to refer to the same font resource as the
+        // generated appearance, so we reuse its font resources and only add the font
+        // if the appearance does not use it.
+        let mut fonts = stream
+            .resource_dictionary
+            .fonts
+            .get_entries()
+            .collect::<Vec<_>>();
+        let font_name = match fonts.iter().find(|(_, r)| *r == font_ref) {
+            Some((name, _)) => name.clone(),
+            None => {
+                let name = format!("f{}", fonts.len());
+                fonts.push((name.clone(), font_ref));
+                name
+            }
+        };
+
+        self.appearance = Some(FreeTextAppearance {
+            stream,
+            rect: outer,
+            fonts,
+            font_name,
+            color_space,
+        });
+    }
+
+    fn serialize_type(
+        &self,
+        sc: &mut SerializeContext,
+        annotation: &mut pdf_writer::writers::Annotation,
+        page_height: f32,
+    ) {
+        let transform = page_root_transform(page_height);
+        let appearance = self
+            .appearance
+            .as_ref()
+            .expect("appearance of free text annotation should have been created");
+        let outer = &appearance.rect;
+
+        annotation.pair(Name(b"Subtype"), Name(b"FreeText"));
+        annotation.rect(outer.transform(transform).unwrap().to_pdf_rect());
+        // The offsets of the text box from the rect of the annotation, in the order
+        // left, top, right, bottom.
+        annotation.insert(Name(b"RD")).array().items([
+            self.rect.left() - outer.left(),
+            self.rect.top() - outer.top(),
+            outer.right() - self.rect.right(),
+            outer.bottom() - self.rect.bottom(),
+        ]);
+        annotation.pair(Name(b"Q"), self.justification.to_pdf());
+        self.border.serialize(annotation);
+
+        if let Some(callout) = &self.callout {
+            annotation.pair(Name(b"IT"), Name(b"FreeTextCallout"));
+            annotation
+                .insert(Name(b"CL"))
+                .array()
+                .items(callout.points().flat_map(|p| {
+                    let mut p = p.to_tsp();
+                    transform.to_tsp().map_point(&mut p);
+                    [p.x, p.y]
+                }));
+            annotation.pair(Name(b"LE"), callout.ending.to_pdf());
+        }
+
+        let [r, g, b] = self.color.to_pdf_color();
+        let color_operator = match appearance.color_space {
+            Some(_) => format!("/cs0 cs {r} {g} {b} sc"),
+            None => format!("{r} {g} {b} rg"),
+        };
+        let default_appearance = format!(
+            "/{} {} Tf {}",
+            appearance.font_name, self.font_size, color_operator
+        );
+        annotation.pair(Name(b"DA"), sc.str(default_appearance.as_bytes()));
+
+        let mut resources = annotation.insert(Name(b"DR")).dict();
+        let mut font_dict = resources.insert(Name(b"Font")).dict();
+        for (name, font_ref) in &appearance.fonts {
+            font_dict.pair(Name(name.as_bytes()), *font_ref);
+        }
+        font_dict.finish();
+        if let Some(color_space) = appearance.color_space {
+            resources
+                .insert(Name(b"ColorSpace"))
+                .dict()
+                .pair(Name(b"cs0"), color_space);
+        }
+        resources.finish();
+
+        let bbox = Rect::from_xywh(0.0, 0.0, outer.width(), outer.height()).unwrap();
+        let appearance_ref = sc.register_cacheable(XObject::new(
+            appearance.stream.clone(),
+            false,
+            false,
+            Some(bbox),
+        ));
+        annotation
+            .insert(Name(b"AP"))
+            .dict()
+            .pair(Name(b"N"), appearance_ref);
+    }
+}
+
+impl From<FreeTextAnnotation> for Annotation {
+    fn from(value: FreeTextAnnotation) -> Self {
+        // The contents of a free text annotation is the text it displays.
+        let alt = Some(value.text.clone());
+        Self::from_type(AnnotationType::FreeText(value), alt)
+    }
+}
+
+/// An ink annotation, which consists of one or more freehand strokes.
+pub struct InkAnnotation {
+    pub(crate) paths: Vec<Vec<Point>>,
+    pub(crate) width: f32,
+    pub(crate) color: rgb::Color,
+    pub(crate) opacity: NormalizedF32,
+    pub(crate) contents: Option<String>,
+}
+
+impl InkAnnotation {
+    /// Create a new ink annotation.
+    ///
+    /// `paths`: The strokes of the annotation, each one given as a polyline.
+    /// `width`: The width of the strokes.
+    /// `color`: The color of the strokes.
+    /// `opacity`: The opacity of the strokes.
+    /// `contents`: The text that should be displayed for the annotation. Note that this
+    /// might be required in some cases, for example when exporting to PDF/UA.
+    ///
+    /// Panics if the paths don't contain any points.
+    pub fn new(
+        paths: Vec<Vec<Point>>,
+        width: f32,
+        color: rgb::Color,
+        opacity: NormalizedF32,
+        contents: Option<String>,
+    ) -> Self {
+        assert!(
+            paths.iter().any(|path| !path.is_empty()),
+            "an ink annotation needs to contain at least one point"
+        );
+
+        Self {
+            paths,
+            width,
+            color,
+            opacity,
+            contents,
+        }
+    }
+
+    fn serialize_type(
+        &self,
+        sc: &mut SerializeContext,
+        annotation: &mut pdf_writer::writers::Annotation,
+        page_height: f32,
+        opacity: NormalizedF32,
+        extra: &mut Chunk,
+    ) {
+        let opacity = combined_opacity(self.opacity, opacity);
+        annotation.pair(Name(b"Subtype"), Name(b"Ink"));
+
+        if opacity != NormalizedF32::ONE {
+            sc.register_validation_error(ValidationError::Transparency(sc.location));
+        }
+
+        // Convert the points into PDF coordinates, in the same way as page content.
+        let transform = page_root_transform(page_height).to_tsp();
+        let paths = self
+            .paths
+            .iter()
+            .map(|path| {
+                path.iter()
+                    .map(|p| {
+                        let mut p = p.to_tsp();
+                        transform.map_point(&mut p);
+                        (p.x, p.y)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut appearance = ColoredAppearance::new(sc, self.color, self.color);
+        let content = &mut appearance.content;
+        content.set_line_width(self.width);
+        content.set_line_cap(LineCapStyle::RoundCap);
+        content.set_line_join(LineJoinStyle::RoundJoin);
+
+        for path in &paths {
+            let Some((first, rest)) = path.split_first() else {
+                continue;
+            };
+
+            content.move_to(first.0, first.1);
+            if rest.is_empty() {
+                // With round caps, this results in a dot.
+                content.line_to(first.0, first.1);
+            }
+            for p in rest {
+                content.line_to(p.0, p.1);
+            }
+            content.stroke();
+        }
+
+        let rect = bounding_rect(paths.iter().flatten().copied(), self.width / 2.0);
+
+        annotation.rect(rect);
+        let mut ink_list = annotation.insert(Name(b"InkList")).array();
+        for path in &paths {
+            ink_list
+                .push()
+                .array()
+                .items(path.iter().flat_map(|p| [p.0, p.1]));
+        }
+        ink_list.finish();
+
+        let color = self.color.to_pdf_color();
+        annotation.color_rgb(color[0], color[1], color[2]);
+        annotation.pair(Name(b"CA"), opacity.get());
+        annotation
+            .insert(Name(b"BS"))
+            .dict()
+            .pair(Name(b"W"), self.width);
+
+        let appearance_ref = appearance.finish(sc, extra, rect, opacity, None);
+        annotation
+            .insert(Name(b"AP"))
+            .dict()
+            .pair(Name(b"N"), appearance_ref);
+    }
+}
+
+impl From<InkAnnotation> for Annotation {
+    fn from(mut value: InkAnnotation) -> Self {
+        let alt = value.contents.take();
+        Self::from_type(AnnotationType::Ink(value), alt)
+    }
+}
+
+/// One of the standard stamps defined by the PDF specification. Their appearance is
+/// provided by the viewer, and can therefore differ between viewers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(missing_docs)]
+pub enum StandardStamp {
+    Approved,
+    Experimental,
+    NotApproved,
+    AsIs,
+    Expired,
+    NotForPublicRelease,
+    Confidential,
+    Final,
+    Sold,
+    Departmental,
+    ForComment,
+    TopSecret,
+    Draft,
+    ForPublicRelease,
+}
+
+impl StandardStamp {
+    fn to_pdf(self) -> Name<'static> {
+        Name(match self {
+            StandardStamp::Approved => b"Approved",
+            StandardStamp::Experimental => b"Experimental",
+            StandardStamp::NotApproved => b"NotApproved",
+            StandardStamp::AsIs => b"AsIs",
+            StandardStamp::Expired => b"Expired",
+            StandardStamp::NotForPublicRelease => b"NotForPublicRelease",
+            StandardStamp::Confidential => b"Confidential",
+            StandardStamp::Final => b"Final",
+            StandardStamp::Sold => b"Sold",
+            StandardStamp::Departmental => b"Departmental",
+            StandardStamp::ForComment => b"ForComment",
+            StandardStamp::TopSecret => b"TopSecret",
+            StandardStamp::Draft => b"Draft",
+            StandardStamp::ForPublicRelease => b"ForPublicRelease",
+        })
+    }
+}
+
+/// The appearance of a [`StampAnnotation`].
+pub enum StampAppearance {
+    /// One of the standard stamps.
+    ///
+    /// Note that since these don't have an appearance stream, they cannot be used in
+    /// some export modes, for example PDF/A2.
+    Standard(StandardStamp),
+    /// A custom appearance, which is drawn by the given function onto a new surface
+    /// once the stamp is added to a page. The region covered by the drawn contents will
+    /// be scaled to fill the region of the stamp.
+    ///
+    /// If stamps on several pages are drawn with the same contents, the appearance
+    /// will only be written once.
+    Custom(Box<dyn FnOnce(&mut Surface)>),
+}
+
+/// A stamp annotation, which displays a stamp like "Approved" or "Draft" that can be
+/// moved around in viewers.
+pub struct StampAnnotation {
+    pub(crate) rect: Rect,
+    /// The appearance of the stamp. Custom appearances are taken once they have
+    /// been drawn into `stream`.
+    pub(crate) appearance: Option<StampAppearance>,
+    pub(crate) stream: Option<Stream>,
+    pub(crate) rotation: f32,
+    pub(crate) opacity: NormalizedF32,
+    pub(crate) contents: Option<String>,
+}
+
+impl StampAnnotation {
+    /// Create a new stamp annotation.
+    ///
+    /// `rect`: The region of the stamp on the page, before it is rotated.
+    /// `appearance`: The appearance of the stamp.
+    /// `contents`: The text that should be displayed for the annotation. Note that this
+    /// might be required in some cases, for example when exporting to PDF/UA.
+    pub fn new(rect: Rect, appearance: StampAppearance, contents: Option<String>) -> Self {
+        Self {
+            rect,
+            appearance: Some(appearance),
+            stream: None,
+            rotation: 0.0,
+            opacity: NormalizedF32::ONE,
+            contents,
+        }
+    }
+
+    /// Rotate the stamp around its center by the given angle in degrees,
+    /// counter-clockwise.
+    ///
+    /// Note that this only has an effect for stamps with a custom appearance.
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Set the opacity of the stamp.
+    pub fn with_opacity(mut self, opacity: NormalizedF32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    /// Draw the custom appearance of the stamp, if it has one.
+    fn register(&mut self, sc: &mut SerializeContext) {
+        match self.appearance.take() {
+            Some(StampAppearance::Custom(draw)) => {
+                let mut stream_builder = StreamBuilder::new(sc);
+                let mut surface = stream_builder.surface();
+                draw(&mut surface);
+                surface.finish();
+                self.stream = Some(stream_builder.finish());
+            }
+            appearance => self.appearance = appearance,
+        }
+    }
+
+    fn serialize_type(
+        &self,
+        sc: &mut SerializeContext,
+        annotation: &mut pdf_writer::writers::Annotation,
+        page_height: f32,
+        opacity: NormalizedF32,
+        extra: &mut Chunk,
+    ) {
+        let opacity = combined_opacity(self.opacity, opacity);
+        let transform = page_root_transform(page_height);
+        annotation.pair(Name(b"Subtype"), Name(b"Stamp"));
+
+        if opacity != NormalizedF32::ONE {
+            sc.register_validation_error(ValidationError::Transparency(sc.location));
+            annotation.pair(Name(b"CA"), opacity.get());
+        }
+
+        let stream = match (&self.appearance, &self.stream) {
+            (Some(StampAppearance::Standard(stamp)), _) => {
+                annotation.rect(self.rect.transform(transform).unwrap().to_pdf_rect());
+                annotation.pair(Name(b"Name"), stamp.to_pdf());
+                sc.register_validation_error(ValidationError::MissingAnnotationAppearance);
+
+                return;
+            }
+            (_, Some(stream)) => stream,
+            _ => unreachable!("appearance of stamp annotation should have been drawn"),
+        };
+
+        let (width, height) = (self.rect.width(), self.rect.height());
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+
+        // The rotated stamp needs to fit into the rect of the annotation, so that
+        // the appearance doesn't get distorted.
+        let rotated_width = (width * cos).abs() + (height * sin).abs();
+        let rotated_height = (width * sin).abs() + (height * cos).abs();
+        let center = (
+            self.rect.left() + width / 2.0,
+            self.rect.top() + height / 2.0,
+        );
+        let rotated_rect = Rect::from_xywh(
+            center.0 - rotated_width / 2.0,
+            center.1 - rotated_height / 2.0,
+            rotated_width,
+            rotated_height,
+        )
+        .unwrap();
+        annotation.rect(rotated_rect.transform(transform).unwrap().to_pdf_rect());
+        annotation.pair(Name(b"Name"), Name(b"Custom"));
+
+        // The stream itself can be shared across all stamps that use it, so we register
+        // it as a normal XObject and only write a small wrapper for each annotation
+        // that positions and rotates it.
+        let bbox = stream.bbox;
+        let x_object_ref = sc.register_cacheable(XObject::new(stream.clone(), false, false, None));
+        let scale_x = if bbox.width() > 0.0 {
+            width / bbox.width()
+        } else {
+            1.0
+        };
+        let scale_y = if bbox.height() > 0.0 {
+            height / bbox.height()
+        } else {
+            1.0
+        };
+
+        let mut content = Content::new();
+        // Streams use the coordinate system of pages, so we need to flip them.
+        content.transform([
+            scale_x,
+            0.0,
+            0.0,
+            -scale_y,
+            -bbox.left() * scale_x,
+            bbox.bottom() * scale_y,
+        ]);
+        content.x_object(Name(b"x0"));
+        let content = content.finish();
+        sc.register_limits(content.limits());
+
+        let appearance_ref = sc.new_ref();
+        let serialize_settings = sc.serialize_settings();
+        let encryptor = sc.encryptor();
+        let wrapper = FilterStreamBuilder::new_from_content_stream(&content, &serialize_settings)
+            .finish(&serialize_settings, encryptor);
+        let mut x_object = extra.form_xobject(appearance_ref, wrapper.encoded_data());
+        wrapper.write_filters(x_object.deref_mut().deref_mut());
+        x_object.bbox(pdf_writer::Rect::new(0.0, 0.0, width, height));
+        // Rotate around the center of the bounding box.
+        let (cx, cy) = (width / 2.0, height / 2.0);
+        x_object.matrix([
+            cos,
+            sin,
+            -sin,
+            cos,
+            cx - cos * cx + sin * cy,
+            cy - sin * cx - cos * cy,
+        ]);
+        x_object
+            .resources()
+            .x_objects()
+            .pair(Name(b"x0"), x_object_ref);
+        x_object.finish();
+
+        annotation
+            .insert(Name(b"AP"))
+            .dict()
+            .pair(Name(b"N"), appearance_ref);
+    }
+}
+
+impl From<StampAnnotation> for Annotation {
+    fn from(mut value: StampAnnotation) -> Self {
+        let alt = value.contents.take();
+        Self::from_type(AnnotationType::Stamp(value), alt)
+    }
+}
+
+/// The style shared by all geometric annotations.
+struct ShapeStyle {
+    color: rgb::Color,
+    interior_color: Option<rgb::Color>,
+    border: BorderStyle,
+    contents: Option<String>,
+}
+
+impl ShapeStyle {
+    fn new(color: rgb::Color, contents: Option<String>) -> Self {
+        Self {
+            color,
+            interior_color: None,
+            border: BorderStyle::default(),
+            contents,
+        }
+    }
+
+    /// Write the entries of the style into the annotation dictionary.
+    fn serialize(&self, annotation: &mut pdf_writer::writers::Annotation) {
+        let color = self.color.to_pdf_color();
+        annotation.color_rgb(color[0], color[1], color[2]);
+        if let Some(interior_color) = self.interior_color {
+            annotation
+                .insert(Name(b"IC"))
+                .array()
+                .items(interior_color.to_pdf_color());
+        }
+        self.border.serialize(annotation);
+    }
+
+    /// Start the appearance of the shape.
+    fn appearance(&self, sc: &mut SerializeContext) -> ColoredAppearance {
+        let mut appearance =
+            ColoredAppearance::new(sc, self.color, self.interior_color.unwrap_or(self.color));
+        let content = &mut appearance.content;
+        content.set_line_width(self.border.width);
+        if let Some(dash) = &self.border.dash {
+            content.set_dash_pattern(dash.iter().copied(), 0.0);
+        }
+
+        appearance
+    }
+
+    /// Paint the current path. Closed paths are filled with the interior color, if
+    /// there is one.
+    fn paint(&self, content: &mut Content, closed: bool) {
+        let fill = closed && self.interior_color.is_some();
+        let stroke = self.border.width > 0.0;
+
+        match (fill, stroke) {
+            (true, true) => content.fill_nonzero_and_stroke(),
+            (true, false) => content.fill_nonzero(),
+            (false, true) => content.stroke(),
+            (false, false) => content.end_path(),
+        };
+    }
+
+    /// Draw the ending at `tip`, for a line that arrives at `tip` coming from `from`.
+    fn draw_ending(
+        &self,
+        content: &mut Content,
+        ending: LineEnding,
+        from: (f32, f32),
+        tip: (f32, f32),
+    ) {
+        if ending.add_path(content, from, tip, self.border.width) {
+            self.paint(content, ending.is_closed());
+        }
+    }
+
+    /// The distance by which the strokes extend beyond the geometry of the shape.
+    fn padding(&self) -> f32 {
+        self.border.width / 2.0
+    }
+}
+
+macro_rules! shape_annotation {
+    ($name:ident, $variant:ident) => {
+        impl $name {
+            /// Set the color that closed shapes should be filled with. By default, they
+            /// are not filled.
+            pub fn with_interior_color(mut self, interior_color: rgb::Color) -> Self {
+                self.style.interior_color = Some(interior_color);
+                self
+            }
+
+            /// Set the style of the border, i.e. its width and dash pattern.
+            pub fn with_border_style(mut self, border: BorderStyle) -> Self {
+                self.style.border = border;
+                self
+            }
+        }
+
+        impl From<$name> for Annotation {
+            fn from(mut value: $name) -> Self {
+                let alt = value.style.contents.take();
+                Self::from_type(AnnotationType::$variant(value), alt)
+            }
+        }
+    };
+}
+
+shape_annotation!(SquareAnnotation, Square);
+shape_annotation!(CircleAnnotation, Circle);
+shape_annotation!(LineAnnotation, Line);
+shape_annotation!(PolygonAnnotation, Polygon);
+shape_annotation!(PolylineAnnotation, Polyline);
+
+/// Convert points from the coordinate system of the page into PDF coordinates.
+fn to_pdf_points(points: &[Point], page_height: f32) -> Vec<(f32, f32)> {
+    let transform = page_root_transform(page_height).to_tsp();
+    points
+        .iter()
+        .map(|p| {
+            let mut p = p.to_tsp();
+            transform.map_point(&mut p);
+            (p.x, p.y)
+        })
+        .collect()
+}
+
+/// A square annotation, which draws a rectangle on the page.
+pub struct SquareAnnotation {
+    rect: Rect,
+    style: ShapeStyle,
+}
+
+impl SquareAnnotation {
+    /// Create a new square annotation.
+    ///
+    /// `rect`: The rectangle that should be drawn. The border is drawn inside of it.
+    /// `color`: The color of the border.
+    /// `contents`: The text that should be displayed for the annotation. Note that this
+    /// might be required in some cases, for example when exporting to PDF/UA.
+    pub fn new(rect: Rect, color: rgb::Color, contents: Option<String>) -> Self {
+        Self {
+            rect,
+            style: ShapeStyle::new(color, contents),
+        }
+    }
+
+    fn serialize_type(
+        &self,
+        sc: &mut SerializeContext,
+        annotation: &mut pdf_writer::writers::Annotation,
+        page_height: f32,
+        extra: &mut Chunk,
+    ) {
+        let rect = self
+            .rect
+            .transform(page_root_transform(page_height))
+            .unwrap();
+        annotation.subtype(pdf_writer::types::AnnotationType::Square);
+        annotation.rect(rect.to_pdf_rect());
+        self.style.serialize(annotation);
+
+        let inset = self.style.padding();
+        let mut appearance = self.style.appearance(sc);
+        appearance.content.rect(
+            rect.left() + inset,
+            rect.top() + inset,
+            (rect.width() - 2.0 * inset).max(0.0),
+            (rect.height() - 2.0 * inset).max(0.0),
+        );
+        self.style.paint(&mut appearance.content, true);
+
+        let appearance_ref =
+            appearance.finish(sc, extra, rect.to_pdf_rect(), NormalizedF32::ONE, None);
+        annotation
+            .insert(Name(b"AP"))
+            .dict()
+            .pair(Name(b"N"), appearance_ref);
+    }
+}
+
+/// A circle annotation, which draws an ellipse on the page.
+pub struct CircleAnnotation {
+    rect: Rect,
+    style: ShapeStyle,
+}
+
+impl CircleAnnotation {
+    /// Create a new circle annotation.
+    ///
+    /// `rect`: The rectangle the ellipse should be inscribed in. The border is drawn
+    /// inside of it.
+    /// `color`: The color of the border.
+    /// `contents`: The text that should be displayed for the annotation. Note that this
+    /// might be required in some cases, for example when exporting to PDF/UA.
+    pub fn new(rect: Rect, color: rgb::Color, contents: Option<String>) -> Self {
+        Self {
+            rect,
+            style: ShapeStyle::new(color, contents),
+        }
+    }
+
+    fn serialize_type(
+        &self,
+        sc: &mut SerializeContext,
+        annotation: &mut pdf_writer::writers::Annotation,
+        page_height: f32,
+        extra: &mut Chunk,
+    ) {
+        let rect = self
+            .rect
+            .transform(page_root_transform(page_height))
+            .unwrap();
+        annotation.subtype(pdf_writer::types::AnnotationType::Circle);
+        annotation.rect(rect.to_pdf_rect());
+        self.style.serialize(annotation);
+
+        let inset = self.style.padding();
+        let mut appearance = self.style.appearance(sc);
+        add_ellipse(
+            &mut appearance.content,
+            (
+                rect.left() + rect.width() / 2.0,
+                rect.top() + rect.height() / 2.0,
+            ),
+            (rect.width() / 2.0 - inset).max(0.0),
+            (rect.height() / 2.0 - inset).max(0.0),
+        );
+        self.style.paint(&mut appearance.content, true);
+
+        let appearance_ref =
+            appearance.finish(sc, extra, rect.to_pdf_rect(), NormalizedF32::ONE, None);
+        annotation
+            .insert(Name(b"AP"))
+            .dict()
+            .pair(Name(b"N"), appearance_ref);
+    }
+}
+
+/// A line annotation, which draws a single straight line on the page, optionally
+/// with line endings and leader lines.
+pub struct LineAnnotation {
+    start: Point,
+    end: Point,
+    start_ending: LineEnding,
+    end_ending: LineEnding,
+    leader_length: f32,
+    leader_extension: f32,
+    leader_offset: f32,
+    style: ShapeStyle,
+}
+
+impl LineAnnotation {
+    /// Create a new line annotation.
+    ///
+    /// `start`: The start point of the line.
+    /// `end`: The end point of the line.
+    /// `color`: The color of the line.
+    /// `contents`: The text that should be displayed for the annotation. Note that this
+    /// might be required in some cases, for example when exporting to PDF/UA.
+    pub fn new(start: Point, end: Point, color: rgb::Color, contents: Option<String>) -> Self {
+        Self {
+            start,
+            end,
+            start_ending: LineEnding::None,
+            end_ending: LineEnding::None,
+            leader_length: 0.0,
+            leader_extension: 0.0,
+            leader_offset: 0.0,
+            style: ShapeStyle::new(color, contents),
+        }
+    }
+
+    /// Set the endings at the start and at the end of the line.
+    pub fn with_line_endings(mut self, start: LineEnding, end: LineEnding) -> Self {
+        self.start_ending = start;
+        self.end_ending = end;
+        self
+    }
+
+    /// Add leader lines, which extend from the endpoints perpendicular to the line,
+    /// for example to indicate a measured distance.
+    ///
+    /// `length`: The length of the leader lines. The line itself is moved by this
+    /// distance. Positive values place the leader lines clockwise of the line, when
+    /// going from its start to its end, negative values counter-clockwise.
+    /// `extension`: How far the leader lines extend beyond the line.
+    /// `offset`: The distance between the endpoints and the start of the leader lines.
+    pub fn with_leader_lines(mut self, length: f32, extension: f32, offset: f32) -> Self {
+        self.leader_length = length;
+        self.leader_extension = extension;
+        self.leader_offset = offset;
+        self
+    }
+
+    fn serialize_type(
+        &self,
+        sc: &mut SerializeContext,
+        annotation: &mut pdf_writer::writers::Annotation,
+        page_height: f32,
+        extra: &mut Chunk,
+    ) {
+        let points = to_pdf_points(&[self.start, self.end], page_height);
+        let (p1, p2) = (points[0], points[1]);
 
-/// An annotation.
-pub struct Annotation {
-    pub(crate) annotation_type: AnnotationType,
-    pub(crate) alt: Option<String>,
-    pub(crate) struct_parent: Option<i32>,
+        // The direction that is clockwise of the line.
+        let (dx, dy) = (p2.0 - p1.0, p2.1 - p1.1);
+        let length = (dx * dx + dy * dy).sqrt();
+        let perpendicular = if length > 0.0 {
+            (dy / length, -dx / length)
+        } else {
+            (0.0, 0.0)
+        };
+        let offset = |p: (f32, f32), distance: f32| {
+            (
+                p.0 + distance * perpendicular.0,
+                p.1 + distance * perpendicular.1,
+            )
+        };
+
+        // The line itself is moved by the length of the leader lines.
+        let (q1, q2) = (
+            offset(p1, self.leader_length),
+            offset(p2, self.leader_length),
+        );
+        let mut appearance = self.style.appearance(sc);
+        let content = &mut appearance.content;
+        content.move_to(q1.0, q1.1);
+        content.line_to(q2.0, q2.1);
+        self.style.paint(content, false);
+
+        let mut leader_points = vec![];
+        if self.leader_length != 0.0 {
+            let sign = self.leader_length.signum();
+            for p in [p1, p2] {
+                let from = offset(p, sign * self.leader_offset);
+                let to = offset(p, self.leader_length + sign * self.leader_extension);
+                content.move_to(from.0, from.1);
+                content.line_to(to.0, to.1);
+                self.style.paint(content, false);
+                leader_points.extend([from, to]);
+            }
+        }
+
+        // Line endings are never dashed.
+        content.set_dash_pattern([], 0.0);
+        self.style.draw_ending(content, self.start_ending, q2, q1);
+        self.style.draw_ending(content, self.end_ending, q1, q2);
+
+        let padding = self
+            .start_ending
+            .extent(self.style.border.width)
+            .max(self.end_ending.extent(self.style.border.width));
+        let mut rect = bounding_rect([q1, q2].into_iter(), padding);
+        if !leader_points.is_empty() {
+            let leader_rect = bounding_rect(leader_points.into_iter(), self.style.padding());
+            rect = pdf_writer::Rect::new(
+                rect.x1.min(leader_rect.x1),
+                rect.y1.min(leader_rect.y1),
+                rect.x2.max(leader_rect.x2),
+                rect.y2.max(leader_rect.y2),
+            );
+        }
+
+        annotation.subtype(pdf_writer::types::AnnotationType::Line);
+        annotation.rect(rect);
+        annotation
+            .insert(Name(b"L"))
+            .array()
+            .items([p1.0, p1.1, p2.0, p2.1]);
+        annotation
+            .insert(Name(b"LE"))
+            .array()
+            .items([self.start_ending.to_pdf(), self.end_ending.to_pdf()]);
+        if self.leader_length != 0.0 {
+            annotation.pair(Name(b"LL"), self.leader_length);
+            annotation.pair(Name(b"LLE"), self.leader_extension);
+            annotation.pair(Name(b"LLO"), self.leader_offset);
+        }
+        self.style.serialize(annotation);
+
+        let appearance_ref = appearance.finish(sc, extra, rect, NormalizedF32::ONE, None);
+        annotation
+            .insert(Name(b"AP"))
+            .dict()
+            .pair(Name(b"N"), appearance_ref);
+    }
 }
 
-impl Annotation {
-    /// Create a new link annotation with some alt text.
+/// A polygon annotation, which draws a closed polygon on the page.
+pub struct PolygonAnnotation {
+    vertices: Vec<Point>,
+    style: ShapeStyle,
+}
+
+impl PolygonAnnotation {
+    /// Create a new polygon annotation.
     ///
-    /// Note that the alt text might be required in some cases, for example
-    /// when exporting to PDF/UA.
-    pub fn new_link(annotation: LinkAnnotation, alt_text: Option<String>) -> Self {
+    /// `vertices`: The vertices of the polygon.
+    /// `color`: The color of the border.
+    /// `contents`: The text that should be displayed for the annotation. Note that this
+    /// might be required in some cases, for example when exporting to PDF/UA.
+    ///
+    /// Panics if there are no vertices.
+    pub fn new(vertices: Vec<Point>, color: rgb::Color, contents: Option<String>) -> Self {
+        assert!(
+            !vertices.is_empty(),
+            "a polygon annotation needs to contain at least one vertex"
+        );
+
         Self {
-            annotation_type: AnnotationType::Link(annotation),
-            alt: alt_text,
-            struct_parent: None,
+            vertices,
+            style: ShapeStyle::new(color, contents),
+        }
+    }
+
+    fn serialize_type(
+        &self,
+        sc: &mut SerializeContext,
+        annotation: &mut pdf_writer::writers::Annotation,
+        page_height: f32,
+        extra: &mut Chunk,
+    ) {
+        let vertices = to_pdf_points(&self.vertices, page_height);
+
+        let mut appearance = self.style.appearance(sc);
+        let content = &mut appearance.content;
+        // Round joins make sure that the strokes don't extend beyond the padding.
+        content.set_line_join(LineJoinStyle::RoundJoin);
+        content.move_to(vertices[0].0, vertices[0].1);
+        for p in &vertices[1..] {
+            content.line_to(p.0, p.1);
         }
+        content.close_path();
+        self.style.paint(content, true);
+
+        let rect = bounding_rect(vertices.iter().copied(), self.style.padding());
+        annotation.pair(Name(b"Subtype"), Name(b"Polygon"));
+        annotation.rect(rect);
+        annotation
+            .insert(Name(b"Vertices"))
+            .array()
+            .items(vertices.iter().flat_map(|p| [p.0, p.1]));
+        self.style.serialize(annotation);
+
+        let appearance_ref = appearance.finish(sc, extra, rect, NormalizedF32::ONE, None);
+        annotation
+            .insert(Name(b"AP"))
+            .dict()
+            .pair(Name(b"N"), appearance_ref);
     }
 }
 
-impl From<LinkAnnotation> for Annotation {
-    fn from(value: LinkAnnotation) -> Self {
+/// A polyline annotation, which draws an open sequence of lines on the page,
+/// optionally with line endings.
+pub struct PolylineAnnotation {
+    vertices: Vec<Point>,
+    start_ending: LineEnding,
+    end_ending: LineEnding,
+    style: ShapeStyle,
+}
+
+impl PolylineAnnotation {
+    /// Create a new polyline annotation.
+    ///
+    /// `vertices`: The vertices of the polyline.
+    /// `color`: The color of the lines.
+    /// `contents`: The text that should be displayed for the annotation. Note that this
+    /// might be required in some cases, for example when exporting to PDF/UA.
+    ///
+    /// Panics if there are no vertices.
+    pub fn new(vertices: Vec<Point>, color: rgb::Color, contents: Option<String>) -> Self {
+        assert!(
+            !vertices.is_empty(),
+            "a polyline annotation needs to contain at least one vertex"
+        );
+
         Self {
-            annotation_type: AnnotationType::Link(value),
-            alt: None,
-            struct_parent: None,
+            vertices,
+            start_ending: LineEnding::None,
+            end_ending: LineEnding::None,
+            style: ShapeStyle::new(color, contents),
+        }
+    }
+
+    /// Set the endings at the first and at the last vertex.
+    pub fn with_line_endings(mut self, start: LineEnding, end: LineEnding) -> Self {
+        self.start_ending = start;
+        self.end_ending = end;
+        self
+    }
+
+    fn serialize_type(
+        &self,
+        sc: &mut SerializeContext,
+        annotation: &mut pdf_writer::writers::Annotation,
+        page_height: f32,
+        extra: &mut Chunk,
+    ) {
+        let vertices = to_pdf_points(&self.vertices, page_height);
+
+        let mut appearance = self.style.appearance(sc);
+        let content = &mut appearance.content;
+        // Round joins make sure that the strokes don't extend beyond the padding.
+        content.set_line_join(LineJoinStyle::RoundJoin);
+        content.move_to(vertices[0].0, vertices[0].1);
+        for p in &vertices[1..] {
+            content.line_to(p.0, p.1);
+        }
+        self.style.paint(content, false);
+
+        let mut padding = self.style.padding();
+        if let [first, second, ..] = vertices[..] {
+            content.set_dash_pattern([], 0.0);
+            self.style
+                .draw_ending(content, self.start_ending, second, first);
+            let (last, second_last) = (vertices[vertices.len() - 1], vertices[vertices.len() - 2]);
+            self.style
+                .draw_ending(content, self.end_ending, second_last, last);
+
+            padding = self
+                .start_ending
+                .extent(self.style.border.width)
+                .max(self.end_ending.extent(self.style.border.width));
+        }
+
+        let rect = bounding_rect(vertices.iter().copied(), padding);
+        annotation.pair(Name(b"Subtype"), Name(b"PolyLine"));
+        annotation.rect(rect);
+        annotation
+            .insert(Name(b"Vertices"))
+            .array()
+            .items(vertices.iter().flat_map(|p| [p.0, p.1]));
+        annotation
+            .insert(Name(b"LE"))
+            .array()
+            .items([self.start_ending.to_pdf(), self.end_ending.to_pdf()]);
+        self.style.serialize(annotation);
+
+        let appearance_ref = appearance.finish(sc, extra, rect, NormalizedF32::ONE, None);
+        annotation
+            .insert(Name(b"AP"))
+            .dict()
+            .pair(Name(b"N"), appearance_ref);
+    }
+}
+
+/// The icon of a [`FileAttachmentAnnotation`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FileAttachmentIcon {
+    /// A bar chart.
+    Graph,
+    /// A push pin.
+    PushPin,
+    /// A paperclip.
+    #[default]
+    Paperclip,
+    /// A tag.
+    Tag,
+}
+
+impl FileAttachmentIcon {
+    fn to_pdf(self) -> Name<'static> {
+        Name(match self {
+            FileAttachmentIcon::Graph => b"Graph",
+            FileAttachmentIcon::PushPin => b"PushPin",
+            FileAttachmentIcon::Paperclip => b"Paperclip",
+            FileAttachmentIcon::Tag => b"Tag",
+        })
+    }
+
+    /// Draw the icon into the rect with the given origin and size. Returns whether the
+    /// path needs to be filled instead of stroked.
+    fn draw(self, content: &mut Content, x: f32, y: f32, width: f32, height: f32) -> bool {
+        // A point in the rect, given as fractions of its width and height.
+        let at = |u: f32, v: f32| (x + u * width, y + v * height);
+        let sink: &mut dyn PathSink = content;
+        let polyline = |sink: &mut dyn PathSink, points: &[(f32, f32)]| {
+            let start = at(points[0].0, points[0].1);
+            sink.move_to(start.0, start.1);
+            for p in &points[1..] {
+                let p = at(p.0, p.1);
+                sink.line_to(p.0, p.1);
+            }
+        };
+
+        match self {
+            FileAttachmentIcon::Graph => {
+                for (left, top) in [(0.15, 0.45), (0.4, 0.85), (0.65, 0.6)] {
+                    polyline(
+                        sink,
+                        &[
+                            (left, 0.1),
+                            (left + 0.2, 0.1),
+                            (left + 0.2, top),
+                            (left, top),
+                        ],
+                    );
+                    sink.close();
+                }
+
+                true
+            }
+            FileAttachmentIcon::PushPin => {
+                let (cx, cy) = at(0.6, 0.65);
+                add_ellipse(sink, (cx, cy), 0.25 * width, 0.25 * height);
+                polyline(sink, &[(0.45, 0.5), (0.1, 0.1), (0.5, 0.45)]);
+                sink.close();
+
+                true
+            }
+            FileAttachmentIcon::Paperclip => {
+                // A single wire with three bends.
+                let bend = |sink: &mut dyn PathSink, cx: f32, cy: f32, r: f32, up: bool| {
+                    // Two quarter circles, going clockwise over the top for `up` and
+                    // clockwise under the bottom otherwise.
+                    let (rx, ry) = (r * width, r * height);
+                    let (cx, cy) = at(cx, cy);
+                    let (kx, ky) = (KAPPA * rx, KAPPA * ry);
+                    if up {
+                        sink.cubic_to(cx - rx, cy + ky, cx - kx, cy + ry, cx, cy + ry);
+                        sink.cubic_to(cx + kx, cy + ry, cx + rx, cy + ky, cx + rx, cy);
+                    } else {
+                        sink.cubic_to(cx + rx, cy - ky, cx + kx, cy - ry, cx, cy - ry);
+                        sink.cubic_to(cx - kx, cy - ry, cx - rx, cy - ky, cx - rx, cy);
+                    }
+                };
+
+                polyline(sink, &[(0.3, 0.3), (0.3, 0.75)]);
+                bend(sink, 0.5, 0.75, 0.2, true);
+                polyline(sink, &[(0.7, 0.75), (0.7, 0.2)]);
+                bend(sink, 0.55, 0.2, 0.15, false);
+                polyline(sink, &[(0.4, 0.2), (0.4, 0.7)]);
+                bend(sink, 0.5, 0.7, 0.1, true);
+                polyline(sink, &[(0.6, 0.7), (0.6, 0.3)]);
+
+                false
+            }
+            FileAttachmentIcon::Tag => {
+                polyline(
+                    sink,
+                    &[
+                        (0.1, 0.5),
+                        (0.35, 0.85),
+                        (0.9, 0.85),
+                        (0.9, 0.15),
+                        (0.35, 0.15),
+                    ],
+                );
+                sink.close();
+                // The hole of the tag.
+                let (cx, cy) = at(0.35, 0.5);
+                add_ellipse(sink, (cx, cy), 0.07 * width, 0.07 * height);
+
+                false
+            }
+        }
+    }
+}
+
+/// A file attachment annotation, which is displayed as an icon on the page and
+/// allows opening an embedded file.
+///
+/// If the same file is also embedded in the document with [`Document::embed_file`],
+/// it will only be written once.
+///
+/// [`Document::embed_file`]: crate::Document::embed_file
+pub struct FileAttachmentAnnotation {
+    pub(crate) rect: Rect,
+    pub(crate) icon: FileAttachmentIcon,
+    pub(crate) color: rgb::Color,
+    pub(crate) file: EmbeddedFile,
+    pub(crate) contents: Option<String>,
+}
+
+impl FileAttachmentAnnotation {
+    /// Create a new file attachment annotation.
+    ///
+    /// `rect`: The region of the icon on the page.
+    /// `icon`: The icon that should be displayed.
+    /// `color`: The color of the icon.
+    /// `file`: The file that should be attached.
+    /// `contents`: The text that should be displayed for the annotation, for example a
+    /// description of the file. Note that this might be required in some cases, for
+    /// example when exporting to PDF/UA.
+    pub fn new(
+        rect: Rect,
+        icon: FileAttachmentIcon,
+        color: rgb::Color,
+        file: EmbeddedFile,
+        contents: Option<String>,
+    ) -> Self {
+        Self {
+            rect,
+            icon,
+            color,
+            file,
+            contents,
         }
     }
+
+    fn serialize_type(
+        &self,
+        sc: &mut SerializeContext,
+        annotation: &mut pdf_writer::writers::Annotation,
+        page_height: f32,
+        extra: &mut Chunk,
+    ) {
+        let rect = self
+            .rect
+            .transform(page_root_transform(page_height))
+            .unwrap();
+        annotation.subtype(pdf_writer::types::AnnotationType::FileAttachment);
+        annotation.rect(rect.to_pdf_rect());
+        annotation.pair(Name(b"Name"), self.icon.to_pdf());
+        let color = self.color.to_pdf_color();
+        annotation.color_rgb(color[0], color[1], color[2]);
+
+        // Files are cached, so if the same file is also part of the embedded files of the
+        // document, the file specification will be shared.
+        let file_ref = sc.register_cacheable(self.file.clone());
+        annotation.pair(Name(b"FS"), file_ref);
+        if sc
+            .serialize_settings()
+            .validator()
+            .allows_associated_files()
+        {
+            annotation.insert(Name(b"AF")).array().item(file_ref);
+        }
+
+        // We generate our own appearance for the icon, since some export modes require
+        // all annotations to have one.
+        let mut appearance = ColoredAppearance::new(sc, self.color, self.color);
+        let content = &mut appearance.content;
+        content.set_line_width(0.06 * rect.width().min(rect.height()));
+        content.set_line_cap(LineCapStyle::RoundCap);
+        content.set_line_join(LineJoinStyle::RoundJoin);
+        if self.icon.draw(
+            content,
+            rect.left(),
+            rect.top(),
+            rect.width(),
+            rect.height(),
+        ) {
+            content.fill_nonzero();
+        } else {
+            content.stroke();
+        }
+
+        let appearance_ref =
+            appearance.finish(sc, extra, rect.to_pdf_rect(), NormalizedF32::ONE, None);
+        annotation
+            .insert(Name(b"AP"))
+            .dict()
+            .pair(Name(b"N"), appearance_ref);
+    }
+}
+
+impl From<FileAttachmentAnnotation> for Annotation {
+    fn from(mut value: FileAttachmentAnnotation) -> Self {
+        let alt = value.contents.take();
+        Self::from_type(AnnotationType::FileAttachment(value), alt)
+    }
 }
 
 impl Annotation {
+    /// Register the resources of the annotation, like for example the font of a free text
+    /// annotation or the custom appearance of a stamp. Needs to be called when the annotation is added to a page, since fonts
+    /// are written before pages.
+    pub(crate) fn register(&mut self, sc: &mut SerializeContext) {
+        match &mut self.annotation_type {
+            AnnotationType::FreeText(f) => f.register(sc),
+            AnnotationType::Stamp(s) => s.register(sc),
+            _ => {}
+        }
+    }
+
+    /// Serialize the annotation. Returns the chunk of the annotation, as well as the ref of
+    /// its popup annotation, if it has one.
     pub(crate) fn serialize(
         &self,
         sc: &mut SerializeContext,
         root_ref: Ref,
         page_height: f32,
-    ) -> KrillaResult<Chunk> {
+    ) -> KrillaResult<(Chunk, Option<Ref>)> {
         let mut chunk = Chunk::new();
+        // Other objects the annotation depends on, like for example its appearance stream.
+        let mut extra = Chunk::new();
         let mut annotation = chunk
             .indirect(root_ref)
             .start::<pdf_writer::writers::Annotation>();
 
-        self.annotation_type
-            .serialize_type(sc, &mut annotation, page_height)?;
+        let popup_ref = self.annotation_type.serialize_type(
+            sc,
+            &mut annotation,
+            root_ref,
+            page_height,
+            self.opacity,
+            &mut extra,
+        )?;
+
+        // PDF/A requires annotations to be printable, so we set the print flag by default.
+        if !self.flags.print() || self.flags.hidden() || self.flags.no_view() {
+            sc.register_validation_error(ValidationError::InvalidAnnotationFlags);
+        }
+        annotation.pair(Name(b"F"), self.flags.0 as i32);
+
+        let blend_mode = if sc.serialize_settings().pdf_version() >= PdfVersion::Pdf20 {
+            self.blend_mode
+        } else {
+            BlendMode::Normal
+        };
+
+        if self.opacity != NormalizedF32::ONE || blend_mode != BlendMode::Normal {
+            sc.register_validation_error(ValidationError::Transparency(sc.location));
+        }
+
+        // Annotations with an opacity of their own already write it themselves.
+        if self.opacity != NormalizedF32::ONE && !self.annotation_type.has_opacity() {
+            annotation.pair(Name(b"CA"), self.opacity.get());
+        }
 
-        // Only required by PDF/A, but we always write this regardless.
-        annotation.flags(AnnotationFlags::PRINT);
+        if blend_mode != BlendMode::Normal {
+            annotation.pair(Name(b"BM"), blend_mode.to_pdf_name());
+        }
 
         if let Some(struct_parent) = self.struct_parent {
             annotation.struct_parent(struct_parent);
         }
 
         if let Some(alt_text) = &self.alt {
-            annotation.contents(TextStr(alt_text));
+            annotation.pair(Name(b"Contents"), sc.text_str(alt_text));
         } else {
             sc.register_validation_error(ValidationError::MissingAnnotationAltText);
         }
 
         annotation.finish();
+        chunk.extend(&extra);
 
-        Ok(chunk)
+        Ok((chunk, popup_ref))
     }
 }
 
@@ -86,19 +2317,119 @@ impl Annotation {
 pub enum AnnotationType {
     /// A link annotation.
     Link(LinkAnnotation),
+    /// A highlight annotation.
+    Highlight(HighlightAnnotation),
+    /// An underline annotation.
+    Underline(UnderlineAnnotation),
+    /// A strike-out annotation.
+    StrikeOut(StrikeOutAnnotation),
+    /// A squiggly-underline annotation.
+    Squiggly(SquigglyAnnotation),
+    /// A text annotation.
+    Text(TextAnnotation),
+    /// A free text annotation.
+    FreeText(FreeTextAnnotation),
+    /// An ink annotation.
+    Ink(InkAnnotation),
+    /// A stamp annotation.
+    Stamp(StampAnnotation),
+    /// A square annotation.
+    Square(SquareAnnotation),
+    /// A circle annotation.
+    Circle(CircleAnnotation),
+    /// A line annotation.
+    Line(LineAnnotation),
+    /// A polygon annotation.
+    Polygon(PolygonAnnotation),
+    /// A polyline annotation.
+    Polyline(PolylineAnnotation),
+    /// A file attachment annotation.
+    FileAttachment(FileAttachmentAnnotation),
 }
 
 impl AnnotationType {
+    /// Serialize the type-specific entries of the annotation, and write any objects it
+    /// depends on into `extra`. Returns the ref of the popup annotation, if one was created.
     fn serialize_type(
         &self,
         sc: &mut SerializeContext,
         annotation: &mut pdf_writer::writers::Annotation,
+        root_ref: Ref,
         page_height: f32,
-    ) -> KrillaResult<()> {
+        opacity: NormalizedF32,
+        extra: &mut Chunk,
+    ) -> KrillaResult<Option<Ref>> {
         match self {
-            AnnotationType::Link(l) => l.serialize_type(sc, annotation, page_height),
+            AnnotationType::Link(l) => {
+                l.serialize_type(sc, annotation, page_height)?;
+                Ok(None)
+            }
+            AnnotationType::Highlight(HighlightAnnotation(m))
+            | AnnotationType::Underline(UnderlineAnnotation(m))
+            | AnnotationType::StrikeOut(StrikeOutAnnotation(m))
+            | AnnotationType::Squiggly(SquigglyAnnotation(m)) => {
+                m.serialize_type(sc, annotation, page_height, opacity, extra);
+                Ok(None)
+            }
+            AnnotationType::Text(t) => Ok(Some(t.serialize_type(
+                sc,
+                annotation,
+                root_ref,
+                page_height,
+                extra,
+            ))),
+            AnnotationType::FreeText(f) => {
+                f.serialize_type(sc, annotation, page_height);
+                Ok(None)
+            }
+            AnnotationType::Ink(i) => {
+                i.serialize_type(sc, annotation, page_height, opacity, extra);
+                Ok(None)
+            }
+            AnnotationType::Stamp(s) => {
+                s.serialize_type(sc, annotation, page_height, opacity, extra);
+                Ok(None)
+            }
+            AnnotationType::Square(s) => {
+                s.serialize_type(sc, annotation, page_height, extra);
+                Ok(None)
+            }
+            AnnotationType::Circle(c) => {
+                c.serialize_type(sc, annotation, page_height, extra);
+                Ok(None)
+            }
+            AnnotationType::Line(l) => {
+                l.serialize_type(sc, annotation, page_height, extra);
+                Ok(None)
+            }
+            AnnotationType::Polygon(p) => {
+                p.serialize_type(sc, annotation, page_height, extra);
+                Ok(None)
+            }
+            AnnotationType::Polyline(p) => {
+                p.serialize_type(sc, annotation, page_height, extra);
+                Ok(None)
+            }
+            AnnotationType::FileAttachment(f) => {
+                f.serialize_type(sc, annotation, page_height, extra);
+                Ok(None)
+            }
         }
     }
+
+    /// Whether the annotation has an opacity of its own, which it combines with the
+    /// opacity of the annotation.
+    fn has_opacity(&self) -> bool {
+        matches!(
+            self,
+            AnnotationType::Highlight(_)
+                | AnnotationType::Underline(_)
+                | AnnotationType::StrikeOut(_)
+                | AnnotationType::Squiggly(_)
+                | AnnotationType::Ink(_)
+                | AnnotationType::Stamp(_)
+        )
+    }
 }
 
 /// An annotation target.
@@ -109,6 +2440,10 @@ pub enum Target {
     Action(Action),
 }
 
+/// A quadrilateral, given by its top left, top right, bottom left and bottom right
+/// point, relative to the direction of the text.
+pub type Quad = [Point; 4];
+
 /// A link annotation.
 pub struct LinkAnnotation {
     pub(crate) rect: Rect,
@@ -133,6 +2468,31 @@ impl LinkAnnotation {
         }
     }
 
+    /// Create a new link annotation that only covers the given quadrilaterals, for
+    /// example the fragments of a link that is broken across multiple lines.
+    ///
+    /// The bounding box of the link annotation is computed automatically from
+    /// the quadrilaterals. Note that viewers that don't support quad points will
+    /// activate the link in the whole bounding box instead.
+    ///
+    /// Panics if `quads` is empty.
+    pub fn new_with_quads(quads: Vec<Quad>, target: Target) -> Self {
+        assert!(
+            !quads.is_empty(),
+            "a link annotation needs to contain at least one quadrilateral"
+        );
+
+        let points = quads.into_iter().flatten().collect::<Vec<_>>();
+        let left = points.iter().map(|p| p.x).fold(f32::INFINITY, f32::min);
+        let top = points.iter().map(|p| p.y).fold(f32::INFINITY, f32::min);
+        let right = points.iter().map(|p| p.x).fold(f32::NEG_INFINITY, f32::max);
+        let bottom = points.iter().map(|p| p.y).fold(f32::NEG_INFINITY, f32::max);
+        let rect = Rect::from_ltrb(left, top, right, bottom)
+            .expect("the quadrilaterals need to have finite coordinates");
+
+        Self::new(rect, Some(points), target)
+    }
+
     fn serialize_type(
         &self,
         sc: &mut SerializeContext,
@@ -159,6 +2519,10 @@ impl LinkAnnotation {
         }
 
         match &self.target {
+            // Structure destinations need to be wrapped in a go-to action.
+            Target::Destination(destination) if destination.requires_action(sc) => {
+                Action::Goto(destination.clone()).serialize(sc, annotation.action())?
+            }
             Target::Destination(destination) => {
                 destination.serialize(sc, annotation.insert(Name(b"Dest")))?
             }
@@ -168,3 +2532,433 @@ impl LinkAnnotation {
         Ok(())
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextMarkupKind {
+    Highlight,
+    Underline,
+    StrikeOut,
+    Squiggly,
+}
+
+struct TextMarkup {
+    kind: TextMarkupKind,
+    quad_points: Vec<Quad>,
+    color: rgb::Color,
+    opacity: NormalizedF32,
+    contents: Option<String>,
+}
+
+impl TextMarkup {
+    fn serialize_type(
+        &self,
+        sc: &mut SerializeContext,
+        annotation: &mut pdf_writer::writers::Annotation,
+        page_height: f32,
+        opacity: NormalizedF32,
+        extra: &mut Chunk,
+    ) {
+        let opacity = combined_opacity(self.opacity, opacity);
+        annotation.subtype(match self.kind {
+            TextMarkupKind::Highlight => pdf_writer::types::AnnotationType::Highlight,
+            TextMarkupKind::Underline => pdf_writer::types::AnnotationType::Underline,
+            TextMarkupKind::StrikeOut => pdf_writer::types::AnnotationType::StrikeOut,
+            TextMarkupKind::Squiggly => pdf_writer::types::AnnotationType::Squiggly,
+        });
+
+        // Highlights are always drawn with the multiply blend mode.
+        if self.kind == TextMarkupKind::Highlight || opacity != NormalizedF32::ONE {
+            sc.register_validation_error(ValidationError::Transparency(sc.location));
+        }
+
+        // Convert the quadrilaterals into PDF coordinates.
+        let transform = page_root_transform(page_height).to_tsp();
+        let quads = self
+            .quad_points
+            .iter()
+            .map(|quad| {
+                quad.map(|p| {
+                    let mut p = p.to_tsp();
+                    transform.map_point(&mut p);
+                    (p.x, p.y)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut appearance = ColoredAppearance::new(sc, self.color, self.color);
+        let stroke_width = self.draw(&mut appearance.content, &quads);
+
+        // The bounding box needs to include the whole appearance, including the width of
+        // the strokes.
+        let rect = bounding_rect(quads.iter().flatten().copied(), stroke_width / 2.0);
+
+        annotation.rect(rect);
+        annotation.quad_points(quads.iter().flatten().flat_map(|p| [p.0, p.1]));
+        let color = self.color.to_pdf_color();
+        annotation.color_rgb(color[0], color[1], color[2]);
+        annotation.pair(Name(b"CA"), opacity.get());
+
+        // Multiply the color with the underlying text, so that it stays readable.
+        let blend_mode = (self.kind == TextMarkupKind::Highlight)
+            .then_some(pdf_writer::types::BlendMode::Multiply);
+        let appearance_ref = appearance.finish(sc, extra, rect, opacity, blend_mode);
+        annotation
+            .insert(Name(b"AP"))
+            .dict()
+            .pair(Name(b"N"), appearance_ref);
+    }
+
+    /// Draw the markup of the quadrilaterals and return the width of the strokes.
+    fn draw(&self, content: &mut Content, quads: &[[(f32, f32); 4]]) -> f32 {
+        let mut stroke_width: f32 = 0.0;
+
+        for quad in quads {
+            let [top_left, top_right, bottom_left, bottom_right] = *quad;
+            // The vectors along the baseline and from the bottom to the top of the quad.
+            let along = (
+                bottom_right.0 - bottom_left.0,
+                bottom_right.1 - bottom_left.1,
+            );
+            let up = (top_left.0 - bottom_left.0, top_left.1 - bottom_left.1);
+            let length = (along.0 * along.0 + along.1 * along.1).sqrt();
+            let height = (up.0 * up.0 + up.1 * up.1).sqrt();
+            // A point in the quad, given as fractions of its width and height.
+            let at = |s: f32, t: f32| {
+                (
+                    bottom_left.0 + s * along.0 + t * up.0,
+                    bottom_left.1 + s * along.1 + t * up.1,
+                )
+            };
+
+            match self.kind {
+                TextMarkupKind::Highlight => {
+                    content.move_to(top_left.0, top_left.1);
+                    content.line_to(top_right.0, top_right.1);
+                    content.line_to(bottom_right.0, bottom_right.1);
+                    content.line_to(bottom_left.0, bottom_left.1);
+                    content.close_path();
+                    content.fill_nonzero();
+                }
+                TextMarkupKind::Underline | TextMarkupKind::StrikeOut => {
+                    let width = height / 14.0;
+                    stroke_width = stroke_width.max(width);
+                    let t = if self.kind == TextMarkupKind::Underline {
+                        1.0 / 7.0
+                    } else {
+                        0.375
+                    };
+
+                    let (start, end) = (at(0.0, t), at(1.0, t));
+                    content.set_line_width(width);
+                    content.move_to(start.0, start.1);
+                    content.line_to(end.0, end.1);
+                    content.stroke();
+                }
+                TextMarkupKind::Squiggly => {
+                    let width = height / 20.0;
+                    stroke_width = stroke_width.max(width);
+                    // The length of one period of the wave, relative to the length of the quad.
+                    let period = if length > 0.0 && height > 0.0 {
+                        height / 4.0 / length
+                    } else {
+                        1.0
+                    };
+
+                    content.set_line_width(width);
+                    content.set_line_cap(LineCapStyle::RoundCap);
+                    let start = at(0.0, 0.0);
+                    content.move_to(start.0, start.1);
+
+                    let mut s = 0.0;
+                    let mut up_stroke = true;
+                    while s < 1.0 {
+                        s = (s + period / 2.0).min(1.0);
+                        let p = at(s, if up_stroke { 1.0 / 8.0 } else { 0.0 });
+                        content.line_to(p.0, p.1);
+                        up_stroke = !up_stroke;
+                    }
+
+                    content.stroke();
+                }
+            }
+        }
+
+        stroke_width
+    }
+}
+
+/// An appearance stream that we generate ourselves, which is drawn in a single color
+/// and with a constant opacity.
+struct ColoredAppearance {
+    content: Content,
+    color_space: MaybeDeviceColorSpace,
+}
+
+impl ColoredAppearance {
+    /// Start a new appearance with the given stroke and fill color.
+    fn new(sc: &mut SerializeContext, stroke_color: rgb::Color, fill_color: rgb::Color) -> Self {
+        let color_space =
+            sc.register_colorspace(rgb::color_space(sc.serialize_settings().no_device_cs));
+
+        let mut content = Content::new();
+        content.set_parameters(Name(b"gs0"));
+        let stroke_color = stroke_color.to_pdf_color();
+        let fill_color = fill_color.to_pdf_color();
+        match &color_space {
+            MaybeDeviceColorSpace::DeviceRgb => {
+                content.set_fill_rgb(fill_color[0], fill_color[1], fill_color[2]);
+                content.set_stroke_rgb(stroke_color[0], stroke_color[1], stroke_color[2]);
+            }
+            MaybeDeviceColorSpace::ColorSpace(_) => {
+                content.set_fill_color_space(Name(b"cs0"));
+                content.set_fill_color(fill_color);
+                content.set_stroke_color_space(Name(b"cs0"));
+                content.set_stroke_color(stroke_color);
+            }
+            _ => unreachable!(),
+        }
+
+        Self {
+            content,
+            color_space,
+        }
+    }
+
+    /// Write the appearance into `chunk` and return its ref.
+    fn finish(
+        self,
+        sc: &mut SerializeContext,
+        chunk: &mut Chunk,
+        bbox: pdf_writer::Rect,
+        opacity: NormalizedF32,
+        blend_mode: Option<pdf_writer::types::BlendMode>,
+    ) -> Ref {
+        let root_ref = sc.new_ref();
+        let content = self.content.finish();
+        sc.register_limits(content.limits());
+
+        let serialize_settings = sc.serialize_settings();
+        let encryptor = sc.encryptor();
+        let stream = FilterStreamBuilder::new_from_content_stream(&content, &serialize_settings)
+            .finish(&serialize_settings, encryptor);
+        let mut x_object = chunk.form_xobject(root_ref, stream.encoded_data());
+        stream.write_filters(x_object.deref_mut().deref_mut());
+        x_object.bbox(bbox);
+
+        let mut resources = x_object.resources();
+        let mut ext_g_states = resources.ext_g_states();
+        let mut ext_g_state = ext_g_states
+            .insert(Name(b"gs0"))
+            .start::<pdf_writer::writers::ExtGraphicsState>();
+        ext_g_state.non_stroking_alpha(opacity.get());
+        ext_g_state.stroking_alpha(opacity.get());
+        if let Some(blend_mode) = blend_mode {
+            ext_g_state.blend_mode(blend_mode);
+        }
+        ext_g_state.finish();
+        ext_g_states.finish();
+
+        if let MaybeDeviceColorSpace::ColorSpace(cs) = self.color_space {
+            resources.color_spaces().pair(Name(b"cs0"), cs.get_ref());
+        }
+
+        resources.finish();
+        x_object.finish();
+
+        root_ref
+    }
+}
+
+/// The bounding box of some points in PDF coordinates, extended by `padding` on each side.
+fn bounding_rect(
+    points: impl Iterator<Item = (f32, f32)> + Clone,
+    padding: f32,
+) -> pdf_writer::Rect {
+    let x_min = points.clone().map(|p| p.0).fold(f32::INFINITY, f32::min) - padding;
+    let y_min = points.clone().map(|p| p.1).fold(f32::INFINITY, f32::min) - padding;
+    let x_max = points
+        .clone()
+        .map(|p| p.0)
+        .fold(f32::NEG_INFINITY, f32::max)
+        + padding;
+    let y_max = points.map(|p| p.1).fold(f32::NEG_INFINITY, f32::max) + padding;
+
+    pdf_writer::Rect::new(x_min, y_min, x_max, y_max)
+}
+
+/// Combine the opacity of an annotation type with the opacity of the whole annotation.
+fn combined_opacity(a: NormalizedF32, b: NormalizedF32) -> NormalizedF32 {
+    NormalizedF32::new(a.get() * b.get()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interactive::annotation::{ending_size, LineEnding, PathSink, ARROW_HALF_ANGLE};
+
+    /// Records the end points of all segments of a path.
+    #[derive(Default)]
+    struct Recorder {
+        points: Vec<(f32, f32)>,
+        closed: bool,
+    }
+
+    impl PathSink for Recorder {
+        fn move_to(&mut self, x: f32, y: f32) {
+            self.points.push((x, y));
+        }
+
+        fn line_to(&mut self, x: f32, y: f32) {
+            self.points.push((x, y));
+        }
+
+        fn cubic_to(&mut self, _: f32, _: f32, _: f32, _: f32, x: f32, y: f32) {
+            self.points.push((x, y));
+        }
+
+        fn close(&mut self) {
+            self.closed = true;
+        }
+    }
+
+    const ALL_ENDINGS: [LineEnding; 9] = [
+        LineEnding::OpenArrow,
+        LineEnding::ClosedArrow,
+        LineEnding::ROpenArrow,
+        LineEnding::RClosedArrow,
+        LineEnding::Square,
+        LineEnding::Circle,
+        LineEnding::Diamond,
+        LineEnding::Butt,
+        LineEnding::Slash,
+    ];
+
+    /// Lines with a length of 50 starting at the same point, at various angles
+    /// that are not multiples of 90 degrees.
+    fn lines() -> impl Iterator<Item = ((f32, f32), (f32, f32))> {
+        (0..16).map(|i| {
+            let (sin, cos) = (i as f32 * 23.0 + 7.0).to_radians().sin_cos();
+            let from = (10.0, 20.0);
+            (from, (from.0 + 50.0 * cos, from.1 + 50.0 * sin))
+        })
+    }
+
+    fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+        ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+    }
+
+    /// The cosine of the angle between the vector from `a` to `b` and `direction`.
+    fn cos_angle(a: (f32, f32), b: (f32, f32), direction: (f32, f32)) -> f32 {
+        let v = (b.0 - a.0, b.1 - a.1);
+        (v.0 * direction.0 + v.1 * direction.1) / distance(a, b) / distance((0.0, 0.0), direction)
+    }
+
+    fn assert_approx(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3, "{a} != {b}");
+    }
+
+    #[test]
+    fn line_ending_arrows_at_angles() {
+        for (from, tip) in lines() {
+            let direction = (tip.0 - from.0, tip.1 - from.1);
+            let backwards = (-direction.0, -direction.1);
+
+            for (ending, along) in [
+                (LineEnding::OpenArrow, backwards),
+                (LineEnding::ClosedArrow, backwards),
+                (LineEnding::ROpenArrow, direction),
+                (LineEnding::RClosedArrow, direction),
+            ] {
+                let mut recorder = Recorder::default();
+                assert!(ending.add_path(&mut recorder, from, tip, 2.0));
+                assert_eq!(recorder.closed, ending.is_closed());
+
+                let [left, apex, right] = recorder.points[..] else {
+                    panic!("expected three points for {ending:?}");
+                };
+                assert_approx(distance(apex, tip), 0.0);
+
+                // Both sides have the same length and enclose the same angle with the line,
+                // on different sides of it.
+                for side in [left, right] {
+                    assert_approx(distance(tip, side), ending_size(2.0));
+                    assert_approx(cos_angle(tip, side, along), ARROW_HALF_ANGLE.cos());
+                }
+                assert_approx(
+                    distance(left, right),
+                    2.0 * ending_size(2.0) * ARROW_HALF_ANGLE.sin(),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn line_ending_shapes_at_angles() {
+        for (from, tip) in lines() {
+            let direction = (tip.0 - from.0, tip.1 - from.1);
+            let half = ending_size(1.0) / 2.0;
+
+            let mut recorder = Recorder::default();
+            LineEnding::Square.add_path(&mut recorder, from, tip, 1.0);
+            let points = &recorder.points;
+            assert_eq!(points.len(), 4);
+            // The sides of the square are parallel and perpendicular to the line.
+            assert_approx(cos_angle(points[0], points[1], direction), 1.0);
+            assert_approx(cos_angle(points[1], points[2], direction), 0.0);
+            for p in points {
+                assert_approx(distance(*p, tip), half * 2.0_f32.sqrt());
+            }
+
+            let mut recorder = Recorder::default();
+            LineEnding::Diamond.add_path(&mut recorder, from, tip, 1.0);
+            assert_approx(
+                cos_angle(recorder.points[2], recorder.points[0], direction),
+                -1.0,
+            );
+            for p in &recorder.points {
+                assert_approx(distance(*p, tip), half);
+            }
+
+            let mut recorder = Recorder::default();
+            LineEnding::Butt.add_path(&mut recorder, from, tip, 1.0);
+            assert_approx(
+                cos_angle(recorder.points[0], recorder.points[1], direction),
+                0.0,
+            );
+
+            let mut recorder = Recorder::default();
+            LineEnding::Slash.add_path(&mut recorder, from, tip, 1.0);
+            assert_approx(
+                cos_angle(recorder.points[0], recorder.points[1], direction).abs(),
+                0.5,
+            );
+        }
+    }
+
+    #[test]
+    fn line_ending_within_extent() {
+        for (from, tip) in lines() {
+            for ending in ALL_ENDINGS {
+                for width in [0.5, 1.0, 3.0] {
+                    let mut recorder = Recorder::default();
+                    ending.add_path(&mut recorder, from, tip, width);
+
+                    for p in recorder.points {
+                        assert!(distance(p, tip) + width / 2.0 <= ending.extent(width));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn line_ending_degenerate_line() {
+        for ending in ALL_ENDINGS {
+            let mut recorder = Recorder::default();
+            assert!(!ending.add_path(&mut recorder, (10.0, 10.0), (10.0, 10.0), 1.0));
+            assert!(recorder.points.is_empty());
+        }
+
+        let mut recorder = Recorder::default();
+        assert!(!LineEnding::None.add_path(&mut recorder, (0.0, 0.0), (10.0, 10.0), 1.0));
+    }
+}
@@ -4,44 +4,257 @@
 //! example when defining the outline, or when link to a different section in the document
 //! from a link. To achieve this, you can use destinations, which are associated with a page
 //! and a specific location on that page.
+//!
+//! Apart from [`XyzDestination`], which jumps to a specific point, there are destinations
+//! that control how the target page is magnified: [`FitDestination`] fits the whole page,
+//! [`FitHDestination`] and [`FitVDestination`] fit its width or height, and
+//! [`FitRDestination`] fits a rectangle on the page into the window. As everywhere else
+//! in krilla, all coordinates are relative to the top-left corner of the page.
+//!
+//! In PDF 2.0, a [`StructureDestination`] can additionally point to an element of the
+//! tag tree, so that it stays valid even if the document is reflowed.
 
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 
-use pdf_writer::{Chunk, Obj, Ref, Str};
+use pdf_writer::{Chunk, Finish, Name, Null, Obj, Ref};
 use tiny_skia_path::Transform;
 
+use crate::configure::PdfVersion;
 use crate::error::KrillaResult;
-use crate::geom::Point;
+use crate::geom::{Point, Rect};
+use crate::interchange::tagging::{Identifier, IdentifierInner};
 use crate::serialize::SerializeContext;
 
 /// The type of destination.
-#[derive(Hash)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum Destination {
     /// An XYZ destination.
     Xyz(XyzDestination),
+    /// A destination that fits the whole page into the window.
+    Fit(FitDestination),
+    /// A destination that fits the width of the page into the window.
+    FitH(FitHDestination),
+    /// A destination that fits the height of the page into the window.
+    FitV(FitVDestination),
+    /// A destination that fits a rectangle of the page into the window.
+    FitR(FitRDestination),
     /// A named destination.
     Named(NamedDestination),
+    /// A destination pointing to an element of the tag tree.
+    Structure(StructureDestination),
 }
 
 impl Destination {
     pub(crate) fn serialize(&self, sc: &mut SerializeContext, buffer: Obj) -> KrillaResult<()> {
         match self {
-            Destination::Xyz(xyz) => {
-                let ref_ = sc.register_xyz_destination(xyz.clone());
+            Destination::Named(named) => named.serialize(sc, buffer),
+            // Structure destinations can only be written in go-to actions, so in all
+            // other places, we write the fallback instead.
+            Destination::Structure(structure) => structure.fallback.serialize(sc, buffer),
+            _ => {
+                let ref_ = sc.register_page_destination(self.clone());
                 buffer.primitive(ref_);
                 Ok(())
             }
-            Destination::Named(named) => named.serialize(sc, buffer),
         }
     }
+
+    /// Whether the destination can only be written as part of a go-to action.
+    pub(crate) fn requires_action(&self, sc: &SerializeContext) -> bool {
+        matches!(self, Destination::Structure(structure) if structure.is_written(sc))
+    }
+
+    /// Serialize a destination that directly points to a page.
+    pub(crate) fn serialize_page_destination(
+        &self,
+        sc: &mut SerializeContext,
+        root_ref: Ref,
+    ) -> KrillaResult<Chunk> {
+        if let Destination::Structure(structure) = self {
+            return structure.fallback.serialize_page_destination(sc, root_ref);
+        }
+
+        self.serialize_explicit(sc, root_ref, None)
+    }
+
+    /// Serialize a destination that points to a page, or to the structure element
+    /// `struct_elem` while using the view of the page destination.
+    fn serialize_explicit(
+        &self,
+        sc: &mut SerializeContext,
+        root_ref: Ref,
+        struct_elem: Option<Ref>,
+    ) -> KrillaResult<Chunk> {
+        let page_index = match self {
+            Destination::Xyz(xyz) => xyz.0.page_index,
+            Destination::Fit(fit) => fit.page_index,
+            Destination::FitH(fit_h) => fit_h.page_index,
+            Destination::FitV(fit_v) => fit_v.page_index,
+            Destination::FitR(fit_r) => fit_r.page_index,
+            Destination::Named(_) => unreachable!("named destinations don't point to a page"),
+            Destination::Structure(_) => {
+                unreachable!("structure destinations are written separately")
+            }
+        };
+
+        let page_info = sc
+            .page_at(page_index)
+            .map(|i| &sc.page_infos()[i])
+            .unwrap_or_else(|| {
+                panic!(
+                    "attempted to link to page {}, but document only has {} pages",
+                    page_index + 1,
+                    sc.num_pages()
+                )
+            });
+        let target = struct_elem.unwrap_or(page_info.ref_);
+        let page_size = page_info.surface_size.height();
+
+        // Convert to PDF coordinates
+        let invert_transform = Transform::from_row(1.0, 0.0, 0.0, -1.0, 0.0, page_size);
+        let map_point = |x: f32, y: f32| {
+            let mut point = tiny_skia_path::Point::from_xy(x, y);
+            invert_transform.map_point(&mut point);
+            point
+        };
+
+        let mut chunk = Chunk::new();
+
+        if let Destination::Xyz(xyz) = self {
+            // pdf-writer doesn't allow leaving the coordinates unspecified, so
+            // we write the array manually.
+            let mut array = chunk.indirect(root_ref).array();
+            array.item(target).item(Name(b"XYZ"));
+
+            match xyz.0.left {
+                Some(left) => array.item(left),
+                None => array.item(Null),
+            };
+            match xyz.0.top {
+                Some(top) => array.item(map_point(0.0, top).y),
+                None => array.item(Null),
+            };
+            match xyz.0.zoom {
+                Some(zoom) => array.item(zoom),
+                None => array.item(Null),
+            };
+
+            array.finish();
+            return Ok(chunk);
+        }
+
+        let destination = chunk.destination(root_ref).page(target);
+
+        match self {
+            Destination::Xyz(_) => unreachable!(),
+            Destination::Fit(_) => destination.fit(),
+            Destination::FitH(fit_h) => destination.fit_horizontal(map_point(0.0, fit_h.top).y),
+            Destination::FitV(fit_v) => destination.fit_vertical(map_point(fit_v.left, 0.0).x),
+            Destination::FitR(fit_r) => {
+                let top_left = map_point(fit_r.rect.left(), fit_r.rect.top());
+                let bottom_right = map_point(fit_r.rect.right(), fit_r.rect.bottom());
+                destination.fit_rect(pdf_writer::Rect::new(
+                    top_left.x,
+                    bottom_right.y,
+                    bottom_right.x,
+                    top_left.y,
+                ));
+            }
+            Destination::Named(_) | Destination::Structure(_) => unreachable!(),
+        }
+
+        Ok(chunk)
+    }
+}
+
+/// A destination pointing to the element of the tag tree that contains some
+/// content or an annotation.
+///
+/// Since structure destinations don't depend on where the content is located on
+/// the page, they are better suited for documents that are reflowed, for example
+/// when they are viewed on a small screen. Structure destinations require PDF 2.0
+/// and an enabled tagging. Otherwise, only the fallback destination is written,
+/// which is also used by viewers that don't support structure destinations.
+///
+/// Note that structure destinations are only written for links and outline entries
+/// that directly use them, and for go-to actions. When they are used as part of a
+/// named destination, only the fallback destination is written.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct StructureDestination {
+    identifier: Identifier,
+    fallback: Arc<Destination>,
+}
+
+impl From<StructureDestination> for Destination {
+    fn from(val: StructureDestination) -> Self {
+        Destination::Structure(val)
+    }
+}
+
+impl StructureDestination {
+    /// Create a new structure destination that points to the element of the tag tree
+    /// that contains the content or annotation with the given identifier, as returned
+    /// by [`Surface::start_tagged`] and [`Page::add_tagged_annotation`].
+    ///
+    /// The view of the fallback destination, like the zoom factor of an XYZ
+    /// destination, is also used for the structure destination. The identifier needs
+    /// to appear in the tag tree of the document.
+    ///
+    /// # Panics
+    /// Panics if `fallback` is a named or structure destination.
+    ///
+    /// [`Surface::start_tagged`]: crate::surface::Surface::start_tagged
+    /// [`Page::add_tagged_annotation`]: crate::page::Page::add_tagged_annotation
+    pub fn new(identifier: Identifier, fallback: impl Into<Destination>) -> Self {
+        let fallback = fallback.into();
+        assert!(
+            !matches!(fallback, Destination::Named(_) | Destination::Structure(_)),
+            "the fallback of a structure destination must point to a page"
+        );
+
+        Self {
+            identifier,
+            fallback: Arc::new(fallback),
+        }
+    }
+
+    /// Whether the structure destination is written, or whether only the fallback
+    /// destination is used.
+    pub(crate) fn is_written(&self, sc: &SerializeContext) -> bool {
+        sc.serialize_settings().pdf_version() >= PdfVersion::Pdf20
+            && matches!(self.identifier.0, IdentifierInner::Real(_))
+    }
+
+    /// The identifier of the content the destination points to.
+    pub(crate) fn identifier(&self) -> Identifier {
+        self.identifier
+    }
+
+    pub(crate) fn serialize(
+        &self,
+        sc: &mut SerializeContext,
+        root_ref: Ref,
+        struct_elem: Ref,
+    ) -> KrillaResult<Chunk> {
+        self.fallback
+            .serialize_explicit(sc, root_ref, Some(struct_elem))
+    }
 }
 
 /// A destination associated with a name.
+///
+/// A named destination can either be created together with the destination it
+/// points to, or it can only reference a name, in which case the actual destination
+/// needs to be added via [`Document::add_named_destination`]. This makes it possible
+/// to link to destinations that are not known yet, for example because the page they
+/// point to hasn't been created yet.
+///
+/// [`Document::add_named_destination`]: crate::Document::add_named_destination
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub struct NamedDestination {
     pub(crate) name: Arc<String>,
-    pub(crate) xyz_dest: Arc<XyzDestination>,
+    pub(crate) destination: Option<Arc<Destination>>,
 }
 
 impl From<NamedDestination> for Destination {
@@ -51,15 +264,38 @@ impl From<NamedDestination> for Destination {
 }
 
 impl NamedDestination {
-    /// Create a new named destination.
-    /// Note that named destinations need to be added via
-    /// `add_named_destination` on [`Document`] when being used!
+    /// Create a new named destination that points to `destination`.
+    ///
+    /// The destination will automatically be added to the document once the
+    /// named destination is used.
     ///
-    /// [`Document`]: crate::Document
-    pub fn new(name: String, xyz_dest: XyzDestination) -> Self {
+    /// # Panics
+    /// Panics if `destination` is a named destination itself.
+    pub fn new(name: String, destination: impl Into<Destination>) -> Self {
+        let destination = destination.into();
+        assert!(
+            !matches!(destination, Destination::Named(_)),
+            "a named destination cannot point to another named destination"
+        );
+
         Self {
             name: Arc::new(name),
-            xyz_dest: Arc::new(xyz_dest),
+            destination: Some(Arc::new(destination)),
+        }
+    }
+
+    /// Create a new named destination that only references a name.
+    ///
+    /// A destination with the same name needs to be added via
+    /// [`Document::add_named_destination`] before finishing the document, otherwise
+    /// export will fail with [`KrillaError::UnresolvedNamedDestinations`].
+    ///
+    /// [`Document::add_named_destination`]: crate::Document::add_named_destination
+    /// [`KrillaError::UnresolvedNamedDestinations`]: crate::error::KrillaError::UnresolvedNamedDestinations
+    pub fn by_name(name: String) -> Self {
+        Self {
+            name: Arc::new(name),
+            destination: None,
         }
     }
 
@@ -68,31 +304,39 @@ impl NamedDestination {
         sc: &mut SerializeContext,
         destination: Obj,
     ) -> KrillaResult<()> {
-        sc.register_named_destination(self.clone());
-        destination.primitive(Str(self.name.as_bytes()));
+        match &self.destination {
+            Some(dest) => sc.register_named_destination((*self.name).clone(), (**dest).clone()),
+            None => sc.register_named_destination_reference((*self.name).clone()),
+        }
+
+        destination.primitive(sc.str(self.name.as_bytes()));
         Ok(())
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct XyzDestRepr {
     page_index: usize,
-    point: Point,
+    left: Option<f32>,
+    top: Option<f32>,
+    zoom: Option<f32>,
 }
 
 impl Hash for XyzDestRepr {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.page_index.hash(state);
-        self.point.x.to_bits().hash(state);
-        self.point.y.to_bits().hash(state);
+        self.left.map(f32::to_bits).hash(state);
+        self.top.map(f32::to_bits).hash(state);
+        self.zoom.map(f32::to_bits).hash(state);
     }
 }
 
 impl PartialEq for XyzDestRepr {
     fn eq(&self, other: &Self) -> bool {
         self.page_index == other.page_index
-            && self.point.x == other.point.x
-            && self.point.y == other.point.y
+            && self.left == other.left
+            && self.top == other.top
+            && self.zoom == other.zoom
     }
 }
 
@@ -112,37 +356,178 @@ impl XyzDestination {
     /// Create a new XYZ destination. `page_index` should be the index (i.e. number) of the
     /// target page, and point indicates the specific location on that page that should be
     /// targeted. If the `page_index` is out of range, export will fail gracefully.
+    ///
+    /// By default, the viewer keeps its current zoom factor when jumping to the destination.
     pub fn new(page_index: usize, point: Point) -> Self {
-        Self(Arc::new(XyzDestRepr { page_index, point }))
+        Self(Arc::new(XyzDestRepr {
+            page_index,
+            left: Some(point.x),
+            top: Some(point.y),
+            zoom: None,
+        }))
     }
 
-    pub(crate) fn serialize(
-        &self,
-        sc: &mut SerializeContext,
-        root_ref: Ref,
-    ) -> KrillaResult<Chunk> {
-        let mut chunk = Chunk::new();
-        let destination = chunk.destination(root_ref);
-
-        let page_info = sc.page_infos().get(self.0.page_index).unwrap_or_else(|| {
-            panic!(
-                "attempted to link to page {}, but document only has {} pages",
-                self.0.page_index + 1,
-                sc.page_infos().len()
-            )
-        });
-        let page_ref = page_info.ref_;
-        let page_size = page_info.surface_size.height();
+    /// Set the zoom factor the viewer should use when jumping to the destination,
+    /// where `1.0` corresponds to 100%. `None` means that the viewer should keep its
+    /// current zoom factor.
+    pub fn with_zoom(mut self, zoom: Option<f32>) -> Self {
+        Arc::make_mut(&mut self.0).zoom = zoom;
+        self
+    }
 
-        let mut mapped_point = self.0.point.to_tsp();
-        // Convert to PDF coordinates
-        let invert_transform = Transform::from_row(1.0, 0.0, 0.0, -1.0, 0.0, page_size);
-        invert_transform.map_point(&mut mapped_point);
+    /// Set the horizontal coordinate that should be positioned at the left edge of the
+    /// window. `None` means that the viewer should keep its current horizontal position.
+    pub fn with_left(mut self, left: Option<f32>) -> Self {
+        Arc::make_mut(&mut self.0).left = left;
+        self
+    }
 
-        destination
-            .page(page_ref)
-            .xyz(mapped_point.x, mapped_point.y, None);
+    /// Set the vertical coordinate that should be positioned at the top edge of the
+    /// window. `None` means that the viewer should keep its current vertical position.
+    pub fn with_top(mut self, top: Option<f32>) -> Self {
+        Arc::make_mut(&mut self.0).top = top;
+        self
+    }
+}
 
-        Ok(chunk)
+/// A destination that displays a whole page, with its contents magnified just
+/// enough to fit the entire page into the window.
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub struct FitDestination {
+    page_index: usize,
+}
+
+impl From<FitDestination> for Destination {
+    fn from(val: FitDestination) -> Self {
+        Destination::Fit(val)
+    }
+}
+
+impl FitDestination {
+    /// Create a new fit destination. `page_index` should be the index (i.e. number) of the
+    /// target page. If the `page_index` is out of range, export will fail gracefully.
+    pub fn new(page_index: usize) -> Self {
+        Self { page_index }
+    }
+}
+
+/// A destination that displays a page with the vertical coordinate `top` positioned at
+/// the top edge of the window, with its contents magnified just enough to fit the entire
+/// width of the page into the window.
+#[derive(Clone, Copy, Debug)]
+pub struct FitHDestination {
+    page_index: usize,
+    top: f32,
+}
+
+impl Hash for FitHDestination {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.page_index.hash(state);
+        self.top.to_bits().hash(state);
+    }
+}
+
+impl PartialEq for FitHDestination {
+    fn eq(&self, other: &Self) -> bool {
+        self.page_index == other.page_index && self.top.to_bits() == other.top.to_bits()
+    }
+}
+
+impl Eq for FitHDestination {}
+
+impl From<FitHDestination> for Destination {
+    fn from(val: FitHDestination) -> Self {
+        Destination::FitH(val)
+    }
+}
+
+impl FitHDestination {
+    /// Create a new FitH destination. `page_index` should be the index (i.e. number) of the
+    /// target page, and `top` is the vertical coordinate on that page that should be positioned
+    /// at the top edge of the window. If the `page_index` is out of range, export will
+    /// fail gracefully.
+    pub fn new(page_index: usize, top: f32) -> Self {
+        Self { page_index, top }
+    }
+}
+
+/// A destination that displays a page with the horizontal coordinate `left` positioned at
+/// the left edge of the window, with its contents magnified just enough to fit the entire
+/// height of the page into the window.
+#[derive(Clone, Copy, Debug)]
+pub struct FitVDestination {
+    page_index: usize,
+    left: f32,
+}
+
+impl Hash for FitVDestination {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.page_index.hash(state);
+        self.left.to_bits().hash(state);
+    }
+}
+
+impl PartialEq for FitVDestination {
+    fn eq(&self, other: &Self) -> bool {
+        self.page_index == other.page_index && self.left.to_bits() == other.left.to_bits()
+    }
+}
+
+impl Eq for FitVDestination {}
+
+impl From<FitVDestination> for Destination {
+    fn from(val: FitVDestination) -> Self {
+        Destination::FitV(val)
+    }
+}
+
+impl FitVDestination {
+    /// Create a new FitV destination. `page_index` should be the index (i.e. number) of the
+    /// target page, and `left` is the horizontal coordinate on that page that should be positioned
+    /// at the left edge of the window. If the `page_index` is out of range, export will
+    /// fail gracefully.
+    pub fn new(page_index: usize, left: f32) -> Self {
+        Self { page_index, left }
+    }
+}
+
+/// A destination that displays a page with its contents magnified just enough
+/// to fit the given rectangle into the window.
+#[derive(Clone, Copy, Debug)]
+pub struct FitRDestination {
+    page_index: usize,
+    rect: Rect,
+}
+
+impl Hash for FitRDestination {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.page_index.hash(state);
+        self.rect.left().to_bits().hash(state);
+        self.rect.top().to_bits().hash(state);
+        self.rect.right().to_bits().hash(state);
+        self.rect.bottom().to_bits().hash(state);
+    }
+}
+
+impl PartialEq for FitRDestination {
+    fn eq(&self, other: &Self) -> bool {
+        self.page_index == other.page_index && self.rect == other.rect
+    }
+}
+
+impl Eq for FitRDestination {}
+
+impl From<FitRDestination> for Destination {
+    fn from(val: FitRDestination) -> Self {
+        Destination::FitR(val)
+    }
+}
+
+impl FitRDestination {
+    /// Create a new FitR destination. `page_index` should be the index (i.e. number) of the
+    /// target page, and `rect` is the area on that page that should be fitted into the window.
+    /// If the `page_index` is out of range, export will fail gracefully.
+    pub fn new(page_index: usize, rect: Rect) -> Self {
+        Self { page_index, rect }
     }
 }
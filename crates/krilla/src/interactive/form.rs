@@ -0,0 +1,1401 @@
+//! Interactive forms.
+//!
+//! PDF allows you to add interactive form fields to a document, which the user can then, for
+//! example, fill in with a PDF viewer. Each form field is associated with a region on a
+//! page (a so-called widget annotation).
+//!
+//! krilla currently supports the following kinds of fields:
+//! - Text fields, which allow the user to enter text. You can add them by calling
+//!   [`Document::add_text_field`].
+//! - Choice fields, which allow the user to choose from a list of options, either in the form
+//!   of a combo box or a list box. You can add them by calling [`Document::add_choice_field`].
+//! - Check boxes, which can be toggled on and off. You can add them by calling
+//!   [`Document::add_check_box`].
+//! - Radio groups, which allow the user to choose one of multiple radio buttons. You can add
+//!   them by calling [`Document::add_radio_group`].
+//! - Push buttons, which perform an action when clicked, like for example submitting or
+//!   resetting the form. You can add them by calling [`Document::add_push_button`].
+//! - Signature fields, which reserve space for a digital signature that can be added after the
+//!   file has been created, for example by an external signing service.
+//!
+//! You can add a signature field by calling [`Document::add_signature_field`]
+//! and then finishing the document with [`Document::finish_with_signatures`], which will return
+//! the location of the placeholder in the file along with any warnings, so that the signature
+//! can be spliced in afterwards. By calling [`SignatureField::appearance`], you can additionally draw a visible
+//! appearance for the signature, like for example the name of the signer.
+//!
+//! [`Document::add_text_field`]: crate::Document::add_text_field
+//! [`Document::add_choice_field`]: crate::Document::add_choice_field
+//! [`Document::add_check_box`]: crate::Document::add_check_box
+//! [`Document::add_radio_group`]: crate::Document::add_radio_group
+//! [`Document::add_push_button`]: crate::Document::add_push_button
+//! [`Document::add_signature_field`]: crate::Document::add_signature_field
+//! [`Document::finish_with_signatures`]: crate::Document::finish_with_signatures
+
+use std::ops::{DerefMut, Range};
+
+use pdf_writer::types::AnnotationFlags;
+use pdf_writer::{Chunk, Content, Dict, Finish, Name, Ref, Str};
+use skrifa::MetadataProvider;
+
+use crate::color::luma;
+use crate::content::ContentBuilder;
+use crate::error::KrillaResult;
+use crate::geom::{PathBuilder, Point, Rect, Size, KAPPA};
+use crate::graphics::paint::Fill;
+use crate::graphics::xobject::XObject;
+use crate::interactive::action::Action;
+use crate::page::page_root_transform;
+use crate::resource::Resource;
+use crate::serialize::SerializeContext;
+use crate::stream::{FilterStreamBuilder, Stream};
+use crate::surface::Surface;
+use crate::text::cid::Cid;
+#[cfg(feature = "simple-text")]
+use crate::text::shape::{naive_shape, TextDirection};
+use crate::text::{Font, GlyphId, KrillaGlyph};
+
+/// The placeholder we write for the `ByteRange` entry. The actual values can only be
+/// determined once the whole file has been written, so we reserve enough space for
+/// files up to 2GB.
+const BYTE_RANGE_PLACEHOLDER: &[u8] = b"[0 2147483647 2147483647 2147483647]";
+
+/// A text field.
+#[derive(Debug, Clone)]
+pub struct TextField {
+    /// The name of the field, which must be unique and must not contain a period.
+    pub name: String,
+    /// The index of the page the field should be placed on.
+    pub page_index: usize,
+    /// The region of the field on the page.
+    pub rect: Rect,
+    /// The value the field should have by default.
+    pub default_value: Option<String>,
+    /// The maximum number of characters the field can hold. A longer default value
+    /// is truncated.
+    pub max_len: Option<u32>,
+    /// Whether the field can contain multiple lines of text.
+    pub multiline: bool,
+    /// Whether the field should be divided into `max_len` equally spaced positions, each
+    /// holding one character. Only has an effect if `max_len` is set and the field is
+    /// not multiline.
+    pub comb: bool,
+    /// The font that should be used for the text of the field. It needs to be a TrueType
+    /// or CFF font, otherwise exporting the document will fail.
+    pub font: Font,
+    /// The font size that should be used for the text of the field.
+    pub font_size: f32,
+}
+
+/// The settings of a signature field.
+#[derive(Debug, Clone)]
+pub struct SignatureFieldSettings {
+    /// The name of the field, which must be unique and must not contain a period.
+    pub name: String,
+    /// The index of the page the field should be placed on.
+    pub page_index: usize,
+    /// The region of the field on the page. Use a zero-sized rectangle
+    /// to create an invisible signature.
+    pub rect: Rect,
+    /// The number of bytes that should be reserved for the signature. It needs to be large
+    /// enough to hold the whole DER-encoded PKCS#7 blob.
+    pub reserved_size: usize,
+}
+
+/// A signature field.
+///
+/// You cannot create an instance of this type yourself. Instead, you should use the
+/// [`Document::add_signature_field`] method to add a new signature field to a document.
+/// The field will be added to the document once it is dropped.
+///
+/// [`Document::add_signature_field`]: crate::Document::add_signature_field
+pub struct SignatureField<'a> {
+    sc: &'a mut SerializeContext,
+    settings: Option<SignatureFieldSettings>,
+    appearance: Option<(Stream, Size)>,
+}
+
+impl<'a> SignatureField<'a> {
+    pub(crate) fn new(sc: &'a mut SerializeContext, settings: SignatureFieldSettings) -> Self {
+        Self {
+            sc,
+            settings: Some(settings),
+            appearance: None,
+        }
+    }
+
+    /// Get the surface to draw the visible appearance of the signature on. The coordinate
+    /// system of the surface starts at the top-left of the appearance and spans `size`, and
+    /// viewers will scale it so that it fits into the rectangle of the field. Calling this
+    /// multiple times will reset any previous drawings.
+    pub fn appearance(&mut self, size: Size) -> Surface {
+        appearance_surface(self.sc, &mut self.appearance, size)
+    }
+
+    /// A shorthand for `std::mem::drop`.
+    pub fn finish(self) {}
+}
+
+impl Drop for SignatureField<'_> {
+    fn drop(&mut self) {
+        if let Some(settings) = self.settings.take() {
+            self.sc
+                .register_form_field(InternalField::Signature(InternalSignatureField {
+                    settings,
+                    appearance: self.appearance.take(),
+                }));
+        }
+    }
+}
+
+/// An option of a [`ChoiceField`].
+#[derive(Debug, Clone)]
+pub struct ChoiceOption {
+    /// The value of the field when this option is selected.
+    pub export_value: String,
+    /// The text that should be displayed for this option. If `None`, the export value
+    /// will be displayed.
+    pub label: Option<String>,
+}
+
+/// The kind of a [`ChoiceField`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChoiceKind {
+    /// A drop-down list of options.
+    ComboBox {
+        /// Whether the user can enter a custom value, in addition to choosing one of the
+        /// options.
+        editable: bool,
+    },
+    /// A scrollable list of options.
+    ListBox {
+        /// Whether the user can select more than one option.
+        multi_select: bool,
+    },
+}
+
+/// A choice field, which allows the user to choose from a list of options.
+#[derive(Debug, Clone)]
+pub struct ChoiceField {
+    /// The name of the field, which must be unique and must not contain a period.
+    pub name: String,
+    /// The index of the page the field should be placed on.
+    pub page_index: usize,
+    /// The region of the field on the page.
+    pub rect: Rect,
+    /// The kind of the field.
+    pub kind: ChoiceKind,
+    /// The options of the field.
+    pub options: Vec<ChoiceOption>,
+    /// The export values of the options that should be selected by default. Only multi-select
+    /// list boxes can have more than one selected option.
+    pub selected: Vec<String>,
+    /// The font that should be used for the text of the field. It needs to be a TrueType
+    /// or CFF font, otherwise exporting the document will fail.
+    pub font: Font,
+    /// The font size that should be used for the text of the field.
+    pub font_size: f32,
+}
+
+/// The settings of a check box.
+#[derive(Debug, Clone)]
+pub struct CheckBoxSettings {
+    /// The name of the field, which must be unique and must not contain a period.
+    pub name: String,
+    /// The index of the page the check box should be placed on.
+    pub page_index: usize,
+    /// The region of the check box on the page.
+    pub rect: Rect,
+    /// The value of the field when the check box is checked. Must not be `Off`, otherwise
+    /// exporting the document will fail.
+    pub export_value: String,
+    /// Whether the check box should be checked initially.
+    pub checked: bool,
+}
+
+/// A check box.
+///
+/// You cannot create an instance of this type yourself. Instead, you should use the
+/// [`Document::add_check_box`] method to add a new check box to a document.
+/// The check box will be added to the document once it is dropped. Unless you draw
+/// custom appearances, krilla will draw a simple check mark for the checked state.
+///
+/// [`Document::add_check_box`]: crate::Document::add_check_box
+pub struct CheckBox<'a> {
+    sc: &'a mut SerializeContext,
+    settings: Option<CheckBoxSettings>,
+    on_appearance: Option<(Stream, Size)>,
+    off_appearance: Option<(Stream, Size)>,
+}
+
+impl<'a> CheckBox<'a> {
+    pub(crate) fn new(sc: &'a mut SerializeContext, settings: CheckBoxSettings) -> Self {
+        if settings.export_value == "Off" {
+            sc.register_invalid_export_value(settings.name.clone());
+        }
+
+        Self {
+            sc,
+            settings: Some(settings),
+            on_appearance: None,
+            off_appearance: None,
+        }
+    }
+
+    /// Get the surface to draw the appearance of the checked state on. The coordinate
+    /// system of the surface starts at the top-left of the appearance and spans `size`, and
+    /// viewers will scale it so that it fits into the rectangle of the check box. Calling this
+    /// multiple times will reset any previous drawings.
+    pub fn on_appearance(&mut self, size: Size) -> Surface {
+        appearance_surface(self.sc, &mut self.on_appearance, size)
+    }
+
+    /// Get the surface to draw the appearance of the unchecked state on. See
+    /// [`CheckBox::on_appearance`] for more information.
+    pub fn off_appearance(&mut self, size: Size) -> Surface {
+        appearance_surface(self.sc, &mut self.off_appearance, size)
+    }
+
+    /// A shorthand for `std::mem::drop`.
+    pub fn finish(self) {}
+}
+
+impl Drop for CheckBox<'_> {
+    fn drop(&mut self) {
+        if let Some(settings) = self.settings.take() {
+            self.sc
+                .register_form_field(InternalField::CheckBox(InternalCheckBox {
+                    settings,
+                    on_appearance: self.on_appearance.take(),
+                    off_appearance: self.off_appearance.take(),
+                }));
+        }
+    }
+}
+
+/// A radio button that is part of a [`RadioGroup`].
+#[derive(Debug, Clone)]
+pub struct RadioButton {
+    /// The index of the page the radio button should be placed on.
+    pub page_index: usize,
+    /// The region of the radio button on the page.
+    pub rect: Rect,
+    /// The value of the group when this radio button is selected. Must not be `Off`,
+    /// otherwise exporting the document will fail.
+    pub export_value: String,
+}
+
+/// The settings of a group of radio buttons, of which at most one can be selected
+/// at a time.
+#[derive(Debug, Clone)]
+pub struct RadioGroupSettings {
+    /// The name of the field, which must be unique and must not contain a period.
+    pub name: String,
+    /// The radio buttons of the group.
+    pub buttons: Vec<RadioButton>,
+    /// The export value of the radio button that should be selected initially.
+    pub selected: Option<String>,
+    /// Whether radio buttons with the same export value should be turned on and off in
+    /// unison.
+    pub radios_in_unison: bool,
+}
+
+/// The custom appearances of the `On` and `Off` state of a button.
+#[derive(Default)]
+struct ButtonAppearances {
+    on: Option<(Stream, Size)>,
+    off: Option<(Stream, Size)>,
+}
+
+/// A group of radio buttons.
+///
+/// You cannot create an instance of this type yourself. Instead, you should use the
+/// [`Document::add_radio_group`] method to add a new radio group to a document.
+/// The radio group will be added to the document once it is dropped. Unless you draw
+/// custom appearances, krilla will draw a simple dot for the selected state of each button.
+///
+/// [`Document::add_radio_group`]: crate::Document::add_radio_group
+pub struct RadioGroup<'a> {
+    sc: &'a mut SerializeContext,
+    settings: Option<RadioGroupSettings>,
+    appearances: Vec<ButtonAppearances>,
+}
+
+impl<'a> RadioGroup<'a> {
+    pub(crate) fn new(sc: &'a mut SerializeContext, settings: RadioGroupSettings) -> Self {
+        if settings
+            .buttons
+            .iter()
+            .any(|button| button.export_value == "Off")
+        {
+            sc.register_invalid_export_value(settings.name.clone());
+        }
+
+        let appearances = settings
+            .buttons
+            .iter()
+            .map(|_| ButtonAppearances::default())
+            .collect();
+
+        Self {
+            sc,
+            settings: Some(settings),
+            appearances,
+        }
+    }
+
+    /// Get the surface to draw the appearance of the selected state of the radio button
+    /// with the given index on. The coordinate system of the surface starts at the top-left
+    /// of the appearance and spans `size`, and viewers will scale it so that it fits into
+    /// the rectangle of the radio button. Calling this multiple times will reset any
+    /// previous drawings.
+    ///
+    /// Panics if the group doesn't have a radio button with the given index.
+    pub fn on_appearance(&mut self, index: usize, size: Size) -> Surface {
+        appearance_surface(self.sc, &mut self.appearances[index].on, size)
+    }
+
+    /// Get the surface to draw the appearance of the unselected state of the radio button
+    /// with the given index on. See [`RadioGroup::on_appearance`] for more information.
+    pub fn off_appearance(&mut self, index: usize, size: Size) -> Surface {
+        appearance_surface(self.sc, &mut self.appearances[index].off, size)
+    }
+
+    /// A shorthand for `std::mem::drop`.
+    pub fn finish(self) {}
+}
+
+impl Drop for RadioGroup<'_> {
+    fn drop(&mut self) {
+        if let Some(settings) = self.settings.take() {
+            self.sc
+                .register_form_field(InternalField::RadioGroup(InternalRadioGroup {
+                    settings,
+                    appearances: std::mem::take(&mut self.appearances),
+                }));
+        }
+    }
+}
+
+/// A push button, which performs an action when clicked.
+pub struct PushButton {
+    /// The name of the field, which must be unique and must not contain a period.
+    pub name: String,
+    /// The index of the page the button should be placed on.
+    pub page_index: usize,
+    /// The region of the button on the page.
+    pub rect: Rect,
+    /// The caption of the button.
+    pub caption: String,
+    /// The font that should be used for the caption. It needs to be a TrueType
+    /// or CFF font, otherwise exporting the document will fail.
+    pub font: Font,
+    /// The font size that should be used for the caption.
+    pub font_size: f32,
+    /// The action that should be performed when the button is clicked, for example
+    /// a [`SubmitFormAction`] or a [`ResetFormAction`].
+    ///
+    /// [`SubmitFormAction`]: crate::action::SubmitFormAction
+    /// [`ResetFormAction`]: crate::action::ResetFormAction
+    pub action: Option<Action>,
+}
+
+/// The location of a signature placeholder in a finished PDF file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SignaturePlaceholder {
+    /// The name of the signature field.
+    pub name: String,
+    /// The values that have been written into the `ByteRange` entry of the signature, i.e.
+    /// pairs of offset and length that describe all parts of the file except for the
+    /// signature contents. These are the bytes that need to be signed.
+    pub byte_range: [usize; 4],
+    /// The range of the hex string of the `Contents` entry in the file, including the
+    /// angle brackets. The hex-encoded signature needs to be written into this range,
+    /// padded with zeros.
+    pub contents: Range<usize>,
+}
+
+pub(crate) enum InternalField {
+    Text(InternalTextField),
+    Choice(InternalChoiceField),
+    CheckBox(InternalCheckBox),
+    RadioGroup(InternalRadioGroup),
+    PushButton(InternalPushButton),
+    Signature(InternalSignatureField),
+}
+
+impl InternalField {
+    /// The (partial) name of the field.
+    pub(crate) fn name(&self) -> &str {
+        match self {
+            InternalField::Text(t) => &t.field.name,
+            InternalField::Choice(c) => &c.field.name,
+            InternalField::CheckBox(c) => &c.settings.name,
+            InternalField::RadioGroup(r) => &r.settings.name,
+            InternalField::PushButton(p) => &p.button.name,
+            InternalField::Signature(s) => &s.settings.name,
+        }
+    }
+
+    /// The font that needs to be added to the default resources of the form.
+    pub(crate) fn font_ref(&self) -> Option<Ref> {
+        match self {
+            InternalField::Text(t) => Some(t.font_ref),
+            InternalField::Choice(c) => Some(c.font_ref),
+            InternalField::PushButton(p) => Some(p.font_ref),
+            InternalField::CheckBox(_)
+            | InternalField::RadioGroup(_)
+            | InternalField::Signature(_) => None,
+        }
+    }
+
+    pub(crate) fn serialize(
+        &self,
+        sc: &mut SerializeContext,
+        root_ref: Ref,
+        font_refs: &[Ref],
+    ) -> KrillaResult<Chunk> {
+        Ok(match self {
+            InternalField::Text(t) => t.serialize(sc, root_ref, font_refs),
+            InternalField::Choice(c) => c.serialize(sc, root_ref, font_refs),
+            InternalField::CheckBox(c) => c.serialize(sc, root_ref),
+            InternalField::RadioGroup(r) => r.serialize(sc, root_ref),
+            InternalField::PushButton(p) => p.serialize(sc, root_ref, font_refs)?,
+            InternalField::Signature(s) => s.serialize(sc, root_ref),
+        })
+    }
+}
+
+/// The name under which the font with the given index is stored in the default resources
+/// of the form.
+pub(crate) fn font_resource_name(index: usize) -> String {
+    format!("f{}", index)
+}
+
+/// Returns the page index, the ref and the height of the page at the given position.
+fn widget_page(sc: &SerializeContext, position: usize) -> (usize, Ref, f32) {
+    let page_index = sc.page_at(position).unwrap_or_else(|| {
+        panic!(
+            "attempted to add a form field to page {}, but document only has {} pages",
+            position + 1,
+            sc.num_pages()
+        )
+    });
+    let page_info = &sc.page_infos()[page_index];
+
+    (page_index, page_info.ref_, page_info.surface_size.height())
+}
+
+/// Write the entries of the widget annotation of a field into its dictionary.
+fn write_widget(
+    sc: &mut SerializeContext,
+    field: &mut Dict,
+    root_ref: Ref,
+    page_index: usize,
+    rect: Rect,
+) {
+    let (page_index, page_ref, page_height) = widget_page(sc, page_index);
+
+    field.pair(Name(b"Type"), Name(b"Annot"));
+    field.pair(Name(b"Subtype"), Name(b"Widget"));
+    let rect = rect.transform(page_root_transform(page_height)).unwrap();
+    field.pair(Name(b"Rect"), rect.to_pdf_rect());
+    field.pair(Name(b"P"), page_ref);
+    field.pair(Name(b"F"), AnnotationFlags::PRINT.bits() as i32);
+
+    sc.page_infos_mut()[page_index]
+        .widget_annotations
+        .push(root_ref);
+}
+
+/// Create a surface for drawing a custom appearance of a field, which will be stored
+/// in `appearance` once the surface is finished.
+fn appearance_surface<'a>(
+    sc: &'a mut SerializeContext,
+    appearance: &'a mut Option<(Stream, Size)>,
+    size: Size,
+) -> Surface<'a> {
+    let root_builder = ContentBuilder::new(page_root_transform(size.height()), true);
+
+    let finish_fn = Box::new(move |stream, _| {
+        *appearance = Some((stream, size));
+    });
+
+    Surface::new(sc, root_builder, None, finish_fn)
+}
+
+/// Register a custom appearance that has been drawn with a surface. The appearance shares
+/// its resources with the rest of the document, so we register it like any other XObject.
+fn register_appearance(sc: &mut SerializeContext, appearance: &(Stream, Size)) -> Ref {
+    let (stream, size) = appearance;
+    let bbox = Rect::from_xywh(0.0, 0.0, size.width(), size.height()).unwrap();
+    sc.register_cacheable(XObject::new(stream.clone(), false, false, Some(bbox)))
+}
+
+/// Write an appearance stream that we have generated ourselves.
+fn write_appearance(
+    sc: &mut SerializeContext,
+    chunk: &mut Chunk,
+    root_ref: Ref,
+    content: Content,
+    rect: Rect,
+    font: Option<(&str, Ref)>,
+) {
+    let content = content.finish();
+    sc.register_limits(content.limits());
+
+    let serialize_settings = sc.serialize_settings();
+    let encryptor = sc.encryptor();
+    let stream = FilterStreamBuilder::new_from_content_stream(&content, &serialize_settings)
+        .finish(&serialize_settings, encryptor);
+    let mut x_object = chunk.form_xobject(root_ref, stream.encoded_data());
+    stream.write_filters(x_object.deref_mut().deref_mut());
+    x_object.bbox(pdf_writer::Rect::new(0.0, 0.0, rect.width(), rect.height()));
+    if let Some((font_name, font_ref)) = font {
+        x_object
+            .resources()
+            .fonts()
+            .pair(Name(font_name.as_bytes()), font_ref);
+    }
+    x_object.finish();
+}
+
+/// Write the `On` and `Off` appearances of a button widget, as well as its appearance state.
+fn write_button_appearances(
+    widget: &mut Dict,
+    export_value: &str,
+    on_ref: Ref,
+    off_ref: Ref,
+    is_on: bool,
+) {
+    let mut appearances = widget.insert(Name(b"AP")).dict();
+    let mut normal = appearances.insert(Name(b"N")).dict();
+    normal.pair(Name(export_value.as_bytes()), on_ref);
+    normal.pair(Name(b"Off"), off_ref);
+    normal.finish();
+    appearances.finish();
+
+    let state = if is_on { export_value } else { "Off" };
+    widget.pair(Name(b"AS"), Name(state.as_bytes()));
+}
+
+/// The glyphs of a line of text in a field, consisting of the CIDs and the advances
+/// (normalized by the units per em of the font) of the glyphs.
+type FieldLine = Vec<(Cid, f32)>;
+
+/// Register the font of a field, as well as the glyphs of the given lines that we need
+/// to draw the appearance of the field. Returns the ref of the font and the glyphs.
+fn register_field_text(
+    sc: &mut SerializeContext,
+    font: &Font,
+    lines: &[&str],
+) -> (Ref, Vec<FieldLine>) {
+    if !font.font_info().can_be_cid_font() {
+        // Exporting the document will fail, so the field is never written.
+        sc.register_invalid_field_font(font.clone());
+        return (sc.new_ref(), vec![]);
+    }
+
+    let font_container = sc.register_font_container(font.clone());
+    let mut font_container = font_container.borrow_mut();
+    let cid_font = font_container.cid_font_mut();
+    // Always add the .notdef glyph so that the font is written, even if the field is empty.
+    cid_font.add_glyph(GlyphId::new(0));
+
+    let charmap = font.font_ref().charmap();
+
+    // Since we are writing the glyphs ourselves, we have to register them in the
+    // font so that they are included in the subset and can be extracted again.
+    let lines = lines
+        .iter()
+        .map(|line| {
+            line.chars()
+                .map(|c| {
+                    let glyph_id = GlyphId::new(charmap.map(c).map(|g| g.to_u32()).unwrap_or(0));
+                    let cid = cid_font.add_glyph(glyph_id);
+                    cid_font.set_codepoints(cid, c.to_string(), None);
+                    let advance = font.advance_width(glyph_id).unwrap_or(0.0) / font.units_per_em();
+
+                    (cid, advance)
+                })
+                .collect()
+        })
+        .collect();
+
+    let font_ref = sc.register_font_identifier(cid_font.identifier()).get_ref();
+
+    (font_ref, lines)
+}
+
+/// Shape a single line of text of a field.
+#[cfg(feature = "simple-text")]
+fn shape_line(font: &Font, text: &str) -> Vec<KrillaGlyph> {
+    naive_shape(text, font.clone(), TextDirection::Auto)
+}
+
+/// Map each character of a single line of text of a field to a glyph, since we can
+/// only shape text with the `simple-text` feature.
+#[cfg(not(feature = "simple-text"))]
+fn shape_line(font: &Font, text: &str) -> Vec<KrillaGlyph> {
+    let charmap = font.font_ref().charmap();
+
+    text.char_indices()
+        .map(|(i, c)| {
+            let glyph_id = GlyphId::new(charmap.map(c).map(|g| g.to_u32()).unwrap_or(0));
+            let advance = font.advance_width(glyph_id).unwrap_or(0.0) / font.units_per_em();
+
+            KrillaGlyph::new(glyph_id, advance, 0.0, 0.0, 0.0, i..i + c.len_utf8(), None)
+        })
+        .collect()
+}
+
+/// Show the glyphs of a line at the given position.
+fn show_line(content: &mut Content, x: f32, y: f32, glyphs: &[(Cid, f32)]) {
+    let encoded = glyphs
+        .iter()
+        .flat_map(|(cid, _)| cid.to_be_bytes())
+        .collect::<Vec<_>>();
+    content.set_text_matrix([1.0, 0.0, 0.0, 1.0, x, y]);
+    content.show(Str(&encoded));
+}
+
+/// The default appearance string of a field.
+fn default_appearance(font_name: &str, font_size: f32) -> String {
+    format!("/{} {} Tf 0 g", font_name, font_size)
+}
+
+// Field flags, see section 12.7.5 of the PDF 2.0 specification.
+const MULTILINE: i32 = 1 << 12;
+const NO_TOGGLE_TO_OFF: i32 = 1 << 14;
+const RADIO: i32 = 1 << 15;
+const PUSH_BUTTON: i32 = 1 << 16;
+const COMBO: i32 = 1 << 17;
+const EDIT: i32 = 1 << 18;
+const MULTI_SELECT: i32 = 1 << 21;
+const COMB: i32 = 1 << 24;
+const RADIOS_IN_UNISON: i32 = 1 << 25;
+
+pub(crate) struct InternalTextField {
+    field: TextField,
+    font_ref: Ref,
+    /// The glyphs of each line of the default value.
+    lines: Vec<FieldLine>,
+}
+
+impl InternalTextField {
+    pub(crate) fn new(sc: &mut SerializeContext, mut field: TextField) -> Self {
+        if let (Some(value), Some(max_len)) = (&mut field.default_value, field.max_len) {
+            if let Some((index, _)) = value.char_indices().nth(max_len as usize) {
+                value.truncate(index);
+            }
+        }
+
+        let lines = field
+            .default_value
+            .as_deref()
+            .map(|v| v.split('\n').collect::<Vec<_>>())
+            .unwrap_or_default();
+        let (font_ref, lines) = register_field_text(sc, &field.font, &lines);
+
+        Self {
+            field,
+            font_ref,
+            lines,
+        }
+    }
+
+    fn is_comb(&self) -> bool {
+        self.field.comb && self.field.max_len.is_some() && !self.field.multiline
+    }
+
+    fn serialize(&self, sc: &mut SerializeContext, root_ref: Ref, font_refs: &[Ref]) -> Chunk {
+        let field = &self.field;
+        let font_name =
+            font_resource_name(font_refs.iter().position(|r| *r == self.font_ref).unwrap());
+        let appearance_ref = sc.new_ref();
+        let mut chunk = Chunk::new();
+
+        let mut flags = 0;
+        if field.multiline {
+            flags |= MULTILINE;
+        }
+        if self.is_comb() {
+            flags |= COMB;
+        }
+
+        let default_appearance = default_appearance(&font_name, field.font_size);
+
+        // We merge the field dictionary with the dictionary of its widget annotation.
+        let mut dict = chunk.indirect(root_ref).dict();
+        dict.pair(Name(b"FT"), Name(b"Tx"));
+        dict.pair(Name(b"T"), sc.text_str(&field.name));
+        if let Some(value) = &field.default_value {
+            dict.pair(Name(b"V"), sc.text_str(value));
+            dict.pair(Name(b"DV"), sc.text_str(value));
+        }
+        if let Some(max_len) = field.max_len {
+            dict.pair(Name(b"MaxLen"), max_len as i32);
+        }
+        dict.pair(Name(b"Ff"), flags);
+        dict.pair(Name(b"DA"), sc.str(default_appearance.as_bytes()));
+        write_widget(sc, &mut dict, root_ref, field.page_index, field.rect);
+        dict.insert(Name(b"AP"))
+            .dict()
+            .pair(Name(b"N"), appearance_ref);
+        dict.finish();
+
+        self.serialize_appearance(sc, &mut chunk, appearance_ref, &font_name);
+
+        chunk
+    }
+
+    fn serialize_appearance(
+        &self,
+        sc: &mut SerializeContext,
+        chunk: &mut Chunk,
+        root_ref: Ref,
+        font_name: &str,
+    ) {
+        let field = &self.field;
+        let font = &field.font;
+        let width = field.rect.width();
+        let height = field.rect.height();
+        let font_size = field.font_size;
+        let ascent = font.ascent() / font.units_per_em() * font_size;
+        let descent = font.descent() / font.units_per_em() * font_size;
+        let line_height = ascent - descent;
+        // The padding Acrobat uses for the text of fields.
+        let padding = 2.0;
+
+        let mut content = Content::new();
+        content.begin_marked_content(Name(b"Tx"));
+        content.save_state();
+        content.begin_text();
+        content.set_font(Name(font_name.as_bytes()), font_size);
+        content.set_fill_gray(0.0);
+
+        if self.is_comb() {
+            // Place each character in the middle of its cell.
+            let cell_width = width / field.max_len.unwrap().max(1) as f32;
+            let y = (height - line_height) / 2.0 - descent;
+
+            for (i, glyph) in self.lines.iter().flatten().enumerate() {
+                let x = i as f32 * cell_width + (cell_width - glyph.1 * font_size) / 2.0;
+                show_line(&mut content, x, y, std::slice::from_ref(glyph));
+            }
+        } else if field.multiline {
+            let mut y = height - padding - ascent;
+
+            for line in &self.lines {
+                show_line(&mut content, padding, y, line);
+                y -= line_height;
+            }
+        } else {
+            let y = (height - line_height) / 2.0 - descent;
+
+            if let Some(line) = self.lines.first() {
+                show_line(&mut content, padding, y, line);
+            }
+        }
+
+        content.end_text();
+        content.restore_state();
+        content.end_marked_content();
+
+        write_appearance(
+            sc,
+            chunk,
+            root_ref,
+            content,
+            field.rect,
+            Some((font_name, self.font_ref)),
+        );
+    }
+}
+
+pub(crate) struct InternalChoiceField {
+    field: ChoiceField,
+    font_ref: Ref,
+    /// The glyphs of the label of each option.
+    lines: Vec<FieldLine>,
+}
+
+impl InternalChoiceField {
+    pub(crate) fn new(sc: &mut SerializeContext, field: ChoiceField) -> Self {
+        let labels = field
+            .options
+            .iter()
+            .map(|o| o.label.as_deref().unwrap_or(&o.export_value))
+            .collect::<Vec<_>>();
+        let (font_ref, lines) = register_field_text(sc, &field.font, &labels);
+
+        Self {
+            field,
+            font_ref,
+            lines,
+        }
+    }
+
+    fn is_selected(&self, option: &ChoiceOption) -> bool {
+        self.field.selected.contains(&option.export_value)
+    }
+
+    fn serialize(&self, sc: &mut SerializeContext, root_ref: Ref, font_refs: &[Ref]) -> Chunk {
+        let field = &self.field;
+        let font_name =
+            font_resource_name(font_refs.iter().position(|r| *r == self.font_ref).unwrap());
+        let appearance_ref = sc.new_ref();
+        let mut chunk = Chunk::new();
+
+        let flags = match field.kind {
+            ChoiceKind::ComboBox { editable: false } => COMBO,
+            ChoiceKind::ComboBox { editable: true } => COMBO | EDIT,
+            ChoiceKind::ListBox {
+                multi_select: false,
+            } => 0,
+            ChoiceKind::ListBox { multi_select: true } => MULTI_SELECT,
+        };
+
+        let default_appearance = default_appearance(&font_name, field.font_size);
+
+        // We merge the field dictionary with the dictionary of its widget annotation.
+        let mut dict = chunk.indirect(root_ref).dict();
+        dict.pair(Name(b"FT"), Name(b"Ch"));
+        dict.pair(Name(b"T"), sc.text_str(&field.name));
+        dict.pair(Name(b"Ff"), flags);
+
+        let mut options = dict.insert(Name(b"Opt")).array();
+        for option in &field.options {
+            match &option.label {
+                Some(label) => {
+                    options
+                        .push()
+                        .array()
+                        .item(sc.text_str(&option.export_value))
+                        .item(sc.text_str(label));
+                }
+                None => {
+                    options.item(sc.text_str(&option.export_value));
+                }
+            }
+        }
+        options.finish();
+
+        match field.selected.as_slice() {
+            [] => {}
+            [value] => {
+                dict.pair(Name(b"V"), sc.text_str(value));
+                dict.pair(Name(b"DV"), sc.text_str(value));
+            }
+            values => {
+                for key in [Name(b"V"), Name(b"DV")] {
+                    dict.insert(key)
+                        .array()
+                        .items(values.iter().map(|v| sc.text_str(v)));
+                }
+            }
+        }
+
+        // Viewers use the indices of the selected options to distinguish between options
+        // with the same export value.
+        if matches!(field.kind, ChoiceKind::ListBox { .. }) && !field.selected.is_empty() {
+            dict.insert(Name(b"I")).array().items(
+                field
+                    .options
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, o)| self.is_selected(o))
+                    .map(|(i, _)| i as i32),
+            );
+        }
+
+        dict.pair(Name(b"DA"), sc.str(default_appearance.as_bytes()));
+        write_widget(sc, &mut dict, root_ref, field.page_index, field.rect);
+        dict.insert(Name(b"AP"))
+            .dict()
+            .pair(Name(b"N"), appearance_ref);
+        dict.finish();
+
+        self.serialize_appearance(sc, &mut chunk, appearance_ref, &font_name);
+
+        chunk
+    }
+
+    fn serialize_appearance(
+        &self,
+        sc: &mut SerializeContext,
+        chunk: &mut Chunk,
+        root_ref: Ref,
+        font_name: &str,
+    ) {
+        let field = &self.field;
+        let font = &field.font;
+        let width = field.rect.width();
+        let height = field.rect.height();
+        let font_size = field.font_size;
+        let ascent = font.ascent() / font.units_per_em() * font_size;
+        let descent = font.descent() / font.units_per_em() * font_size;
+        let line_height = ascent - descent;
+        // The padding Acrobat uses for the text of fields.
+        let padding = 2.0;
+
+        let mut content = Content::new();
+        content.begin_marked_content(Name(b"Tx"));
+        content.save_state();
+
+        match field.kind {
+            ChoiceKind::ComboBox { .. } => {
+                // Only the selected option is visible in a combo box.
+                let selected = field.options.iter().position(|o| self.is_selected(o));
+
+                if let Some(line) = selected.map(|i| &self.lines[i]) {
+                    let y = (height - line_height) / 2.0 - descent;
+                    content.begin_text();
+                    content.set_font(Name(font_name.as_bytes()), font_size);
+                    content.set_fill_gray(0.0);
+                    show_line(&mut content, padding, y, line);
+                    content.end_text();
+                }
+            }
+            ChoiceKind::ListBox { .. } => {
+                // Highlight the selected options, with the same color as Acrobat.
+                if !field.selected.is_empty() {
+                    content.set_fill_rgb(0.6, 0.75686, 0.8549);
+                    for (i, option) in field.options.iter().enumerate() {
+                        if self.is_selected(option) {
+                            let y = height - padding - (i + 1) as f32 * line_height;
+                            content.rect(0.0, y, width, line_height);
+                        }
+                    }
+                    content.fill_nonzero();
+                }
+
+                content.begin_text();
+                content.set_font(Name(font_name.as_bytes()), font_size);
+                content.set_fill_gray(0.0);
+                let mut y = height - padding - ascent;
+                for line in &self.lines {
+                    show_line(&mut content, padding, y, line);
+                    y -= line_height;
+                }
+                content.end_text();
+            }
+        }
+
+        content.restore_state();
+        content.end_marked_content();
+
+        write_appearance(
+            sc,
+            chunk,
+            root_ref,
+            content,
+            field.rect,
+            Some((font_name, self.font_ref)),
+        );
+    }
+}
+
+pub(crate) struct InternalPushButton {
+    button: PushButton,
+    font_ref: Ref,
+    appearance: (Stream, Size),
+}
+
+impl InternalPushButton {
+    pub(crate) fn new(sc: &mut SerializeContext, button: PushButton) -> Self {
+        // The caption is drawn with a surface, so we only need to register the font.
+        let (font_ref, _) = register_field_text(sc, &button.font, &[]);
+        let appearance = Self::create_appearance(sc, &button);
+
+        Self {
+            button,
+            font_ref,
+            appearance,
+        }
+    }
+
+    fn create_appearance(sc: &mut SerializeContext, button: &PushButton) -> (Stream, Size) {
+        let font = &button.font;
+        let width = button.rect.width();
+        let height = button.rect.height();
+        let font_size = button.font_size;
+        let ascent = font.ascent() / font.units_per_em() * font_size;
+        let descent = font.descent() / font.units_per_em() * font_size;
+        let size = Size::from_wh(width, height).unwrap();
+
+        let mut appearance = None;
+        let mut surface = appearance_surface(sc, &mut appearance, size);
+
+        let mut builder = PathBuilder::new();
+        builder.push_rect(Rect::from_xywh(0.0, 0.0, width, height).unwrap());
+        surface.set_fill(Some(Fill {
+            paint: luma::Color::new(191).into(),
+            ..Fill::default()
+        }));
+        surface.set_stroke(None);
+        surface.draw_path(&builder.finish().unwrap());
+
+        // Center the caption in the button.
+        let glyphs = shape_line(font, &button.caption);
+        let caption_width = glyphs.iter().map(|g| g.x_advance).sum::<f32>() * font_size;
+        let x = (width - caption_width) / 2.0;
+        let y = (height - (ascent - descent)) / 2.0 + ascent;
+        surface.set_fill(Some(Fill {
+            paint: luma::Color::black().into(),
+            ..Fill::default()
+        }));
+        surface.draw_glyphs(
+            Point::from_xy(x, y),
+            &glyphs,
+            font.clone(),
+            &button.caption,
+            font_size,
+            false,
+        );
+        surface.finish();
+
+        appearance.unwrap()
+    }
+
+    fn serialize(
+        &self,
+        sc: &mut SerializeContext,
+        root_ref: Ref,
+        font_refs: &[Ref],
+    ) -> KrillaResult<Chunk> {
+        let button = &self.button;
+        let font_name =
+            font_resource_name(font_refs.iter().position(|r| *r == self.font_ref).unwrap());
+        let appearance_ref = register_appearance(sc, &self.appearance);
+        let mut chunk = Chunk::new();
+
+        let default_appearance = default_appearance(&font_name, button.font_size);
+
+        // We merge the field dictionary with the dictionary of its widget annotation.
+        let mut dict = chunk.indirect(root_ref).dict();
+        dict.pair(Name(b"FT"), Name(b"Btn"));
+        dict.pair(Name(b"T"), sc.text_str(&button.name));
+        dict.pair(Name(b"Ff"), PUSH_BUTTON);
+        dict.pair(Name(b"DA"), sc.str(default_appearance.as_bytes()));
+        write_widget(sc, &mut dict, root_ref, button.page_index, button.rect);
+        dict.insert(Name(b"MK"))
+            .dict()
+            .pair(Name(b"CA"), sc.text_str(&button.caption));
+        dict.insert(Name(b"AP"))
+            .dict()
+            .pair(Name(b"N"), appearance_ref);
+        if let Some(action) = &button.action {
+            action.serialize(
+                sc,
+                dict.insert(Name(b"A"))
+                    .start::<pdf_writer::writers::Action>(),
+            )?;
+        }
+        dict.finish();
+
+        Ok(chunk)
+    }
+}
+
+pub(crate) struct InternalCheckBox {
+    settings: CheckBoxSettings,
+    on_appearance: Option<(Stream, Size)>,
+    off_appearance: Option<(Stream, Size)>,
+}
+
+impl InternalCheckBox {
+    fn serialize(&self, sc: &mut SerializeContext, root_ref: Ref) -> Chunk {
+        let settings = &self.settings;
+        let mut chunk = Chunk::new();
+
+        let on_ref = match &self.on_appearance {
+            Some(appearance) => register_appearance(sc, appearance),
+            None => {
+                let on_ref = sc.new_ref();
+                let content = check_mark(settings.rect.width(), settings.rect.height());
+                write_appearance(sc, &mut chunk, on_ref, content, settings.rect, None);
+                on_ref
+            }
+        };
+        let off_ref = match &self.off_appearance {
+            Some(appearance) => register_appearance(sc, appearance),
+            None => {
+                let off_ref = sc.new_ref();
+                write_appearance(sc, &mut chunk, off_ref, Content::new(), settings.rect, None);
+                off_ref
+            }
+        };
+
+        let state = Name(settings.export_value.as_bytes());
+
+        // We merge the field dictionary with the dictionary of its widget annotation.
+        let mut field = chunk.indirect(root_ref).dict();
+        field.pair(Name(b"FT"), Name(b"Btn"));
+        field.pair(Name(b"T"), sc.text_str(&settings.name));
+        if settings.checked {
+            field.pair(Name(b"V"), state);
+        } else {
+            field.pair(Name(b"V"), Name(b"Off"));
+        }
+        write_widget(sc, &mut field, root_ref, settings.page_index, settings.rect);
+        write_button_appearances(
+            &mut field,
+            &settings.export_value,
+            on_ref,
+            off_ref,
+            settings.checked,
+        );
+        field.finish();
+
+        chunk
+    }
+}
+
+/// A check mark that fills a box with the given dimensions.
+fn check_mark(width: f32, height: f32) -> Content {
+    let mut content = Content::new();
+    content.save_state();
+    content.set_line_width(0.1 * width.min(height));
+    content.set_line_cap(pdf_writer::types::LineCapStyle::RoundCap);
+    content.set_line_join(pdf_writer::types::LineJoinStyle::RoundJoin);
+    content.move_to(0.2 * width, 0.5 * height);
+    content.line_to(0.4 * width, 0.25 * height);
+    content.line_to(0.8 * width, 0.8 * height);
+    content.stroke();
+    content.restore_state();
+
+    content
+}
+
+/// A dot that is placed in the middle of a box with the given dimensions.
+fn radio_dot(width: f32, height: f32) -> Content {
+    let (cx, cy) = (width / 2.0, height / 2.0);
+    let r = 0.25 * width.min(height);
+    let k = KAPPA * r;
+
+    let mut content = Content::new();
+    content.move_to(cx + r, cy);
+    content.cubic_to(cx + r, cy + k, cx + k, cy + r, cx, cy + r);
+    content.cubic_to(cx - k, cy + r, cx - r, cy + k, cx - r, cy);
+    content.cubic_to(cx - r, cy - k, cx - k, cy - r, cx, cy - r);
+    content.cubic_to(cx + k, cy - r, cx + r, cy - k, cx + r, cy);
+    content.close_path();
+    content.fill_nonzero();
+
+    content
+}
+
+pub(crate) struct InternalRadioGroup {
+    settings: RadioGroupSettings,
+    appearances: Vec<ButtonAppearances>,
+}
+
+impl InternalRadioGroup {
+    fn serialize(&self, sc: &mut SerializeContext, root_ref: Ref) -> Chunk {
+        let settings = &self.settings;
+        let mut chunk = Chunk::new();
+
+        let mut flags = RADIO | NO_TOGGLE_TO_OFF;
+        if settings.radios_in_unison {
+            flags |= RADIOS_IN_UNISON;
+        }
+
+        let kid_refs = settings
+            .buttons
+            .iter()
+            .map(|_| sc.new_ref())
+            .collect::<Vec<_>>();
+
+        let mut field = chunk.indirect(root_ref).dict();
+        field.pair(Name(b"FT"), Name(b"Btn"));
+        field.pair(Name(b"T"), sc.text_str(&settings.name));
+        field.pair(Name(b"Ff"), flags);
+        let value = settings.selected.as_deref().unwrap_or("Off");
+        field.pair(Name(b"V"), Name(value.as_bytes()));
+        field
+            .insert(Name(b"Kids"))
+            .array()
+            .items(kid_refs.iter().copied());
+        field.finish();
+
+        for ((button, appearances), kid_ref) in
+            settings.buttons.iter().zip(&self.appearances).zip(kid_refs)
+        {
+            let on_ref = match &appearances.on {
+                Some(appearance) => register_appearance(sc, appearance),
+                None => {
+                    let on_ref = sc.new_ref();
+                    let content = radio_dot(button.rect.width(), button.rect.height());
+                    write_appearance(sc, &mut chunk, on_ref, content, button.rect, None);
+                    on_ref
+                }
+            };
+            let off_ref = match &appearances.off {
+                Some(appearance) => register_appearance(sc, appearance),
+                None => {
+                    let off_ref = sc.new_ref();
+                    write_appearance(sc, &mut chunk, off_ref, Content::new(), button.rect, None);
+                    off_ref
+                }
+            };
+            let is_on = settings.selected.as_ref() == Some(&button.export_value);
+
+            let mut widget = chunk.indirect(kid_ref).dict();
+            widget.pair(Name(b"Parent"), root_ref);
+            write_widget(sc, &mut widget, kid_ref, button.page_index, button.rect);
+            write_button_appearances(&mut widget, &button.export_value, on_ref, off_ref, is_on);
+            widget.finish();
+        }
+
+        chunk
+    }
+}
+
+pub(crate) struct InternalSignatureField {
+    settings: SignatureFieldSettings,
+    appearance: Option<(Stream, Size)>,
+}
+
+impl InternalSignatureField {
+    fn serialize(&self, sc: &mut SerializeContext, root_ref: Ref) -> Chunk {
+        let settings = &self.settings;
+        let signature_ref = sc.new_ref();
+        let appearance_ref = self
+            .appearance
+            .as_ref()
+            .map(|appearance| register_appearance(sc, appearance));
+        let mut chunk = Chunk::new();
+
+        // We merge the field dictionary with the dictionary of its widget annotation.
+        let mut field = chunk.indirect(root_ref).dict();
+        field.pair(Name(b"FT"), Name(b"Sig"));
+        field.pair(Name(b"T"), sc.text_str(&settings.name));
+        field.pair(Name(b"V"), signature_ref);
+        write_widget(sc, &mut field, root_ref, settings.page_index, settings.rect);
+        if let Some(appearance_ref) = appearance_ref {
+            field
+                .insert(Name(b"AP"))
+                .dict()
+                .pair(Name(b"N"), appearance_ref);
+        }
+        field.finish();
+
+        sc.register_signature(SignatureChunk::new(
+            settings.name.clone(),
+            signature_ref,
+            settings.reserved_size,
+        ));
+
+        chunk
+    }
+}
+
+/// The signature dictionary of a signature field. It is written into a chunk of its own,
+/// so that we can keep track of where its placeholders end up in the file.
+pub(crate) struct SignatureChunk {
+    name: String,
+    pub(crate) chunk: Chunk,
+    /// The distance between the start of the `ByteRange` placeholder and the end of the chunk.
+    /// The dictionary doesn't contain any references, so renumbering the chunk only changes
+    /// the object header and the distance stays the same in the final file.
+    byte_range: usize,
+    /// The distance between the start of the `Contents` placeholder and the end of the chunk.
+    contents: usize,
+    /// The length of the `Contents` placeholder, including the delimiters.
+    contents_len: usize,
+}
+
+impl SignatureChunk {
+    fn new(name: String, root_ref: Ref, reserved_size: usize) -> Self {
+        let mut chunk = Chunk::new();
+        let mut signature = chunk.indirect(root_ref).dict();
+        signature.pair(Name(b"Type"), Name(b"Sig"));
+        signature.pair(Name(b"Filter"), Name(b"Adobe.PPKLite"));
+        signature.pair(Name(b"SubFilter"), Name(b"adbe.pkcs7.detached"));
+        signature
+            .insert(Name(b"ByteRange"))
+            .array()
+            .items([0, i32::MAX, i32::MAX, i32::MAX]);
+        // We write the placeholder as a literal string of zeros, which will later be converted
+        // into an equally long hex string by replacing the parentheses with angle brackets.
+        // Note that the contents of signatures are never encrypted.
+        signature.pair(Name(b"Contents"), Str(&vec![b'0'; 2 * reserved_size]));
+        signature.finish();
+
+        // The chunk only contains the dictionary we just wrote, so the positions are unambiguous.
+        let bytes = chunk.as_bytes();
+        let byte_range = find(bytes, BYTE_RANGE_PLACEHOLDER).unwrap();
+        let contents = find(bytes, b"/Contents (").unwrap() + b"/Contents ".len();
+
+        Self {
+            name,
+            byte_range: bytes.len() - byte_range,
+            contents: bytes.len() - contents,
+            contents_len: 2 * reserved_size + 2,
+            chunk,
+        }
+    }
+
+    /// Return the location of the placeholders, given the position of the end of the
+    /// chunk in the file.
+    pub(crate) fn locate(&self, end: usize) -> SignatureLocation {
+        let contents_start = end - self.contents;
+
+        SignatureLocation {
+            name: self.name.clone(),
+            byte_range: end - self.byte_range,
+            contents: contents_start..contents_start + self.contents_len,
+        }
+    }
+}
+
+/// The location of the placeholders of a signature in the file.
+pub(crate) struct SignatureLocation {
+    name: String,
+    byte_range: usize,
+    contents: Range<usize>,
+}
+
+/// Fill in the byte ranges of all signature placeholders in a finished PDF, and convert the
+/// placeholders of the contents into hex strings.
+pub(crate) fn fill_signature_placeholders(
+    pdf: &mut [u8],
+    locations: Vec<SignatureLocation>,
+) -> Vec<SignaturePlaceholder> {
+    let mut placeholders = vec![];
+
+    for location in locations {
+        let byte_range_start = location.byte_range;
+        let (contents_start, contents_end) = (location.contents.start, location.contents.end);
+        debug_assert_eq!(pdf[contents_start], b'(');
+        debug_assert_eq!(pdf[contents_end - 1], b')');
+
+        pdf[contents_start] = b'<';
+        pdf[contents_end - 1] = b'>';
+
+        let byte_range = [0, contents_start, contents_end, pdf.len() - contents_end];
+
+        // Pad with spaces so that the length of the file doesn't change.
+        let mut formatted = format!(
+            "[{} {} {} {}",
+            byte_range[0], byte_range[1], byte_range[2], byte_range[3]
+        )
+        .into_bytes();
+        formatted.resize(BYTE_RANGE_PLACEHOLDER.len() - 1, b' ');
+        formatted.push(b']');
+        pdf[byte_range_start..byte_range_start + formatted.len()].copy_from_slice(&formatted);
+
+        placeholders.push(SignaturePlaceholder {
+            name: location.name,
+            byte_range,
+            contents: location.contents,
+        });
+    }
+
+    placeholders
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
@@ -1,12 +1,18 @@
 //! Embedding attachments to a PDF file.
+//!
+//! Files can either be embedded as plain attachments using [`Document::embed_file`], or
+//! they can be associated with the whole document, a page or a tag group, for example
+//! to provide the data a chart was created from. Associated files require PDF 2.0 or
+//! PDF/A-3 and later.
+//!
+//! [`Document::embed_file`]: crate::Document::embed_file
 
 use std::ops::DerefMut;
 
-use pdf_writer::{Chunk, Finish, Name, Ref, Str, TextStr};
+use pdf_writer::{Chunk, Finish, Name, Ref};
 
 use crate::chunk_container::ChunkContainerFn;
 use crate::configure::{PdfVersion, ValidationError};
-use crate::interchange::metadata::pdf_date;
 use crate::serialize::{Cacheable, SerializeContext};
 use crate::stream::FilterStreamBuilder;
 use crate::surface::Location;
@@ -54,6 +60,46 @@ impl Cacheable for EmbeddedFile {
     }
 
     fn serialize(self, sc: &mut SerializeContext, root_ref: Ref) -> Deferred<Chunk> {
+        let write_association_kind = sc
+            .serialize_settings()
+            .validator()
+            .allows_associated_files();
+
+        self.serialize_file_spec(sc, root_ref, write_association_kind)
+    }
+}
+
+/// An embedded file that is associated with the document, a page or a tag group.
+#[derive(Debug, Clone, Hash)]
+pub(crate) struct AssociatedFile(pub(crate) EmbeddedFile);
+
+impl Cacheable for AssociatedFile {
+    fn chunk_container(&self) -> ChunkContainerFn {
+        |cc| &mut cc.embedded_files
+    }
+
+    fn serialize(self, sc: &mut SerializeContext, root_ref: Ref) -> Deferred<Chunk> {
+        let write_association_kind = supports_associated_files(sc);
+        self.0
+            .serialize_file_spec(sc, root_ref, write_association_kind)
+    }
+}
+
+/// Whether associated files can be written with the current settings.
+pub(crate) fn supports_associated_files(sc: &SerializeContext) -> bool {
+    sc.serialize_settings()
+        .validator()
+        .allows_associated_files()
+        || sc.serialize_settings().pdf_version() >= PdfVersion::Pdf20
+}
+
+impl EmbeddedFile {
+    fn serialize_file_spec(
+        self,
+        sc: &mut SerializeContext,
+        root_ref: Ref,
+        write_association_kind: bool,
+    ) -> Deferred<Chunk> {
         sc.register_validation_error(ValidationError::EmbeddedFile(
             EmbedError::Existence,
             self.location,
@@ -67,7 +113,7 @@ impl Cacheable for EmbeddedFile {
             Some(false) => FilterStreamBuilder::new_from_uncompressed(self.data.as_ref()),
             None => FilterStreamBuilder::new_auto_compressed(self.data.as_ref()),
         }
-        .finish(&sc.serialize_settings());
+        .finish(&sc.serialize_settings(), sc.encryptor());
 
         let mut embedded_file_stream = chunk.embedded_file(stream_ref, file_stream.encoded_data());
         file_stream.write_filters(embedded_file_stream.deref_mut().deref_mut());
@@ -85,8 +131,7 @@ impl Cacheable for EmbeddedFile {
         params.size(self.data.as_ref().len() as i32);
 
         if let Some(date_time) = sc.metadata().and_then(|m| m.creation_date) {
-            let date = pdf_date(date_time);
-            params.modification_date(date);
+            params.pair(Name(b"ModDate"), sc.date(date_time));
         } else {
             sc.register_validation_error(ValidationError::EmbeddedFile(
                 EmbedError::MissingDate,
@@ -98,10 +143,10 @@ impl Cacheable for EmbeddedFile {
         embedded_file_stream.finish();
 
         let mut file_spec = chunk.file_spec(root_ref);
-        file_spec.path(Str(self.path.as_bytes()));
+        file_spec.pair(Name(b"F"), sc.str(self.path.as_bytes()));
 
         if sc.serialize_settings().pdf_version() >= PdfVersion::Pdf17 {
-            file_spec.unic_file(TextStr(&self.path));
+            file_spec.pair(Name(b"UF"), sc.text_str(&self.path));
         }
 
         let mut ef = file_spec.insert(Name(b"EF")).dict();
@@ -113,16 +158,12 @@ impl Cacheable for EmbeddedFile {
 
         ef.finish();
 
-        if sc
-            .serialize_settings()
-            .validator()
-            .allows_associated_files()
-        {
+        if write_association_kind {
             file_spec.association_kind(self.association_kind.to_pdf());
         }
 
         if let Some(description) = self.description {
-            file_spec.description(TextStr(&description));
+            file_spec.pair(Name(b"Desc"), sc.text_str(&description));
         } else {
             sc.register_validation_error(ValidationError::EmbeddedFile(
                 EmbedError::MissingDescription,
@@ -0,0 +1,148 @@
+//! Creating Factur-X and ZUGFeRD electronic invoices.
+//!
+//! A Factur-X invoice is a PDF/A-3 document that contains a machine-readable version
+//! of the invoice as an embedded XML file. Apart from embedding the file with the
+//! right name and association kind, this requires declaring the Factur-X extension
+//! schema and the conformance level of the invoice in the XMP metadata of the document.
+//! All of this is done by [`Document::set_facturx`], you only need to provide the XML
+//! data and make sure that the document is exported as PDF/A-3.
+//!
+//! [`Document::set_facturx`]: crate::Document::set_facturx
+
+use xmp_writer::pdfa::PdfAExtSchemasWriter;
+use xmp_writer::{CustomNamespace, Namespace, XmpWriter};
+
+use crate::interchange::embed::{AssociationKind, EmbeddedFile};
+use crate::Data;
+
+/// The namespace of the Factur-X extension schema, which is shared with ZUGFeRD 2.
+fn namespace() -> Namespace<'static> {
+    Namespace::Custom(Box::new(CustomNamespace::new(
+        "Factur-X PDFA Extension Schema",
+        "fx",
+        "urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#",
+    )))
+}
+
+/// The version of the Factur-X XML schema.
+const VERSION: &str = "1.0";
+
+/// An error while creating a Factur-X invoice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FacturXError {
+    /// The document is not exported as PDF/A-3, which is required for Factur-X
+    /// invoices.
+    NotPdfA3,
+    /// Another file with the name of the invoice was embedded in the document.
+    DuplicateInvoice,
+}
+
+/// The conformance level of a Factur-X invoice, which determines how much of the
+/// invoice is represented in the XML data.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ConformanceLevel {
+    /// Only the data needed for booking the invoice. Not a valid invoice in all
+    /// countries.
+    Minimum,
+    /// The minimum level plus the document level information, but without the
+    /// invoice lines.
+    BasicWl,
+    /// The basic level, which includes the invoice lines.
+    Basic,
+    /// The level that conforms to the European standard EN 16931.
+    En16931,
+    /// The extended level, which covers additional data for more complex invoices.
+    Extended,
+    /// The German XRechnung format.
+    XRechnung,
+}
+
+impl ConformanceLevel {
+    fn as_str(self) -> &'static str {
+        match self {
+            ConformanceLevel::Minimum => "MINIMUM",
+            ConformanceLevel::BasicWl => "BASIC WL",
+            ConformanceLevel::Basic => "BASIC",
+            ConformanceLevel::En16931 => "EN 16931",
+            ConformanceLevel::Extended => "EXTENDED",
+            ConformanceLevel::XRechnung => "XRECHNUNG",
+        }
+    }
+
+    /// The name of the embedded XML file, which is prescribed by the standard.
+    fn file_name(self) -> &'static str {
+        match self {
+            ConformanceLevel::XRechnung => "xrechnung.xml",
+            _ => "factur-x.xml",
+        }
+    }
+
+    /// The association kind of the embedded XML file. The data of the lower levels
+    /// is not a full representation of the invoice.
+    fn association_kind(self) -> AssociationKind {
+        match self {
+            ConformanceLevel::Minimum | ConformanceLevel::BasicWl => AssociationKind::Data,
+            _ => AssociationKind::Alternative,
+        }
+    }
+}
+
+/// The XML data of a Factur-X invoice, along with its conformance level.
+#[derive(Debug, Clone)]
+pub(crate) struct FacturX {
+    pub(crate) data: Data,
+    pub(crate) conformance_level: ConformanceLevel,
+}
+
+impl FacturX {
+    pub(crate) fn file_name(&self) -> &'static str {
+        self.conformance_level.file_name()
+    }
+
+    /// The embedded file containing the XML data of the invoice.
+    pub(crate) fn embedded_file(&self) -> EmbeddedFile {
+        EmbeddedFile {
+            path: self.file_name().to_string(),
+            mime_type: Some("text/xml".to_string()),
+            description: Some("Factur-X invoice".to_string()),
+            association_kind: self.conformance_level.association_kind(),
+            data: self.data.clone(),
+            compress: None,
+            location: None,
+        }
+    }
+
+    /// Describe the properties of the Factur-X extension schema.
+    pub(crate) fn describe_schema(&self, extension_schemas: &mut PdfAExtSchemasWriter) {
+        let mut schema = extension_schemas.add_schema();
+        schema.namespace(namespace());
+
+        let mut properties = schema.properties();
+        for (name, description) in [
+            (
+                "DocumentFileName",
+                "The name of the embedded XML invoice file",
+            ),
+            ("DocumentType", "The type of the hybrid document"),
+            ("Version", "The version of the XML schema of the invoice"),
+            ("ConformanceLevel", "The conformance level of the invoice"),
+        ] {
+            properties
+                .add_property()
+                .name(name)
+                .value_type("Text")
+                // The properties are provided by the user, not derived from the document.
+                .category(false)
+                .description(description);
+        }
+    }
+
+    pub(crate) fn write_xmp(&self, xmp: &mut XmpWriter) {
+        xmp.element("DocumentFileName", namespace())
+            .value(self.file_name());
+        xmp.element("DocumentType", namespace()).value("INVOICE");
+        xmp.element("Version", namespace()).value(VERSION);
+        xmp.element("ConformanceLevel", namespace())
+            .value(self.conformance_level.as_str());
+    }
+}
@@ -6,10 +6,14 @@
 //!
 //! [`Document::set_metadata`]: crate::document::Document::set_metadata
 
-use pdf_writer::{Finish, Pdf, Ref, TextStr};
+use std::num::NonZeroU32;
+use std::ops::RangeInclusive;
+
+use pdf_writer::{Finish, Name, Pdf, Ref};
 use xmp_writer::{LangId, Timezone, XmpWriter};
 
 use crate::configure::{Configuration, PdfVersion, ValidationError};
+use crate::encryption::{Encryptor, PdfStr};
 use crate::serialize::SerializeContext;
 
 /// Metadata for a PDF document.
@@ -26,6 +30,8 @@ pub struct Metadata {
     pub(crate) creation_date: Option<DateTime>,
     pub(crate) text_direction: Option<TextDirection>,
     pub(crate) page_layout: Option<PageLayout>,
+    pub(crate) page_mode: Option<PageMode>,
+    pub(crate) viewer_preferences: Option<ViewerPreferences>,
 }
 
 impl Metadata {
@@ -119,6 +125,18 @@ impl Metadata {
         self
     }
 
+    /// How the viewer should display the document when it is opened.
+    pub fn page_mode(mut self, page_mode: PageMode) -> Self {
+        self.page_mode = Some(page_mode);
+        self
+    }
+
+    /// How the viewer should present the document.
+    pub fn viewer_preferences(mut self, viewer_preferences: ViewerPreferences) -> Self {
+        self.viewer_preferences = Some(viewer_preferences);
+        self
+    }
+
     pub(crate) fn has_document_info(&self) -> bool {
         self.title.is_some()
             || self.producer.is_some()
@@ -235,6 +253,7 @@ impl Metadata {
         ref_: &mut Ref,
         pdf: &mut Pdf,
         config: Configuration,
+        encryptor: Option<Encryptor>,
     ) {
         if !config.validator().allows_info_dict() {
             return;
@@ -248,35 +267,35 @@ impl Metadata {
             // to the XMP metadata.
             if config.version() < PdfVersion::Pdf20 {
                 if let Some(title) = &self.title {
-                    document_info.title(TextStr(title));
+                    document_info.pair(Name(b"Title"), PdfStr::text_str(title, encryptor));
                 }
 
                 if let Some(subject) = &self.subject {
-                    document_info.subject(TextStr(subject));
+                    document_info.pair(Name(b"Subject"), PdfStr::text_str(subject, encryptor));
                 }
 
                 if let Some(keywords) = &self.keywords {
                     let joined = keywords.join(", ");
-                    document_info.keywords(TextStr(&joined));
+                    document_info.pair(Name(b"Keywords"), PdfStr::text_str(&joined, encryptor));
                 }
 
                 if let Some(authors) = &self.authors {
                     let joined = authors.join(", ");
-                    document_info.author(TextStr(&joined));
+                    document_info.pair(Name(b"Author"), PdfStr::text_str(&joined, encryptor));
                 }
 
                 if let Some(creator) = &self.creator {
-                    document_info.creator(TextStr(creator));
+                    document_info.pair(Name(b"Creator"), PdfStr::text_str(creator, encryptor));
                 }
 
                 if let Some(producer) = &self.producer {
-                    document_info.producer(TextStr(producer));
+                    document_info.pair(Name(b"Producer"), PdfStr::text_str(producer, encryptor));
                 }
             }
 
             if let Some(date_time) = self.creation_date {
-                document_info.modified_date(pdf_date(date_time));
-                document_info.creation_date(pdf_date(date_time));
+                document_info.pair(Name(b"ModDate"), PdfStr::date(date_time, encryptor));
+                document_info.pair(Name(b"CreationDate"), PdfStr::date(date_time, encryptor));
             }
         }
     }
@@ -388,6 +407,26 @@ pub(crate) fn pdf_date(date_time: DateTime) -> pdf_writer::Date {
         .utc_offset_minute(date_time.utc_offset_minute)
 }
 
+/// Converts a datetime to the string that pdf-writer writes for `pdf_date`.
+pub(crate) fn pdf_date_string(date_time: DateTime) -> String {
+    let mut string = format!(
+        "D:{:04}{:02}{:02}{:02}{:02}{:02}",
+        date_time.year,
+        date_time.month.unwrap_or(1),
+        date_time.day.unwrap_or(1),
+        date_time.hour.unwrap_or(0),
+        date_time.minute.unwrap_or(0),
+        date_time.second.unwrap_or(0),
+    );
+
+    match date_time.utc_offset_hour.unwrap_or(0) {
+        0 if date_time.utc_offset_minute == 0 => string.push('Z'),
+        hour => string.push_str(&format!("{:+03}'{:02}", hour, date_time.utc_offset_minute)),
+    }
+
+    string
+}
+
 /// Converts a datetime to an xmp-writer datetime.
 fn xmp_date(datetime: DateTime) -> xmp_writer::DateTime {
     let timezone = match (datetime.utc_offset_hour, datetime.utc_offset_minute) {
@@ -413,7 +452,7 @@ fn xmp_date(datetime: DateTime) -> xmp_writer::DateTime {
 
 /// The main text direction of the document.
 #[allow(missing_docs)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum TextDirection {
     LeftToRight,
     RightToLeft,
@@ -429,7 +468,7 @@ impl TextDirection {
 }
 
 /// How the viewer should lay out the pages.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum PageLayout {
     /// Only a single page at a time.
     SinglePage,
@@ -461,3 +500,177 @@ impl PageLayout {
         }
     }
 }
+
+/// How the viewer should display the document when it is opened.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PageMode {
+    /// Neither the outline nor thumbnails are visible.
+    UseNone,
+    /// The outline is visible.
+    UseOutlines,
+    /// Thumbnails of the pages are visible.
+    UseThumbs,
+    /// The document is displayed in full-screen mode, without any
+    /// menu bar or other window controls.
+    FullScreen,
+    /// The panel with the embedded files is visible. PDF 1.6+.
+    UseAttachments,
+}
+
+impl PageMode {
+    pub(crate) fn to_pdf(self) -> pdf_writer::types::PageMode {
+        match self {
+            PageMode::UseNone => pdf_writer::types::PageMode::UseNone,
+            PageMode::UseOutlines => pdf_writer::types::PageMode::UseOutlines,
+            PageMode::UseThumbs => pdf_writer::types::PageMode::UseThumbs,
+            PageMode::FullScreen => pdf_writer::types::PageMode::FullScreen,
+            PageMode::UseAttachments => pdf_writer::types::PageMode::UseAttachments,
+        }
+    }
+}
+
+/// Preferences for how a viewer should present the document.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ViewerPreferences {
+    pub(crate) hide_toolbar: bool,
+    pub(crate) hide_menubar: bool,
+    pub(crate) hide_window_ui: bool,
+    pub(crate) fit_window: bool,
+    pub(crate) center_window: bool,
+    pub(crate) display_doc_title: Option<bool>,
+    pub(crate) direction: Option<TextDirection>,
+    pub(crate) duplex: Option<Duplex>,
+    pub(crate) print_scaling: Option<PrintScaling>,
+    pub(crate) print_page_range: Vec<RangeInclusive<usize>>,
+    pub(crate) num_copies: Option<NonZeroU32>,
+}
+
+impl ViewerPreferences {
+    /// Create new viewer preferences.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the toolbars of the viewer should be hidden while the
+    /// document is active.
+    pub fn hide_toolbar(mut self, hide_toolbar: bool) -> Self {
+        self.hide_toolbar = hide_toolbar;
+        self
+    }
+
+    /// Whether the menu bar of the viewer should be hidden while the
+    /// document is active.
+    pub fn hide_menubar(mut self, hide_menubar: bool) -> Self {
+        self.hide_menubar = hide_menubar;
+        self
+    }
+
+    /// Whether user interface elements like scroll bars should be hidden, so that
+    /// only the contents of the document are displayed.
+    pub fn hide_window_ui(mut self, hide_window_ui: bool) -> Self {
+        self.hide_window_ui = hide_window_ui;
+        self
+    }
+
+    /// Whether the window of the viewer should be resized to fit the size of
+    /// the first page.
+    pub fn fit_window(mut self, fit_window: bool) -> Self {
+        self.fit_window = fit_window;
+        self
+    }
+
+    /// Whether the window of the viewer should be centered on the screen.
+    pub fn center_window(mut self, center_window: bool) -> Self {
+        self.center_window = center_window;
+        self
+    }
+
+    /// Whether the title bar of the window should display the title of the document
+    /// instead of its file name.
+    ///
+    /// If not set, it will only be enabled if the validator requires it (e.g. PDF/UA).
+    pub fn display_doc_title(mut self, display_doc_title: bool) -> Self {
+        self.display_doc_title = Some(display_doc_title);
+        self
+    }
+
+    /// The reading order of the document, which determines how pages are laid out
+    /// when displayed side by side. For right-to-left languages like Arabic and Hebrew,
+    /// this should be set to [`TextDirection::RightToLeft`].
+    ///
+    /// Takes precedence over [`Metadata::text_direction`].
+    pub fn direction(mut self, direction: TextDirection) -> Self {
+        self.direction = Some(direction);
+        self
+    }
+
+    /// The duplex mode that should be preselected in the print dialog. PDF 1.7+.
+    pub fn duplex(mut self, duplex: Duplex) -> Self {
+        self.duplex = Some(duplex);
+        self
+    }
+
+    /// The page scaling option that should be preselected in the print dialog. PDF 1.6+.
+    pub fn print_scaling(mut self, print_scaling: PrintScaling) -> Self {
+        self.print_scaling = Some(print_scaling);
+        self
+    }
+
+    /// The ranges of page indices that should be preselected in the print dialog. PDF 1.7+.
+    ///
+    /// The ranges must be in ascending order and must not overlap.
+    pub fn print_page_range(mut self, print_page_range: Vec<RangeInclusive<usize>>) -> Self {
+        self.print_page_range = print_page_range;
+        self
+    }
+
+    /// The number of copies that should be preselected in the print dialog. PDF 1.7+.
+    pub fn num_copies(mut self, num_copies: NonZeroU32) -> Self {
+        self.num_copies = Some(num_copies);
+        self
+    }
+
+    /// Whether any of the preferences that are only available in PDF 1.7 have been set.
+    pub(crate) fn has_pdf17_preferences(&self) -> bool {
+        self.duplex.is_some() || !self.print_page_range.is_empty() || self.num_copies.is_some()
+    }
+}
+
+/// The duplex mode that should be used when printing the document.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Duplex {
+    /// Print single-sided.
+    Simplex,
+    /// Print double-sided and flip on the short edge of the sheet.
+    DuplexFlipShortEdge,
+    /// Print double-sided and flip on the long edge of the sheet.
+    DuplexFlipLongEdge,
+}
+
+impl Duplex {
+    pub(crate) fn to_name(self) -> Name<'static> {
+        match self {
+            Duplex::Simplex => Name(b"Simplex"),
+            Duplex::DuplexFlipShortEdge => Name(b"DuplexFlipShortEdge"),
+            Duplex::DuplexFlipLongEdge => Name(b"DuplexFlipLongEdge"),
+        }
+    }
+}
+
+/// The page scaling that should be used when printing the document.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PrintScaling {
+    /// Use the default scaling of the viewer.
+    AppDefault,
+    /// Don't scale the pages.
+    None,
+}
+
+impl PrintScaling {
+    pub(crate) fn to_name(self) -> Name<'static> {
+        match self {
+            PrintScaling::AppDefault => Name(b"AppDefault"),
+            PrintScaling::None => Name(b"None"),
+        }
+    }
+}
@@ -1,4 +1,5 @@
 pub mod embed;
+pub mod facturx;
 pub mod metadata;
 pub mod outline;
 pub mod tagging;
@@ -12,17 +12,34 @@
 //! nest in any way you wish while processing your document. In the end, you need to push
 //! the first layer of children to the [`Outline`] object.
 //!
+//! Each outline node either jumps to a destination in the document when clicking on it,
+//! or performs an arbitrary [`Action`], like opening a URL. Both kinds of nodes can be
+//! mixed freely within the same outline.
+//!
 //! Finally, once you are done building your outline tree, you can use the [`Document::set_outline`]
 //! function of [`Document`] to store the outline in the document.
 //!
+//! If you are creating a tagged document, you can alternatively let krilla generate
+//! the outline from the headings in the tag tree by calling
+//! [`Document::outline_from_headings`].
+//!
+//! [`Action`]: crate::action::Action
 //! [`Document`]: crate::Document
 //! [`Document::set_outline`]: crate::Document::set_outline
+//! [`Document::outline_from_headings`]: crate::Document::outline_from_headings
+
+use std::collections::HashMap;
+use std::num::NonZeroU32;
 
 use pdf_writer::writers::OutlineItem;
-use pdf_writer::{Chunk, Finish, Name, Ref, TextStr};
+use pdf_writer::{Chunk, Finish, Name, Ref};
 
+use crate::color::rgb;
 use crate::error::KrillaResult;
-use crate::interactive::destination::XyzDestination;
+use crate::geom::Point;
+use crate::interactive::action::Action;
+use crate::interactive::destination::{Destination, XyzDestination};
+use crate::interchange::tagging::{Heading, PageTagIdentifier};
 use crate::serialize::SerializeContext;
 
 /// An outline.
@@ -37,6 +54,56 @@ impl Outline {
     pub(crate) fn is_empty(&self) -> bool {
         self.children.is_empty()
     }
+
+    /// Synthesize an outline from a list of headings, nesting them according to
+    /// their levels.
+    ///
+    /// `page_position` maps the page index of a heading to the final position
+    /// of the page in the document.
+    pub(crate) fn from_headings(
+        headings: Vec<Heading>,
+        positions: &HashMap<PageTagIdentifier, Point>,
+        page_position: impl Fn(usize) -> usize,
+    ) -> Self {
+        let mut outline = Outline::new();
+        // The chain of nodes that can still receive children, along with their level.
+        let mut stack: Vec<(NonZeroU32, OutlineNode)> = vec![];
+
+        fn attach(
+            outline: &mut Outline,
+            stack: &mut [(NonZeroU32, OutlineNode)],
+            node: OutlineNode,
+        ) {
+            match stack.last_mut() {
+                Some((_, parent)) => parent.push_child(node),
+                None => outline.push_child(node),
+            }
+        }
+
+        for heading in headings {
+            while matches!(stack.last(), Some((level, _)) if *level >= heading.level) {
+                let (_, node) = stack.pop().unwrap();
+                attach(&mut outline, &mut stack, node);
+            }
+
+            // If nothing was drawn for the heading, fall back to the top of the page.
+            let point = positions
+                .get(&heading.location)
+                .copied()
+                .unwrap_or(Point::from_xy(0.0, 0.0));
+            let destination =
+                XyzDestination::new(page_position(heading.location.page_index), point);
+            let text = heading.title.unwrap_or_default().to_string();
+
+            stack.push((heading.level, OutlineNode::new(text, destination)));
+        }
+
+        while let Some((_, node)) = stack.pop() {
+            attach(&mut outline, &mut stack, node);
+        }
+
+        outline
+    }
 }
 
 impl Default for Outline {
@@ -102,7 +169,7 @@ impl Outline {
             &mut sub_chunks,
             sc,
             &mut outline,
-            false,
+            true,
         )?;
         outline.finish();
 
@@ -124,8 +191,23 @@ pub struct OutlineNode {
     children: Vec<OutlineNode>,
     /// The text of the outline entry.
     text: String,
-    /// The destination of the outline entry.
-    destination: XyzDestination,
+    /// What should happen when clicking on the outline entry.
+    target: OutlineTarget,
+    /// The color of the text of the outline entry.
+    color: Option<rgb::Color>,
+    /// Whether the text of the outline entry should be bold.
+    bold: bool,
+    /// Whether the text of the outline entry should be italic.
+    italic: bool,
+    /// Whether the children of the outline entry should be expanded by default.
+    open: bool,
+}
+
+/// What should happen when clicking on an outline entry.
+#[derive(Debug, Clone)]
+enum OutlineTarget {
+    Destination(Destination),
+    Action(Action),
 }
 
 impl OutlineNode {
@@ -134,14 +216,56 @@ impl OutlineNode {
     /// `text` is the string that should be displayed in the outline tree, and
     /// `destination` is the destination that should be jumped to when clicking on
     /// the outline entry.
-    pub fn new(text: String, destination: XyzDestination) -> Self {
+    pub fn new(text: String, destination: impl Into<Destination>) -> Self {
+        Self::new_with_target(text, OutlineTarget::Destination(destination.into()))
+    }
+
+    /// Create a new outline node that performs an action when clicking on it.
+    ///
+    /// `text` is the string that should be displayed in the outline tree, and
+    /// `action` is the action that should be performed when clicking on the
+    /// outline entry.
+    pub fn new_with_action(text: String, action: Action) -> Self {
+        Self::new_with_target(text, OutlineTarget::Action(action))
+    }
+
+    fn new_with_target(text: String, target: OutlineTarget) -> Self {
         Self {
             children: vec![],
             text,
-            destination,
+            target,
+            color: None,
+            bold: false,
+            italic: false,
+            open: false,
         }
     }
 
+    /// Set the color of the text of the outline entry.
+    pub fn with_color(mut self, color: rgb::Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Set whether the text of the outline entry should be bold.
+    pub fn with_bold(mut self, bold: bool) -> Self {
+        self.bold = bold;
+        self
+    }
+
+    /// Set whether the text of the outline entry should be italic.
+    pub fn with_italic(mut self, italic: bool) -> Self {
+        self.italic = italic;
+        self
+    }
+
+    /// Set whether the children of the outline entry should be expanded when the
+    /// document is opened. By default, they are collapsed.
+    pub fn with_open(mut self, open: bool) -> Self {
+        self.open = open;
+        self
+    }
+
     /// Add a new child to the outline node.
     pub fn push_child(&mut self, node: OutlineNode) {
         self.children.push(node)
@@ -176,13 +300,53 @@ impl OutlineNode {
             &mut sub_chunks,
             sc,
             &mut outline_entry,
-            true,
+            self.open,
         )?;
 
-        outline_entry.title(TextStr(&self.text));
+        outline_entry.pair(Name(b"Title"), sc.text_str(&self.text));
 
-        let dest_ref = sc.register_xyz_destination(self.destination.clone());
-        outline_entry.pair(Name(b"Dest"), dest_ref);
+        if let Some(color) = self.color {
+            outline_entry
+                .insert(Name(b"C"))
+                .array()
+                .items(color.to_pdf_color());
+        }
+
+        let mut flags = 0;
+        if self.italic {
+            // Italic
+            flags |= 1;
+        }
+        if self.bold {
+            // Bold
+            flags |= 1 << 1;
+        }
+        if flags != 0 {
+            outline_entry.pair(Name(b"F"), flags);
+        }
+
+        match &self.target {
+            // Structure destinations need to be wrapped in a go-to action.
+            OutlineTarget::Destination(destination) if destination.requires_action(sc) => {
+                Action::Goto(destination.clone()).serialize(
+                    sc,
+                    outline_entry
+                        .insert(Name(b"A"))
+                        .start::<pdf_writer::writers::Action>(),
+                )?;
+            }
+            OutlineTarget::Destination(destination) => {
+                destination.serialize(sc, outline_entry.insert(Name(b"Dest")))?;
+            }
+            OutlineTarget::Action(action) => {
+                action.serialize(
+                    sc,
+                    outline_entry
+                        .insert(Name(b"A"))
+                        .start::<pdf_writer::writers::Action>(),
+                )?;
+            }
+        }
 
         outline_entry.finish();
 
@@ -200,7 +364,7 @@ fn serialize_children(
     sub_chunks: &mut Vec<Chunk>,
     sc: &mut SerializeContext,
     outlineable: &mut impl Outlineable,
-    negate_count: bool,
+    open: bool,
 ) -> KrillaResult<()> {
     if !children.is_empty() {
         let first = sc.new_ref();
@@ -227,8 +391,11 @@ fn serialize_children(
         outlineable.first(first);
         outlineable.last(last);
 
-        let mut count = i32::try_from(children.len()).unwrap();
-        if negate_count {
+        // For open entries, the count is the number of visible descendants, while for
+        // closed ones, it's the negated number of descendants that would be visible if
+        // the entry was opened.
+        let mut count = i32::try_from(visible_descendants(children)).unwrap();
+        if !open {
             count = -count;
         }
         outlineable.count(count);
@@ -236,3 +403,16 @@ fn serialize_children(
 
     Ok(())
 }
+
+fn visible_descendants(children: &[OutlineNode]) -> usize {
+    children
+        .iter()
+        .map(|child| {
+            1 + if child.open {
+                visible_descendants(&child.children)
+            } else {
+                0
+            }
+        })
+        .sum()
+}
@@ -122,13 +122,17 @@
 use std::cmp::PartialEq;
 use std::collections::{BTreeMap, HashMap};
 use std::num::NonZeroU32;
+use std::ops::Range;
 
 use pdf_writer::types::{ArtifactSubtype, StructRole};
 use pdf_writer::writers::{PropertyList, StructElement};
-use pdf_writer::{Chunk, Finish, Name, Ref, Str, TextStr};
+use pdf_writer::{Chunk, Finish, Name, Ref, TextStr};
 
 use crate::configure::{PdfVersion, ValidationError};
-use crate::error::KrillaResult;
+use crate::error::{KrillaError, KrillaResult};
+use crate::geom::Rect;
+use crate::interchange::embed::{supports_associated_files, AssociatedFile, EmbeddedFile};
+use crate::page::page_root_transform;
 use crate::serialize::SerializeContext;
 
 /// A type of artifact.
@@ -138,9 +142,20 @@ pub enum ArtifactType {
     Header,
     /// The footer of the page.
     Footer,
+    /// A watermark of a page.
+    Watermark,
+    /// Any other pagination artifact, such as for example page numbers.
+    Pagination,
+    /// Layout artifacts, such as for example decorative rules or table strokes.
+    Layout,
     /// Page artifacts, such as for example cut marks or color bars.
     Page,
-    /// Any other type of artifact (e.g. table strokes).
+    /// The background of a page or of some other content.
+    ///
+    /// Background artifacts should have a bounding box. In PDF versions before
+    /// PDF 1.7, they are written as layout artifacts.
+    Background,
+    /// Any other type of artifact.
     Other,
 }
 
@@ -149,7 +164,11 @@ impl ArtifactType {
         match self {
             ArtifactType::Header => true,
             ArtifactType::Footer => true,
+            ArtifactType::Watermark => true,
+            ArtifactType::Pagination => true,
+            ArtifactType::Layout => true,
             ArtifactType::Page => true,
+            ArtifactType::Background => true,
             ArtifactType::Other => false,
         }
     }
@@ -173,7 +192,10 @@ pub enum ContentTag<'a> {
     /// Artifacts represent pieces of content that are not really part of the logical structure
     /// of a document and should be excluded in the logical tree. These include for example headers,
     /// footers, page background and similar.
-    Artifact(ArtifactType),
+    ///
+    /// Can optionally contain the bounding box of the artifact, in the coordinates of
+    /// the page.
+    Artifact(ArtifactType, Option<Rect>),
     /// A content tag that wraps some text with specific properties.
     ///
     /// Spans should not be too long. At most, they should contain a single line of text, but they
@@ -189,7 +211,7 @@ pub enum ContentTag<'a> {
 impl ContentTag<'_> {
     pub(crate) fn name(&self) -> Name {
         match self {
-            ContentTag::Artifact(_) => Name(b"Artifact"),
+            ContentTag::Artifact(..) => Name(b"Artifact"),
             ContentTag::Span(_) => Name(b"Span"),
             ContentTag::Other => Name(b"P"),
         }
@@ -197,19 +219,26 @@ impl ContentTag<'_> {
 
     pub(crate) fn write_properties(&self, sc: &mut SerializeContext, mut properties: PropertyList) {
         match self {
-            ContentTag::Artifact(at) => {
+            ContentTag::Artifact(at, bbox) => {
                 let mut artifact = properties.artifact();
+                let pdf_version = sc.serialize_settings().pdf_version();
 
                 let artifact_type = match at {
-                    ArtifactType::Header => pdf_writer::types::ArtifactType::Pagination,
-                    ArtifactType::Footer => pdf_writer::types::ArtifactType::Pagination,
-                    ArtifactType::Page => pdf_writer::types::ArtifactType::Page,
-                    // This method should only be called with artifacts that actually
-                    // require a property.
-                    ArtifactType::Other => unreachable!(),
+                    ArtifactType::Header => Some(pdf_writer::types::ArtifactType::Pagination),
+                    ArtifactType::Footer => Some(pdf_writer::types::ArtifactType::Pagination),
+                    ArtifactType::Watermark => Some(pdf_writer::types::ArtifactType::Pagination),
+                    ArtifactType::Pagination => Some(pdf_writer::types::ArtifactType::Pagination),
+                    ArtifactType::Layout => Some(pdf_writer::types::ArtifactType::Layout),
+                    ArtifactType::Page => Some(pdf_writer::types::ArtifactType::Page),
+                    ArtifactType::Background if pdf_version >= PdfVersion::Pdf17 => {
+                        Some(pdf_writer::types::ArtifactType::Background)
+                    }
+                    ArtifactType::Background => Some(pdf_writer::types::ArtifactType::Layout),
+                    // Artifacts of other types only have properties if they have a bbox.
+                    ArtifactType::Other => None,
                 };
 
-                if sc.serialize_settings().pdf_version() >= PdfVersion::Pdf17 {
+                if pdf_version >= PdfVersion::Pdf17 {
                     if *at == ArtifactType::Header {
                         artifact.attached([pdf_writer::types::ArtifactAttachment::Top]);
                         artifact.subtype(ArtifactSubtype::Header);
@@ -219,9 +248,20 @@ impl ContentTag<'_> {
                         artifact.attached([pdf_writer::types::ArtifactAttachment::Bottom]);
                         artifact.subtype(ArtifactSubtype::Footer);
                     }
+
+                    if *at == ArtifactType::Watermark {
+                        artifact.subtype(ArtifactSubtype::Watermark);
+                    }
                 }
 
-                artifact.kind(artifact_type);
+                // The bbox has already been converted to PDF coordinates by the surface.
+                if let Some(bbox) = bbox {
+                    artifact.bounding_box(bbox.to_pdf_rect());
+                }
+
+                if let Some(artifact_type) = artifact_type {
+                    artifact.kind(artifact_type);
+                }
             }
             ContentTag::Span(SpanTag {
                 lang,
@@ -230,6 +270,10 @@ impl ContentTag<'_> {
                 actual_text,
             }) => {
                 if let Some(lang) = lang {
+                    if lang.is_empty() {
+                        sc.register_empty_language();
+                    }
+
                     properties.pair(Name(b"Lang"), TextStr(lang));
                 }
 
@@ -257,7 +301,7 @@ impl ContentTag<'_> {
 /// A span tag.
 #[derive(Clone, Copy, Debug)]
 pub struct SpanTag<'a> {
-    /// The language of the text.
+    /// The language of the text, which must not be empty.
     pub lang: Option<Lang<'a>>,
     /// An optional alternate text that describes the text (for example, if the text consists
     /// of a star symbol, the alt text should describe that in natural language).
@@ -348,7 +392,7 @@ pub(crate) enum IdentifierType {
     AnnotationIdentifier(AnnotationIdentifier),
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub(crate) enum IdentifierInner {
     Real(IdentifierType),
     Dummy,
@@ -357,7 +401,7 @@ pub(crate) enum IdentifierInner {
 /// An identifier for an annotation or certain parts of page content.
 ///
 /// Need to be used as a leaf node in a tag tree.
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Identifier(pub(crate) IdentifierInner);
 
 impl Identifier {
@@ -415,10 +459,15 @@ pub enum Tag {
     /// A list item.
     ///
     /// **Best practice**: Should consist of one or more list labels and/or list bodies.
+    ///
+    /// In PDF/UA, list items of lists with a numbering other than
+    /// [`ListNumbering::None`] need to have a label.
     LI,
     /// Label for a list item.
     Lbl,
     /// Description of the list item.
+    ///
+    /// **Best practice**: May contain a nested list.
     LBody,
     /// A table.
     ///
@@ -432,9 +481,13 @@ pub enum Tag {
     TR,
     /// A table header cell.
     // Table header scope is only required for PDF/UA, but we include it always for simplicity.
-    TH(TableHeaderScope),
+    TH(TableHeaderCell),
     /// A table data cell.
-    TD,
+    ///
+    /// In PDF/UA, each data cell needs to be associated with header cells, either
+    /// because a header cell in the same row or column has a matching scope, or
+    /// because the data cell refers to header cells by their IDs.
+    TD(TableDataCell),
     /// A table header row group.
     THead,
     /// A table data row group.
@@ -445,7 +498,9 @@ pub enum Tag {
     InlineQuote,
     /// A foot- or endnote, potentially referred to from within the text.
     ///
-    /// **Best practice**: It may have a label as a child.
+    /// **Best practice**: It may have a label as a child. Notes always have an ID, which
+    /// is generated automatically if none was set with [`TagGroup::set_id`], so that
+    /// references can refer to it with [`TagGroup::add_ref`].
     Note,
     /// A reference to elsewhere in the document.
     ///
@@ -475,11 +530,37 @@ pub enum Tag {
     /// Item of graphical content, with some optional alt text.
     ///
     /// Providing the alt text is required in some export modes, like for example PDF/UA1.
+    /// A bounding box can be set with [`TagGroup::set_bbox`].
     Figure(Option<String>),
     /// A mathematical formula, with some optional alt text.
     ///
     /// Providing the alt text is required in some export modes, like for example PDF/UA1.
     Formula(Option<String>),
+    /// A ruby annotation, for example furigana in Japanese text.
+    ///
+    /// **Best practice**: Should consist of a ruby base text, followed by the ruby
+    /// annotation text, which can optionally be surrounded by ruby punctuation.
+    /// Export will panic if a ruby contains anything else than these tags.
+    Ruby,
+    /// The ruby base text. Can only appear within a [`Tag::Ruby`].
+    RB,
+    /// The ruby annotation text. Can only appear within a [`Tag::Ruby`].
+    RT,
+    /// Ruby punctuation surrounding the annotation text, which is shown if ruby is
+    /// not supported. Can only appear within a [`Tag::Ruby`].
+    RP,
+    /// A warichu annotation, i.e. a comment set in two smaller lines within a line of
+    /// Japanese or Chinese text.
+    ///
+    /// **Best practice**: Should consist of the warichu text, which can optionally be
+    /// surrounded by warichu punctuation. Export will panic if a warichu contains anything
+    /// else than these tags.
+    Warichu,
+    /// The warichu text. Can only appear within a [`Tag::Warichu`].
+    WT,
+    /// Warichu punctuation surrounding the warichu text. Can only appear within a
+    /// [`Tag::Warichu`].
+    WP,
     // All below are non-standard attributes.
     /// A date or time.
     Datetime,
@@ -487,6 +568,9 @@ pub enum Tag {
     Terms,
     /// A title.
     Title,
+    /// A tag with a custom name, for example from a different format, which can be
+    /// mapped to a standard tag.
+    Custom(CustomTag),
 }
 
 impl Tag {
@@ -496,43 +580,10 @@ impl Tag {
             // Fall back to P in case the tag is not supported with the current
             // PDF version
             struct_elem.kind(StructRole::P);
+        } else if let Some(role) = self.struct_role() {
+            struct_elem.kind(role);
         } else {
             match self {
-                Tag::Part => struct_elem.kind(StructRole::Part),
-                Tag::Article => struct_elem.kind(StructRole::Art),
-                Tag::Section => struct_elem.kind(StructRole::Sect),
-                Tag::BlockQuote => struct_elem.kind(StructRole::BlockQuote),
-                Tag::Caption => struct_elem.kind(StructRole::Caption),
-                Tag::TOC => struct_elem.kind(StructRole::TOC),
-                Tag::TOCI => struct_elem.kind(StructRole::TOCI),
-                Tag::Index => struct_elem.kind(StructRole::Index),
-                Tag::P => struct_elem.kind(StructRole::P),
-                Tag::Hn(n, _) if n.get() == 1 => struct_elem.kind(StructRole::H1),
-                Tag::Hn(n, _) if n.get() == 2 => struct_elem.kind(StructRole::H2),
-                Tag::Hn(n, _) if n.get() == 3 => struct_elem.kind(StructRole::H3),
-                Tag::Hn(n, _) if n.get() == 4 => struct_elem.kind(StructRole::H4),
-                Tag::Hn(n, _) if n.get() == 5 => struct_elem.kind(StructRole::H5),
-                Tag::Hn(n, _) if n.get() == 6 => struct_elem.kind(StructRole::H6),
-                Tag::L(_) => struct_elem.kind(StructRole::L),
-                Tag::LI => struct_elem.kind(StructRole::LI),
-                Tag::Lbl => struct_elem.kind(StructRole::Lbl),
-                Tag::LBody => struct_elem.kind(StructRole::LBody),
-                Tag::Table => struct_elem.kind(StructRole::Table),
-                Tag::TR => struct_elem.kind(StructRole::TR),
-                Tag::TH(_) => struct_elem.kind(StructRole::TH),
-                Tag::TD => struct_elem.kind(StructRole::TD),
-                Tag::THead => struct_elem.kind(StructRole::THead),
-                Tag::TBody => struct_elem.kind(StructRole::TBody),
-                Tag::TFoot => struct_elem.kind(StructRole::TFoot),
-                Tag::InlineQuote => struct_elem.kind(StructRole::Quote),
-                Tag::Note => struct_elem.kind(StructRole::Note),
-                Tag::Reference => struct_elem.kind(StructRole::Reference),
-                Tag::BibEntry => struct_elem.kind(StructRole::BibEntry),
-                Tag::Code => struct_elem.kind(StructRole::Code),
-                Tag::Link => struct_elem.kind(StructRole::Link),
-                Tag::Annot => struct_elem.kind(StructRole::Annot),
-                Tag::Figure(_) => struct_elem.kind(StructRole::Figure),
-                Tag::Formula(_) => struct_elem.kind(StructRole::Formula),
                 // Every additional tag needs to be registered in the role map!
                 Tag::Datetime => struct_elem.custom_kind(Name(b"Datetime")),
                 Tag::Terms => struct_elem.custom_kind(Name(b"Terms")),
@@ -545,10 +596,99 @@ impl Tag {
                     let name = format!("H{level}");
                     struct_elem.custom_kind(Name(name.as_bytes()))
                 }
+                Tag::Custom(custom) => {
+                    match custom
+                        .role
+                        .as_ref()
+                        .and_then(|r| r.mapped_role(pdf_version))
+                    {
+                        Some(role) => {
+                            sc.global_objects
+                                .custom_roles
+                                .insert(custom.name.clone(), role);
+                        }
+                        None => sc.register_validation_error(
+                            ValidationError::MissingCustomTagRole(custom.name.clone()),
+                        ),
+                    }
+
+                    struct_elem.custom_kind(Name(custom.name.as_bytes()))
+                }
+                _ => unreachable!(),
             };
         }
     }
 
+    /// Return the standard structure type of the tag, or `None` if it's a
+    /// non-standard tag.
+    fn struct_role(&self) -> Option<StructRole> {
+        Some(match self {
+            Tag::Part => StructRole::Part,
+            Tag::Article => StructRole::Art,
+            Tag::Section => StructRole::Sect,
+            Tag::BlockQuote => StructRole::BlockQuote,
+            Tag::Caption => StructRole::Caption,
+            Tag::TOC => StructRole::TOC,
+            Tag::TOCI => StructRole::TOCI,
+            Tag::Index => StructRole::Index,
+            Tag::P => StructRole::P,
+            Tag::Hn(n, _) if n.get() == 1 => StructRole::H1,
+            Tag::Hn(n, _) if n.get() == 2 => StructRole::H2,
+            Tag::Hn(n, _) if n.get() == 3 => StructRole::H3,
+            Tag::Hn(n, _) if n.get() == 4 => StructRole::H4,
+            Tag::Hn(n, _) if n.get() == 5 => StructRole::H5,
+            Tag::Hn(n, _) if n.get() == 6 => StructRole::H6,
+            Tag::L(_) => StructRole::L,
+            Tag::LI => StructRole::LI,
+            Tag::Lbl => StructRole::Lbl,
+            Tag::LBody => StructRole::LBody,
+            Tag::Table => StructRole::Table,
+            Tag::TR => StructRole::TR,
+            Tag::TH(_) => StructRole::TH,
+            Tag::TD(_) => StructRole::TD,
+            Tag::THead => StructRole::THead,
+            Tag::TBody => StructRole::TBody,
+            Tag::TFoot => StructRole::TFoot,
+            Tag::InlineQuote => StructRole::Quote,
+            Tag::Note => StructRole::Note,
+            Tag::Reference => StructRole::Reference,
+            Tag::BibEntry => StructRole::BibEntry,
+            Tag::Code => StructRole::Code,
+            Tag::Link => StructRole::Link,
+            Tag::Annot => StructRole::Annot,
+            Tag::Figure(_) => StructRole::Figure,
+            Tag::Formula(_) => StructRole::Formula,
+            Tag::Ruby => StructRole::Ruby,
+            Tag::RB => StructRole::RB,
+            Tag::RT => StructRole::RT,
+            Tag::RP => StructRole::RP,
+            Tag::Warichu => StructRole::Warichu,
+            Tag::WT => StructRole::WT,
+            Tag::WP => StructRole::WP,
+            Tag::Hn(_, _) | Tag::Datetime | Tag::Terms | Tag::Title | Tag::Custom(_) => {
+                return None
+            }
+        })
+    }
+
+    /// Return the standard structure type the tag is mapped to when it's used as
+    /// the role of a custom tag.
+    fn mapped_role(&self, pdf_version: PdfVersion) -> Option<StructRole> {
+        if self.minimum_version() > pdf_version {
+            return Some(StructRole::P);
+        }
+
+        self.struct_role().or_else(|| match self {
+            // The same roles as in the role map.
+            Tag::Datetime => Some(StructRole::Span),
+            Tag::Terms => Some(StructRole::Part),
+            Tag::Title => Some(StructRole::H1),
+            Tag::Hn(_, _) => Some(StructRole::P),
+            Tag::Custom(custom) => custom.role.as_ref()?.mapped_role(pdf_version),
+            _ => None,
+        })
+    }
+
     pub(crate) fn can_have_alt(&self) -> bool {
         matches!(self, Tag::Figure(_) | Tag::Formula(_))
     }
@@ -580,7 +720,7 @@ impl Tag {
             Tag::Table => PdfVersion::Pdf14,
             Tag::TR => PdfVersion::Pdf14,
             Tag::TH(_) => PdfVersion::Pdf14,
-            Tag::TD => PdfVersion::Pdf14,
+            Tag::TD(_) => PdfVersion::Pdf14,
             Tag::THead => PdfVersion::Pdf15,
             Tag::TBody => PdfVersion::Pdf15,
             Tag::TFoot => PdfVersion::Pdf15,
@@ -593,9 +733,17 @@ impl Tag {
             Tag::Annot => PdfVersion::Pdf15,
             Tag::Figure(_) => PdfVersion::Pdf15,
             Tag::Formula(_) => PdfVersion::Pdf15,
+            Tag::Ruby => PdfVersion::Pdf15,
+            Tag::RB => PdfVersion::Pdf15,
+            Tag::RT => PdfVersion::Pdf15,
+            Tag::RP => PdfVersion::Pdf15,
+            Tag::Warichu => PdfVersion::Pdf15,
+            Tag::WT => PdfVersion::Pdf15,
+            Tag::WP => PdfVersion::Pdf15,
             Tag::Datetime => PdfVersion::Pdf15,
             Tag::Terms => PdfVersion::Pdf15,
             Tag::Title => PdfVersion::Pdf15,
+            Tag::Custom(_) => PdfVersion::Pdf14,
         }
     }
 
@@ -609,6 +757,19 @@ impl Tag {
     pub(crate) fn can_have_title(&self) -> bool {
         matches!(self, Tag::Hn(_, _))
     }
+
+    pub(crate) fn can_have_bbox(&self) -> bool {
+        matches!(self, Tag::Figure(_) | Tag::Formula(_) | Tag::Table)
+    }
+
+    /// Return the span and headers of table cells.
+    fn table_cell(&self) -> Option<(TableCellSpan, &[String])> {
+        match self {
+            Tag::TH(cell) => Some((cell.span, &cell.headers)),
+            Tag::TD(cell) => Some((cell.span, &cell.headers)),
+            _ => None,
+        }
+    }
 }
 
 /// A node in a tag tree.
@@ -626,7 +787,7 @@ impl Node {
         parent_tree_map: &mut HashMap<IdentifierType, Ref>,
         id_tree: &mut BTreeMap<String, Ref>,
         parent: Ref,
-        note_id: &mut u32,
+        ids: &mut StructIds,
         struct_elems: &mut Vec<Chunk>,
     ) -> KrillaResult<Option<Reference>> {
         match self {
@@ -635,7 +796,7 @@ impl Node {
                 parent_tree_map,
                 id_tree,
                 parent,
-                note_id,
+                ids,
                 struct_elems,
             )?)),
             Node::Leaf(ci) => match ci.0 {
@@ -668,6 +829,18 @@ pub(crate) enum Reference {
 pub struct TagGroup {
     /// The tag of the tag group.
     tag: Tag,
+    /// The ID of the tag group.
+    id: Option<String>,
+    /// The language of the tag group.
+    lang: Option<String>,
+    /// The actual text of the tag group.
+    actual_text: Option<String>,
+    /// The IDs of the tag groups this tag group refers to.
+    refs: Vec<String>,
+    /// The layout attributes of the tag group.
+    layout: Box<LayoutAttributes>,
+    /// The files associated with the tag group.
+    associated_files: Vec<EmbeddedFile>,
     /// The children of the tag group.
     children: Vec<Node>,
 }
@@ -677,13 +850,28 @@ impl TagGroup {
     pub fn new(tag: Tag) -> Self {
         Self {
             tag,
+            id: None,
+            lang: None,
+            actual_text: None,
+            refs: vec![],
+            layout: Box::default(),
+            associated_files: vec![],
             children: vec![],
         }
     }
 
     /// Create a new tag group with a specific tag and a list of children.
     pub fn with_children(tag: Tag, children: Vec<Node>) -> Self {
-        Self { tag, children }
+        Self {
+            tag,
+            id: None,
+            lang: None,
+            actual_text: None,
+            refs: vec![],
+            layout: Box::default(),
+            associated_files: vec![],
+            children,
+        }
     }
 
     /// Append a new child to the tag group.
@@ -691,21 +879,134 @@ impl TagGroup {
         self.children.push(child.into())
     }
 
+    /// Set the ID of the tag group, which allows other tag groups to refer to it,
+    /// for example table cells to their header cells.
+    ///
+    /// IDs need to be unique in the whole tag tree, otherwise export will panic.
+    pub fn set_id(&mut self, id: impl Into<String>) {
+        self.id = Some(id.into());
+    }
+
+    /// Set the language of the content of the tag group, as specified in RFC 3066.
+    ///
+    /// If no language is set, the language of the enclosing tag group applies, or the
+    /// language of the document set in the [`Metadata`]. The language must not be empty,
+    /// otherwise export will fail with [`KrillaError::EmptyLanguage`].
+    ///
+    /// [`Metadata`]: crate::metadata::Metadata
+    pub fn set_lang(&mut self, lang: impl Into<String>) {
+        self.lang = Some(lang.into());
+    }
+
+    /// Set the actual text of the tag group, which replaces the text of all of its
+    /// content, for example when extracting text.
+    pub fn set_actual_text(&mut self, actual_text: impl Into<String>) {
+        self.actual_text = Some(actual_text.into());
+    }
+
+    /// Add a reference to another tag group with the given ID, for example from the
+    /// reference to a footnote to the note itself.
+    ///
+    /// References are only written in PDF 2.0. The ID needs to be set for a tag
+    /// group in the tag tree with [`TagGroup::set_id`], otherwise export will panic.
+    pub fn add_ref(&mut self, id: impl Into<String>) {
+        self.refs.push(id.into());
+    }
+
+    /// Embed a file and associate it with the tag group, using the association kind
+    /// of the file. For example, this can be used to provide the data a chart was
+    /// created from.
+    ///
+    /// Associated files require PDF 2.0 or PDF/A-3 and later, otherwise the file
+    /// is ignored. The file is not listed among the attachments of the document.
+    pub fn add_associated_file(&mut self, file: EmbeddedFile) {
+        self.associated_files.push(file);
+    }
+
+    /// Set the layout attributes of the tag group, replacing any previously set
+    /// placement and bounding box.
+    pub fn set_layout(&mut self, layout: LayoutAttributes) {
+        *self.layout = layout;
+    }
+
+    /// Set how the tag group is placed relative to its surroundings.
+    pub fn set_placement(&mut self, placement: Placement) {
+        self.layout.placement = Some(placement);
+    }
+
+    /// Set the bounding box of the tag group, which is only written for figures,
+    /// formulas and tables.
+    ///
+    /// All content of the tag group needs to be on the same page, otherwise export
+    /// will fail with [`KrillaError::MultiPageBBox`].
+    pub fn set_bbox(&mut self, bbox: BBox) {
+        self.layout.bbox = Some(bbox);
+    }
+
+    /// Return the bounding box of the tag group in PDF coordinates, or `None` if
+    /// it doesn't have any content.
+    fn pdf_bbox(
+        &self,
+        sc: &SerializeContext,
+        bbox: BBox,
+    ) -> KrillaResult<Option<pdf_writer::Rect>> {
+        let mut identifiers = vec![];
+        collect_identifiers(&self.children, &mut identifiers);
+
+        let mut page_indices = identifiers.iter().map(|identifier| match identifier {
+            IdentifierType::PageIdentifier(pi) => pi.page_index,
+            IdentifierType::AnnotationIdentifier(ai) => ai.page_index,
+        });
+
+        let Some(page_index) = page_indices.next() else {
+            return Ok(None);
+        };
+
+        if page_indices.any(|i| i != page_index) {
+            return Err(KrillaError::MultiPageBBox);
+        }
+
+        let rect = match bbox {
+            BBox::Auto => identifiers
+                .iter()
+                .filter_map(|identifier| match identifier {
+                    IdentifierType::PageIdentifier(pi) => sc.tag_bbox(*pi),
+                    IdentifierType::AnnotationIdentifier(_) => None,
+                })
+                .reduce(|mut bbox, other| {
+                    bbox.expand(&other);
+                    bbox
+                }),
+            BBox::Rect(rect) => Some(rect),
+        };
+
+        let height = sc.page_infos()[page_index].surface_size.height();
+
+        Ok(rect
+            .and_then(|rect| rect.transform(page_root_transform(height)))
+            .map(|rect| rect.to_pdf_rect()))
+    }
+
     pub(crate) fn serialize(
         &self,
         sc: &mut SerializeContext,
         parent_tree_map: &mut HashMap<IdentifierType, Ref>,
         id_tree: &mut BTreeMap<String, Ref>,
         parent: Ref,
-        note_id: &mut u32,
+        ids: &mut StructIds,
         struct_elems: &mut Vec<Chunk>,
     ) -> KrillaResult<Reference> {
-        let root_ref = sc.new_ref();
+        // Tag groups with an ID already have a reference, so that they can be referred
+        // to before they are serialized.
+        let root_ref = match &self.id {
+            Some(id) => ids.get_ref(id),
+            None => sc.new_ref(),
+        };
         let mut children_refs = vec![];
 
         for child in &self.children {
             let serialized =
-                child.serialize(sc, parent_tree_map, id_tree, parent, note_id, struct_elems)?;
+                child.serialize(sc, parent_tree_map, id_tree, parent, ids, struct_elems)?;
             if let Some(ref_) = serialized {
                 children_refs.push(ref_);
             }
@@ -717,37 +1018,133 @@ impl TagGroup {
         struct_elem.parent(parent);
 
         if let Some(alt) = self.tag.alt() {
-            struct_elem.alt(TextStr(alt));
+            struct_elem.pair(Name(b"Alt"), sc.text_str(alt));
         } else if self.tag.can_have_alt() {
             sc.register_validation_error(ValidationError::MissingAltText);
         }
 
         if let Some(title) = self.tag.title() {
-            struct_elem.title(TextStr(title));
+            struct_elem.pair(Name(b"T"), sc.text_str(title));
         } else if self.tag.can_have_title() {
             sc.register_validation_error(ValidationError::MissingHeadingTitle);
         }
 
-        match self.tag {
+        if let Some(lang) = &self.lang {
+            if lang.is_empty() {
+                return Err(KrillaError::EmptyLanguage(None));
+            }
+
+            struct_elem.pair(Name(b"Lang"), sc.text_str(lang));
+        }
+
+        if let Some(actual_text) = &self.actual_text {
+            struct_elem.pair(Name(b"ActualText"), sc.text_str(actual_text));
+        }
+
+        if !self.refs.is_empty() && sc.serialize_settings().pdf_version() >= PdfVersion::Pdf20 {
+            struct_elem
+                .insert(Name(b"Ref"))
+                .array()
+                .items(self.refs.iter().map(|id| ids.get_ref(id)));
+        }
+
+        if !self.associated_files.is_empty() && supports_associated_files(sc) {
+            let refs = self
+                .associated_files
+                .iter()
+                .map(|file| sc.register_cacheable(AssociatedFile(file.clone())))
+                .collect::<Vec<_>>();
+
+            struct_elem.insert(Name(b"AF")).array().items(refs);
+        }
+
+        let list_numbering = match &self.tag {
             Tag::L(ln) => {
-                struct_elem
-                    .attributes()
-                    .push()
-                    .list()
-                    .list_numbering(ln.to_pdf());
+                if *ln != ListNumbering::None && !list_has_labels(&self.children) {
+                    sc.register_validation_error(ValidationError::MissingListLabel);
+                }
+
+                Some(*ln)
             }
-            Tag::TH(ths) => {
-                if sc.serialize_settings().pdf_version() >= PdfVersion::Pdf15 {
-                    struct_elem.attributes().push().table().scope(ths.to_pdf());
+            Tag::Table => {
+                if !table_has_headers(&self.children) {
+                    sc.register_validation_error(ValidationError::MissingTableHeaders);
                 }
+
+                None
             }
-            Tag::Note => {
-                let id = format!("Note {}", note_id);
-                *note_id += 1;
-                id_tree.insert(id.clone(), root_ref);
-                struct_elem.id(Str(id.as_bytes()));
+            _ => None,
+        };
+
+        let table_cell = self.tag.table_cell().and_then(|(span, headers)| {
+            let scope = match &self.tag {
+                Tag::TH(cell) if sc.serialize_settings().pdf_version() >= PdfVersion::Pdf15 => {
+                    Some(cell.scope)
+                }
+                _ => None,
+            };
+
+            (scope.is_some() || span != TableCellSpan::default() || !headers.is_empty())
+                .then_some((scope, span, headers))
+        });
+
+        let bbox = match self.layout.bbox {
+            Some(bbox) if self.tag.can_have_bbox() => self.pdf_bbox(sc, bbox)?,
+            _ => None,
+        };
+
+        let has_layout = self.layout.has_non_default_values() || bbox.is_some();
+
+        if list_numbering.is_some() || table_cell.is_some() || has_layout {
+            let mut attributes = struct_elem.attributes();
+
+            if let Some(ln) = list_numbering {
+                attributes.push().list().list_numbering(ln.to_pdf());
+            }
+
+            if let Some((scope, span, headers)) = table_cell {
+                let mut table = attributes.push().table();
+
+                if let Some(scope) = scope {
+                    table.scope(scope.to_pdf());
+                }
+
+                if span.rows.get() != 1 {
+                    table.row_span(span.rows.get() as i32);
+                }
+
+                if span.cols.get() != 1 {
+                    table.col_span(span.cols.get() as i32);
+                }
+
+                if !headers.is_empty() {
+                    table
+                        .insert(Name(b"Headers"))
+                        .array()
+                        .items(headers.iter().map(|h| sc.str(h.as_bytes())));
+                }
+            }
+
+            if has_layout {
+                let mut layout = attributes.push().layout();
+                self.layout.write(&mut layout);
+
+                if let Some(bbox) = bbox {
+                    layout.bbox(bbox);
+                }
             }
-            _ => {}
+        }
+
+        // Notes always get an ID, so that they can be referred to.
+        let id = match (&self.id, &self.tag) {
+            (Some(id), _) => Some(id.clone()),
+            (None, Tag::Note) => Some(ids.next_note_id()),
+            (None, _) => None,
+        };
+
+        if let Some(id) = id {
+            struct_elem.pair(Name(b"ID"), sc.str(id.as_bytes()));
+            id_tree.insert(id, root_ref);
         }
 
         serialize_children(
@@ -787,6 +1184,14 @@ impl TagTree {
         self.children.push(child.into())
     }
 
+    /// Return all headings of the tag tree in reading order.
+    pub(crate) fn headings(&self) -> Vec<Heading<'_>> {
+        let mut headings = vec![];
+        collect_headings(&self.children, &mut headings);
+
+        headings
+    }
+
     pub(crate) fn serialize(
         &self,
         sc: &mut SerializeContext,
@@ -797,10 +1202,8 @@ impl TagTree {
         let root_ref = sc.new_ref();
         let mut struct_elems = vec![];
 
-        // Keeps track of the ID of notes in the IDTree. We currently only write IDs for notes,
-        // which is why we use this simple variable, but this should be refactored if we write
-        // the IDs for multiple types of struct elements in the future.
-        let mut note_id = 1;
+        check_nesting(None, &self.children);
+        let mut ids = StructIds::new(&self.children, sc);
 
         let mut children_refs = vec![];
 
@@ -810,7 +1213,7 @@ impl TagTree {
                 parent_tree_map,
                 id_tree_map,
                 root_ref,
-                &mut note_id,
+                &mut ids,
                 &mut struct_elems,
             )?;
 
@@ -842,6 +1245,246 @@ impl TagTree {
     }
 }
 
+/// Keeps track of the IDs of the struct elements in the IDTree.
+pub(crate) struct StructIds {
+    /// The IDs that were set for tag groups, along with the references of the
+    /// tag groups.
+    used: HashMap<String, Ref>,
+    /// The number of the next note.
+    next_note: u32,
+}
+
+impl StructIds {
+    fn new(children: &[Node], sc: &mut SerializeContext) -> Self {
+        let mut used = HashMap::new();
+        collect_ids(children, &mut used, sc);
+
+        Self { used, next_note: 1 }
+    }
+
+    /// Return the reference of the tag group with the given ID.
+    fn get_ref(&self, id: &str) -> Ref {
+        match self.used.get(id) {
+            Some(ref_) => *ref_,
+            None => panic!("the ID {:?} doesn't appear in the tag tree", id),
+        }
+    }
+
+    /// Return the ID for the next note without an ID, which must not collide with
+    /// any of the IDs that were set for tag groups.
+    fn next_note_id(&mut self) -> String {
+        loop {
+            let id = format!("Note {}", self.next_note);
+            self.next_note += 1;
+
+            if !self.used.contains_key(&id) {
+                return id;
+            }
+        }
+    }
+}
+
+/// Check that the tags of ruby and warichu annotations are nested correctly, i.e. that
+/// ruby and warichu annotations only contain their own tags, and that these tags don't
+/// appear anywhere else.
+fn check_nesting(parent: Option<&Tag>, children: &[Node]) {
+    for child in children {
+        let valid = match (parent, child) {
+            (Some(Tag::Ruby), Node::Group(group)) => {
+                matches!(group.tag, Tag::RB | Tag::RT | Tag::RP)
+            }
+            (Some(Tag::Warichu), Node::Group(group)) => matches!(group.tag, Tag::WT | Tag::WP),
+            (Some(Tag::Ruby | Tag::Warichu), Node::Leaf(_)) => false,
+            (_, Node::Group(group)) => {
+                !matches!(group.tag, Tag::RB | Tag::RT | Tag::RP | Tag::WT | Tag::WP)
+            }
+            (_, Node::Leaf(_)) => true,
+        };
+
+        if !valid {
+            panic!("the tag tree contains an invalid ruby or warichu annotation");
+        }
+
+        if let Node::Group(group) = child {
+            check_nesting(Some(&group.tag), &group.children);
+        }
+    }
+}
+
+/// Collect the identifiers of all content in a list of nodes.
+fn collect_identifiers(children: &[Node], identifiers: &mut Vec<IdentifierType>) {
+    for child in children {
+        match child {
+            Node::Group(group) => collect_identifiers(&group.children, identifiers),
+            Node::Leaf(identifier) => {
+                if let IdentifierInner::Real(it) = identifier.0 {
+                    identifiers.push(it);
+                }
+            }
+        }
+    }
+}
+
+fn collect_ids(children: &[Node], ids: &mut HashMap<String, Ref>, sc: &mut SerializeContext) {
+    for child in children {
+        if let Node::Group(group) = child {
+            if let Some(id) = &group.id {
+                if ids.insert(id.clone(), sc.new_ref()).is_some() {
+                    panic!("the ID {:?} appears twice in the tag tree", id);
+                }
+            }
+
+            collect_ids(&group.children, ids, sc);
+        }
+    }
+}
+
+/// Whether all list items of a list have a label.
+///
+/// Nested lists are checked separately when their own tag group is serialized.
+fn list_has_labels(children: &[Node]) -> bool {
+    children.iter().all(|child| match child {
+        Node::Group(item) if matches!(item.tag, Tag::LI) => item
+            .children
+            .iter()
+            .any(|child| matches!(child, Node::Group(group) if matches!(group.tag, Tag::Lbl))),
+        _ => true,
+    })
+}
+
+/// Whether all data cells of a table are associated with header cells.
+///
+/// A data cell is associated with header cells if it refers to header cells by their
+/// IDs, or if a header cell in one of its rows has the row scope, or a header cell in
+/// one of its columns has the column scope, taking the spans of the cells into account.
+fn table_has_headers(children: &[Node]) -> bool {
+    // The rows of the table, which can also be grouped into a header, body and footer.
+    let rows = children
+        .iter()
+        .filter_map(|child| match child {
+            Node::Group(group) => Some(group),
+            Node::Leaf(_) => None,
+        })
+        .flat_map(|group| match group.tag {
+            Tag::THead | Tag::TBody | Tag::TFoot => group
+                .children
+                .iter()
+                .filter_map(|child| match child {
+                    Node::Group(group) if matches!(group.tag, Tag::TR) => Some(group),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+            Tag::TR => vec![group],
+            _ => vec![],
+        });
+
+    // The rows and columns covered by each cell.
+    let mut cells: Vec<(Range<u32>, Range<u32>, &Tag)> = vec![];
+    // For each column, the first row that isn't covered by a cell with a row span.
+    let mut occupied: Vec<u32> = vec![];
+
+    for (row, group) in rows.enumerate() {
+        let row = row as u32;
+        let mut col = 0;
+
+        for child in &group.children {
+            let Node::Group(cell) = child else {
+                continue;
+            };
+
+            let Some((span, _)) = cell.tag.table_cell() else {
+                continue;
+            };
+
+            // Skip the columns that are still covered by cells of previous rows.
+            while occupied.get(col as usize).is_some_and(|r| *r > row) {
+                col += 1;
+            }
+
+            let cols = col..col + span.cols.get();
+            let rows = row..row + span.rows.get();
+
+            for c in cols.clone() {
+                if occupied.len() <= c as usize {
+                    occupied.resize(c as usize + 1, 0);
+                }
+
+                occupied[c as usize] = rows.end;
+            }
+
+            col = cols.end;
+            cells.push((rows, cols, &cell.tag));
+        }
+    }
+
+    let overlaps = |a: &Range<u32>, b: &Range<u32>| a.start < b.end && b.start < a.end;
+
+    cells.iter().all(|(rows, cols, tag)| {
+        let Tag::TD(cell) = tag else {
+            return true;
+        };
+
+        !cell.headers.is_empty()
+            || cells
+                .iter()
+                .any(|(header_rows, header_cols, tag)| match tag {
+                    Tag::TH(header) => match header.scope {
+                        TableHeaderScope::Row => overlaps(rows, header_rows),
+                        TableHeaderScope::Column => overlaps(cols, header_cols),
+                        TableHeaderScope::Both => {
+                            overlaps(rows, header_rows) || overlaps(cols, header_cols)
+                        }
+                    },
+                    _ => false,
+                })
+    })
+}
+
+/// A heading in the tag tree.
+pub(crate) struct Heading<'a> {
+    /// The level of the heading.
+    pub(crate) level: NonZeroU32,
+    /// The title of the heading.
+    pub(crate) title: Option<&'a str>,
+    /// The first marked content sequence that belongs to the heading.
+    pub(crate) location: PageTagIdentifier,
+}
+
+fn collect_headings<'a>(children: &'a [Node], headings: &mut Vec<Heading<'a>>) {
+    for child in children {
+        if let Node::Group(group) = child {
+            if let Tag::Hn(level, title) = &group.tag {
+                // Headings that don't contain any page content cannot be jumped to,
+                // so we skip them.
+                if let Some(location) = first_location(&group.children) {
+                    headings.push(Heading {
+                        level: *level,
+                        title: title.as_deref(),
+                        location,
+                    });
+                }
+            } else {
+                collect_headings(&group.children, headings);
+            }
+        }
+    }
+}
+
+/// Find the marked content sequence that comes first in the document. In case the
+/// content spans multiple pages, this will be a marked content sequence on the first page.
+fn first_location(children: &[Node]) -> Option<PageTagIdentifier> {
+    children
+        .iter()
+        .filter_map(|child| match child {
+            Node::Group(group) => first_location(&group.children),
+            Node::Leaf(Identifier(IdentifierInner::Real(IdentifierType::PageIdentifier(id)))) => {
+                Some(*id)
+            }
+            Node::Leaf(_) => None,
+        })
+        .min_by_key(|id| (id.page_index, id.mcid))
+}
+
 fn serialize_children(
     sc: &mut SerializeContext,
     root_ref: Ref,
@@ -881,6 +1524,7 @@ fn serialize_children(
                     }
 
                     parent_tree_map.insert(pi.into(), root_ref);
+                    sc.register_content_struct_elem(pi.into(), root_ref);
 
                     if struct_page_ref == Some(page_ref) {
                         struct_children.marked_content_id(pi.mcid);
@@ -909,6 +1553,7 @@ fn serialize_children(
                         panic!("identifier {:?} appears twice in the tag tree", ai);
                     }
                     parent_tree_map.insert(ai.into(), annotation_ref);
+                    sc.register_content_struct_elem(ai.into(), root_ref);
 
                     struct_children
                         .object_ref()
@@ -976,6 +1621,263 @@ impl ListNumbering {
     }
 }
 
+/// A tag with a custom name.
+#[derive(Debug, Clone)]
+pub struct CustomTag {
+    pub(crate) name: String,
+    pub(crate) role: Option<Box<Tag>>,
+}
+
+impl CustomTag {
+    /// Create a new custom tag with the given name, which must not be the name of
+    /// a standard structure type.
+    ///
+    /// Custom tags without a role can't be used in some export modes, like for
+    /// example PDF/UA1.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            role: None,
+        }
+    }
+
+    /// Map the custom tag to the standard tag it is most similar to, which is
+    /// written to the role map of the document. Only the type of the tag is taken
+    /// into account, but not its other properties, such as alt texts.
+    pub fn with_role(mut self, role: Tag) -> Self {
+        self.role = Some(Box::new(role));
+        self
+    }
+}
+
+/// A table header cell.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TableHeaderCell {
+    pub(crate) scope: TableHeaderScope,
+    pub(crate) span: TableCellSpan,
+    pub(crate) headers: Vec<String>,
+}
+
+impl TableHeaderCell {
+    /// Create a new table header cell with the given scope.
+    pub fn new(scope: TableHeaderScope) -> Self {
+        Self {
+            scope,
+            span: TableCellSpan::default(),
+            headers: vec![],
+        }
+    }
+
+    /// Set the number of rows and columns the cell spans, which is one by default.
+    pub fn with_span(mut self, rows: NonZeroU32, cols: NonZeroU32) -> Self {
+        self.span = TableCellSpan { rows, cols };
+        self
+    }
+
+    /// Set the IDs of the header cells that apply to this header cell, for example
+    /// the IDs of the header cells of a group of columns.
+    ///
+    /// The IDs are the ones set with [`TagGroup::set_id`].
+    pub fn with_headers(mut self, headers: Vec<String>) -> Self {
+        self.headers = headers;
+        self
+    }
+}
+
+/// A table data cell.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Default)]
+pub struct TableDataCell {
+    pub(crate) span: TableCellSpan,
+    pub(crate) headers: Vec<String>,
+}
+
+impl TableDataCell {
+    /// Create a new table data cell.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of rows and columns the cell spans, which is one by default.
+    pub fn with_span(mut self, rows: NonZeroU32, cols: NonZeroU32) -> Self {
+        self.span = TableCellSpan { rows, cols };
+        self
+    }
+
+    /// Set the IDs of the header cells that apply to this data cell.
+    ///
+    /// The IDs are the ones set with [`TagGroup::set_id`]. This is only necessary
+    /// if the header cells can't be determined from the scopes of the header cells
+    /// in the same row or column.
+    pub fn with_headers(mut self, headers: Vec<String>) -> Self {
+        self.headers = headers;
+        self
+    }
+}
+
+/// The number of rows and columns spanned by a table cell.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct TableCellSpan {
+    pub(crate) rows: NonZeroU32,
+    pub(crate) cols: NonZeroU32,
+}
+
+impl Default for TableCellSpan {
+    fn default() -> Self {
+        Self {
+            rows: NonZeroU32::MIN,
+            cols: NonZeroU32::MIN,
+        }
+    }
+}
+
+/// The layout attributes of a tag group, which describe how its content is laid
+/// out, for example when converting the document to a different format.
+///
+/// All lengths are in points. Only values that differ from the default values
+/// are written.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct LayoutAttributes {
+    /// How the tag group is placed relative to its surroundings. Defaults to
+    /// [`Placement::Inline`].
+    pub placement: Option<Placement>,
+    /// The amount of space before the tag group in the block-progression direction.
+    pub space_before: f32,
+    /// The amount of space after the tag group in the block-progression direction.
+    pub space_after: f32,
+    /// The distance from the start edge of the reference area to the start edge of
+    /// the tag group in the inline-progression direction.
+    pub start_indent: f32,
+    /// The distance from the end edge of the tag group to the end edge of the
+    /// reference area in the inline-progression direction.
+    pub end_indent: f32,
+    /// The alignment of the text in the tag group.
+    pub text_align: TextAlign,
+    /// The bounding box of the tag group, which is only written for figures, formulas
+    /// and tables. See [`TagGroup::set_bbox`].
+    pub bbox: Option<BBox>,
+    /// The width of the tag group, or `None` if it is determined automatically.
+    pub width: Option<f32>,
+    /// The height of the tag group, or `None` if it is determined automatically.
+    pub height: Option<f32>,
+}
+
+impl LayoutAttributes {
+    /// Whether any attribute apart from the bbox has a value that isn't the default.
+    fn has_non_default_values(&self) -> bool {
+        self.placement.is_some_and(|p| p != Placement::Inline)
+            || self.space_before != 0.0
+            || self.space_after != 0.0
+            || self.start_indent != 0.0
+            || self.end_indent != 0.0
+            || self.text_align != TextAlign::Start
+            || self.width.is_some()
+            || self.height.is_some()
+    }
+
+    /// Write all attributes apart from the bbox, which needs to be converted first.
+    fn write(&self, layout: &mut pdf_writer::writers::LayoutAttributes<'_>) {
+        if let Some(placement) = self.placement.filter(|p| *p != Placement::Inline) {
+            layout.placement(placement.to_pdf());
+        }
+
+        if self.space_before != 0.0 {
+            layout.space_before(self.space_before);
+        }
+
+        if self.space_after != 0.0 {
+            layout.space_after(self.space_after);
+        }
+
+        if self.start_indent != 0.0 {
+            layout.start_indent(self.start_indent);
+        }
+
+        if self.end_indent != 0.0 {
+            layout.end_indent(self.end_indent);
+        }
+
+        if self.text_align != TextAlign::Start {
+            layout.text_align(self.text_align.to_pdf());
+        }
+
+        if let Some(width) = self.width {
+            layout.width(width);
+        }
+
+        if let Some(height) = self.height {
+            layout.height(height);
+        }
+    }
+}
+
+/// The alignment of text in the inline-progression direction.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub enum TextAlign {
+    /// Aligned with the start edge.
+    #[default]
+    Start,
+    /// Centered between the start and end edge.
+    Center,
+    /// Aligned with the end edge.
+    End,
+    /// Aligned with both the start and end edge.
+    Justify,
+}
+
+impl TextAlign {
+    fn to_pdf(self) -> pdf_writer::types::TextAlign {
+        match self {
+            TextAlign::Start => pdf_writer::types::TextAlign::Start,
+            TextAlign::Center => pdf_writer::types::TextAlign::Center,
+            TextAlign::End => pdf_writer::types::TextAlign::End,
+            TextAlign::Justify => pdf_writer::types::TextAlign::Justify,
+        }
+    }
+}
+
+/// How a tag group is placed relative to its surroundings.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Placement {
+    /// Stacked in the block-progression direction, for example a paragraph.
+    Block,
+    /// Placed in the inline-progression direction within a line, for example a
+    /// word or an inline image.
+    Inline,
+    /// Placed before the start edge of its enclosing tag group, for example
+    /// a float that is placed above text.
+    Before,
+    /// Placed at the start edge of its enclosing tag group, for example a
+    /// margin note.
+    Start,
+    /// Placed at the end edge of its enclosing tag group.
+    End,
+}
+
+impl Placement {
+    fn to_pdf(self) -> pdf_writer::types::Placement {
+        match self {
+            Placement::Block => pdf_writer::types::Placement::Block,
+            Placement::Inline => pdf_writer::types::Placement::Inline,
+            Placement::Before => pdf_writer::types::Placement::Before,
+            Placement::Start => pdf_writer::types::Placement::Start,
+            Placement::End => pdf_writer::types::Placement::End,
+        }
+    }
+}
+
+/// The bounding box of a tag group.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BBox {
+    /// Compute the bounding box automatically from the content of the tag group.
+    ///
+    /// Only content that was drawn into a surface is taken into account, but not
+    /// annotations.
+    Auto,
+    /// A custom bounding box, in the coordinates of the page the content of the tag
+    /// group is drawn on.
+    Rect(Rect),
+}
+
 /// The scope of a table header cell.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum TableHeaderScope {
@@ -3,15 +3,15 @@
 use std::num::NonZeroUsize;
 use std::ops::DerefMut;
 
-use pdf_writer::types::TabOrder;
 use pdf_writer::writers::NumberTree;
-use pdf_writer::{Chunk, Finish, Ref, TextStr};
+use pdf_writer::{Chunk, Dict, Finish, Name, Ref};
 
-use crate::configure::PdfVersion;
+use crate::configure::{PdfVersion, ValidationError};
 use crate::content::ContentBuilder;
-use crate::error::KrillaResult;
+use crate::error::{KrillaError, KrillaResult};
 use crate::geom::{Rect, Size, Transform};
 use crate::interactive::annotation::Annotation;
+use crate::interchange::embed::{supports_associated_files, AssociatedFile, EmbeddedFile};
 use crate::interchange::tagging::{Identifier, PageTagIdentifier};
 use crate::resource::ResourceDictionary;
 use crate::serialize::SerializeContext;
@@ -19,6 +19,9 @@ use crate::stream::{FilterStreamBuilder, Stream};
 use crate::surface::Surface;
 use crate::util::Deferred;
 
+/// The maximum width and height of a page in user space units.
+const MAX_PAGE_DIMENSION: f32 = 14_400.0;
+
 #[derive(Clone, Debug)]
 /// The settings of a page.
 pub struct PageSettings {
@@ -36,6 +39,16 @@ pub struct PageSettings {
     trim_box: Option<Rect>,
     /// The actual content boundaries
     art_box: Option<Rect>,
+    /// The rotation of the page when it's displayed or printed.
+    rotation: PageRotation,
+    /// The transition effect used when moving to the page during a presentation.
+    transition: Option<PageTransition>,
+    /// The maximum number of seconds the page is displayed during a presentation.
+    display_duration: Option<f32>,
+    /// The size of a unit in user space, in multiples of 1/72 inch.
+    user_unit: f32,
+    /// The order in which the annotations of the page are visited.
+    tab_order: Option<TabOrder>,
 }
 
 impl PageSettings {
@@ -57,6 +70,9 @@ impl PageSettings {
     ///
     /// If set to `None`, the dimensions will be chosen in such a way that all
     /// contents fit on the page.
+    ///
+    /// If a media box is set, the crop, bleed, trim and art boxes must be contained
+    /// within it, otherwise export will fail with [`KrillaError::PageBoxOutsideMediaBox`].
     pub fn with_media_box(mut self, media_box: Option<Rect>) -> PageSettings {
         self.media_box = media_box;
         self
@@ -147,6 +163,156 @@ impl PageSettings {
     pub(crate) fn art_box(&self) -> Option<Rect> {
         self.art_box
     }
+
+    /// Change the rotation of the page.
+    ///
+    /// The page will be rotated clockwise by the given angle when it's displayed
+    /// or printed. Note that this does not affect the coordinate system: The contents
+    /// of the page, as well as the rectangles of annotations and destinations pointing
+    /// to the page, still need to be specified in the coordinate space of the
+    /// unrotated page.
+    pub fn with_rotation(mut self, rotation: PageRotation) -> PageSettings {
+        self.rotation = rotation;
+        self
+    }
+
+    /// The current rotation.
+    pub(crate) fn rotation(&self) -> PageRotation {
+        self.rotation
+    }
+
+    /// Change the transition effect that should be used when moving to this page
+    /// during a presentation.
+    pub fn with_transition(mut self, transition: Option<PageTransition>) -> PageSettings {
+        self.transition = transition;
+        self
+    }
+
+    /// The current transition.
+    pub(crate) fn transition(&self) -> Option<PageTransition> {
+        self.transition
+    }
+
+    /// Change the maximum number of seconds the page is displayed before the viewer
+    /// automatically advances to the next page during a presentation.
+    ///
+    /// If set to `None`, the viewer doesn't advance automatically.
+    pub fn with_display_duration(mut self, display_duration: Option<f32>) -> PageSettings {
+        self.display_duration = display_duration;
+        self
+    }
+
+    /// The current display duration.
+    pub(crate) fn display_duration(&self) -> Option<f32> {
+        self.display_duration
+    }
+
+    /// Change the size of a unit in user space, in multiples of 1/72 inch.
+    ///
+    /// By default, one unit corresponds to one point, and the dimensions of a page
+    /// are limited to 14,400 units in each direction, i.e. 200 inches. If you need a
+    /// larger page, you can increase the user unit, which scales the size of all units
+    /// on the page. For example, with a user unit of 2, a page that is 10,000 units wide
+    /// is displayed as if it were 20,000 points wide. Note that all coordinates on the
+    /// page, including the surface size and the media box, are specified in units.
+    ///
+    /// Requires PDF 1.6, otherwise export will fail with [`KrillaError::UserUnit`].
+    pub fn with_user_unit(mut self, user_unit: f32) -> PageSettings {
+        self.user_unit = user_unit;
+        self
+    }
+
+    /// The current user unit.
+    pub(crate) fn user_unit(&self) -> f32 {
+        self.user_unit
+    }
+
+    /// Change the order in which the annotations of the page are visited when
+    /// navigating with the keyboard.
+    ///
+    /// If set to `None`, the tab order set with [`Document::set_tab_order`] is used.
+    /// If no tab order was set there either, tagged pages use [`TabOrder::Structure`].
+    /// The tab order is only written for pages with annotations, and requires
+    /// PDF 1.5.
+    ///
+    /// [`Document::set_tab_order`]: crate::Document::set_tab_order
+    pub fn with_tab_order(mut self, tab_order: Option<TabOrder>) -> PageSettings {
+        self.tab_order = tab_order;
+        self
+    }
+
+    /// The current tab order.
+    pub(crate) fn tab_order(&self) -> Option<TabOrder> {
+        self.tab_order
+    }
+
+    /// Check that all page boxes are contained within the media box.
+    pub(crate) fn check_boxes(&self, page_index: usize) -> KrillaResult<()> {
+        let Some(media_box) = self.media_box else {
+            return Ok(());
+        };
+
+        let is_contained = |rect: &Rect| {
+            rect.left() >= media_box.left()
+                && rect.top() >= media_box.top()
+                && rect.right() <= media_box.right()
+                && rect.bottom() <= media_box.bottom()
+        };
+
+        let boxes = [self.crop_box, self.bleed_box, self.trim_box, self.art_box];
+        if boxes.iter().flatten().all(is_contained) {
+            Ok(())
+        } else {
+            Err(KrillaError::PageBoxOutsideMediaBox(page_index))
+        }
+    }
+}
+
+/// The clockwise rotation of a page.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub enum PageRotation {
+    /// No rotation.
+    #[default]
+    None,
+    /// A rotation by 90 degrees.
+    Rotate90,
+    /// A rotation by 180 degrees.
+    Rotate180,
+    /// A rotation by 270 degrees.
+    Rotate270,
+}
+
+impl PageRotation {
+    fn to_degrees(self) -> i32 {
+        match self {
+            PageRotation::None => 0,
+            PageRotation::Rotate90 => 90,
+            PageRotation::Rotate180 => 180,
+            PageRotation::Rotate270 => 270,
+        }
+    }
+}
+
+/// The order in which the annotations of a page are visited when navigating
+/// with the keyboard.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TabOrder {
+    /// Row by row, from top to bottom.
+    Row,
+    /// Column by column, from left to right.
+    Column,
+    /// In the order of the structure tree. Required by PDF/UA.
+    Structure,
+}
+
+impl TabOrder {
+    fn to_pdf(self) -> pdf_writer::types::TabOrder {
+        match self {
+            TabOrder::Row => pdf_writer::types::TabOrder::RowOrder,
+            TabOrder::Column => pdf_writer::types::TabOrder::ColumnOrder,
+            TabOrder::Structure => pdf_writer::types::TabOrder::StructureOrder,
+        }
+    }
 }
 
 impl Default for PageSettings {
@@ -163,6 +329,11 @@ impl Default for PageSettings {
             bleed_box: None,
             trim_box: None,
             art_box: None,
+            rotation: PageRotation::default(),
+            transition: None,
+            display_duration: None,
+            user_unit: 1.0,
+            tab_order: None,
         }
     }
 }
@@ -183,6 +354,7 @@ pub struct Page<'a> {
     page_stream: Stream,
     num_mcids: i32,
     annotations: Vec<Annotation>,
+    associated_files: Vec<Ref>,
 }
 
 impl<'a> Page<'a> {
@@ -191,6 +363,8 @@ impl<'a> Page<'a> {
         page_index: usize,
         page_settings: PageSettings,
     ) -> Self {
+        sc.current_page = Some(page_index);
+
         Self {
             sc,
             page_settings,
@@ -198,6 +372,7 @@ impl<'a> Page<'a> {
             num_mcids: 0,
             page_stream: Stream::empty(),
             annotations: vec![],
+            associated_files: vec![],
         }
     }
 
@@ -206,10 +381,23 @@ impl<'a> Page<'a> {
     }
 
     /// Add an annotation to the page.
-    pub fn add_annotation(&mut self, annotation: Annotation) {
+    pub fn add_annotation(&mut self, mut annotation: Annotation) {
+        annotation.register(self.sc);
         self.annotations.push(annotation);
     }
 
+    /// Embed a file and associate it with the page, using the association kind
+    /// of the file.
+    ///
+    /// Associated files require PDF 2.0 or PDF/A-3 and later, otherwise the file
+    /// is ignored. The file is not listed among the attachments of the document.
+    pub fn add_associated_file(&mut self, file: EmbeddedFile) {
+        if supports_associated_files(self.sc) {
+            let ref_ = self.sc.register_cacheable(AssociatedFile(file));
+            self.associated_files.push(ref_);
+        }
+    }
+
     /// Add a tagged annotation to the page.
     pub fn add_tagged_annotation(&mut self, mut annotation: Annotation) -> Identifier {
         let annot_index = self.annotations.len();
@@ -228,11 +416,20 @@ impl<'a> Page<'a> {
     /// Get the surface of the page to draw on. Calling this multiple times
     /// on the same page will reset any previous drawings.
     pub fn surface(&mut self) -> Surface {
-        let root_builder = ContentBuilder::new(
+        let mut root_builder = ContentBuilder::new(
             self.root_transform(),
             self.page_settings.media_box.is_none(),
         );
 
+        if self
+            .sc
+            .serialize_settings()
+            .configuration
+            .black_point_compensation()
+        {
+            root_builder.set_black_point_compensation(true);
+        }
+
         let finish_fn = Box::new(|stream, num_mcids| {
             self.page_stream = stream;
             self.num_mcids = num_mcids;
@@ -260,6 +457,7 @@ impl Drop for Page<'_> {
         // Since we cannot take ownership in `drop`, just make use `mem::take` to pick
         // what we need.
         let annotations = std::mem::take(&mut self.annotations);
+        let associated_files = std::mem::take(&mut self.associated_files);
         let page_settings = std::mem::take(&mut self.page_settings);
 
         let struct_parent = self
@@ -271,11 +469,224 @@ impl Drop for Page<'_> {
             stream,
             self.sc,
             annotations,
+            associated_files,
             struct_parent,
             page_settings,
             self.page_index,
         );
         self.sc.register_page(page);
+        self.sc.current_page = None;
+    }
+}
+
+/// The visual style of a page transition.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub enum TransitionStyle {
+    /// Two lines sweep across the screen, revealing the new page.
+    Split,
+    /// Multiple lines sweep across the screen, revealing the new page.
+    Blinds,
+    /// A rectangular box sweeps inward from the edges or outward from the
+    /// center of the screen.
+    Box,
+    /// A single line sweeps across the screen.
+    Wipe,
+    /// The old page dissolves gradually to reveal the new one.
+    Dissolve,
+    /// Similar to `Dissolve`, except that the effect sweeps across the screen.
+    Glitter,
+    /// The new page simply replaces the old one.
+    #[default]
+    Replace,
+    /// The new page flies in or the old page flies out.
+    ///
+    /// Requires PDF 1.5, falls back to `Replace` otherwise.
+    Fly,
+    /// The old page slides off the screen while the new page slides in.
+    ///
+    /// Requires PDF 1.5, falls back to `Replace` otherwise.
+    Push,
+    /// The new page slides on to the screen, covering the old page.
+    ///
+    /// Requires PDF 1.5, falls back to `Replace` otherwise.
+    Cover,
+    /// The old page slides off the screen, uncovering the new page.
+    ///
+    /// Requires PDF 1.5, falls back to `Replace` otherwise.
+    Uncover,
+    /// The new page gradually becomes visible through the old one.
+    ///
+    /// Requires PDF 1.5, falls back to `Replace` otherwise.
+    Fade,
+}
+
+impl TransitionStyle {
+    fn to_pdf_name(self) -> &'static [u8] {
+        match self {
+            TransitionStyle::Split => b"Split",
+            TransitionStyle::Blinds => b"Blinds",
+            TransitionStyle::Box => b"Box",
+            TransitionStyle::Wipe => b"Wipe",
+            TransitionStyle::Dissolve => b"Dissolve",
+            TransitionStyle::Glitter => b"Glitter",
+            TransitionStyle::Replace => b"R",
+            TransitionStyle::Fly => b"Fly",
+            TransitionStyle::Push => b"Push",
+            TransitionStyle::Cover => b"Cover",
+            TransitionStyle::Uncover => b"Uncover",
+            TransitionStyle::Fade => b"Fade",
+        }
+    }
+
+    fn pdf_version(self) -> PdfVersion {
+        match self {
+            TransitionStyle::Split
+            | TransitionStyle::Blinds
+            | TransitionStyle::Box
+            | TransitionStyle::Wipe
+            | TransitionStyle::Dissolve
+            | TransitionStyle::Glitter
+            | TransitionStyle::Replace => PdfVersion::Pdf14,
+            TransitionStyle::Fly
+            | TransitionStyle::Push
+            | TransitionStyle::Cover
+            | TransitionStyle::Uncover
+            | TransitionStyle::Fade => PdfVersion::Pdf15,
+        }
+    }
+}
+
+/// The dimension in which a `Split` or `Blinds` transition occurs.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TransitionDimension {
+    /// A horizontal transition.
+    Horizontal,
+    /// A vertical transition.
+    Vertical,
+}
+
+/// The direction of motion of a `Split`, `Box` or `Fly` transition.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TransitionMotion {
+    /// From the edges of the screen towards the center.
+    Inward,
+    /// From the center of the screen towards the edges.
+    Outward,
+}
+
+/// The direction in which a `Wipe`, `Glitter`, `Fly`, `Cover`, `Uncover` or
+/// `Push` transition moves across the screen.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TransitionDirection {
+    /// From left to right.
+    LeftToRight,
+    /// From bottom to top. Only supported by `Wipe`.
+    BottomToTop,
+    /// From right to left. Only supported by `Wipe`.
+    RightToLeft,
+    /// From top to bottom.
+    TopToBottom,
+    /// From the top-left corner to the bottom-right corner. Only supported by `Glitter`.
+    TopLeftToBottomRight,
+    /// No direction. Only supported by `Fly`, if its scale is not 1.
+    None,
+}
+
+/// A transition effect that is used when moving to a page during a presentation.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PageTransition {
+    style: TransitionStyle,
+    duration: f32,
+    dimension: Option<TransitionDimension>,
+    motion: Option<TransitionMotion>,
+    direction: Option<TransitionDirection>,
+}
+
+impl PageTransition {
+    /// Create a new page transition with a specific style and a duration
+    /// of one second.
+    pub fn new(style: TransitionStyle) -> Self {
+        Self {
+            style,
+            duration: 1.0,
+            dimension: None,
+            motion: None,
+            direction: None,
+        }
+    }
+
+    /// Change the duration of the transition effect in seconds.
+    pub fn with_duration(mut self, duration: f32) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Change the dimension in which the transition occurs.
+    ///
+    /// Only relevant for the `Split` and `Blinds` styles.
+    pub fn with_dimension(mut self, dimension: TransitionDimension) -> Self {
+        self.dimension = Some(dimension);
+        self
+    }
+
+    /// Change the direction of motion of the transition.
+    ///
+    /// Only relevant for the `Split`, `Box` and `Fly` styles.
+    pub fn with_motion(mut self, motion: TransitionMotion) -> Self {
+        self.motion = Some(motion);
+        self
+    }
+
+    /// Change the direction in which the transition moves across the screen.
+    ///
+    /// Only relevant for the `Wipe`, `Glitter`, `Fly`, `Cover`, `Uncover`
+    /// and `Push` styles.
+    pub fn with_direction(mut self, direction: TransitionDirection) -> Self {
+        self.direction = Some(direction);
+        self
+    }
+
+    fn serialize(&self, sc: &SerializeContext, mut transition: Dict) {
+        let style = if self.style.pdf_version() <= sc.serialize_settings().pdf_version() {
+            self.style
+        } else {
+            TransitionStyle::Replace
+        };
+
+        transition.pair(Name(b"Type"), Name(b"Trans"));
+        transition.pair(Name(b"S"), Name(style.to_pdf_name()));
+        transition.pair(Name(b"D"), self.duration);
+
+        if let Some(dimension) = self.dimension {
+            let name = match dimension {
+                TransitionDimension::Horizontal => Name(b"H"),
+                TransitionDimension::Vertical => Name(b"V"),
+            };
+            transition.pair(Name(b"Dm"), name);
+        }
+
+        if let Some(motion) = self.motion {
+            let name = match motion {
+                TransitionMotion::Inward => Name(b"I"),
+                TransitionMotion::Outward => Name(b"O"),
+            };
+            transition.pair(Name(b"M"), name);
+        }
+
+        if let Some(direction) = self.direction {
+            let angle = match direction {
+                TransitionDirection::LeftToRight => 0,
+                TransitionDirection::BottomToTop => 90,
+                TransitionDirection::RightToLeft => 180,
+                TransitionDirection::TopToBottom => 270,
+                TransitionDirection::TopLeftToBottomRight => 315,
+                TransitionDirection::None => {
+                    transition.pair(Name(b"Di"), Name(b"None"));
+                    return;
+                }
+            };
+            transition.pair(Name(b"Di"), angle);
+        }
     }
 }
 
@@ -314,6 +725,7 @@ pub(crate) struct InternalPage {
     pub page_index: usize,
     pub struct_parent: Option<i32>,
     pub bbox: Rect,
+    pub associated_files: Vec<Ref>,
     pub annotations: Vec<Annotation>,
 }
 
@@ -322,6 +734,7 @@ impl InternalPage {
         mut stream: Stream,
         sc: &mut SerializeContext,
         annotations: Vec<Annotation>,
+        associated_files: Vec<Ref>,
         struct_parent: Option<i32>,
         page_settings: PageSettings,
         page_index: usize,
@@ -332,13 +745,14 @@ impl InternalPage {
 
         let stream_ref = sc.new_ref();
         let serialize_settings = sc.serialize_settings().clone();
+        let encryptor = sc.encryptor();
         let stream_resources = std::mem::take(&mut stream.resource_dictionary);
 
         let stream_chunk = Deferred::new(move || {
             let mut chunk = Chunk::new();
             let page_stream =
                 FilterStreamBuilder::new_from_content_stream(&stream.content, &serialize_settings)
-                    .finish(&serialize_settings.clone());
+                    .finish(&serialize_settings, encryptor);
 
             let mut stream = chunk.stream(stream_ref, page_stream.encoded_data());
             page_stream.write_filters(stream.deref_mut());
@@ -354,6 +768,7 @@ impl InternalPage {
             struct_parent,
             bbox: stream.bbox,
             annotations,
+            associated_files,
             page_settings,
             page_index,
         }
@@ -363,28 +778,122 @@ impl InternalPage {
         self,
         sc: &mut SerializeContext,
         root_ref: Ref,
+        duplicate_refs: &[Ref],
     ) -> KrillaResult<Deferred<Chunk>> {
+        // Errors should refer to the final position of the page in the document.
+        let position = sc.page_position(self.page_index);
+        self.page_settings.check_boxes(position)?;
+
+        let user_unit = self.page_settings.user_unit();
+        if user_unit != 1.0 && sc.serialize_settings().pdf_version() < PdfVersion::Pdf16 {
+            return Err(KrillaError::UserUnit(position));
+        }
+
+        // Most viewers clamp pages that exceed the maximum page size, so we reject
+        // them instead.
+        let media_box = self.page_settings.media_box().unwrap_or(self.bbox);
+        if media_box.width() > MAX_PAGE_DIMENSION || media_box.height() > MAX_PAGE_DIMENSION {
+            return Err(KrillaError::MediaBoxTooLarge(position));
+        }
+
         let mut chunk = Chunk::new();
 
         let mut annotation_refs = vec![];
+        // Popups are kept separately, so that the indices of `annotation_refs` still
+        // correspond to the indices of the annotations.
+        let mut popup_refs = vec![];
 
         if !self.annotations.is_empty() {
             for annotation in &self.annotations {
                 let annot_ref = sc.new_ref();
 
-                let a = annotation.serialize(
+                let (a, popup_ref) = annotation.serialize(
                     sc,
                     annot_ref,
                     self.page_settings.surface_size().height(),
                 )?;
                 chunk.extend(&a);
                 annotation_refs.push(annot_ref);
+                popup_refs.extend(popup_ref);
             }
         }
 
+        let widget_refs = sc.page_infos()[self.page_index].widget_annotations.clone();
+        let has_annotations = !self.annotations.is_empty() || !widget_refs.is_empty();
+
+        // Using the structure order is only required for PDF/UA, but might as well
+        // always use it for tagged pages.
+        let is_tagged = self.struct_parent.is_some()
+            || self.annotations.iter().any(|a| a.struct_parent.is_some());
+        let tab_order = self
+            .page_settings
+            .tab_order()
+            .or(sc.tab_order())
+            .or(is_tagged.then_some(TabOrder::Structure))
+            .filter(|_| {
+                has_annotations && sc.serialize_settings().pdf_version() >= PdfVersion::Pdf15
+            });
+
+        if has_annotations && tab_order != Some(TabOrder::Structure) {
+            sc.register_validation_error(ValidationError::InvalidTabOrder(position));
+        }
+
         let mut page = chunk.page(root_ref);
+        self.write_common_entries(sc, &mut page, media_box);
+
+        if let Some(struct_parent) = self.struct_parent {
+            page.struct_parents(struct_parent);
+        }
+
+        if let Some(tab_order) = tab_order {
+            page.tab_order(tab_order.to_pdf());
+        }
+
+        if !self.associated_files.is_empty() {
+            page.insert(Name(b"AF"))
+                .array()
+                .items(self.associated_files.iter().copied());
+        }
+
+        if !annotation_refs.is_empty() || !widget_refs.is_empty() {
+            page.annotations(
+                annotation_refs
+                    .iter()
+                    .chain(popup_refs.iter())
+                    .chain(widget_refs.iter())
+                    .copied(),
+            );
+        }
+
+        // Populate the refs for each annotation in page infos.
+        let page_info = &mut sc.page_infos_mut()[self.page_index];
+        page_info.annotations = annotation_refs;
+
+        page.finish();
+
+        // Duplicated pages share the content stream and resources of the original page,
+        // but not its annotations.
+        for duplicate_ref in duplicate_refs {
+            let mut page = chunk.page(*duplicate_ref);
+            self.write_common_entries(sc, &mut page, media_box);
+            page.finish();
+        }
+
+        Ok(Deferred::new(move || {
+            chunk.extend(self.stream_chunk.wait());
+            chunk
+        }))
+    }
+
+    /// Write the entries that are shared between a page and its duplicates.
+    fn write_common_entries(
+        &self,
+        sc: &mut SerializeContext,
+        page: &mut pdf_writer::writers::Page,
+        media_box: Rect,
+    ) {
         self.stream_resources
-            .to_pdf_resources(&mut page, sc.serialize_settings().pdf_version());
+            .to_pdf_resources(page, sc.serialize_settings().pdf_version());
 
         let transform_rect = |rect: Rect| {
             rect.transform(page_root_transform(
@@ -394,7 +903,7 @@ impl InternalPage {
         };
 
         // media box is mandatory, so we need to fall back to the default bbox
-        let media_box = transform_rect(self.page_settings.media_box().unwrap_or(self.bbox));
+        let media_box = transform_rect(media_box);
         page.media_box(media_box.to_pdf_rect());
 
         // the remaining type of box are not mandatory, so we only set them if they are present
@@ -418,34 +927,24 @@ impl InternalPage {
             page.art_box(art_box.to_pdf_rect());
         }
 
-        if let Some(struct_parent) = self.struct_parent {
-            page.struct_parents(struct_parent);
-
-            // Only required for PDF/UA, but might as well always set it.
-            if !self.annotations.is_empty()
-                && sc.serialize_settings().pdf_version() >= PdfVersion::Pdf15
-            {
-                page.tab_order(TabOrder::StructureOrder);
-            }
+        if self.page_settings.rotation() != PageRotation::None {
+            page.rotate(self.page_settings.rotation().to_degrees());
         }
 
-        page.parent(sc.page_tree_ref());
-        page.contents(self.stream_ref);
-
-        if !annotation_refs.is_empty() {
-            page.annotations(annotation_refs.iter().copied());
+        if let Some(transition) = self.page_settings.transition() {
+            transition.serialize(sc, page.insert(Name(b"Trans")).dict());
         }
 
-        // Populate the refs for each annotation in page infos.
-        let page_info = &mut sc.page_infos_mut()[self.page_index];
-        page_info.annotations = annotation_refs;
+        if let Some(display_duration) = self.page_settings.display_duration() {
+            page.pair(Name(b"Dur"), display_duration);
+        }
 
-        page.finish();
+        if self.page_settings.user_unit() != 1.0 {
+            page.pair(Name(b"UserUnit"), self.page_settings.user_unit());
+        }
 
-        Ok(Deferred::new(move || {
-            chunk.extend(self.stream_chunk.wait());
-            chunk
-        }))
+        page.parent(sc.page_tree_ref());
+        page.contents(self.stream_ref);
     }
 }
 
@@ -478,7 +977,7 @@ impl PageLabel {
         self.style.is_none() && self.prefix.is_none() && self.offset.is_none()
     }
 
-    pub(crate) fn serialize(&self, root_ref: Ref) -> Chunk {
+    pub(crate) fn serialize(&self, sc: &SerializeContext, root_ref: Ref) -> Chunk {
         let mut chunk = Chunk::new();
         let mut label = chunk
             .indirect(root_ref)
@@ -489,7 +988,7 @@ impl PageLabel {
         }
 
         if let Some(prefix) = &self.prefix {
-            label.prefix(TextStr(prefix));
+            label.pair(Name(b"P"), sc.text_str(prefix));
         }
 
         if let Some(offset) = self.offset.and_then(|o| i32::try_from(o.get()).ok()) {
@@ -502,6 +1001,43 @@ impl PageLabel {
     }
 }
 
+/// Apply the page label ranges of a document to the labels of its pages. Labels that
+/// were set on individual pages take precedence over the ranges.
+///
+/// Ranges must be sorted by their start index, and each range must start on an
+/// existing page.
+pub(crate) fn apply_page_label_ranges(
+    labels: &mut [PageLabel],
+    ranges: &[(usize, PageLabel)],
+) -> KrillaResult<()> {
+    let mut prev_start = None;
+
+    for (start, _) in ranges {
+        if prev_start.is_some_and(|prev| prev >= *start) || *start >= labels.len() {
+            return Err(KrillaError::InvalidPageLabelRange(*start));
+        }
+
+        prev_start = Some(*start);
+    }
+
+    for (i, (start, label)) in ranges.iter().enumerate() {
+        let end = ranges.get(i + 1).map(|(s, _)| *s).unwrap_or(labels.len());
+        // If a numbering style is set, numbering starts at 1 by default.
+        let first = label.offset.map(|o| o.get()).or(label.style.map(|_| 1));
+
+        for (offset, page_label) in labels[*start..end].iter_mut().enumerate() {
+            if page_label.is_empty() {
+                *page_label = PageLabel {
+                    offset: first.and_then(|f| NonZeroUsize::new(f + offset)),
+                    ..label.clone()
+                };
+            }
+        }
+    }
+
+    Ok(())
+}
+
 #[derive(Hash)]
 pub(crate) struct PageLabelContainer<'a> {
     labels: &'a [PageLabel],
@@ -523,18 +1059,26 @@ impl<'a> PageLabelContainer<'a> {
         let mut prev: Option<PageLabel> = None;
 
         for (i, label) in self.labels.iter().enumerate() {
-            if let Some(n_prev) = &prev {
-                if n_prev.style != label.style
-                    || n_prev.prefix != label.prefix
-                    || n_prev.offset.map(|n| n.get()) != label.offset.map(|n| n.get() + 1)
-                {
-                    filtered_entries.push((i, label.clone()));
-                    prev = Some(label.clone());
-                }
-            } else {
+            // A label that just continues the numbering of the previous one doesn't
+            // need an entry of its own.
+            let continues = prev.as_ref().is_some_and(|n_prev| {
+                n_prev.style == label.style
+                    && n_prev.prefix == label.prefix
+                    && match label.style {
+                        // Without a numbering style, only the prefix is shown.
+                        None => true,
+                        Some(_) => {
+                            n_prev.offset.map_or(1, |n| n.get()) + 1
+                                == label.offset.map_or(1, |n| n.get())
+                        }
+                    }
+            });
+
+            if !continues {
                 filtered_entries.push((i, label.clone()));
-                prev = Some(label.clone());
             }
+
+            prev = Some(label.clone());
         }
 
         let mut chunk = Chunk::new();
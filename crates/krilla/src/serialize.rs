@@ -5,31 +5,37 @@ use std::ops::{Deref, DerefMut};
 use std::rc::Rc;
 use std::sync::Arc;
 
-use pdf_writer::types::StructRole;
-use pdf_writer::writers::{NameTree, NumberTree, OutputIntent, RoleMap};
-use pdf_writer::{Chunk, Dict, Finish, Limits, Name, Pdf, Ref, Str, TextStr};
+use pdf_writer::types::{OutputIntentSubtype, StructRole};
+use pdf_writer::writers::{NameTree, NumberTree, RoleMap};
+use pdf_writer::{Chunk, Dict, Finish, Limits, Name, Ref, Str};
 
-use crate::chunk_container::{ChunkContainer, ChunkContainerFn};
+use crate::chunk_container::{ChunkContainer, ChunkContainerFn, UnfinishedPdf};
 use crate::configure::{Configuration, PdfVersion, ValidationError, Validator};
-use crate::error::{KrillaError, KrillaResult};
-use crate::geom::Size;
-use crate::graphics::color::{rgb, ColorSpace};
+use crate::encryption::{encrypt_bytes, Encryption, Encryptor, PdfStr, SecurityHandler};
+use crate::error::{KrillaError, KrillaResult, KrillaWarning};
+use crate::geom::{Point, Rect, Size};
+use crate::graphics::color::lab::LabColorSpace;
+use crate::graphics::color::{rgb, Color, ColorSpace};
 use crate::graphics::icc::{ICCBasedColorSpace, ICCProfile};
 #[cfg(feature = "raster-images")]
 use crate::graphics::image::Image;
-use crate::interactive::destination::{NamedDestination, XyzDestination};
-use crate::interchange::embed::EmbeddedFile;
-use crate::interchange::metadata::Metadata;
+use crate::interactive::action::{serialize_open_action, DocumentActions};
+use crate::interactive::annotation::Target;
+use crate::interactive::destination::{Destination, StructureDestination};
+use crate::interactive::form::{font_resource_name, InternalField, SignatureChunk};
+use crate::interchange::embed::{supports_associated_files, AssociatedFile, EmbeddedFile};
+use crate::interchange::facturx::{FacturX, FacturXError};
+use crate::interchange::metadata::{DateTime, Metadata};
 use crate::interchange::outline::Outline;
 use crate::interchange::tagging::{
-    AnnotationIdentifier, IdentifierType, PageTagIdentifier, TagTree,
+    AnnotationIdentifier, IdentifierInner, IdentifierType, PageTagIdentifier, TagTree,
 };
-use crate::page::{InternalPage, PageLabel, PageLabelContainer};
+use crate::page::{apply_page_label_ranges, InternalPage, PageLabel, PageLabelContainer, TabOrder};
 use crate::resource;
 use crate::resource::{Resource, Resourceable};
 use crate::surface::{Location, Surface};
 use crate::text::GlyphId;
-use crate::text::{Font, FontContainer, FontIdentifier, FontInfo};
+use crate::text::{ColorPalette, Font, FontContainer, FontIdentifier, FontInfo};
 use crate::util::{Deferred, SipHashable};
 
 /// Settings that should be applied when creating a PDF document.
@@ -61,12 +67,40 @@ pub struct SerializeSettings {
     /// you use. For example, when exporting to PDF/A, this value will be set to
     /// true, regardless of what value will be passed.
     pub xmp_metadata: bool,
-    /// The ICC profile that should be used for CMYK colors
-    /// when `no_device_cs` is enabled.
+    /// The ICC profile that should be used for CMYK colors.
     ///
-    /// This is usually not required, but it is for example required when exporting
-    /// to PDF/A and using a CMYK color, since they have to be device-independent.
+    /// If set, all CMYK colors will be ICC-based instead of using the `DeviceCMYK`
+    /// color space, and the profile will be used as the output intent in case the
+    /// validator requires one. This is for example required when exporting to PDF/A
+    /// and using a CMYK color, since they have to be device-independent.
+    ///
+    /// Note that the version of the profile must be supported by the used PDF version,
+    /// otherwise exporting the document will fail.
     pub cmyk_profile: Option<ICCProfile<4>>,
+    /// Whether CMYK colors should be converted to RGB in case device-independent
+    /// colors are required (for example by a validator), but no CMYK profile was provided.
+    ///
+    /// If this is disabled, using CMYK colors in such a case results in a
+    /// validation error instead. Note that the conversion is only an approximation, and
+    /// that it doesn't apply to images and DeviceN colors.
+    pub convert_cmyk_to_rgb: bool,
+    /// Whether images with the same content should only be written once.
+    ///
+    /// Images are identified by a hash of their data and parameters, so loading the
+    /// same file multiple times still results in a single image in the PDF. If this
+    /// is disabled, each drawn image will be written separately instead.
+    ///
+    /// This setting only applies to images, since hashing them can be expensive. Masks,
+    /// patterns and shadings are always identified by their content, so identical ones
+    /// are written only once, regardless of how many times they have been created.
+    pub deduplicate_images: bool,
+    /// Whether fonts should be subsetted, so that only the glyphs that are actually
+    /// used are embedded.
+    ///
+    /// If this is disabled, fonts are embedded as a whole, which can be required for
+    /// archival purposes or by the license of a font, but results in much larger files.
+    /// The glyphs of color fonts are still drawn as Type3 glyphs.
+    pub subset_fonts: bool,
     /// A validator and PDF version used for export.
     ///
     /// In case validation fails, export will fail, and a list of validation errors that
@@ -103,6 +137,11 @@ pub struct SerializeSettings {
     /// just use the default function which doesn't render them at all. If you do want this, it
     /// is recommended that you use the function provided by the `krilla-svg` crate.
     pub render_svg_glyph_fn: RenderSvgGlyphFn,
+    /// Whether and how the document should be encrypted. See the documentation of the
+    /// [`encryption`] module for more information.
+    ///
+    /// [`encryption`]: crate::encryption
+    pub encryption: Option<Encryption>,
 }
 
 pub type RenderSvgGlyphFn = fn(&[u8], rgb::Color, GlyphId, &mut Surface) -> Option<()>;
@@ -115,6 +154,11 @@ impl SerializeSettings {
     pub(crate) fn validator(&self) -> Validator {
         self.configuration.validator()
     }
+
+    /// Whether CMYK paints need to be converted to RGB.
+    pub(crate) fn converts_cmyk_to_rgb(&self) -> bool {
+        self.convert_cmyk_to_rgb && self.no_device_cs && self.cmyk_profile.is_none()
+    }
 }
 
 impl Default for SerializeSettings {
@@ -125,9 +169,13 @@ impl Default for SerializeSettings {
             no_device_cs: false,
             xmp_metadata: true,
             cmyk_profile: None,
+            convert_cmyk_to_rgb: false,
+            deduplicate_images: true,
+            subset_fonts: true,
             configuration: Configuration::new(),
             enable_tagging: true,
             render_svg_glyph_fn: |_, _, _, _| None,
+            encryption: None,
         }
     }
 }
@@ -144,6 +192,13 @@ pub(crate) struct PageInfo {
     /// `page_infos` in `SerializeContext`, and only once we actually serialize
     /// the page will the annotations be populated.
     pub(crate) annotations: Vec<Ref>,
+    /// The refs of the widget annotations of form fields that are placed on that page.
+    ///
+    /// Similarly to `annotations`, this will only be populated once the form fields
+    /// are serialized.
+    pub(crate) widget_annotations: Vec<Ref>,
+    /// The index of the page this page is a duplicate of, if any.
+    pub(crate) duplicate_of: Option<usize>,
 }
 
 enum StructParentElement {
@@ -179,7 +234,12 @@ pub(crate) struct SerializeContext {
     /// All global objects, such as PDF fonts, that are populated over time.
     pub(crate) global_objects: GlobalObjects,
     /// Information for each page written so far, index by the page index.
+    ///
+    /// Note that the page index is the index of the page in creation order, which
+    /// might differ from the final position of the page in the document.
     page_infos: Vec<PageInfo>,
+    /// The indices of all pages in the order they appear in the document.
+    page_order: Vec<usize>,
     /// Keep track of object hashes and their corresponding reference. This is used for
     /// caching, so that for example same images will not be embedded twice in the document.
     cached_mappings: HashMap<u128, Ref>,
@@ -191,6 +251,10 @@ pub(crate) struct SerializeContext {
     chunk_container: ChunkContainer,
     /// All validation errors that are collected as part of the export process.
     validation_errors: Vec<ValidationError>,
+    /// All warnings that have been collected and not taken yet.
+    warnings: Vec<KrillaWarning>,
+    /// The index of the page that is currently being drawn, if any.
+    pub(crate) current_page: Option<usize>,
     /// Settings used for serialization.
     serialize_settings: Arc<SerializeSettings>,
     /// The limits created as part of the serialization process. In principle, we could
@@ -201,6 +265,22 @@ pub(crate) struct SerializeContext {
     limits: Limits,
     /// The current location, if set.
     pub(crate) location: Option<Location>,
+    /// Whether the outline should be generated from the headings in the tag tree.
+    outline_from_headings: bool,
+    /// The position of the first content drawn for each marked content sequence,
+    /// in krilla coordinates.
+    tag_positions: HashMap<PageTagIdentifier, Point>,
+    /// The bbox of the content drawn in each marked content sequence, in krilla
+    /// coordinates.
+    tag_bboxes: HashMap<PageTagIdentifier, Rect>,
+    /// All palettes for COLR glyphs that have been used so far, where the first one
+    /// is the default palette.
+    palettes: Vec<ColorPalette>,
+    /// The security handler used for encrypting strings and streams, if the document
+    /// is encrypted.
+    security_handler: Option<SecurityHandler>,
+    /// Whether the CMYK profile in the settings isn't supported by the PDF version.
+    unsupported_cmyk_profile: bool,
 }
 
 impl SerializeContext {
@@ -210,6 +290,23 @@ impl SerializeContext {
         serialize_settings.enable_tagging |= serialize_settings.validator().requires_tagging();
         serialize_settings.xmp_metadata |= serialize_settings.validator().xmp_metadata();
 
+        // Invalid encryption settings are reported when finishing the document.
+        let security_handler = serialize_settings
+            .encryption
+            .as_ref()
+            .and_then(|e| SecurityHandler::new(e).ok());
+
+        // The same goes for an unsupported CMYK profile, which we remove right away so
+        // that no object refers to it.
+        let pdf_version = serialize_settings.pdf_version();
+        let unsupported_cmyk_profile = serialize_settings
+            .cmyk_profile
+            .as_ref()
+            .is_some_and(|profile| !pdf_version.supports_icc(profile.metadata()));
+        if unsupported_cmyk_profile {
+            serialize_settings.cmyk_profile = None;
+        }
+
         Self {
             cached_mappings: HashMap::new(),
             font_cache: HashMap::new(),
@@ -218,13 +315,37 @@ impl SerializeContext {
             chunk_container: ChunkContainer::new(),
             page_tree_ref: None,
             page_infos: vec![],
+            page_order: vec![],
             location: None,
             validation_errors: vec![],
+            warnings: vec![],
+            current_page: None,
             serialize_settings: Arc::new(serialize_settings),
             limits: Limits::new(),
+            outline_from_headings: false,
+            tag_positions: HashMap::new(),
+            tag_bboxes: HashMap::new(),
+            palettes: vec![ColorPalette::default()],
+            security_handler,
+            unsupported_cmyk_profile,
         }
     }
 
+    /// Register a palette for COLR glyphs and return its index, so that it can be
+    /// referred to by glyphs.
+    pub(crate) fn register_palette(&mut self, palette: ColorPalette) -> usize {
+        if let Some(index) = self.palettes.iter().position(|p| *p == palette) {
+            return index;
+        }
+
+        self.palettes.push(palette);
+        self.palettes.len() - 1
+    }
+
+    pub(crate) fn palette(&self, index: usize) -> &ColorPalette {
+        &self.palettes[index]
+    }
+
     pub(crate) fn page_infos(&self) -> &[PageInfo] {
         &self.page_infos
     }
@@ -233,6 +354,75 @@ impl SerializeContext {
         &mut self.page_infos
     }
 
+    /// The number of pages in the document, including pages that are currently
+    /// being drawn.
+    pub(crate) fn num_pages(&self) -> usize {
+        self.page_order.len()
+    }
+
+    /// Return the page index of the page at the given position in the document.
+    pub(crate) fn page_at(&self, position: usize) -> Option<usize> {
+        self.page_order.get(position).copied()
+    }
+
+    /// Return the position in the document of the page with the given page index.
+    pub(crate) fn page_position(&self, page_index: usize) -> usize {
+        self.page_order
+            .iter()
+            .position(|i| *i == page_index)
+            .expect("page index is not part of the document")
+    }
+
+    pub(crate) fn insert_page_position(&mut self, position: usize, page_index: usize) {
+        if position > self.page_order.len() {
+            panic!(
+                "attempted to insert a page at index {}, but document only has {} pages",
+                position,
+                self.page_order.len()
+            );
+        }
+
+        self.page_order.insert(position, page_index);
+    }
+
+    pub(crate) fn move_page(&mut self, from: usize, to: usize) {
+        if from >= self.page_order.len() || to >= self.page_order.len() {
+            panic!(
+                "attempted to move page {} to index {}, but document only has {} pages",
+                from,
+                to,
+                self.page_order.len()
+            );
+        }
+
+        let page_index = self.page_order.remove(from);
+        self.page_order.insert(to, page_index);
+    }
+
+    pub(crate) fn duplicate_page(&mut self, position: usize) {
+        let page_index = self.page_at(position).unwrap_or_else(|| {
+            panic!(
+                "attempted to duplicate page {}, but document only has {} pages",
+                position,
+                self.page_order.len()
+            )
+        });
+        let original = &self.page_infos[page_index];
+        // Duplicates of duplicates share the stream of the original page.
+        let duplicate_of = original.duplicate_of.unwrap_or(page_index);
+        let surface_size = original.surface_size;
+
+        let ref_ = self.new_ref();
+        self.page_order.push(self.page_infos.len());
+        self.page_infos.push(PageInfo {
+            ref_,
+            surface_size,
+            annotations: vec![],
+            widget_annotations: vec![],
+            duplicate_of: Some(duplicate_of),
+        });
+    }
+
     pub(crate) fn set_outline(&mut self, outline: Outline) {
         // Only set it if it's not empty or if the current validator requires an
         // outline.
@@ -246,6 +436,28 @@ impl SerializeContext {
         }
     }
 
+    pub(crate) fn add_page_label_range(&mut self, start_index: usize, label: PageLabel) {
+        self.global_objects
+            .page_label_ranges
+            .push((start_index, label));
+    }
+
+    pub(crate) fn set_outline_from_headings(&mut self) {
+        self.outline_from_headings = true;
+    }
+
+    pub(crate) fn register_tag_position(&mut self, identifier: PageTagIdentifier, point: Point) {
+        self.tag_positions.entry(identifier).or_insert(point);
+    }
+
+    pub(crate) fn register_tag_bbox(&mut self, identifier: PageTagIdentifier, bbox: Rect) {
+        self.tag_bboxes.insert(identifier, bbox);
+    }
+
+    pub(crate) fn tag_bbox(&self, identifier: PageTagIdentifier) -> Option<Rect> {
+        self.tag_bboxes.get(&identifier).copied()
+    }
+
     pub(crate) fn set_location(&mut self, location: Location) {
         self.location = Some(location)
     }
@@ -261,6 +473,26 @@ impl SerializeContext {
     pub(crate) fn embed_file(&mut self, file: EmbeddedFile) -> Option<()> {
         let name = file.path.clone();
         let ref_ = self.register_cacheable(file);
+        self.insert_embedded_file(name, ref_)
+    }
+
+    pub(crate) fn add_associated_file(&mut self, file: EmbeddedFile) -> Option<()> {
+        let name = file.path.clone();
+        let ref_ = self.register_cacheable(AssociatedFile(file));
+        self.insert_embedded_file(name, ref_)?;
+
+        if supports_associated_files(self) {
+            self.global_objects.associated_files.push(ref_);
+        }
+
+        Some(())
+    }
+
+    pub(crate) fn set_facturx(&mut self, facturx: FacturX) {
+        self.global_objects.facturx = Some(facturx);
+    }
+
+    fn insert_embedded_file(&mut self, name: String, ref_: Ref) -> Option<()> {
         if self
             .global_objects
             .embedded_files
@@ -277,6 +509,22 @@ impl SerializeContext {
         self.chunk_container.metadata.as_ref()
     }
 
+    pub(crate) fn set_open_action(&mut self, open_action: Target) {
+        self.global_objects.open_action = MaybeTaken::new(Some(open_action));
+    }
+
+    pub(crate) fn set_document_actions(&mut self, actions: DocumentActions) {
+        self.global_objects.document_actions = MaybeTaken::new(Some(actions));
+    }
+
+    pub(crate) fn set_tab_order(&mut self, tab_order: TabOrder) {
+        self.global_objects.tab_order = Some(tab_order);
+    }
+
+    pub(crate) fn tab_order(&self) -> Option<TabOrder> {
+        self.global_objects.tab_order
+    }
+
     pub(crate) fn set_tag_tree(&mut self, root: TagTree) {
         // Only set the tag tree if the user actually enabled tagging.
         if self.serialize_settings.enable_tagging {
@@ -292,6 +540,30 @@ impl SerializeContext {
         self.serialize_settings.clone()
     }
 
+    pub(crate) fn security_handler(&self) -> Option<&SecurityHandler> {
+        self.security_handler.as_ref()
+    }
+
+    /// The encryptor for strings and streams, if the document is encrypted.
+    pub(crate) fn encryptor(&self) -> Option<Encryptor> {
+        self.security_handler.as_ref().map(|h| h.encryptor())
+    }
+
+    /// A byte string, encrypted if necessary.
+    pub(crate) fn str<'a>(&self, bytes: &'a [u8]) -> PdfStr<'a> {
+        PdfStr::str(bytes, self.encryptor())
+    }
+
+    /// A text string, encrypted if necessary.
+    pub(crate) fn text_str<'a>(&self, text: &'a str) -> PdfStr<'a> {
+        PdfStr::text_str(text, self.encryptor())
+    }
+
+    /// A date string, encrypted if necessary.
+    pub(crate) fn date(&self, date_time: DateTime) -> PdfStr<'static> {
+        PdfStr::date(date_time, self.encryptor())
+    }
+
     pub(crate) fn page_tree_ref(&mut self) -> Ref {
         *self
             .page_tree_ref
@@ -306,38 +578,63 @@ impl SerializeContext {
                 self.font_cache
                     .insert(font.font_info().clone(), font.clone());
 
-                Rc::new(RefCell::new(FontContainer::new(font.clone())))
+                Rc::new(RefCell::new(FontContainer::new(
+                    font.clone(),
+                    self.serialize_settings.subset_fonts,
+                )))
             })
             .clone()
     }
 
-    pub(crate) fn finish(mut self) -> KrillaResult<Pdf> {
+    pub(crate) fn finish(mut self) -> KrillaResult<(UnfinishedPdf, Vec<KrillaWarning>)> {
         // We need to be careful here that we serialize the objects in the right order,
         // as in some cases we use MaybeTake::take to remove an object, which means that
         // no object that is serialized afterwards must depend on it.
 
+        self.check_encryption()?;
+        self.check_cmyk_profile()?;
+        self.check_stroke_dashes()?;
+        self.check_languages()?;
+        self.check_field_fonts()?;
+        self.check_export_values()?;
+        self.check_field_names()?;
+        self.serialize_facturx()?;
+
         // Serialize all objects that can only be written in the end.
         self.serialize_destination_profiles();
-        self.serialize_page_label_tree();
+        self.serialize_page_label_tree()?;
         self.serialize_outline()?;
+        self.serialize_document_actions()?;
         self.serialize_fonts()?;
+        // Needs to happen before serializing the pages, so that we know the widget
+        // annotations of each page.
+        self.serialize_form()?;
         self.serialize_pages()?;
         self.serialize_page_tree();
-        self.serialize_xyz_destinations()?;
+        self.serialize_page_destinations()?;
         // It is important that we serialize the tags AFTER we have serialized the pages,
         // because page serialization will update the annotation refs of the page infos,
         // and when serializing the parent tree map we need to know the refs of the annotations
         self.serialize_tag_tree()?;
+        // Needs to happen after serializing the tag tree, so that we know the struct
+        // elements of all identifiers.
+        self.serialize_structure_destinations()?;
+        self.check_named_destination_references()?;
+        self.check_spot_colors()?;
 
         // Create the final PDF.
         let pdf = {
             let chunk_container = std::mem::take(&mut self.chunk_container);
             chunk_container.finish(&mut self)?
         };
-        self.register_limits(pdf.limits());
+        self.register_limits(pdf.pdf.limits());
 
         self.check_limits();
 
+        if self.serialize_settings.encryption.is_some() {
+            self.register_validation_error(ValidationError::Encryption);
+        }
+
         if !self.validation_errors.is_empty() {
             // Deduplicate errors, while still preserving order.
             let mut errors = vec![];
@@ -356,7 +653,7 @@ impl SerializeContext {
         // Just a sanity check that we've actually processed all items.
         self.global_objects.assert_all_taken();
 
-        Ok(pdf)
+        Ok((pdf, self.warnings))
     }
 }
 
@@ -368,6 +665,14 @@ impl SerializeContext {
         }
     }
 
+    pub(crate) fn register_warning(&mut self, warning: KrillaWarning) {
+        self.warnings.push(warning);
+    }
+
+    pub(crate) fn take_warnings(&mut self) -> Vec<KrillaWarning> {
+        std::mem::take(&mut self.warnings)
+    }
+
     pub(crate) fn register_limits(&mut self, limits: &Limits) {
         self.limits.merge(limits);
     }
@@ -411,9 +716,70 @@ impl SerializeContext {
         }
     }
 
-    pub(crate) fn register_named_destination(&mut self, nd: NamedDestination) {
-        let dest_ref = self.register_xyz_destination((*nd.xyz_dest).clone());
-        self.global_objects.named_destinations.insert(nd, dest_ref);
+    pub(crate) fn add_named_destination(&mut self, name: String, destination: Destination) {
+        let dest_ref = self.register_page_destination(destination);
+        self.global_objects
+            .named_destinations
+            .insert(name, dest_ref);
+    }
+
+    pub(crate) fn register_named_destination(&mut self, name: String, destination: Destination) {
+        // Destinations that were added to the document explicitly take precedence.
+        if !self.global_objects.named_destinations.contains_key(&name) {
+            self.add_named_destination(name, destination);
+        }
+    }
+
+    pub(crate) fn register_named_destination_reference(&mut self, name: String) {
+        self.global_objects
+            .named_destination_references
+            .insert(name);
+    }
+
+    /// Register the colorant name of a separation or DeviceN color space, along with
+    /// the alternate color that corresponds to the full tint of the colorant.
+    pub(crate) fn register_spot_color(&mut self, name: &str, alternate: Color) {
+        match self.global_objects.spot_colors.get(name) {
+            Some(existing) if *existing != alternate => {
+                self.global_objects
+                    .conflicting_spot_colors
+                    .insert(name.to_string());
+            }
+            Some(_) => {}
+            None => {
+                self.global_objects
+                    .spot_colors
+                    .insert(name.to_string(), alternate);
+            }
+        }
+    }
+
+    pub(crate) fn register_invalid_stroke_dash(&mut self) {
+        let location = self.location;
+        self.global_objects
+            .invalid_stroke_dash
+            .get_or_insert(location);
+    }
+
+    pub(crate) fn register_empty_language(&mut self) {
+        let location = self.location;
+        self.global_objects.empty_language.get_or_insert(location);
+    }
+
+    pub(crate) fn register_invalid_field_font(&mut self, font: Font) {
+        self.global_objects.invalid_field_font.get_or_insert(font);
+    }
+
+    pub(crate) fn register_invalid_export_value(&mut self, name: String) {
+        self.global_objects.invalid_export_value.get_or_insert(name);
+    }
+
+    pub(crate) fn register_form_field(&mut self, field: InternalField) {
+        self.global_objects.form_fields.push(field);
+    }
+
+    pub(crate) fn register_signature(&mut self, signature: SignatureChunk) {
+        self.chunk_container.signatures.push(signature);
     }
 
     pub(crate) fn register_page(&mut self, page: InternalPage) {
@@ -423,6 +789,9 @@ impl SerializeContext {
             surface_size: page.page_settings.surface_size(),
             // Will be populated when the page is serialized.
             annotations: vec![],
+            // Will be populated when the form fields are serialized.
+            widget_annotations: vec![],
+            duplicate_of: None,
         });
         self.global_objects.pages.push((ref_, page));
     }
@@ -463,21 +832,43 @@ impl SerializeContext {
 
     #[cfg(feature = "raster-images")]
     pub(crate) fn register_image(&mut self, image: Image) -> Ref {
-        self.register_cached(image, |sc, object, root_ref| {
+        let register = |sc: &mut Self, object: Image, root_ref| {
             let chunk = object.serialize(sc, root_ref);
             sc.chunk_container.images.push(chunk);
+        };
+
+        if self.serialize_settings.deduplicate_images {
+            self.register_cached(image, register)
+        } else {
+            let root_ref = self.new_ref();
+            register(self, image, root_ref);
+            root_ref
+        }
+    }
+
+    pub(crate) fn register_page_destination(&mut self, dest: Destination) -> Ref {
+        self.register_cached(dest, |sc, object, root_ref| {
+            sc.global_objects.page_destinations.push((root_ref, object));
         })
     }
 
-    pub(crate) fn register_xyz_destination(&mut self, dest: XyzDestination) -> Ref {
+    pub(crate) fn register_structure_destination(&mut self, dest: StructureDestination) -> Ref {
         self.register_cached(dest, |sc, object, root_ref| {
-            sc.global_objects.xyz_destinations.push((root_ref, object));
+            sc.global_objects
+                .structure_destinations
+                .push((root_ref, object));
         })
     }
 
+    pub(crate) fn register_content_struct_elem(&mut self, identifier: IdentifierType, ref_: Ref) {
+        self.global_objects
+            .content_struct_elems
+            .insert(identifier, ref_);
+    }
+
     pub(crate) fn register_page_label(&mut self, page_label: PageLabel) -> Ref {
         let ref_ = self.new_ref();
-        let chunk = page_label.serialize(ref_);
+        let chunk = page_label.serialize(self, ref_);
         self.chunk_container.page_labels.push(chunk);
         ref_
     }
@@ -504,6 +895,9 @@ impl SerializeContext {
             ColorSpace::Cmyk(cs) => {
                 MaybeDeviceColorSpace::ColorSpace(self.register_resourceable(cs))
             }
+            ColorSpace::Lab(white_point) => MaybeDeviceColorSpace::ColorSpace(
+                self.register_resourceable(LabColorSpace(white_point)),
+            ),
             ColorSpace::DeviceGray => MaybeDeviceColorSpace::DeviceGray,
             ColorSpace::DeviceRgb => MaybeDeviceColorSpace::DeviceRgb,
             ColorSpace::DeviceCmyk => MaybeDeviceColorSpace::DeviceCMYK,
@@ -521,22 +915,39 @@ impl SerializeContext {
             let mut chunk = Chunk::new();
 
             let oi_ref = self.new_ref();
-            let mut oi = chunk.indirect(oi_ref).start::<OutputIntent>();
-            let icc_profile = self.serialize_settings.pdf_version().rgb_icc();
-
-            oi.dest_output_profile(self.register_cacheable(icc_profile.clone()))
-                .subtype(subtype)
-                .output_condition_identifier(TextStr("Custom"))
-                .output_condition(TextStr("sRGB"))
-                .registry_name(TextStr(""))
-                .info(TextStr(
-                    format!(
-                        "sRGB v{}.{}",
-                        icc_profile.metadata().major,
-                        icc_profile.metadata().minor
-                    )
-                    .as_str(),
-                ));
+
+            // If a CMYK profile was provided, we assume that the document is intended
+            // for print and use it as the output intent instead of sRGB.
+            let (profile_ref, condition, metadata) =
+                match self.serialize_settings.cmyk_profile.clone() {
+                    Some(icc_profile) => {
+                        let metadata = icc_profile.metadata().clone();
+                        (self.register_cacheable(icc_profile), "CMYK", metadata)
+                    }
+                    None => {
+                        let icc_profile = self.serialize_settings.pdf_version().rgb_icc();
+                        let metadata = icc_profile.metadata().clone();
+                        (self.register_cacheable(icc_profile), "sRGB", metadata)
+                    }
+                };
+
+            let info = format!("{} v{}.{}", condition, metadata.major, metadata.minor);
+            // The typed `OutputIntent` writer only accepts unencrypted text strings,
+            // so we write the dictionary directly.
+            let subtype = match subtype {
+                OutputIntentSubtype::PDFX => Name(b"GTS_PDFX"),
+                OutputIntentSubtype::PDFA => Name(b"GTS_PDFA1"),
+                OutputIntentSubtype::PDFE => Name(b"ISO_PDFE1"),
+                OutputIntentSubtype::Custom(name) => name,
+            };
+            let mut oi = chunk.indirect(oi_ref).dict();
+            oi.pair(Name(b"Type"), Name(b"OutputIntent"));
+            oi.pair(Name(b"DestOutputProfile"), profile_ref);
+            oi.pair(Name(b"S"), subtype);
+            oi.pair(Name(b"OutputConditionIdentifier"), self.text_str("Custom"));
+            oi.pair(Name(b"OutputCondition"), self.text_str(condition));
+            oi.pair(Name(b"RegistryName"), self.text_str(""));
+            oi.pair(Name(b"Info"), self.text_str(&info));
             oi.finish();
 
             let mut array = chunk.indirect(root_ref).array();
@@ -547,22 +958,48 @@ impl SerializeContext {
         });
     }
 
-    fn serialize_page_label_tree(&mut self) {
-        if let Some(container) = PageLabelContainer::new(
-            &self
-                .global_objects
-                .pages
-                .iter()
-                .map(|(_, p)| p.page_settings.page_label().clone())
-                .collect::<Vec<_>>(),
-        ) {
+    fn serialize_page_label_tree(&mut self) -> KrillaResult<()> {
+        let page_labels = self
+            .global_objects
+            .pages
+            .iter()
+            .map(|(_, p)| (p.page_index, p.page_settings.page_label()))
+            .collect::<HashMap<_, _>>();
+        // Duplicated pages inherit the label of their original page.
+        let mut labels = self
+            .page_order
+            .iter()
+            .map(|i| {
+                let page_index = self.page_infos[*i].duplicate_of.unwrap_or(*i);
+                page_labels[&page_index].clone()
+            })
+            .collect::<Vec<_>>();
+        let ranges = std::mem::take(&mut self.global_objects.page_label_ranges);
+        apply_page_label_ranges(&mut labels, &ranges)?;
+
+        if let Some(container) = PageLabelContainer::new(&labels) {
             let page_label_tree_ref = self.new_ref();
             let chunk = container.serialize(self, page_label_tree_ref);
             self.chunk_container.page_label_tree = Some((page_label_tree_ref, chunk));
         }
+
+        Ok(())
     }
 
     fn serialize_outline(&mut self) -> KrillaResult<()> {
+        // An outline that was set manually always takes precedence.
+        if self.outline_from_headings && self.global_objects.outline.is_none() {
+            let outline = self.global_objects.tag_tree.as_ref().map(|tag_tree| {
+                Outline::from_headings(tag_tree.headings(), &self.tag_positions, |i| {
+                    self.page_position(i)
+                })
+            });
+
+            if let Some(outline) = outline {
+                self.set_outline(outline);
+            }
+        }
+
         let outline = self.global_objects.outline.take();
         if let Some(outline) = &outline {
             let outline_ref = self.new_ref();
@@ -575,6 +1012,24 @@ impl SerializeContext {
         Ok(())
     }
 
+    fn serialize_document_actions(&mut self) -> KrillaResult<()> {
+        let open_action = self.global_objects.open_action.take();
+        if let Some(open_action) = &open_action {
+            let open_action_ref = self.new_ref();
+            let chunk = serialize_open_action(self, open_action, open_action_ref)?;
+            self.chunk_container.open_action = Some((open_action_ref, chunk));
+        }
+
+        let document_actions = self.global_objects.document_actions.take();
+        if let Some(actions) = document_actions.filter(|a| !a.is_empty()) {
+            let actions_ref = self.new_ref();
+            let chunk = actions.serialize(self, actions_ref)?;
+            self.chunk_container.document_actions = Some((actions_ref, chunk));
+        }
+
+        Ok(())
+    }
+
     fn serialize_fonts(&mut self) -> KrillaResult<()> {
         let fonts = self.global_objects.font_map.take();
         let mut sorted = fonts.values().collect::<Vec<_>>();
@@ -590,10 +1045,12 @@ impl SerializeContext {
                 }
             }
 
-            if !borrowed.cid_font().is_empty() {
-                let f = self.register_font_identifier(borrowed.cid_font().identifier());
-                let chunk = borrowed.cid_font().serialize(self, f.get_ref())?;
-                self.chunk_container.fonts.push(chunk);
+            for cid_font in [borrowed.cid_font(), borrowed.vertical_cid_font()] {
+                if !cid_font.is_empty() {
+                    let f = self.register_font_identifier(cid_font.identifier());
+                    let chunk = cid_font.serialize(self, f.get_ref())?;
+                    self.chunk_container.fonts.push(chunk);
+                }
             }
         }
 
@@ -603,34 +1060,228 @@ impl SerializeContext {
     fn serialize_pages(&mut self) -> KrillaResult<()> {
         let pages = self.global_objects.pages.take();
         for (ref_, page) in pages {
-            let chunk = page.serialize(self, ref_)?;
+            let duplicate_refs = self
+                .page_infos
+                .iter()
+                .filter(|i| i.duplicate_of == Some(page.page_index))
+                .map(|i| i.ref_)
+                .collect::<Vec<_>>();
+            let chunk = page.serialize(self, ref_, &duplicate_refs)?;
             self.chunk_container.pages.push(chunk);
         }
 
         Ok(())
     }
 
+    fn serialize_form(&mut self) -> KrillaResult<()> {
+        let fields = self.global_objects.form_fields.take();
+
+        if fields.is_empty() {
+            return Ok(());
+        }
+
+        let mut font_refs = vec![];
+        for font_ref in fields.iter().filter_map(|f| f.font_ref()) {
+            if !font_refs.contains(&font_ref) {
+                font_refs.push(font_ref);
+            }
+        }
+
+        let mut field_refs = vec![];
+
+        for field in &fields {
+            let field_ref = self.new_ref();
+            let chunk = field.serialize(self, field_ref, &font_refs)?;
+            self.chunk_container.form_fields.push(chunk);
+            field_refs.push(field_ref);
+        }
+
+        let acro_form_ref = self.new_ref();
+        let mut chunk = Chunk::new();
+        let mut acro_form = chunk.indirect(acro_form_ref).dict();
+        acro_form
+            .insert(Name(b"Fields"))
+            .array()
+            .items(field_refs.iter().copied());
+
+        if !font_refs.is_empty() {
+            let mut resources = acro_form.insert(Name(b"DR")).dict();
+            let mut fonts = resources.insert(Name(b"Font")).dict();
+            for (i, font_ref) in font_refs.iter().enumerate() {
+                fonts.pair(Name(font_resource_name(i).as_bytes()), *font_ref);
+            }
+            fonts.finish();
+            resources.finish();
+        }
+
+        if fields
+            .iter()
+            .any(|f| matches!(f, InternalField::Signature(_)))
+        {
+            // SignaturesExist | AppendOnly
+            acro_form.pair(Name(b"SigFlags"), 3);
+        }
+
+        acro_form.finish();
+
+        self.chunk_container.acro_form = Some((acro_form_ref, chunk));
+
+        Ok(())
+    }
+
     fn serialize_page_tree(&mut self) {
         if let Some(page_tree_ref) = self.page_tree_ref {
             let mut page_tree_chunk = Chunk::new();
             page_tree_chunk
                 .pages(page_tree_ref)
-                .count(self.page_infos.len() as i32)
-                .kids(self.page_infos.iter().map(|i| i.ref_));
+                .count(self.page_order.len() as i32)
+                .kids(self.page_order.iter().map(|i| self.page_infos[*i].ref_));
             self.chunk_container.page_tree = Some((page_tree_ref, page_tree_chunk));
         }
     }
 
-    fn serialize_xyz_destinations(&mut self) -> KrillaResult<()> {
-        let xyz_destinations = self.global_objects.xyz_destinations.take();
-        for (ref_, dest) in &xyz_destinations {
-            let chunk = dest.serialize(self, *ref_)?;
+    fn serialize_page_destinations(&mut self) -> KrillaResult<()> {
+        let page_destinations = self.global_objects.page_destinations.take();
+        for (ref_, dest) in &page_destinations {
+            let chunk = dest.serialize_page_destination(self, *ref_)?;
             self.chunk_container.destinations.push(chunk);
         }
 
         Ok(())
     }
 
+    fn serialize_structure_destinations(&mut self) -> KrillaResult<()> {
+        let structure_destinations =
+            std::mem::take(&mut self.global_objects.structure_destinations);
+        for (ref_, dest) in &structure_destinations {
+            let IdentifierInner::Real(identifier) = dest.identifier().0 else {
+                unreachable!("structure destinations with dummy identifiers are never written")
+            };
+
+            let struct_elem = *self
+                .global_objects
+                .content_struct_elems
+                .get(&identifier)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "structure destination points to identifier {:?}, which doesn't appear in the tag tree",
+                        identifier
+                    )
+                });
+
+            let chunk = dest.serialize(self, *ref_, struct_elem)?;
+            self.chunk_container.destinations.push(chunk);
+        }
+
+        Ok(())
+    }
+
+    fn check_named_destination_references(&self) -> KrillaResult<()> {
+        let unresolved = self
+            .global_objects
+            .named_destination_references
+            .iter()
+            .filter(|name| !self.global_objects.named_destinations.contains_key(*name))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if unresolved.is_empty() {
+            Ok(())
+        } else {
+            Err(KrillaError::UnresolvedNamedDestinations(unresolved))
+        }
+    }
+
+    fn check_encryption(&self) -> KrillaResult<()> {
+        match &self.serialize_settings.encryption {
+            Some(encryption) => encryption
+                .check(self.serialize_settings.pdf_version())
+                .map_err(KrillaError::Encryption),
+            None => Ok(()),
+        }
+    }
+
+    fn check_cmyk_profile(&self) -> KrillaResult<()> {
+        if self.unsupported_cmyk_profile {
+            Err(KrillaError::UnsupportedCMYKProfile)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_stroke_dashes(&self) -> KrillaResult<()> {
+        match self.global_objects.invalid_stroke_dash {
+            Some(location) => Err(KrillaError::InvalidStrokeDash(location)),
+            None => Ok(()),
+        }
+    }
+
+    fn check_languages(&self) -> KrillaResult<()> {
+        match self.global_objects.empty_language {
+            Some(location) => Err(KrillaError::EmptyLanguage(location)),
+            None => Ok(()),
+        }
+    }
+
+    fn check_field_fonts(&self) -> KrillaResult<()> {
+        match &self.global_objects.invalid_field_font {
+            Some(font) => Err(KrillaError::Font(
+                font.clone(),
+                "the font of a form field needs to be a TrueType or CFF font".to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn check_export_values(&self) -> KrillaResult<()> {
+        match &self.global_objects.invalid_export_value {
+            Some(name) => Err(KrillaError::InvalidExportValue(name.clone())),
+            None => Ok(()),
+        }
+    }
+
+    fn check_field_names(&self) -> KrillaResult<()> {
+        let mut names = HashSet::new();
+
+        // All fields are added at the top level, so they are all siblings of each other.
+        for field in self.global_objects.form_fields.iter() {
+            let name = field.name();
+
+            if name.contains('.') {
+                return Err(KrillaError::InvalidFieldName(name.to_string()));
+            }
+
+            if !names.insert(name) {
+                return Err(KrillaError::DuplicateFieldName(name.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn serialize_facturx(&mut self) -> KrillaResult<()> {
+        let Some(facturx) = &self.global_objects.facturx else {
+            return Ok(());
+        };
+
+        if !self.serialize_settings.validator().is_pdf_a3() {
+            return Err(KrillaError::FacturX(FacturXError::NotPdfA3));
+        }
+
+        let file = facturx.embedded_file();
+        match self.add_associated_file(file) {
+            Some(()) => Ok(()),
+            None => Err(KrillaError::FacturX(FacturXError::DuplicateInvoice)),
+        }
+    }
+
+    fn check_spot_colors(&self) -> KrillaResult<()> {
+        match self.global_objects.conflicting_spot_colors.first() {
+            Some(name) => Err(KrillaError::ConflictingSpotColor(name.clone())),
+            None => Ok(()),
+        }
+    }
+
     fn serialize_tag_tree(&mut self) -> KrillaResult<()> {
         let tag_tree = self.global_objects.tag_tree.take();
         let struct_parents = self.global_objects.struct_parents.take();
@@ -657,6 +1308,9 @@ impl SerializeContext {
                 let name = format!("H{level}");
                 role_map.insert(Name(name.as_bytes()), StructRole::P);
             }
+            for (name, role) in self.global_objects.custom_roles.iter() {
+                role_map.insert(Name(name.as_bytes()), *role);
+            }
             role_map.finish();
             tree.insert(Name(b"K")).array().item(document_ref);
 
@@ -708,7 +1362,8 @@ impl SerializeContext {
                 let mut names = id_tree.names();
 
                 for (name, ref_) in id_tree_map {
-                    names.insert(Str(name.as_bytes()), ref_);
+                    let name = encrypt_bytes(name.as_bytes(), self.encryptor());
+                    names.insert(Str(&name), ref_);
                 }
             }
 
@@ -816,13 +1471,16 @@ impl<T> DerefMut for MaybeTaken<T> {
 pub(crate) struct GlobalObjects {
     /// All named destinations that have been registered, including a Ref to their destination.
     // Needs to be pub(crate) because writing of named destinations happens in `ChunkContainer`.
-    pub(crate) named_destinations: MaybeTaken<HashMap<NamedDestination, Ref>>,
+    pub(crate) named_destinations: MaybeTaken<HashMap<String, Ref>>,
+    /// The names of all named destinations that have been referenced by name only, and
+    /// must be resolved by the end of the export process.
+    named_destination_references: BTreeSet<String>,
     /// A map from fonts to font container.
     font_map: MaybeTaken<HashMap<Font, Rc<RefCell<FontContainer>>>>,
-    /// All XYZ destinations used in the document. The reason we need to store them
+    /// All destinations pointing to a page used in the document. The reason we need to store them
     /// separately is that we can only serialize them in the very end, once all pages
     /// have been written, so that we know the Ref of the page they belong to.
-    xyz_destinations: MaybeTaken<Vec<(Ref, XyzDestination)>>,
+    page_destinations: MaybeTaken<Vec<(Ref, Destination)>>,
     /// All pages and their corresponding chunks. Similarly to destinations, they need
     /// to be written in the very end, because pages might contain annotations which in turn
     /// depend on future pages (not written yet), so pages must also only be written in the
@@ -832,26 +1490,67 @@ pub(crate) struct GlobalObjects {
     struct_parents: MaybeTaken<Vec<StructParentElement>>,
     /// Stores the document outline.
     outline: MaybeTaken<Option<Outline>>,
+    /// Stores the action that should be performed when opening the document.
+    open_action: MaybeTaken<Option<Target>>,
+    /// Stores the actions for document events.
+    document_actions: MaybeTaken<Option<DocumentActions>>,
     /// Stores the tag tree.
     tag_tree: MaybeTaken<Option<TagTree>>,
     /// Stores the association of the names of embedded files to their refs,
     /// for the catalog dictionary.
     pub(crate) embedded_files: MaybeTaken<BTreeMap<String, Ref>>,
+    /// The refs of the embedded files that are associated with the document.
+    pub(crate) associated_files: Vec<Ref>,
+    /// The Factur-X invoice of the document.
+    pub(crate) facturx: Option<FacturX>,
+    /// Stores all form fields of the document.
+    form_fields: MaybeTaken<Vec<InternalField>>,
+
+    /// The page label ranges of the document, along with the index of the
+    /// page they start at.
+    page_label_ranges: Vec<(usize, PageLabel)>,
+    /// The tab order of pages that don't set their own tab order.
+    tab_order: Option<TabOrder>,
+    /// Stores the structure destinations, which are written after the tag tree.
+    structure_destinations: Vec<(Ref, StructureDestination)>,
+    /// The struct elements containing the content of each identifier in the tag tree.
+    content_struct_elems: HashMap<IdentifierType, Ref>,
+
+    /// The colorant names of all spot colors used in the document, along with the
+    /// alternate color of their full tint.
+    spot_colors: HashMap<String, Color>,
+    /// The colorant names that have been used with more than one alternate color.
+    conflicting_spot_colors: BTreeSet<String>,
+    /// The location of the first stroke with an invalid dash, if there was one.
+    invalid_stroke_dash: Option<Option<Location>>,
+    /// The location of the first content tag with an empty language, if there was one.
+    empty_language: Option<Option<Location>>,
+    /// The font of the first form field that can't be embedded as a CID font, if there
+    /// was one.
+    invalid_field_font: Option<Font>,
+    /// The name of the first check box or radio group with `Off` as an export value.
+    invalid_export_value: Option<String>,
 
     /// A list of custom headings numbers used in the document.
     pub(crate) custom_heading_roles: BTreeSet<NonZeroU32>,
+    /// The names of all custom tags with a role used in the document, along with
+    /// their role.
+    pub(crate) custom_roles: BTreeMap<String, StructRole>,
 }
 
 impl GlobalObjects {
     pub(crate) fn assert_all_taken(&self) {
         assert!(self.named_destinations.is_taken());
         assert!(self.font_map.is_taken());
-        assert!(self.xyz_destinations.is_taken());
+        assert!(self.page_destinations.is_taken());
         assert!(self.pages.is_taken());
         assert!(self.struct_parents.is_taken());
         assert!(self.outline.is_taken());
+        assert!(self.open_action.is_taken());
+        assert!(self.document_actions.is_taken());
         assert!(self.tag_tree.is_taken());
         assert!(self.embedded_files.is_taken());
+        assert!(self.form_fields.is_taken());
     }
 }
 
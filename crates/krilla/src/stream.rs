@@ -9,9 +9,10 @@
 //! are there for. Currently, there are only two situations where you need to do that:
 //!
 //! - When using masks and defining the contents of the mask.
-//! - When using a pattern fill or stroke and defining the contents of the pattern.
+//! - When using a [`Pattern`] fill or stroke and defining the contents of the pattern. Note
+//!   that it is usually more convenient to use [`Surface::create_tiling_pattern`] instead.
 //!
-//! If you want to do any of the two above, you need to call the [`Surface::stream_builder`] method
+//! If you want to do any of the above, you need to call the [`Surface::stream_builder`] method
 //! of the current surface. The stream builder represents a kind of sub-context that is
 //! independent of the main surface you are working with. Once you have a stream builder, you
 //! can once again invoke the [`StreamBuilder::surface`] method, and use this new surface to define the contents
@@ -21,6 +22,7 @@
 //!
 //! [`Page::surface`]: crate::page::Page::surface
 //! [`Surface::stream_builder`]: crate::surface::Surface::stream_builder
+//! [`Surface::create_tiling_pattern`]: crate::surface::Surface::create_tiling_pattern
 //! [`Pattern`]: crate::graphics::paint::Pattern
 //! [`Mask`]: crate::graphics::mask::Mask
 
@@ -29,10 +31,11 @@ use std::ops::DerefMut;
 
 use flate2::write::ZlibEncoder;
 use flate2::Compression;
-use pdf_writer::{Array, Dict, Name};
+use pdf_writer::{Array, Dict, Name, Null};
 
 use crate::configure::ValidationError;
 use crate::content::ContentBuilder;
+use crate::encryption::Encryptor;
 use crate::geom::{Rect, Transform};
 use crate::resource::{ResourceDictionary, ResourceDictionaryBuilder};
 use crate::serialize::SerializeContext;
@@ -88,6 +91,8 @@ impl Stream {
 pub struct StreamBuilder<'a> {
     sc: &'a mut SerializeContext,
     stream: Stream,
+    /// Whether the stream defines the tile of an uncolored tiling pattern.
+    pub(crate) uncolored: bool,
 }
 
 impl<'a> StreamBuilder<'a> {
@@ -95,23 +100,22 @@ impl<'a> StreamBuilder<'a> {
         Self {
             sc,
             stream: Stream::empty(),
+            uncolored: false,
         }
     }
 
     /// Get the surface of the stream builder.
     pub fn surface(&mut self) -> Surface {
+        let mut content_builder = ContentBuilder::new(Transform::identity(), true);
+        content_builder.uncolored = self.uncolored;
+
         // Stream builders cannot have any tags since we always pass a dummy
         // identifier. Only main page content streams can have one.
         let finish_fn = Box::new(|stream, _| {
             self.stream = stream;
         });
 
-        Surface::new(
-            self.sc,
-            ContentBuilder::new(Transform::identity(), true),
-            None,
-            finish_fn,
-        )
+        Surface::new(self.sc, content_builder, None, finish_fn)
     }
 
     /// Turn the stream builder into a stream.
@@ -127,6 +131,9 @@ pub(crate) enum StreamFilter {
     FlateMemoized,
     AsciiHex,
     Dct,
+    Jpx,
+    /// CCITT Group 4 encoding of a bilevel image with the given number of columns and rows.
+    Ccitt(u32, u32),
 }
 
 impl StreamFilter {
@@ -136,6 +143,8 @@ impl StreamFilter {
             Self::Flate => Name(b"FlateDecode"),
             Self::FlateMemoized => Name(b"FlateDecode"),
             Self::Dct => Name(b"DCTDecode"),
+            Self::Jpx => Name(b"JPXDecode"),
+            Self::Ccitt(..) => Name(b"CCITTFaxDecode"),
         }
     }
 
@@ -145,6 +154,23 @@ impl StreamFilter {
             StreamFilter::FlateMemoized => true,
             StreamFilter::AsciiHex => false,
             StreamFilter::Dct => true,
+            StreamFilter::Jpx => true,
+            StreamFilter::Ccitt(..) => true,
+        }
+    }
+
+    fn has_decode_params(&self) -> bool {
+        matches!(self, StreamFilter::Ccitt(..))
+    }
+
+    fn write_decode_params(&self, dict: &mut Dict) {
+        if let StreamFilter::Ccitt(columns, rows) = self {
+            // Pure two-dimensional (Group 4) encoding.
+            dict.pair(Name(b"K"), -1);
+            dict.pair(Name(b"Columns"), *columns as i32);
+            dict.pair(Name(b"Rows"), *rows as i32);
+            // 0 bits represent black pixels, as usual for `DeviceGray`.
+            dict.pair(Name(b"BlackIs1"), false);
         }
     }
 }
@@ -160,6 +186,11 @@ impl StreamFilter {
             // this is only used for JPEG images which are already encoded,
             // so this shouldn't be called at all.
             StreamFilter::Dct => panic!("can't apply dct decode"),
+            // Same as above, but for JPEG 2000 images.
+            StreamFilter::Jpx => panic!("can't apply jpx decode"),
+            // CCITT encoding only makes sense for bilevel images, which are
+            // encoded when creating the image.
+            StreamFilter::Ccitt(..) => panic!("can't apply ccitt decode"),
         }
     }
 }
@@ -256,13 +287,39 @@ impl<'a> FilterStreamBuilder<'a> {
         filter_stream
     }
 
-    pub(crate) fn finish(mut self, serialize_settings: &SerializeSettings) -> FilterStream<'a> {
+    pub(crate) fn new_from_jpx_data(content: &'a [u8]) -> Self {
+        let mut filter_stream = Self::empty(content);
+        // JPEG 2000 data already is JPX encoded.
+        filter_stream.add_unapplied_filter(StreamFilter::Jpx);
+
+        filter_stream
+    }
+
+    pub(crate) fn new_from_ccitt_data(content: &'a [u8], columns: u32, rows: u32) -> Self {
+        let mut filter_stream = Self::empty(content);
+        filter_stream.add_unapplied_filter(StreamFilter::Ccitt(columns, rows));
+
+        filter_stream
+    }
+
+    /// Finish the stream. If the document is encrypted, the data is encrypted after
+    /// applying all filters.
+    pub(crate) fn finish(
+        mut self,
+        serialize_settings: &SerializeSettings,
+        encryptor: Option<Encryptor>,
+    ) -> FilterStream<'a> {
         if serialize_settings.ascii_compatible && self.filters.is_binary() {
             self.add_filter(StreamFilter::AsciiHex);
         }
 
+        let content = match encryptor {
+            Some(encryptor) => Cow::Owned(encryptor.encrypt(&self.content)),
+            None => self.content,
+        };
+
         FilterStream {
-            content: self.content,
+            content,
             filters: self.filters,
         }
     }
@@ -303,6 +360,28 @@ impl FilterStream<'_> {
                     .items(filters.iter().map(|f| f.to_name()).rev());
             }
         }
+
+        match &self.filters {
+            StreamFilters::Single(filter) if filter.has_decode_params() => {
+                let mut params = dict.deref_mut().insert(Name(b"DecodeParms")).dict();
+                filter.write_decode_params(&mut params);
+            }
+            StreamFilters::Multiple(filters) if filters.iter().any(|f| f.has_decode_params()) => {
+                let mut array = dict
+                    .deref_mut()
+                    .insert(Name(b"DecodeParms"))
+                    .start::<Array>();
+
+                for filter in filters.iter().rev() {
+                    if filter.has_decode_params() {
+                        filter.write_decode_params(&mut array.push().dict());
+                    } else {
+                        array.item(Null);
+                    }
+                }
+            }
+            _ => {}
+        }
     }
 }
 
@@ -320,6 +399,17 @@ pub(crate) fn deflate_encode(data: &[u8]) -> Vec<u8> {
     e.finish().unwrap()
 }
 
+#[cfg(feature = "raster-images")]
+pub(crate) fn deflate_decode(data: &[u8]) -> Option<Vec<u8>> {
+    use std::io::Read;
+
+    let mut decoded = Vec::new();
+    flate2::read::ZlibDecoder::new(data)
+        .read_to_end(&mut decoded)
+        .ok()?;
+    Some(decoded)
+}
+
 fn hex_encode(data: &[u8]) -> Vec<u8> {
     use std::fmt::Write;
 
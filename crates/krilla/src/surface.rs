@@ -6,26 +6,30 @@
 
 use crate::color::rgb;
 use crate::content::ContentBuilder;
+use crate::error::KrillaWarning;
 use crate::geom::Path;
-#[cfg(feature = "raster-images")]
-use crate::geom::Size;
-use crate::geom::{Point, Transform};
+use crate::geom::{Point, Size, Transform};
 use crate::graphic::Graphic;
-use crate::graphics::blend::BlendMode;
+use crate::graphics::blend::{BlendMode, GroupColorSpace, OverprintMode};
+use crate::graphics::color::RenderingIntent;
 use crate::graphics::graphics_state::ExtGState;
 #[cfg(feature = "raster-images")]
 use crate::graphics::image::Image;
-use crate::graphics::mask::Mask;
-use crate::graphics::paint::{Fill, FillRule, Stroke};
+use crate::graphics::mask::{Mask, MaskType};
+use crate::graphics::paint::{Fill, FillRule, LineJoin, Stroke, TilingPattern};
 use crate::graphics::shading_function::ShadingFunction;
-use crate::interchange::tagging::{ContentTag, Identifier, PageTagIdentifier};
+use crate::interchange::tagging::{ArtifactType, ContentTag, Identifier, PageTagIdentifier};
 use crate::num::NormalizedF32;
 use crate::paint::{InnerPaint, Paint};
 use crate::serialize::SerializeContext;
 use crate::stream::{Stream, StreamBuilder};
 use crate::tagging::SpanTag;
+use crate::template::Template;
+use crate::text::type3::GlyphColors;
 use crate::text::Font;
-use crate::text::{draw_glyph, Glyph};
+use crate::text::{
+    draw_glyph, ColorPalette, Glyph, GlyphId, SyntheticStyle, TextSpacing, WritingMode,
+};
 #[cfg(feature = "simple-text")]
 use crate::text::{shape::naive_shape, TextDirection};
 
@@ -66,9 +70,19 @@ pub struct Surface<'a> {
     pub(crate) sc: &'a mut SerializeContext,
     fill: Option<Fill>,
     stroke: Option<Stroke>,
+    text_spacing: TextSpacing,
+    writing_mode: WritingMode,
+    synthetic_style: SyntheticStyle,
+    /// The palette for COLR glyphs, as registered with the `SerializeContext`.
+    palette: usize,
     bd: Builders,
     push_instructions: Vec<PushInstruction>,
     page_identifier: Option<PageTagIdentifier>,
+    /// The identifier of the current tagged section, if nothing has been drawn
+    /// in it so far.
+    pending_tag_position: Option<PageTagIdentifier>,
+    /// The identifier of the current tagged section.
+    active_tag: Option<PageTagIdentifier>,
     finish_fn: Box<dyn FnMut(Stream, i32) + 'a>,
 }
 
@@ -83,8 +97,14 @@ impl<'a> Surface<'a> {
             sc,
             bd: Builders::new(root_builder),
             page_identifier,
+            pending_tag_position: None,
+            active_tag: None,
             fill: None,
             stroke: None,
+            text_spacing: TextSpacing::default(),
+            writing_mode: WritingMode::default(),
+            synthetic_style: SyntheticStyle::default(),
+            palette: 0,
             push_instructions: vec![],
             finish_fn,
         }
@@ -95,13 +115,64 @@ impl<'a> Surface<'a> {
         StreamBuilder::new(self.sc)
     }
 
+    /// Create a new mask, whose content is drawn by `f`.
+    ///
+    /// For luminosity masks, the luminosity of the content determines the opacity,
+    /// which makes it possible to create effects like vignettes or feathered edges by
+    /// drawing a gradient. Creating masks with the same content is cheap, since each
+    /// distinct mask is only written once.
+    pub fn create_mask(&mut self, mask_type: MaskType, f: impl FnOnce(&mut Surface)) -> Mask {
+        Mask::from_surface(self.sc, mask_type, f)
+    }
+
+    /// Create a new tiling pattern, whose tile of the given size is drawn by `f`.
+    ///
+    /// Adjacent tiles are placed `x_spacing` and `y_spacing` apart from each other,
+    /// so a spacing of zero means that the tiles touch. The transform is applied to
+    /// the whole pattern. See [`TilingPattern`] for more information.
+    ///
+    /// # Panics
+    /// Panics if a spacing is smaller than or equal to the negative size of the tile.
+    pub fn create_tiling_pattern(
+        &mut self,
+        size: Size,
+        x_spacing: f32,
+        y_spacing: f32,
+        transform: Transform,
+        f: impl FnOnce(&mut Surface),
+    ) -> TilingPattern {
+        TilingPattern::new(self.sc, size, x_spacing, y_spacing, transform, false, f)
+    }
+
+    /// Create a new uncolored tiling pattern, whose tile of the given size is drawn by `f`.
+    ///
+    /// All colors that are used when drawing the tile are ignored, and the whole tile is
+    /// instead painted with the color of the pattern, see [`TilingPattern::with_color`].
+    /// Otherwise, this works the same as [`Surface::create_tiling_pattern`].
+    ///
+    /// # Panics
+    /// Panics if a spacing is smaller than or equal to the negative size of the tile.
+    pub fn create_uncolored_tiling_pattern(
+        &mut self,
+        size: Size,
+        x_spacing: f32,
+        y_spacing: f32,
+        transform: Transform,
+        f: impl FnOnce(&mut Surface),
+    ) -> TilingPattern {
+        TilingPattern::new(self.sc, size, x_spacing, y_spacing, transform, true, f)
+    }
+
     /// Set the fill that should be used for the next drawing operation.
     ///
     /// You can set it to `None` if you want to disable filling (though
     /// if there is no active stroke, than krilla will choose to fill
     /// with black by default).
     pub fn set_fill(&mut self, fill: Option<Fill>) {
-        self.fill = fill;
+        self.fill = fill.map(|mut fill| {
+            fill.paint = self.convert_paint(fill.paint);
+            fill
+        });
     }
 
     /// Get the currently active fill.
@@ -113,7 +184,18 @@ impl<'a> Surface<'a> {
     ///
     /// You can set it to `None` if you want to disable stroking.
     pub fn set_stroke(&mut self, stroke: Option<Stroke>) {
-        self.stroke = stroke;
+        self.stroke = stroke.map(|mut stroke| {
+            stroke.paint = self.convert_paint(stroke.paint);
+            stroke
+        });
+    }
+
+    fn convert_paint(&self, paint: Paint) -> Paint {
+        if self.sc.serialize_settings().converts_cmyk_to_rgb() {
+            paint.cmyk_to_rgb()
+        } else {
+            paint
+        }
     }
 
     /// Get the currently active stroke.
@@ -121,8 +203,74 @@ impl<'a> Surface<'a> {
         self.stroke.as_ref()
     }
 
+    /// Set the spacing that should be applied when drawing glyphs.
+    ///
+    /// See [`TextSpacing`] for how the spacing interacts with the advances of
+    /// the glyphs.
+    pub fn set_text_spacing(&mut self, spacing: TextSpacing) {
+        self.text_spacing = spacing;
+    }
+
+    /// Get the currently active text spacing.
+    pub fn get_text_spacing(&self) -> TextSpacing {
+        self.text_spacing
+    }
+
+    /// Set the writing mode that should be used when drawing glyphs.
+    ///
+    /// In the vertical writing mode, the glyphs should be shaped with a top-to-bottom
+    /// direction, so that the vertical variants of the glyphs are used and their
+    /// advances and offsets are vertical. See [`WritingMode`] for more information.
+    /// Text spacing is ignored in the vertical writing mode.
+    pub fn set_writing_mode(&mut self, writing_mode: WritingMode) {
+        self.writing_mode = writing_mode;
+    }
+
+    /// Get the currently active writing mode.
+    pub fn get_writing_mode(&self) -> WritingMode {
+        self.writing_mode
+    }
+
+    /// Set the synthetic style that should be applied when drawing glyphs.
+    ///
+    /// This allows emboldening and slanting the glyphs of fonts that don't come
+    /// with a bold or italic style. See [`SyntheticStyle`] for more information.
+    pub fn set_synthetic_style(&mut self, synthetic_style: SyntheticStyle) {
+        self.synthetic_style = synthetic_style;
+    }
+
+    /// Get the currently active synthetic style.
+    pub fn get_synthetic_style(&self) -> SyntheticStyle {
+        self.synthetic_style
+    }
+
+    /// Set the palette that should be used when drawing COLR glyphs.
+    ///
+    /// This allows choosing one of the palettes of a font, for example one that is
+    /// intended for dark backgrounds, and overriding the colors of single entries of
+    /// the palette. See [`ColorPalette`] for more information.
+    pub fn set_color_palette(&mut self, palette: ColorPalette) {
+        self.palette = self.sc.register_palette(palette);
+    }
+
+    /// Get the currently active palette for COLR glyphs.
+    pub fn get_color_palette(&self) -> ColorPalette {
+        self.sc.palette(self.palette).clone()
+    }
+
+    /// The text spacing that applies to the glyphs in the current writing mode.
+    fn glyph_spacing(&self) -> TextSpacing {
+        match self.writing_mode {
+            WritingMode::Horizontal => self.text_spacing,
+            WritingMode::Vertical => TextSpacing::default(),
+        }
+    }
+
     /// Draw a path using the currently active fill and/or stroke.
     pub fn draw_path(&mut self, path: &Path) {
+        let bounds = path.0.bounds();
+        self.record_tag_position(bounds.left(), bounds.top());
+
         if self.fill.is_some() || self.stroke.is_some() {
             if self.has_complex_fill_or_stroke() {
                 self.bd
@@ -159,8 +307,16 @@ impl<'a> Surface<'a> {
                 // the API of krilla that conflates artifacts with tagged content,
                 // for the sake of simplicity. But the user of the library does not need to know
                 // about this.
-                ContentTag::Artifact(at) => {
-                    if at.requires_properties() {
+                ContentTag::Artifact(at, bbox) => {
+                    if at.requires_properties() || bbox.is_some() {
+                        // The bbox is given in the coordinates of the page, so it needs
+                        // to be converted with the transform of the root builder.
+                        let root_transform = self.bd.root_builder.root_transform();
+                        let tag = ContentTag::Artifact(
+                            at,
+                            bbox.and_then(|bbox| bbox.transform(root_transform)),
+                        );
+
                         self.bd
                             .get_mut()
                             .start_marked_content_with_properties(self.sc, None, tag);
@@ -176,7 +332,10 @@ impl<'a> Surface<'a> {
                         Some(id.mcid),
                         tag,
                     );
-                    id.bump().into()
+                    let identifier = id.bump();
+                    self.pending_tag_position = Some(identifier);
+                    self.active_tag = Some(identifier);
+                    identifier.into()
                 }
             }
         } else {
@@ -205,44 +364,79 @@ impl<'a> Surface<'a> {
         self.bd.get_mut().end_marked_content();
     }
 
+    /// Start a section whose content is extracted as the given text, instead of the
+    /// text that the glyphs inside of it map to.
+    ///
+    /// This is useful if the text of a whole run of glyphs can't be expressed by mapping
+    /// each glyph to some codepoints, or if it should be extracted differently than it
+    /// is drawn. For overriding the text of individual glyphs, use
+    /// [`Glyph::text_override`] instead. In contrast to [`Surface::start_tagged`], the
+    /// section can also be started inside of a tagged section.
+    ///
+    /// # Panics
+    /// Panics if a section with an actual text has already been started.
+    pub fn start_actual_text(&mut self, text: &str) {
+        self.bd.get_mut().start_actual_text(text);
+    }
+
+    /// End the current section with an actual text.
+    ///
+    /// # Panics
+    /// Panics if no section with an actual text has been started.
+    pub fn end_actual_text(&mut self) {
+        self.bd.get_mut().end_actual_text();
+    }
+
     /// End the current tagged section.
     ///
     /// # Panics
     /// Panics if no tagged section has been started.
     pub fn end_tagged(&mut self) {
+        self.pending_tag_position = None;
+
         if self.page_identifier.is_some() {
-            self.bd.get_mut().end_marked_content();
+            let bbox = self.bd.get_mut().end_marked_content();
+
+            // Record the bbox of the content, so that it can be used to compute
+            // the bbox of tag groups.
+            if let (Some(identifier), Some(bbox)) = (self.active_tag.take(), bbox) {
+                if let Some(bbox) = bbox.transform(self.bd.parent_transform()) {
+                    self.sc.register_tag_bbox(identifier, bbox);
+                }
+            }
         }
     }
 
     fn outline_glyphs(
         &mut self,
         glyphs: &[impl Glyph],
-        context_color: rgb::Color,
+        colors: GlyphColors,
         start: Point,
         font: Font,
+        text: &str,
         font_size: f32,
     ) {
         let (mut cur_x, y) = (start.x, start.y);
+        let spacing = self.glyph_spacing();
 
         for glyph in glyphs {
             let mut base_transform = tiny_skia_path::Transform::from_translate(
-                cur_x + glyph.x_offset(font_size),
+                cur_x + glyph.x_offset(font_size) * spacing.horizontal_scaling,
                 y - glyph.y_offset(font_size),
             );
             base_transform = base_transform.pre_concat(tiny_skia_path::Transform::from_scale(
-                font_size / font.units_per_em(),
+                spacing.horizontal_scaling * font_size / font.units_per_em(),
                 -font_size / font.units_per_em(),
             ));
             draw_glyph(
                 font.clone(),
-                context_color,
+                colors,
                 glyph.glyph_id(),
                 Transform::from_tsp(base_transform),
                 self,
             );
 
-            cur_x += glyph.x_advance(font_size);
+            cur_x += spacing.x_advance(glyph, text, font_size);
         }
     }
 
@@ -251,6 +445,13 @@ impl<'a> Surface<'a> {
     /// This is a very low-level method, which gives you full control over how to place
     /// the glyphs that make up the text. This means that you must have your own text processing
     /// logic for dealing with bidirectional text, font fallback, text layouting, etc.
+    ///
+    /// If only a stroke is set, the outlines of the glyphs are stroked, and if both a
+    /// fill and a stroke are set, the glyphs are filled and stroked, each with their own
+    /// paint. To draw text that is invisible, use [`Surface::draw_invisible_glyphs`].
+    ///
+    /// The synthetic style set with [`Surface::set_synthetic_style`] is applied to the
+    /// glyphs as well.
     pub fn draw_glyphs(
         &mut self,
         start: Point,
@@ -260,9 +461,150 @@ impl<'a> Surface<'a> {
         font_size: f32,
         outlined: bool,
     ) {
-        let context_color = self.context_color();
+        self.report_notdef_glyphs(glyphs, &font);
+
+        let SyntheticStyle { bold, oblique } = self.synthetic_style;
+
+        if oblique != 0.0 {
+            // Skew the glyphs around the baseline, so that their tops lean to the right
+            // for positive angles.
+            let skew = oblique.to_radians().tan();
+            self.push_transform(&Transform::from_row(
+                1.0,
+                0.0,
+                -skew,
+                1.0,
+                skew * start.y,
+                0.0,
+            ));
+        }
+
+        if bold > 0.0 {
+            let old_fill = self.fill.clone();
+            let old_stroke = self.stroke.clone();
+            let width = bold * font_size;
+
+            // Embolden the glyphs by stroking them with the paint of the fill, or by
+            // widening the stroke if one is set.
+            self.stroke = Some(match old_stroke.clone() {
+                Some(stroke) => Stroke {
+                    width: stroke.width + width,
+                    ..stroke
+                },
+                None => {
+                    let fill = old_fill.clone().unwrap_or_default();
+                    self.fill = Some(fill.clone());
+
+                    Stroke {
+                        paint: fill.paint,
+                        width,
+                        line_join: LineJoin::Round,
+                        opacity: fill.opacity,
+                        ..Default::default()
+                    }
+                }
+            });
+
+            self.draw_glyphs_impl(start, glyphs, font, text, font_size, outlined);
+
+            self.fill = old_fill;
+            self.stroke = old_stroke;
+        } else {
+            self.draw_glyphs_impl(start, glyphs, font, text, font_size, outlined);
+        }
+
+        if oblique != 0.0 {
+            self.pop();
+        }
+    }
+
+    /// Draw a sequence of glyphs that belong to different fonts.
+    ///
+    /// This works the same as [`Surface::draw_glyphs`], except that each glyph is drawn
+    /// with the font in `fonts` at the index returned by [`Glyph::font_index`]. This is
+    /// useful for runs that contain glyphs from fallback fonts, since the glyphs don't
+    /// need to be split into separate runs manually. Each group of consecutive glyphs
+    /// using the same font is drawn at the position where the previous one ended, and
+    /// each font is embedded the same as if its glyphs were drawn separately.
+    ///
+    /// # Panics
+    /// Panics if the font index of a glyph is out of bounds.
+    pub fn draw_glyphs_with_fallback<G: Glyph>(
+        &mut self,
+        start: Point,
+        glyphs: &[G],
+        fonts: &[Font],
+        text: &str,
+        font_size: f32,
+        outlined: bool,
+    ) {
+        let spacing = self.glyph_spacing();
+        let mut position = start;
+        let mut rest = glyphs;
+
+        while let Some(first) = rest.first() {
+            let font_index = first.font_index();
+            let len = rest
+                .iter()
+                .position(|g| g.font_index() != font_index)
+                .unwrap_or(rest.len());
+            let (run, next) = rest.split_at(len);
+
+            let font = fonts.get(font_index).unwrap_or_else(|| {
+                panic!(
+                    "font index {} is out of bounds for {} fonts",
+                    font_index,
+                    fonts.len()
+                )
+            });
+
+            self.draw_glyphs(position, run, font.clone(), text, font_size, outlined);
+
+            for glyph in run {
+                match self.writing_mode {
+                    WritingMode::Horizontal => {
+                        position.x += spacing.x_advance(glyph, text, font_size);
+                    }
+                    WritingMode::Vertical => {
+                        position.x += glyph.x_advance(font_size);
+                        position.y -= glyph.y_advance(font_size);
+                    }
+                }
+            }
+
+            rest = next;
+        }
+    }
+
+    /// Report all `.notdef` glyphs of a glyph run as warnings.
+    fn report_notdef_glyphs(&mut self, glyphs: &[impl Glyph], font: &Font) {
+        for glyph in glyphs {
+            if glyph.glyph_id() == GlyphId::new(0) {
+                self.sc.register_warning(KrillaWarning::NotDefGlyph(
+                    font.clone(),
+                    glyph.text_range(),
+                    self.sc.current_page,
+                    glyph.location(),
+                ));
+            }
+        }
+    }
+
+    fn draw_glyphs_impl(
+        &mut self,
+        start: Point,
+        glyphs: &[impl Glyph],
+        font: Font,
+        text: &str,
+        font_size: f32,
+        outlined: bool,
+    ) {
+        self.record_tag_position(start.x, start.y - font_size);
+
+        let colors = self.glyph_colors();
+        let spacing = self.glyph_spacing();
         if outlined {
-            self.outline_glyphs(glyphs, context_color, start, font, font_size);
+            self.outline_glyphs(glyphs, colors, start, font, text, font_size);
         } else {
             match (self.fill.as_ref(), self.stroke.as_ref()) {
                 (Some(f), Some(s)) => {
@@ -274,25 +616,29 @@ impl<'a> Surface<'a> {
                             self.sc,
                             Some(f),
                             None,
-                            context_color,
+                            colors,
                             glyphs,
                             font.clone(),
                             text,
                             font_size,
+                            spacing,
+                            self.writing_mode,
                         );
 
-                        self.outline_glyphs(glyphs, context_color, start, font, font_size);
+                        self.outline_glyphs(glyphs, colors, start, font, text, font_size);
                     } else {
                         self.bd.get_mut().draw_glyphs(
                             start,
                             self.sc,
                             Some(f),
                             Some(s),
-                            context_color,
+                            colors,
                             glyphs,
                             font,
                             text,
                             font_size,
+                            spacing,
+                            self.writing_mode,
                         );
                     }
                 }
@@ -302,11 +648,13 @@ impl<'a> Surface<'a> {
                         self.sc,
                         None,
                         Some(s),
-                        context_color,
+                        colors,
                         glyphs,
                         font,
                         text,
                         font_size,
+                        spacing,
+                        self.writing_mode,
                     );
                 }
                 (Some(f), None) => {
@@ -315,11 +663,13 @@ impl<'a> Surface<'a> {
                         self.sc,
                         Some(f),
                         None,
-                        context_color,
+                        colors,
                         glyphs,
                         font,
                         text,
                         font_size,
+                        spacing,
+                        self.writing_mode,
                     );
                 }
                 (None, None) => {
@@ -329,11 +679,13 @@ impl<'a> Surface<'a> {
                         self.sc,
                         Some(&Fill::default()),
                         None,
-                        context_color,
+                        colors,
                         glyphs,
                         font,
                         text,
                         font_size,
+                        spacing,
+                        self.writing_mode,
                     );
                 }
             }
@@ -341,18 +693,59 @@ impl<'a> Surface<'a> {
         }
     }
 
+    /// Draw a sequence of glyphs that are invisible, but can still be selected and
+    /// extracted.
+    ///
+    /// This works the same as [`Surface::draw_glyphs`], except that the glyphs are
+    /// neither filled nor stroked. This is useful for placing a text layer on top of
+    /// a scanned image, so that the recognized text can be searched and copied.
+    /// In contrast to [`Surface::push_glyph_clip`], the text is not marked as an
+    /// artifact, so it is part of the tag tree like any other text.
+    pub fn draw_invisible_glyphs(
+        &mut self,
+        start: Point,
+        glyphs: &[impl Glyph],
+        font: Font,
+        text: &str,
+        font_size: f32,
+    ) {
+        self.report_notdef_glyphs(glyphs, &font);
+        self.record_tag_position(start.x, start.y - font_size);
+
+        let colors = self.glyph_colors();
+        let spacing = self.glyph_spacing();
+        self.bd.get_mut().draw_invisible_glyphs(
+            start,
+            self.sc,
+            colors,
+            glyphs,
+            font,
+            text,
+            font_size,
+            spacing,
+            self.writing_mode,
+        );
+    }
+
     /// Draw some text using the currently active fill and/or stroke.
     ///
     /// This is a high-level method which allows you to just provide some text, which will
-    /// then be rendered into a single line. However, this approach has restrictions:
+    /// then be shaped with the given font and rendered into a single line. The advances
+    /// and offsets of the glyphs are taken from the shaping result, and each glyph is
+    /// mapped to the cluster it belongs to, so that the text can be extracted properly.
+    ///
+    /// It is meant for simple use cases like labels, captions or tests, and is _not_ a
+    /// layout engine. This means that it has restrictions:
     ///
     /// - It will not perform BIDI resolution and only supports a single script, meaning that you
     ///   must ensure that your text does not contain multiple scripts.
     /// - It will only use the single font you provided to draw the text, no font fallback will
     ///   be performed.
+    /// - It will not perform any line breaking, so the text is always drawn in a single line.
     ///
-    /// If you need more advanced control over how your text looks,
-    /// you can use the `fill_glyphs` method.
+    /// If you need more advanced control over how your text looks, you should use a text
+    /// layout library like `parley` or `cosmic-text` and draw the resulting glyphs with
+    /// [`Surface::draw_glyphs`].
     #[cfg(feature = "simple-text")]
     pub fn draw_text(
         &mut self,
@@ -379,8 +772,14 @@ impl<'a> Surface<'a> {
     }
 
     /// Return the current transformation matrix.
+    ///
+    /// This is the accumulated transform of all active `push_transform` calls, including
+    /// the ones that were pushed before a mask, opacity or transparency group. It maps
+    /// from the current user space to the coordinate system of the surface, which for
+    /// pages is the same one that is used for placing annotations. Use
+    /// [`Transform::invert`] to map a point on the page back to the current user space.
     pub fn ctm(&self) -> Transform {
-        self.bd.get().cur_transform()
+        self.bd.ctm()
     }
 
     /// Concatenate a new transform to the current transformation matrix.
@@ -397,19 +796,94 @@ impl<'a> Surface<'a> {
         self.bd.get_mut().set_blend_mode(blend_mode.to_pdf());
     }
 
+    /// Push a new rendering intent, which determines how colors that can't be
+    /// reproduced directly by the output device are mapped.
+    ///
+    /// If no rendering intent is pushed, the default of the PDF viewer is used.
+    pub fn push_rendering_intent(&mut self, rendering_intent: RenderingIntent) {
+        self.push_instructions
+            .push(PushInstruction::RenderingIntent);
+        self.bd.get_mut().save_graphics_state();
+        self.bd.get_mut().set_rendering_intent(rendering_intent);
+    }
+
+    /// Push new overprint settings, which determine whether subsequent fills and strokes
+    /// overprint the existing content instead of knocking it out when printing with
+    /// separate colorants.
+    ///
+    /// Note that PDF/A forbids the nonzero overprint mode for overprinted CMYK colors
+    /// if a CMYK profile was provided.
+    pub fn push_overprint(&mut self, fill: bool, stroke: bool, mode: OverprintMode) {
+        self.push_instructions.push(PushInstruction::Overprint);
+        self.bd.get_mut().save_graphics_state();
+        self.bd.get_mut().set_overprint(fill, stroke, mode);
+    }
+
+    /// Enable or disable black point compensation for subsequent drawing operations.
+    ///
+    /// Note that black point compensation is only available in PDF 2.0, for older
+    /// PDF versions this doesn't have any effect.
+    pub fn push_black_point_compensation(&mut self, black_point_compensation: bool) {
+        self.push_instructions
+            .push(PushInstruction::BlackPointCompensation);
+        self.bd.get_mut().save_graphics_state();
+        self.bd
+            .get_mut()
+            .set_black_point_compensation(black_point_compensation);
+    }
+
     /// Push a new clip path.
     pub fn push_clip_path(&mut self, path: &Path, clip_rule: &FillRule) {
         self.push_instructions.push(PushInstruction::ClipPath);
         self.bd.get_mut().push_clip_path(&path.0, clip_rule);
     }
 
+    /// Push a new clip path that consists of the outlines of the given glyphs.
+    ///
+    /// All subsequent drawing operations are clipped to the glyphs, until the
+    /// corresponding `pop`. The glyphs are placed in the same way as in
+    /// [`Surface::draw_glyphs`], but they are not painted themselves. Color glyphs
+    /// that are not based on outlines are not supported for clipping.
+    ///
+    /// Since the text used for clipping is never visible, it is marked as an artifact
+    /// on tagged pages, unless a tag has already been started. If the text should
+    /// still be accessible, you can start a [`ContentTag::Span`] with an actual text
+    /// using [`Surface::start_tagged`] before pushing the clip.
+    pub fn push_glyph_clip(
+        &mut self,
+        start: Point,
+        glyphs: &[impl Glyph],
+        font: Font,
+        text: &str,
+        font_size: f32,
+    ) {
+        self.report_notdef_glyphs(glyphs, &font);
+
+        // The content of clipping text can't be part of the tag tree.
+        let mark_artifact = self.page_identifier.is_some() && !self.bd.get().active_marked_content;
+        let colors = self.glyph_colors();
+        let spacing = self.glyph_spacing();
+
+        self.push_instructions.push(PushInstruction::ClipPath);
+        self.bd.get_mut().push_glyph_clip(
+            start,
+            self.sc,
+            colors,
+            glyphs,
+            font,
+            text,
+            font_size,
+            spacing,
+            self.writing_mode,
+            mark_artifact,
+        );
+    }
+
     /// Push a new mask.
     pub fn push_mask(&mut self, mask: Mask) {
         self.push_instructions
             .push(PushInstruction::Mask(Box::new(mask)));
-        self.bd
-            .sub_builders
-            .push(ContentBuilder::new(Transform::identity(), true));
+        self.bd.push_sub_builder();
     }
 
     /// Push a new opacity, meaning that each subsequent graphics object will be
@@ -417,23 +891,47 @@ impl<'a> Surface<'a> {
     ///
     /// This stacks, meaning that if you do `push_opacity(0.5)` twice, the resulting
     /// base opacity will be 0.25.
+    ///
+    /// In contrast to the opacity of fills and strokes, the opacity is applied to the
+    /// enclosed content as a whole, so overlapping objects don't darken each other.
+    /// This is achieved by wrapping the content in a transparency group.
     pub fn push_opacity(&mut self, opacity: NormalizedF32) {
         self.push_instructions
             .push(PushInstruction::Opacity(opacity));
 
         if opacity != NormalizedF32::ONE {
-            self.bd
-                .sub_builders
-                .push(ContentBuilder::new(Transform::identity(), true));
+            self.bd.push_sub_builder();
         }
     }
 
     /// Push a new isolated layer.
     pub fn push_isolated(&mut self) {
         self.push_instructions.push(PushInstruction::Isolated);
-        self.bd
-            .sub_builders
-            .push(ContentBuilder::new(Transform::identity(), true));
+        self.bd.push_sub_builder();
+    }
+
+    /// Push a new transparency group.
+    ///
+    /// Everything drawn until the corresponding `pop` is composited into the
+    /// group first, which is then drawn as a whole. If `isolated` is true, the
+    /// group is composited against a fully transparent backdrop instead of the
+    /// content below it. If `knockout` is true, each object in the group is
+    /// composited with the backdrop of the group instead of the objects that
+    /// were drawn before it in the group. The color space determines in which
+    /// color space the contents of the group are blended.
+    pub fn push_transparency_group(
+        &mut self,
+        isolated: bool,
+        knockout: bool,
+        color_space: GroupColorSpace,
+    ) {
+        self.push_instructions
+            .push(PushInstruction::TransparencyGroup(
+                isolated,
+                knockout,
+                color_space,
+            ));
+        self.bd.push_sub_builder();
     }
 
     /// Pop the last `push` instruction.
@@ -451,6 +949,9 @@ impl<'a> Surface<'a> {
             }
             PushInstruction::ClipPath => self.bd.get_mut().pop_clip_path(),
             PushInstruction::BlendMode => self.bd.get_mut().restore_graphics_state(),
+            PushInstruction::RenderingIntent => self.bd.get_mut().restore_graphics_state(),
+            PushInstruction::Overprint => self.bd.get_mut().restore_graphics_state(),
+            PushInstruction::BlackPointCompensation => self.bd.get_mut().restore_graphics_state(),
             PushInstruction::Mask(mask) => {
                 let stream = self.bd.sub_builders.pop().unwrap().finish(self.sc);
                 self.bd.get_mut().draw_masked(self.sc, *mask, stream)
@@ -459,13 +960,53 @@ impl<'a> Surface<'a> {
                 let stream = self.bd.sub_builders.pop().unwrap().finish(self.sc);
                 self.bd.get_mut().draw_isolated(self.sc, stream);
             }
+            PushInstruction::TransparencyGroup(isolated, knockout, color_space) => {
+                let stream = self.bd.sub_builders.pop().unwrap().finish(self.sc);
+                self.bd.get_mut().draw_transparency_group(
+                    self.sc,
+                    isolated,
+                    knockout,
+                    color_space,
+                    stream,
+                );
+            }
         }
     }
 
     #[cfg(feature = "raster-images")]
     /// Draw a new bitmap image.
+    ///
+    /// Stencil masks created with [`Image::stencil`] are painted with the currently
+    /// active fill.
     pub fn draw_image(&mut self, image: Image, size: Size) {
-        self.bd.get_mut().draw_image(image, size, self.sc);
+        self.record_tag_position(0.0, 0.0);
+        self.bd
+            .get_mut()
+            .draw_image(image, size, self.fill.as_ref(), self.sc);
+    }
+
+    #[cfg(feature = "raster-images")]
+    /// Draw a new bitmap image at the given point, with a size that is based on the
+    /// resolution of the image in dots per inch.
+    ///
+    /// One inch corresponds to 72 units, so an image with a resolution of 72 DPI
+    /// is drawn with one unit per pixel, while an image with a resolution of 144 DPI is
+    /// drawn at half that size. Nothing is drawn if the resolution is not positive.
+    pub fn draw_image_dpi(&mut self, image: Image, point: Point, dpi: f32) {
+        if !dpi.is_finite() || dpi <= 0.0 {
+            return;
+        }
+
+        let (width, height) = image.size();
+        let scale = 72.0 / dpi;
+
+        let Some(size) = Size::from_wh(width as f32 * scale, height as f32 * scale) else {
+            return;
+        };
+
+        self.push_transform(&Transform::from_translate(point.x, point.y));
+        self.draw_image(image, size);
+        self.pop();
     }
 
     /// Draw a new graphic.
@@ -478,6 +1019,33 @@ impl<'a> Surface<'a> {
             .draw_xobject(self.sc, graphic.x_object, &ExtGState::new())
     }
 
+    /// Draw a template with the given transform.
+    ///
+    /// The template is only written once to the PDF, no matter how often it is drawn.
+    /// On tagged pages, the template is marked as an artifact, unless a tag has already
+    /// been started.
+    pub fn draw_template(&mut self, template: &Template, transform: Transform) {
+        // The content of templates can't be part of the tag tree.
+        let mark_artifact = self.page_identifier.is_some() && !self.bd.get().active_marked_content;
+
+        if mark_artifact {
+            self.bd
+                .get_mut()
+                .start_marked_content(ContentTag::Artifact(ArtifactType::Other, None).name());
+        }
+
+        self.push_transform(&transform);
+        self.record_tag_position(0.0, 0.0);
+        self.bd
+            .get_mut()
+            .draw_xobject(self.sc, template.x_object.clone(), &ExtGState::new());
+        self.pop();
+
+        if mark_artifact {
+            self.bd.get_mut().end_marked_content();
+        }
+    }
+
     pub(crate) fn draw_shading(&mut self, shading: &ShadingFunction) {
         self.bd.get_mut().draw_shading(shading, self.sc);
     }
@@ -497,6 +1065,23 @@ impl<'a> Surface<'a> {
         self.bd.get().cur_transform()
     }
 
+    /// Record the position of the first content drawn in the current tagged section,
+    /// so that it can be used as a destination when generating an outline from
+    /// the tag tree.
+    fn record_tag_position(&mut self, x: f32, y: f32) {
+        if let Some(identifier) = self.pending_tag_position.take() {
+            let point = self.ctm().map_point(Point::from_xy(x, y));
+            self.sc.register_tag_position(identifier, point);
+        }
+    }
+
+    fn glyph_colors(&self) -> GlyphColors {
+        GlyphColors {
+            context_color: self.context_color(),
+            palette: self.palette,
+        }
+    }
+
     fn context_color(&self) -> rgb::Color {
         self.fill
             .as_ref()
@@ -516,6 +1101,8 @@ impl<'a> Surface<'a> {
 
         let check_paint = |paint: &Paint| match &paint.0 {
             InnerPaint::Color(_) => false,
+            InnerPaint::Spot(_) => false,
+            InnerPaint::DeviceN(_) => false,
             InnerPaint::LinearGradient(l) => {
                 l.stops.iter().any(|s| s.opacity != NormalizedF32::ONE)
             }
@@ -523,6 +1110,10 @@ impl<'a> Surface<'a> {
                 r.stops.iter().any(|s| s.opacity != NormalizedF32::ONE)
             }
             InnerPaint::SweepGradient(r) => r.stops.iter().any(|s| s.opacity != NormalizedF32::ONE),
+            InnerPaint::MeshGradient(m) => m
+                .patches
+                .iter()
+                .any(|p| p.opacities.iter().any(|o| *o != NormalizedF32::ONE)),
             InnerPaint::Pattern(_) => false,
         };
 
@@ -572,6 +1163,13 @@ impl Builders {
         }
     }
 
+    fn push_sub_builder(&mut self) {
+        let mut sub_builder = ContentBuilder::new(Transform::identity(), true);
+        // Sub-streams of an uncolored tiling pattern mustn't specify any colors either.
+        sub_builder.uncolored = self.root_builder.uncolored;
+        self.sub_builders.push(sub_builder);
+    }
+
     fn get_mut(&mut self) -> &mut ContentBuilder {
         self.sub_builders
             .last_mut()
@@ -581,6 +1179,28 @@ impl Builders {
     fn get(&self) -> &ContentBuilder {
         self.sub_builders.last().unwrap_or(&self.root_builder)
     }
+
+    /// The transform under which the parent builders will draw the current builder.
+    fn parent_transform(&self) -> Transform {
+        match self.sub_builders.split_last() {
+            Some((_, parents)) => parents
+                .iter()
+                .fold(self.root_builder.cur_transform(), |ctm, sb| {
+                    ctm.pre_concat(sb.cur_transform())
+                }),
+            None => Transform::identity(),
+        }
+    }
+
+    /// The transform of the current builder, including the transforms under
+    /// which its parent builders will draw it.
+    fn ctm(&self) -> Transform {
+        self.sub_builders
+            .iter()
+            .fold(self.root_builder.cur_transform(), |ctm, sb| {
+                ctm.pre_concat(sb.cur_transform())
+            })
+    }
 }
 
 pub(crate) enum PushInstruction {
@@ -588,6 +1208,10 @@ pub(crate) enum PushInstruction {
     Opacity(NormalizedF32),
     ClipPath,
     BlendMode,
+    RenderingIntent,
+    Overprint,
+    BlackPointCompensation,
     Mask(Box<Mask>),
     Isolated,
+    TransparencyGroup(bool, bool, GroupColorSpace),
 }
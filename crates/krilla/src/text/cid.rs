@@ -9,19 +9,23 @@ use skrifa::raw::tables::cff::Cff;
 use skrifa::raw::{TableProvider, TopLevelTable};
 use subsetter::GlyphRemapper;
 
-use super::{CIDIdentifier, FontIdentifier, PDF_UNITS_PER_EM};
+use super::{CIDIdentifier, FontIdentifier, WritingMode, PDF_UNITS_PER_EM};
 use crate::configure::ValidationError;
-use crate::error::{KrillaError, KrillaResult};
+use crate::encryption::encrypt_bytes;
+use crate::error::{KrillaError, KrillaResult, KrillaWarning};
 use crate::geom::Rect;
 use crate::serialize::SerializeContext;
 use crate::stream::FilterStreamBuilder;
 use crate::surface::Location;
+use crate::text::glyph::outline::can_load_outline;
+use crate::text::instance::{instance_glyf, write_font};
 use crate::text::Font;
 use crate::text::GlyphId;
 use crate::util::{hash128, SliceExt};
 
 const SUBSET_TAG_LEN: usize = 6;
 pub(crate) const IDENTITY_H: &str = "Identity-H";
+pub(crate) const IDENTITY_V: &str = "Identity-V";
 pub(crate) const CMAP_NAME: Name = Name(b"Custom");
 pub(crate) const SYSTEM_INFO: SystemInfo = SystemInfo {
     registry: Str(b"Adobe"),
@@ -31,6 +35,18 @@ pub(crate) const SYSTEM_INFO: SystemInfo = SystemInfo {
 
 pub(crate) type Cid = u16;
 
+/// Return the vertical advance and the y coordinate of the vertical origin of a glyph,
+/// in font units. If the font doesn't contain vertical metrics, the glyph is assumed to
+/// span from the ascender to the descender.
+pub(crate) fn vertical_metrics(font: &Font, glyph_id: GlyphId) -> (f32, f32) {
+    let advance = font
+        .advance_height(glyph_id)
+        .unwrap_or(font.ascent() - font.descent());
+    let origin = font.vertical_origin(glyph_id).unwrap_or(font.ascent());
+
+    (advance, origin)
+}
+
 /// A shared function for CID fonts and Type3 fonts to write the cmap entries.
 pub(crate) fn write_cmap_entry<G>(
     font: &Font,
@@ -103,23 +119,40 @@ pub(crate) struct CIDFont {
     glyph_remapper: GlyphRemapper,
     /// A mapping from CIDs to their string in the original text.
     cmap_entries: FxHashMap<u16, (String, Option<Location>)>,
+    /// The glyph IDs in the original font, _indexed by their CID_.
+    glyph_ids: Vec<GlyphId>,
     /// The widths of the glyphs, _indexed by their CID_.
     widths: Vec<f32>,
+    /// The vertical advances and origins of the glyphs, _indexed by their CID_. Only
+    /// used in the vertical writing mode.
+    vertical_metrics: Vec<(f32, f32)>,
+    writing_mode: WritingMode,
+    /// Whether the font should be subsetted. Otherwise, the whole font is embedded
+    /// and the CIDs are the same as the glyph IDs.
+    subset: bool,
     is_empty: bool,
 }
 
 impl CIDFont {
     /// Create a new CID-keyed font.
-    pub(crate) fn new(font: Font) -> CIDFont {
+    pub(crate) fn new(font: Font, writing_mode: WritingMode, subset: bool) -> CIDFont {
         // Always include the .notdef glyph. Will also always be included by the subsetter in
         // the glyph remapper.
         let widths = vec![font.advance_width(GlyphId::new(0)).unwrap_or(0.0)];
+        let vertical_metrics = match writing_mode {
+            WritingMode::Horizontal => vec![],
+            WritingMode::Vertical => vec![vertical_metrics(&font, GlyphId::new(0))],
+        };
 
         Self {
             glyph_remapper: GlyphRemapper::new(),
             cmap_entries: FxHashMap::default(),
+            glyph_ids: vec![GlyphId::new(0)],
             widths,
+            vertical_metrics,
+            writing_mode,
             font,
+            subset,
             is_empty: true,
         }
     }
@@ -140,7 +173,13 @@ impl CIDFont {
 
     #[inline]
     pub(crate) fn get_cid(&self, glyph_id: GlyphId) -> Option<u16> {
-        self.glyph_remapper.get(glyph_id.to_u32() as u16)
+        let cid = self.glyph_remapper.get(glyph_id.to_u32() as u16);
+
+        if self.subset {
+            cid
+        } else {
+            cid.map(|_| glyph_id.to_u32() as u16)
+        }
     }
 
     /// Add a new glyph (if it has not already been added) and return its CID.
@@ -148,19 +187,67 @@ impl CIDFont {
     pub(crate) fn add_glyph(&mut self, glyph_id: GlyphId) -> Cid {
         self.is_empty = false;
 
+        if !self.subset {
+            return self.add_glyph_without_subset(glyph_id);
+        }
+
         let new_id = self
             .glyph_remapper
             .remap(u16::try_from(glyph_id.to_u32()).unwrap());
 
         // This means that the glyph ID has been newly assigned, and thus we need to add its width.
         if new_id as usize >= self.widths.len() {
+            self.glyph_ids.push(glyph_id);
             self.widths
                 .push(self.font.advance_width(glyph_id).unwrap_or(0.0));
+
+            if self.writing_mode == WritingMode::Vertical {
+                self.vertical_metrics
+                    .push(vertical_metrics(&self.font, glyph_id));
+            }
         }
 
         new_id
     }
 
+    fn add_glyph_without_subset(&mut self, glyph_id: GlyphId) -> Cid {
+        let cid = u16::try_from(glyph_id.to_u32()).unwrap();
+        let index = cid as usize;
+        // The glyph remapper is only used for keeping track of the used glyphs.
+        self.glyph_remapper.remap(cid);
+
+        // Glyphs that aren't used keep a width of zero and the default vertical metrics,
+        // so that they are omitted when writing the metrics.
+        if index >= self.widths.len() {
+            self.widths.resize(index + 1, 0.0);
+
+            if self.writing_mode == WritingMode::Vertical {
+                let default = (self.font.ascent() - self.font.descent(), self.font.ascent());
+                self.vertical_metrics.resize(index + 1, default);
+            }
+        }
+
+        self.widths[index] = self.font.advance_width(glyph_id).unwrap_or(0.0);
+
+        if self.writing_mode == WritingMode::Vertical {
+            self.vertical_metrics[index] = vertical_metrics(&self.font, glyph_id);
+        }
+
+        cid
+    }
+
+    /// The glyph IDs of all glyphs that have been added, excluding the `.notdef` glyph.
+    fn used_glyphs(&self) -> Vec<GlyphId> {
+        if self.subset {
+            self.glyph_ids[1..].to_vec()
+        } else {
+            (1..self.widths.len() as u32)
+                .map(GlyphId::new)
+                .filter(|g| self.glyph_remapper.get(g.to_u32() as u16).is_some())
+                .collect()
+        }
+    }
+
     #[inline]
     pub(crate) fn get_codepoints(&self, cid: Cid) -> Option<&str> {
         self.cmap_entries.get(&cid).map(|s| s.0.as_str())
@@ -173,7 +260,7 @@ impl CIDFont {
 
     #[inline]
     pub(crate) fn identifier(&self) -> FontIdentifier {
-        FontIdentifier::Cid(CIDIdentifier(self.font.clone()))
+        FontIdentifier::Cid(CIDIdentifier(self.font.clone(), self.writing_mode))
     }
 
     pub(crate) fn serialize(
@@ -210,11 +297,36 @@ impl CIDFont {
             };
         }
 
-        let subsetted = subset_font(self.font.clone(), glyph_remapper)?;
+        for glyph_id in self.used_glyphs() {
+            if !can_load_outline(&self.font, glyph_id) {
+                sc.register_warning(KrillaWarning::MissingGlyphOutline(
+                    self.font.clone(),
+                    glyph_id,
+                ));
+            }
+        }
+
+        let (mut font_program, glyph_ids) = if self.subset {
+            (
+                subset_font(self.font.clone(), glyph_remapper)?,
+                self.glyph_ids.clone(),
+            )
+        } else {
+            let num_glyphs = self.font.font_ref().maxp().map_or(0, |m| m.num_glyphs());
+            (
+                full_font(self.font.clone())?,
+                (0..num_glyphs as u32).map(GlyphId::new).collect(),
+            )
+        };
+
+        // The font program always contains the default instance of variable fonts.
+        if is_glyf && !self.font.variations().is_empty() {
+            font_program = instance_glyf(&self.font, &font_program, &glyph_ids)?;
+        }
         let num_glyphs;
 
         let font_stream = {
-            let mut data = subsetted.as_slice();
+            let mut data = font_program.as_slice();
 
             // If we have a CFF font, only embed the standalone CFF program.
             let subsetted_ref = skrifa::FontRef::new(data).map_err(|_| {
@@ -229,12 +341,18 @@ impl CIDFont {
                 data = cff.as_bytes();
             }
 
-            FilterStreamBuilder::new_from_binary_data(data).finish(&sc.serialize_settings())
+            FilterStreamBuilder::new_from_binary_data(data)
+                .finish(&sc.serialize_settings(), sc.encryptor())
+        };
+
+        let (encoding, wmode) = match self.writing_mode {
+            WritingMode::Horizontal => (IDENTITY_H, WMode::Horizontal),
+            WritingMode::Vertical => (IDENTITY_V, WMode::Vertical),
         };
 
-        let base_font = base_font_name(&self.font, &self.glyph_remapper);
+        let base_font = base_font_name(&self.font, &self.glyph_remapper, self.subset);
         let base_font_type0 = if is_cff {
-            format!("{base_font}-{}", IDENTITY_H)
+            format!("{base_font}-{}", encoding)
         } else {
             base_font.clone()
         };
@@ -242,7 +360,7 @@ impl CIDFont {
         chunk
             .type0_font(root_ref)
             .base_font(Name(base_font_type0.as_bytes()))
-            .encoding_predefined(Name(IDENTITY_H.as_bytes()))
+            .encoding_predefined(Name(encoding.as_bytes()))
             .descendant_font(cid_ref)
             .to_unicode(cmap_ref);
 
@@ -253,7 +371,12 @@ impl CIDFont {
             CidFontType::Type2
         });
         cid.base_font(Name(base_font.as_bytes()));
-        cid.system_info(SYSTEM_INFO);
+        // Write the system info manually, so that its strings can be encrypted.
+        cid.insert(Name(b"CIDSystemInfo"))
+            .dict()
+            .pair(Name(b"Registry"), sc.str(SYSTEM_INFO.registry.0))
+            .pair(Name(b"Ordering"), sc.str(SYSTEM_INFO.ordering.0))
+            .pair(Name(b"Supplement"), SYSTEM_INFO.supplement);
         cid.font_descriptor(descriptor_ref);
         cid.default_width(0.0);
 
@@ -276,6 +399,43 @@ impl CIDFont {
         }
 
         width_writer.finish();
+
+        if self.writing_mode == WritingMode::Vertical {
+            let (default_advance, default_origin) =
+                (self.font.ascent() - self.font.descent(), self.font.ascent());
+
+            cid.insert(Name(b"DW2"))
+                .array()
+                .items([to_pdf_units(default_origin), -to_pdf_units(default_advance)]);
+
+            // The horizontal component of the vertical origin is always half of the
+            // width of the glyph, same as the shapers assume.
+            let metrics = self
+                .widths
+                .iter()
+                .zip(&self.vertical_metrics)
+                .map(|(w, (advance, origin))| (*w / 2.0, *advance, *origin))
+                .collect::<Vec<_>>();
+
+            let mut first = 0;
+            let mut vertical_writer = cid.insert(Name(b"W2")).array();
+            for ((vx, advance, origin), group) in metrics.group_by_key(|&m| m) {
+                let end = first + group.len();
+                if advance != default_advance || origin != default_origin {
+                    let last = end - 1;
+                    vertical_writer.items([first as i32, last as i32]);
+                    vertical_writer.items([
+                        -to_pdf_units(advance),
+                        to_pdf_units(vx),
+                        to_pdf_units(origin),
+                    ]);
+                }
+                first = end;
+            }
+
+            vertical_writer.finish();
+        }
+
         cid.finish();
 
         // The only reason we write this in the first place is that PDF/A1-b requires
@@ -295,7 +455,7 @@ impl CIDFont {
             };
 
             let cid_stream = FilterStreamBuilder::new_from_binary_data(&cid_stream_data)
-                .finish(&sc.serialize_settings());
+                .finish(&sc.serialize_settings(), sc.encryptor());
             let mut cid_set = chunk.stream(cid_set_ref, cid_stream.encoded_data());
             cid_stream.write_filters(cid_set.deref_mut());
             cid_set.finish();
@@ -360,7 +520,13 @@ impl CIDFont {
 
             // For the .notdef glyph, it's fine if no mapping exists, since it is included
             // even if it was not referenced in the text.
-            for g in 1..self.glyph_remapper.num_gids() {
+            for g in 1..self.widths.len() {
+                let g = g as u16;
+
+                if !self.subset && self.glyph_remapper.get(g).is_none() {
+                    continue;
+                }
+
                 let entry = self.cmap_entries.get(&g);
                 write_cmap_entry(&self.font, entry, sc, &mut cmap, g);
             }
@@ -369,8 +535,9 @@ impl CIDFont {
         };
 
         let cmap_stream = cmap.finish();
+        let cmap_stream = encrypt_bytes(&cmap_stream, sc.encryptor());
         let mut cmap = chunk.cmap(cmap_ref, &cmap_stream);
-        cmap.writing_mode(WMode::Horizontal);
+        cmap.writing_mode(wmode);
         cmap.finish();
 
         let mut stream = chunk.stream(data_ref, font_stream.encoded_data());
@@ -397,13 +564,25 @@ pub(crate) fn subset_tag<T: Hash>(data: &T) -> String {
     std::str::from_utf8(&letter).unwrap().to_string()
 }
 
-pub(crate) fn base_font_name<T: Hash>(font: &Font, data: &T) -> String {
+pub(crate) fn base_font_name<T: Hash>(font: &Font, data: &T, subset: bool) -> String {
     const REST_LEN: usize = SUBSET_TAG_LEN + 1 + 1 + IDENTITY_H.len();
 
     let postscript_name = font.postscript_name().unwrap_or("unknown");
     let max_len = 127 - REST_LEN;
     let trimmed = &postscript_name[..postscript_name.len().min(max_len)];
-    let subset_tag = subset_tag(&data);
+
+    // Fonts that are embedded as a whole don't get a subset tag, unless they
+    // are an instance of a variable font.
+    if !subset && font.variations().is_empty() {
+        return trimmed.to_string();
+    }
+
+    // Different instances of the same variable font need to have different names.
+    let subset_tag = if font.variations().is_empty() {
+        subset_tag(&data)
+    } else {
+        subset_tag(&(data, font.variations()))
+    };
 
     format!("{subset_tag}+{trimmed}")
 }
@@ -414,3 +593,44 @@ fn subset_font(font: Font, glyph_remapper: &GlyphRemapper) -> KrillaResult<Vec<u
     subsetter::subset(font_data.as_ref(), font.index(), glyph_remapper)
         .map_err(|e| KrillaError::Font(font.clone(), format!("failed to subset font: {}", e)))
 }
+
+/// Return the font program of the whole font.
+fn full_font(font: Font) -> KrillaResult<Vec<u8>> {
+    let font_ref = font.font_ref();
+    let err = || KrillaError::Font(font.clone(), "failed to read font".to_string());
+
+    if font_ref.cff().is_ok() {
+        // The CIDs of CID-keyed CFF fonts don't necessarily correspond to their glyph IDs,
+        // so CFF fonts are still passed through the subsetter, but with all of their glyphs,
+        // which ensures that the CIDs are the same as the glyph IDs.
+        let num_glyphs = font_ref.maxp().map_err(|_| err())?.num_glyphs();
+        let mut glyph_remapper = GlyphRemapper::new();
+
+        for glyph_id in 0..num_glyphs {
+            glyph_remapper.remap(glyph_id);
+        }
+
+        return subset_font(font, &glyph_remapper);
+    }
+
+    let data = font.font_data();
+
+    if data.as_ref().starts_with(b"ttcf") {
+        // Font collections can't be embedded, so the font is extracted from the
+        // collection with its tables as they are.
+        let tables = font_ref
+            .table_directory
+            .table_records()
+            .iter()
+            .map(|record| {
+                let table = font_ref.table_data(record.tag())?;
+                Some((record.tag(), table.as_bytes().to_vec()))
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(err)?;
+
+        return Ok(write_font(tables));
+    }
+
+    Ok(data.as_ref().to_vec())
+}
@@ -3,14 +3,15 @@ use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 
 use skrifa::instance::{Location, LocationRef, Size};
-use skrifa::metrics::GlyphMetrics;
-use skrifa::raw::types::NameId;
+use skrifa::metrics::GlyphMetrics as SkrifaGlyphMetrics;
+use skrifa::raw::types::{NameId, Tag};
 use skrifa::raw::TableProvider;
 use skrifa::{FontRef, MetadataProvider};
 use tiny_skia_path::FiniteF32;
 use yoke::{Yoke, Yokeable};
 
-use crate::geom::Rect;
+use crate::geom::{Path, Rect};
+use crate::text::glyph::outline::glyph_path;
 use crate::text::GlyphId;
 use crate::util::Prehashed;
 use crate::Data;
@@ -28,21 +29,58 @@ impl Font {
     ///
     /// The `index` indicates the index that should be
     /// associated with this font for TrueType collections, otherwise this value should be
-    /// set to 0. The location indicates the variation axes that should be associated with
-    /// the font.
-    ///
-    /// The `allow_color` property allows you to specify whether krilla should render the font
-    /// as a color font. When setting this property to false, krilla will always only use the
-    /// `glyf`/`CFF` tables of the font. If you don't know what this means, just set it to `true`.
+    /// set to 0. Variable fonts are embedded with their default instance, use
+    /// [`Font::new_with_variations`] to choose a different one. With the `woff`
+    /// feature, WOFF and WOFF2 files are accepted as well.
     ///
     /// Returns `None` if the index is invalid or the font couldn't be read.
     pub fn new(data: Data, index: u32) -> Option<Self> {
-        let font_info = FontInfo::new(data.as_ref(), index)?;
+        Font::new_with_variations(data, index, &[])
+    }
+
+    /// Create a new font from some data, using the given coordinates for the variation
+    /// axes of the font.
+    ///
+    /// Each variation consists of the tag of an axis, such as `wght` for the weight,
+    /// and the value of the axis, for example `[("wght", 650.0)]`. Values outside of the
+    /// range of an axis are clamped, and axes that aren't part of the font are ignored.
+    /// When embedding the font, its outlines and metrics are instanced with the given
+    /// coordinates, so fonts that were created with different coordinates are embedded
+    /// separately, even if they use the same data.
+    ///
+    /// Returns `None` if the index is invalid, the font couldn't be read or one of
+    /// the tags is not a valid tag.
+    pub fn new_with_variations(data: Data, index: u32, variations: &[(&str, f32)]) -> Option<Self> {
+        #[cfg(feature = "woff")]
+        let data = if crate::text::woff::is_woff(data.as_ref()) {
+            Data::from(crate::text::woff::decode_woff(data.as_ref()).ok()?)
+        } else {
+            data
+        };
+
+        let axes = FontRef::from_index(data.as_ref(), index).ok()?.axes();
+        let mut filtered = vec![];
+
+        for (tag, value) in variations {
+            let tag = Tag::new_checked(tag.as_bytes()).ok()?;
+
+            // Only keep the axes that actually exist, so that a font without any of the
+            // axes is considered the same as the font with the default instance.
+            if axes.iter().any(|a| a.tag() == tag) {
+                filtered.push((tag, FiniteF32::new(*value)?));
+            }
+        }
 
-        Font::new_with_info(data.clone(), Arc::new(font_info))
+        let font_info = FontInfo::new(data.as_ref(), index, &filtered)?;
+
+        Font::new_with_info(data.clone(), Arc::new(font_info), filtered)
     }
 
-    pub(crate) fn new_with_info(data: Data, font_info: Arc<FontInfo>) -> Option<Self> {
+    pub(crate) fn new_with_info(
+        data: Data,
+        font_info: Arc<FontInfo>,
+        variations: Vec<(Tag, FiniteF32)>,
+    ) -> Option<Self> {
         let font_ref_yoke =
             Yoke::<FontRefYoke<'static>, Arc<dyn AsRef<[u8]> + Send + Sync>>::attach_to_cart(
                 data.0.clone(),
@@ -60,6 +98,7 @@ impl Font {
             font_data: data,
             font_ref_yoke,
             font_info,
+            variations,
         }))))
     }
 
@@ -113,29 +152,140 @@ impl Font {
         self.0.font_info.global_bbox
     }
 
-    // For now, location will always be default, until we support variable fonts.
     pub(crate) fn location_ref(&self) -> LocationRef {
         (&self.0.font_info.location).into()
     }
 
+    /// Return the coordinates of the variation axes of the font, which is empty if
+    /// the default instance is used.
+    pub(crate) fn variations(&self) -> &[(Tag, FiniteF32)] {
+        &self.0.variations
+    }
+
     pub(crate) fn font_ref(&self) -> &FontRef {
         &self.0.font_ref_yoke.get().font_ref
     }
 
-    pub(crate) fn glyph_metrics(&self) -> &GlyphMetrics {
+    fn cached_glyph_metrics(&self) -> &SkrifaGlyphMetrics {
         &self.0.font_ref_yoke.get().glyph_metrics
     }
 
+    /// Call the function with the glyph metrics of the chosen instance of the font.
+    fn with_glyph_metrics<T>(&self, f: impl FnOnce(&SkrifaGlyphMetrics) -> T) -> T {
+        if self.variations().is_empty() {
+            f(self.cached_glyph_metrics())
+        } else {
+            // The cached glyph metrics always use the default instance, since they can't
+            // borrow the location.
+            f(&self
+                .font_ref()
+                .glyph_metrics(Size::unscaled(), self.location_ref()))
+        }
+    }
+
     pub(crate) fn font_data(&self) -> Data {
         self.0.font_data.clone()
     }
 
     #[inline]
     pub(crate) fn advance_width(&self, glyph_id: GlyphId) -> Option<f32> {
-        self.glyph_metrics().advance_width(glyph_id.to_skrifa())
+        self.with_glyph_metrics(|m| m.advance_width(glyph_id.to_skrifa()))
+    }
+
+    #[inline]
+    pub(crate) fn advance_height(&self, glyph_id: GlyphId) -> Option<f32> {
+        let vmtx = self.font_ref().vmtx().ok()?;
+        vmtx.advance(glyph_id.to_skrifa()).map(|a| a as f32)
+    }
+
+    /// Return the y coordinate of the vertical origin of a glyph, which is either
+    /// stored in the `VORG` table or derived from the top side bearing of the glyph.
+    pub(crate) fn vertical_origin(&self, glyph_id: GlyphId) -> Option<f32> {
+        let glyph_id = glyph_id.to_skrifa();
+
+        if let Ok(vorg) = self.font_ref().vorg() {
+            return Some(vorg.vertical_origin_y(glyph_id) as f32);
+        }
+
+        let tsb = self.font_ref().vmtx().ok()?.side_bearing(glyph_id)?;
+        let bounds = self.with_glyph_metrics(|m| m.bounds(glyph_id))?;
+
+        Some(bounds.y_max + tsb as f32)
+    }
+
+    /// Return the metrics of a glyph, or `None` if the glyph doesn't exist in the font.
+    ///
+    /// Variable fonts use the metrics of the chosen instance.
+    pub fn glyph_metrics(&self, glyph_id: GlyphId) -> Option<GlyphMetrics> {
+        self.with_glyph_metrics(|m| {
+            let glyph_id = glyph_id.to_skrifa();
+            let advance_width = m.advance_width(glyph_id)?;
+            // Glyphs without an outline, like spaces, have an empty bounding box.
+            let bbox = m
+                .bounds(glyph_id)
+                .filter(|b| b.x_min < b.x_max && b.y_min < b.y_max)
+                .and_then(|b| Rect::from_ltrb(b.x_min, b.y_min, b.x_max, b.y_max));
+
+            Some(GlyphMetrics {
+                advance_width,
+                bbox,
+            })
+        })
+    }
+
+    /// Return the position and thickness of underlines, if the font specifies them.
+    pub fn underline_metrics(&self) -> Option<DecorationMetrics> {
+        self.font_ref()
+            .metrics(Size::unscaled(), self.location_ref())
+            .underline
+            .map(|d| DecorationMetrics {
+                position: d.offset,
+                thickness: d.thickness,
+            })
+    }
+
+    /// Return the position and thickness of strikeouts, if the font specifies them.
+    pub fn strikeout_metrics(&self) -> Option<DecorationMetrics> {
+        self.font_ref()
+            .metrics(Size::unscaled(), self.location_ref())
+            .strikeout
+            .map(|d| DecorationMetrics {
+                position: d.offset,
+                thickness: d.thickness,
+            })
+    }
+
+    /// Return the outline of a glyph as a path, in font units with the y axis pointing
+    /// upwards. To draw the outline on a surface, you need to scale it by
+    /// `font_size / units_per_em` and flip it vertically.
+    ///
+    /// Returns `None` if the glyph has no outline, as is the case for spaces, or if the
+    /// outline couldn't be loaded. Variable fonts use the outline of the chosen instance.
+    pub fn outline_glyph(&self, glyph_id: GlyphId) -> Option<Path> {
+        glyph_path(self.clone(), glyph_id).map(Path)
     }
 }
 
+/// The metrics of a glyph, in font units with the y axis pointing upwards.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GlyphMetrics {
+    /// The horizontal advance of the glyph.
+    pub advance_width: f32,
+    /// The bounding box of the outline of the glyph, or `None` if it has no outline.
+    pub bbox: Option<Rect>,
+}
+
+/// The metrics of a text decoration, such as an underline, in font units with the
+/// y axis pointing upwards.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DecorationMetrics {
+    /// The position of the top of the decoration line, relative to the baseline.
+    /// Negative values are below the baseline.
+    pub position: f32,
+    /// The thickness of the decoration line.
+    pub thickness: f32,
+}
+
 impl Debug for Font {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "Font {{..}}")
@@ -174,6 +324,9 @@ pub(crate) struct FontInfo {
 struct Repr {
     font_info: Arc<FontInfo>,
     font_data: Data,
+    // Not part of the hash, since the location of the font info already
+    // distinguishes different instances.
+    variations: Vec<(Tag, FiniteF32)>,
     font_ref_yoke: Yoke<FontRefYoke<'static>, Arc<dyn AsRef<[u8]> + Send + Sync>>,
 }
 
@@ -189,12 +342,18 @@ impl Hash for Repr {
 }
 
 impl FontInfo {
-    pub(crate) fn new(data: &[u8], index: u32) -> Option<Self> {
+    pub(crate) fn new(data: &[u8], index: u32, variations: &[(Tag, FiniteF32)]) -> Option<Self> {
         let font_ref = FontRef::from_index(data, index).ok()?;
         let data_len = data.len();
         let checksum = font_ref.head().ok()?.checksum_adjustment();
 
-        let location = Location::default();
+        let location = if variations.is_empty() {
+            Location::default()
+        } else {
+            font_ref
+                .axes()
+                .location(variations.iter().map(|(tag, value)| (*tag, value.get())))
+        };
         let metrics = font_ref.metrics(Size::unscaled(), &location);
         let os_2 = font_ref.os2().ok();
         let ascent = FiniteF32::new(
@@ -266,7 +425,9 @@ impl FontInfo {
     }
 
     pub(crate) fn can_be_cid_font(&self) -> bool {
-        self.has_cff || self.has_glyf || self.has_cff2
+        // CFF2 fonts can't be embedded in PDF, so their glyphs are drawn as Type3
+        // glyphs instead, which also takes care of instancing them.
+        self.has_cff || self.has_glyf
     }
 }
 
@@ -275,5 +436,5 @@ impl FontInfo {
 #[derive(Yokeable, Clone)]
 struct FontRefYoke<'a> {
     pub font_ref: FontRef<'a>,
-    pub glyph_metrics: GlyphMetrics<'a>,
+    pub glyph_metrics: SkrifaGlyphMetrics<'a>,
 }
@@ -1,4 +1,4 @@
-use skrifa::bitmap::{BitmapData, BitmapFormat, BitmapGlyph, Origin};
+use skrifa::bitmap::{BitmapData, BitmapFormat, BitmapGlyph, MaskData, Origin};
 use skrifa::MetadataProvider;
 
 use crate::geom::{Size, Transform};
@@ -8,8 +8,10 @@ use crate::text::Font;
 use crate::text::GlyphId;
 
 pub(crate) fn has_bitmap_data(font: &Font, glyph: GlyphId) -> bool {
-    // We only support PNG.
-    get_bitmap_glyph(font, glyph).is_some_and(|b| matches!(b.data, BitmapData::Png(_)))
+    // Monochrome and grayscale bitmaps are only used for glyphs without an outline,
+    // since they are usually only meant for rendering at small sizes.
+    get_bitmap_glyph(font, glyph)
+        .is_some_and(|b| matches!(b.data, BitmapData::Png(_) | BitmapData::Bgra(_)))
 }
 
 pub(crate) fn get_bitmap_glyph(font: &Font, glyph: GlyphId) -> Option<BitmapGlyph> {
@@ -29,53 +31,122 @@ pub(crate) fn draw_glyph(font: Font, glyph: GlyphId, surface: &mut Surface) -> O
 
     let upem = metrics.units_per_em as f32;
 
-    match bitmap_glyph.data {
-        BitmapData::Png(data) => {
-            let image = Image::from_png(data.to_vec().into(), false)?;
-            let size = Size::from_wh(image.size().0 as f32, image.size().1 as f32).unwrap();
-
-            // Adapted from vello.
-            let scale_factor = upem / (bitmap_glyph.ppem_y);
-            let mut transform =
-                Transform::from_translate(-bitmap_glyph.bearing_x, bitmap_glyph.bearing_y)
-                    .pre_concat(Transform::from_scale(scale_factor, scale_factor))
-                    .pre_concat(Transform::from_translate(
-                        -bitmap_glyph.inner_bearing_x,
-                        -bitmap_glyph.inner_bearing_y,
-                    ));
-
-            transform = match bitmap_glyph.placement_origin {
-                Origin::TopLeft => transform,
-                Origin::BottomLeft => {
-                    transform.pre_concat(Transform::from_translate(0.0, -(image.size().1 as f32)))
-                }
-            };
-
-            transform = if let Some(format) = bitmap_strikes.format() {
-                if format == BitmapFormat::Sbix {
-                    // For unknown reasons, using Apple Color Emoji will lead to a vertical shift on MacOS, but this shift
-                    // doesn't seem to be coming from the font and most likely is somehow hardcoded. On Windows,
-                    // this shift will not be applied. However, if this shift is not applied the emojis are a bit
-                    // too high up when being together with other text, so we try to imitate this.
-                    // See also https://github.com/harfbuzz/harfbuzz/issues/2679#issuecomment-1345595425
-                    // We approximate this vertical shift that seems to be produced by it.
-                    // This value seems to be pretty close to what is happening on MacOS.
-                    transform
-                        .pre_concat(Transform::from_translate(0.0, 0.128 * upem / scale_factor))
-                } else {
-                    transform
-                }
-            } else {
-                transform
-            };
-
-            surface.push_transform(&transform);
-            surface.draw_image(image, size);
-            surface.pop();
-
-            Some(())
+    let image = match bitmap_glyph.data {
+        BitmapData::Png(data) => Image::from_png(data.to_vec().into(), false)?,
+        BitmapData::Bgra(data) => bgra_image(data, bitmap_glyph.width, bitmap_glyph.height)?,
+        BitmapData::Mask(mask) => {
+            // Prefer the outline of the glyph, if it has one.
+            if font
+                .font_ref()
+                .outline_glyphs()
+                .get(glyph.to_skrifa())
+                .is_some()
+            {
+                return None;
+            }
+
+            mask_image(&mask, bitmap_glyph.width, bitmap_glyph.height)?
+        }
+    };
+
+    let size = Size::from_wh(image.size().0 as f32, image.size().1 as f32).unwrap();
+
+    // Adapted from vello.
+    let scale_factor = upem / (bitmap_glyph.ppem_y);
+    let mut transform = Transform::from_translate(-bitmap_glyph.bearing_x, bitmap_glyph.bearing_y)
+        .pre_concat(Transform::from_scale(scale_factor, scale_factor))
+        .pre_concat(Transform::from_translate(
+            -bitmap_glyph.inner_bearing_x,
+            -bitmap_glyph.inner_bearing_y,
+        ));
+
+    transform = match bitmap_glyph.placement_origin {
+        Origin::TopLeft => transform,
+        Origin::BottomLeft => {
+            transform.pre_concat(Transform::from_translate(0.0, -(image.size().1 as f32)))
+        }
+    };
+
+    transform = if let Some(format) = bitmap_strikes.format() {
+        if format == BitmapFormat::Sbix {
+            // For unknown reasons, using Apple Color Emoji will lead to a vertical shift on MacOS, but this shift
+            // doesn't seem to be coming from the font and most likely is somehow hardcoded. On Windows,
+            // this shift will not be applied. However, if this shift is not applied the emojis are a bit
+            // too high up when being together with other text, so we try to imitate this.
+            // See also https://github.com/harfbuzz/harfbuzz/issues/2679#issuecomment-1345595425
+            // We approximate this vertical shift that seems to be produced by it.
+            // This value seems to be pretty close to what is happening on MacOS.
+            transform.pre_concat(Transform::from_translate(0.0, 0.128 * upem / scale_factor))
+        } else {
+            transform
+        }
+    } else {
+        transform
+    };
+
+    surface.push_transform(&transform);
+    surface.draw_image(image, size);
+    surface.pop();
+
+    Some(())
+}
+
+/// Create an image from premultiplied BGRA pixels.
+fn bgra_image(data: &[u8], width: u32, height: u32) -> Option<Image> {
+    if data.len() != width as usize * height as usize * 4 {
+        return None;
+    }
+
+    let unmultiply = |c: u8, a: u8| (c as u16 * 255 / a as u16).min(255) as u8;
+
+    let rgba = data
+        .chunks_exact(4)
+        .flat_map(|p| match p[3] {
+            0 => [0, 0, 0, 0],
+            a => [
+                unmultiply(p[2], a),
+                unmultiply(p[1], a),
+                unmultiply(p[0], a),
+                a,
+            ],
+        })
+        .collect();
+
+    Some(Image::from_rgba8(rgba, width, height))
+}
+
+/// Create a black image from a monochrome or grayscale bitmap, where the
+/// bitmap determines the coverage of each pixel.
+fn mask_image(mask: &MaskData, width: u32, height: u32) -> Option<Image> {
+    let bpp = mask.bpp as usize;
+
+    if width == 0 || height == 0 || !matches!(bpp, 1 | 2 | 4 | 8) {
+        return None;
+    }
+
+    let (width, height) = (width as usize, height as usize);
+    // Unless the bitmap is bit-aligned, each row starts at a new byte.
+    let row_bits = if mask.is_packed {
+        width * bpp
+    } else {
+        (width * bpp).div_ceil(8) * 8
+    };
+
+    if mask.data.len() * 8 < row_bits * (height - 1) + width * bpp {
+        return None;
+    }
+
+    let max = (1u16 << bpp) - 1;
+    let mut rgba = Vec::with_capacity(width * height * 4);
+
+    for y in 0..height {
+        for x in 0..width {
+            let bit = y * row_bits + x * bpp;
+            let byte = mask.data[bit / 8];
+            let value = (byte >> (8 - bpp - bit % 8)) as u16 & max;
+            rgba.extend([0, 0, 0, (value * 255 / max) as u8]);
         }
-        BitmapData::Bgra(_) => None,
-        BitmapData::Mask(_) => None,
     }
+
+    Some(Image::from_rgba8(rgba, width as u32, height as u32))
 }
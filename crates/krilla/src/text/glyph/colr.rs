@@ -1,6 +1,5 @@
 use skrifa::color::{Brush, ColorPainter, ColorStop, CompositeMode};
 use skrifa::outline::DrawSettings;
-use skrifa::prelude::LocationRef;
 use skrifa::raw::types::BoundingBox;
 use skrifa::raw::TableProvider;
 use skrifa::MetadataProvider;
@@ -9,14 +8,15 @@ use tiny_skia_path::{Path, PathBuilder};
 use crate::geom::Transform;
 use crate::graphics::blend::BlendMode;
 use crate::graphics::color::rgb;
+use crate::graphics::mask::{MaskType, TransferFunction};
 use crate::graphics::paint::{
     Fill, FillRule, LinearGradient, RadialGradient, SpreadMethod, Stop, SweepGradient,
 };
 use crate::num::NormalizedF32;
 use crate::surface::Surface;
 use crate::text::outline::OutlineBuilder;
-use crate::text::Font;
 use crate::text::GlyphId;
+use crate::text::{ColorPalette, Font};
 
 pub(crate) fn has_colr_data(font: &Font, glyph: GlyphId) -> bool {
     font.font_ref()
@@ -29,6 +29,7 @@ pub(crate) fn has_colr_data(font: &Font, glyph: GlyphId) -> bool {
 pub(crate) fn draw_glyph(
     font: Font,
     context_color: rgb::Color,
+    palette: &ColorPalette,
     glyph: GlyphId,
     surface: &mut Surface,
 ) -> Option<()> {
@@ -41,7 +42,7 @@ pub(crate) fn draw_glyph(
     let colr_glyphs = font.font_ref().color_glyphs();
     let colr_glyph = colr_glyphs.get(glyph.to_skrifa())?;
 
-    let mut colr_canvas = ColrBuilder::new(font.clone(), context_color);
+    let mut colr_canvas = ColrBuilder::new(font.clone(), context_color, palette.clone());
     colr_glyph
         .paint(font.location_ref(), &mut colr_canvas)
         .ok()?;
@@ -65,6 +66,18 @@ fn interpret(instructions: Vec<Instruction>, surface: &mut Surface) {
                 surface.pop();
                 surface.pop();
             }
+            Instruction::Masked(inverted, mask_instructions, instructions) => {
+                let mut mask =
+                    surface.create_mask(MaskType::Alpha, |s| interpret(mask_instructions, s));
+
+                if inverted {
+                    mask = mask.with_transfer_function(TransferFunction::inverted());
+                }
+
+                surface.push_mask(mask);
+                interpret(instructions, surface);
+                surface.pop();
+            }
             Instruction::Filled(fill, mut clips) => {
                 let filled = clips.split_off(clips.len() - 1);
 
@@ -96,24 +109,30 @@ fn interpret(instructions: Vec<Instruction>, surface: &mut Surface) {
 struct ColrBuilder {
     font: Font,
     context_color: rgb::Color,
+    palette: ColorPalette,
     clips: Vec<Vec<Path>>,
     stack: Vec<Vec<Instruction>>,
-    layers: Vec<BlendMode>,
+    layers: Vec<CompositeMode>,
     transforms: Vec<Transform>,
     error: bool,
 }
 
 /// A bytecode instruction for drawing a COLR glyph.
+#[derive(Clone)]
 enum Instruction {
     Layer(BlendMode, Vec<Instruction>),
+    /// Draw the second instructions masked by the alpha of the first ones, or by
+    /// their inverted alpha if the flag is set.
+    Masked(bool, Vec<Instruction>, Vec<Instruction>),
     Filled(Box<Fill>, Vec<Path>),
 }
 
 impl ColrBuilder {
-    pub fn new(font: Font, context_color: rgb::Color) -> Self {
+    pub fn new(font: Font, context_color: rgb::Color, palette: ColorPalette) -> Self {
         Self {
             font,
             context_color,
+            palette,
             stack: vec![vec![]],
             transforms: vec![Transform::identity()],
             clips: vec![vec![]],
@@ -140,17 +159,35 @@ impl ColrBuilder {
         alpha: f32,
     ) -> Option<(rgb::Color, NormalizedF32)> {
         if palette_index != u16::MAX {
-            let color = self
-                .font
-                .font_ref()
-                .cpal()
-                .ok()?
-                .color_records_array()?
-                .ok()?[palette_index as usize];
+            let cpal = self.font.font_ref().cpal().ok()?;
+
+            // Fall back to the first palette if the font doesn't have the chosen one.
+            let palette = if self.palette.index() < cpal.num_palettes() {
+                self.palette.index()
+            } else {
+                0
+            };
+
+            let record = if palette_index < cpal.num_palette_entries() {
+                let first = cpal.color_record_indices().get(palette as usize)?.get();
+                cpal.color_records_array()?
+                    .ok()?
+                    .get(first as usize + palette_index as usize)
+                    .copied()
+            } else {
+                None
+            };
+
+            let color = match (self.palette.get_override(palette_index), record) {
+                (Some(color), _) => color,
+                (None, Some(record)) => rgb::Color::new(record.red, record.green, record.blue),
+                (None, None) => return None,
+            };
+            let record_alpha = record.map(|r| r.alpha).unwrap_or(255);
 
             Some((
-                rgb::Color::new(color.red, color.green, color.blue),
-                NormalizedF32::new(alpha * color.alpha as f32 / 255.0)?,
+                color,
+                NormalizedF32::new(alpha * record_alpha as f32 / 255.0)?,
             ))
         } else {
             Some((self.context_color, NormalizedF32::new(alpha)?))
@@ -228,7 +265,7 @@ impl ColorPainter for ColrBuilder {
         };
 
         let Ok(_) = outline_glyph.draw(
-            DrawSettings::unhinted(skrifa::instance::Size::unscaled(), LocationRef::default()),
+            DrawSettings::unhinted(skrifa::instance::Size::unscaled(), self.font.location_ref()),
             &mut glyph_builder,
         ) else {
             self.error = true;
@@ -432,41 +469,90 @@ impl ColorPainter for ColrBuilder {
     }
 
     fn push_layer(&mut self, composite_mode: CompositeMode) {
-        let mode = match composite_mode {
-            CompositeMode::SrcOver => BlendMode::Normal,
-            CompositeMode::Screen => BlendMode::Screen,
-            CompositeMode::Overlay => BlendMode::Overlay,
-            CompositeMode::Darken => BlendMode::Darken,
-            CompositeMode::Lighten => BlendMode::Lighten,
-            CompositeMode::ColorDodge => BlendMode::ColorDodge,
-            CompositeMode::ColorBurn => BlendMode::ColorBurn,
-            CompositeMode::HardLight => BlendMode::HardLight,
-            CompositeMode::SoftLight => BlendMode::SoftLight,
-            CompositeMode::Difference => BlendMode::Difference,
-            CompositeMode::Exclusion => BlendMode::Exclusion,
-            CompositeMode::Multiply => BlendMode::Multiply,
-            CompositeMode::HslHue => BlendMode::Hue,
-            CompositeMode::HslColor => BlendMode::Color,
-            CompositeMode::HslLuminosity => BlendMode::Luminosity,
-            CompositeMode::HslSaturation => BlendMode::Saturation,
-            _ => BlendMode::Normal,
-        };
-
-        self.layers.push(mode);
+        self.layers.push(composite_mode);
         self.stack.push(vec![]);
     }
 
     fn pop_layer(&mut self) {
-        let (Some(blend), Some(instructions)) = (self.layers.pop(), self.stack.pop()) else {
+        let (Some(composite_mode), Some(source)) = (self.layers.pop(), self.stack.pop()) else {
             self.error = true;
             return;
         };
 
-        let Some(stack) = self.stack.last_mut() else {
+        // The backdrop consists of everything that was drawn in the enclosing layer so far.
+        let Some(backdrop) = self.stack.last_mut() else {
             self.error = true;
             return;
         };
 
-        stack.push(Instruction::Layer(blend, instructions));
+        let layer = |instructions| Instruction::Layer(BlendMode::Normal, instructions);
+
+        // PDF doesn't have an equivalent for the Porter-Duff composite modes, so they
+        // are emulated by masking the source with the backdrop, or the other way around.
+        match composite_mode {
+            CompositeMode::Clear => backdrop.clear(),
+            CompositeMode::Src => *backdrop = vec![layer(source)],
+            CompositeMode::Dest => {}
+            CompositeMode::DestOver => backdrop.insert(0, layer(source)),
+            CompositeMode::SrcIn => {
+                let mask = std::mem::take(backdrop);
+                backdrop.push(Instruction::Masked(false, mask, source));
+            }
+            CompositeMode::DestIn => {
+                let instructions = std::mem::take(backdrop);
+                backdrop.push(Instruction::Masked(false, source, instructions));
+            }
+            CompositeMode::SrcOut => {
+                let mask = std::mem::take(backdrop);
+                backdrop.push(Instruction::Masked(true, mask, source));
+            }
+            CompositeMode::DestOut => {
+                let instructions = std::mem::take(backdrop);
+                backdrop.push(Instruction::Masked(true, source, instructions));
+            }
+            // The atop modes are only exact if the lower layer is opaque wherever the
+            // upper one is drawn, which is usually the case in practice.
+            CompositeMode::SrcAtop => {
+                let mask = backdrop.clone();
+                backdrop.push(Instruction::Masked(false, mask, source));
+            }
+            CompositeMode::DestAtop => {
+                let instructions = std::mem::take(backdrop);
+                backdrop.push(layer(source.clone()));
+                backdrop.push(Instruction::Masked(false, source, instructions));
+            }
+            _ => {
+                let Some(blend_mode) = to_blend_mode(composite_mode) else {
+                    // Modes like `Xor` and `Plus` can't be emulated, in which case
+                    // we fall back to drawing the outline of the glyph.
+                    self.error = true;
+                    return;
+                };
+
+                backdrop.push(Instruction::Layer(blend_mode, source));
+            }
+        }
     }
 }
+
+fn to_blend_mode(composite_mode: CompositeMode) -> Option<BlendMode> {
+    Some(match composite_mode {
+        CompositeMode::SrcOver => BlendMode::Normal,
+        CompositeMode::Screen => BlendMode::Screen,
+        CompositeMode::Overlay => BlendMode::Overlay,
+        CompositeMode::Darken => BlendMode::Darken,
+        CompositeMode::Lighten => BlendMode::Lighten,
+        CompositeMode::ColorDodge => BlendMode::ColorDodge,
+        CompositeMode::ColorBurn => BlendMode::ColorBurn,
+        CompositeMode::HardLight => BlendMode::HardLight,
+        CompositeMode::SoftLight => BlendMode::SoftLight,
+        CompositeMode::Difference => BlendMode::Difference,
+        CompositeMode::Exclusion => BlendMode::Exclusion,
+        CompositeMode::Multiply => BlendMode::Multiply,
+        CompositeMode::HslHue => BlendMode::Hue,
+        CompositeMode::HslColor => BlendMode::Color,
+        CompositeMode::HslLuminosity => BlendMode::Luminosity,
+        CompositeMode::HslSaturation => BlendMode::Saturation,
+        _ => return None,
+    })
+}
@@ -1,8 +1,10 @@
+use std::collections::BTreeMap;
 use std::ops::Range;
 
 use crate::color::rgb;
 use crate::geom::Transform;
 use crate::surface::Surface;
+use crate::text::type3::GlyphColors;
 use crate::text::Font;
 
 #[cfg(feature = "raster-images")]
@@ -34,15 +36,21 @@ impl GlyphId {
 /// Draw a color glyph to a surface.
 pub(crate) fn draw_color_glyph(
     font: Font,
-    context_color: rgb::Color,
+    colors: GlyphColors,
     glyph: GlyphId,
     base_transform: Transform,
     surface: &mut Surface,
 ) -> Option<()> {
+    let GlyphColors {
+        context_color,
+        palette,
+    } = colors;
+    let palette = surface.sc.palette(palette).clone();
+
     surface.push_transform(&base_transform);
     surface.push_transform(&Transform::from_scale(1.0, -1.0));
 
-    let drawn = colr::draw_glyph(font.clone(), context_color, glyph, surface)
+    let drawn = colr::draw_glyph(font.clone(), context_color, &palette, glyph, surface)
         .or_else(|| svg::draw_glyph(font.clone(), context_color, glyph, surface))
         .or_else(|| {
             #[cfg(feature = "raster-images")]
@@ -73,12 +81,12 @@ pub(crate) fn should_outline(font: &Font, glyph: GlyphId) -> bool {
 /// Draw a color glyph or outline glyph to a surface.
 pub(crate) fn draw_glyph(
     font: Font,
-    context_color: rgb::Color,
+    colors: GlyphColors,
     glyph: GlyphId,
     base_transform: Transform,
     surface: &mut Surface,
 ) -> Option<()> {
-    draw_color_glyph(font.clone(), context_color, glyph, base_transform, surface)
+    draw_color_glyph(font.clone(), colors, glyph, base_transform, surface)
         .or_else(|| outline::draw_glyph(font, glyph, base_transform, surface))
 }
 
@@ -94,12 +102,34 @@ pub trait Glyph {
     /// The offset in the x direction of the glyph, at the given font size.
     fn x_offset(&self, size: f32) -> f32;
     /// The offset in the y direction of the glyph, at the given font size.
+    ///
+    /// Successive glyphs with the same offset are shifted using the text rise, so
+    /// that subscripts and superscripts are still considered to be part of the same
+    /// line when extracting the text.
     fn y_offset(&self, size: f32) -> f32;
     /// The advance in the y direction of the glyph, at the given font size.
     fn y_advance(&self, size: f32) -> f32;
     /// A location identifying the glyph. If set, `krilla` will automatically call
     /// `set_location` before processing the glyph.
     fn location(&self) -> Option<crate::surface::Location>;
+    /// The text that should be extracted for the glyph, if it differs from the text
+    /// in its text range.
+    ///
+    /// This is useful if the mapping derived from the text is wrong, for example for
+    /// ligatures that don't correspond to the characters of their cluster, or for
+    /// glyphs in the private use area with a known meaning. The override is used in
+    /// the `ToUnicode` mapping of the glyph, and glyphs whose override consists of
+    /// more than one codepoint are additionally wrapped in an `ActualText` span. For
+    /// glyphs that are part of the same cluster, only the override of the first glyph
+    /// is taken into account.
+    fn text_override(&self) -> Option<&str> {
+        None
+    }
+    /// The index of the font that the glyph belongs to, in the list of fonts passed
+    /// to [`Surface::draw_glyphs_with_fallback`]. It is ignored by all other methods.
+    fn font_index(&self) -> usize {
+        0
+    }
 }
 
 /// A glyph type that implements `Glyph`.
@@ -122,6 +152,12 @@ pub struct KrillaGlyph {
     pub y_advance: f32,
     /// The location of the glyph.
     pub location: Option<crate::surface::Location>,
+    /// The text that should be extracted for the glyph instead of the
+    /// text in its range.
+    pub text_override: Option<String>,
+    /// The index of the font of the glyph when drawing glyphs with
+    /// [`Surface::draw_glyphs_with_fallback`].
+    pub font_index: usize,
 }
 
 impl Glyph for KrillaGlyph {
@@ -152,6 +188,14 @@ impl Glyph for KrillaGlyph {
     fn location(&self) -> Option<crate::surface::Location> {
         self.location
     }
+
+    fn text_override(&self) -> Option<&str> {
+        self.text_override.as_deref()
+    }
+
+    fn font_index(&self) -> usize {
+        self.font_index
+    }
 }
 
 impl KrillaGlyph {
@@ -176,10 +220,135 @@ impl KrillaGlyph {
             y_advance,
             text_range: range,
             location,
+            text_override: None,
+            font_index: 0,
+        }
+    }
+
+    /// Set the text that should be extracted for the glyph instead of the
+    /// text in its range.
+    pub fn with_text_override(mut self, text: impl Into<String>) -> Self {
+        self.text_override = Some(text.into());
+        self
+    }
+
+    /// Set the index of the font of the glyph when drawing glyphs with
+    /// [`Surface::draw_glyphs_with_fallback`].
+    pub fn with_font_index(mut self, font_index: usize) -> Self {
+        self.font_index = font_index;
+        self
+    }
+}
+
+/// The spacing that should be applied to a sequence of glyphs, in addition to
+/// their advances.
+///
+/// The advances of the glyphs themselves are not affected by the spacing. Instead,
+/// the character spacing is added after each glyph, and the word spacing after each
+/// glyph whose cluster consists of a single space character. Finally, the whole
+/// horizontal displacement of the sequence, including the x offsets of the glyphs,
+/// is scaled by the horizontal scaling, which also squeezes or stretches the
+/// glyphs themselves. This means that the advance of a glyph is
+/// `(x_advance + char_spacing + word_spacing) * horizontal_scaling`, where the word
+/// spacing is zero for glyphs that aren't spaces. The spacing only applies in the
+/// horizontal direction, and is ignored in the vertical writing mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextSpacing {
+    /// The additional spacing after each glyph, in user space units.
+    pub char_spacing: f32,
+    /// The additional spacing after each space, in user space units.
+    pub word_spacing: f32,
+    /// The factor by which the text should be scaled horizontally, where `1.0`
+    /// means no scaling.
+    pub horizontal_scaling: f32,
+}
+
+impl TextSpacing {
+    /// Return the word spacing that applies to the given glyph.
+    pub(crate) fn word_spacing(&self, glyph: &impl Glyph, text: &str) -> f32 {
+        if self.word_spacing != 0.0 && text.get(glyph.text_range()) == Some(" ") {
+            self.word_spacing
+        } else {
+            0.0
+        }
+    }
+
+    /// Return the advance of the glyph with spacing applied, at the given font size.
+    pub(crate) fn x_advance(&self, glyph: &impl Glyph, text: &str, size: f32) -> f32 {
+        (glyph.x_advance(size) + self.char_spacing + self.word_spacing(glyph, text))
+            * self.horizontal_scaling
+    }
+}
+
+impl Default for TextSpacing {
+    fn default() -> Self {
+        Self {
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            horizontal_scaling: 1.0,
         }
     }
 }
 
+/// A style that is synthesized when drawing glyphs, for fonts that lack a bold or
+/// italic style.
+///
+/// Synthetic bold strokes the glyphs in addition to filling them, with a stroke width
+/// of `bold` times the font size. If a stroke is set, its width is increased by the
+/// same amount instead. Synthetic oblique skews the glyphs by `oblique` degrees around
+/// the baseline, where positive angles lean the glyphs to the right. Neither affects the
+/// advances of the glyphs, so their metrics need to be adjusted by the caller if needed.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SyntheticStyle {
+    /// The stroke width used for emboldening the glyphs, relative to the font size.
+    /// A value of `0.02` is a good choice for most fonts.
+    pub bold: f32,
+    /// The angle in degrees by which the glyphs should be slanted.
+    pub oblique: f32,
+}
+
+/// The palette that is used for drawing COLR glyphs.
+///
+/// The `CPAL` table of a font can contain multiple palettes, for example one for
+/// light and one for dark backgrounds. By default, the first palette is used, and
+/// the colors of the entries in the palette are used as they are. Glyphs that are
+/// drawn with different palettes are embedded separately.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Default)]
+pub struct ColorPalette {
+    index: u16,
+    overrides: BTreeMap<u16, rgb::Color>,
+}
+
+impl ColorPalette {
+    /// Create a new palette that uses the palette with the given index of the font.
+    ///
+    /// If a font doesn't have a palette with that index, its first palette is used
+    /// instead.
+    pub fn new(index: u16) -> Self {
+        Self {
+            index,
+            overrides: BTreeMap::new(),
+        }
+    }
+
+    /// Override the color of the entry with the given index in the palette.
+    ///
+    /// The opacity of the entry is preserved, only its color is replaced.
+    pub fn with_override(mut self, entry: u16, color: rgb::Color) -> Self {
+        self.overrides.insert(entry, color);
+        self
+    }
+
+    /// The index of the palette of the font.
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    pub(crate) fn get_override(&self, entry: u16) -> Option<rgb::Color> {
+        self.overrides.get(&entry).copied()
+    }
+}
+
 /// A glyph that belongs either to a CID font or a Type 3 font.
 #[derive(Clone, Copy)]
 pub(crate) enum PDFGlyph {
@@ -23,6 +23,22 @@ pub(crate) fn glyph_path(font: Font, glyph: GlyphId) -> Option<tiny_skia_path::P
     outline_builder.finish()
 }
 
+/// Check whether the outline of a glyph can be loaded. Glyphs with an empty outline,
+/// such as spaces, can be loaded as well.
+pub(crate) fn can_load_outline(font: &Font, glyph: GlyphId) -> bool {
+    font.font_ref()
+        .outline_glyphs()
+        .get(glyph.to_skrifa())
+        .is_some_and(|outline_glyph| {
+            outline_glyph
+                .draw(
+                    DrawSettings::unhinted(skrifa::instance::Size::unscaled(), font.location_ref()),
+                    &mut OutlineBuilder::new(),
+                )
+                .is_ok()
+        })
+}
+
 /// Draw an outline-based glyph on a surface.
 pub(crate) fn draw_glyph(
     font: Font,
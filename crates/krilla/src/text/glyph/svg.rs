@@ -6,10 +6,12 @@ use crate::text::Font;
 use crate::text::GlyphId;
 
 pub(crate) fn has_svg_data(font: &Font, glyph: GlyphId) -> bool {
-    font.font_ref()
-        .svg()
-        .map(|svg_table| svg_table.glyph_data(glyph.to_skrifa()).is_ok())
-        .is_ok()
+    // Not every glyph of a font with an `SVG` table necessarily has an SVG document.
+    font.font_ref().svg().is_ok_and(|svg_table| {
+        svg_table
+            .glyph_data(glyph.to_skrifa())
+            .is_ok_and(|d| d.is_some())
+    })
 }
 
 /// Draw an SVG-based glyph on a surface.
@@ -2,10 +2,9 @@ use std::cell::{RefCell, RefMut};
 use std::ops::Range;
 use std::rc::Rc;
 
-use crate::color::rgb;
-use crate::text::type3::ColoredGlyph;
+use crate::text::type3::{ColoredGlyph, GlyphColors};
 use crate::text::Glyph;
-use crate::text::{FontContainer, FontIdentifier};
+use crate::text::{FontContainer, FontIdentifier, WritingMode};
 
 pub(crate) enum GlyphSpan<'a, T>
 where
@@ -56,9 +55,10 @@ where
     T: Glyph,
 {
     slice: &'a [T],
-    context_color: rgb::Color,
+    colors: GlyphColors,
     forbid_invalid_codepoints: bool,
     font_container: Rc<RefCell<FontContainer>>,
+    writing_mode: WritingMode,
     text: &'a str,
 }
 
@@ -70,15 +70,17 @@ where
         slice: &'a [T],
         text: &'a str,
         forbid_invalid_codepoints: bool,
-        context_color: rgb::Color,
+        colors: GlyphColors,
         font_container: Rc<RefCell<FontContainer>>,
+        writing_mode: WritingMode,
     ) -> Self {
         Self {
             slice,
-            context_color,
+            colors,
             forbid_invalid_codepoints,
             text,
             font_container,
+            writing_mode,
         }
     }
 }
@@ -93,23 +95,24 @@ where
     fn next(&mut self) -> Option<Self::Item> {
         fn func<U>(
             g: &U,
-            context_color: rgb::Color,
+            colors: GlyphColors,
             previous_range: Option<Range<usize>>,
             forbid_invalid_codepoints: bool,
             mut font_container: RefMut<FontContainer>,
+            writing_mode: WritingMode,
             text: &str,
         ) -> (Range<usize>, bool)
         where
             U: Glyph,
         {
             let (identifier, pdf_glyph) =
-                font_container.add_glyph(ColoredGlyph::new(g.glyph_id(), context_color));
+                font_container.add_glyph(ColoredGlyph::new(g.glyph_id(), colors), writing_mode);
             let pdf_font = font_container
                 .get_from_identifier_mut(identifier.clone())
                 .unwrap();
 
             let range = g.text_range().clone();
-            let text = &text[range.clone()];
+            let text = glyph_text(g, text);
             let codepoints = pdf_font.get_codepoints(pdf_glyph);
             // Check if the glyph has already been assigned codepoints that don't match the
             // one we are seeing right now.
@@ -136,34 +139,38 @@ where
                 pdf_font.set_codepoints(pdf_glyph, text.to_string(), g.location());
             }
 
-            (range, incompatible_codepoint)
+            (range, incompatible_codepoint || requires_span(g))
         }
 
         let mut use_span = None;
         let mut count = 1;
 
-        let mut iter = self.slice.iter();
+        let slice = self.slice;
+        let first = slice.first()?;
+        let mut iter = slice.iter();
 
         // Get the range of the first glyph, as well as whether it's
         // incompatible.
         let (first_range, first_incompatible) = func(
             iter.next()?,
-            self.context_color,
+            self.colors,
             None,
             self.forbid_invalid_codepoints,
             self.font_container.borrow_mut(),
+            self.writing_mode,
             self.text,
         );
 
-        let mut prev_range = first_range.clone();
+        let mut prev_range = first_range;
 
         for next in iter {
             let (next_range, next_incompatible) = func(
                 next,
-                self.context_color,
+                self.colors,
                 Some(prev_range.clone()),
                 self.forbid_invalid_codepoints,
                 self.font_container.borrow_mut(),
+                self.writing_mode,
                 self.text,
             );
 
@@ -234,7 +241,7 @@ where
         self.slice = tail;
 
         let fragment = match use_span.unwrap_or(false) {
-            true => GlyphSpan::Spanned(head, &self.text[first_range]),
+            true => GlyphSpan::Spanned(head, glyph_text(first, self.text)),
             false => GlyphSpan::Unspanned(head),
         };
 
@@ -281,7 +288,8 @@ where
     T: Glyph,
 {
     font_container: Rc<RefCell<FontContainer>>,
-    context_color: rgb::Color,
+    colors: GlyphColors,
+    writing_mode: WritingMode,
     slice: &'a [T],
 }
 
@@ -291,12 +299,14 @@ where
 {
     pub fn new(
         font_container: Rc<RefCell<FontContainer>>,
-        context_color: rgb::Color,
+        colors: GlyphColors,
+        writing_mode: WritingMode,
         slice: &'a [T],
     ) -> Self {
         Self {
             font_container,
-            context_color,
+            colors,
+            writing_mode,
             slice,
         }
     }
@@ -318,23 +328,31 @@ where
             let mut iter = self.slice.iter();
             let first = get_glyph_props(
                 iter.next()?,
-                self.context_color,
+                self.colors,
+                self.writing_mode,
                 &mut self.font_container.borrow_mut(),
             );
 
             for next in iter {
                 let temp_glyph = get_glyph_props(
                     next,
-                    self.context_color,
+                    self.colors,
+                    self.writing_mode,
                     &mut self.font_container.borrow_mut(),
                 );
 
-                // If either of those is different, we need to start a new subrun.
-                if first.font_identifier != temp_glyph.font_identifier
+                // In the vertical writing mode, the glyphs are positioned individually
+                // while encoding them, so only the font needs to be the same.
+                if first.font_identifier.is_vertical() {
+                    if first.font_identifier != temp_glyph.font_identifier {
+                        break;
+                    }
+                } else if first.font_identifier != temp_glyph.font_identifier
                     || first.y_offset != temp_glyph.y_offset
                     || first.y_advance != 0.0
                     || temp_glyph.y_advance != 0.0
                 {
+                    // If either of those is different, we need to start a new subrun.
                     break;
                 }
 
@@ -361,7 +379,8 @@ pub(crate) struct GlyphProps {
 
 pub(crate) fn get_glyph_props<U>(
     g: &U,
-    context_color: rgb::Color,
+    colors: GlyphColors,
+    writing_mode: WritingMode,
     font_container: &mut FontContainer,
 ) -> GlyphProps
 where
@@ -369,7 +388,7 @@ where
 {
     // Safe because we've already added all glyphs in the text spanner.
     let font_identifier = font_container
-        .font_identifier(ColoredGlyph::new(g.glyph_id(), context_color))
+        .font_identifier(ColoredGlyph::new(g.glyph_id(), colors), writing_mode)
         .unwrap();
 
     GlyphProps {
@@ -382,7 +401,8 @@ where
 pub fn use_text_spanner(
     glyphs: &[impl Glyph],
     text: &str,
-    context_color: rgb::Color,
+    colors: GlyphColors,
+    writing_mode: WritingMode,
     font_container: &mut FontContainer,
 ) -> bool {
     if glyphs.is_empty() {
@@ -403,7 +423,14 @@ pub fn use_text_spanner(
         // The only reason we keep going and don't early abort is in order to fully
         // check the `do_glyph_grouping` property.
         if !*do_text_span {
-            check_text_span_prop(glyph, text, context_color, font_container, do_text_span);
+            check_text_span_prop(
+                glyph,
+                text,
+                colors,
+                writing_mode,
+                font_container,
+                do_text_span,
+            );
         }
     };
 
@@ -426,21 +453,21 @@ pub fn use_text_spanner(
 fn check_text_span_prop(
     glyph: &impl Glyph,
     text: &str,
-    context_color: rgb::Color,
+    colors: GlyphColors,
+    writing_mode: WritingMode,
     font_container: &mut FontContainer,
     do_text_span: &mut bool,
 ) {
     let (identifier, pdf_glyph) =
-        font_container.add_glyph(ColoredGlyph::new(glyph.glyph_id(), context_color));
+        font_container.add_glyph(ColoredGlyph::new(glyph.glyph_id(), colors), writing_mode);
     let pdf_font = font_container
         .get_from_identifier_mut(identifier.clone())
         .unwrap();
 
-    let range = glyph.text_range().clone();
-    let text = &text[range.clone()];
+    let text = glyph_text(glyph, text);
     let codepoints = pdf_font.get_codepoints(pdf_glyph);
 
-    if codepoints.is_some_and(|t| t != text) {
+    if codepoints.is_some_and(|t| t != text) || requires_span(glyph) {
         // If there already exists an entry, then the codepoints are invalid, and we will have to
         // use a text span.
         *do_text_span = true;
@@ -448,3 +475,20 @@ fn check_text_span_prop(
         pdf_font.set_codepoints(pdf_glyph, text.to_string(), glyph.location());
     }
 }
+
+/// The text that should be extracted for a glyph, which is either its override or the
+/// text of its cluster.
+pub(crate) fn glyph_text<'a>(glyph: &'a impl Glyph, text: &'a str) -> &'a str {
+    glyph
+        .text_override()
+        .unwrap_or_else(|| &text[glyph.text_range()])
+}
+
+/// Whether the glyph needs to be wrapped in an `ActualText` span, regardless of its
+/// codepoints. This is the case for glyphs whose override consists of more than one
+/// codepoint, since not all viewers handle such mappings in the `ToUnicode` CMap well.
+fn requires_span(glyph: &impl Glyph) -> bool {
+    glyph
+        .text_override()
+        .is_some_and(|t| t.chars().nth(1).is_some())
+}
@@ -0,0 +1,412 @@
+use skrifa::instance::Size;
+use skrifa::outline::{DrawSettings, OutlinePen};
+use skrifa::raw::types::Tag;
+use skrifa::raw::TableProvider;
+use skrifa::{FontRef, MetadataProvider};
+
+use crate::error::{KrillaError, KrillaResult};
+use crate::text::{Font, GlyphId};
+
+/// Tables that only contain variation data, which don't apply anymore after instancing.
+const VARIATION_TABLES: &[&[u8; 4]] = &[
+    b"avar", b"cvar", b"fvar", b"gvar", b"HVAR", b"MVAR", b"STAT", b"VVAR",
+];
+
+/// Replace the `glyf` outlines and horizontal metrics of a subset with the ones of the
+/// instance of the font.
+///
+/// The subsetter always keeps the outlines and metrics of the default instance, but PDF
+/// viewers don't know anything about font variations, so the ones of the chosen instance
+/// need to be written instead. `glyph_ids` maps the glyph IDs in the subset to the
+/// ones in the original font.
+pub(crate) fn instance_glyf(
+    font: &Font,
+    subset: &[u8],
+    glyph_ids: &[GlyphId],
+) -> KrillaResult<Vec<u8>> {
+    let err = || KrillaError::Font(font.clone(), "failed to instance font".to_string());
+
+    let subset_ref = FontRef::new(subset).map_err(|_| err())?;
+    let num_glyphs = subset_ref.maxp().map_err(|_| err())?.num_glyphs();
+    let outline_glyphs = font.font_ref().outline_glyphs();
+
+    let mut glyf = vec![];
+    let mut loca = vec![];
+    let mut h_metrics = vec![];
+
+    for gid in 0..num_glyphs {
+        loca.extend((glyf.len() as u32).to_be_bytes());
+
+        let Some(glyph_id) = glyph_ids.get(gid as usize) else {
+            // Glyphs that were only added by the subsetter since they are components
+            // of composite glyphs are left empty, since the instanced glyphs are always
+            // written as simple glyphs.
+            h_metrics.push((0, None));
+            continue;
+        };
+
+        // The advance widths of the instance are derived from `HVAR` or, if the font
+        // doesn't have one, from the phantom points in `gvar`.
+        let advance = font.advance_width(*glyph_id).unwrap_or(0.0).round() as u16;
+
+        let Some(outline_glyph) = outline_glyphs.get(glyph_id.to_skrifa()) else {
+            h_metrics.push((advance, None));
+            continue;
+        };
+
+        // Composite glyphs are flattened into simple glyphs, with the variations of
+        // the offsets of their components already applied when drawing them.
+        let mut pen = GlyfPen::default();
+        outline_glyph
+            .draw(
+                DrawSettings::unhinted(Size::unscaled(), font.location_ref()),
+                &mut pen,
+            )
+            .map_err(|_| err())?;
+        h_metrics.push((advance, pen.finish(&mut glyf)));
+    }
+
+    loca.extend((glyf.len() as u32).to_be_bytes());
+
+    let mut head = subset_ref
+        .table_data(Tag::new(b"head"))
+        .ok_or_else(err)?
+        .as_bytes()
+        .to_vec();
+
+    if head.len() < 54 {
+        return Err(err());
+    }
+
+    // Always use the long format for `loca`.
+    head[50..52].copy_from_slice(&1i16.to_be_bytes());
+
+    let mut hhea = subset_ref
+        .table_data(Tag::new(b"hhea"))
+        .ok_or_else(err)?
+        .as_bytes()
+        .to_vec();
+
+    if hhea.len() < 36 {
+        return Err(err());
+    }
+
+    let (hmtx, summary) = write_hmtx(&h_metrics);
+
+    hhea[10..12].copy_from_slice(&summary.advance_width_max.to_be_bytes());
+    hhea[12..14].copy_from_slice(&summary.min_left_side_bearing.to_be_bytes());
+    hhea[14..16].copy_from_slice(&summary.min_right_side_bearing.to_be_bytes());
+    hhea[16..18].copy_from_slice(&summary.x_max_extent.to_be_bytes());
+    // Always write a long horizontal metric for each glyph.
+    hhea[34..36].copy_from_slice(&num_glyphs.to_be_bytes());
+
+    let mut tables = vec![];
+
+    for record in subset_ref.table_directory.table_records() {
+        let tag = record.tag();
+
+        let data = match &tag.to_be_bytes() {
+            b"glyf" => glyf.clone(),
+            b"loca" => loca.clone(),
+            b"head" => head.clone(),
+            b"hhea" => hhea.clone(),
+            b"hmtx" => hmtx.clone(),
+            t if VARIATION_TABLES.contains(&t) => continue,
+            _ => subset_ref
+                .table_data(tag)
+                .ok_or_else(err)?
+                .as_bytes()
+                .to_vec(),
+        };
+
+        tables.push((tag, data));
+    }
+
+    Ok(write_font(tables))
+}
+
+/// The values of the `hhea` table that summarize the horizontal metrics of all glyphs.
+#[derive(Default)]
+struct HMetricsSummary {
+    advance_width_max: u16,
+    min_left_side_bearing: i16,
+    min_right_side_bearing: i16,
+    x_max_extent: i16,
+}
+
+/// Encode the `hmtx` table from the advance widths and horizontal bounds of the glyphs.
+///
+/// As is the convention for `glyf` fonts, the left side bearing of each glyph is the
+/// minimum x coordinate of its outline.
+fn write_hmtx(h_metrics: &[(u16, Option<(i16, i16)>)]) -> (Vec<u8>, HMetricsSummary) {
+    let mut hmtx = vec![];
+    let mut summary = HMetricsSummary::default();
+    let mut first_outline = true;
+
+    for (advance, bounds) in h_metrics {
+        let lsb = bounds.map_or(0, |(x_min, _)| x_min);
+        hmtx.extend(advance.to_be_bytes());
+        hmtx.extend(lsb.to_be_bytes());
+
+        summary.advance_width_max = summary.advance_width_max.max(*advance);
+
+        // Glyphs without an outline don't contribute to the bounds.
+        if let Some((x_min, x_max)) = bounds {
+            let rsb = (*advance as i16).wrapping_sub(*x_max);
+
+            if first_outline {
+                summary.min_left_side_bearing = *x_min;
+                summary.min_right_side_bearing = rsb;
+                summary.x_max_extent = *x_max;
+                first_outline = false;
+            } else {
+                summary.min_left_side_bearing = summary.min_left_side_bearing.min(*x_min);
+                summary.min_right_side_bearing = summary.min_right_side_bearing.min(rsb);
+                summary.x_max_extent = summary.x_max_extent.max(*x_max);
+            }
+        }
+    }
+
+    (hmtx, summary)
+}
+
+/// A pen that encodes an outline as a simple glyph of the `glyf` table.
+#[derive(Default)]
+struct GlyfPen {
+    /// The points of all contours, and whether they are on the curve.
+    points: Vec<(i16, i16, bool)>,
+    /// The index of the last point of each contour.
+    end_points: Vec<u16>,
+    /// The index of the first point of the current contour.
+    start: usize,
+    /// The current point, without rounding.
+    current: (f32, f32),
+}
+
+impl GlyfPen {
+    fn push(&mut self, x: f32, y: f32, on_curve: bool) {
+        self.points
+            .push((x.round() as i16, y.round() as i16, on_curve));
+    }
+
+    /// Write the glyph and return its minimum and maximum x coordinate, or `None` if
+    /// it doesn't have an outline.
+    fn finish(mut self, out: &mut Vec<u8>) -> Option<(i16, i16)> {
+        self.close();
+
+        if self.points.is_empty() {
+            return None;
+        }
+
+        let x_min = self.points.iter().map(|p| p.0).min().unwrap();
+        let y_min = self.points.iter().map(|p| p.1).min().unwrap();
+        let x_max = self.points.iter().map(|p| p.0).max().unwrap();
+        let y_max = self.points.iter().map(|p| p.1).max().unwrap();
+
+        out.extend((self.end_points.len() as i16).to_be_bytes());
+
+        for v in [x_min, y_min, x_max, y_max] {
+            out.extend(v.to_be_bytes());
+        }
+
+        for end_point in &self.end_points {
+            out.extend(end_point.to_be_bytes());
+        }
+
+        // No instructions.
+        out.extend(0u16.to_be_bytes());
+
+        // Without any of the short vector flags, all coordinates are written as signed
+        // 16-bit deltas, so only the on-curve flag needs to be set.
+        out.extend(self.points.iter().map(|p| p.2 as u8));
+
+        let mut prev = 0;
+        for (x, _, _) in &self.points {
+            out.extend(x.wrapping_sub(prev).to_be_bytes());
+            prev = *x;
+        }
+
+        let mut prev = 0;
+        for (_, y, _) in &self.points {
+            out.extend(y.wrapping_sub(prev).to_be_bytes());
+            prev = *y;
+        }
+
+        while !out.len().is_multiple_of(4) {
+            out.push(0);
+        }
+
+        Some((x_min, x_max))
+    }
+}
+
+impl OutlinePen for GlyfPen {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.close();
+        self.push(x, y, true);
+        self.current = (x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.push(x, y, true);
+        self.current = (x, y);
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        self.push(cx0, cy0, false);
+        self.push(x, y, true);
+        self.current = (x, y);
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        // `glyf` outlines only consist of quadratic curves, so cubic curves, which
+        // can't occur in `glyf`-based fonts anyway, are approximated.
+        let (x0, y0) = self.current;
+        let cx = (3.0 * (cx0 + cx1) - x0 - x) / 4.0;
+        let cy = (3.0 * (cy0 + cy1) - y0 - y) / 4.0;
+        self.quad_to(cx, cy, x, y);
+    }
+
+    fn close(&mut self) {
+        if self.points.len() == self.start {
+            return;
+        }
+
+        // The contour is closed implicitly, so a last point that coincides with the
+        // first one is redundant.
+        if self.points.len() - self.start > 1 && self.points.last() == self.points.get(self.start) {
+            self.points.pop();
+        }
+
+        self.end_points.push((self.points.len() - 1) as u16);
+        self.start = self.points.len();
+    }
+}
+
+/// Write an OpenType font with the given tables.
+pub(crate) fn write_font(mut tables: Vec<(Tag, Vec<u8>)>) -> Vec<u8> {
+    tables.sort_by_key(|(tag, _)| *tag);
+
+    let num_tables = tables.len() as u16;
+    let entry_selector = 15 - num_tables.max(1).leading_zeros() as u16;
+    let search_range = (1 << entry_selector) * 16;
+    let range_shift = num_tables * 16 - search_range;
+
+    let is_cff = tables
+        .iter()
+        .any(|(tag, _)| tag == &Tag::new(b"CFF ") || tag == &Tag::new(b"CFF2"));
+
+    let mut out = vec![];
+    out.extend(if is_cff {
+        *b"OTTO"
+    } else {
+        0x00010000u32.to_be_bytes()
+    });
+
+    for v in [num_tables, search_range, entry_selector, range_shift] {
+        out.extend(v.to_be_bytes());
+    }
+
+    let mut data = vec![];
+    let mut head_offset = None;
+    let data_offset = out.len() + tables.len() * 16;
+
+    for (tag, table) in &tables {
+        let offset = data_offset + data.len();
+
+        if tag == &Tag::new(b"head") && table.len() >= 12 {
+            head_offset = Some(offset);
+        }
+
+        out.extend(tag.to_be_bytes());
+        out.extend(checksum(table).to_be_bytes());
+        out.extend((offset as u32).to_be_bytes());
+        out.extend((table.len() as u32).to_be_bytes());
+
+        data.extend(table);
+
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+    }
+
+    out.extend(data);
+
+    if let Some(offset) = head_offset {
+        // The checksum adjustment needs to be zero while calculating the checksum.
+        out[offset + 8..offset + 12].copy_from_slice(&[0; 4]);
+        let adjustment = 0xB1B0AFBAu32.wrapping_sub(checksum(&out));
+        out[offset + 8..offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+
+    out
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut bytes = [0; 4];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(bytes))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use skrifa::instance::{LocationRef, Size};
+    use skrifa::raw::tables::glyf::Glyph;
+    use skrifa::raw::TableProvider;
+    use skrifa::{FontRef, MetadataProvider};
+
+    use crate::text::instance::instance_glyf;
+    use crate::text::{Font, GlyphId};
+    use crate::util::test_utils::NOTO_SANS_VARIABLE;
+
+    #[test]
+    fn instance_composite_glyph() {
+        let font =
+            Font::new_with_variations(NOTO_SANS_VARIABLE.clone(), 0, &[("wght", 900.0)]).unwrap();
+        let font_ref = font.font_ref();
+        let num_glyphs = font_ref.maxp().unwrap().num_glyphs();
+        let glyph_ids = (0..num_glyphs as u32).map(GlyphId::new).collect::<Vec<_>>();
+
+        let instanced = instance_glyf(&font, font.font_data().as_ref(), &glyph_ids).unwrap();
+        let instanced_ref = FontRef::new(&instanced).unwrap();
+
+        // "Ä" is a composite glyph made up of "A" and the dieresis.
+        let glyph_id = font_ref.charmap().map('Ä').unwrap();
+        let glyf = font_ref.glyf().unwrap();
+        let loca = font_ref.loca(None).unwrap();
+        assert!(matches!(
+            loca.get_glyf(glyph_id, &glyf),
+            Ok(Some(Glyph::Composite(_)))
+        ));
+
+        let default_metrics = font_ref.glyph_metrics(Size::unscaled(), LocationRef::default());
+        let instance_metrics = font_ref.glyph_metrics(Size::unscaled(), font.location_ref());
+        let instanced_metrics =
+            instanced_ref.glyph_metrics(Size::unscaled(), LocationRef::default());
+
+        let advance = instanced_metrics.advance_width(glyph_id).unwrap();
+        assert_eq!(advance, instance_metrics.advance_width(glyph_id).unwrap());
+        assert_ne!(advance, default_metrics.advance_width(glyph_id).unwrap());
+
+        // The bounds of the instance include the varied offsets of the components, so
+        // they only match if those are instanced, too.
+        let bounds = instanced_metrics.bounds(glyph_id).unwrap();
+        let expected = instance_metrics.bounds(glyph_id).unwrap();
+        assert_ne!(expected, default_metrics.bounds(glyph_id).unwrap());
+        for (a, b) in [
+            (bounds.x_min, expected.x_min),
+            (bounds.y_min, expected.y_min),
+            (bounds.x_max, expected.x_max),
+            (bounds.y_max, expected.y_max),
+        ] {
+            assert!((a - b).abs() <= 1.0, "{a} != {b}");
+        }
+
+        assert_eq!(
+            instanced_metrics.left_side_bearing(glyph_id).unwrap(),
+            bounds.x_min
+        );
+    }
+}
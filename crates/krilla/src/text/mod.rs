@@ -3,7 +3,7 @@
 //! krilla has extensive support for OpenType fonts. It supports CFF-based as well
 //! as TTF-based OpenType fonts. In addition to that, krilla also supports
 //! all major tables used in color fonts, including the `SVG`, `COLR`, `sbix` and
-//! `CBDT`/`EBDT` (only PNG) tables, something that, to the best of my knowledge, no other
+//! `CBDT`/`EBDT` tables, something that, to the best of my knowledge, no other
 //! Rust crates provides.
 //!
 //! Even better is the fact that you do not need to take care of choosing the right
@@ -22,20 +22,46 @@ pub(crate) mod cid;
 pub(crate) mod font;
 pub(crate) mod glyph;
 pub(crate) mod group;
+pub(crate) mod instance;
 #[cfg(feature = "simple-text")]
 pub(crate) mod shape;
+#[cfg(feature = "system-fonts")]
+pub(crate) mod system;
 pub(crate) mod type3;
+#[cfg(feature = "woff")]
+pub(crate) mod woff;
 
 pub use font::*;
 pub use glyph::*;
 #[cfg(feature = "simple-text")]
 pub use shape::TextDirection;
+#[cfg(feature = "system-fonts")]
+pub use system::SystemFontStyle;
+#[cfg(feature = "woff")]
+pub use woff::WoffError;
 
 pub(crate) const PDF_UNITS_PER_EM: f32 = 1000.0;
 
+/// The writing mode of text.
+///
+/// In the vertical writing mode, glyphs are written from top to bottom, as is
+/// common for CJK text, and the font is embedded with vertical metrics. Glyphs should
+/// then be shaped vertically, so that their y advances move the pen downward and
+/// their offsets place them relative to their vertical origin. Glyphs that can't
+/// be embedded as outlines, which for example is the case for color glyphs, are
+/// still drawn correctly, but are written using the horizontal writing mode.
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq)]
+pub enum WritingMode {
+    /// Glyphs are written from left to right.
+    #[default]
+    Horizontal,
+    /// Glyphs are written from top to bottom.
+    Vertical,
+}
+
 /// A unique CID identifier.
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
-pub(crate) struct CIDIdentifier(pub Font);
+pub(crate) struct CIDIdentifier(pub Font, pub WritingMode);
 
 /// A unique Type3 font identifier. Type3 fonts can only hold 256 glyphs, which
 /// means that we might have to create more than one Type3 font. This is why we
@@ -51,22 +77,35 @@ pub(crate) enum FontIdentifier {
     Type3(Type3Identifier),
 }
 
+impl FontIdentifier {
+    pub(crate) fn is_vertical(&self) -> bool {
+        matches!(
+            self,
+            FontIdentifier::Cid(CIDIdentifier(_, WritingMode::Vertical))
+        )
+    }
+}
+
 /// A container that holds all PDF fonts belonging to an OTF font.
 pub(crate) struct FontContainer {
     font: Font,
     type3_mapper: Type3FontMapper,
     cid_font: CIDFont,
     cid_cache: FxHashMap<u32, (FontIdentifier, PDFGlyph)>,
+    vertical_cid_font: CIDFont,
+    vertical_cid_cache: FxHashMap<u32, (FontIdentifier, PDFGlyph)>,
     type3_cache: HashMap<ColoredGlyph, (FontIdentifier, PDFGlyph)>,
 }
 
 impl FontContainer {
-    pub(crate) fn new(font: Font) -> Self {
+    pub(crate) fn new(font: Font, subset: bool) -> Self {
         Self {
             font: font.clone(),
             type3_mapper: Type3FontMapper::new(font.clone()),
-            cid_font: CIDFont::new(font.clone()),
+            cid_font: CIDFont::new(font.clone(), WritingMode::Horizontal, subset),
             cid_cache: Default::default(),
+            vertical_cid_font: CIDFont::new(font.clone(), WritingMode::Vertical, subset),
+            vertical_cid_cache: Default::default(),
             type3_cache: Default::default(),
         }
     }
@@ -79,10 +118,26 @@ impl FontContainer {
         &self.cid_font
     }
 
+    pub(crate) fn cid_font_mut(&mut self) -> &mut CIDFont {
+        &mut self.cid_font
+    }
+
+    pub(crate) fn vertical_cid_font(&self) -> &CIDFont {
+        &self.vertical_cid_font
+    }
+
     #[inline]
-    pub(crate) fn font_identifier(&self, glyph: ColoredGlyph) -> Option<FontIdentifier> {
-        let (id, _) = self
-            .cid_cache
+    pub(crate) fn font_identifier(
+        &self,
+        glyph: ColoredGlyph,
+        writing_mode: WritingMode,
+    ) -> Option<FontIdentifier> {
+        let cid_cache = match writing_mode {
+            WritingMode::Horizontal => &self.cid_cache,
+            WritingMode::Vertical => &self.vertical_cid_cache,
+        };
+
+        let (id, _) = cid_cache
             .get(&glyph.glyph_id.to_u32())
             .or_else(|| self.type3_cache.get(&glyph.to_owned()))?;
         Some(id.clone())
@@ -95,6 +150,8 @@ impl FontContainer {
     ) -> Option<&mut dyn PdfFont> {
         if self.cid_font.identifier() == font_identifier {
             Some(&mut self.cid_font)
+        } else if self.vertical_cid_font.identifier() == font_identifier {
+            Some(&mut self.vertical_cid_font)
         } else {
             // If the identifier doesn't match either of CID or Type3, this will
             // return `None`.
@@ -109,6 +166,8 @@ impl FontContainer {
     ) -> Option<&dyn PdfFont> {
         if self.cid_font.identifier() == font_identifier {
             Some(&self.cid_font)
+        } else if self.vertical_cid_font.identifier() == font_identifier {
+            Some(&self.vertical_cid_font)
         } else {
             // If the identifier doesn't match either of CID or Type3, this will
             // return `None`.
@@ -117,18 +176,28 @@ impl FontContainer {
     }
 
     #[inline]
-    pub(crate) fn add_glyph(&mut self, glyph: ColoredGlyph) -> (FontIdentifier, PDFGlyph) {
-        if let Some(e) = self
-            .cid_cache
+    pub(crate) fn add_glyph(
+        &mut self,
+        glyph: ColoredGlyph,
+        writing_mode: WritingMode,
+    ) -> (FontIdentifier, PDFGlyph) {
+        // Type3 fonts only support the horizontal writing mode, so they are shared
+        // between both writing modes.
+        let (cid_font, cid_cache) = match writing_mode {
+            WritingMode::Horizontal => (&mut self.cid_font, &mut self.cid_cache),
+            WritingMode::Vertical => (&mut self.vertical_cid_font, &mut self.vertical_cid_cache),
+        };
+
+        if let Some(e) = cid_cache
             .get(&glyph.glyph_id.to_u32())
             .or_else(|| self.type3_cache.get(&glyph.to_owned()))
         {
             // We already know whether this glyph uses a CID or Type3 glyph.
             e.clone()
         } else if should_outline(&self.font, glyph.glyph_id) {
-            let cid = self.cid_font.add_glyph(glyph.glyph_id);
-            let res = (self.cid_font.identifier(), PDFGlyph::Cid(cid));
-            self.cid_cache.insert(glyph.glyph_id.to_u32(), res.clone());
+            let cid = cid_font.add_glyph(glyph.glyph_id);
+            let res = (cid_font.identifier(), PDFGlyph::Cid(cid));
+            cid_cache.insert(glyph.glyph_id.to_u32(), res.clone());
             res
         } else {
             let (identifier, gid) = self.type3_mapper.add_glyph(glyph.to_owned());
@@ -1,12 +1,25 @@
-use rustybuzz::{Direction, UnicodeBuffer};
+use rustybuzz::{ttf_parser, Direction, UnicodeBuffer, Variation};
 
 use crate::text::Font;
 use crate::text::{GlyphId, KrillaGlyph};
 
 /// Naively shape some text with a single font.
+///
+/// Returns no glyphs if the font can't be read by the shaper.
 pub(crate) fn naive_shape(text: &str, font: Font, direction: TextDirection) -> Vec<KrillaGlyph> {
     let data = font.font_data();
-    let rb_font = rustybuzz::Face::from_slice(data.as_ref(), font.index()).unwrap();
+    let Some(mut rb_font) = rustybuzz::Face::from_slice(data.as_ref(), font.index()) else {
+        return vec![];
+    };
+    let variations = font
+        .variations()
+        .iter()
+        .map(|(tag, value)| Variation {
+            tag: ttf_parser::Tag::from_bytes(&tag.to_be_bytes()),
+            value: value.get(),
+        })
+        .collect::<Vec<_>>();
+    rb_font.set_variations(&variations);
 
     let mut buffer = UnicodeBuffer::new();
     buffer.push_str(text);
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use fontdb::{Database, Family, Query, Source, Stretch, Style, Weight};
+use once_cell::sync::Lazy;
+
+use crate::text::Font;
+use crate::Data;
+
+/// The fonts installed on the system, which are only searched for once they
+/// are needed for the first time.
+static SYSTEM_FONTS: Lazy<SystemFonts> = Lazy::new(SystemFonts::new);
+
+struct SystemFonts {
+    db: Database,
+    /// The data of all font files that have been loaded so far, so that each file
+    /// is only read once, even if multiple fonts of a collection are used.
+    files: Mutex<HashMap<PathBuf, Data>>,
+}
+
+impl SystemFonts {
+    fn new() -> Self {
+        let mut db = Database::new();
+        db.load_system_fonts();
+
+        Self {
+            db,
+            files: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn load_file(&self, path: &Path) -> Option<Data> {
+        let mut files = self.files.lock().unwrap();
+
+        if let Some(data) = files.get(path) {
+            return Some(data.clone());
+        }
+
+        let data = Data::from(std::fs::read(path).ok()?);
+        files.insert(path.to_path_buf(), data.clone());
+
+        Some(data)
+    }
+}
+
+/// The style of a font installed on the system.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SystemFontStyle {
+    /// The weight of the font, for example 400 for regular and 700 for bold fonts.
+    pub weight: u16,
+    /// Whether the font should be italic.
+    pub italic: bool,
+}
+
+impl Default for SystemFontStyle {
+    fn default() -> Self {
+        Self {
+            weight: 400,
+            italic: false,
+        }
+    }
+}
+
+impl Font {
+    /// Load a font that is installed on the system.
+    ///
+    /// The font with the given family name that matches the style best is chosen.
+    /// The data of each font file is only loaded once and then shared, so loading
+    /// the same font multiple times results in the same font, which is also only
+    /// embedded once. For font collections, the font with the right index in the
+    /// collection is loaded.
+    ///
+    /// Returns `None` if no font with the given family name is installed, or if the
+    /// font couldn't be read.
+    pub fn from_system(family: &str, style: SystemFontStyle) -> Option<Font> {
+        let fonts = &*SYSTEM_FONTS;
+        let query = Query {
+            families: &[Family::Name(family)],
+            weight: Weight(style.weight),
+            stretch: Stretch::Normal,
+            style: if style.italic {
+                Style::Italic
+            } else {
+                Style::Normal
+            },
+        };
+
+        let id = fonts.db.query(&query)?;
+        let (source, index) = fonts.db.face_source(id)?;
+
+        let data = match source {
+            Source::File(path) | Source::SharedFile(path, _) => fonts.load_file(&path)?,
+            Source::Binary(data) => Data::from(Arc::clone(&data)),
+        };
+
+        Font::new(data, index)
+    }
+
+    /// The names of all font families that are installed on the system, in
+    /// alphabetical order.
+    pub fn system_families() -> Vec<String> {
+        let mut families = SYSTEM_FONTS
+            .db
+            .faces()
+            .flat_map(|face| face.families.iter().map(|(name, _)| name.clone()))
+            .collect::<Vec<_>>();
+
+        families.sort();
+        families.dedup();
+
+        families
+    }
+}
@@ -5,11 +5,12 @@ use std::ops::DerefMut;
 use fxhash::FxHashMap;
 use pdf_writer::types::{FontFlags, UnicodeCmap};
 use pdf_writer::writers::WMode;
-use pdf_writer::{Chunk, Content, Finish, Name, Ref, Str};
+use pdf_writer::{Chunk, Content, Finish, Name, Ref};
 
 use super::{FontIdentifier, Type3Identifier};
 use crate::color::rgb;
 use crate::configure::PdfVersion;
+use crate::encryption::encrypt_bytes;
 use crate::geom::Path;
 use crate::geom::{Rect, Transform};
 use crate::graphics::paint::Fill;
@@ -27,20 +28,26 @@ use crate::util::NameExt;
 
 pub(crate) type Gid = u8;
 
+/// The colors that are used for drawing a color glyph.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Default)]
+pub(crate) struct GlyphColors {
+    /// The color of the text, which COLR and SVG glyphs can refer to.
+    pub(crate) context_color: rgb::Color,
+    /// The palette for COLR glyphs, as registered with the `SerializeContext`.
+    pub(crate) palette: usize,
+}
+
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub(crate) struct ColoredGlyph {
     pub(crate) glyph_id: GlyphId,
     // Some formats like COLR and SVG allow drawing the same glyph
-    // with a different context color, so we need to store that as well.
-    pub(crate) context_color: rgb::Color,
+    // with a different context color or palette, so we need to store that as well.
+    pub(crate) colors: GlyphColors,
 }
 
 impl ColoredGlyph {
-    pub(crate) fn new(glyph_id: GlyphId, context_color: rgb::Color) -> ColoredGlyph {
-        Self {
-            glyph_id,
-            context_color,
-        }
+    pub(crate) fn new(glyph_id: GlyphId, colors: GlyphColors) -> ColoredGlyph {
+        Self { glyph_id, colors }
     }
 }
 
@@ -144,7 +151,7 @@ impl Type3Font {
                 // In case this returns `None`, the surface is guaranteed to be empty.
                 let drawn_color_glyph = text::draw_color_glyph(
                     self.font.clone(),
-                    glyph.context_color,
+                    glyph.colors,
                     glyph.glyph_id,
                     Transform::default(),
                     &mut surface,
@@ -194,7 +201,7 @@ impl Type3Font {
                     stream.as_slice(),
                     &sc.serialize_settings(),
                 )
-                .finish(&sc.serialize_settings());
+                .finish(&sc.serialize_settings(), sc.encryptor());
 
                 let stream_ref = sc.new_ref();
                 let mut stream = chunk.stream(stream_ref, font_stream.encoded_data());
@@ -246,13 +253,14 @@ impl Type3Font {
                 .bbox(font_bbox.to_pdf_rect())
                 .italic_angle(italic_angle)
                 .ascent(ascender)
-                .descent(descender)
-                // Adobe recommends these for tagged PDF for 1.5+ (descriptors for Type3 fonts
-                // are only written for 1.5+ in the first place, so no additional checks needed)
-                // so we write them as well.
-                // Unfortunately we have no way of determining the actual family name, so we just
-                // take the next best thing
-                .family(Str(base_font.as_bytes()))
+                .descent(descender);
+            // Adobe recommends these for tagged PDF for 1.5+ (descriptors for Type3 fonts
+            // are only written for 1.5+ in the first place, so no additional checks needed)
+            // so we write them as well.
+            // Unfortunately we have no way of determining the actual family name, so we just
+            // take the next best thing
+            font_descriptor.pair(Name(b"FontFamily"), sc.str(base_font.as_bytes()));
+            font_descriptor
                 .stretch(
                     match skrifa::attribute::Stretch::new(self.font().stretch()) {
                         skrifa::attribute::Stretch::ULTRA_CONDENSED => {
@@ -358,6 +366,7 @@ impl Type3Font {
         };
 
         let cmap_stream = cmap.finish();
+        let cmap_stream = encrypt_bytes(&cmap_stream, sc.encryptor());
         let mut cmap = chunk.cmap(cmap_ref, &cmap_stream);
         cmap.writing_mode(WMode::Horizontal);
         cmap.finish();
@@ -473,8 +482,7 @@ pub(crate) fn base_font_name(font: &Font) -> String {
 
 #[cfg(test)]
 mod tests {
-    use crate::color::rgb;
-    use crate::text::type3::{ColoredGlyph, Type3FontMapper};
+    use crate::text::type3::{ColoredGlyph, GlyphColors, Type3FontMapper};
     use crate::text::Font;
     use crate::text::GlyphId;
     use crate::util::test_utils::NOTO_COLOR_EMOJI_COLR;
@@ -485,16 +493,16 @@ mod tests {
         let mut t3 = Type3FontMapper::new(font.clone());
 
         for i in 2..258 {
-            t3.add_glyph(ColoredGlyph::new(GlyphId::new(i), rgb::Color::black()));
+            t3.add_glyph(ColoredGlyph::new(GlyphId::new(i), GlyphColors::default()));
         }
 
         assert_eq!(t3.fonts.len(), 1);
         assert_eq!(
-            t3.fonts[0].add_glyph(ColoredGlyph::new(GlyphId::new(20), rgb::Color::black())),
+            t3.fonts[0].add_glyph(ColoredGlyph::new(GlyphId::new(20), GlyphColors::default())),
             18
         );
 
-        t3.add_glyph(ColoredGlyph::new(GlyphId::new(512), rgb::Color::black()));
+        t3.add_glyph(ColoredGlyph::new(GlyphId::new(512), GlyphColors::default()));
         assert_eq!(t3.fonts.len(), 2);
     }
 }
@@ -0,0 +1,693 @@
+use std::io::Read;
+
+use skrifa::raw::types::Tag;
+
+use crate::text::instance::write_font;
+use crate::text::Font;
+use crate::Data;
+
+/// An error that occurred while decoding a WOFF or WOFF2 file.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum WoffError {
+    /// The data is neither a WOFF nor a WOFF2 file.
+    UnknownFormat,
+    /// The file ended before all of its data could be read.
+    Truncated,
+    /// The compressed data of the file couldn't be decompressed.
+    Decompression,
+    /// The file contains inconsistent data, for example a transformed table that
+    /// can't be reconstructed, or the decoded font couldn't be read.
+    Malformed,
+    /// The file contains a font collection, which is not supported.
+    Collection,
+}
+
+type WoffResult<T> = Result<T, WoffError>;
+
+impl Font {
+    /// Create a new font from the data of a WOFF or WOFF2 file.
+    ///
+    /// The file is decoded into a regular OpenType font, which is then used like any
+    /// other font. [`Font::new`] accepts WOFF and WOFF2 files as well, but in contrast
+    /// to it, this method returns an error that describes why a file couldn't be
+    /// decoded.
+    pub fn from_woff(data: &[u8], index: u32) -> WoffResult<Font> {
+        let decoded = decode_woff(data)?;
+        Font::new(Data::from(decoded), index).ok_or(WoffError::Malformed)
+    }
+}
+
+/// Whether the data starts with the signature of a WOFF or WOFF2 file.
+pub(crate) fn is_woff(data: &[u8]) -> bool {
+    data.starts_with(b"wOFF") || data.starts_with(b"wOF2")
+}
+
+/// Decode a WOFF or WOFF2 file into an OpenType font.
+pub(crate) fn decode_woff(data: &[u8]) -> WoffResult<Vec<u8>> {
+    match data.get(..4) {
+        Some(b"wOFF") => decode_woff1(data),
+        Some(b"wOF2") => decode_woff2(data),
+        _ => Err(WoffError::UnknownFormat),
+    }
+}
+
+/// Fonts with more tables than that can't be written, since the fields in the
+/// header of the table directory would overflow.
+fn check_num_tables(num_tables: u16) -> WoffResult<()> {
+    if num_tables >= 4096 {
+        return Err(WoffError::Malformed);
+    }
+
+    Ok(())
+}
+
+const GLYF: Tag = Tag::new(b"glyf");
+const LOCA: Tag = Tag::new(b"loca");
+const HMTX: Tag = Tag::new(b"hmtx");
+const HHEA: Tag = Tag::new(b"hhea");
+const HEAD: Tag = Tag::new(b"head");
+const MAXP: Tag = Tag::new(b"maxp");
+
+fn decode_woff1(data: &[u8]) -> WoffResult<Vec<u8>> {
+    let mut r = Reader::new(data);
+    // Signature, flavor and length.
+    r.skip(12)?;
+    let num_tables = r.read_u16()?;
+    check_num_tables(num_tables)?;
+    // The rest of the header, which only contains the versions and the
+    // locations of the metadata and private data.
+    r.skip(30)?;
+
+    let mut tables = vec![];
+
+    for _ in 0..num_tables {
+        let tag = Tag::new(&r.read_array()?);
+        let offset = r.read_u32()? as usize;
+        let compressed_length = r.read_u32()? as usize;
+        let length = r.read_u32()? as usize;
+        // The checksum, which is recalculated anyway.
+        r.skip(4)?;
+
+        let compressed = offset
+            .checked_add(compressed_length)
+            .and_then(|end| data.get(offset..end))
+            .ok_or(WoffError::Truncated)?;
+
+        // Tables are only compressed if that makes them smaller.
+        let table = if compressed_length < length {
+            let mut table = vec![];
+            flate2::read::ZlibDecoder::new(compressed)
+                .take(length as u64 + 1)
+                .read_to_end(&mut table)
+                .map_err(|_| WoffError::Decompression)?;
+
+            if table.len() != length {
+                return Err(WoffError::Malformed);
+            }
+
+            table
+        } else if compressed_length == length {
+            compressed.to_vec()
+        } else {
+            return Err(WoffError::Malformed);
+        };
+
+        tables.push((tag, table));
+    }
+
+    Ok(write_font(tables))
+}
+
+/// The tags that can be referenced by their index in the table directory of
+/// WOFF2 files.
+const KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];
+
+struct TableEntry {
+    tag: Tag,
+    transformed: bool,
+    /// The length of the table in the decompressed data.
+    length: usize,
+}
+
+fn decode_woff2(data: &[u8]) -> WoffResult<Vec<u8>> {
+    let mut r = Reader::new(data);
+    // Signature.
+    r.skip(4)?;
+    let flavor: [u8; 4] = r.read_array()?;
+    // Length.
+    r.skip(4)?;
+    let num_tables = r.read_u16()?;
+    check_num_tables(num_tables)?;
+    // Reserved and the total size of the decoded font.
+    r.skip(6)?;
+    let compressed_size = r.read_u32()? as usize;
+    // The versions and the locations of the metadata and private data.
+    r.skip(24)?;
+
+    let mut entries = vec![];
+
+    for _ in 0..num_tables {
+        let flags = r.read_u8()?;
+        let tag = match flags & 0x3f {
+            63 => Tag::new(&r.read_array()?),
+            index => Tag::new(KNOWN_TAGS[index as usize]),
+        };
+
+        let version = flags >> 6;
+        let orig_length = r.read_base128()? as usize;
+
+        // For `glyf` and `loca`, version 0 denotes the transformed table and version 3
+        // the original one, while it's the other way around for all other tables.
+        let transformed = if tag == GLYF || tag == LOCA {
+            match version {
+                0 => true,
+                3 => false,
+                _ => return Err(WoffError::Malformed),
+            }
+        } else {
+            match version {
+                0 => false,
+                1 if tag == HMTX => true,
+                _ => return Err(WoffError::Malformed),
+            }
+        };
+
+        let length = if transformed {
+            r.read_base128()? as usize
+        } else {
+            orig_length
+        };
+
+        entries.push(TableEntry {
+            tag,
+            transformed,
+            length,
+        });
+    }
+
+    if &flavor == b"ttcf" {
+        return Err(WoffError::Collection);
+    }
+
+    let total_length = entries.iter().map(|e| e.length as u64).sum::<u64>();
+    let mut decompressed = vec![];
+    brotli_decompressor::Decompressor::new(r.read_bytes(compressed_size)?, 4096)
+        .take(total_length + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|_| WoffError::Decompression)?;
+
+    if decompressed.len() as u64 != total_length {
+        return Err(WoffError::Malformed);
+    }
+
+    // The tables are stored one after another, in the order of the table directory.
+    let mut tables = vec![];
+    let mut offset = 0;
+
+    for entry in &entries {
+        tables.push((entry, &decompressed[offset..offset + entry.length]));
+        offset += entry.length;
+    }
+
+    let find = |tag: Tag| tables.iter().find(|(e, _)| e.tag == tag);
+
+    // The transformed `glyf` table also contains the data of the `loca` table, and
+    // the bounding boxes of its glyphs are used for reconstructing the `hmtx` table.
+    let glyf = match find(GLYF) {
+        Some((entry, data)) if entry.transformed => Some(reconstruct_glyf(data)?),
+        _ => None,
+    };
+
+    let mut out = vec![];
+
+    for (entry, data) in &tables {
+        let table = if !entry.transformed {
+            data.to_vec()
+        } else if entry.tag == GLYF {
+            glyf.as_ref().ok_or(WoffError::Malformed)?.glyf.clone()
+        } else if entry.tag == LOCA {
+            glyf.as_ref().ok_or(WoffError::Malformed)?.loca.clone()
+        } else {
+            let num_glyphs = find(MAXP)
+                .and_then(|(_, maxp)| maxp.get(4..6))
+                .ok_or(WoffError::Malformed)?;
+            let num_h_metrics = find(HHEA)
+                .and_then(|(_, hhea)| hhea.get(34..36))
+                .ok_or(WoffError::Malformed)?;
+
+            reconstruct_hmtx(
+                data,
+                u16::from_be_bytes([num_glyphs[0], num_glyphs[1]]) as usize,
+                u16::from_be_bytes([num_h_metrics[0], num_h_metrics[1]]) as usize,
+                glyf.as_ref().map(|g| g.x_mins.as_slice()),
+            )?
+        };
+
+        out.push((entry.tag, table));
+    }
+
+    // The reconstructed `loca` table might need to use a different format than the
+    // original one.
+    if let Some(glyf) = &glyf {
+        let (_, head) = out
+            .iter_mut()
+            .find(|(tag, _)| *tag == HEAD)
+            .ok_or(WoffError::Malformed)?;
+        let index_format = head.get_mut(50..52).ok_or(WoffError::Malformed)?;
+        index_format.copy_from_slice(&(glyf.long_loca as i16).to_be_bytes());
+    }
+
+    Ok(write_font(out))
+}
+
+struct Glyf {
+    glyf: Vec<u8>,
+    loca: Vec<u8>,
+    long_loca: bool,
+    /// The minimum x coordinate of each glyph, which is zero for empty glyphs.
+    x_mins: Vec<i16>,
+}
+
+/// Reconstruct the `glyf` and `loca` tables from the transformed `glyf` table.
+fn reconstruct_glyf(data: &[u8]) -> WoffResult<Glyf> {
+    let mut r = Reader::new(data);
+    // Reserved.
+    r.skip(2)?;
+    let option_flags = r.read_u16()?;
+    let num_glyphs = r.read_u16()? as usize;
+    let index_format = r.read_u16()?;
+
+    let mut sizes = [0; 7];
+    for size in &mut sizes {
+        *size = r.read_u32()? as usize;
+    }
+
+    let mut n_contours = Reader::new(r.read_bytes(sizes[0])?);
+    let mut n_points = Reader::new(r.read_bytes(sizes[1])?);
+    let mut flags = Reader::new(r.read_bytes(sizes[2])?);
+    let mut glyphs = Reader::new(r.read_bytes(sizes[3])?);
+    let mut composites = Reader::new(r.read_bytes(sizes[4])?);
+    let mut bboxes = Reader::new(r.read_bytes(sizes[5])?);
+    let mut instructions = Reader::new(r.read_bytes(sizes[6])?);
+
+    let bbox_bitmap = bboxes.read_bytes(4 * num_glyphs.div_ceil(32))?;
+    let overlap_bitmap = if option_flags & 1 != 0 {
+        Some(r.read_bytes(num_glyphs.div_ceil(8))?)
+    } else {
+        None
+    };
+
+    let is_set = |bitmap: &[u8], index: usize| bitmap[index >> 3] & (0x80 >> (index & 7)) != 0;
+
+    let mut glyf = vec![];
+    let mut offsets = vec![];
+    let mut x_mins = vec![];
+
+    for index in 0..num_glyphs {
+        offsets.push(glyf.len());
+
+        let num_contours = n_contours.read_i16()?;
+        let explicit_bbox = if is_set(bbox_bitmap, index) {
+            Some([
+                bboxes.read_i16()?,
+                bboxes.read_i16()?,
+                bboxes.read_i16()?,
+                bboxes.read_i16()?,
+            ])
+        } else {
+            None
+        };
+
+        match num_contours {
+            0 => {
+                if explicit_bbox.is_some() {
+                    return Err(WoffError::Malformed);
+                }
+
+                x_mins.push(0);
+            }
+            -1 => {
+                // Composite glyphs always need to have an explicit bounding box.
+                let bbox = explicit_bbox.ok_or(WoffError::Malformed)?;
+                write_glyph_header(&mut glyf, -1, bbox);
+
+                let mut has_instructions = false;
+
+                loop {
+                    let component_flags = composites.read_u16()?;
+                    glyf.extend(component_flags.to_be_bytes());
+
+                    let mut length = 2;
+                    length += if component_flags & 0x0001 != 0 { 4 } else { 2 };
+
+                    if component_flags & 0x0008 != 0 {
+                        length += 2;
+                    } else if component_flags & 0x0040 != 0 {
+                        length += 4;
+                    } else if component_flags & 0x0080 != 0 {
+                        length += 8;
+                    }
+
+                    glyf.extend(composites.read_bytes(length)?);
+                    has_instructions |= component_flags & 0x0100 != 0;
+
+                    if component_flags & 0x0020 == 0 {
+                        break;
+                    }
+                }
+
+                if has_instructions {
+                    let length = glyphs.read_255_u16()?;
+                    glyf.extend(length.to_be_bytes());
+                    glyf.extend(instructions.read_bytes(length as usize)?);
+                }
+
+                x_mins.push(bbox[0]);
+            }
+            num_contours if num_contours > 0 => {
+                let mut end_points = vec![];
+                let mut num_points = 0;
+
+                for _ in 0..num_contours {
+                    let count = n_points.read_255_u16()? as usize;
+                    if count == 0 {
+                        return Err(WoffError::Malformed);
+                    }
+
+                    num_points += count;
+                    let end_point =
+                        u16::try_from(num_points - 1).map_err(|_| WoffError::Malformed)?;
+                    end_points.push(end_point);
+                }
+
+                let mut points = vec![];
+                let (mut x, mut y) = (0i32, 0i32);
+
+                for _ in 0..num_points {
+                    let flag = flags.read_u8()?;
+                    let (dx, dy) = read_triplet(flag & 0x7f, &mut glyphs)?;
+                    x += dx;
+                    y += dy;
+                    points.push((dx, dy, x, y, flag & 0x80 == 0));
+                }
+
+                let instruction_length = glyphs.read_255_u16()?;
+                let bbox = match explicit_bbox {
+                    Some(bbox) => bbox,
+                    None => {
+                        let clamp = |v: i32| v.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+                        let x_min = points.iter().map(|p| p.2).min().unwrap_or(0);
+                        let y_min = points.iter().map(|p| p.3).min().unwrap_or(0);
+                        let x_max = points.iter().map(|p| p.2).max().unwrap_or(0);
+                        let y_max = points.iter().map(|p| p.3).max().unwrap_or(0);
+                        [clamp(x_min), clamp(y_min), clamp(x_max), clamp(y_max)]
+                    }
+                };
+
+                write_glyph_header(&mut glyf, num_contours, bbox);
+
+                for end_point in end_points {
+                    glyf.extend(end_point.to_be_bytes());
+                }
+
+                glyf.extend(instruction_length.to_be_bytes());
+                glyf.extend(instructions.read_bytes(instruction_length as usize)?);
+
+                let overlap = overlap_bitmap.is_some_and(|bitmap| is_set(bitmap, index));
+                write_points(&mut glyf, &points, overlap)?;
+
+                x_mins.push(bbox[0]);
+            }
+            _ => return Err(WoffError::Malformed),
+        }
+
+        while glyf.len() % 4 != 0 {
+            glyf.push(0);
+        }
+    }
+
+    offsets.push(glyf.len());
+
+    // The short format can only be used if all offsets can be represented with it.
+    let long_loca = index_format != 0 || glyf.len() / 2 > u16::MAX as usize;
+    let mut loca = vec![];
+
+    for offset in offsets {
+        if long_loca {
+            loca.extend((offset as u32).to_be_bytes());
+        } else {
+            loca.extend(((offset / 2) as u16).to_be_bytes());
+        }
+    }
+
+    Ok(Glyf {
+        glyf,
+        loca,
+        long_loca,
+        x_mins,
+    })
+}
+
+fn write_glyph_header(out: &mut Vec<u8>, num_contours: i16, bbox: [i16; 4]) {
+    out.extend(num_contours.to_be_bytes());
+
+    for v in bbox {
+        out.extend(v.to_be_bytes());
+    }
+}
+
+/// Write the flags and coordinates of the points of a simple glyph.
+fn write_points(
+    out: &mut Vec<u8>,
+    points: &[(i32, i32, i32, i32, bool)],
+    overlap: bool,
+) -> WoffResult<()> {
+    let mut flags = vec![];
+    let mut xs = vec![];
+    let mut ys = vec![];
+
+    // Encode a coordinate as a short vector if it fits into one byte, and omit it
+    // if it's the same as the previous one.
+    fn encode(delta: i32, short: u8, same: u8, flag: &mut u8, out: &mut Vec<u8>) -> WoffResult<()> {
+        if delta == 0 {
+            *flag |= same;
+        } else if delta.abs() <= 255 {
+            *flag |= short;
+
+            if delta > 0 {
+                *flag |= same;
+            }
+
+            out.push(delta.unsigned_abs() as u8);
+        } else {
+            let delta = i16::try_from(delta).map_err(|_| WoffError::Malformed)?;
+            out.extend(delta.to_be_bytes());
+        }
+
+        Ok(())
+    }
+
+    for (i, (dx, dy, _, _, on_curve)) in points.iter().enumerate() {
+        let mut flag = *on_curve as u8;
+
+        if overlap && i == 0 {
+            flag |= 0x40;
+        }
+
+        encode(*dx, 0x02, 0x10, &mut flag, &mut xs)?;
+        encode(*dy, 0x04, 0x20, &mut flag, &mut ys)?;
+        flags.push(flag);
+    }
+
+    out.extend(flags);
+    out.extend(xs);
+    out.extend(ys);
+
+    Ok(())
+}
+
+/// Read the coordinate deltas of a point from the glyph stream, given the flag of
+/// the point without its on-curve bit.
+fn read_triplet(flag: u8, glyphs: &mut Reader) -> WoffResult<(i32, i32)> {
+    let with_sign = |flag: u8, value: i32| if flag & 1 != 0 { value } else { -value };
+    let flag_i = flag as i32;
+
+    Ok(if flag < 10 {
+        let b0 = glyphs.read_u8()? as i32;
+        (0, with_sign(flag, ((flag_i & 14) << 7) + b0))
+    } else if flag < 20 {
+        let b0 = glyphs.read_u8()? as i32;
+        (with_sign(flag, (((flag_i - 10) & 14) << 7) + b0), 0)
+    } else if flag < 84 {
+        let b0 = flag_i - 20;
+        let b1 = glyphs.read_u8()? as i32;
+        (
+            with_sign(flag, 1 + (b0 & 0x30) + (b1 >> 4)),
+            with_sign(flag >> 1, 1 + ((b0 & 0x0c) << 2) + (b1 & 0x0f)),
+        )
+    } else if flag < 120 {
+        let b0 = flag_i - 84;
+        let b1 = glyphs.read_u8()? as i32;
+        let b2 = glyphs.read_u8()? as i32;
+        (
+            with_sign(flag, 1 + ((b0 / 12) << 8) + b1),
+            with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b2),
+        )
+    } else if flag < 124 {
+        let b1 = glyphs.read_u8()? as i32;
+        let b2 = glyphs.read_u8()? as i32;
+        let b3 = glyphs.read_u8()? as i32;
+        (
+            with_sign(flag, (b1 << 4) + (b2 >> 4)),
+            with_sign(flag >> 1, ((b2 & 0x0f) << 8) + b3),
+        )
+    } else {
+        let b1 = glyphs.read_u8()? as i32;
+        let b2 = glyphs.read_u8()? as i32;
+        let b3 = glyphs.read_u8()? as i32;
+        let b4 = glyphs.read_u8()? as i32;
+        (
+            with_sign(flag, (b1 << 8) + b2),
+            with_sign(flag >> 1, (b3 << 8) + b4),
+        )
+    })
+}
+
+/// Reconstruct the `hmtx` table from the transformed one, where the left side
+/// bearings can be omitted if they are the same as the minimum x coordinates of
+/// the glyphs.
+fn reconstruct_hmtx(
+    data: &[u8],
+    num_glyphs: usize,
+    num_h_metrics: usize,
+    x_mins: Option<&[i16]>,
+) -> WoffResult<Vec<u8>> {
+    if num_h_metrics == 0 || num_h_metrics > num_glyphs {
+        return Err(WoffError::Malformed);
+    }
+
+    let mut r = Reader::new(data);
+    let flags = r.read_u8()?;
+
+    let advances = (0..num_h_metrics)
+        .map(|_| r.read_u16())
+        .collect::<WoffResult<Vec<_>>>()?;
+
+    let mut read_bearings = |range: std::ops::Range<usize>, omitted: bool| {
+        if omitted {
+            x_mins
+                .and_then(|x_mins| x_mins.get(range))
+                .map(|x_mins| x_mins.to_vec())
+                .ok_or(WoffError::Malformed)
+        } else {
+            range.map(|_| r.read_i16()).collect::<WoffResult<Vec<_>>>()
+        }
+    };
+
+    let bearings = read_bearings(0..num_h_metrics, flags & 1 != 0)?;
+    let mono_bearings = read_bearings(num_h_metrics..num_glyphs, flags & 2 != 0)?;
+
+    let mut out = vec![];
+
+    for (advance, bearing) in advances.iter().zip(bearings) {
+        out.extend(advance.to_be_bytes());
+        out.extend(bearing.to_be_bytes());
+    }
+
+    for bearing in mono_bearings {
+        out.extend(bearing.to_be_bytes());
+    }
+
+    Ok(out)
+}
+
+/// A reader that fails instead of panicking if the data ends too early.
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    fn read_bytes(&mut self, len: usize) -> WoffResult<&'a [u8]> {
+        let bytes = self
+            .offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.offset..end))
+            .ok_or(WoffError::Truncated)?;
+        self.offset += len;
+
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> WoffResult<[u8; N]> {
+        Ok(self.read_bytes(N)?.try_into().unwrap())
+    }
+
+    fn skip(&mut self, len: usize) -> WoffResult<()> {
+        self.read_bytes(len).map(|_| ())
+    }
+
+    fn read_u8(&mut self) -> WoffResult<u8> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    fn read_u16(&mut self) -> WoffResult<u16> {
+        Ok(u16::from_be_bytes(self.read_array()?))
+    }
+
+    fn read_i16(&mut self) -> WoffResult<i16> {
+        Ok(i16::from_be_bytes(self.read_array()?))
+    }
+
+    fn read_u32(&mut self) -> WoffResult<u32> {
+        Ok(u32::from_be_bytes(self.read_array()?))
+    }
+
+    /// Read a variable-length integer with up to five bytes, of which each
+    /// contributes seven bits.
+    fn read_base128(&mut self) -> WoffResult<u32> {
+        let mut value = 0u32;
+
+        for i in 0..5 {
+            let byte = self.read_u8()?;
+
+            // Leading zeros and values that overflow are not allowed.
+            if (i == 0 && byte == 0x80) || value & 0xFE00_0000 != 0 {
+                return Err(WoffError::Malformed);
+            }
+
+            value = (value << 7) | (byte & 0x7f) as u32;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(WoffError::Malformed)
+    }
+
+    /// Read a variable-length 16-bit integer, whose first byte determines how the
+    /// value is encoded.
+    fn read_255_u16(&mut self) -> WoffResult<u16> {
+        const LOWEST_U_CODE: u16 = 253;
+
+        Ok(match self.read_u8()? {
+            253 => self.read_u16()?,
+            254 => self.read_u8()? as u16 + LOWEST_U_CODE * 2,
+            255 => self.read_u8()? as u16 + LOWEST_U_CODE,
+            code => code as u16,
+        })
+    }
+}
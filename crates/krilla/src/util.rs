@@ -268,4 +268,6 @@ pub(crate) mod test_utils {
         NOTO_COLOR_EMOJI_COLR,
         FONT_PATH.join("NotoColorEmoji.COLR.subset.ttf")
     );
+
+    lazy_font!(NOTO_SANS_VARIABLE, FONT_PATH.join("NotoSans_variable.ttf"));
 }